@@ -0,0 +1,226 @@
+// 定时语音播报：按播报计划的重复规则（cron 表达式或 RRULE）在到期时向目标设备下发播报命令，
+// 命令经发件箱由 Bridge 转发，连接设备的 Bridge 合成语音后播放
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use echo_shared::{AnnouncementConfig, CommandIssuer, DeviceCommand, DeviceCommandKind, DeviceId, UserId, UserRole};
+use tracing::{debug, info, warn};
+use crate::app_state::AppState;
+use crate::database::AnnouncementSchedule;
+use crate::handlers::devices::command_message;
+use crate::recurrence::Recurrence;
+use crate::telemetry;
+
+/// 不是领导者时重新检查领导权的间隔
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 每次检查最多处理的到期计划数
+const DUE_BATCH: u32 = 100;
+
+/// 超过触发时间太久（如 API Gateway 停机期间）的播报不再播放，直接安排下一次
+const MISSED_RUN_GRACE: chrono::Duration = chrono::Duration::minutes(10);
+
+/// 两次触发之间的最短间隔
+const MIN_REPEAT_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
+
+/// 解析 IANA 时区名
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>().map_err(|_| format!("Unknown timezone {:?}", name))
+}
+
+/// 检查重复规则并返回 `after` 之后的下一次触发时间；规则不再有触发时间时返回 None
+///
+/// 重复间隔短于一分钟的规则会被拒绝，避免设备被连续播报
+pub fn next_run(
+    recurrence: &str,
+    timezone: &str,
+    starts_at: DateTime<Utc>,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    let tz = parse_timezone(timezone)?;
+    let rule = Recurrence::parse(recurrence)?;
+    let Some(next) = rule.next_after(starts_at, tz, after) else {
+        return Ok(None);
+    };
+    if let Some(following) = rule.next_after(starts_at, tz, next) {
+        if following - next < MIN_REPEAT_INTERVAL {
+            return Err("Announcements can repeat at most once per minute".to_string());
+        }
+    }
+    Ok(Some(next))
+}
+
+/// 启动播报计划调度任务
+///
+/// 多副本部署时只有领导者副本触发；触发时间记录在数据库中，切换领导者后由新的领导者继续
+pub fn spawn_announcement_scheduler(app_state: AppState, config: AnnouncementConfig) {
+    tokio::spawn(async move {
+        info!("Announcement scheduler started (check interval: {}s)", config.check_interval_seconds);
+
+        let leader = app_state.database.leader_election("announcements");
+        let shutdown = app_state.shutdown.clone();
+        loop {
+            if leader.is_leader().await {
+                if let Exit::Shutdown = run(&app_state, &config, &leader).await {
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(LEADER_RETRY_INTERVAL) => {}
+                _ = shutdown.wait() => break,
+            }
+        }
+
+        leader.release().await;
+        info!("Announcement scheduler stopped");
+    });
+}
+
+enum Exit {
+    Shutdown,
+    LostLeadership,
+}
+
+/// 作为领导者定期触发到期的计划，直到收到关闭信号或失去领导权
+async fn run(app_state: &AppState, config: &AnnouncementConfig, leader: &echo_shared::LeaderElection) -> Exit {
+    let mut check = tokio::time::interval(Duration::from_secs(config.check_interval_seconds));
+    check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = check.tick() => {
+                if !leader.is_leader().await {
+                    return Exit::LostLeadership;
+                }
+                run_due_schedules(app_state).await;
+            }
+            _ = app_state.shutdown.wait() => return Exit::Shutdown,
+        }
+    }
+}
+
+/// 触发所有到期的计划
+async fn run_due_schedules(app_state: &AppState) {
+    let now = Utc::now();
+    let schedules = match app_state.database.due_announcement_schedules(now, DUE_BATCH).await {
+        Ok(schedules) => schedules,
+        Err(e) => {
+            warn!("Failed to load due announcement schedules: {}", e);
+            return;
+        }
+    };
+    for schedule in &schedules {
+        run_schedule(app_state, schedule, now).await;
+    }
+}
+
+async fn run_schedule(app_state: &AppState, schedule: &AnnouncementSchedule, now: DateTime<Utc>) {
+    let Some(run_at) = schedule.next_run_at else {
+        return;
+    };
+    // 规则在创建时已检查过，这里出错只可能是时区数据变化，停止该计划
+    let next_run_at = next_run(&schedule.recurrence, &schedule.timezone, schedule.starts_at, now).unwrap_or_else(|e| {
+        warn!("Announcement schedule {} can no longer be scheduled: {}", schedule.id, e);
+        None
+    });
+
+    let (device_count, error) = if now - run_at > MISSED_RUN_GRACE {
+        info!("Skipping announcement schedule {} missed at {}", schedule.id, run_at);
+        telemetry::record_announcement("missed");
+        (0, Some(format!("Missed scheduled time {}", run_at.format("%Y-%m-%d %H:%M:%S UTC"))))
+    } else {
+        announce(app_state, schedule).await
+    };
+
+    if let Err(e) = app_state
+        .database
+        .record_announcement_run(schedule.id, run_at, next_run_at, device_count, error.as_deref())
+        .await
+    {
+        warn!("Failed to record run of announcement schedule {}: {}", schedule.id, e);
+    }
+}
+
+/// 以计划创建者的身份向目标设备下发播报命令，返回下发的设备数和错误
+async fn announce(app_state: &AppState, schedule: &AnnouncementSchedule) -> (i32, Option<String>) {
+    let issuer = match app_state.database.get_user_by_id(&UserId::new(schedule.user_id.as_str())).await {
+        Ok(Some(user)) => CommandIssuer { user_id: user.id, username: user.username, role: user.role },
+        Ok(None) => return (0, Some(format!("User {} not found", schedule.user_id))),
+        Err(e) => {
+            warn!("Failed to load owner of announcement schedule {}: {}", schedule.id, e);
+            return (0, Some("Failed to load schedule owner".to_string()));
+        }
+    };
+
+    let devices = match app_state
+        .database
+        .announcement_target_devices(
+            &schedule.user_id,
+            &schedule.device_ids,
+            &schedule.locations,
+            issuer.role == UserRole::Admin,
+        )
+        .await
+    {
+        Ok(devices) => devices,
+        Err(e) => {
+            warn!("Failed to resolve targets of announcement schedule {}: {}", schedule.id, e);
+            return (0, Some("Failed to resolve target devices".to_string()));
+        }
+    };
+
+    let kind = DeviceCommandKind::Announcement;
+    let authorizer = &app_state.command_authorizer;
+    let mut queued = 0;
+    let mut last_error = None;
+    for device_id in &devices {
+        if let Err(denied) = authorizer.check(device_id, kind, Some(&issuer)).await {
+            warn!("Denied scheduled announcement {} for device {}: {}", schedule.id, device_id, denied);
+            telemetry::record_command_denied(kind.as_str(), denied.reason());
+            telemetry::record_announcement("denied");
+            if let Err(e) = authorizer.record_denial(device_id, kind, &issuer, &denied, "announcement", None).await {
+                warn!("Failed to record denied command: {}", e);
+            }
+            last_error = Some(format!("Device {}: {}", device_id, denied));
+            continue;
+        }
+
+        let device_id = DeviceId::new(device_id.as_str());
+        let result = match command_message(app_state, &device_id, DeviceCommand::announce(&schedule.text), Some(issuer.clone())) {
+            Ok(message) => app_state.database.enqueue_device_command(&message).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => {
+                debug!("Queued scheduled announcement {} for device {}", schedule.id, device_id);
+                telemetry::record_announcement("queued");
+                queued += 1;
+            }
+            Err(e) => {
+                warn!("Failed to queue scheduled announcement {} for device {}: {}", schedule.id, device_id, e);
+                telemetry::record_announcement("failed");
+                last_error = Some(format!("Device {}: failed to queue command", device_id));
+            }
+        }
+    }
+
+    info!("Announcement schedule {} ({}) queued for {} of {} devices", schedule.id, schedule.name, queued, devices.len());
+    if devices.is_empty() {
+        last_error = Some("No target devices".to_string());
+    }
+    (queued, last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_run_rejects_sub_minute_rules() {
+        let starts_at = DateTime::parse_from_rfc3339("2025-12-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let next = next_run("30 7 * * *", "Asia/Shanghai", starts_at, starts_at).unwrap();
+        assert_eq!(next, Some(DateTime::parse_from_rfc3339("2025-12-02T07:30:00+08:00").unwrap().with_timezone(&Utc)));
+
+        assert!(next_run("*/10 * * * * *", "UTC", starts_at, starts_at).is_err());
+        assert!(next_run("30 7 * * *", "Mars/Olympus", starts_at, starts_at).is_err());
+    }
+}
@@ -1,10 +1,17 @@
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::AtomicBool;
+use tokio::sync::{broadcast, RwLock};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::database::Database;
 use crate::cache::Cache;
+use crate::bridge_client::BridgeClient;
+use crate::notifier;
+use crate::shutdown::Shutdown;
+use crate::rate_limit::RateLimiter;
+use echo_shared::{CommandAuthorizer, CommandSigner, Storage, ConfigWatcher, IngressLimitsConfig, IpFilterHandle, MessageSender, User, NotificationConfig, AnnouncementConfig, WebSocketMessage, WebhookConfig};
+use echo_shared::AppConfig as ServiceConfig;
 
 /// 应用程序状态
 #[derive(Clone)]
@@ -21,6 +28,30 @@ pub struct AppState {
     pub database: Arc<Database>,
     /// Redis缓存
     pub cache: Arc<Cache>,
+    /// 邮件/短信/Slack/Telegram 发送者
+    pub message_sender: Arc<dyn MessageSender>,
+    /// Bridge 内部 API 客户端
+    pub bridge: Arc<BridgeClient>,
+    /// 实时事件广播通道（推送给 WebSocket 订阅者）
+    pub events: broadcast::Sender<WebSocketMessage>,
+    /// 关闭信号（通知长连接退出）
+    pub shutdown: Shutdown,
+    /// 可热加载的运行时配置
+    pub config_watcher: ConfigWatcher,
+    /// API 限流
+    pub rate_limiter: Arc<RateLimiter>,
+    /// MQTT 实时事件监听是否已连接 broker
+    pub mqtt_connected: Arc<AtomicBool>,
+    /// 就绪检查中视为关键的依赖
+    pub critical_dependencies: Arc<Vec<String>>,
+    /// 用户管理接口的内存用户存储（用户 ID -> 用户）
+    pub users: Arc<RwLock<HashMap<String, User>>>,
+    /// 下发给设备的命令签名（未启用时为 None）
+    pub command_signer: Option<Arc<CommandSigner>>,
+    /// 当前生效的 IP 过滤规则
+    pub ip_filter: IpFilterHandle,
+    /// 设备命令授权检查
+    pub command_authorizer: Arc<CommandAuthorizer>,
 }
 
 /// 应用状态
@@ -37,6 +68,14 @@ pub struct AppStatus {
 pub struct AppConfig {
     pub server: ServerConfig,
     pub features: FeatureConfig,
+    /// 外部输入的大小限制（limits）
+    pub limits: IngressLimitsConfig,
+    /// Webhook 订阅（webhooks）
+    pub webhooks: WebhookConfig,
+    /// 通知（notifications）
+    pub notifications: NotificationConfig,
+    /// 定时语音播报（announcements）
+    pub announcements: AnnouncementConfig,
 }
 
 /// 服务器配置
@@ -54,6 +93,12 @@ pub struct FeatureConfig {
     pub websocket_enabled: bool,
     pub sessions_enabled: bool,
     pub rate_limiting: bool,
+    /// 是否启用会话统计汇总表（SESSION_ROLLUPS_ENABLED）
+    pub session_rollups: bool,
+    /// 是否启用会话摘要任务（SESSION_SUMMARY_ENABLED）
+    pub session_summaries: bool,
+    /// 设备接口是否合并 Bridge 写入 Redis 的实时在线状态（bridge.presence.enabled）
+    pub device_presence: bool,
 }
 
 /// 应用统计信息
@@ -77,38 +122,72 @@ pub struct RuntimeInfo {
 }
 
 impl AppState {
-    pub async fn new() -> Result<Self, anyhow::Error> {
+    pub async fn new(
+        events: broadcast::Sender<WebSocketMessage>,
+        service_config: &ServiceConfig,
+        config_watcher: ConfigWatcher,
+        storage: Option<Arc<dyn Storage>>,
+    ) -> Result<Self, anyhow::Error> {
         let config = AppConfig {
             server: ServerConfig {
-                host: "0.0.0.0".to_string(),
-                port: 8080,
-                workers: 4,
+                host: service_config.server.host.clone(),
+                port: service_config.server.port,
+                workers: service_config.server.workers,
             },
             features: FeatureConfig {
                 auth_enabled: true,
                 websocket_enabled: true,
                 sessions_enabled: true,
-                rate_limiting: false,
+                rate_limiting: service_config.rate_limit.enabled,
+                session_rollups: std::env::var("SESSION_ROLLUPS_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                session_summaries: std::env::var("SESSION_SUMMARY_ENABLED")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                device_presence: service_config.bridge.presence.enabled,
             },
+            limits: service_config.limits.clone(),
+            webhooks: service_config.webhooks.clone(),
+            notifications: service_config.notifications.clone(),
+            announcements: service_config.announcements.clone(),
         };
 
         let status = AppStatus {
             health: "healthy".to_string(),
             start_time: Utc::now(),
-            version: "0.1.0".to_string(),
+            version: crate::build_info::build_info().version,
             environment: "development".to_string(),
         };
 
-        // 初始化数据库连接
-        let database = Database::new().await?;
+        // 初始化数据库连接，单进程模式下使用与 Bridge 共享的存储
+        let mut database = match storage {
+            Some(storage) => Database::from_storage(storage),
+            None => Database::new(&service_config.database).await?,
+        };
+        // 会话转写和回复的静态加密，读取时透明解密
+        if let Some(encryptor) = echo_shared::FieldEncryptor::from_config(&service_config.encryption)? {
+            tracing::info!("Transcript encryption at rest enabled (master key {})", encryptor.current_key_id());
+            database = database.with_encryption(Arc::new(encryptor));
+        }
 
-        // 运行数据库迁移
+        // 运行数据库迁移（SQLite 的表结构在连接时创建）
         if let Err(e) = database.run_migrations().await {
             tracing::warn!("Database migrations failed: {}", e);
         }
 
         // 初始化Redis缓存
-        let cache = Cache::new().await?;
+        let cache = Cache::new(&service_config.redis).await?;
+
+        let command_signer = CommandSigner::from_config(&service_config.command_signing)?.map(Arc::new);
+        if let Some(signer) = &command_signer {
+            tracing::info!("Device command signing enabled (public key {})", signer.public_key());
+        }
+
+        let command_authorizer = Arc::new(CommandAuthorizer::new(
+            service_config.command_authorization.clone(),
+            database.storage().clone(),
+        ));
 
         Ok(Self {
             status: Arc::new(RwLock::new(status)),
@@ -121,6 +200,21 @@ impl AppState {
             })),
             database: Arc::new(database),
             cache: Arc::new(cache),
+            message_sender: notifier::message_sender(&service_config.notifications)?,
+            bridge: Arc::new(BridgeClient::new(
+                &service_config.bridge.internal_url,
+                &service_config.bridge.internal_token,
+            )?),
+            events,
+            shutdown: Shutdown::new(),
+            rate_limiter: Arc::new(RateLimiter::new(config_watcher.subscribe())),
+            config_watcher,
+            mqtt_connected: Arc::new(AtomicBool::new(false)),
+            critical_dependencies: Arc::new(service_config.health.gateway_critical.clone()),
+            users: Arc::new(RwLock::new(crate::handlers::users::default_users())),
+            command_signer,
+            ip_filter: crate::ip_filter::initial_filter(&service_config.ip_filter),
+            command_authorizer,
         })
     }
 
@@ -187,11 +281,16 @@ impl AppState {
     pub async fn get_system_info(&self) -> SystemInfo {
         let status = self.get_health_status().await;
         let stats = self.get_stats().await;
-        let runtime = self.runtime.read().await.clone();
+        let mut runtime = self.runtime.read().await.clone();
+        runtime.uptime_seconds = echo_shared::process_uptime_seconds();
+
+        // 限流开关可热加载，以当前生效的配置为准
+        let mut config = self.config.clone();
+        config.features.rate_limiting = self.rate_limiter.enabled();
 
         SystemInfo {
             status,
-            config: self.config.clone(),
+            config,
             stats,
             runtime,
         }
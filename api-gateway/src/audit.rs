@@ -0,0 +1,100 @@
+//! 管理操作审计日志
+//!
+//! 设备删除、所有权转移、用户角色变更、会话强制终止、配置重载和加密主密钥轮换完成后写入 `audit_log`，
+//! 记录操作人、来源 IP 和操作前后的对象快照；被拒绝的设备命令也会记录（`device_command_denied`）。
+//! 写入失败只记录错误日志，不影响操作本身。
+
+use axum::http::HeaderMap;
+use serde::Serialize;
+use tracing::error;
+
+use crate::database::{Database, NewAuditLogEntry};
+use crate::handlers::auth::Claims;
+use crate::middleware::client_ip;
+
+/// 审计的操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    DeviceDelete,
+    DeviceTransfer,
+    UserRoleChange,
+    SessionTerminate,
+    ConfigReload,
+    EncryptionKeyRotation,
+    IpFilterRuleAdd,
+    IpFilterRuleDelete,
+    DeviceCommandDenied,
+}
+
+impl AuditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::DeviceDelete => "device_delete",
+            AuditAction::DeviceTransfer => "device_transfer",
+            AuditAction::UserRoleChange => "user_role_change",
+            AuditAction::SessionTerminate => "session_terminate",
+            AuditAction::ConfigReload => "config_reload",
+            AuditAction::EncryptionKeyRotation => "encryption_key_rotation",
+            AuditAction::IpFilterRuleAdd => "ip_filter_rule_add",
+            AuditAction::IpFilterRuleDelete => "ip_filter_rule_delete",
+            AuditAction::DeviceCommandDenied => echo_shared::command_authz::AUDIT_ACTION_COMMAND_DENIED,
+        }
+    }
+
+    /// 操作对象的类型
+    pub fn target_type(self) -> &'static str {
+        match self {
+            AuditAction::DeviceDelete | AuditAction::DeviceTransfer | AuditAction::DeviceCommandDenied => "device",
+            AuditAction::UserRoleChange => "user",
+            AuditAction::SessionTerminate => "session",
+            AuditAction::ConfigReload => "config",
+            AuditAction::EncryptionKeyRotation => "encryption_key",
+            AuditAction::IpFilterRuleAdd | AuditAction::IpFilterRuleDelete => "ip_filter_rule",
+        }
+    }
+}
+
+/// 操作人
+#[derive(Debug, Clone)]
+pub struct AuditActor {
+    pub id: String,
+    pub username: String,
+    pub ip: Option<String>,
+}
+
+impl AuditActor {
+    /// 从令牌中获取操作人身份，从代理头中获取来源 IP
+    pub fn new(claims: &Claims, headers: &HeaderMap) -> Self {
+        Self { id: claims.sub.clone(), username: claims.username.clone(), ip: client_ip(headers) }
+    }
+
+    /// 服务自身触发的操作（如收到 SIGHUP 重载配置）
+    pub fn system() -> Self {
+        Self { id: "system".to_string(), username: "system".to_string(), ip: None }
+    }
+}
+
+/// 写入一条审计日志，`before`/`after` 为操作前后的对象快照
+pub async fn record<T: Serialize>(
+    database: &Database,
+    actor: &AuditActor,
+    action: AuditAction,
+    target_id: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    let entry = NewAuditLogEntry {
+        action: action.as_str().to_string(),
+        actor_id: actor.id.clone(),
+        actor_username: actor.username.clone(),
+        actor_ip: actor.ip.clone(),
+        target_type: action.target_type().to_string(),
+        target_id: target_id.to_string(),
+        before: before.and_then(|value| serde_json::to_value(value).ok()),
+        after: after.and_then(|value| serde_json::to_value(value).ok()),
+    };
+
+    if let Err(e) = database.insert_audit_log(&entry).await {
+        error!("Failed to write audit log for {} on {} {}: {}", entry.action, entry.target_type, target_id, e);
+    }
+}
@@ -0,0 +1,248 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use echo_shared::{DatabaseBackupConfig, ObjectStorageClient};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use crate::database::{BackupRecord, Database};
+use crate::shutdown::Shutdown;
+
+/// 检查是否需要备份的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 当天的备份失败后，至少间隔这么久再重试
+const RETRY_AFTER: chrono::Duration = chrono::Duration::hours(1);
+
+/// 启动每日数据库备份后台任务
+///
+/// 每天 hour_utc 点之后用 pg_dump 导出一次整个数据库（custom 格式），写入 local_dir 并按配置上传到对象存储，
+/// 然后删除超出保留份数的备份。失败时一小时后重试。多副本部署时只有领导者副本执行备份
+pub fn spawn_backup_job(database: Arc<Database>, database_url: String, config: DatabaseBackupConfig, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        info!(
+            "Database backup job started (daily after {:02}:00 UTC, keeping {} backups in {})",
+            config.hour_utc, config.retention_count, config.local_dir
+        );
+
+        let object_storage = match config.object_storage.as_ref().map(ObjectStorageClient::new).transpose() {
+            Ok(object_storage) => object_storage,
+            Err(e) => {
+                warn!("Database backups disabled: invalid object storage configuration: {}", e);
+                return;
+            }
+        };
+
+        let leader = database.leader_election("database_backup");
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+            if !leader.is_leader().await {
+                continue;
+            }
+
+            let stale_before = Utc::now() - chrono::Duration::seconds(config.timeout_seconds as i64) - RETRY_AFTER;
+            if let Err(e) = database.fail_stale_backups(stale_before).await {
+                warn!("Failed to mark interrupted backups as failed: {}", e);
+            }
+
+            let latest = match database.latest_backup().await {
+                Ok(latest) => latest,
+                Err(e) => {
+                    warn!("Failed to query the latest backup: {}", e);
+                    continue;
+                }
+            };
+            if !backup_due(latest.as_ref(), Utc::now(), config.hour_utc) {
+                continue;
+            }
+
+            run_backup(&database, &database_url, &config, object_storage.as_ref()).await;
+            expire_backups(&database, &config, object_storage.as_ref()).await;
+        }
+
+        leader.release().await;
+    });
+}
+
+/// 今天的备份时间已到，且今天还没有成功或正在进行的备份（失败的备份一小时后重试）
+fn backup_due(latest: Option<&BackupRecord>, now: DateTime<Utc>, hour_utc: u32) -> bool {
+    let Some(scheduled) = now.date_naive().and_hms_opt(hour_utc, 0, 0).map(|t| t.and_utc()) else {
+        return false;
+    };
+    if now < scheduled {
+        return false;
+    }
+
+    match latest {
+        None => true,
+        Some(backup) if backup.started_at < scheduled => true,
+        Some(backup) => backup.status == "failed" && now - backup.started_at >= RETRY_AFTER,
+    }
+}
+
+async fn run_backup(
+    database: &Database,
+    database_url: &str,
+    config: &DatabaseBackupConfig,
+    object_storage: Option<&ObjectStorageClient>,
+) {
+    let file_name = format!("echo-{}.dump", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let path = Path::new(&config.local_dir).join(&file_name);
+
+    let id = match database.start_backup(&file_name).await {
+        Ok(id) => id,
+        Err(e) => {
+            warn!("Failed to record backup {}: {}", file_name, e);
+            return;
+        }
+    };
+    info!("Starting database backup {}", file_name);
+
+    match create_backup(database_url, config, &path, &file_name, object_storage).await {
+        Ok((size, sha256, object_url)) => {
+            let local_path = path.to_string_lossy();
+            if let Err(e) = database.complete_backup(id, size, &sha256, &local_path, object_url.as_deref()).await {
+                warn!("Failed to record completed backup {}: {}", file_name, e);
+            }
+            info!(
+                "Database backup {} completed ({} bytes{})",
+                file_name,
+                size,
+                object_url.map(|url| format!(", uploaded to {}", url)).unwrap_or_default()
+            );
+        }
+        Err(e) => {
+            warn!("Database backup {} failed: {:#}", file_name, e);
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to delete incomplete backup file {}: {}", path.display(), e);
+                }
+            }
+            if let Err(e) = database.fail_backup(id, &format!("{:#}", e)).await {
+                warn!("Failed to record failed backup {}: {}", file_name, e);
+            }
+        }
+    }
+}
+
+/// 执行 pg_dump 并上传，返回 (文件大小, SHA-256, 对象存储 URL)
+async fn create_backup(
+    database_url: &str,
+    config: &DatabaseBackupConfig,
+    path: &Path,
+    file_name: &str,
+    object_storage: Option<&ObjectStorageClient>,
+) -> Result<(i64, String, Option<String>)> {
+    tokio::fs::create_dir_all(&config.local_dir)
+        .await
+        .with_context(|| format!("Failed to create backup directory {}", config.local_dir))?;
+
+    // 密码通过 PGPASSWORD 传给 pg_dump，不出现在进程参数中
+    let mut url = reqwest::Url::parse(database_url).context("Invalid database URL")?;
+    let password = url.password().map(percent_decode);
+    url.set_password(None).map_err(|_| anyhow!("Invalid database URL"))?;
+
+    let mut command = tokio::process::Command::new(&config.pg_dump_path);
+    command
+        .arg("--format=custom")
+        .arg("--no-owner")
+        .arg("--no-privileges")
+        .arg("--file")
+        .arg(path)
+        .arg("--dbname")
+        .arg(url.as_str())
+        .kill_on_drop(true);
+    if let Some(password) = password {
+        command.env("PGPASSWORD", password);
+    }
+
+    let output = tokio::time::timeout(Duration::from_secs(config.timeout_seconds), command.output())
+        .await
+        .map_err(|_| anyhow!("pg_dump timed out after {}s", config.timeout_seconds))?
+        .with_context(|| format!("Failed to run {}", config.pg_dump_path))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "pg_dump exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let contents = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read backup file {}", path.display()))?;
+    let size = contents.len() as i64;
+    let sha256: String = Sha256::digest(&contents).iter().map(|b| format!("{:02x}", b)).collect();
+
+    let object_url = match object_storage {
+        Some(object_storage) => {
+            object_storage.put_object(file_name, contents).await?;
+            Some(object_storage.object_url(file_name))
+        }
+        None => None,
+    };
+
+    Ok((size, sha256, object_url))
+}
+
+/// 删除超出保留份数的备份：本地文件、对象存储中的对象和备份记录
+async fn expire_backups(database: &Database, config: &DatabaseBackupConfig, object_storage: Option<&ObjectStorageClient>) {
+    let expired = match database.expired_backups(config.retention_count).await {
+        Ok(expired) => expired,
+        Err(e) => {
+            warn!("Failed to query expired backups: {}", e);
+            return;
+        }
+    };
+
+    for backup in expired {
+        if let Some(path) = &backup.local_path {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => {}
+                // 文件已不存在，视为已删除
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    warn!("Failed to delete backup file {}: {}", path, e);
+                    continue;
+                }
+            }
+        }
+        if let (Some(_), Some(object_storage)) = (&backup.object_url, object_storage) {
+            if let Err(e) = object_storage.delete_object(&backup.file_name).await {
+                warn!("Failed to delete backup {} from object storage: {}", backup.file_name, e);
+                continue;
+            }
+        }
+        match database.delete_backup_record(backup.id).await {
+            Ok(()) => info!("Deleted expired database backup {}", backup.file_name),
+            Err(e) => warn!("Failed to delete backup record {}: {}", backup.file_name, e),
+        }
+    }
+}
+
+/// 解码 URL 中的 %XX 转义
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
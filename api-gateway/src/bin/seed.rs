@@ -0,0 +1,98 @@
+//! 演示数据生成工具
+//!
+//! 向全新部署的数据库写入演示用户、设备、注册令牌和历史会话，用于查看仪表盘和分页效果。
+//! 数据库和加密配置与 API Gateway 相同（配置文件 + 环境变量）。
+//!
+//! 运行：
+//!
+//! ```text
+//! cargo run -p echo-api-gateway --bin seed -- --devices 200 --sessions 50000 --days 90
+//! ```
+//!
+//! 演示用户共用同一个登录密码，不要在生产数据库上运行。
+
+use anyhow::{bail, Context, Result};
+use echo_api_gateway::seed::{self, SeedOptions};
+
+const USAGE: &str = "\
+Usage: seed [OPTIONS]
+
+Options:
+  --users <N>             Demo users to create, the first one is an Admin [default: 10]
+  --devices <N>           Demo devices to create, including pending ones [default: 50]
+  --pending-devices <N>   Devices waiting for pairing, with registration tokens [default: 5]
+  --sessions <N>          Historical sessions spread across registered devices [default: 5000]
+  --days <N>              Spread sessions over the last N days [default: 30]
+  --password <PASSWORD>   Login password shared by all demo users [default: demo123456]
+  --seed <N>              Random seed for reproducible data
+  --reset                 Delete previously seeded demo data first
+  -h, --help              Print this help";
+
+fn parse_options() -> Result<SeedOptions> {
+    let mut options = SeedOptions::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "--reset" => {
+                options.reset = true;
+                continue;
+            }
+            _ => {}
+        }
+        let value = args.next().with_context(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+        let invalid = || format!("Invalid value for {}: {}", flag, value);
+        match flag.as_str() {
+            "--users" => options.users = value.parse().with_context(invalid)?,
+            "--devices" => options.devices = value.parse().with_context(invalid)?,
+            "--pending-devices" => options.pending_devices = value.parse().with_context(invalid)?,
+            "--sessions" => options.sessions = value.parse().with_context(invalid)?,
+            "--days" => options.days = value.parse().with_context(invalid)?,
+            "--password" => options.password = value,
+            "--seed" => options.seed = Some(value.parse().with_context(invalid)?),
+            _ => bail!("Unknown option: {}\n\n{}", flag, USAGE),
+        }
+    }
+
+    options.validate()?;
+    Ok(options)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    let options = parse_options()?;
+
+    // 加载 .env 文件（如果存在）
+    // 注意：系统环境变量优先级高于 .env 文件
+    dotenvy::dotenv().ok();
+    let config = echo_shared::load_config().await?;
+
+    println!("Seeding demo data...");
+    let report = seed::seed(&config, &options).await?;
+
+    if options.reset {
+        println!("Removed {} demo users and {} demo devices", report.removed_users, report.removed_devices);
+    }
+    println!(
+        "Created {} users, {} devices ({} pending with registration tokens) and {} sessions over {} days",
+        report.users, report.devices, report.registration_tokens, report.sessions, options.days
+    );
+    println!("Refreshed {} session rollup rows", report.rollup_rows);
+    println!(
+        "Demo users ({}*) log in with password {:?}; do not seed a production database",
+        seed::USERNAME_PREFIX,
+        options.password
+    );
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+use anyhow::Result;
+use echo_shared::{BridgeOnlineDevice, BridgeSessionState, SessionId};
+use reqwest::StatusCode;
+
+/// Bridge 内部 API 客户端
+#[derive(Clone)]
+pub struct BridgeClient {
+    base_url: String,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl BridgeClient {
+    /// 创建新的 Bridge 客户端，请求时携带内部 API 鉴权令牌
+    pub fn new(base_url: impl Into<String>, token: impl Into<String>) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(3))
+            .build()?;
+
+        Ok(Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+            http,
+        })
+    }
+
+    /// 获取当前连接到 Bridge 的设备及其正在进行的会话
+    pub async fn list_online_devices(&self) -> Result<Vec<BridgeOnlineDevice>> {
+        let url = format!("{}/internal/devices/online", self.base_url);
+        let response = self.http.get(&url).bearer_auth(&self.token).send().await?;
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// 获取 Bridge 上所有活跃会话的实时状态
+    pub async fn list_active_sessions(&self) -> Result<Vec<BridgeSessionState>> {
+        let url = format!("{}/internal/sessions/active", self.base_url);
+        let response = self.http.get(&url).bearer_auth(&self.token).send().await?;
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// 获取会话的实时状态，Bridge 未跟踪该会话时返回 None
+    pub async fn get_session_state(&self, session_id: &SessionId) -> Result<Option<BridgeSessionState>> {
+        let url = format!("{}/internal/sessions/{}", self.base_url, session_id);
+        let response = self.http.get(&url).bearer_auth(&self.token).send().await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let state = response.error_for_status()?.json::<BridgeSessionState>().await?;
+        Ok(Some(state))
+    }
+
+    /// 强制结束 Bridge 上的活跃会话，Bridge 未跟踪该会话或会话已结束时返回 false
+    pub async fn end_session(&self, session_id: &SessionId, reason: &str) -> Result<bool> {
+        let url = format!("{}/internal/sessions/{}/end", self.base_url, session_id);
+        let response = self
+            .http
+            .post(&url)
+            .bearer_auth(&self.token)
+            .json(&serde_json::json!({ "reason": reason }))
+            .send()
+            .await?;
+
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::CONFLICT) {
+            return Ok(false);
+        }
+
+        response.error_for_status()?;
+        Ok(true)
+    }
+}
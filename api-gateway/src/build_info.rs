@@ -0,0 +1,17 @@
+//! 编译期生成的构建信息
+
+use echo_shared::BuildInfo;
+
+mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
+/// 当前二进制的构建信息
+pub fn build_info() -> BuildInfo {
+    BuildInfo::new(
+        built_info::PKG_VERSION,
+        built_info::GIT_COMMIT_HASH_SHORT,
+        built_info::GIT_DIRTY,
+        built_info::BUILT_TIME_UTC,
+    )
+}
@@ -1,70 +1,142 @@
-use std::env;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 use anyhow::Result;
-use redis::Client as RedisClient;
-use tracing::{info, error, warn};
+use echo_shared::{
+    CacheDegradation, CacheDegradationEvent, CacheInvalidation, CacheKey, CacheOperation, Device, DevicePresence,
+    DistributedLock, Job, JobQueue, LocalCache, RedisConfig, SessionLimitExceeded, SessionLimits, WsTickets,
+};
+use redis::{Client as RedisClient, ErrorKind, RedisError};
+use tracing::{info, warn};
 use serde::{Deserialize, Serialize};
+use crate::shutdown::Shutdown;
+use crate::telemetry;
 
 /// Redis 缓存连接
 #[derive(Clone)]
 pub struct Cache {
     client: RedisClient,
+    // Redis 前面的进程内设备缓存（按 ID 读取）
+    devices: Arc<LocalCache<Device>>,
+    // Redis Streams 任务队列
+    jobs: Arc<JobQueue>,
+    // 会话配额，未启用时为 None
+    session_limits: Option<Arc<SessionLimits>>,
+    // WebSocket 连接票据，未启用时为 None
+    ws_tickets: Option<Arc<WsTickets>>,
+    // Redis 不可用时的降级状态，降级期间缓存读取视为未命中、写入跳过
+    degradation: Arc<CacheDegradation>,
 }
 
 impl Cache {
     /// 创建新的缓存连接
-    pub async fn new() -> Result<Self> {
-        let redis_url = env::var("REDIS_URL")
-            .unwrap_or_else(|_| "redis://:redis_password@localhost:6379".to_string());
-
-        info!("Connecting to Redis: {}", redis_url);
-
-        let client = RedisClient::open(redis_url)?;
+    ///
+    /// 启用降级时 Redis 不可用不影响启动，以降级模式启动并由后台探测恢复
+    pub async fn new(config: &RedisConfig) -> Result<Self> {
+        info!("Connecting to Redis: {}", config.url);
+
+        let client = RedisClient::open(config.url.as_str())?;
+        let degradation = CacheDegradation::new(&config.degradation).with_listener(|event| {
+            match event {
+                CacheDegradationEvent::Degraded => {
+                    warn!("Redis unavailable, cache degraded: reads go to the database and writes are skipped")
+                }
+                CacheDegradationEvent::Recovered => info!("Redis recovered, cache re-enabled"),
+                CacheDegradationEvent::Skipped(_) => {}
+            }
+            telemetry::record_cache_degradation(event);
+        });
 
         // 测试连接
-        let mut conn = client.get_multiplexed_async_connection().await?;
-        let _: Option<String> = redis::cmd("PING").query_async(&mut conn).await?;
+        let ping = async {
+            let mut conn = client.get_multiplexed_async_connection().await?;
+            redis::cmd("PING").query_async::<_, ()>(&mut conn).await
+        };
+        match tokio::time::timeout(degradation.connect_timeout(), ping).await.unwrap_or_else(|_| Err(timed_out())) {
+            Ok(()) => info!("Redis connection established successfully"),
+            Err(e) if config.degradation.enabled => {
+                warn!("Redis is not reachable at startup: {}", e);
+                degradation.degrade();
+            }
+            Err(e) => return Err(e.into()),
+        }
 
-        info!("Redis connection established successfully");
+        let devices = LocalCache::from_config("device", &config.local_cache).with_listener(telemetry::record_local_cache);
 
-        Ok(Cache { client })
+        Ok(Cache {
+            client,
+            devices: Arc::new(devices),
+            jobs: Arc::new(JobQueue::new(config.jobs.clone())),
+            session_limits: SessionLimits::from_config(&config.session_limits).map(Arc::new),
+            ws_tickets: WsTickets::from_config(&config.ws_tickets).map(Arc::new),
+            degradation: Arc::new(degradation),
+        })
     }
 
-    /// 获取连接
+    /// 获取连接，降级期间直接失败；连接失败计入降级判定
     async fn get_connection(&self) -> Result<redis::aio::MultiplexedConnection, redis::RedisError> {
+        if self.degradation.is_degraded() {
+            return Err(RedisError::from((ErrorKind::ClientError, "Redis unavailable", "cache is degraded".to_string())));
+        }
+        let result = tokio::time::timeout(self.degradation.connect_timeout(), self.client.get_multiplexed_async_connection())
+            .await
+            .unwrap_or_else(|_| Err(timed_out()));
+        match &result {
+            Ok(_) => self.degradation.record_success(),
+            Err(e) => self.degradation.record_failure(e),
+        }
+        result
+    }
+
+    /// 降级期间返回 true 并计入跳过的操作，调用方应跳过本次缓存读写（读取视为未命中）
+    pub fn skip(&self, operation: CacheOperation) -> bool {
+        self.degradation.skip(operation)
+    }
+
+    /// 建立独立连接，用于阻塞读取等会占用连接的命令
+    pub async fn dedicated_connection(&self) -> Result<redis::aio::MultiplexedConnection, redis::RedisError> {
         self.client.get_multiplexed_async_connection().await
     }
 
-    /// 健康检查
-    pub async fn health_check(&self) -> Result<bool> {
-        match self.get_connection().await {
-            Ok(mut conn) => {
-                match redis::cmd("PING").query_async::<_, Option<String>>(&mut conn).await {
-                    Ok(Some(response)) if response == "PONG" => {
-                        info!("Redis health check: OK");
-                        Ok(true)
-                    }
-                    Ok(_) => {
-                        warn!("Redis health check: unexpected response");
-                        Ok(false)
-                    }
-                    Err(e) => {
-                        error!("Redis health check failed: {}", e);
-                        Ok(false)
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Redis connection failed: {}", e);
-                Ok(false)
-            }
+    /// 健康检查，失败时返回错误原因
+    pub async fn health_check(&self) -> Result<()> {
+        let mut conn = self.get_connection().await?;
+        let response: String = redis::cmd("PING").query_async(&mut conn).await?;
+        if response != "PONG" {
+            anyhow::bail!("unexpected PING response: {}", response);
         }
+        Ok(())
     }
 }
 
+// 连接超时按 IO 错误处理，计入降级判定
+fn timed_out() -> RedisError {
+    RedisError::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "Redis connection timed out"))
+}
+
+/// 启动降级探测：降级期间定期 PING Redis，成功后恢复使用缓存
+pub fn spawn_degradation_probe(cache: Arc<Cache>, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(cache.degradation.probe_interval());
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => return,
+            }
+            cache.degradation.probe(&cache.client).await;
+        }
+    });
+}
+
 // 基本缓存操作
 impl Cache {
-    /// 获取缓存值
+    /// 获取缓存值，降级期间视为未命中
     pub async fn get<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>> {
+        if self.skip(CacheOperation::Read) {
+            return Ok(None);
+        }
         let mut conn = self.get_connection().await?;
         let value: Option<String> = redis::cmd("GET").arg(key).query_async(&mut conn).await?;
 
@@ -77,8 +149,16 @@ impl Cache {
         }
     }
 
-    /// 设置缓存值（带过期时间）
+    /// 设置缓存值（带过期时间），降级期间跳过
     pub async fn set<T: Serialize>(&self, key: &str, value: &T, ttl_seconds: u64) -> Result<()> {
+        if self.skip(CacheOperation::Write) {
+            return Ok(());
+        }
+        self.setex(key, value, ttl_seconds).await
+    }
+
+    // 写入 Redis，不经过降级判断（降级期间连接直接失败）
+    async fn setex<T: Serialize>(&self, key: &str, value: &T, ttl_seconds: u64) -> Result<()> {
         let mut conn = self.get_connection().await?;
         let json_str = serde_json::to_string(value)?;
 
@@ -92,8 +172,51 @@ impl Cache {
         Ok(())
     }
 
-    /// 删除缓存值
+    /// 写入缓存，过期时间由键决定
+    pub async fn put<T: Serialize>(&self, key: &CacheKey, value: &T) -> Result<()> {
+        match key.ttl() {
+            Some(ttl_seconds) => self.set(key.as_str(), value, ttl_seconds).await,
+            None => {
+                if self.skip(CacheOperation::Write) {
+                    return Ok(());
+                }
+                let mut conn = self.get_connection().await?;
+                let json_str = serde_json::to_string(value)?;
+                redis::cmd("SET").arg(key).arg(json_str).query_async::<_, ()>(&mut conn).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// 旁路缓存读取：命中时直接返回，未命中时调用 `load` 并写入缓存（结果为 None 时不缓存）
+    ///
+    /// Redis 读写失败只记录日志，不影响 `load` 的结果
+    pub async fn get_or_load<T, F, Fut>(&self, key: &CacheKey, load: F) -> Result<Option<T>>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<T>>>,
+    {
+        match self.get::<T>(key.as_str()).await {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cache {}: {}", key, e),
+        }
+
+        let value = load().await?;
+        if let Some(value) = &value {
+            if let Err(e) = self.put(key, value).await {
+                warn!("Failed to write cache {}: {}", key, e);
+            }
+        }
+        Ok(value)
+    }
+
+    /// 删除缓存值，降级期间跳过
     pub async fn delete(&self, key: &str) -> Result<bool> {
+        if self.skip(CacheOperation::Write) {
+            return Ok(false);
+        }
         let mut conn = self.get_connection().await?;
         let count: i32 = redis::cmd("DEL").arg(key).query_async(&mut conn).await?;
         Ok(count > 0)
@@ -101,6 +224,9 @@ impl Cache {
 
     /// 检查键是否存在
     pub async fn exists(&self, key: &str) -> Result<bool> {
+        if self.skip(CacheOperation::Read) {
+            return Ok(false);
+        }
         let mut conn = self.get_connection().await?;
         let exists: bool = redis::cmd("EXISTS").arg(key).query_async(&mut conn).await?;
         Ok(exists)
@@ -108,6 +234,9 @@ impl Cache {
 
     /// 设置过期时间
     pub async fn expire(&self, key: &str, ttl_seconds: u64) -> Result<bool> {
+        if self.skip(CacheOperation::Write) {
+            return Ok(false);
+        }
         let mut conn = self.get_connection().await?;
         let result: bool = redis::cmd("EXPIRE").arg(key).arg(ttl_seconds).query_async(&mut conn).await?;
         Ok(result)
@@ -118,12 +247,17 @@ impl Cache {
 impl Cache {
     /// 生成用户会话缓存键
     pub fn user_session_key(user_id: &str) -> String {
-        format!("user:session:{}", user_id)
+        CacheKey::user_session(user_id).into()
     }
 
     /// 生成用户Token缓存键
     pub fn user_token_key(token: &str) -> String {
-        format!("user:token:{}", token)
+        CacheKey::user_token(token).into()
+    }
+
+    /// 生成用户令牌版本号缓存键
+    pub fn user_token_version_key(user_id: &str) -> String {
+        CacheKey::user_token_version(user_id).into()
     }
 
     /// 缓存用户会话
@@ -153,14 +287,46 @@ impl Cache {
 
 // 设备相关缓存操作
 impl Cache {
+    /// 生成按 ID 读取的设备缓存键
+    pub fn device_key(device_id: &str) -> String {
+        CacheKey::device(device_id).into()
+    }
+
+    /// 按 ID 读取设备：依次读取进程内缓存、Redis，都未命中时调用 `load` 查询数据库并写入两级缓存
+    ///
+    /// 同一个设备并发未命中时只查询一次；Redis 不可用时直接查询数据库
+    pub async fn get_device<F, Fut>(&self, device_id: &str, load: F) -> Result<Option<Device>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<Device>>>,
+    {
+        let key = CacheKey::device(device_id);
+        self.devices.get_or_load(key.as_str(), || self.get_or_load(&key, load)).await
+    }
+
+    /// 设备修改后删除两级缓存，并通知其他实例删除进程内缓存；失败时仅记录日志，缓存会在 TTL 到期后失效
+    pub async fn invalidate_device(&self, device_id: &str) {
+        let invalidation = CacheInvalidation::keys([Self::device_key(device_id)]);
+        if let Err(e) = self.apply_invalidation(&invalidation).await {
+            warn!("Failed to invalidate cached device {}: {}", device_id, e);
+        }
+        if self.skip(CacheOperation::Write) {
+            return;
+        }
+        let result = async { invalidation.publish(&mut self.get_connection().await?).await }.await;
+        if let Err(e) = result {
+            warn!("Failed to publish cache invalidation for device {}: {}", device_id, e);
+        }
+    }
+
     /// 生成设备状态缓存键
     pub fn device_status_key(device_id: &str) -> String {
-        format!("device:status:{}", device_id)
+        CacheKey::device_status(device_id).into()
     }
 
     /// 生成设备配置缓存键
     pub fn device_config_key(device_id: &str) -> String {
-        format!("device:config:{}", device_id)
+        CacheKey::device_config(device_id).into()
     }
 
     /// 缓存设备状态
@@ -186,6 +352,241 @@ impl Cache {
         let key = Self::device_config_key(device_id);
         self.get(&key).await
     }
+
+    /// 批量读取 Bridge 写入的设备实时在线状态，没有记录的设备视为离线（不在结果中）
+    ///
+    /// 降级期间无法区分离线和未知，直接返回错误，调用方应保留数据库中的状态
+    pub async fn get_device_presence(&self, device_ids: &[&str]) -> Result<HashMap<String, DevicePresence>> {
+        if device_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let keys: Vec<String> = device_ids.iter().map(|device_id| DevicePresence::key(device_id)).collect();
+        let mut conn = self.get_connection().await?;
+        let values: Vec<Option<String>> = redis::cmd("MGET").arg(keys).query_async(&mut conn).await?;
+
+        Ok(values
+            .into_iter()
+            .flatten()
+            .filter_map(|value| serde_json::from_str::<DevicePresence>(&value).ok())
+            .map(|presence| (presence.device_id.clone(), presence))
+            .collect())
+    }
+}
+
+// 密码重置相关缓存操作
+impl Cache {
+    /// 生成密码重置令牌缓存键
+    pub fn password_reset_key(token: &str) -> String {
+        CacheKey::password_reset(token).into()
+    }
+
+    /// 保存密码重置令牌（令牌 -> 用户ID），降级期间直接失败
+    pub async fn store_password_reset_token(&self, token: &str, user_id: &str, ttl_seconds: u64) -> Result<()> {
+        let key = Self::password_reset_key(token);
+        self.setex(&key, &user_id.to_string(), ttl_seconds).await
+    }
+
+    /// 取出并删除密码重置令牌（一次性使用）
+    pub async fn consume_password_reset_token(&self, token: &str) -> Result<Option<String>> {
+        let mut conn = self.get_connection().await?;
+        let key = Self::password_reset_key(token);
+        let value: Option<String> = redis::cmd("GETDEL").arg(&key).query_async(&mut conn).await?;
+
+        match value {
+            Some(json_str) => Ok(Some(serde_json::from_str(&json_str)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+// HTTP 响应缓存操作
+//
+// 每个作用域（如 devices、sessions）维护一个版本号，缓存键包含版本号；
+// 写操作只需递增版本号即可让该作用域下的所有缓存失效，无需扫描删除
+impl Cache {
+    /// 生成作用域版本号的缓存键
+    pub fn response_generation_key(scope: &str) -> String {
+        CacheKey::response_generation(scope).into()
+    }
+
+    /// 生成响应缓存键
+    pub fn response_key(scope: &str, generation: u64, variant: &str) -> String {
+        CacheKey::response(scope, generation, variant).into()
+    }
+
+    /// 获取作用域当前的版本号
+    pub async fn response_generation(&self, scope: &str) -> Result<u64> {
+        let mut conn = self.get_connection().await?;
+        let generation: Option<u64> = redis::cmd("GET")
+            .arg(Self::response_generation_key(scope))
+            .query_async(&mut conn)
+            .await?;
+        Ok(generation.unwrap_or(0))
+    }
+
+    /// 递增作用域版本号，使该作用域下的响应缓存全部失效；降级期间跳过，返回 0
+    pub async fn bump_response_generation(&self, scope: &str) -> Result<u64> {
+        if self.skip(CacheOperation::Write) {
+            return Ok(0);
+        }
+        let mut conn = self.get_connection().await?;
+        let generation: u64 = redis::cmd("INCR")
+            .arg(Self::response_generation_key(scope))
+            .query_async(&mut conn)
+            .await?;
+        Ok(generation)
+    }
+
+    /// 获取缓存的响应体（原始 JSON 文本）
+    pub async fn get_cached_response(&self, key: &str) -> Result<Option<String>> {
+        if self.skip(CacheOperation::Read) {
+            return Ok(None);
+        }
+        let mut conn = self.get_connection().await?;
+        let body: Option<String> = redis::cmd("GET").arg(key).query_async(&mut conn).await?;
+        Ok(body)
+    }
+
+    /// 缓存响应体（原始 JSON 文本）
+    pub async fn cache_response(&self, key: &str, body: &str, ttl_seconds: u64) -> Result<()> {
+        if self.skip(CacheOperation::Write) {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+        redis::cmd("SETEX")
+            .arg(key)
+            .arg(ttl_seconds)
+            .arg(body)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+}
+
+// 缓存失效通知
+impl Cache {
+    /// 订阅缓存失效通知（使用独立连接）
+    pub async fn subscribe_invalidations(&self) -> Result<redis::aio::PubSub, redis::RedisError> {
+        echo_shared::subscribe_invalidations(&self.client).await
+    }
+
+    /// 执行缓存失效通知：删除缓存键（包括进程内缓存），并使响应缓存作用域失效
+    pub async fn apply_invalidation(&self, invalidation: &CacheInvalidation) -> Result<()> {
+        for key in &invalidation.keys {
+            self.devices.remove(key);
+        }
+        for key in &invalidation.keys {
+            self.delete(key).await?;
+        }
+        for scope in &invalidation.scopes {
+            self.bump_response_generation(scope).await?;
+        }
+        Ok(())
+    }
+}
+
+// WebSocket 连接票据
+impl Cache {
+    /// 票据的有效期（秒），未启用票据时为 None
+    pub fn ws_ticket_ttl(&self) -> Option<u64> {
+        self.ws_tickets.as_ref().map(|tickets| tickets.config().ttl_seconds)
+    }
+
+    /// 为用户签发连接设备的一次性票据，未启用票据时返回 None；降级期间直接失败
+    pub async fn issue_ws_ticket(&self, device_id: &str, user_id: &str) -> Result<Option<String>> {
+        let Some(tickets) = &self.ws_tickets else {
+            return Ok(None);
+        };
+        let mut conn = self.get_connection().await?;
+        Ok(Some(tickets.issue(&mut conn, device_id, user_id).await?))
+    }
+}
+
+// 分布式锁
+impl Cache {
+    /// 持有设备级分布式锁执行 `run`，锁已被其他实例持有时不执行，返回 `Ok(None)`
+    ///
+    /// 执行期间在后台续期，结束后释放。Redis 不可用（包括降级期间）时返回错误，不在没有锁的情况下执行
+    pub async fn with_device_lock<T, F, Fut>(&self, device_id: &str, operation: &str, ttl: Duration, run: F) -> Result<Option<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut conn = self.get_connection().await?;
+        let lock = Arc::new(DistributedLock::device(device_id, operation, ttl));
+        if !lock.try_acquire(&mut conn).await? {
+            return Ok(None);
+        }
+
+        let extension = lock.spawn_auto_extend(conn.clone());
+        let value = run().await;
+        if extension.is_lost() {
+            warn!("Lost lock {} while running {} on device {}", lock.key(), operation, device_id);
+        }
+        extension.stop();
+
+        match lock.release(&mut conn).await {
+            Ok(true) => {}
+            Ok(false) => warn!("Lock {} expired before it was released", lock.key()),
+            Err(e) => warn!("Failed to release lock {}: {}", lock.key(), e),
+        }
+        Ok(Some(value))
+    }
+}
+
+// 任务队列
+impl Cache {
+    pub fn job_queue(&self) -> &JobQueue {
+        &self.jobs
+    }
+
+    /// 写入任务队列，返回条目 ID
+    pub async fn enqueue_job(&self, job: &Job) -> Result<String> {
+        let mut conn = self.get_connection().await?;
+        Ok(self.jobs.enqueue(&mut conn, job).await?)
+    }
+
+    /// 为新会话占用会话配额，未启用会话配额或降级期间直接通过
+    pub async fn acquire_session_slot(
+        &self,
+        session_id: &str,
+        device_id: &str,
+        user_id: Option<&str>,
+    ) -> Result<Result<(), SessionLimitExceeded>> {
+        let Some(limits) = &self.session_limits else {
+            return Ok(Ok(()));
+        };
+        if self.skip(CacheOperation::Write) {
+            return Ok(Ok(()));
+        }
+        let mut conn = self.get_connection().await?;
+        Ok(limits.acquire(&mut conn, session_id, device_id, user_id).await?)
+    }
+
+    /// 会话结束，释放设备的并发配额
+    pub async fn release_session_slot(&self, session_id: &str, device_id: &str) -> Result<()> {
+        let Some(limits) = &self.session_limits else {
+            return Ok(());
+        };
+        if self.skip(CacheOperation::Write) {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+        Ok(limits.release(&mut conn, session_id, device_id).await?)
+    }
+
+    /// 占用配额后会话创建失败，释放配额并退还用户当天的计数
+    pub async fn cancel_session_slot(&self, session_id: &str, device_id: &str, user_id: Option<&str>) -> Result<()> {
+        let Some(limits) = &self.session_limits else {
+            return Ok(());
+        };
+        if self.skip(CacheOperation::Write) {
+            return Ok(());
+        }
+        let mut conn = self.get_connection().await?;
+        Ok(limits.cancel(&mut conn, session_id, device_id, user_id).await?)
+    }
 }
 
 // 清理相关操作
@@ -195,8 +596,9 @@ impl Cache {
         let session_key = Self::user_session_key(user_id);
         let _token_pattern = format!("user:token:*");
 
-        // 删除会话缓存
+        // 删除会话缓存和令牌版本号，旧令牌在下次请求时即按新版本号校验
         self.delete(&session_key).await?;
+        self.delete(&Self::user_token_version_key(user_id)).await?;
 
         // 这里简化实现，实际应该使用SCAN来避免KEYS命令的性能问题
         // 暂时跳过批量删除token
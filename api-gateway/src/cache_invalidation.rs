@@ -0,0 +1,51 @@
+// 缓存失效通知监听：订阅 Redis 频道 cache:invalidate，执行其他服务（Bridge、运维脚本等）发布的缓存失效
+use std::sync::Arc;
+use std::time::Duration;
+use echo_shared::CacheInvalidation;
+use futures::StreamExt;
+use tracing::{debug, info, warn};
+use crate::cache::Cache;
+use crate::shutdown::Shutdown;
+
+/// 断线后的重新订阅间隔
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 启动缓存失效通知监听任务
+///
+/// 每个 API Gateway 实例各自订阅并执行；订阅断开期间错过的通知不会补发，相关缓存在 TTL 到期后失效
+pub fn spawn_invalidation_listener(cache: Arc<Cache>, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        loop {
+            match cache.subscribe_invalidations().await {
+                Ok(mut pubsub) => {
+                    info!("Subscribed to cache invalidations");
+                    let mut messages = pubsub.on_message();
+                    loop {
+                        let message = tokio::select! {
+                            message = messages.next() => message,
+                            _ = shutdown.wait() => return,
+                        };
+                        let Some(message) = message else {
+                            warn!("Cache invalidation subscription closed, resubscribing");
+                            break;
+                        };
+                        let Some(invalidation) = CacheInvalidation::from_message(&message) else {
+                            debug!("Ignoring malformed cache invalidation");
+                            continue;
+                        };
+                        debug!("Applying cache invalidation: {:?}", invalidation);
+                        if let Err(e) = cache.apply_invalidation(&invalidation).await {
+                            warn!("Failed to apply cache invalidation: {}", e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to subscribe to cache invalidations: {}", e),
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(RESUBSCRIBE_INTERVAL) => {}
+                _ = shutdown.wait() => return,
+            }
+        }
+    });
+}
@@ -0,0 +1,33 @@
+// 配置热加载：SIGHUP 或 POST /admin/reload 触发重新加载，日志级别等配置通过 watch 通道生效
+use std::sync::Arc;
+use echo_shared::ConfigWatcher;
+use crate::audit::{self, AuditAction, AuditActor};
+use crate::database::Database;
+use crate::shutdown::Shutdown;
+
+/// 重新加载配置并记录变化的配置项，成功时写入审计日志
+pub async fn reload(
+    watcher: &ConfigWatcher,
+    database: &Database,
+    actor: &AuditActor,
+) -> anyhow::Result<Vec<&'static str>> {
+    let before = watcher.current();
+    let changed = echo_shared::config_reload::reload(watcher).await?;
+    let after = watcher.current();
+    audit::record(database, actor, AuditAction::ConfigReload, "runtime", Some(&before), Some(&after)).await;
+    Ok(changed)
+}
+
+/// 收到 SIGHUP 时重新加载配置并写入审计日志，服务关闭时退出
+pub fn spawn_sighup_listener(watcher: ConfigWatcher, database: Arc<Database>, shutdown: Shutdown) {
+    echo_shared::spawn_sighup_listener(
+        move || {
+            let watcher = watcher.clone();
+            let database = database.clone();
+            async move {
+                let _ = reload(&watcher, &database, &AuditActor::system()).await;
+            }
+        },
+        async move { shutdown.wait().await },
+    );
+}
@@ -0,0 +1,161 @@
+// 浏览器客户端的 Cookie 会话：JWT 保存在脚本无法读取的 HttpOnly Cookie 中，修改状态的请求必须在请求头中
+// 带上登录时下发的 CSRF 令牌。令牌的摘要写在 JWT 的 csrf 声明中，服务端不需要保存会话状态
+use std::sync::{LazyLock, RwLock};
+use axum::http::{header, HeaderMap, HeaderValue, Method};
+use echo_shared::{CookieAuthConfig, CookieSameSite};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use crate::error::ApiError;
+
+static SETTINGS: LazyLock<RwLock<CookieAuthConfig>> = LazyLock::new(|| RwLock::new(CookieAuthConfig::default()));
+
+/// 使用配置初始化（启动时调用）
+pub fn init(config: &CookieAuthConfig) {
+    *SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+}
+
+/// 当前配置，未启用 Cookie 会话时返回 None
+pub fn settings() -> Option<CookieAuthConfig> {
+    let settings = SETTINGS.read().unwrap_or_else(|e| e.into_inner());
+    settings.enabled.then(|| settings.clone())
+}
+
+/// 生成新的 CSRF 令牌
+pub fn generate_csrf_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+/// 写入 JWT 的 CSRF 令牌摘要
+pub fn csrf_digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 读取请求中指定名称的 Cookie
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// 请求中的会话令牌，未启用 Cookie 会话时返回 None
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    let settings = settings()?;
+    cookie(headers, &settings.session_cookie)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+}
+
+/// 请求的 Origin 与 Host 是否一致，没有 Origin 的非浏览器请求视为同源
+pub fn is_same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok()) else {
+        return true;
+    };
+    let host = headers.get(header::HOST).and_then(|value| value.to_str().ok());
+    let origin_host = origin.split_once("://").map(|(_, rest)| rest);
+    matches!((origin_host, host), (Some(origin_host), Some(host)) if origin_host.eq_ignore_ascii_case(host))
+}
+
+/// 登录成功时下发的会话 Cookie 和 CSRF Cookie
+pub fn session_cookies(settings: &CookieAuthConfig, token: &str, csrf_token: &str, max_age_secs: u64) -> Vec<HeaderValue> {
+    vec![
+        build_cookie(settings, &settings.session_cookie, token, max_age_secs, true),
+        build_cookie(settings, &settings.csrf_cookie, csrf_token, max_age_secs, false),
+    ]
+}
+
+/// 退出登录时清除 Cookie
+pub fn clear_cookies(settings: &CookieAuthConfig) -> Vec<HeaderValue> {
+    vec![
+        build_cookie(settings, &settings.session_cookie, "", 0, true),
+        build_cookie(settings, &settings.csrf_cookie, "", 0, false),
+    ]
+}
+
+fn build_cookie(settings: &CookieAuthConfig, name: &str, value: &str, max_age_secs: u64, http_only: bool) -> HeaderValue {
+    let same_site = match settings.same_site {
+        CookieSameSite::Strict => "Strict",
+        CookieSameSite::Lax => "Lax",
+        CookieSameSite::None => "None",
+    };
+    let mut cookie = format!("{}={}; Path=/; Max-Age={}; SameSite={}", name, value, max_age_secs, same_site);
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if settings.secure {
+        cookie.push_str("; Secure");
+    }
+    // 名称在配置校验时已限制为字母数字，值为 JWT 或字母数字令牌
+    HeaderValue::from_str(&cookie).expect("cookie header value")
+}
+
+/// 校验 Cookie 会话中修改状态的请求携带的 CSRF 令牌，GET/HEAD/OPTIONS 请求不检查
+pub fn check_csrf(method: &Method, headers: &HeaderMap, expected_digest: Option<&str>) -> Result<(), ApiError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+    let Some(settings) = settings() else {
+        return Ok(());
+    };
+
+    let presented = headers
+        .get(settings.csrf_header.as_str())
+        .and_then(|value| value.to_str().ok())
+        .map(csrf_digest);
+    match (presented, expected_digest) {
+        (Some(presented), Some(expected)) if presented == expected => Ok(()),
+        (None, _) => {
+            warn!("Rejected {} request with session cookie but no CSRF token", method);
+            Err(ApiError::forbidden("Missing CSRF token"))
+        }
+        _ => {
+            warn!("Rejected {} request with session cookie and invalid CSRF token", method);
+            Err(ApiError::forbidden("Invalid CSRF token"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookies_and_csrf() {
+        init(&CookieAuthConfig { enabled: true, ..CookieAuthConfig::default() });
+        let settings = settings().unwrap();
+
+        let cookies = session_cookies(&settings, "jwt-token", "csrf-token", 3600);
+        assert_eq!(
+            cookies[0].to_str().unwrap(),
+            "echo_session=jwt-token; Path=/; Max-Age=3600; SameSite=Strict; HttpOnly; Secure"
+        );
+        assert_eq!(cookies[1].to_str().unwrap(), "echo_csrf=csrf-token; Path=/; Max-Age=3600; SameSite=Strict; Secure");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_static("theme=dark; echo_session=jwt-token"));
+        assert_eq!(session_token(&headers).as_deref(), Some("jwt-token"));
+
+        let digest = csrf_digest("csrf-token");
+        assert!(check_csrf(&Method::GET, &headers, Some(&digest)).is_ok());
+        assert!(check_csrf(&Method::POST, &headers, Some(&digest)).is_err());
+        headers.insert("x-csrf-token", HeaderValue::from_static("csrf-token"));
+        assert!(check_csrf(&Method::POST, &headers, Some(&digest)).is_ok());
+        assert!(check_csrf(&Method::DELETE, &headers, None).is_err());
+
+        headers.insert(header::HOST, HeaderValue::from_static("echo.example.com"));
+        assert!(is_same_origin(&headers));
+        headers.insert(header::ORIGIN, HeaderValue::from_static("https://echo.example.com"));
+        assert!(is_same_origin(&headers));
+        headers.insert(header::ORIGIN, HeaderValue::from_static("https://evil.example.net"));
+        assert!(!is_same_origin(&headers));
+    }
+}
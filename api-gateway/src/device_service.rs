@@ -4,7 +4,7 @@ use sqlx::{PgPool, Row};
 use echo_shared::{
     Device, DeviceType, DeviceStatus, DeviceRecord, DeviceFilter,
     DatabaseError, CreateDeviceRequest, UpdateDeviceRequest,
-    RedisCache, CacheStrategy, ttl, DeviceStatusCache, DeviceConfigCache,
+    RedisCache, CacheStrategy, CacheKey, DeviceStatusCache, DeviceConfigCache,
 };
 use anyhow::Result;
 use uuid::Uuid;
@@ -27,10 +27,10 @@ impl DeviceService {
         user_id: &str,
         filter: Option<DeviceFilter>,
     ) -> Result<Vec<Device>> {
-        let cache_key = format!("devices:list:{}:{:?}", user_id, filter);
+        let cache_key = CacheKey::device_list(user_id, filter.as_ref());
 
         // 尝试从缓存获取
-        if let Some(devices) = self.cache.get::<Vec<Device>>(&cache_key).await? {
+        if let Some(devices) = self.cache.get::<Vec<Device>>(cache_key.as_str()).await? {
             return Ok(devices);
         }
 
@@ -140,7 +140,7 @@ impl DeviceService {
         let result: Vec<Device> = devices.into_iter().map(|record| self.record_to_device(record)).collect();
 
         // 缓存结果
-        self.cache.set(&cache_key, &result, ttl::DEVICE_LIST).await
+        self.cache.put(&cache_key, &result).await
             .map_err(|e| DatabaseError::Connection(e.to_string()))?;
 
         Ok(result)
@@ -148,10 +148,10 @@ impl DeviceService {
 
     /// 根据ID获取设备
     pub async fn get_device_by_id(&self, device_id: &str) -> Result<Option<Device>> {
-        let cache_key = format!("device:{}", device_id);
+        let cache_key = CacheKey::device(device_id);
 
         // 尝试从缓存获取
-        if let Some(device) = self.cache.get::<Device>(&cache_key).await? {
+        if let Some(device) = self.cache.get::<Device>(cache_key.as_str()).await? {
             return Ok(Some(device));
         }
 
@@ -175,7 +175,7 @@ impl DeviceService {
             let device = self.record_to_device(record);
 
             // 缓存结果
-            self.cache.set(&cache_key, &device, ttl::DEVICE_STATUS).await
+            self.cache.put(&cache_key, &device).await
                 .map_err(|e| DatabaseError::Connection(e.to_string()))?;
 
             Ok(Some(device))
@@ -256,8 +256,7 @@ impl DeviceService {
             let device = self.record_to_device(record);
 
             // 清除相关缓存
-            let cache_key = format!("device:{}", device_id);
-            let _ = self.cache.delete(&cache_key).await;
+            let _ = self.cache.delete(CacheKey::device(device_id).as_str()).await;
             let _ = CacheStrategy::clear_device_cache(self.cache.as_ref(), device_id).await;
             let _ = CacheStrategy::clear_user_cache(self.cache.as_ref(), &device.owner).await;
 
@@ -314,12 +313,10 @@ impl DeviceService {
                 is_online: is_online.unwrap_or(false),
             };
 
-            let cache_key = format!("device:status:{}", device_id);
-            let _ = self.cache.set(&cache_key, &status_cache, ttl::DEVICE_STATUS).await;
+            let _ = self.cache.put(&CacheKey::device_status(device_id), &status_cache).await;
 
             // 清除设备详情缓存
-            let device_cache_key = format!("device:{}", device_id);
-            let _ = self.cache.delete(&device_cache_key).await;
+            let _ = self.cache.delete(CacheKey::device(device_id).as_str()).await;
         }
 
         Ok(updated)
@@ -344,7 +341,7 @@ impl DeviceService {
 
         if deleted {
             // 清除所有相关缓存
-            let _ = self.cache.delete(&format!("device:{}", device_id)).await;
+            let _ = self.cache.delete(CacheKey::device(device_id).as_str()).await;
             let _ = CacheStrategy::clear_device_cache(self.cache.as_ref(), device_id).await;
             let _ = CacheStrategy::clear_user_cache(self.cache.as_ref(), owner_id).await;
         }
@@ -0,0 +1,8 @@
+// HTTP 错误响应：ApiError 在 echo-shared 中实现（axum07 feature），这里补充 handler 的返回类型
+use axum::response::Json;
+use echo_shared::ApiResponse;
+
+pub use echo_shared::ApiError;
+
+/// handler 统一的返回类型
+pub type ApiResult<T> = Result<Json<ApiResponse<T>>, ApiError>;
@@ -0,0 +1,121 @@
+// 内部 gRPC 服务：接收 Bridge 推送的会话事件和设备上下线，转发到 WebSocket 广播通道
+//
+// 作为 MQTT 事件订阅的替代方案，需启用 grpc feature
+use std::net::SocketAddr;
+use chrono::{DateTime, Utc};
+use echo_shared::grpc::{
+    gateway_service_server::{GatewayService, GatewayServiceServer},
+    require_internal_token, DevicePresence, PublishAck, SessionEvent,
+};
+use echo_shared::{DeviceStatus, MqttPayload, WebSocketMessage};
+use tokio::sync::broadcast;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tracing::{debug, info};
+use crate::live_events::to_websocket_message;
+use crate::shutdown::Shutdown;
+
+/// 默认的 gRPC 监听端口
+const DEFAULT_GRPC_PORT: u16 = 10041;
+
+pub struct GatewayGrpcService {
+    events: broadcast::Sender<WebSocketMessage>,
+    shutdown: Shutdown,
+}
+
+impl GatewayGrpcService {
+    /// 按 MQTT 事件相同的规则转换并广播，没有 WebSocket 订阅者时发送失败属于正常情况
+    fn forward(&self, payload: MqttPayload) {
+        if let Some(message) = to_websocket_message(payload) {
+            let _ = self.events.send(message);
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl GatewayService for GatewayGrpcService {
+    async fn publish_session_events(
+        &self,
+        request: Request<Streaming<SessionEvent>>,
+    ) -> Result<Response<PublishAck>, Status> {
+        let mut stream = request.into_inner();
+        let mut accepted = 0;
+
+        loop {
+            // 关闭时主动结束事件流，否则长连接会阻塞服务退出
+            let event = tokio::select! {
+                event = stream.message() => match event? {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = self.shutdown.wait() => break,
+            };
+
+            let message = match serde_json::from_str::<WebSocketMessage>(&event.event_json) {
+                Ok(message) => message,
+                Err(e) => {
+                    debug!("Ignoring unparseable gRPC session event for {}: {}", event.device_id, e);
+                    continue;
+                }
+            };
+
+            self.forward(MqttPayload::SessionEvent {
+                device_id: event.device_id,
+                event: message,
+                timestamp: from_millis(event.timestamp_ms),
+            });
+            accepted += 1;
+        }
+
+        Ok(Response::new(PublishAck { accepted }))
+    }
+
+    async fn report_device_presence(
+        &self,
+        request: Request<DevicePresence>,
+    ) -> Result<Response<PublishAck>, Status> {
+        let presence = request.into_inner();
+        let status = if presence.online { DeviceStatus::Online } else { DeviceStatus::Offline };
+
+        self.forward(MqttPayload::DeviceStatus {
+            device_id: presence.device_id,
+            status,
+            battery_level: None,
+            volume: None,
+            location: None,
+            last_seen: from_millis(presence.timestamp_ms),
+            metadata: None,
+        });
+
+        Ok(Response::new(PublishAck { accepted: 1 }))
+    }
+}
+
+/// Unix 毫秒转换为 UTC 时间，无效值使用当前时间
+fn from_millis(timestamp_ms: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(timestamp_ms).unwrap_or_else(Utc::now)
+}
+
+/// 在 GRPC_PORT 上提供内部 gRPC 服务，收到关闭信号后退出
+pub async fn serve(
+    events: broadcast::Sender<WebSocketMessage>,
+    token: String,
+    shutdown: Shutdown,
+) -> anyhow::Result<()> {
+    let port = std::env::var("GRPC_PORT")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_GRPC_PORT);
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    let service = GatewayServiceServer::with_interceptor(
+        GatewayGrpcService { events, shutdown: shutdown.clone() },
+        require_internal_token(token),
+    );
+
+    info!("Internal gRPC server listening on {}", addr);
+    Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(addr, async move { shutdown.wait().await })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use echo_shared::{ApiResponse, ConfigReloadResult, IpFilterConfig, IpFilterRule, PaginatedResponse, PaginationParams, UserRole};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use crate::app_state::AppState;
+use crate::audit::{self, AuditAction, AuditActor};
+use crate::config_reload;
+use crate::ip_filter;
+use crate::database::{AuditLogEntry, AuditLogFilter, BackupRecord, KeyRotationResult};
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::auth::Claims;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQueryParams {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+    pub action: Option<String>,
+    pub actor_id: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+/// 重新加载配置（仅管理员），返回发生变化的配置项和当前生效的运行时配置
+pub async fn reload_config(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+) -> ApiResult<ConfigReloadResult> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can reload the configuration"));
+    }
+
+    let actor = AuditActor::new(&claims, &headers);
+    let changed = config_reload::reload(&app_state.config_watcher, &app_state.database, &actor)
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Configuration reload failed: {}", e)))?;
+
+    Ok(Json(ApiResponse::success(ConfigReloadResult {
+        changed: changed.into_iter().map(String::from).collect(),
+        config: app_state.config_watcher.current(),
+    })))
+}
+
+/// 查询管理操作审计日志（仅管理员，最新的在前）
+pub async fn get_audit_log(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Query(params): Query<AuditLogQueryParams>,
+) -> ApiResult<PaginatedResponse<AuditLogEntry>> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can view the audit log"));
+    }
+
+    let pagination = PaginationParams {
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(50).clamp(1, 200),
+    };
+    let filter = AuditLogFilter {
+        action: params.action,
+        actor_id: params.actor_id,
+        target_type: params.target_type,
+        target_id: params.target_id,
+        start_date: params.start_date,
+        end_date: params.end_date,
+    };
+
+    match app_state.database.list_audit_log(&filter, &pagination).await {
+        Ok((entries, total)) => Ok(Json(ApiResponse::success(PaginatedResponse::new(entries, total, pagination)))),
+        Err(e) => {
+            error!("Failed to query audit log: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BackupQueryParams {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+/// 查询数据库备份记录（仅管理员，最新的在前），包括进行中和失败的备份
+pub async fn list_backups(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Query(params): Query<BackupQueryParams>,
+) -> ApiResult<PaginatedResponse<BackupRecord>> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can view database backups"));
+    }
+
+    let pagination = PaginationParams {
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(20).clamp(1, 100),
+    };
+
+    match app_state.database.list_backups(&pagination).await {
+        Ok((records, total)) => Ok(Json(ApiResponse::success(PaginatedResponse::new(records, total, pagination)))),
+        Err(e) => {
+            error!("Failed to query database backups: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// 每批重新包装的会话数
+const KEY_ROTATION_BATCH_SIZE: u32 = 500;
+
+/// 用当前主密钥重新包装已加密会话的数据密钥（仅管理员），轮换主密钥并重启服务后调用
+pub async fn rotate_encryption_keys(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+) -> ApiResult<KeyRotationResult> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can rotate encryption keys"));
+    }
+    let Some(key_id) = app_state.database.encryption_key_id().map(str::to_string) else {
+        return Err(ApiError::bad_request("Encryption at rest is not enabled"));
+    };
+
+    let result = app_state.database.rewrap_session_keys(KEY_ROTATION_BATCH_SIZE).await.map_err(|e| {
+        error!("Failed to rotate encryption keys: {}", e);
+        ApiError::from(e)
+    })?;
+
+    let actor = AuditActor::new(&claims, &headers);
+    audit::record(&app_state.database, &actor, AuditAction::EncryptionKeyRotation, &key_id, None, Some(&result)).await;
+
+    Ok(Json(ApiResponse::success(result)))
+}
+
+/// 当前生效的 IP 过滤配置和管理接口添加的规则
+#[derive(Debug, Serialize)]
+pub struct IpFilterOverview {
+    pub config: IpFilterConfig,
+    pub rules: Vec<IpFilterRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIpFilterRuleRequest {
+    /// 单个地址或 CIDR
+    pub cidr: String,
+    /// allow / deny
+    pub action: String,
+    pub note: Option<String>,
+}
+
+/// 查询 IP 过滤配置和规则（仅管理员）
+pub async fn get_ip_filter(
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<IpFilterOverview> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can view IP filter rules"));
+    }
+
+    match echo_shared::ip_filter::list_rules(app_state.database.pool()?).await {
+        Ok(rules) => Ok(Json(ApiResponse::success(IpFilterOverview {
+            config: app_state.config_watcher.current().ip_filter,
+            rules,
+        }))),
+        Err(e) => {
+            error!("Failed to query IP filter rules: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 添加 IP 过滤规则（仅管理员），立即生效；Bridge 在下一次刷新时生效
+pub async fn create_ip_filter_rule(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+    Json(request): Json<CreateIpFilterRuleRequest>,
+) -> ApiResult<IpFilterRule> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can manage IP filter rules"));
+    }
+    let cidr = request.cidr.trim();
+    echo_shared::ip_filter::validate_rule(cidr, &request.action).map_err(|e| ApiError::bad_request(e.to_string()))?;
+
+    let rule = app_state
+        .database
+        .add_ip_filter_rule(cidr, &request.action, request.note.as_deref(), &claims.username)
+        .await
+        .map_err(|e| {
+            error!("Failed to add IP filter rule: {}", e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::conflict(format!("A rule for {} already exists", cidr)))?;
+
+    let actor = AuditActor::new(&claims, &headers);
+    audit::record(&app_state.database, &actor, AuditAction::IpFilterRuleAdd, &rule.id.to_string(), None, Some(&rule)).await;
+    ip_filter::refresh(&app_state.ip_filter, &app_state.config_watcher.current().ip_filter, &app_state.database).await;
+
+    Ok(Json(ApiResponse::success(rule)))
+}
+
+/// 删除 IP 过滤规则（仅管理员）
+pub async fn delete_ip_filter_rule(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+    Path(rule_id): Path<i64>,
+) -> ApiResult<IpFilterRule> {
+    if claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can manage IP filter rules"));
+    }
+
+    let rule = app_state
+        .database
+        .delete_ip_filter_rule(rule_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete IP filter rule {}: {}", rule_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("IP filter rule not found"))?;
+
+    let actor = AuditActor::new(&claims, &headers);
+    audit::record(&app_state.database, &actor, AuditAction::IpFilterRuleDelete, &rule.id.to_string(), Some(&rule), None).await;
+    ip_filter::refresh(&app_state.ip_filter, &app_state.config_watcher.current().ip_filter, &app_state.database).await;
+
+    Ok(Json(ApiResponse::success(rule)))
+}
+
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/reload", post(reload_config))
+        .route("/audit-log", get(get_audit_log))
+        .route("/backups", get(list_backups))
+        .route("/encryption/rotate", post(rotate_encryption_keys))
+        .route("/ip-filter", get(get_ip_filter))
+        .route("/ip-filter/rules", post(create_ip_filter_rule))
+        .route("/ip-filter/rules/:id", delete(delete_ip_filter_rule))
+}
@@ -0,0 +1,271 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use echo_shared::{ApiResponse, CommandIssuer, DeviceCommandKind, DeviceId};
+use serde::Deserialize;
+use tracing::{error, info};
+use crate::announcements;
+use crate::app_state::AppState;
+use crate::database::{AnnouncementSchedule, NewAnnouncementSchedule};
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::auth::Claims;
+
+/// 播报计划名称的最大字符数
+const MAX_NAME_CHARS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAnnouncementRequest {
+    pub name: String,
+    /// 播报文本
+    pub text: String,
+    /// cron 表达式（"0 8 * * 1-5"）或 RRULE 规则（"FREQ=WEEKLY;BYDAY=MO;BYHOUR=9"）
+    pub recurrence: String,
+    /// IANA 时区名，默认使用配置的 default_timezone
+    pub timezone: Option<String>,
+    /// 规则开始生效的时间，默认为创建时间
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub device_ids: Vec<String>,
+    /// 按设备位置指定目标
+    #[serde(default)]
+    pub locations: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAnnouncementRequest {
+    pub name: Option<String>,
+    pub text: Option<String>,
+    pub recurrence: Option<String>,
+    pub timezone: Option<String>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub device_ids: Option<Vec<String>>,
+    pub locations: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+// 去掉空白和重复的目标
+fn normalize_targets(targets: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(targets.len());
+    for target in targets {
+        let target = target.trim();
+        if !target.is_empty() && !normalized.iter().any(|existing| existing == target) {
+            normalized.push(target.to_string());
+        }
+    }
+    normalized
+}
+
+/// 检查计划的名称、文本和目标；指定的设备需存在且当前用户有播报权限
+async fn check_schedule(app_state: &AppState, claims: &Claims, schedule: &AnnouncementSchedule) -> Result<(), ApiError> {
+    let config = &app_state.config.announcements;
+    if schedule.name.is_empty() || schedule.name.chars().count() > MAX_NAME_CHARS {
+        return Err(ApiError::bad_request(format!("name must be 1 to {} characters", MAX_NAME_CHARS)));
+    }
+    if schedule.text.is_empty() || schedule.text.chars().count() > config.max_text_chars {
+        return Err(ApiError::bad_request(format!("text must be 1 to {} characters", config.max_text_chars)));
+    }
+    let targets = schedule.device_ids.len() + schedule.locations.len();
+    if targets == 0 {
+        return Err(ApiError::bad_request("At least one device or location is required"));
+    }
+    if targets > config.max_targets {
+        return Err(ApiError::bad_request(format!("At most {} devices and locations can be targeted", config.max_targets)));
+    }
+
+    let issuer = CommandIssuer {
+        user_id: claims.sub.clone(),
+        username: claims.username.clone(),
+        role: claims.role.clone(),
+    };
+    for device_id in &schedule.device_ids {
+        if app_state.database.get_device_by_id(&DeviceId::new(device_id.as_str())).await?.is_none() {
+            return Err(ApiError::not_found(format!("Device {} not found", device_id)));
+        }
+        if let Err(denied) = app_state
+            .command_authorizer
+            .check(device_id, DeviceCommandKind::Announcement, Some(&issuer))
+            .await
+        {
+            return Err(ApiError::forbidden(format!("Device {}: {}", device_id, denied)));
+        }
+    }
+    Ok(())
+}
+
+/// 计算计划的下一次触发时间
+fn schedule_next_run(schedule: &AnnouncementSchedule) -> Result<Option<DateTime<Utc>>, ApiError> {
+    announcements::next_run(&schedule.recurrence, &schedule.timezone, schedule.starts_at, Utc::now())
+        .map_err(ApiError::bad_request)
+}
+
+/// 查询当前用户的播报计划
+pub async fn list_announcements(
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<Vec<AnnouncementSchedule>> {
+    match app_state.database.list_announcement_schedules(&claims.sub).await {
+        Ok(schedules) => Ok(Json(ApiResponse::success(schedules))),
+        Err(e) => {
+            error!("Failed to list announcement schedules for user {}: {}", claims.sub, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 创建播报计划
+pub async fn create_announcement(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Json(request): Json<CreateAnnouncementRequest>,
+) -> ApiResult<AnnouncementSchedule> {
+    let config = &app_state.config.announcements;
+    if !config.enabled {
+        return Err(ApiError::bad_request("Announcements are not enabled"));
+    }
+
+    let now = Utc::now();
+    let schedule = AnnouncementSchedule {
+        id: 0,
+        user_id: claims.sub.clone(),
+        name: request.name.trim().to_string(),
+        text: request.text.trim().to_string(),
+        recurrence: request.recurrence.trim().to_string(),
+        timezone: request.timezone.unwrap_or_else(|| config.default_timezone.clone()).trim().to_string(),
+        starts_at: request.starts_at.unwrap_or(now),
+        device_ids: normalize_targets(request.device_ids),
+        locations: normalize_targets(request.locations),
+        enabled: true,
+        next_run_at: None,
+        last_run_at: None,
+        last_device_count: None,
+        last_error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    check_schedule(&app_state, &claims, &schedule).await?;
+    let next_run_at = schedule_next_run(&schedule)?
+        .ok_or_else(|| ApiError::bad_request("recurrence has no upcoming occurrences"))?;
+
+    let count = app_state.database.count_announcement_schedules(&claims.sub).await?;
+    if count >= config.max_schedules_per_user as u64 {
+        return Err(ApiError::conflict(format!("At most {} announcement schedules can be created", config.max_schedules_per_user)));
+    }
+
+    let new_schedule = NewAnnouncementSchedule {
+        user_id: schedule.user_id,
+        name: schedule.name,
+        text: schedule.text,
+        recurrence: schedule.recurrence,
+        timezone: schedule.timezone,
+        starts_at: schedule.starts_at,
+        device_ids: schedule.device_ids,
+        locations: schedule.locations,
+        next_run_at: Some(next_run_at),
+    };
+    let schedule = app_state.database.create_announcement_schedule(&new_schedule).await.map_err(|e| {
+        error!("Failed to create announcement schedule for user {}: {}", claims.sub, e);
+        ApiError::from(e)
+    })?;
+    info!(
+        "Announcement schedule {} created by {} ({}, next run at {})",
+        schedule.id, claims.username, schedule.recurrence, next_run_at
+    );
+
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+pub async fn get_announcement(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(schedule_id): Path<i64>,
+) -> ApiResult<AnnouncementSchedule> {
+    let schedule = app_state
+        .database
+        .get_announcement_schedule(schedule_id, &claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Announcement schedule not found"))?;
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+/// 修改播报计划，修改后重新计算下一次触发时间
+pub async fn update_announcement(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(schedule_id): Path<i64>,
+    Json(request): Json<UpdateAnnouncementRequest>,
+) -> ApiResult<AnnouncementSchedule> {
+    let mut schedule = app_state
+        .database
+        .get_announcement_schedule(schedule_id, &claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Announcement schedule not found"))?;
+
+    if let Some(name) = request.name {
+        schedule.name = name.trim().to_string();
+    }
+    if let Some(text) = request.text {
+        schedule.text = text.trim().to_string();
+    }
+    if let Some(recurrence) = request.recurrence {
+        schedule.recurrence = recurrence.trim().to_string();
+    }
+    if let Some(timezone) = request.timezone {
+        schedule.timezone = timezone.trim().to_string();
+    }
+    if let Some(starts_at) = request.starts_at {
+        schedule.starts_at = starts_at;
+    }
+    if let Some(device_ids) = request.device_ids {
+        schedule.device_ids = normalize_targets(device_ids);
+    }
+    if let Some(locations) = request.locations {
+        schedule.locations = normalize_targets(locations);
+    }
+    if let Some(enabled) = request.enabled {
+        schedule.enabled = enabled;
+    }
+    check_schedule(&app_state, &claims, &schedule).await?;
+    schedule.next_run_at = schedule_next_run(&schedule)?;
+
+    let schedule = app_state
+        .database
+        .update_announcement_schedule(&schedule)
+        .await
+        .map_err(|e| {
+            error!("Failed to update announcement schedule {}: {}", schedule_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Announcement schedule not found"))?;
+
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+/// 删除播报计划
+pub async fn delete_announcement(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(schedule_id): Path<i64>,
+) -> ApiResult<AnnouncementSchedule> {
+    let schedule = app_state
+        .database
+        .delete_announcement_schedule(schedule_id, &claims.sub)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete announcement schedule {}: {}", schedule_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Announcement schedule not found"))?;
+    info!("Announcement schedule {} deleted by {}", schedule.id, claims.username);
+
+    Ok(Json(ApiResponse::success(schedule)))
+}
+
+pub fn announcement_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_announcements).post(create_announcement))
+        .route("/:id", get(get_announcement).patch(update_announcement).delete(delete_announcement))
+}
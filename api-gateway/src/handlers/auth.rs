@@ -1,26 +1,39 @@
 use axum::{
-    extract::{State},
-    http::StatusCode,
-    response::Json,
+    async_trait,
+    extract::{FromRef, FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, Method},
+    response::{AppendHeaders, IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
-use echo_shared::{ApiResponse, UserRole};
+use echo_shared::{ApiResponse, EchoError, OutboundMessage, UserId, UserRole};
 use serde_json::json;
 use serde::{Deserialize, Serialize};
 use crate::app_state::AppState;
-use jsonwebtoken::{encode, Header, EncodingKey};
+use crate::cache::Cache;
+use crate::error::{ApiError, ApiResult};
 use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use tracing::{error, info, warn};
+use crate::{cookie_auth, jwt_keys};
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// 浏览器客户端使用 Cookie 会话（需启用 cookie_auth），令牌不返回给脚本
+    #[serde(default)]
+    pub cookie: bool,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
-    pub token: String,
+    /// Bearer 令牌，Cookie 会话时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Cookie 会话中修改状态的请求需要携带的 CSRF 令牌
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
     pub user: UserInfo,
     pub expires_in: u64,
 }
@@ -33,66 +46,99 @@ pub struct UserInfo {
     pub role: UserRole,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,     // 用户ID
     pub username: String,
     pub role: UserRole,
     pub exp: i64,        // 过期时间
     pub iat: i64,        // 签发时间
+    /// Cookie 会话的 CSRF 令牌摘要，Bearer 令牌没有此声明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csrf: Option<String>,
+    /// 签发时用户的令牌版本号，重置密码后版本号变化，旧令牌随之失效
+    #[serde(default)]
+    pub ver: i32,
 }
 
-// 简化的登录处理（硬编码验证，后续可连接数据库）
+impl Claims {
+    /// 令牌所属用户的 ID
+    pub fn user_id(&self) -> UserId {
+        UserId::from(self.sub.as_str())
+    }
+}
+
+// 登录：按数据库中的密码哈希校验用户名和密码
 pub async fn login(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<ApiResponse<LoginResponse>>, StatusCode> {
-    // 简化的用户验证（硬编码，仅用于测试）
-    if payload.username == "admin" && payload.password == "admin123" {
-        let user_info = UserInfo {
-            id: "admin-001".to_string(),
-            username: "admin".to_string(),
-            email: "admin@echo.system".to_string(),
-            role: UserRole::Admin,
-        };
-
-        // 生成 JWT token
-        let token = generate_jwt_token(&user_info).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        let login_response = LoginResponse {
-            token,
-            user: user_info,
-            expires_in: 24 * 3600, // 24小时
-        };
-
-        Ok(Json(ApiResponse::success(login_response)))
-    } else if payload.username == "user" && payload.password == "user123" {
-        let user_info = UserInfo {
-            id: "user-001".to_string(),
-            username: "user".to_string(),
-            email: "user@echo.system".to_string(),
-            role: UserRole::User,
-        };
+) -> Result<Response, ApiError> {
+    let user = match app_state.database.verify_password(&payload.username, &payload.password).await? {
+        Some(user) => user,
+        None => {
+            warn!("Failed login attempt for user {}", payload.username);
+            return Err(ApiError::unauthorized("Invalid username or password"));
+        }
+    };
+    let user_id = UserId::from(user.id.as_str());
+    let token_version = app_state
+        .database
+        .get_user_token_version(&user_id)
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("Invalid username or password"))?;
+    let user_info = UserInfo {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        role: user.role,
+    };
 
+    let expires_in = jwt_keys::expiration_hours() * 3600;
+    if !payload.cookie {
         // 生成 JWT token
-        let token = generate_jwt_token(&user_info).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
+        let token = generate_jwt_token(&user_info, token_version, None)?;
         let login_response = LoginResponse {
-            token,
+            token: Some(token),
+            csrf_token: None,
             user: user_info,
-            expires_in: 24 * 3600, // 24小时
+            expires_in,
         };
-
-        Ok(Json(ApiResponse::success(login_response)))
-    } else {
-        Ok(Json(ApiResponse::error("Invalid username or password".to_string())))
+        return Ok(Json(ApiResponse::success(login_response)).into_response());
     }
+
+    // Cookie 会话：JWT 只写入 HttpOnly Cookie，CSRF 令牌的摘要写入 JWT
+    let settings = cookie_auth::settings().ok_or_else(|| ApiError::bad_request("Cookie sessions are not enabled"))?;
+    let csrf_token = cookie_auth::generate_csrf_token();
+    let token = generate_jwt_token(&user_info, token_version, Some(cookie_auth::csrf_digest(&csrf_token)))?;
+    let cookies = cookie_auth::session_cookies(&settings, &token, &csrf_token, expires_in);
+    let login_response = LoginResponse {
+        token: None,
+        csrf_token: Some(csrf_token),
+        user: user_info,
+        expires_in,
+    };
+    Ok((
+        AppendHeaders(cookies.into_iter().map(|cookie| (header::SET_COOKIE, cookie))),
+        Json(ApiResponse::success(login_response)),
+    )
+        .into_response())
 }
 
 // 生成JWT token
-fn generate_jwt_token(user: &UserInfo) -> Result<String, Box<dyn std::error::Error>> {
+fn generate_jwt_token(user: &UserInfo, token_version: i32, csrf: Option<String>) -> Result<String, ApiError> {
     let now = Utc::now();
-    let exp = now + Duration::hours(24);
+    let exp = now + Duration::hours(jwt_keys::expiration_hours() as i64);
 
     let claims = Claims {
         sub: user.id.clone(),
@@ -100,21 +146,134 @@ fn generate_jwt_token(user: &UserInfo) -> Result<String, Box<dyn std::error::Err
         role: user.role.clone(),
         exp: exp.timestamp(),
         iat: now.timestamp(),
+        csrf,
+        ver: token_version,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret("your-super-secret-jwt-key-change-in-production".as_ref()),
-    )?;
+    let token = jwt_keys::sign(&claims).map_err(EchoError::from)?;
 
     Ok(token)
 }
 
+// 校验并解析 JWT token（包含过期校验，按 kid 选择签名密钥）
+pub fn decode_jwt_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    jwt_keys::verify(token)
+}
+
+impl Claims {
+    // 测试模式下匿名请求使用的管理员身份
+    pub fn test_admin() -> Self {
+        Claims {
+            sub: "test".to_string(),
+            username: "test".to_string(),
+            role: UserRole::Admin,
+            exp: 0,
+            iat: 0,
+            csrf: None,
+            ver: 0,
+        }
+    }
+
+    fn is_test_admin(&self) -> bool {
+        self.sub == "test" && self.iat == 0
+    }
+}
+
+// 读取 Authorization: Bearer <token>
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+// 校验 token 并返回用户身份，测试模式下未携带 token 的请求以管理员身份通过
+pub fn authenticate(token: Option<&str>) -> Result<Claims, ApiError> {
+    match token.map(decode_jwt_token) {
+        Some(Ok(claims)) => Ok(claims),
+        Some(Err(e)) => {
+            warn!("Rejected request with invalid token: {}", e);
+            Err(EchoError::from(e).into())
+        }
+        None if std::env::var("RUST_ENV").unwrap_or_default() == "test" => Ok(Claims::test_admin()),
+        None => Err(ApiError::unauthorized("Missing bearer token")),
+    }
+}
+
+// 按 Authorization: Bearer <token> 或 Cookie 会话校验请求，Cookie 会话中修改状态的请求还需校验 CSRF 令牌
+pub fn authenticate_request(method: &Method, headers: &HeaderMap) -> Result<Claims, ApiError> {
+    if let Some(token) = bearer_token(headers) {
+        return authenticate(Some(token));
+    }
+    match cookie_auth::session_token(headers) {
+        Some(token) => {
+            let claims = authenticate(Some(&token))?;
+            cookie_auth::check_csrf(method, headers, claims.csrf.as_deref())?;
+            Ok(claims)
+        }
+        None => authenticate(None),
+    }
+}
+
+// 校验令牌版本号：用户已停用或重置过密码时，之前签发的令牌不再接受
+// 版本号在 Redis 中缓存 ttl::USER_TOKEN_VERSION 秒，重置密码时清除
+pub async fn ensure_token_current(app_state: &AppState, claims: &Claims) -> Result<(), ApiError> {
+    if claims.is_test_admin() {
+        return Ok(());
+    }
+
+    let user_id = claims.user_id();
+    let key = Cache::user_token_version_key(&user_id);
+    let cached = match app_state.cache.get::<i32>(&key).await {
+        Ok(cached) => cached,
+        Err(e) => {
+            warn!("Failed to read cached token version for user {}: {}", user_id, e);
+            None
+        }
+    };
+    let version = match cached {
+        Some(version) => Some(version),
+        None => {
+            let version = app_state.database.get_user_token_version(&user_id).await?;
+            if let Some(version) = version {
+                if let Err(e) = app_state.cache.set(&key, &version, echo_shared::ttl::USER_TOKEN_VERSION).await {
+                    warn!("Failed to cache token version for user {}: {}", user_id, e);
+                }
+            }
+            version
+        }
+    };
+
+    match version {
+        Some(version) if version == claims.ver => Ok(()),
+        Some(_) => {
+            warn!("Rejected revoked token for user {}", user_id);
+            Err(ApiError::unauthorized("Token has been revoked"))
+        }
+        None => Err(ApiError::unauthorized("User is not active")),
+    }
+}
+
+// 从 Authorization: Bearer <token> 或 Cookie 会话中提取当前用户身份
+#[async_trait]
+impl<S> FromRequestParts<S> for Claims
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = authenticate_request(&parts.method, &parts.headers)?;
+        ensure_token_current(&AppState::from_ref(state), &claims).await?;
+        Ok(claims)
+    }
+}
+
 // 用户信息获取（简化版，实际应从JWT解析）
 pub async fn get_user_info(
     State(_app_state): State<AppState>,
-) -> Result<Json<ApiResponse<UserInfo>>, StatusCode> {
+) -> ApiResult<UserInfo> {
     // TODO: 从 JWT token 中解析用户信息
     let user_info = UserInfo {
         id: "admin-001".to_string(),
@@ -126,14 +285,133 @@ pub async fn get_user_info(
     Ok(Json(ApiResponse::success(user_info)))
 }
 
-// 退出登录
-pub async fn logout() -> Json<ApiResponse<serde_json::Value>> {
+// 退出登录，启用 Cookie 会话时清除会话 Cookie
+pub async fn logout() -> Response {
     // TODO: 实现 token 黑名单机制
-    let response = json!({
+    let response = Json(ApiResponse::success(json!({
         "message": "Logged out successfully"
-    });
+    })));
+
+    match cookie_auth::settings() {
+        Some(settings) => {
+            let cookies = cookie_auth::clear_cookies(&settings);
+            (AppendHeaders(cookies.into_iter().map(|cookie| (header::SET_COOKIE, cookie))), response).into_response()
+        }
+        None => response.into_response(),
+    }
+}
+
+// 忘记密码：生成一次性重置令牌并通过邮件发送
+// 无论邮箱是否存在都返回相同响应，避免泄露用户是否注册
+pub async fn forgot_password(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Json<ApiResponse<serde_json::Value>> {
+    let response = Json(ApiResponse::success(json!({
+        "message": "If the email is registered, a password reset link has been sent"
+    })));
+
+    if !echo_shared::validate_email(&payload.email) {
+        return response;
+    }
+
+    let user = match app_state.database.get_user_by_email(&payload.email).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            info!("Password reset requested for unknown email");
+            return response;
+        }
+        Err(e) => {
+            error!("Failed to look up user for password reset: {}", e);
+            return response;
+        }
+    };
+
+    let token = generate_reset_token();
+    if let Err(e) = app_state
+        .cache
+        .store_password_reset_token(&token, &user.id, echo_shared::ttl::PASSWORD_RESET)
+        .await
+    {
+        error!("Failed to store password reset token for user {}: {}", user.id, e);
+        return response;
+    }
+
+    let reset_url = std::env::var("PASSWORD_RESET_URL")
+        .unwrap_or_else(|_| "http://localhost:5173/reset-password".to_string());
+    let message = OutboundMessage::email(
+        user.email.clone(),
+        "Echo 密码重置",
+        format!(
+            "您好 {}，请在 {} 分钟内访问以下链接重置密码：{}?token={}",
+            user.username,
+            echo_shared::ttl::PASSWORD_RESET / 60,
+            reset_url,
+            token
+        ),
+    );
+
+    if let Err(e) = app_state.message_sender.send(&message).await {
+        error!(
+            "Failed to deliver password reset message via {}: {}",
+            app_state.message_sender.name(),
+            e
+        );
+    }
+
+    response
+}
+
+// 重置密码：校验并消费一次性令牌后更新密码
+pub async fn reset_password(
+    State(app_state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> ApiResult<serde_json::Value> {
+    if payload.new_password.len() < 8 {
+        return Err(ApiError::bad_request("Password must be at least 8 characters"));
+    }
+
+    let user_id = match app_state.cache.consume_password_reset_token(&payload.token).await {
+        Ok(Some(user_id)) => UserId::from(user_id),
+        Ok(None) => {
+            warn!("Invalid or expired password reset token used");
+            return Err(ApiError::bad_request("Invalid or expired reset token"));
+        }
+        Err(e) => {
+            error!("Failed to read password reset token: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    let password_hash = echo_shared::hash_password(&payload.new_password)?;
+
+    match app_state.database.update_user_password(&user_id, &password_hash).await {
+        Ok(true) => {
+            // 密码已修改，令牌版本号已递增；清理会话缓存和缓存的版本号，旧令牌立即失效
+            if let Err(e) = app_state.cache.clear_user_cache(&user_id).await {
+                warn!("Failed to clear cache for user {}: {}", user_id, e);
+            }
+
+            info!("Password reset completed for user {}", user_id);
+            Ok(Json(ApiResponse::success(json!({
+                "message": "Password has been reset successfully"
+            }))))
+        }
+        Ok(false) => Err(ApiError::not_found("User not found")),
+        Err(e) => {
+            error!("Failed to update password for user {}: {}", user_id, e);
+            Err(e.into())
+        }
+    }
+}
 
-    Json(ApiResponse::success(response))
+// 生成密码重置令牌
+fn generate_reset_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
 }
 
 pub fn auth_routes() -> Router<AppState> {
@@ -141,4 +419,6 @@ pub fn auth_routes() -> Router<AppState> {
         .route("/login", post(login))
         .route("/me", get(get_user_info))
         .route("/logout", post(logout))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
 }
\ No newline at end of file
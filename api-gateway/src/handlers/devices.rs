@@ -1,32 +1,55 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, RawQuery, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
     routing::{get, post, delete},
     Router,
 };
-use echo_shared::{ApiResponse, Device, DeviceStatus, DeviceType, DeviceConfig, PaginationParams, PaginatedResponse, generate_uuid, now_utc,
+use echo_shared::{ApiResponse, EchoError, Device, DeviceId, DeviceStatus, DeviceType, DeviceConfig, PaginationParams, PaginatedResponse, generate_uuid, now_utc,
                   DeviceRegistrationRequest, DeviceRegistrationResponse, DeviceVerificationRequest, DeviceVerificationResponse,
-                  RegistrationExtensionRequest, RegistrationExtensionResponse};
+                  RegistrationExtensionRequest, RegistrationExtensionResponse, BridgeOnlineDevice, UserRole, CacheOperation, apply_presence,
+                  DeviceCommand, DeviceConfiguration, MqttMessageBuilder, CommandIssuer, DeviceCommandKind};
 use tracing::{info, error, warn};
 use serde::Deserialize;
 use serde_json::json;
+use validator::Validate;
 use crate::app_state::AppState;
+use crate::audit::{self, AuditAction, AuditActor};
+use crate::error::{ApiError, ApiResult};
+use crate::validation::ValidatedJson;
+use crate::http_cache;
+use crate::database::{DeviceFilter, DeviceUpdate};
+use crate::handlers::auth::Claims;
+use crate::handlers::sessions::parse_cursor_param;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateDeviceRequest {
+    #[validate(length(min = 1, max = 50, message = "设备名称长度为1-50个字符"))]
     pub name: String,
     pub device_type: DeviceType,
+    #[validate(length(max = 100, message = "位置长度不能超过100个字符"))]
     pub location: String,
+    #[validate(url(message = "EchoKit Server URL 格式不正确"))]
     pub echokit_server_url: String,  // 必填：EchoKit Server URL
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateDeviceRequest {
+    #[validate(length(min = 1, max = 50, message = "设备名称长度为1-50个字符"))]
     pub name: Option<String>,
+    #[validate(length(max = 100, message = "位置长度不能超过100个字符"))]
     pub location: Option<String>,
     pub config: Option<DeviceConfig>,
+    #[validate(url(message = "EchoKit Server URL 格式不正确"))]
     pub echokit_server_url: Option<String>,
+    /// 读取设备时的版本号，与 If-Match 请求头二选一（If-Match 优先）
+    pub version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TransferDeviceRequest {
+    #[validate(length(min = 1, max = 255, message = "新所有者 ID 不能为空"))]
+    pub new_owner: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,24 +59,69 @@ pub struct DeviceQueryParams {
     pub status: Option<DeviceStatus>,
     pub device_type: Option<DeviceType>,
     pub location: Option<String>,
+    /// 游标分页：首页传空字符串，之后传上一页返回的 next_cursor
+    pub cursor: Option<String>,
 }
 
-// 模拟设备数据存储
-static mut DEVICES: Option<Vec<Device>> = None;
-
 // 获取设备列表
 pub async fn get_devices(
     State(app_state): State<AppState>,
     Query(params): Query<DeviceQueryParams>,
-) -> Json<ApiResponse<PaginatedResponse<Device>>> {
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let variant = format!("list:{}", query.unwrap_or_default());
+    http_cache::cached_json(&app_state.cache, http_cache::DEVICES_SCOPE, &variant, &headers, || {
+        load_devices(&app_state, params)
+    }).await
+}
+
+// 从数据库读取设备列表（按查询条件过滤并分页）
+async fn load_devices(
+    app_state: &AppState,
+    params: DeviceQueryParams,
+) -> Result<PaginatedResponse<Device>, ApiError> {
     let pagination = PaginationParams {
         page: params.page.unwrap_or(1),
         page_size: params.page_size.unwrap_or(20),
     };
 
+    // 游标分页模式，直接在数据库中过滤和分页
+    if let Some(cursor) = params.cursor.as_deref() {
+        let cursor = parse_cursor_param(cursor).map_err(ApiError::bad_request)?;
+        let filter = DeviceFilter {
+            status: params.status,
+            device_type: params.device_type,
+            location: params.location,
+        };
+        let page_size = pagination.page_size.clamp(1, 100);
+
+        return match app_state.database.list_devices_after(&filter, cursor.as_ref(), page_size).await {
+            Ok((mut devices, total, next_cursor)) => {
+                // 数据库按存储的状态过滤，实时状态不同的设备从本页去掉
+                merge_presence(app_state, &mut devices).await;
+                if let Some(status) = &filter.status {
+                    devices.retain(|d| &d.status == status);
+                }
+                Ok(PaginatedResponse::with_cursor(
+                    devices,
+                    total,
+                    page_size,
+                    next_cursor.map(|c| c.encode()),
+                ))
+            }
+            Err(e) => {
+                error!("Failed to get devices from database: {}", e);
+                Err(e.into())
+            }
+        };
+    }
+
     // 从数据库获取设备列表
     match app_state.database.get_all_devices().await {
-        Ok(devices) => {
+        Ok(mut devices) => {
+            merge_presence(app_state, &mut devices).await;
+
             // 应用过滤条件
             let mut filtered_devices: Vec<Device> = devices;
 
@@ -80,28 +148,33 @@ pub async fn get_devices(
                 vec![]
             };
 
-            let response = PaginatedResponse::new(paginated_devices, total, pagination);
-            Json(ApiResponse::success(response))
+            Ok(PaginatedResponse::new(paginated_devices, total, pagination))
         }
         Err(e) => {
             error!("Failed to get devices from database: {}", e);
-            let empty_response = PaginatedResponse::new(vec![], 0, pagination);
-            Json(ApiResponse::success(empty_response))
+            Err(e.into())
         }
     }
 }
 
 // 获取单个设备详情
 pub async fn get_device(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
-) -> Result<Json<ApiResponse<Device>>, StatusCode> {
-    match app_state.database.get_device_by_id(&device_id).await {
-        Ok(Some(device)) => Ok(Json(ApiResponse::success(device))),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+) -> Result<Response, ApiError> {
+    let device = app_state
+        .cache
+        .get_device(&device_id, || app_state.database.get_device_by_id(&device_id))
+        .await;
+    match device {
+        Ok(Some(mut device)) => {
+            merge_presence(&app_state, std::slice::from_mut(&mut device)).await;
+            Ok(device_response(device))
+        }
+        Ok(None) => Err(EchoError::DeviceNotFound(device_id.into()).into()),
         Err(e) => {
             error!("Failed to get device by id {}: {}", device_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -109,8 +182,8 @@ pub async fn get_device(
 // 创建新设备
 pub async fn create_device(
     State(app_state): State<AppState>,
-    Json(payload): Json<CreateDeviceRequest>,
-) -> Json<ApiResponse<Device>> {
+    ValidatedJson(payload): ValidatedJson<CreateDeviceRequest>,
+) -> ApiResult<Device> {
     let new_device = Device {
         id: generate_uuid(),
         name: payload.name,
@@ -124,139 +197,319 @@ pub async fn create_device(
         is_online: false,
         owner: "user001".to_string(), // TODO: 从认证信息中获取
         echokit_server_url: Some(payload.echokit_server_url),  // 使用请求中的必填 URL
+        serial_number: None,
+        mac_address: None,
+        version: 1,
+        current_session_id: None,
     };
 
     match app_state.database.create_device(
         &new_device,
-        None, // serial_number
-        None, // mac_address
         None, // pairing_code
         None, // registration_token
     ).await {
-        Ok(created_device) => Json(ApiResponse::success(created_device)),
+        Ok(created_device) => {
+            http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
+            Ok(Json(ApiResponse::success(created_device)))
+        }
         Err(e) => {
             error!("Failed to create device: {}", e);
-            Json(ApiResponse::error("Failed to create device".to_string()))
+            Err(e.into())
         }
     }
 }
 
 // 更新设备信息
+//
+// 乐观并发控制：请求头 If-Match（或请求体 version）为读取设备时的版本号，设备已被其他请求修改时返回 409；
+// 未提供时以本次读取到的版本为准，读取与写入之间被修改同样返回 409
 pub async fn update_device(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
-    Json(payload): Json<UpdateDeviceRequest>,
-) -> Result<Json<ApiResponse<Device>>, StatusCode> {
-    // 获取现有设备信息
-    match app_state.database.get_device_by_id(&device_id).await {
-        Ok(Some(mut device)) => {
-            // TODO: 从认证信息中获取真实的 owner_id
-            let owner_id = &device.owner;
-
-            // 更新设备名称
-            if let Some(ref name) = payload.name {
-                match app_state.database.update_device_name(&device_id, owner_id, name).await {
-                    Ok(true) => device.name = name.clone(),
-                    Ok(false) => return Err(StatusCode::NOT_FOUND),
-                    Err(e) => {
-                        error!("Failed to update device name: {}", e);
-                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                    }
-                }
-            }
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<UpdateDeviceRequest>,
+) -> Result<Response, ApiError> {
+    let expected_version = if_match_version(&headers)?.or(payload.version);
 
-            // 更新设备位置
-            if let Some(ref location) = payload.location {
-                match app_state.database.update_device_location(&device_id, owner_id, location).await {
-                    Ok(true) => device.location = location.clone(),
-                    Ok(false) => return Err(StatusCode::NOT_FOUND),
-                    Err(e) => {
-                        error!("Failed to update device location: {}", e);
-                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                    }
-                }
-            }
-
-            // 更新 EchoKit Server URL
-            if payload.echokit_server_url.is_some() {
-                let url_ref = payload.echokit_server_url.as_deref();
-                match app_state.database.update_device_echokit_server(
-                    &device_id,
-                    owner_id,
-                    url_ref
-                ).await {
-                    Ok(true) => device.echokit_server_url = payload.echokit_server_url.clone(),
-                    Ok(false) => return Err(StatusCode::NOT_FOUND),
-                    Err(e) => {
-                        error!("Failed to update device echokit_server_url: {}", e);
-                        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-                    }
-                }
-            }
+    let mut device = match app_state.database.get_device_by_id(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return Err(EchoError::DeviceNotFound(device_id.into()).into()),
+        Err(e) => {
+            error!("Failed to get device for update: {}", e);
+            return Err(e.into());
+        }
+    };
+    let expected_version = expected_version.unwrap_or(device.version);
 
-            // 更新配置信息（音量和电池电量）
-            if let Some(config) = payload.config {
-                if let Some(volume) = config.volume {
-                    device.volume = volume;
-                }
-                if let Some(battery_level) = config.battery_level {
-                    device.battery_level = battery_level;
-                }
+    if let Some(name) = payload.name {
+        device.name = name;
+    }
+    if let Some(location) = payload.location {
+        device.location = location;
+    }
+    if payload.echokit_server_url.is_some() {
+        device.echokit_server_url = payload.echokit_server_url;
+    }
+    // 更新配置信息（音量和电池电量）
+    if let Some(config) = payload.config {
+        for (name, value) in [("volume", config.volume), ("battery_level", config.battery_level)] {
+            if value.is_some_and(|value| !(0..=100).contains(&value)) {
+                return Err(ApiError::bad_request(format!("config.{} must be between 0 and 100", name)));
             }
-            device.last_seen = now_utc();
+        }
+        if let Some(volume) = config.volume {
+            device.volume = volume;
+        }
+        if let Some(battery_level) = config.battery_level {
+            device.battery_level = battery_level;
+        }
+    }
 
-            Ok(Json(ApiResponse::success(device)))
+    match app_state.database.update_device(&device, expected_version).await {
+        Ok(DeviceUpdate::Updated(device)) => {
+            app_state.cache.invalidate_device(&device.id).await;
+            http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
+            Ok(device_response(*device))
         }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Ok(DeviceUpdate::VersionConflict(current)) => Err(ApiError::conflict(format!(
+            "Device {} was modified by another request (expected version {}, current version {})",
+            device_id, expected_version, current
+        ))),
+        Ok(DeviceUpdate::NotFound) => Err(EchoError::DeviceNotFound(device_id.into()).into()),
         Err(e) => {
-            error!("Failed to get device for update: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            error!("Failed to update device {}: {}", device_id, e);
+            Err(e.into())
         }
     }
 }
 
+// 用 Bridge 写入 Redis 的实时在线状态覆盖数据库中的在线状态，Redis 不可用时保留数据库中的状态
+async fn merge_presence(app_state: &AppState, devices: &mut [Device]) {
+    if !app_state.config.features.device_presence || devices.is_empty() || app_state.cache.skip(CacheOperation::Read) {
+        return;
+    }
+
+    let device_ids: Vec<&str> = devices.iter().map(|d| d.id.as_str()).collect();
+    match app_state.cache.get_device_presence(&device_ids).await {
+        Ok(presence) => {
+            for device in devices.iter_mut() {
+                apply_presence(device, presence.get(&device.id));
+            }
+        }
+        Err(e) => warn!("Failed to read device presence from Redis, using database status: {}", e),
+    }
+}
+
+// 单个设备的响应，ETag 为设备版本号，更新时作为 If-Match 传回
+fn device_response(device: Device) -> Response {
+    let etag = format!("\"{}\"", device.version);
+    ([(header::ETAG, etag)], Json(ApiResponse::success(device))).into_response()
+}
+
+// 解析 If-Match 中的版本号，未提供或为 * 时返回 None
+fn if_match_version(headers: &HeaderMap) -> Result<Option<i32>, ApiError> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| ApiError::bad_request("Invalid If-Match header"))?
+        .trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .map(Some)
+        .map_err(|_| ApiError::bad_request(format!("If-Match must be a device ETag such as \"1\", got {}", value)))
+}
+
 // 删除设备
 pub async fn delete_device(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
+    claims: Claims,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
     // 首先检查设备是否存在
     match app_state.database.get_device_by_id(&device_id).await {
-        Ok(Some(_device)) => {
+        Ok(Some(device)) => {
             // 实现数据库删除操作
             match app_state.database.delete_device(&device_id).await {
                 Ok(()) => {
                     info!("Device {} deleted successfully", device_id);
+                    app_state.cache.invalidate_device(&device_id).await;
+                    http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
+                    let actor = AuditActor::new(&claims, &headers);
+                    audit::record(&app_state.database, &actor, AuditAction::DeviceDelete, &device_id, Some(&device), None)
+                        .await;
                     let response = json!({
                         "message": "Device deleted successfully",
                         "device_id": device_id
                     });
-                    Json(ApiResponse::success(response))
+                    Ok(Json(ApiResponse::success(response)))
                 }
                 Err(e) => {
                     error!("Failed to delete device: {}", e);
-                    Json(ApiResponse::error("Failed to delete device".to_string()))
+                    Err(e.into())
                 }
             }
         }
-        Ok(None) => {
-            Json(ApiResponse::error("Device not found".to_string()))
-        }
+        Ok(None) => Err(EchoError::DeviceNotFound(device_id.into()).into()),
         Err(e) => {
             error!("Failed to get device for deletion: {}", e);
-            Json(ApiResponse::error("Failed to delete device".to_string()))
+            Err(e.into())
         }
     }
 }
 
+// 转移设备所有权（仅当前所有者或管理员）
+pub async fn transfer_device(
+    Path(device_id): Path<DeviceId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<TransferDeviceRequest>,
+) -> ApiResult<Device> {
+    let device = match app_state.database.get_device_by_id(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return Err(EchoError::DeviceNotFound(device_id.into()).into()),
+        Err(e) => {
+            error!("Failed to get device for transfer: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    if claims.role != UserRole::Admin && device.owner != claims.sub {
+        return Err(ApiError::forbidden("Only the device owner or an administrator can transfer this device"));
+    }
+    if device.owner == payload.new_owner {
+        return Err(ApiError::conflict("Device is already owned by this user"));
+    }
+
+    match app_state
+        .database
+        .transfer_device_owner(&device_id, &device.owner, &payload.new_owner)
+        .await
+    {
+        Ok(true) => {}
+        // 查询后所有者已被并发修改
+        Ok(false) => return Err(ApiError::conflict("Device owner changed, please retry")),
+        Err(e) => {
+            error!("Failed to transfer device {}: {}", device_id, e);
+            return Err(e.into());
+        }
+    }
+
+    let mut transferred = device.clone();
+    transferred.owner = payload.new_owner;
+    info!("Device {} transferred from {} to {} by {}", device_id, device.owner, transferred.owner, claims.username);
+
+    app_state.cache.invalidate_device(&device_id).await;
+    http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
+    let actor = AuditActor::new(&claims, &headers);
+    audit::record(&app_state.database, &actor, AuditAction::DeviceTransfer, &device_id, Some(&device), Some(&transferred))
+        .await;
+
+    Ok(Json(ApiResponse::success(transferred)))
+}
+
+// 签发连接设备 WebSocket 的一次性票据（仅设备所有者或管理员），Web UI 将票据附在 Bridge 的 `/ws/{id}?ticket=` 上
+pub async fn issue_ws_ticket(
+    Path(device_id): Path<DeviceId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<serde_json::Value> {
+    let Some(ttl_seconds) = app_state.cache.ws_ticket_ttl() else {
+        return Err(ApiError::not_implemented("WebSocket connection tickets are not enabled"));
+    };
+    let device = match app_state.database.get_device_by_id(&device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return Err(EchoError::DeviceNotFound(device_id.into()).into()),
+        Err(e) => {
+            error!("Failed to get device for connection ticket: {}", e);
+            return Err(e.into());
+        }
+    };
+    if claims.role != UserRole::Admin && device.owner != claims.sub {
+        return Err(ApiError::forbidden("Only the device owner or an administrator can connect to this device"));
+    }
+
+    match app_state.cache.issue_ws_ticket(&device_id, &claims.sub).await {
+        Ok(Some(ticket)) => Ok(Json(ApiResponse::success(json!({
+            "ticket": ticket,
+            "device_id": device_id,
+            "expires_in": ttl_seconds
+        })))),
+        Ok(None) => Err(ApiError::not_implemented("WebSocket connection tickets are not enabled")),
+        Err(e) => {
+            error!("Failed to issue connection ticket for device {}: {}", device_id, e);
+            Err(ApiError::service_unavailable("Failed to issue connection ticket, please retry"))
+        }
+    }
+}
+
+// 按 command_authorization 检查用户能否向设备下发该类命令，拒绝时写入审计日志
+async fn authorize_command(
+    app_state: &AppState,
+    claims: &Claims,
+    headers: &HeaderMap,
+    device_id: &DeviceId,
+    kind: DeviceCommandKind,
+) -> Result<CommandIssuer, ApiError> {
+    let issuer = CommandIssuer {
+        user_id: claims.sub.clone(),
+        username: claims.username.clone(),
+        role: claims.role.clone(),
+    };
+    let Err(denied) = app_state.command_authorizer.check(device_id.as_str(), kind, Some(&issuer)).await else {
+        return Ok(issuer);
+    };
+
+    warn!("Denied {} command for device {} from {}: {}", kind.as_str(), device_id, claims.username, denied);
+    crate::telemetry::record_command_denied(kind.as_str(), denied.reason());
+    let details = json!({ "command": kind.as_str(), "reason": denied.to_string(), "source": "gateway" });
+    audit::record(
+        &app_state.database,
+        &AuditActor::new(claims, headers),
+        AuditAction::DeviceCommandDenied,
+        device_id.as_str(),
+        None,
+        Some(&details),
+    )
+    .await;
+    Err(ApiError::forbidden(denied.to_string()))
+}
+
+// 构建下发给设备的命令消息，启用命令签名时连同下发用户一起签名，Bridge 验证后才转发给设备；
+// issuer 为 None 时为系统命令
+pub(crate) fn command_message(app_state: &AppState, device_id: &DeviceId, command: DeviceCommand, issuer: Option<CommandIssuer>) -> anyhow::Result<echo_shared::mqtt::MqttMessage> {
+    Ok(match &app_state.command_signer {
+        Some(signer) => MqttMessageBuilder::signed_command(
+            device_id.to_string(),
+            signer.sign_as(device_id.as_str(), &command, issuer.as_ref())?,
+        ),
+        None => MqttMessageBuilder::device_control_by(device_id.to_string(), command, issuer),
+    })
+}
+
+// 设备重启锁的过期时间，持有期间自动续期
+const DEVICE_RESTART_LOCK_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
 // 重启设备
 pub async fn restart_device(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
+    claims: Claims,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
     // 检查设备是否存在
     match app_state.database.get_device_by_id(&device_id).await {
         Ok(Some(_device)) => {
+            let issuer = authorize_command(&app_state, &claims, &headers, &device_id, DeviceCommandKind::Restart).await?;
+
             // TODO: 实现数据库状态更新操作
             // match app_state.database.update_device_status(&device_id, DeviceStatus::Maintenance).await {
             //     Ok(()) => {
@@ -283,30 +536,145 @@ pub async fn restart_device(
             //     }
             // }
 
-            // 暂时返回成功响应
-            let response = json!({
-                "message": "Device restart not yet fully implemented",
-                "device_id": device_id,
-                "estimated_recovery_time": "5 seconds"
-            });
-            Json(ApiResponse::success(response))
-        }
-        Ok(None) => {
-            Json(ApiResponse::error("Device not found".to_string()))
+            // 同一设备的重启在所有 Gateway 实例之间互斥
+            let restart = app_state.cache.with_device_lock(&device_id, "restart", DEVICE_RESTART_LOCK_TTL, || async {
+                let message = command_message(&app_state, &device_id, DeviceCommand::Reboot, Some(issuer.clone()))?;
+                app_state.database.enqueue_device_command(&message).await?;
+                info!("Queued restart command for device {}", device_id);
+                Ok::<_, anyhow::Error>(json!({
+                    "message": "Device restart command sent",
+                    "device_id": device_id,
+                    "signed": app_state.command_signer.is_some(),
+                    "estimated_recovery_time": "5 seconds"
+                }))
+            }).await;
+            match restart {
+                Ok(Some(Ok(response))) => Ok(Json(ApiResponse::success(response))),
+                Ok(Some(Err(e))) => {
+                    error!("Failed to send restart command to device {}: {}", device_id, e);
+                    Err(e.into())
+                }
+                Ok(None) => Err(ApiError::conflict("Device restart already in progress")),
+                Err(e) => {
+                    error!("Failed to lock device {} for restart: {}", device_id, e);
+                    Err(ApiError::service_unavailable("Device lock unavailable, please retry"))
+                }
+            }
         }
+        Ok(None) => Err(EchoError::DeviceNotFound(device_id.into()).into()),
         Err(e) => {
             error!("Failed to get device for restart: {}", e);
-            Json(ApiResponse::error("Failed to restart device".to_string()))
+            Err(e.into())
         }
     }
 }
 
+// 下发设备命令（音量、固件升级、提示音等），重启使用 /restart 接口
+pub async fn send_device_command(
+    Path(device_id): Path<DeviceId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+    Json(command): Json<DeviceCommand>,
+) -> ApiResult<serde_json::Value> {
+    match &command {
+        DeviceCommand::Reboot => return Err(ApiError::bad_request("Use POST /devices/:id/restart to restart a device")),
+        DeviceCommand::SetVolume { level } if !(0..=100).contains(level) => {
+            return Err(ApiError::bad_request("Volume level must be between 0 and 100"));
+        }
+        _ => {}
+    }
+
+    match app_state.database.get_device_by_id(&device_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(EchoError::DeviceNotFound(device_id.into()).into()),
+        Err(e) => {
+            error!("Failed to get device for command: {}", e);
+            return Err(e.into());
+        }
+    }
+
+    let kind = DeviceCommandKind::of(&command);
+    let issuer = authorize_command(&app_state, &claims, &headers, &device_id, kind).await?;
+    let message = command_message(&app_state, &device_id, command, Some(issuer)).map_err(|e| {
+        error!("Failed to sign command for device {}: {}", device_id, e);
+        ApiError::from(e)
+    })?;
+    if let Err(e) = app_state.database.enqueue_device_command(&message).await {
+        error!("Failed to queue {} command for device {}: {}", kind.as_str(), device_id, e);
+        return Err(e.into());
+    }
+    info!("Queued {} command for device {} from {}", kind.as_str(), device_id, claims.username);
+
+    Ok(Json(ApiResponse::success(json!({
+        "message": "Device command sent",
+        "device_id": device_id,
+        "command": kind.as_str(),
+        "signed": app_state.command_signer.is_some()
+    }))))
+}
+
+// 推送设备配置（音量、语言、时区等），按 command_authorization 中的 other 权限检查
+pub async fn push_device_config(
+    Path(device_id): Path<DeviceId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+    Json(config): Json<DeviceConfiguration>,
+) -> ApiResult<serde_json::Value> {
+    if config.volume.is_some_and(|volume| !(0..=100).contains(&volume)) {
+        return Err(ApiError::bad_request("Volume level must be between 0 and 100"));
+    }
+
+    match app_state.database.get_device_by_id(&device_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(EchoError::DeviceNotFound(device_id.into()).into()),
+        Err(e) => {
+            error!("Failed to get device for config push: {}", e);
+            return Err(e.into());
+        }
+    }
+
+    let issuer = authorize_command(&app_state, &claims, &headers, &device_id, DeviceCommandKind::Other).await?;
+    // 启用命令签名时配置与命令一样签名，Bridge 会拒绝未签名的配置
+    let message = match &app_state.command_signer {
+        Some(signer) => signer
+            .sign_as(device_id.as_str(), &config, Some(&issuer))
+            .map(|signed| MqttMessageBuilder::signed_command(device_id.to_string(), signed))
+            .map_err(|e| {
+                error!("Failed to sign config for device {}: {}", device_id, e);
+                ApiError::from(anyhow::Error::from(e))
+            })?,
+        None => MqttMessageBuilder::device_config(device_id.to_string(), config, claims.username.clone()),
+    };
+    if let Err(e) = app_state.database.enqueue_device_command(&message).await {
+        error!("Failed to queue config for device {}: {}", device_id, e);
+        return Err(e.into());
+    }
+    info!("Queued configuration for device {} from {}", device_id, claims.username);
+
+    Ok(Json(ApiResponse::success(json!({
+        "message": "Device configuration sent",
+        "device_id": device_id,
+        "signed": app_state.command_signer.is_some()
+    }))))
+}
+
 // 获取设备统计信息
 pub async fn get_device_stats(
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    http_cache::cached_json(&app_state.cache, http_cache::DEVICES_SCOPE, "stats", &headers, || {
+        load_device_stats(&app_state)
+    }).await
+}
+
+// 根据设备列表计算统计信息
+async fn load_device_stats(app_state: &AppState) -> Result<serde_json::Value, ApiError> {
     match app_state.database.get_all_devices().await {
-        Ok(devices) => {
+        Ok(mut devices) => {
+            merge_presence(app_state, &mut devices).await;
             let total = devices.len();
             let online = devices.iter().filter(|d| d.status == DeviceStatus::Online).count();
             let offline = devices.iter().filter(|d| d.status == DeviceStatus::Offline).count();
@@ -328,11 +696,24 @@ pub async fn get_device_stats(
                 }
             });
 
-            Json(ApiResponse::success(stats))
+            Ok(stats)
         }
         Err(e) => {
             error!("Failed to get devices for stats: {}", e);
-            Json(ApiResponse::error("Failed to get device statistics".to_string()))
+            Err(e.into())
+        }
+    }
+}
+
+// 获取当前连接到 Bridge 的设备（含正在进行的会话，用于显示设备是否正在对话）
+pub async fn get_live_devices(
+    State(app_state): State<AppState>,
+) -> ApiResult<Vec<BridgeOnlineDevice>> {
+    match app_state.bridge.list_online_devices().await {
+        Ok(devices) => Ok(Json(ApiResponse::success(devices))),
+        Err(e) => {
+            error!("Failed to list online devices from bridge: {}", e);
+            Err(ApiError::service_unavailable(format!("Bridge service unavailable: {}", e)))
         }
     }
 }
@@ -342,26 +723,9 @@ pub async fn get_device_stats(
 // 注册新设备
 pub async fn register_device(
     State(app_state): State<AppState>,
-    Json(payload): Json<DeviceRegistrationRequest>,
-) -> Result<Json<ApiResponse<DeviceRegistrationResponse>>, StatusCode> {
-    // 验证必填字段
-    if payload.name.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-
-    // 验证序列号和MAC地址是否提供（至少一个）
-    if payload.serial_number.is_none() && payload.mac_address.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-
-    // 验证MAC地址格式（如果提供）
-    if let Some(ref mac) = payload.mac_address {
-        // 支持带冒号或不带冒号的MAC地址格式
-        let clean_mac = mac.replace(":", "").replace("-", "");
-        if clean_mac.len() != 12 || !clean_mac.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    }
+    ValidatedJson(payload): ValidatedJson<DeviceRegistrationRequest>,
+) -> ApiResult<DeviceRegistrationResponse> {
+    // 名称、序列号、MAC地址格式已由 DeviceRegistrationRequest 的校验规则检查
 
     // 生成配对码和QR令牌
     let pairing_code = generate_pairing_code();
@@ -386,21 +750,21 @@ pub async fn register_device(
         }
         (None, None) => {
             // 这种情况已经在前面检查过了
-            return Err(StatusCode::BAD_REQUEST);
+            return Err(ApiError::bad_request("Either serial_number or mac_address is required"));
         }
     };
 
     // 检查序列号唯一性（如果提供）
     if let Some(ref sn) = payload.serial_number {
         if let Ok(true) = app_state.database.check_serial_number_exists(sn).await {
-            return Err(StatusCode::CONFLICT);
+            return Err(ApiError::conflict(format!("Serial number {} is already registered", sn)));
         }
     }
 
     // 检查MAC地址唯一性（如果提供）
     if let Some(ref mac) = payload.mac_address {
         if let Ok(true) = app_state.database.check_mac_address_exists(mac).await {
-            return Err(StatusCode::CONFLICT);
+            return Err(ApiError::conflict(format!("MAC address {} is already registered", mac)));
         }
     }
 
@@ -418,13 +782,15 @@ pub async fn register_device(
         is_online: false,
         owner: "user001".to_string(), // TODO: 从认证信息中获取
         echokit_server_url: payload.echokit_server_url.clone(),
+        serial_number: payload.serial_number.clone(),
+        mac_address: payload.mac_address.clone(),
+        version: 1,
+        current_session_id: None,
     };
 
     // 创建设备和注册令牌
     match app_state.database.create_device(
         &new_device,
-        payload.serial_number.as_deref(),
-        payload.mac_address.as_deref(),
         Some(&pairing_code),
         Some(&qr_token),
     ).await {
@@ -437,7 +803,7 @@ pub async fn register_device(
                 expires_at,
             ).await {
                 error!("Failed to create registration token: {}", e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(e.into());
             }
 
             // 生成二维码数据 (使用设备ID进行设备配对)
@@ -459,11 +825,12 @@ pub async fn register_device(
                 device_type: payload.device_type,
             };
 
+            http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
             Ok(Json(ApiResponse::success(registration_response)))
         }
         Err(e) => {
             error!("Failed to create device: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -480,12 +847,18 @@ pub async fn verify_device(
             success: false,
             message: "配对码不能为空".to_string(),
             device_config: None,
+            command_public_key: None,
         };
         return Json(ApiResponse::success(verification_response));
     }
 
     match app_state.database.verify_device_registration(&payload.pairing_code).await {
         Ok(Some(device_id)) => {
+            app_state.cache.invalidate_device(&device_id).await;
+            http_cache::invalidate(&app_state.cache, http_cache::DEVICES_SCOPE).await;
+            // 设备用此公钥验证之后收到的命令
+            let command_public_key = app_state.command_signer.as_ref().map(|signer| signer.public_key());
+
             // 获取设备信息
             match app_state.database.get_device_by_id(&device_id).await {
                 Ok(Some(device)) => {
@@ -498,6 +871,7 @@ pub async fn verify_device(
                             location: Some(device.location.clone()),
                             battery_level: Some(100),
                         }),
+                        command_public_key,
                     };
 
                     info!("Device registration verified successfully: {}", device_id);
@@ -505,7 +879,7 @@ pub async fn verify_device(
                 }
                 Ok(None) => {
                     let verification_response = DeviceVerificationResponse {
-                        device_id: device_id.clone(),
+                        device_id: device_id.to_string(),
                         success: true,
                         message: "设备注册成功，但无法获取设备信息".to_string(),
                         device_config: Some(DeviceConfig {
@@ -513,16 +887,18 @@ pub async fn verify_device(
                             location: None,
                             battery_level: Some(100),
                         }),
+                        command_public_key,
                     };
                     Json(ApiResponse::success(verification_response))
                 }
                 Err(e) => {
                     error!("Failed to get device info after verification: {}", e);
                     let verification_response = DeviceVerificationResponse {
-                        device_id: device_id.clone(),
+                        device_id: device_id.to_string(),
                         success: true,
                         message: "设备注册成功，但获取设备配置失败".to_string(),
                         device_config: None,
+                        command_public_key,
                     };
                     Json(ApiResponse::success(verification_response))
                 }
@@ -534,6 +910,7 @@ pub async fn verify_device(
                 success: false,
                 message: "配对码无效或已过期".to_string(),
                 device_config: None,
+                command_public_key: None,
             };
             Json(ApiResponse::success(verification_response))
         }
@@ -544,6 +921,7 @@ pub async fn verify_device(
                 success: false,
                 message: "验证设备注册时发生错误".to_string(),
                 device_config: None,
+                command_public_key: None,
             };
             Json(ApiResponse::success(verification_response))
         }
@@ -552,10 +930,10 @@ pub async fn verify_device(
 
 // 延长注册时间
 pub async fn extend_registration(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
     Json(payload): Json<RegistrationExtensionRequest>,
-) -> Json<ApiResponse<RegistrationExtensionResponse>> {
+) -> ApiResult<RegistrationExtensionResponse> {
     // 检查设备是否存在且处于待注册状态
     match app_state.database.get_device_by_id(&device_id).await {
         Ok(Some(device)) => {
@@ -594,7 +972,7 @@ pub async fn extend_registration(
                     message: format!("注册时间已延长{}分钟 (not fully implemented)", extension_duration),
                 };
 
-                Json(ApiResponse::success(extension_response))
+                Ok(Json(ApiResponse::success(extension_response)))
             } else {
                 let extension_response = RegistrationExtensionResponse {
                     success: false,
@@ -603,7 +981,7 @@ pub async fn extend_registration(
                     message: "设备状态不支持延长".to_string(),
                 };
 
-                Json(ApiResponse::success(extension_response))
+                Ok(Json(ApiResponse::success(extension_response)))
             }
         }
         Ok(None) => {
@@ -614,20 +992,20 @@ pub async fn extend_registration(
                 message: "设备不存在".to_string(),
             };
 
-            Json(ApiResponse::success(extension_response))
+            Ok(Json(ApiResponse::success(extension_response)))
         }
         Err(e) => {
             error!("Failed to get device for registration extension: {}", e);
-            Json(ApiResponse::error("Failed to extend registration".to_string()))
+            Err(e.into())
         }
     }
 }
 
 // 取消注册
 pub async fn cancel_registration(
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
+) -> ApiResult<serde_json::Value> {
     // 检查设备是否存在且处于待注册状态
     match app_state.database.get_device_by_id(&device_id).await {
         Ok(Some(device)) => {
@@ -658,17 +1036,15 @@ pub async fn cancel_registration(
                 // TODO: 发送WebSocket消息通知前端
                 // app_state.websocket_sender.send(WebSocketMessage::DeviceRegistrationExpired { ... }).await?;
 
-                Json(ApiResponse::success(response))
+                Ok(Json(ApiResponse::success(response)))
             } else {
-                Json(ApiResponse::error("设备状态不支持取消".to_string()))
+                Err(ApiError::conflict("设备状态不支持取消"))
             }
         }
-        Ok(None) => {
-            Json(ApiResponse::error("设备不存在".to_string()))
-        }
+        Ok(None) => Err(EchoError::DeviceNotFound(device_id.into()).into()),
         Err(e) => {
             error!("Failed to get device for registration cancellation: {}", e);
-            Json(ApiResponse::error("Failed to cancel registration".to_string()))
+            Err(e.into())
         }
     }
 }
@@ -676,7 +1052,7 @@ pub async fn cancel_registration(
 // 获取待注册设备列表
 pub async fn get_pending_registrations(
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<Vec<serde_json::Value>>> {
+) -> ApiResult<Vec<serde_json::Value>> {
     match app_state.database.get_all_devices().await {
         Ok(devices) => {
             let pending_devices: Vec<serde_json::Value> = devices
@@ -694,11 +1070,11 @@ pub async fn get_pending_registrations(
                 })
                 .collect();
 
-            Json(ApiResponse::success(pending_devices))
+            Ok(Json(ApiResponse::success(pending_devices)))
         }
         Err(e) => {
             error!("Failed to get devices for pending registrations: {}", e);
-            Json(ApiResponse::error("Failed to get pending registrations".to_string()))
+            Err(e.into())
         }
     }
 }
@@ -727,10 +1103,15 @@ pub fn device_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(get_devices).post(create_device))
         .route("/stats", get(get_device_stats))
+        .route("/live", get(get_live_devices))
         .route("/register", post(register_device))
         .route("/verify", post(verify_device))
         .route("/pending", get(get_pending_registrations))
         .route("/:id/restart", post(restart_device))
+        .route("/:id/commands", post(send_device_command))
+        .route("/:id/config", post(push_device_config))
+        .route("/:id/transfer", post(transfer_device))
+        .route("/:id/ws-ticket", post(issue_ws_ticket))
         .route("/:id/extend", post(extend_registration))
         .route("/:id/cancel", delete(cancel_registration))
         .route("/:id", get(get_device).put(update_device).delete(delete_device))
@@ -1,6 +1,5 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     response::Json,
     routing::{get, post, delete},
     Router,
@@ -9,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use tracing::{info, error};
 use crate::app_state::AppState;
+use crate::error::{ApiError, ApiResult};
 use echo_shared::ApiResponse;
 
 /// EchoKit Server 数据结构
@@ -31,7 +31,7 @@ pub struct AddServerRequest {
 /// 获取用户的 EchoKit Server 列表
 pub async fn get_servers(
     State(app_state): State<AppState>,
-) -> Result<Json<ApiResponse<Vec<EchoKitServer>>>, StatusCode> {
+) -> ApiResult<Vec<EchoKitServer>> {
     // TODO: 从认证中间件获取真实的 user_id
     let user_id = "user001"; // 临时使用固定值
 
@@ -50,7 +50,7 @@ pub async fn get_servers(
         "#,
         user_id
     )
-    .fetch_all(app_state.database.pool())
+    .fetch_all(app_state.database.pool()?)
     .await
     {
         Ok(servers) => {
@@ -59,7 +59,7 @@ pub async fn get_servers(
         }
         Err(e) => {
             error!("Failed to get EchoKit servers: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -68,13 +68,13 @@ pub async fn get_servers(
 pub async fn add_server(
     State(app_state): State<AppState>,
     Json(payload): Json<AddServerRequest>,
-) -> Result<Json<ApiResponse<EchoKitServer>>, StatusCode> {
+) -> ApiResult<EchoKitServer> {
     // TODO: 从认证中间件获取真实的 user_id
     let user_id = "user001"; // 临时使用固定值
 
     // 验证 URL 格式
     if payload.server_url.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(ApiError::bad_request("server_url is required"));
     }
 
     // 插入新服务器
@@ -93,7 +93,7 @@ pub async fn add_server(
         user_id,
         payload.server_url
     )
-    .fetch_one(app_state.database.pool())
+    .fetch_one(app_state.database.pool()?)
     .await
     {
         Ok(server) => {
@@ -105,11 +105,11 @@ pub async fn add_server(
             if let Some(db_err) = e.as_database_error() {
                 if db_err.constraint() == Some("unique_user_server_url") {
                     error!("Server URL already exists for user {}: {}", user_id, payload.server_url);
-                    return Err(StatusCode::CONFLICT);
+                    return Err(ApiError::conflict(format!("Server URL {} already exists", payload.server_url)));
                 }
             }
             error!("Failed to add EchoKit server: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
@@ -118,7 +118,7 @@ pub async fn add_server(
 pub async fn delete_server(
     Path(server_id): Path<i32>,
     State(app_state): State<AppState>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
+) -> ApiResult<serde_json::Value> {
     // TODO: 从认证中间件获取真实的 user_id
     let user_id = "user001"; // 临时使用固定值
 
@@ -131,7 +131,7 @@ pub async fn delete_server(
         server_id,
         user_id
     )
-    .execute(app_state.database.pool())
+    .execute(app_state.database.pool()?)
     .await
     {
         Ok(result) => {
@@ -143,12 +143,12 @@ pub async fn delete_server(
                 }))))
             } else {
                 error!("Server {} not found or not owned by user {}", server_id, user_id);
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(format!("EchoKit server {} not found", server_id)))
             }
         }
         Err(e) => {
             error!("Failed to delete EchoKit server: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e.into())
         }
     }
 }
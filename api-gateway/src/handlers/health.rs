@@ -1,20 +1,28 @@
-use axum::{extract::State, response::Json, routing::get, Router};
-use echo_shared::ApiResponse;
+use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
+use echo_shared::{ApiResponse, ComponentHealth, PoolStats, ReadinessReport};
 use serde_json::json;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::app_state::AppState;
+use crate::build_info::build_info;
+
+/// 就绪检查中单个依赖的超时时间
+const READINESS_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
+    let build = build_info();
 
     let health_data = json!({
         "status": "healthy",
         "timestamp": timestamp,
         "service": "echo-api-gateway",
-        "version": "0.1.0"
+        "version": build.version,
+        "build": build,
+        "uptime_seconds": echo_shared::process_uptime_seconds()
     });
 
     Json(ApiResponse::success(health_data))
@@ -34,6 +42,7 @@ pub async fn detailed_health_check(
         "timestamp": timestamp,
         "service": "echo-api-gateway",
         "version": system_info.status.version,
+        "build": build_info(),
         "environment": system_info.status.environment,
         "start_time": system_info.status.start_time,
         "uptime_seconds": system_info.runtime.uptime_seconds,
@@ -66,9 +75,55 @@ pub async fn detailed_health_check(
     Json(ApiResponse::success(health_data))
 }
 
+/// 存活探针：进程在运行即返回 200
+pub async fn liveness_check() -> Json<serde_json::Value> {
+    Json(json!({
+        "status": "alive",
+        "service": "echo-api-gateway",
+        "timestamp": chrono::Utc::now()
+    }))
+}
+
+/// 就绪探针：逐项检查依赖，关键依赖不可用时返回 503
+pub async fn readiness_check(
+    State(app_state): State<AppState>,
+) -> (StatusCode, Json<ReadinessReport>) {
+    // 连接池用尽时获取连接会超时，附带连接池状态便于区分数据库故障和连接池用尽（SQLite 存储只显示后端名称）
+    let pool = app_state.database.pool().ok().map(PoolStats::snapshot);
+    let pool_detail = pool.map_or_else(|| app_state.database.storage().backend().to_string(), |pool| pool.to_string());
+    let (database, redis) = tokio::join!(
+        tokio::time::timeout(READINESS_CHECK_TIMEOUT, app_state.database.health_check()),
+        tokio::time::timeout(READINESS_CHECK_TIMEOUT, app_state.cache.health_check()),
+    );
+    let mut components = vec![
+        database.map_or_else(|_| ComponentHealth::down("database", format!("timed out ({})", pool_detail)), |result| match result {
+            Ok(()) => ComponentHealth::up("database").with_detail(pool_detail.clone()),
+            Err(e) => ComponentHealth::down("database", format!("{} ({})", e, pool_detail)),
+        }),
+        redis.map_or_else(|_| ComponentHealth::down("redis", "timed out"), |result| {
+            ComponentHealth::from_result("redis", result)
+        }),
+    ];
+
+    // 未启用实时事件时不连接 MQTT
+    if app_state.config.features.websocket_enabled {
+        components.push(if app_state.mqtt_connected.load(Ordering::Relaxed) {
+            ComponentHealth::up("mqtt")
+        } else {
+            ComponentHealth::down("mqtt", "not connected to broker")
+        });
+    }
+
+    let report = ReadinessReport::new("echo-api-gateway", components, &app_state.critical_dependencies);
+    let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
+
 pub fn health_routes() -> axum::Router<AppState> {
     axum::Router::new()
         .route("/", get(health_check))
         .route("/basic", get(health_check))
         .route("/detailed", get(detailed_health_check))
+        .route("/live", get(liveness_check))
+        .route("/ready", get(readiness_check))
 }
\ No newline at end of file
@@ -4,4 +4,8 @@ pub mod devices;
 pub mod sessions;
 pub mod health;
 pub mod users;
-pub mod echokit_servers;
\ No newline at end of file
+pub mod echokit_servers;
+pub mod admin;
+pub mod webhooks;
+pub mod notifications;
+pub mod announcements;
\ No newline at end of file
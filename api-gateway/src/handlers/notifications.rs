@@ -0,0 +1,204 @@
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{get, patch, post},
+    Router,
+};
+use echo_shared::{ApiResponse, DeliveryChannel, OutboundMessage, UserRole};
+use serde::Deserialize;
+use tracing::{error, info, warn};
+use crate::app_state::AppState;
+use crate::database::{NewNotificationDestination, NotificationDestination, NotificationDestinationUpdate};
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::auth::Claims;
+use crate::notifications;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDestinationRequest {
+    /// email / slack / telegram
+    pub channel: String,
+    /// 邮件地址、Slack incoming webhook 地址或 Telegram chat ID
+    pub target: String,
+    /// 订阅的通知类型：daily_digest / device_offline / session_failed
+    pub notifications: Vec<String>,
+    /// 接收所有设备的通知（仅管理员），默认只接收自己拥有的设备的通知
+    #[serde(default)]
+    pub all_devices: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateDestinationRequest {
+    pub target: Option<String>,
+    pub notifications: Option<Vec<String>>,
+    pub enabled: Option<bool>,
+}
+
+/// 检查通道是否可用，返回对应的投递通道
+fn check_channel(app_state: &AppState, channel: &str) -> Result<DeliveryChannel, ApiError> {
+    let delivery_channel = notifications::delivery_channel(channel)
+        .ok_or_else(|| ApiError::bad_request("channel must be one of email, slack, telegram"))?;
+    let config = &app_state.config.notifications;
+    match delivery_channel {
+        DeliveryChannel::Email if config.smtp.host.is_empty() => {
+            Err(ApiError::bad_request("Email notifications are not configured"))
+        }
+        DeliveryChannel::Telegram if config.telegram.bot_token.is_empty() => {
+            Err(ApiError::bad_request("Telegram notifications are not configured"))
+        }
+        _ => Ok(delivery_channel),
+    }
+}
+
+/// 查询当前用户的通知接收地址
+pub async fn list_destinations(
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<Vec<NotificationDestination>> {
+    match app_state.database.list_notification_destinations(&claims.sub).await {
+        Ok(destinations) => Ok(Json(ApiResponse::success(destinations))),
+        Err(e) => {
+            error!("Failed to list notification destinations for user {}: {}", claims.sub, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 添加通知接收地址
+pub async fn create_destination(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Json(request): Json<CreateDestinationRequest>,
+) -> ApiResult<NotificationDestination> {
+    let config = &app_state.config.notifications;
+    if !config.enabled {
+        return Err(ApiError::bad_request("Notifications are not enabled"));
+    }
+    if request.all_devices && claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can receive notifications for all devices"));
+    }
+    let channel = request.channel.trim().to_lowercase();
+    let delivery_channel = check_channel(&app_state, &channel)?;
+    let target = request.target.trim().to_string();
+    notifications::validate_target(delivery_channel, &target).map_err(ApiError::bad_request)?;
+    let kinds = notifications::validate_notifications(&request.notifications).map_err(ApiError::bad_request)?;
+
+    let count = app_state.database.count_notification_destinations(&claims.sub).await?;
+    if count >= config.max_destinations_per_user as u64 {
+        return Err(ApiError::conflict(format!(
+            "At most {} notification destinations can be added",
+            config.max_destinations_per_user
+        )));
+    }
+
+    let new_destination = NewNotificationDestination {
+        channel,
+        target,
+        notifications: kinds,
+        all_devices: request.all_devices,
+    };
+    let destination = app_state
+        .database
+        .create_notification_destination(&claims.sub, &new_destination)
+        .await
+        .map_err(|e| {
+            error!("Failed to create notification destination for user {}: {}", claims.sub, e);
+            ApiError::from(e)
+        })?;
+    info!(
+        "Notification destination {} ({}) added by {} for {:?}",
+        destination.id, destination.channel, claims.username, destination.notifications
+    );
+
+    Ok(Json(ApiResponse::success(destination)))
+}
+
+/// 修改接收地址、订阅的通知类型或启用状态
+pub async fn update_destination(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(destination_id): Path<i64>,
+    Json(request): Json<UpdateDestinationRequest>,
+) -> ApiResult<NotificationDestination> {
+    let target = request.target.map(|target| target.trim().to_string());
+    if let Some(target) = &target {
+        let existing = app_state
+            .database
+            .get_notification_destination(destination_id, &claims.sub)
+            .await?
+            .ok_or_else(|| ApiError::not_found("Notification destination not found"))?;
+        let delivery_channel = check_channel(&app_state, &existing.channel)?;
+        notifications::validate_target(delivery_channel, target).map_err(ApiError::bad_request)?;
+    }
+    let kinds = request
+        .notifications
+        .map(|kinds| notifications::validate_notifications(&kinds))
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+
+    let update = NotificationDestinationUpdate { target, notifications: kinds, enabled: request.enabled };
+    let destination = app_state
+        .database
+        .update_notification_destination(destination_id, &claims.sub, &update)
+        .await
+        .map_err(|e| {
+            error!("Failed to update notification destination {}: {}", destination_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Notification destination not found"))?;
+
+    Ok(Json(ApiResponse::success(destination)))
+}
+
+/// 删除通知接收地址
+pub async fn delete_destination(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(destination_id): Path<i64>,
+) -> ApiResult<NotificationDestination> {
+    let destination = app_state
+        .database
+        .delete_notification_destination(destination_id, &claims.sub)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete notification destination {}: {}", destination_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Notification destination not found"))?;
+    info!("Notification destination {} deleted by {}", destination.id, claims.username);
+
+    Ok(Json(ApiResponse::success(destination)))
+}
+
+/// 向接收地址发送一条测试消息，用于确认配置正确
+pub async fn test_destination(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(destination_id): Path<i64>,
+) -> ApiResult<()> {
+    let destination = app_state
+        .database
+        .get_notification_destination(destination_id, &claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Notification destination not found"))?;
+    let delivery_channel = check_channel(&app_state, &destination.channel)?;
+
+    let message = OutboundMessage::new(
+        delivery_channel,
+        destination.target.as_str(),
+        "Echo 测试通知",
+        format!("这是一条来自 Echo 的测试通知，{} 添加的接收地址配置正确。", claims.username),
+    );
+    app_state.message_sender.send(&message).await.map_err(|e| {
+        warn!("Test notification to destination {} failed: {}", destination_id, e);
+        ApiError::bad_request(format!("Failed to send test notification: {}", e))
+    })?;
+
+    Ok(Json(ApiResponse::success(())))
+}
+
+pub fn notification_routes() -> Router<AppState> {
+    Router::new()
+        .route("/destinations", get(list_destinations).post(create_destination))
+        .route("/destinations/:id", patch(update_destination).delete(delete_destination))
+        .route("/destinations/:id/test", post(test_destination))
+}
@@ -1,23 +1,32 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{Path, Query, RawQuery, State},
+    http::HeaderMap,
+    response::{sse::{Event, KeepAlive, Sse}, Json, Response},
     routing::{get, post, delete},
     Router,
 };
 use echo_shared::{
-    ApiResponse, Session, PaginationParams, PaginatedResponse,
+    ApiResponse, EchoError, Session, UserRole, PaginationParams, PaginatedResponse, PageCursor, BridgeSessionState,
     generate_session_id, now_utc, EchoKitConfig, EchoKitSession, EchoKitSessionStatus
 };
 use echo_shared::types::SessionStatus;
+use echo_shared::{job_kinds, DeviceId, Job, SessionId, SessionStage, WebSocketMessage};
+use futures::stream::{self, Stream, StreamExt};
+use std::convert::Infallible;
+use tokio::sync::broadcast;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 use serde_json::json;
-use std::collections::HashMap;
-use std::sync::Arc;
 use tracing::{info, warn, error};
 use crate::app_state::AppState;
+use crate::audit::{self, AuditAction, AuditActor};
+use crate::error::{ApiError, ApiResult};
+use crate::validation::ValidatedJson;
+use crate::http_cache;
+use crate::cookie_auth;
+use crate::handlers::auth::{authenticate, bearer_token, ensure_token_current, Claims};
+use crate::database::{DailySessionStats, DeviceSessionStats, SessionFilter, SessionSearchHit, SessionStatusCounts};
 use chrono::{DateTime, Utc};
-use sqlx::Row;
 
 #[derive(Debug, Deserialize)]
 pub struct SessionQueryParams {
@@ -27,30 +36,75 @@ pub struct SessionQueryParams {
     pub status: Option<SessionStatus>,
     pub start_date: Option<String>,
     pub end_date: Option<String>,
+    /// 游标分页：首页传空字符串，之后传上一页返回的 next_cursor
+    pub cursor: Option<String>,
 }
 
+/// 会话搜索查询参数
 #[derive(Debug, Deserialize)]
+pub struct SessionSearchParams {
+    pub q: Option<String>,
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+}
+
+/// 会话统计分析查询参数
+#[derive(Debug, Deserialize)]
+pub struct SessionStatsParams {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub device_id: Option<String>,
+    pub device_limit: Option<u32>,
+}
+
+/// 会话统计分析结果
+#[derive(Debug, Serialize)]
+pub struct SessionAnalytics {
+    #[serde(flatten)]
+    pub counts: SessionStatusCounts,
+    pub average_duration_seconds: i64,
+    pub today_sessions: u64,
+    /// 失败率：(failed + timeout) / 已结束会话数
+    pub failure_rate: f64,
+    pub asr_word_count: u64,
+    pub average_words_per_session: f64,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub daily: Vec<DailySessionStats>,
+    pub busiest_devices: Vec<DeviceSessionStats>,
+    /// 数据来源：sessions（实时聚合）或 rollups（汇总表）
+    pub source: &'static str,
+}
+
+/// 未指定日期范围时按天统计的默认天数
+const DEFAULT_DAILY_STATS_DAYS: i64 = 30;
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateSessionRequest {
+    #[validate(length(min = 1, max = 255, message = "device_id 长度为1-255个字符"))]
     pub device_id: String,
+    #[validate(length(min = 1, max = 255, message = "user_id 长度为1-255个字符"))]
     pub user_id: String,
     pub config: Option<EchoKitConfig>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct EndSessionRequest {
-    pub reason: Option<String>,
+/// 会话完整详情（数据库记录 + Bridge 实时状态）
+#[derive(Debug, Serialize)]
+pub struct SessionDetail {
+    pub session: Session,
+    pub live: Option<BridgeSessionState>,
+    pub bridge_reachable: bool,
 }
 
-// 用于存储活跃的 EchoKit 会话（实时会话管理）
-static mut ECHOKIT_SESSIONS: Option<HashMap<String, EchoKitSession>> = None;
+/// 会话事件流参数（EventSource 无法设置 header，通过 query 传递 token）
+#[derive(Debug, Deserialize)]
+pub struct SessionEventsParams {
+    pub token: Option<String>,
+}
 
-fn get_echokit_sessions() -> &'static mut HashMap<String, EchoKitSession> {
-    unsafe {
-        if ECHOKIT_SESSIONS.is_none() {
-            ECHOKIT_SESSIONS = Some(HashMap::new());
-        }
-        ECHOKIT_SESSIONS.as_mut().unwrap()
-    }
+#[derive(Debug, Deserialize)]
+pub struct EndSessionRequest {
+    pub reason: Option<String>,
 }
 
 // 创建新的 EchoKit 会话
@@ -91,19 +145,6 @@ async fn call_bridge_service_start_session(
     Ok(generate_session_id())
 }
 
-async fn call_bridge_service_end_session(
-    session_id: String,
-    reason: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // TODO: 实现实际的 Bridge 服务调用
-    info!("Simulating Bridge service call - end session: {} (reason: {})", session_id, reason);
-
-    // 模拟网络延迟
-    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-
-    Ok(())
-}
-
 // ========================================================================
 // 历史会话查询（从数据库读取）
 // ========================================================================
@@ -112,191 +153,267 @@ async fn call_bridge_service_end_session(
 pub async fn get_sessions(
     State(app_state): State<AppState>,
     Query(params): Query<SessionQueryParams>,
-) -> Json<ApiResponse<PaginatedResponse<Session>>> {
+) -> ApiResult<PaginatedResponse<Session>> {
     let pagination = PaginationParams {
-        page: params.page.unwrap_or(1),
-        page_size: params.page_size.unwrap_or(20),
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(20).clamp(1, 100),
     };
 
-    // 构建 SQL 查询条件（使用 SQL 转义避免注入）
-    let mut conditions = Vec::new();
-
-    if let Some(device_id) = &params.device_id {
-        // 使用 PostgreSQL 的 quote_literal 风格转义
-        let escaped = device_id.replace("'", "''");
-        conditions.push(format!("device_id = '{}'", escaped));
-    }
-
-    if let Some(status) = &params.status {
-        let status_str = match status {
-            SessionStatus::Active => "active",
-            SessionStatus::Completed => "completed",
-            SessionStatus::Failed => "failed",
-            SessionStatus::Timeout => "timeout",
+    let filter = build_session_filter(&params).map_err(ApiError::bad_request)?;
+
+    if let Some(cursor) = params.cursor.as_deref() {
+        let cursor = parse_cursor_param(cursor).map_err(ApiError::bad_request)?;
+
+        return match app_state
+            .database
+            .list_sessions_after(&filter, cursor.as_ref(), pagination.page_size)
+            .await
+        {
+            Ok((sessions, total, next_cursor)) => Ok(Json(ApiResponse::success(PaginatedResponse::with_cursor(
+                sessions,
+                total,
+                pagination.page_size,
+                next_cursor.map(|c| c.encode()),
+            )))),
+            Err(e) => {
+                error!("Failed to query sessions: {}", e);
+                Err(e.into())
+            }
         };
-        conditions.push(format!("status = '{}'", status_str));
     }
 
-    if let Some(start_date) = &params.start_date {
-        // 解析 ISO 8601 格式的日期
-        if let Ok(start_time) = start_date.parse::<DateTime<Utc>>() {
-            conditions.push(format!("start_time >= '{}'", start_time.to_rfc3339()));
+    match app_state.database.list_sessions(&filter, &pagination).await {
+        Ok((sessions, total)) => {
+            let response = PaginatedResponse::new(sessions, total, pagination);
+            Ok(Json(ApiResponse::success(response)))
+        }
+        Err(e) => {
+            error!("Failed to query sessions: {}", e);
+            Err(e.into())
         }
     }
+}
 
-    if let Some(end_date) = &params.end_date {
-        if let Ok(end_time) = end_date.parse::<DateTime<Utc>>() {
-            conditions.push(format!("start_time <= '{}'", end_time.to_rfc3339()));
-        }
+/// 全文搜索会话转录文本和 AI 回复（结果按相关度排序，附带高亮片段）
+pub async fn search_sessions(
+    State(app_state): State<AppState>,
+    Query(params): Query<SessionSearchParams>,
+) -> ApiResult<PaginatedResponse<SessionSearchHit>> {
+    let query = params.q.as_deref().map(str::trim).unwrap_or_default();
+    if query.is_empty() {
+        return Err(ApiError::bad_request("Search query 'q' is required"));
+    }
+    // 加密后的转写和回复无法在数据库中检索，继续搜索只会匹配到密文
+    if app_state.database.encryption_key_id().is_some() {
+        return Err(EchoError::NotImplemented("Session search is unavailable while encryption at rest is enabled".to_string()).into());
     }
 
-    let where_clause = if conditions.is_empty() {
-        String::new()
-    } else {
-        format!("WHERE {}", conditions.join(" AND "))
+    let pagination = PaginationParams {
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(20).clamp(1, 100),
     };
 
-    // 查询总数
-    let count_query = format!("SELECT COUNT(*) as count FROM sessions {}", where_clause);
+    match app_state.database.search_sessions(query, &pagination).await {
+        Ok((hits, total)) => Ok(Json(ApiResponse::success(PaginatedResponse::new(hits, total, pagination)))),
+        Err(e) => {
+            error!("Failed to search sessions for '{}': {}", query, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 按状态统计会话数量（支持与列表相同的过滤条件）
+pub async fn get_session_counts(
+    State(app_state): State<AppState>,
+    Query(params): Query<SessionQueryParams>,
+) -> ApiResult<SessionStatusCounts> {
+    let filter = build_session_filter(&params).map_err(ApiError::bad_request)?;
 
-    let total: i64 = match sqlx::query(&count_query)
-        .fetch_one(app_state.database.pool())
-        .await
-    {
-        Ok(row) => row.get("count"),
+    match app_state.database.count_sessions_by_status(&filter).await {
+        Ok(counts) => Ok(Json(ApiResponse::success(counts))),
         Err(e) => {
             error!("Failed to count sessions: {}", e);
-            return Json(ApiResponse::error(format!("Database query failed: {}", e)));
+            Err(e.into())
         }
-    };
+    }
+}
 
-    // 查询分页数据
-    let offset = echo_shared::calculate_offset(pagination.page, pagination.page_size);
-    let limit = pagination.page_size;
-
-    let data_query = format!(
-        "SELECT id, device_id, user_id, start_time, end_time, duration, transcription, response, status
-         FROM sessions
-         {}
-         ORDER BY start_time DESC
-         LIMIT {} OFFSET {}",
-        where_clause, limit, offset
-    );
+/// 获取单个会话详情
+pub async fn get_session(
+    Path(session_id): Path<SessionId>,
+    State(app_state): State<AppState>,
+) -> ApiResult<Session> {
+    let session = load_session(&app_state, &session_id).await?;
+    Ok(Json(ApiResponse::success(session)))
+}
 
-    let sessions: Vec<Session> = match sqlx::query(&data_query)
-        .fetch_all(app_state.database.pool())
-        .await
-    {
-        Ok(rows) => {
-            rows.into_iter().map(|row| Session {
-                id: row.get("id"),
-                device_id: row.get("device_id"),
-                user_id: row.get("user_id"),
-                start_time: row.get("start_time"),
-                end_time: row.get("end_time"),
-                duration: row.get("duration"),
-                transcription: row.get("transcription"),
-                response: row.get("response"),
-                status: match row.get::<&str, _>("status") {
-                    "active" => SessionStatus::Active,
-                    "completed" => SessionStatus::Completed,
-                    "failed" => SessionStatus::Failed,
-                    "timeout" => SessionStatus::Timeout,
-                    _ => SessionStatus::Failed,
-                },
-            }).collect()
-        }
+/// 获取会话完整详情（合并数据库记录与 Bridge 实时状态）
+pub async fn get_session_full(
+    Path(session_id): Path<SessionId>,
+    State(app_state): State<AppState>,
+) -> ApiResult<SessionDetail> {
+    let session = load_session(&app_state, &session_id).await?;
+
+    // Bridge 不可用时仍返回数据库记录
+    let (live, bridge_reachable) = match app_state.bridge.get_session_state(&session_id).await {
+        Ok(live) => (live, true),
         Err(e) => {
-            error!("Failed to query sessions: {}", e);
-            return Json(ApiResponse::error(format!("Database query failed: {}", e)));
+            warn!("Failed to fetch live state for session {} from bridge: {}", session_id, e);
+            (None, false)
         }
     };
 
-    let response = PaginatedResponse::new(sessions, total as u64, pagination);
-    Json(ApiResponse::success(response))
+    Ok(Json(ApiResponse::success(SessionDetail {
+        session,
+        live,
+        bridge_reachable,
+    })))
 }
 
-/// 获取单个会话详情
-pub async fn get_session(
-    Path(session_id): Path<String>,
+/// 获取 Bridge 上正在进行的会话实时状态
+pub async fn get_live_sessions(
     State(app_state): State<AppState>,
-) -> Result<Json<ApiResponse<Session>>, StatusCode> {
-    let query = "SELECT id, device_id, user_id, start_time, end_time, duration, transcription, response, status
-                 FROM sessions
-                 WHERE id = $1";
-
-    match sqlx::query(query)
-        .bind(&session_id)
-        .fetch_one(app_state.database.pool())
-        .await
-    {
-        Ok(row) => {
-            let session = Session {
-                id: row.get("id"),
-                device_id: row.get("device_id"),
-                user_id: row.get("user_id"),
-                start_time: row.get("start_time"),
-                end_time: row.get("end_time"),
-                duration: row.get("duration"),
-                transcription: row.get("transcription"),
-                response: row.get("response"),
-                status: match row.get::<&str, _>("status") {
-                    "active" => SessionStatus::Active,
-                    "completed" => SessionStatus::Completed,
-                    "failed" => SessionStatus::Failed,
-                    "timeout" => SessionStatus::Timeout,
-                    _ => SessionStatus::Failed,
-                },
-            };
-            Ok(Json(ApiResponse::success(session)))
-        }
+) -> ApiResult<Vec<BridgeSessionState>> {
+    match app_state.bridge.list_active_sessions().await {
+        Ok(sessions) => Ok(Json(ApiResponse::success(sessions))),
         Err(e) => {
-            error!("Failed to find session {}: {}", session_id, e);
-            Err(StatusCode::NOT_FOUND)
+            error!("Failed to list active sessions from bridge: {}", e);
+            Err(ApiError::service_unavailable(format!("Bridge service unavailable: {}", e)))
         }
     }
 }
 
-/// 获取会话统计信息（从数据库聚合查询）
+/// 将查询参数转换为数据库过滤条件（日期需为 ISO 8601 格式）
+fn build_session_filter(params: &SessionQueryParams) -> Result<SessionFilter, String> {
+    Ok(SessionFilter {
+        device_id: params.device_id.clone(),
+        status: params.status.clone(),
+        start_date: parse_date_param("start_date", &params.start_date)?,
+        end_date: parse_date_param("end_date", &params.end_date)?,
+    })
+}
+
+/// 获取会话统计分析（支持日期范围；未指定范围时总数为全部会话，按天统计默认最近 30 天）
 pub async fn get_session_stats(
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
-    let query = r#"
-        SELECT
-            COUNT(*) as total,
-            COUNT(*) FILTER (WHERE status = 'active') as active,
-            COUNT(*) FILTER (WHERE status = 'completed') as completed,
-            COUNT(*) FILTER (WHERE status = 'failed') as failed,
-            COUNT(*) FILTER (WHERE status = 'timeout') as timeout,
-            CAST(AVG(duration) FILTER (WHERE status = 'completed') AS DOUBLE PRECISION) as avg_duration,
-            COUNT(*) FILTER (WHERE DATE(start_time) = CURRENT_DATE) as today_sessions
-        FROM sessions
-    "#;
-
-    match sqlx::query(query)
-        .fetch_one(app_state.database.pool())
-        .await
-    {
-        Ok(row) => {
-            let avg_duration_f64: Option<f64> = row.get("avg_duration");
-            let avg_duration = avg_duration_f64.map(|d| d.round() as i32).unwrap_or(0);
-
-            let stats = json!({
-                "total": row.get::<i64, _>("total"),
-                "active": row.get::<i64, _>("active"),
-                "completed": row.get::<i64, _>("completed"),
-                "failed": row.get::<i64, _>("failed"),
-                "timeout": row.get::<i64, _>("timeout"),
-                "average_duration_seconds": avg_duration,
-                "today_sessions": row.get::<i64, _>("today_sessions")
-            });
-
-            Json(ApiResponse::success(stats))
-        }
+    Query(params): Query<SessionStatsParams>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let variant = format!("stats:{}", query.unwrap_or_default());
+    http_cache::cached_json(&app_state.cache, http_cache::SESSIONS_SCOPE, &variant, &headers, || {
+        load_session_stats(&app_state, params)
+    }).await
+}
+
+/// 从数据库（或汇总表）计算会话统计分析
+async fn load_session_stats(
+    app_state: &AppState,
+    params: SessionStatsParams,
+) -> Result<SessionAnalytics, ApiError> {
+    let start_date = parse_date_param("start_date", &params.start_date).map_err(ApiError::bad_request)?;
+    let end_date = parse_date_param("end_date", &params.end_date).map_err(ApiError::bad_request)?;
+    let filter = SessionFilter {
+        device_id: params.device_id.clone(),
+        status: None,
+        start_date,
+        end_date,
+    };
+    let device_limit = params.device_limit.unwrap_or(10).clamp(1, 100);
+
+    let database = &app_state.database;
+    let (counts, summary) = match tokio::try_join!(
+        database.count_sessions_by_status(&filter),
+        database.session_summary(&filter),
+    ) {
+        Ok(result) => result,
         Err(e) => {
             error!("Failed to get session stats: {}", e);
-            Json(ApiResponse::error(format!("Database query failed: {}", e)))
+            return Err(e.into());
         }
+    };
+
+    let mut daily_filter = filter.clone();
+    if daily_filter.start_date.is_none() && daily_filter.end_date.is_none() {
+        daily_filter.start_date = Some(Utc::now() - chrono::Duration::days(DEFAULT_DAILY_STATS_DAYS));
+    }
+
+    // 启用汇总表时优先读取汇总数据，汇总表不可用时回退到原始会话表
+    let mut source = "sessions";
+    let mut breakdown = None;
+    if app_state.config.features.session_rollups {
+        match tokio::try_join!(
+            database.daily_session_stats(&daily_filter, true),
+            database.busiest_devices(&filter, device_limit, true),
+        ) {
+            Ok(result) => {
+                source = "rollups";
+                breakdown = Some(result);
+            }
+            Err(e) => warn!("Session rollups unavailable, falling back to raw sessions: {}", e),
+        }
+    }
+
+    let (daily, busiest_devices) = match breakdown {
+        Some(result) => result,
+        None => match tokio::try_join!(
+            database.daily_session_stats(&daily_filter, false),
+            database.busiest_devices(&filter, device_limit, false),
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Failed to get session breakdown: {}", e);
+                return Err(e.into());
+            }
+        },
+    };
+
+    let finished = counts.completed + counts.failed + counts.timeout;
+    let failure_rate = if finished > 0 {
+        (counts.failed + counts.timeout) as f64 / finished as f64
+    } else {
+        0.0
+    };
+    let average_words_per_session = if counts.total > 0 {
+        summary.asr_word_count as f64 / counts.total as f64
+    } else {
+        0.0
+    };
+
+    Ok(SessionAnalytics {
+        counts,
+        average_duration_seconds: summary.average_duration_seconds.round() as i64,
+        today_sessions: summary.today_sessions,
+        failure_rate,
+        asr_word_count: summary.asr_word_count,
+        average_words_per_session,
+        start_date: filter.start_date,
+        end_date: filter.end_date,
+        daily,
+        busiest_devices,
+        source,
+    })
+}
+
+/// 解析游标参数，空字符串表示游标模式的第一页
+pub fn parse_cursor_param(cursor: &str) -> Result<Option<PageCursor>, String> {
+    if cursor.is_empty() {
+        return Ok(None);
+    }
+
+    PageCursor::decode(cursor)
+        .map(Some)
+        .ok_or_else(|| "Invalid cursor".to_string())
+}
+
+/// 解析 ISO 8601 日期参数
+fn parse_date_param(name: &str, value: &Option<String>) -> Result<Option<DateTime<Utc>>, String> {
+    match value {
+        Some(value) => value
+            .parse::<DateTime<Utc>>()
+            .map(Some)
+            .map_err(|_| format!("Invalid {}: expected ISO 8601 timestamp", name)),
+        None => Ok(None),
     }
 }
 
@@ -306,22 +423,18 @@ pub async fn get_session_stats(
 
 /// 创建新会话
 pub async fn create_session(
-    State(_app_state): State<AppState>,
-    Json(payload): Json<CreateSessionRequest>,
-) -> Result<Json<ApiResponse<EchoKitSession>>, (StatusCode, Json<ApiResponse<()>>)> {
+    State(app_state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateSessionRequest>,
+) -> ApiResult<EchoKitSession> {
     let config = payload.config.unwrap_or_default();
 
     // 检查设备是否已有活跃会话
-    {
-        let echokit_sessions = get_echokit_sessions();
-        for session in echokit_sessions.values() {
-            if session.device_id == payload.device_id &&
-               (session.status == EchoKitSessionStatus::Active ||
-                session.status == EchoKitSessionStatus::Processing ||
-                session.status == EchoKitSessionStatus::Responding) {
-                let response = ApiResponse::error("Device already has an active session".to_string());
-                return Err((StatusCode::CONFLICT, Json(response)));
-            }
+    match app_state.database.get_active_session_for_device(&DeviceId::from(payload.device_id.as_str())).await {
+        Ok(Some(_)) => return Err(ApiError::conflict("Device already has an active session")),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to check active sessions for device {}: {}", payload.device_id, e);
+            return Err(e.into());
         }
     }
 
@@ -332,31 +445,76 @@ pub async fn create_session(
         config.clone(),
     );
 
-    // 调用 Bridge 服务启动会话
-    match call_bridge_service_start_session(
+    // 占用会话配额，Redis 不可用时不限制
+    match app_state
+        .cache
+        .acquire_session_slot(&echokit_session.id, &echokit_session.device_id, Some(&echokit_session.user_id))
+        .await
+    {
+        Ok(Ok(())) => {}
+        Ok(Err(exceeded)) => {
+            warn!("Rejecting session for device {}: {}", echokit_session.device_id, exceeded);
+            return Err(EchoError::RateLimited(exceeded.to_string()).into());
+        }
+        Err(e) => warn!("Failed to check session limits for device {}, allowing it: {}", echokit_session.device_id, e),
+    }
+
+    let language = echo_shared::normalize_language_tag(&config.asr_language);
+
+    // 调用 Bridge 服务启动会话（错误不能跨 await 持有，先转为字符串）
+    let started = call_bridge_service_start_session(
         payload.device_id.clone(),
         payload.user_id.clone(),
         config,
-    ).await {
-        Ok(_) => {
-            // Bridge 服务调用成功，更新会话状态
-            echokit_session.status = EchoKitSessionStatus::Active;
+    ).await.map_err(|e| e.to_string());
+    if let Err(e) = started {
+        error!("Failed to create EchoKit session: {}", e);
+        cancel_session_slot(&app_state, &echokit_session).await;
+        return Err(ApiError::service_unavailable(format!("Failed to create session: {}", e)));
+    }
 
-            // 存储会话
-            let echokit_sessions = get_echokit_sessions();
-            echokit_sessions.insert(echokit_session.id.clone(), echokit_session.clone());
+    // Bridge 服务调用成功，更新会话状态
+    echokit_session.status = EchoKitSessionStatus::Active;
 
-            info!("Created new EchoKit session {} for device {}",
-                  echokit_session.id, echokit_session.device_id);
+    // 持久化会话
+    let session = Session {
+        id: echokit_session.id.clone(),
+        device_id: echokit_session.device_id.clone(),
+        user_id: Some(echokit_session.user_id.clone()),
+        start_time: echokit_session.start_time,
+        end_time: None,
+        duration: None,
+        transcription: None,
+        response: None,
+        status: SessionStatus::Active,
+        summary: None,
+        tags: Vec::new(),
+        language,
+    };
 
-            let response = ApiResponse::success(echokit_session);
-            Ok(Json(response))
-        }
-        Err(e) => {
-            error!("Failed to create EchoKit session: {}", e);
-            let response = ApiResponse::error(format!("Failed to create session: {}", e));
-            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
-        }
+    if let Err(e) = app_state.database.create_session(&session).await {
+        error!("Failed to persist session {}: {}", session.id, e);
+        cancel_session_slot(&app_state, &echokit_session).await;
+        return Err(e.into());
+    }
+
+    http_cache::invalidate(&app_state.cache, http_cache::SESSIONS_SCOPE).await;
+
+    info!("Created new EchoKit session {} for device {}",
+          echokit_session.id, echokit_session.device_id);
+
+    let response = ApiResponse::success(echokit_session);
+    Ok(Json(response))
+}
+
+/// 会话创建失败，退还已占用的会话配额；失败时由租约到期兜底
+async fn cancel_session_slot(app_state: &AppState, session: &EchoKitSession) {
+    if let Err(e) = app_state
+        .cache
+        .cancel_session_slot(&session.id, &session.device_id, Some(&session.user_id))
+        .await
+    {
+        warn!("Failed to release session slot for session {}: {}", session.id, e);
     }
 }
 
@@ -365,91 +523,249 @@ pub async fn update_session(
     Path(_session_id): Path<String>,
     State(_app_state): State<AppState>,
     Json(_payload): Json<serde_json::Value>,
-) -> Result<Json<ApiResponse<Session>>, StatusCode> {
+) -> ApiResult<Session> {
     warn!("update_session is deprecated - sessions are now managed directly by Bridge service");
-    Err(StatusCode::NOT_IMPLEMENTED)
+    Err(ApiError::not_implemented("Sessions are managed by the bridge service"))
 }
 
 /// 结束会话 (EchoKit 版本)
 pub async fn end_session(
-    Path(session_id): Path<String>,
-    State(_app_state): State<AppState>,
+    Path(session_id): Path<SessionId>,
+    State(app_state): State<AppState>,
     Json(payload): Json<EndSessionRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> ApiResult<()> {
     let reason = payload.reason.unwrap_or_else(|| "user_request".to_string());
 
-    // 查找 EchoKit 会话
-    let session_info = {
-        let echokit_sessions = get_echokit_sessions();
-        echokit_sessions.get(&session_id).cloned()
-    };
+    // 查找会话
+    let session = load_session(&app_state, &session_id).await?;
 
-    if let Some(mut session) = session_info {
-        // 调用 Bridge 服务结束会话
-        match call_bridge_service_end_session(session_id.clone(), reason.clone()).await {
-            Ok(_) => {
-                // 更新会话状态
-                session.status = EchoKitSessionStatus::Completed;
-                session.end_time = Some(now_utc());
+    if session.status != SessionStatus::Active {
+        return Err(ApiError::conflict("Session is not active"));
+    }
 
-                // 更新存储
-                let echokit_sessions = get_echokit_sessions();
-                echokit_sessions.insert(session_id.clone(), session.clone());
+    force_end_session(&app_state, &session_id, &session.device_id, &reason).await?;
 
-                info!("Ended EchoKit session {} (reason: {})", session_id, reason);
+    info!("Ended EchoKit session {} (reason: {})", session_id, reason);
+    Ok(Json(ApiResponse::success(())))
+}
 
-                let response = ApiResponse::success(());
-                Ok(Json(response))
-            }
-            Err(e) => {
-                error!("Failed to end EchoKit session {}: {}", session_id, e);
-                let response = ApiResponse::error(format!("Failed to end session: {}", e));
-                Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
-            }
+/// 强制终止活跃会话（仅管理员或设备所有者）
+///
+/// 通知 Bridge 关闭 EchoKit 会话并通知设备，会话记录保留用于审计
+pub async fn terminate_session(
+    Path(session_id): Path<SessionId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
+    let session = load_session(&app_state, &session_id).await?;
+
+    ensure_session_access(&app_state, &claims, &session).await?;
+
+    if session.status != SessionStatus::Active {
+        return Err(ApiError::conflict("Session is not active"));
+    }
+
+    let reason = format!("terminated_by_{}", claims.username);
+    force_end_session(&app_state, &session_id, &session.device_id, &reason).await?;
+
+    let after = load_session(&app_state, &session_id).await.ok();
+    let actor = AuditActor::new(&claims, &headers);
+    audit::record(&app_state.database, &actor, AuditAction::SessionTerminate, &session_id, Some(&session), after.as_ref())
+        .await;
+
+    info!("Session {} on device {} terminated by {}", session_id, session.device_id, claims.username);
+    Ok(Json(ApiResponse::success(json!({
+        "message": "Session terminated",
+        "session_id": session_id
+    }))))
+}
+
+/// 非管理员只能访问自己设备上的会话
+async fn ensure_session_access(
+    app_state: &AppState,
+    claims: &Claims,
+    session: &Session,
+) -> Result<(), ApiError> {
+    if claims.role == UserRole::Admin {
+        return Ok(());
+    }
+
+    let device_ids = match app_state.database.get_device_ids_for_user(&claims.user_id()).await {
+        Ok(device_ids) => device_ids,
+        Err(e) => {
+            error!("Failed to load devices for user {}: {}", claims.sub, e);
+            return Err(e.into());
         }
+    };
+
+    if device_ids.contains(&session.device_id) {
+        Ok(())
     } else {
-        let response = ApiResponse::error("Session not found".to_string());
-        Err((StatusCode::NOT_FOUND, Json(response)))
+        Err(ApiError::forbidden("Not allowed to access this session"))
     }
 }
 
-/// 删除会话（不建议使用，保留数据用于审计）
-pub async fn delete_session(
-    Path(session_id): Path<String>,
+/// 读取会话记录，不存在时返回 404
+async fn load_session(app_state: &AppState, session_id: &SessionId) -> Result<Session, ApiError> {
+    match app_state.database.get_session_by_id(session_id).await {
+        Ok(Some(session)) => Ok(session),
+        Ok(None) => Err(EchoError::SessionNotFound(session_id.to_string()).into()),
+        Err(e) => {
+            error!("Failed to find session {}: {}", session_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 单个会话的实时事件流（SSE）
+///
+/// 首先推送会话快照，然后转发该会话的进度、转写和回复事件，会话结束后关闭
+pub async fn session_events(
+    Path(session_id): Path<SessionId>,
     State(app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
-    let query = "DELETE FROM sessions WHERE id = $1";
+    Query(params): Query<SessionEventsParams>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // 浏览器的 EventSource 无法设置请求头，可使用 Cookie 会话（只读请求，不需要 CSRF 令牌）
+    let session_cookie = cookie_auth::session_token(&headers);
+    let token = params.token.as_deref().or_else(|| bearer_token(&headers)).or(session_cookie.as_deref());
+    let claims = authenticate(token)?;
+    ensure_token_current(&app_state, &claims).await?;
+
+    // 先订阅再读取快照，避免丢失两者之间的事件
+    let events = app_state.events.subscribe();
+
+    let session = load_session(&app_state, &session_id).await?;
+
+    ensure_session_access(&app_state, &claims, &session).await?;
+
+    let snapshot = Event::default()
+        .event("snapshot")
+        .json_data(&session)
+        .unwrap_or_else(|_| Event::default().event("snapshot"));
+    let finished = session.status != SessionStatus::Active;
+
+    info!("SSE subscriber {} attached to session {}", claims.username, session_id);
+
+    // 登记长连接，服务关闭时结束事件流
+    let connection = app_state.shutdown.track_connection();
+    let shutdown = app_state.shutdown.clone();
+
+    let updates = stream::unfold(
+        (events, session_id, finished, connection),
+        |(mut events, session_id, finished, connection)| async move {
+            if finished {
+                return None;
+            }
 
-    match sqlx::query(query)
-        .bind(&session_id)
-        .execute(app_state.database.pool())
-        .await
-    {
-        Ok(result) => {
-            let rows_affected = result.rows_affected();
-            if rows_affected > 0 {
-                let response = json!({
-                    "message": "Session deleted successfully",
-                    "session_id": session_id
-                });
-                Json(ApiResponse::success(response))
-            } else {
-                Json(ApiResponse::error("Session not found".to_string()))
+            loop {
+                match events.recv().await {
+                    Ok(message) => {
+                        let Some((name, terminal)) = session_event_kind(&message, &session_id) else {
+                            continue;
+                        };
+                        let event = match Event::default().event(name).json_data(&message) {
+                            Ok(event) => event,
+                            Err(e) => {
+                                warn!("Failed to serialize session event: {}", e);
+                                continue;
+                            }
+                        };
+                        return Some((Ok(event), (events, session_id, terminal, connection)));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("SSE subscriber for session {} lagged, skipped {} events", session_id, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
             }
+        },
+    );
+
+    let stream = stream::once(futures::future::ready(Ok(snapshot)))
+        .chain(updates)
+        .take_until(async move { shutdown.wait().await });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// 返回属于该会话的事件名称，以及该事件是否表示会话结束
+fn session_event_kind(message: &WebSocketMessage, session_id: &str) -> Option<(&'static str, bool)> {
+    match message {
+        WebSocketMessage::SessionProgress { session_id: id, stage, .. } if id == session_id => {
+            Some(("progress", *stage == SessionStage::Completed))
+        }
+        WebSocketMessage::EchoKitTranscription { session_id: id, .. } if id == session_id => {
+            Some(("transcription", false))
         }
+        WebSocketMessage::EchoKitResponse { session_id: id, .. } if id == session_id => {
+            Some(("response", false))
+        }
+        WebSocketMessage::EchoKitError { session_id: id, .. } if id == session_id => {
+            Some(("error", false))
+        }
+        WebSocketMessage::EchoKitSessionEnd { session_id: id, .. } if id == session_id => {
+            Some(("end", true))
+        }
+        _ => None,
+    }
+}
+
+/// 通过 Bridge 内部 API 结束会话并更新数据库状态
+async fn force_end_session(
+    app_state: &AppState,
+    session_id: &SessionId,
+    device_id: &str,
+    reason: &str,
+) -> Result<(), ApiError> {
+    match app_state.bridge.end_session(session_id, reason).await {
+        Ok(true) => {}
+        // Bridge 已不再跟踪该会话（如 Bridge 重启），仅更新数据库记录
+        Ok(false) => warn!("Bridge is not tracking active session {}, marking it ended", session_id),
         Err(e) => {
-            error!("Failed to delete session {}: {}", session_id, e);
-            Json(ApiResponse::error(format!("Database error: {}", e)))
+            error!("Failed to end session {} via bridge: {}", session_id, e);
+            return Err(ApiError::service_unavailable(format!("Bridge service unavailable: {}", e)));
         }
     }
+
+    // Bridge 已写入最终状态，这里确保数据库记录不会停留在 active
+    if let Err(e) = app_state
+        .database
+        .update_session_status(session_id, SessionStatus::Completed)
+        .await
+    {
+        error!("Failed to update session {}: {}", session_id, e);
+        return Err(e.into());
+    }
+
+    http_cache::invalidate(&app_state.cache, http_cache::SESSIONS_SCOPE).await;
+
+    // Bridge 结束会话时已释放，Bridge 未跟踪该会话时在这里释放
+    if let Err(e) = app_state.cache.release_session_slot(session_id.as_str(), device_id).await {
+        warn!("Failed to release session slot for session {}: {}", session_id, e);
+    }
+
+    // 通过任务队列尽快生成摘要，写入失败时由定时摘要任务补上
+    if app_state.config.features.session_summaries {
+        let job = Job { kind: job_kinds::SESSION_SUMMARY.to_string(), payload: json!({ "session_id": session_id }) };
+        if let Err(e) = app_state.cache.enqueue_job(&job).await {
+            warn!("Failed to enqueue summary job for session {}: {}", session_id, e);
+        }
+    }
+    Ok(())
 }
 
 pub fn session_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(get_sessions).post(create_session))
         .route("/stats", get(get_session_stats))
+        .route("/counts", get(get_session_counts))
+        .route("/search", get(search_sessions))
+        .route("/live", get(get_live_sessions))
         .route("/:id", get(get_session))
+        .route("/:id/full", get(get_session_full))
+        .route("/:id/events", get(session_events))
         .route("/:id", post(update_session))
         .route("/:id/end", post(end_session))
-        .route("/:id", delete(delete_session))
+        .route("/:id", delete(terminate_session))
 }
@@ -1,16 +1,21 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
-use echo_shared::{ApiResponse, User, UserRole, PaginationParams, PaginatedResponse, generate_uuid};
+use echo_shared::{ApiResponse, EchoError, User, UserId, UserRole, PaginationParams, PaginatedResponse, generate_uuid};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use crate::app_state::AppState;
+use crate::audit::{self, AuditAction, AuditActor};
+use crate::error::{ApiError, ApiResult};
+use crate::database::DataDeletionRecord;
+use crate::handlers::auth::Claims;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use tracing::{error, info, warn};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
@@ -43,43 +48,36 @@ pub struct ChangePasswordRequest {
     pub new_password: String,
 }
 
-// 模拟用户数据存储
-static mut USERS: Option<HashMap<String, User>> = None;
-
-fn get_mock_users() -> &'static mut HashMap<String, User> {
-    unsafe {
-        if USERS.is_none() {
-            let mut users = HashMap::new();
-
-            // 创建默认管理员用户
-            let admin_password_hash = hash("admin123", DEFAULT_COST).unwrap_or_else(|_| "hashed".to_string());
-            let user_password_hash = hash("user123", DEFAULT_COST).unwrap_or_else(|_| "hashed".to_string());
-
-            users.insert("admin-001".to_string(), User {
-                id: "admin-001".to_string(),
-                username: "admin".to_string(),
-                email: "admin@echo.system".to_string(),
-                password_hash: admin_password_hash,
-                role: UserRole::Admin,
-            });
-
-            users.insert("user-001".to_string(), User {
-                id: "user-001".to_string(),
-                username: "user".to_string(),
-                email: "user@echo.system".to_string(),
-                password_hash: user_password_hash,
-                role: UserRole::User,
-            });
-
-            USERS = Some(users);
-        }
-        USERS.as_mut().unwrap()
-    }
+/// 内存用户存储的初始数据（默认管理员和普通用户），由 AppState 创建时调用
+pub fn default_users() -> HashMap<String, User> {
+    let mut users = HashMap::new();
+
+    // 创建默认管理员用户
+    let admin_password_hash = hash("admin123", DEFAULT_COST).unwrap_or_else(|_| "hashed".to_string());
+    let user_password_hash = hash("user123", DEFAULT_COST).unwrap_or_else(|_| "hashed".to_string());
+
+    users.insert("admin-001".to_string(), User {
+        id: "admin-001".to_string(),
+        username: "admin".to_string(),
+        email: "admin@echo.system".to_string(),
+        password_hash: admin_password_hash,
+        role: UserRole::Admin,
+    });
+
+    users.insert("user-001".to_string(), User {
+        id: "user-001".to_string(),
+        username: "user".to_string(),
+        email: "user@echo.system".to_string(),
+        password_hash: user_password_hash,
+        role: UserRole::User,
+    });
+
+    users
 }
 
 // 获取用户列表
 pub async fn get_users(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
     Query(params): Query<UserQueryParams>,
 ) -> Json<ApiResponse<PaginatedResponse<User>>> {
     let pagination = PaginationParams {
@@ -87,8 +85,7 @@ pub async fn get_users(
         page_size: params.page_size.unwrap_or(20),
     };
 
-    let users = get_mock_users();
-    let mut user_list: Vec<User> = users.values().cloned().collect();
+    let mut user_list: Vec<User> = app_state.users.read().await.values().cloned().collect();
 
     // 应用过滤条件
     if let Some(role) = params.role {
@@ -131,50 +128,44 @@ pub async fn get_users(
 // 获取单个用户详情
 pub async fn get_user(
     Path(user_id): Path<String>,
-    State(_app_state): State<AppState>,
-) -> Result<Json<ApiResponse<User>>, StatusCode> {
-    let users = get_mock_users();
+    State(app_state): State<AppState>,
+) -> ApiResult<User> {
+    let user = app_state.users.read().await.get(&user_id).cloned();
 
-    if let Some(mut user) = users.get(&user_id).cloned() {
+    if let Some(mut user) = user {
         // 隐藏密码哈希
         user.password_hash = "***".to_string();
         Ok(Json(ApiResponse::success(user)))
     } else {
-        Err(StatusCode::NOT_FOUND)
+        Err(ApiError::not_found("User not found"))
     }
 }
 
 // 创建新用户
 pub async fn create_user(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
     Json(payload): Json<CreateUserRequest>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> ApiResult<User> {
     // 验证输入
     if payload.username.is_empty() || payload.email.is_empty() || payload.password.is_empty() {
-        let response = ApiResponse::error("Username, email, and password are required".to_string());
-        return Err((StatusCode::BAD_REQUEST, Json(response)));
+        return Err(ApiError::bad_request("Username, email, and password are required"));
     }
 
+    // 密码加密（耗时较长，在获取写锁之前完成）
+    let password_hash = hash(&payload.password, DEFAULT_COST)
+        .map_err(EchoError::from)?;
+
     // 检查用户名是否已存在
-    let users = get_mock_users();
+    let mut users = app_state.users.write().await;
     if users.values().any(|u| u.username == payload.username) {
-        let response = ApiResponse::error("Username already exists".to_string());
-        return Err((StatusCode::CONFLICT, Json(response)));
+        return Err(ApiError::conflict("Username already exists"));
     }
 
     // 检查邮箱是否已存在
     if users.values().any(|u| u.email == payload.email) {
-        let response = ApiResponse::error("Email already exists".to_string());
-        return Err((StatusCode::CONFLICT, Json(response)));
+        return Err(ApiError::conflict("Email already exists"));
     }
 
-    // 密码加密
-    let password_hash = hash(&payload.password, DEFAULT_COST)
-        .map_err(|_| {
-            let response = ApiResponse::error("Failed to hash password".to_string());
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-        })?;
-
     // 创建新用户
     let new_user = User {
         id: generate_uuid(),
@@ -197,16 +188,23 @@ pub async fn create_user(
 // 更新用户信息
 pub async fn update_user(
     Path(user_id): Path<String>,
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+    headers: HeaderMap,
     Json(payload): Json<UpdateUserRequest>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let users = get_mock_users();
+) -> ApiResult<User> {
+    // 新密码的哈希耗时较长，在获取写锁之前完成
+    let new_password_hash = match payload.password.as_deref().filter(|password| !password.is_empty()) {
+        Some(password) => Some(hash(password, DEFAULT_COST).map_err(EchoError::from)?),
+        None => None,
+    };
+
+    let mut users = app_state.users.write().await;
 
     // 首先检查用户是否存在
     let existing_user = users.get(&user_id).cloned();
     if existing_user.is_none() {
-        let response = ApiResponse::error("User not found".to_string());
-        return Err((StatusCode::NOT_FOUND, Json(response)));
+        return Err(ApiError::not_found("User not found"));
     }
 
     let existing_user = existing_user.unwrap();
@@ -215,8 +213,7 @@ pub async fn update_user(
     if let Some(new_username) = &payload.username {
         if new_username != &existing_user.username {
             if users.values().any(|u| u.id != user_id && u.username == *new_username) {
-                let response = ApiResponse::error("Username already exists".to_string());
-                return Err((StatusCode::CONFLICT, Json(response)));
+                return Err(ApiError::conflict("Username already exists"));
             }
         }
     }
@@ -225,8 +222,7 @@ pub async fn update_user(
     if let Some(new_email) = &payload.email {
         if new_email != &existing_user.email {
             if users.values().any(|u| u.id != user_id && u.email == *new_email) {
-                let response = ApiResponse::error("Email already exists".to_string());
-                return Err((StatusCode::CONFLICT, Json(response)));
+                return Err(ApiError::conflict("Email already exists"));
             }
         }
     }
@@ -244,14 +240,8 @@ pub async fn update_user(
         }
 
         // 更新密码（如果提供）
-        if let Some(new_password) = &payload.password {
-            if !new_password.is_empty() {
-                user.password_hash = hash(new_password, DEFAULT_COST)
-                    .map_err(|_| {
-                        let response = ApiResponse::error("Failed to hash password".to_string());
-                        (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-                    })?;
-            }
+        if let Some(password_hash) = new_password_hash {
+            user.password_hash = password_hash;
         }
 
         // 更新角色
@@ -264,68 +254,77 @@ pub async fn update_user(
         // 返回不包含密码哈希的用户信息
         let mut safe_user = user.clone();
         safe_user.password_hash = "***".to_string();
+        drop(users);
+
+        if safe_user.role != existing_user.role {
+            let mut before = existing_user.clone();
+            before.password_hash = "***".to_string();
+            let actor = AuditActor::new(&claims, &headers);
+            audit::record(&app_state.database, &actor, AuditAction::UserRoleChange, &user_id, Some(&before), Some(&safe_user))
+                .await;
+        }
 
         Ok(Json(ApiResponse::success(safe_user)))
     } else {
-        let response = ApiResponse::error("User not found".to_string());
-        Err((StatusCode::NOT_FOUND, Json(response)))
+        Err(ApiError::not_found("User not found"))
     }
 }
 
 // 删除用户
 pub async fn delete_user(
     Path(user_id): Path<String>,
-    State(_app_state): State<AppState>,
-) -> Json<ApiResponse<serde_json::Value>> {
-    let users = get_mock_users();
+    State(app_state): State<AppState>,
+) -> ApiResult<serde_json::Value> {
+    let removed = app_state.users.write().await.remove(&user_id);
 
-    if users.remove(&user_id).is_some() {
+    if removed.is_some() {
         let response = json!({
             "message": "User deleted successfully",
             "user_id": user_id
         });
-        Json(ApiResponse::success(response))
+        Ok(Json(ApiResponse::success(response)))
     } else {
-        Json(ApiResponse::error("User not found".to_string()))
+        Err(ApiError::not_found("User not found"))
     }
 }
 
 // 修改密码
 pub async fn change_password(
     Path(user_id): Path<String>,
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
     Json(payload): Json<ChangePasswordRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let users = get_mock_users();
-
-    if let Some(user) = users.get_mut(&user_id) {
-        // 验证当前密码
-        if verify(&payload.current_password, &user.password_hash).unwrap_or(false) {
-            // 设置新密码
-            user.password_hash = hash(&payload.new_password, DEFAULT_COST)
-                .map_err(|_| {
-                    let response = ApiResponse::error("Failed to hash new password".to_string());
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-                })?;
+) -> ApiResult<()> {
+    // bcrypt 校验和哈希耗时较长，不持有锁
+    let current_hash = app_state.users.read().await.get(&user_id).map(|user| user.password_hash.clone());
+    let Some(current_hash) = current_hash else {
+        return Err(ApiError::not_found("User not found"));
+    };
 
+    // 验证当前密码
+    if !verify(&payload.current_password, &current_hash).unwrap_or(false) {
+        return Err(ApiError::unauthorized("Current password is incorrect"));
+    }
+    let new_hash = hash(&payload.new_password, DEFAULT_COST)
+        .map_err(EchoError::from)?;
+
+    // 设置新密码，校验期间密码已被修改时要求重试
+    let mut users = app_state.users.write().await;
+    match users.get_mut(&user_id) {
+        Some(user) if user.password_hash == current_hash => {
+            user.password_hash = new_hash;
             // Note: User struct doesn't have updated_at field in shared types
-
             Ok(Json(ApiResponse::success(())))
-        } else {
-            let response = ApiResponse::error("Current password is incorrect".to_string());
-            Err((StatusCode::UNAUTHORIZED, Json(response)))
         }
-    } else {
-        let response = ApiResponse::error("User not found".to_string());
-        Err((StatusCode::NOT_FOUND, Json(response)))
+        Some(_) => Err(ApiError::conflict("Password was changed concurrently, please retry")),
+        None => Err(ApiError::not_found("User not found")),
     }
 }
 
 // 获取用户统计信息
 pub async fn get_user_stats(
-    State(_app_state): State<AppState>,
+    State(app_state): State<AppState>,
 ) -> Json<ApiResponse<serde_json::Value>> {
-    let users = get_mock_users();
+    let users = app_state.users.read().await;
 
     let total = users.len();
     let admin = users.values().filter(|u| u.role == UserRole::Admin).count();
@@ -344,6 +343,129 @@ pub async fn get_user_stats(
     Json(ApiResponse::success(stats))
 }
 
+// 仅管理员或用户本人可以导出、删除其会话数据
+fn ensure_self_or_admin(claims: &Claims, user_id: &str) -> Result<(), ApiError> {
+    if claims.role == UserRole::Admin || claims.sub == user_id {
+        Ok(())
+    } else {
+        Err(ApiError::forbidden("Not allowed to access this user's data"))
+    }
+}
+
+// 导出用户的全部会话数据（JSONL 格式，每行一条会话记录）
+pub async fn export_user_sessions(
+    Path(user_id): Path<UserId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> Result<Response, ApiError> {
+    ensure_self_or_admin(&claims, &user_id)?;
+
+    let sessions = match app_state.database.export_user_sessions(&user_id).await {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            error!("Failed to export sessions for user {}: {}", user_id, e);
+            return Err(e.into());
+        }
+    };
+
+    let mut body = String::new();
+    for session in &sessions {
+        match serde_json::to_string(session) {
+            Ok(line) => {
+                body.push_str(&line);
+                body.push('\n');
+            }
+            Err(e) => {
+                error!("Failed to serialize session {}: {}", session.session.id, e);
+                return Err(EchoError::from(e).into());
+            }
+        }
+    }
+
+    info!("User {} exported {} sessions of user {}", claims.username, sessions.len(), user_id);
+
+    let filename = format!(
+        "sessions-{}-{}.jsonl",
+        user_id,
+        chrono::Utc::now().format("%Y%m%d")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// 永久删除用户的全部会话数据（包括录音文件），并生成删除审计记录
+pub async fn delete_user_sessions(
+    Path(user_id): Path<String>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<DataDeletionRecord> {
+    ensure_self_or_admin(&claims, &user_id)?;
+
+    let (record, audio_files) = match app_state
+        .database
+        .delete_user_sessions(&user_id, &claims.sub)
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to delete sessions for user {}: {}", user_id, e);
+            return Err(e.into());
+        }
+    };
+
+    let mut audio_files_deleted = 0;
+    for path in &audio_files {
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => audio_files_deleted += 1,
+            // 文件已不存在，视为已删除
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => audio_files_deleted += 1,
+            Err(e) => warn!("Failed to delete audio file {}: {}", path, e),
+        }
+    }
+
+    let record = match app_state
+        .database
+        .complete_data_deletion(&record.id, audio_files_deleted)
+        .await
+    {
+        Ok(record) => record,
+        Err(e) => {
+            // 会话已删除，审计记录仍保留（completed_at 为空表示音频清理结果未记录）
+            error!("Failed to complete data deletion record {}: {}", record.id, e);
+            record
+        }
+    };
+
+    info!(
+        "User {} deleted {} sessions and {}/{} audio files of user {} (record {})",
+        claims.username, record.session_count, audio_files_deleted, audio_files.len(), user_id, record.id
+    );
+    Ok(Json(ApiResponse::success(record)))
+}
+
+// 获取用户的数据删除审计记录
+pub async fn get_data_deletion_records(
+    Path(user_id): Path<UserId>,
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<Vec<DataDeletionRecord>> {
+    ensure_self_or_admin(&claims, &user_id)?;
+
+    match app_state.database.list_data_deletion_records(&user_id).await {
+        Ok(records) => Ok(Json(ApiResponse::success(records))),
+        Err(e) => {
+            error!("Failed to load data deletion records for user {}: {}", user_id, e);
+            Err(e.into())
+        }
+    }
+}
+
 pub fn user_routes() -> Router<AppState> {
     Router::new()
         .route("/", get(get_users).post(create_user))
@@ -352,4 +474,7 @@ pub fn user_routes() -> Router<AppState> {
         .route("/:id", post(update_user))
         .route("/:id", axum::routing::delete(delete_user))
         .route("/:id/change-password", post(change_password))
+        .route("/:id/sessions", axum::routing::delete(delete_user_sessions))
+        .route("/:id/sessions/export", get(export_user_sessions))
+        .route("/:id/data-deletions", get(get_data_deletion_records))
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    routing::get,
+    Router,
+};
+use echo_shared::{ApiResponse, PaginatedResponse, PaginationParams, UserRole};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use crate::app_state::AppState;
+use crate::database::{NewWebhook, Webhook, WebhookDelivery, WebhookUpdate};
+use crate::error::{ApiError, ApiResult};
+use crate::handlers::auth::Claims;
+use crate::webhooks;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// 订阅的事件：session.completed / device.offline / transcription.final
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    /// 接收所有设备的事件（仅管理员），默认只接收自己拥有的设备的事件
+    #[serde(default)]
+    pub all_devices: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+/// 创建 Webhook 的响应，签名密钥只在此时返回
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQueryParams {
+    pub page: Option<u32>,
+    pub page_size: Option<u32>,
+    /// pending / delivered / failed
+    pub status: Option<String>,
+}
+
+/// 查询当前用户的 Webhook
+pub async fn list_webhooks(
+    State(app_state): State<AppState>,
+    claims: Claims,
+) -> ApiResult<Vec<Webhook>> {
+    match app_state.database.list_webhooks(&claims.sub).await {
+        Ok(webhooks) => Ok(Json(ApiResponse::success(webhooks))),
+        Err(e) => {
+            error!("Failed to list webhooks for user {}: {}", claims.sub, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 注册 Webhook，返回签名密钥
+pub async fn create_webhook(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Json(request): Json<CreateWebhookRequest>,
+) -> ApiResult<CreatedWebhook> {
+    let config = &app_state.config.webhooks;
+    if !config.enabled {
+        return Err(ApiError::bad_request("Webhooks are not enabled"));
+    }
+    if request.all_devices && claims.role != UserRole::Admin {
+        return Err(ApiError::forbidden("Only administrators can subscribe to events from all devices"));
+    }
+    let url = request.url.trim().to_string();
+    webhooks::validate_url(&url, config.allow_http).map_err(ApiError::bad_request)?;
+    webhooks::DestinationPolicy::new(config).check_url(&url).await.map_err(ApiError::bad_request)?;
+    let events = webhooks::validate_events(&request.events).map_err(ApiError::bad_request)?;
+
+    let count = app_state.database.count_webhooks(&claims.sub).await?;
+    if count >= config.max_webhooks_per_user as u64 {
+        return Err(ApiError::conflict(format!("At most {} webhooks can be registered", config.max_webhooks_per_user)));
+    }
+
+    let secret = webhooks::generate_secret();
+    let new_webhook = NewWebhook { url, events, description: request.description, all_devices: request.all_devices };
+    let webhook = app_state.database.create_webhook(&claims.sub, &new_webhook, &secret).await.map_err(|e| {
+        error!("Failed to create webhook for user {}: {}", claims.sub, e);
+        ApiError::from(e)
+    })?;
+    info!("Webhook {} registered by {} for {:?}", webhook.id, claims.username, webhook.events);
+
+    Ok(Json(ApiResponse::success(CreatedWebhook { webhook, secret })))
+}
+
+pub async fn get_webhook(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(webhook_id): Path<i64>,
+) -> ApiResult<Webhook> {
+    let webhook = app_state
+        .database
+        .get_webhook(webhook_id, &claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Webhook not found"))?;
+    Ok(Json(ApiResponse::success(webhook)))
+}
+
+/// 修改 Webhook 的回调地址、事件、描述或启用状态
+pub async fn update_webhook(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(webhook_id): Path<i64>,
+    Json(request): Json<UpdateWebhookRequest>,
+) -> ApiResult<Webhook> {
+    let url = request.url.map(|url| url.trim().to_string());
+    if let Some(url) = &url {
+        webhooks::validate_url(url, app_state.config.webhooks.allow_http).map_err(ApiError::bad_request)?;
+        webhooks::DestinationPolicy::new(&app_state.config.webhooks)
+            .check_url(url)
+            .await
+            .map_err(ApiError::bad_request)?;
+    }
+    let events = request
+        .events
+        .map(|events| webhooks::validate_events(&events))
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+
+    let update = WebhookUpdate { url, events, description: request.description, enabled: request.enabled };
+    let webhook = app_state
+        .database
+        .update_webhook(webhook_id, &claims.sub, &update)
+        .await
+        .map_err(|e| {
+            error!("Failed to update webhook {}: {}", webhook_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Webhook not found"))?;
+
+    Ok(Json(ApiResponse::success(webhook)))
+}
+
+/// 删除 Webhook 及其投递记录
+pub async fn delete_webhook(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(webhook_id): Path<i64>,
+) -> ApiResult<Webhook> {
+    let webhook = app_state
+        .database
+        .delete_webhook(webhook_id, &claims.sub)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete webhook {}: {}", webhook_id, e);
+            ApiError::from(e)
+        })?
+        .ok_or_else(|| ApiError::not_found("Webhook not found"))?;
+    info!("Webhook {} deleted by {}", webhook.id, claims.username);
+
+    Ok(Json(ApiResponse::success(webhook)))
+}
+
+/// 查询 Webhook 的投递记录（最新的在前）
+pub async fn list_deliveries(
+    State(app_state): State<AppState>,
+    claims: Claims,
+    Path(webhook_id): Path<i64>,
+    Query(params): Query<DeliveryQueryParams>,
+) -> ApiResult<PaginatedResponse<WebhookDelivery>> {
+    if let Some(status) = &params.status {
+        if !["pending", "delivered", "failed"].contains(&status.as_str()) {
+            return Err(ApiError::bad_request("status must be one of pending, delivered, failed"));
+        }
+    }
+    if app_state.database.get_webhook(webhook_id, &claims.sub).await?.is_none() {
+        return Err(ApiError::not_found("Webhook not found"));
+    }
+
+    let pagination = PaginationParams {
+        page: params.page.unwrap_or(1).max(1),
+        page_size: params.page_size.unwrap_or(50).clamp(1, 200),
+    };
+
+    match app_state.database.list_webhook_deliveries(webhook_id, params.status.as_deref(), &pagination).await {
+        Ok((deliveries, total)) => Ok(Json(ApiResponse::success(PaginatedResponse::new(deliveries, total, pagination)))),
+        Err(e) => {
+            error!("Failed to query deliveries for webhook {}: {}", webhook_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_webhooks).post(create_webhook))
+        .route("/:id", get(get_webhook).patch(update_webhook).delete(delete_webhook))
+        .route("/:id/deliveries", get(list_deliveries))
+}
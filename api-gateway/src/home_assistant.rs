@@ -0,0 +1,598 @@
+// Home Assistant 集成：通过 MQTT discovery 把设备发布为 Home Assistant 实体（在线状态、音量、播报），
+// 并把 Home Assistant 的命令经授权检查和命令签名后写入 outbox 下发给设备
+use std::collections::HashMap;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use echo_shared::{
+    CommandIssuer, Device, DeviceCommand, DeviceCommandKind, DeviceId, DeviceStatus, HomeAssistantConfig, MqttConfig,
+    MqttPayload, TopicFilter, UserId,
+};
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Outgoing, Packet, Publish, QoS};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use crate::app_state::AppState;
+use crate::handlers::devices::command_message;
+
+/// 断线后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 关闭时等待 DISCONNECT 报文发出的最长时间
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 不是领导者时重新检查领导权的间隔
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 播报文本的最大字符数
+const MAX_ANNOUNCEMENT_CHARS: usize = 500;
+
+/// 集成的在线状态消息
+const PAYLOAD_ONLINE: &str = "online";
+const PAYLOAD_OFFLINE: &str = "offline";
+
+/// 每个设备发布的实体（组件, 对象 ID）
+const ENTITIES: [(&str, &str); 3] = [("binary_sensor", "presence"), ("number", "volume"), ("notify", "announce")];
+
+/// Home Assistant 下发的命令
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CommandTopic {
+    Volume,
+    Announce,
+}
+
+/// 集成使用的 MQTT 主题
+#[derive(Debug, Clone)]
+struct Topics {
+    discovery_prefix: String,
+    base: String,
+}
+
+impl Topics {
+    fn new(config: &HomeAssistantConfig) -> Self {
+        Self {
+            discovery_prefix: config.discovery_prefix.clone(),
+            base: config.base_topic.clone(),
+        }
+    }
+
+    /// 集成的在线状态（遗嘱消息），所有实体以它作为 availability
+    fn availability(&self) -> String {
+        format!("{}/status", self.base)
+    }
+
+    /// Home Assistant 上线时发布 "online" 的主题，收到后需重新发布 discovery 配置
+    fn home_assistant_status(&self) -> String {
+        format!("{}/status", self.discovery_prefix)
+    }
+
+    fn discovery(&self, component: &str, node: &str, object: &str) -> String {
+        format!("{}/{}/{}/{}/config", self.discovery_prefix, component, node, object)
+    }
+
+    fn state(&self, node: &str) -> String {
+        format!("{}/{}/state", self.base, node)
+    }
+
+    fn volume_command(&self, node: &str) -> String {
+        format!("{}/{}/volume/set", self.base, node)
+    }
+
+    fn announce_command(&self, node: &str) -> String {
+        format!("{}/{}/announce", self.base, node)
+    }
+
+    fn command_filters(&self) -> [String; 2] {
+        [self.volume_command("+"), self.announce_command("+")]
+    }
+
+    /// 解析命令主题，返回 (节点 ID, 命令)
+    fn parse_command<'a>(&self, topic: &'a str) -> Option<(&'a str, CommandTopic)> {
+        let rest = topic.strip_prefix(self.base.as_str())?.strip_prefix('/')?;
+        let (node, command) = rest.split_once('/')?;
+        let command = match command {
+            "volume/set" => CommandTopic::Volume,
+            "announce" => CommandTopic::Announce,
+            _ => return None,
+        };
+        (!node.is_empty()).then_some((node, command))
+    }
+}
+
+/// Home Assistant 的节点 ID 只允许字母、数字、下划线和连字符
+fn node_id(device_id: &str) -> String {
+    device_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// 已发布到 Home Assistant 的设备
+#[derive(Debug, Clone)]
+struct PublishedDevice {
+    device_id: String,
+    online: bool,
+    volume: i32,
+}
+
+impl PublishedDevice {
+    fn state(&self) -> Value {
+        json!({
+            "presence": if self.online { "ON" } else { "OFF" },
+            "volume": self.volume,
+        })
+    }
+}
+
+/// 设备的 discovery 配置（主题, 负载），未启用命令时不发布音量调节和播报实体
+fn discovery_configs(topics: &Topics, device: &Device, commands_enabled: bool) -> Vec<(String, Value)> {
+    let node = node_id(&device.id);
+    let mut info = json!({
+        "identifiers": [format!("etch_{}", node)],
+        "name": device.name,
+        "manufacturer": "Echo",
+        "model": device.device_type.to_string(),
+        "sw_version": device.firmware_version,
+    });
+    if !device.location.is_empty() {
+        info["suggested_area"] = json!(device.location);
+    }
+
+    let entity = |object: &str, name: &str| {
+        json!({
+            "name": name,
+            "unique_id": format!("etch_{}_{}", node, object),
+            "availability_topic": topics.availability(),
+            "device": info,
+        })
+    };
+
+    let mut presence = entity("presence", "Presence");
+    presence["state_topic"] = json!(topics.state(&node));
+    presence["value_template"] = json!("{{ value_json.presence }}");
+    presence["device_class"] = json!("connectivity");
+    presence["payload_on"] = json!("ON");
+    presence["payload_off"] = json!("OFF");
+
+    let mut volume = entity("volume", "Volume");
+    volume["state_topic"] = json!(topics.state(&node));
+    volume["value_template"] = json!("{{ value_json.volume }}");
+    volume["min"] = json!(0);
+    volume["max"] = json!(100);
+    volume["step"] = json!(1);
+    volume["mode"] = json!("slider");
+    volume["icon"] = json!("mdi:volume-high");
+    if commands_enabled {
+        volume["command_topic"] = json!(topics.volume_command(&node));
+    } else {
+        // 没有 command_topic 的 number 实体在 Home Assistant 中无法创建，改为只读传感器
+        volume["unit_of_measurement"] = json!("%");
+    }
+
+    let mut announce = entity("announce", "Announce");
+    announce["command_topic"] = json!(topics.announce_command(&node));
+
+    let mut configs = vec![(topics.discovery("binary_sensor", &node, "presence"), presence)];
+    if commands_enabled {
+        configs.push((topics.discovery("number", &node, "volume"), volume));
+        configs.push((topics.discovery("notify", &node, "announce"), announce));
+    } else {
+        configs.push((topics.discovery("sensor", &node, "volume"), volume));
+    }
+    configs
+}
+
+/// 解析 Home Assistant 的命令负载
+fn parse_command(command: CommandTopic, payload: &[u8]) -> Result<DeviceCommand> {
+    let payload = std::str::from_utf8(payload).map_err(|_| anyhow!("payload is not valid UTF-8"))?.trim();
+    match command {
+        CommandTopic::Volume => {
+            // Home Assistant 的 number 实体可能发送 "40" 或 "40.0"
+            let level = payload.parse::<f64>().map_err(|_| anyhow!("invalid volume {:?}", payload))?.round();
+            if !(0.0..=100.0).contains(&level) {
+                return Err(anyhow!("volume {} is out of range 0-100", level));
+            }
+            Ok(DeviceCommand::SetVolume { level: level as i32 })
+        }
+        CommandTopic::Announce => {
+            if payload.is_empty() {
+                return Err(anyhow!("announcement text is empty"));
+            }
+            if payload.chars().count() > MAX_ANNOUNCEMENT_CHARS {
+                return Err(anyhow!("announcement text exceeds {} characters", MAX_ANNOUNCEMENT_CHARS));
+            }
+            Ok(DeviceCommand::announce(payload))
+        }
+    }
+}
+
+/// 事件循环任务转交给集成的 broker 事件
+enum BrokerEvent {
+    Connected,
+    Disconnected,
+    Message(Publish),
+}
+
+/// 一次领导任期结束的原因
+enum Exit {
+    Shutdown,
+    LostLeadership,
+}
+
+/// 启动 Home Assistant 集成任务
+///
+/// 多副本部署时只有领导者副本连接 broker、发布实体并处理命令，领导权变化时由其他副本接管
+pub fn spawn_home_assistant_integration(app_state: AppState, mqtt: &MqttConfig, config: HomeAssistantConfig) {
+    let client_id = match &mqtt.client_id {
+        Some(client_id) => format!("{}-home-assistant", client_id),
+        None => format!("api-gateway-home-assistant-{}", uuid::Uuid::new_v4()),
+    };
+    let topics = Topics::new(&config);
+    let mut options = MqttOptions::new(client_id, mqtt.broker.clone(), mqtt.port);
+    options.set_keep_alive(Duration::from_secs(mqtt.keep_alive_secs));
+    options.set_clean_session(true);
+    if let (Some(username), Some(password)) = (&mqtt.username, &mqtt.password) {
+        options.set_credentials(username.clone(), password.clone());
+    }
+    // 领导者副本异常退出时实体在 Home Assistant 中显示为不可用
+    options.set_last_will(LastWill::new(topics.availability(), PAYLOAD_OFFLINE, QoS::AtLeastOnce, true));
+
+    tokio::spawn(async move {
+        info!(
+            "Home Assistant integration started (discovery prefix: {}, base topic: {})",
+            config.discovery_prefix, config.base_topic
+        );
+
+        let leader = app_state.database.leader_election("home_assistant");
+        let shutdown = app_state.shutdown.clone();
+        loop {
+            if leader.is_leader().await {
+                let integration = Integration::new(app_state.clone(), config.clone(), topics.clone()).await;
+                if let Exit::Shutdown = integration.run(options.clone(), &leader).await {
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(LEADER_RETRY_INTERVAL) => {}
+                _ = shutdown.wait() => break,
+            }
+        }
+
+        leader.release().await;
+    });
+}
+
+struct Integration {
+    app_state: AppState,
+    config: HomeAssistantConfig,
+    topics: Topics,
+    /// 以 command_user_id 对应的用户身份下发命令，为 None 时为系统命令
+    issuer: Option<CommandIssuer>,
+    /// 已发布的设备（节点 ID -> 设备）
+    devices: HashMap<String, PublishedDevice>,
+}
+
+impl Integration {
+    async fn new(app_state: AppState, config: HomeAssistantConfig, topics: Topics) -> Self {
+        let issuer = if config.command_user_id.is_empty() {
+            None
+        } else {
+            match app_state.database.get_user_by_id(&UserId::new(config.command_user_id.as_str())).await {
+                Ok(Some(user)) => Some(CommandIssuer {
+                    user_id: user.id,
+                    username: user.username,
+                    role: user.role,
+                }),
+                Ok(None) => {
+                    warn!("Home Assistant command user {} not found, commands are disabled", config.command_user_id);
+                    None
+                }
+                Err(e) => {
+                    warn!("Failed to load Home Assistant command user, commands are disabled: {}", e);
+                    None
+                }
+            }
+        };
+
+        Self {
+            app_state,
+            config,
+            topics,
+            issuer,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// 命令用户配置了但无法加载时拒绝所有命令，避免退化为系统命令
+    fn commands_enabled(&self) -> bool {
+        self.config.commands_enabled && (self.config.command_user_id.is_empty() || self.issuer.is_some())
+    }
+
+    /// 作为领导者运行，直到收到关闭信号或失去领导权
+    async fn run(mut self, options: MqttOptions, leader: &echo_shared::LeaderElection) -> Exit {
+        let (client, mut event_loop) = AsyncClient::new(options, 64);
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        info!("Home Assistant integration connecting to MQTT broker");
+
+        // 发布可能等待事件循环腾出队列，事件循环放在单独的任务中驱动
+        let poller = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        let _ = events_tx.send(BrokerEvent::Connected);
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let _ = events_tx.send(BrokerEvent::Message(publish));
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        if events_tx.send(BrokerEvent::Disconnected).is_err() {
+                            break;
+                        }
+                        warn!("Home Assistant MQTT connection error: {}, retrying in {:?}", e, RECONNECT_INTERVAL);
+                        tokio::time::sleep(RECONNECT_INTERVAL).await;
+                    }
+                }
+            }
+        });
+
+        let shutdown = self.app_state.shutdown.clone();
+        let mut refresh = tokio::time::interval(Duration::from_secs(self.config.refresh_interval_seconds));
+        let mut connected = false;
+        let exit = loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(BrokerEvent::Connected) => {
+                        connected = true;
+                        info!("Home Assistant integration connected to MQTT broker");
+                        self.on_connected(&client).await;
+                    }
+                    Some(BrokerEvent::Disconnected) => connected = false,
+                    Some(BrokerEvent::Message(publish)) => self.on_message(&client, publish).await,
+                    None => break Exit::LostLeadership,
+                },
+                _ = refresh.tick() => {
+                    if !leader.is_leader().await {
+                        break Exit::LostLeadership;
+                    }
+                    if connected {
+                        self.publish_devices(&client).await;
+                    }
+                }
+                _ = shutdown.wait() => {
+                    if connected {
+                        let _ = client
+                            .publish(self.topics.availability(), QoS::AtLeastOnce, true, PAYLOAD_OFFLINE)
+                            .await;
+                    }
+                    break Exit::Shutdown;
+                }
+            }
+        };
+
+        // 正常断开不会触发遗嘱消息；失去领导权时由新的领导者发布在线状态
+        info!("Disconnecting Home Assistant integration from MQTT broker");
+        if client.disconnect().await.is_ok() {
+            let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, poller).await;
+        }
+        exit
+    }
+
+    async fn on_connected(&mut self, client: &AsyncClient) {
+        // clean session 模式下每次重连都需要重新订阅
+        let mut filters = vec![
+            (self.topics.home_assistant_status(), QoS::AtLeastOnce),
+            (TopicFilter::all_device_status().topic_pattern, QoS::AtLeastOnce),
+        ];
+        if self.commands_enabled() {
+            filters.extend(self.topics.command_filters().map(|filter| (filter, QoS::AtLeastOnce)));
+        }
+        for (filter, qos) in filters {
+            if let Err(e) = client.subscribe(filter.as_str(), qos).await {
+                warn!("Failed to subscribe to {}: {}", filter, e);
+            }
+        }
+
+        if let Err(e) = client.publish(self.topics.availability(), QoS::AtLeastOnce, true, PAYLOAD_ONLINE).await {
+            warn!("Failed to publish Home Assistant availability: {}", e);
+        }
+        self.publish_devices(client).await;
+    }
+
+    async fn on_message(&mut self, client: &AsyncClient, publish: Publish) {
+        if publish.topic == self.topics.home_assistant_status() {
+            // Home Assistant 重启后会丢失非保留的状态，重新发布全部实体
+            if publish.payload.as_ref() == PAYLOAD_ONLINE.as_bytes() {
+                info!("Home Assistant came online, republishing devices");
+                self.publish_devices(client).await;
+            }
+            return;
+        }
+
+        if let Some((node, command)) = self.topics.parse_command(&publish.topic) {
+            let node = node.to_string();
+            self.on_command(&node, command, &publish.payload).await;
+            return;
+        }
+
+        if let Ok(MqttPayload::DeviceStatus { device_id, status, volume, .. }) =
+            serde_json::from_slice::<MqttPayload>(&publish.payload)
+        {
+            let node = node_id(&device_id);
+            let Some(device) = self.devices.get_mut(&node) else {
+                return;
+            };
+            device.online = status == DeviceStatus::Online;
+            if let Some(volume) = volume {
+                device.volume = volume;
+            }
+            let state = device.state();
+            self.publish_state(client, &node, &state).await;
+        }
+    }
+
+    async fn on_command(&self, node: &str, command: CommandTopic, payload: &[u8]) {
+        let Some(device) = self.devices.get(node) else {
+            debug!("Ignoring Home Assistant command for unknown device {}", node);
+            return;
+        };
+        let device_id = DeviceId::new(device.device_id.as_str());
+        let command = match parse_command(command, payload) {
+            Ok(command) => command,
+            Err(e) => {
+                warn!("Ignoring invalid Home Assistant command for device {}: {}", device_id, e);
+                return;
+            }
+        };
+
+        let kind = DeviceCommandKind::of(&command);
+        let authorizer = &self.app_state.command_authorizer;
+        if let Err(denied) = authorizer.check(device_id.as_str(), kind, self.issuer.as_ref()).await {
+            warn!("Denied {} command for device {} from Home Assistant: {}", kind.as_str(), device_id, denied);
+            crate::telemetry::record_command_denied(kind.as_str(), denied.reason());
+            if let Some(issuer) = &self.issuer {
+                if let Err(e) = authorizer
+                    .record_denial(device_id.as_str(), kind, issuer, &denied, "home_assistant", None)
+                    .await
+                {
+                    warn!("Failed to record denied command: {}", e);
+                }
+            }
+            return;
+        }
+
+        let queued = match command_message(&self.app_state, &device_id, command, self.issuer.clone()) {
+            Ok(message) => self.app_state.database.enqueue_device_command(&message).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        match queued {
+            Ok(()) => info!("Queued {} command for device {} from Home Assistant", kind.as_str(), device_id),
+            Err(e) => warn!("Failed to queue {} command for device {}: {}", kind.as_str(), device_id, e),
+        }
+    }
+
+    /// 从数据库发布所有设备的 discovery 配置和状态，并移除已删除设备的实体
+    async fn publish_devices(&mut self, client: &AsyncClient) {
+        let devices = match self.app_state.database.get_all_devices().await {
+            Ok(devices) => devices,
+            Err(e) => {
+                warn!("Failed to load devices for Home Assistant: {}", e);
+                return;
+            }
+        };
+
+        let commands_enabled = self.commands_enabled();
+        let mut published = HashMap::with_capacity(devices.len());
+        for device in &devices {
+            for (topic, config) in discovery_configs(&self.topics, device, commands_enabled) {
+                if let Err(e) = client.publish(topic.as_str(), QoS::AtLeastOnce, true, config.to_string()).await {
+                    warn!("Failed to publish {}: {}", topic, e);
+                }
+            }
+
+            let node = node_id(&device.id);
+            let state = PublishedDevice {
+                device_id: device.id.clone(),
+                online: device.status == DeviceStatus::Online,
+                volume: device.volume,
+            };
+            self.publish_state(client, &node, &state.state()).await;
+            published.insert(node, state);
+        }
+
+        // 空的保留消息会让 Home Assistant 删除实体
+        for node in self.devices.keys().filter(|node| !published.contains_key(*node)) {
+            info!("Removing deleted device {} from Home Assistant", node);
+            let topics = ENTITIES
+                .iter()
+                .map(|(component, object)| self.topics.discovery(component, node, object))
+                .chain([self.topics.discovery("sensor", node, "volume"), self.topics.state(node)]);
+            for topic in topics {
+                let _ = client.publish(topic, QoS::AtLeastOnce, true, Vec::new()).await;
+            }
+        }
+
+        debug!("Published {} devices to Home Assistant", published.len());
+        self.devices = published;
+    }
+
+    async fn publish_state(&self, client: &AsyncClient, node: &str, state: &Value) {
+        let topic = self.topics.state(node);
+        if let Err(e) = client.publish(topic.as_str(), QoS::AtLeastOnce, true, state.to_string()).await {
+            warn!("Failed to publish {}: {}", topic, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn topics() -> Topics {
+        Topics::new(&HomeAssistantConfig::default())
+    }
+
+    #[test]
+    fn test_parse_command_topic() {
+        let topics = topics();
+        assert_eq!(topics.parse_command("etch/ha/dev-1/volume/set"), Some(("dev-1", CommandTopic::Volume)));
+        assert_eq!(topics.parse_command("etch/ha/dev-1/announce"), Some(("dev-1", CommandTopic::Announce)));
+        assert_eq!(topics.parse_command("etch/ha/dev-1/state"), None);
+        assert_eq!(topics.parse_command("etch/hax/dev-1/announce"), None);
+        assert_eq!(topics.parse_command("etch/ha/status"), None);
+    }
+
+    #[test]
+    fn test_parse_command_payload() {
+        assert!(matches!(
+            parse_command(CommandTopic::Volume, b"42.0"),
+            Ok(DeviceCommand::SetVolume { level: 42 })
+        ));
+        assert!(parse_command(CommandTopic::Volume, b"101").is_err());
+        assert!(parse_command(CommandTopic::Announce, b"  ").is_err());
+        let announce = parse_command(CommandTopic::Announce, "晚饭好了".as_bytes()).unwrap();
+        assert_eq!(DeviceCommandKind::of(&announce), DeviceCommandKind::Announcement);
+    }
+
+    #[test]
+    fn test_discovery_configs() {
+        let topics = topics();
+        let device = Device {
+            id: "dev:1".to_string(),
+            name: "Kitchen".to_string(),
+            device_type: echo_shared::DeviceType::Speaker,
+            status: DeviceStatus::Online,
+            location: "Kitchen".to_string(),
+            firmware_version: "1.2.0".to_string(),
+            battery_level: 80,
+            volume: 40,
+            last_seen: chrono::Utc::now(),
+            is_online: true,
+            owner: "user-1".to_string(),
+            echokit_server_url: None,
+            serial_number: None,
+            mac_address: None,
+            version: 1,
+            current_session_id: None,
+        };
+
+        let configs = discovery_configs(&topics, &device, true);
+        let topics_published: Vec<&str> = configs.iter().map(|(topic, _)| topic.as_str()).collect();
+        assert_eq!(
+            topics_published,
+            [
+                "homeassistant/binary_sensor/dev_1/presence/config",
+                "homeassistant/number/dev_1/volume/config",
+                "homeassistant/notify/dev_1/announce/config",
+            ]
+        );
+        let volume = &configs[1].1;
+        assert_eq!(volume["command_topic"], "etch/ha/dev_1/volume/set");
+        assert_eq!(volume["device"]["identifiers"][0], "etch_dev_1");
+        assert_eq!(volume["device"]["suggested_area"], "Kitchen");
+
+        let read_only = discovery_configs(&topics, &device, false);
+        assert_eq!(read_only.len(), 2);
+        assert!(read_only.iter().all(|(_, config)| config.get("command_topic").is_none()));
+    }
+}
@@ -0,0 +1,119 @@
+// 只读接口的 HTTP 缓存：ETag / If-None-Match 协商，响应体短期缓存在 Redis 中
+use std::future::Future;
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use echo_shared::{ApiResponse, CacheOperation};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use crate::cache::Cache;
+use crate::error::ApiError;
+
+/// 设备相关接口（设备列表、设备统计）的缓存作用域
+pub const DEVICES_SCOPE: &str = echo_shared::response_scopes::DEVICES;
+/// 会话相关接口（会话统计）的缓存作用域
+pub const SESSIONS_SCOPE: &str = echo_shared::response_scopes::SESSIONS;
+
+/// 客户端每次使用前都需要用 ETag 重新验证
+const CACHE_CONTROL: &str = "private, no-cache";
+
+/// 返回可缓存的 JSON 响应
+///
+/// 优先读取 Redis 中的缓存响应体，未命中时调用 `load` 生成并写入缓存；
+/// 请求的 If-None-Match 与响应 ETag 匹配时返回 304。
+/// Redis 不可用时直接查询，不影响接口可用性
+pub async fn cached_json<T, F, Fut>(
+    cache: &Cache,
+    scope: &str,
+    variant: &str,
+    headers: &HeaderMap,
+    load: F,
+) -> Result<Response, ApiError>
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ApiError>>,
+{
+    // Redis 降级期间不读写响应缓存
+    let key = if cache.skip(CacheOperation::Read) {
+        None
+    } else {
+        match cache.response_generation(scope).await {
+            Ok(generation) => Some(Cache::response_key(scope, generation, variant)),
+            Err(e) => {
+                warn!("Response cache unavailable for {}: {}", scope, e);
+                None
+            }
+        }
+    };
+
+    let cached = match &key {
+        Some(key) => cache.get_cached_response(key).await.unwrap_or_else(|e| {
+            warn!("Failed to read cached response {}: {}", key, e);
+            None
+        }),
+        None => None,
+    };
+
+    let body = match cached {
+        Some(body) => body,
+        None => {
+            let data = load().await?;
+            let body = serde_json::to_string(&ApiResponse::success(data))
+                .map_err(|e| ApiError::from(anyhow::Error::from(e)))?;
+            if let Some(key) = &key {
+                if let Err(e) = cache.cache_response(key, &body, echo_shared::ttl::HTTP_RESPONSE).await {
+                    warn!("Failed to cache response {}: {}", key, e);
+                }
+            }
+            body
+        }
+    };
+
+    let etag = compute_etag(&body);
+    let etag_header = HeaderValue::from_str(&etag).expect("ETag is a valid header value");
+
+    if if_none_match(headers, &etag) {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag_header), (header::CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL))],
+        ).into_response());
+    }
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("application/json")),
+            (header::ETAG, etag_header),
+            (header::CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL)),
+        ],
+        body,
+    ).into_response())
+}
+
+/// 写操作后使作用域下的响应缓存失效，失败时仅记录日志，缓存会在 TTL 到期后自然失效
+pub async fn invalidate(cache: &Cache, scope: &str) {
+    if let Err(e) = cache.bump_response_generation(scope).await {
+        warn!("Failed to invalidate response cache for {}: {}", scope, e);
+    }
+}
+
+/// 根据响应体计算强 ETag
+fn compute_etag(body: &str) -> String {
+    let digest = Sha256::digest(body.as_bytes());
+    let hex: String = digest.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// 检查 If-None-Match 是否匹配（GET 请求按弱比较处理）
+fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
@@ -0,0 +1,91 @@
+// 来源 IP 过滤：在鉴权、限流和请求体检查之前拒绝命中拒绝列表、不在允许列表中或来自被拒绝国家的请求。
+// 规则来自可热加载的 ip_filter 配置和 ip_filter_rules 表，定期或配置变化时重新读取
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use echo_shared::{IpFilter, IpFilterConfig, IpFilterHandle, RuntimeConfig};
+use tokio::sync::watch;
+use tracing::{debug, warn};
+use crate::app_state::AppState;
+use crate::database::Database;
+use crate::error::ApiError;
+use crate::middleware::client_ip;
+use crate::shutdown::Shutdown;
+use crate::telemetry;
+
+/// 按配置文件中的规则创建，数据库中的规则由刷新任务加载
+pub fn initial_filter(config: &IpFilterConfig) -> IpFilterHandle {
+    IpFilterHandle::new(IpFilter::new(config, &[]).0)
+}
+
+/// 重新读取配置和数据库中的规则，SQLite 存储没有规则表，只使用配置中的规则
+pub async fn refresh(handle: &IpFilterHandle, config: &IpFilterConfig, database: &Database) {
+    match handle.refresh(config, database.pool().ok()).await {
+        Ok(errors) => {
+            for e in errors {
+                warn!("Skipping invalid IP filter rule: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to load IP filter rules: {}", e),
+    }
+}
+
+/// 每 refresh_seconds 秒或配置热加载后刷新规则
+pub fn spawn_refresh(
+    handle: IpFilterHandle,
+    database: Arc<Database>,
+    mut config: watch::Receiver<RuntimeConfig>,
+    shutdown: Shutdown,
+) {
+    tokio::spawn(async move {
+        loop {
+            let settings = config.borrow_and_update().ip_filter.clone();
+            refresh(&handle, &settings, &database).await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(settings.refresh_seconds.max(1))) => {}
+                changed = config.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                }
+                _ = shutdown.wait() => break,
+            }
+        }
+    });
+}
+
+/// 拒绝被过滤规则拦截的请求，健康检查不受限制（供负载均衡器探测）
+pub async fn ip_filter_middleware(
+    State(app_state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let filter = app_state.ip_filter.current();
+    if !filter.is_enabled() || req.uri().path().starts_with("/health") {
+        return next.run(req).await;
+    }
+
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+    let ip = if filter.trust_proxy_headers() {
+        client_ip(req.headers()).and_then(|ip| ip.parse::<IpAddr>().ok()).or(peer)
+    } else {
+        peer
+    };
+    let country = filter
+        .country_header()
+        .and_then(|header| req.headers().get(header))
+        .and_then(|value| value.to_str().ok());
+
+    if let Err(blocked) = filter.check_ip(ip).and_then(|()| filter.check_country(country)) {
+        debug!("Blocked {} {} from {:?}: {:?}", req.method(), req.uri().path(), ip, blocked);
+        telemetry::record_ip_blocked(blocked.reason());
+        return ApiError::forbidden("Access denied").into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,231 @@
+// 任务队列 worker：以消费者组方式处理 Redis Streams 任务队列中的任务（见 echo_shared::job_queue）
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use echo_shared::{job_kinds, Job, JobDelivery, JobQueue, SessionId};
+use redis::aio::MultiplexedConnection;
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use crate::cache::Cache;
+use crate::database::Database;
+use crate::shutdown::Shutdown;
+use crate::summary_job::{self, SummaryConfig};
+use crate::telemetry;
+
+/// 没有新任务时每次读取的最长等待时间
+const READ_BLOCK: Duration = Duration::from_secs(5);
+
+/// Redis 连接失败后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 任务处理函数
+#[async_trait::async_trait]
+pub trait JobHandler: Send + Sync {
+    async fn handle(&self, job: &Job) -> Result<()>;
+}
+
+/// 任务类型 -> 处理函数，没有处理函数的任务写入死信流
+pub type JobHandlers = HashMap<&'static str, Box<dyn JobHandler>>;
+
+/// 启动任务 worker
+///
+/// 每个 API Gateway 实例一个消费者；处理失败的任务在重试间隔后由任意实例重试，
+/// 达到最大投递次数后写入死信流。停止服务时处理完当前这批任务后退出
+pub fn spawn_job_worker(cache: Arc<Cache>, handlers: JobHandlers, shutdown: Shutdown) {
+    let consumer = consumer_name();
+    tokio::spawn(async move {
+        let queue = cache.job_queue();
+        let config = queue.config();
+        info!(
+            "Job worker {} started (stream: {}, group: {}, handlers: {:?})",
+            consumer,
+            config.stream,
+            config.group,
+            handlers.keys().collect::<Vec<_>>()
+        );
+
+        let claim_interval = Duration::from_secs(config.retry_delay_seconds / 2).max(Duration::from_secs(1));
+        let mut last_claim: Option<Instant> = None;
+        let mut connection: Option<MultiplexedConnection> = None;
+
+        while !shutdown.is_triggered() {
+            if connection.is_none() {
+                match connect(&cache, queue).await {
+                    Ok(conn) => connection = Some(conn),
+                    Err(e) => {
+                        warn!("Job worker failed to connect to Redis: {}", e);
+                        tokio::select! {
+                            _ = tokio::time::sleep(RECONNECT_INTERVAL) => {}
+                            _ = shutdown.wait() => break,
+                        }
+                        continue;
+                    }
+                }
+            }
+            let Some(conn) = connection.as_mut() else { continue };
+
+            // 定期领取失败后待重试或消费者退出后遗留的任务
+            if last_claim.is_none_or(|claimed| claimed.elapsed() >= claim_interval) {
+                last_claim = Some(Instant::now());
+                match queue.claim_stale(conn, &consumer).await {
+                    Ok(deliveries) => {
+                        for delivery in deliveries {
+                            process(queue, conn, &handlers, delivery).await;
+                        }
+                    }
+                    Err(e) => warn!("Failed to claim stale jobs: {}", e),
+                }
+            }
+
+            let deliveries = tokio::select! {
+                deliveries = queue.read_new(conn, &consumer, READ_BLOCK) => deliveries,
+                _ = shutdown.wait() => break,
+            };
+            match deliveries {
+                Ok(deliveries) => {
+                    for delivery in deliveries {
+                        process(queue, conn, &handlers, delivery).await;
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to read jobs: {}", e);
+                    connection = None;
+                }
+            }
+        }
+
+        info!("Job worker {} stopped", consumer);
+    });
+}
+
+async fn connect(cache: &Cache, queue: &JobQueue) -> Result<MultiplexedConnection, redis::RedisError> {
+    let mut conn = cache.dedicated_connection().await?;
+    queue.ensure_group(&mut conn).await?;
+    Ok(conn)
+}
+
+/// 处理单个任务；确认或写入死信流失败时任务留在待确认列表中，稍后重试
+async fn process(queue: &JobQueue, conn: &mut MultiplexedConnection, handlers: &JobHandlers, delivery: JobDelivery) {
+    let config = queue.config();
+    let job = match delivery.job() {
+        Ok(job) => job,
+        Err(e) => {
+            dead_letter(queue, conn, &delivery, "invalid", &format!("Invalid job: {}", e)).await;
+            return;
+        }
+    };
+    let Some(handler) = handlers.get(job.kind.as_str()) else {
+        dead_letter(queue, conn, &delivery, &job.kind, &format!("No handler for job kind {}", job.kind)).await;
+        return;
+    };
+
+    let timeout = Duration::from_secs(config.job_timeout_seconds);
+    let error = match tokio::time::timeout(timeout, handler.handle(&job)).await {
+        Ok(Ok(())) => {
+            debug!("Job {} ({}) completed on attempt {}", delivery.id, job.kind, delivery.attempt);
+            telemetry::record_job(&job.kind, "completed");
+            if let Err(e) = queue.complete(conn, &delivery.id).await {
+                warn!("Failed to acknowledge job {}: {}", delivery.id, e);
+            }
+            return;
+        }
+        Ok(Err(e)) => format!("{:#}", e),
+        Err(_) => format!("Job timed out after {}s", config.job_timeout_seconds),
+    };
+
+    if delivery.attempt >= config.max_attempts {
+        dead_letter(queue, conn, &delivery, &job.kind, &error).await;
+    } else {
+        warn!(
+            "Job {} ({}) failed on attempt {}/{}, retrying in {}s: {}",
+            delivery.id, job.kind, delivery.attempt, config.max_attempts, config.retry_delay_seconds, error
+        );
+        telemetry::record_job(&job.kind, "retried");
+    }
+}
+
+async fn dead_letter(queue: &JobQueue, conn: &mut MultiplexedConnection, delivery: &JobDelivery, kind: &str, error: &str) {
+    warn!("Moving job {} ({}) to the dead-letter stream after {} attempts: {}", delivery.id, kind, delivery.attempt, error);
+    telemetry::record_job(kind, "dead_letter");
+    if let Err(e) = queue.dead_letter(conn, delivery, error).await {
+        warn!("Failed to move job {} to the dead-letter stream: {}", delivery.id, e);
+    }
+}
+
+/// 消费者名称：主机名（容器中为容器 ID 或 Pod 名称）加进程号，同一主机上的多个进程互不冲突
+fn consumer_name() -> String {
+    let host = std::env::var("HOSTNAME").ok().filter(|h| !h.is_empty()).unwrap_or_else(|| "gateway".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+// 任务处理函数
+
+/// Webhook 投递任务的 payload
+#[derive(Debug, Deserialize)]
+struct WebhookJob {
+    url: String,
+    body: serde_json::Value,
+}
+
+/// 投递 Webhook：POST JSON，非 2xx 响应视为失败
+pub struct WebhookHandler {
+    http: reqwest::Client,
+}
+
+impl WebhookHandler {
+    pub fn new() -> Result<Self> {
+        Ok(Self { http: reqwest::Client::builder().timeout(Duration::from_secs(10)).build()? })
+    }
+}
+
+#[async_trait::async_trait]
+impl JobHandler for WebhookHandler {
+    async fn handle(&self, job: &Job) -> Result<()> {
+        let webhook: WebhookJob = job.payload()?;
+        self.http.post(&webhook.url).json(&webhook.body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// 会话摘要任务的 payload
+#[derive(Debug, Deserialize)]
+struct SessionSummaryJob {
+    session_id: SessionId,
+}
+
+/// 生成会话摘要；会话已摘要或没有转写内容时直接完成
+pub struct SessionSummaryHandler {
+    database: Arc<Database>,
+    http: reqwest::Client,
+    config: SummaryConfig,
+}
+
+impl SessionSummaryHandler {
+    pub fn new(database: Arc<Database>, config: SummaryConfig) -> Result<Self> {
+        Ok(Self { database, http: reqwest::Client::builder().timeout(Duration::from_secs(30)).build()?, config })
+    }
+}
+
+#[async_trait::async_trait]
+impl JobHandler for SessionSummaryHandler {
+    async fn handle(&self, job: &Job) -> Result<()> {
+        let SessionSummaryJob { session_id } = job.payload()?;
+        match self.database.session_pending_summary(&session_id).await? {
+            Some(session) => summary_job::summarize_and_save(&self.database, &self.http, &self.config, &session).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// 本实例注册的任务处理函数
+pub fn default_handlers(database: Arc<Database>, session_summaries: bool) -> Result<JobHandlers> {
+    let mut handlers: JobHandlers = HashMap::new();
+    handlers.insert(job_kinds::WEBHOOK, Box::new(WebhookHandler::new()?));
+    if session_summaries {
+        if let Some(config) = SummaryConfig::from_env() {
+            handlers.insert(job_kinds::SESSION_SUMMARY, Box::new(SessionSummaryHandler::new(database, config)?));
+        }
+    }
+    Ok(handlers)
+}
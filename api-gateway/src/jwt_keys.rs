@@ -0,0 +1,312 @@
+// JWT 签名密钥环：令牌头部携带 kid，签名密钥轮换后旧密钥在令牌有效期内仍可用于校验
+// 轮换有两种来源：配置的密钥变化（密钥提供者中轮换），以及按 rotation_hours 周期从配置的密钥派生新密钥
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use echo_shared::{AppConfig, JwtConfig};
+use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use crate::shutdown::Shutdown;
+
+// 只启用自动轮换时检查是否进入新周期的间隔
+const AUTO_ROTATION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 签名密钥
+struct JwtKey {
+    kid: String,
+    secret: String,
+    /// 被新密钥替换的时间，为空表示当前使用的密钥
+    retired_at: Option<DateTime<Utc>>,
+}
+
+/// 签名密钥环，第一个密钥用于签名
+pub struct JwtKeyRing {
+    keys: Vec<JwtKey>,
+    expiration_hours: u64,
+    /// 配置的签名密钥，启用自动轮换时各周期的密钥都从它派生
+    secret: String,
+    rotation_hours: u64,
+}
+
+impl JwtKeyRing {
+    pub fn new(config: &JwtConfig) -> Self {
+        Self::at(config, Utc::now())
+    }
+
+    fn at(config: &JwtConfig, now: DateTime<Utc>) -> Self {
+        let mut ring = Self {
+            keys: Vec::new(),
+            expiration_hours: config.expiration_hours,
+            secret: config.secret.clone(),
+            rotation_hours: config.rotation_hours,
+        };
+        if ring.rotation_hours == 0 {
+            ring.keys.push(JwtKey { kid: key_id(&ring.secret), secret: ring.secret.clone(), retired_at: None });
+            return ring;
+        }
+
+        // 之前几个周期的密钥签发的令牌（其他副本或重启前签发）可能仍在有效期内
+        let current = ring.period(now);
+        let previous = ring.expiration_hours.div_ceil(ring.rotation_hours) as i64;
+        for period in current - previous..=current {
+            let secret = ring.period_secret(period);
+            let retired_at = (period < current).then(|| ring.period_start(period + 1));
+            ring.keys.insert(0, JwtKey { kid: key_id(&secret), secret, retired_at });
+        }
+        ring.prune(now);
+        ring
+    }
+
+    /// 当前签名密钥的 kid
+    pub fn active_kid(&self) -> &str {
+        &self.keys[0].kid
+    }
+
+    /// 配置的签名密钥变化（如密钥提供者中的密钥被轮换）时切换，密钥未变化时返回 false
+    pub fn rotate(&mut self, secret: &str, now: DateTime<Utc>) -> bool {
+        if secret == self.secret {
+            return false;
+        }
+        self.secret = secret.to_string();
+        let signing = self.signing_secret(now);
+        self.install(signing, now)
+    }
+
+    /// 启用自动轮换时，进入新周期后切换到该周期的密钥，未切换时返回 false
+    pub fn rotate_if_due(&mut self, now: DateTime<Utc>) -> bool {
+        if self.rotation_hours == 0 {
+            return false;
+        }
+        let signing = self.signing_secret(now);
+        self.install(signing, now)
+    }
+
+    /// 使用当前签名密钥签发令牌，头部携带 kid
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, JwtError> {
+        let key = &self.keys[0];
+        let header = Header { kid: Some(key.kid.clone()), ..Header::default() };
+        encode(&header, claims, &EncodingKey::from_secret(key.secret.as_bytes()))
+    }
+
+    /// 按令牌的 kid 选择密钥校验并解析令牌（包含过期校验），kid 未知或对应的密钥已过保留期时拒绝
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, JwtError> {
+        let header = decode_header(token)?;
+        let key = self
+            .find(header.kid.as_deref())
+            .ok_or_else(|| JwtError::from(ErrorKind::InvalidSignature))?;
+
+        let data = decode::<T>(token, &DecodingKey::from_secret(key.secret.as_bytes()), &Validation::default())?;
+        Ok(data.claims)
+    }
+
+    // 切换签名密钥，旧密钥保留到其签发的令牌全部过期为止
+    fn install(&mut self, secret: String, now: DateTime<Utc>) -> bool {
+        let kid = key_id(&secret);
+        if kid == self.keys[0].kid {
+            return false;
+        }
+
+        self.keys[0].retired_at = Some(now);
+        self.keys.retain(|key| key.kid != kid);
+        self.keys.insert(0, JwtKey { kid, secret, retired_at: None });
+        self.prune(now);
+        true
+    }
+
+    // 移除签发的令牌已全部过期的旧密钥
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let retention = chrono::Duration::hours(self.expiration_hours as i64);
+        self.keys.retain(|key| key.retired_at.is_none_or(|retired| now - retired <= retention));
+    }
+
+    // 当前用于签名的密钥：未启用自动轮换时为配置的密钥，否则为当前周期派生的密钥
+    fn signing_secret(&self, now: DateTime<Utc>) -> String {
+        if self.rotation_hours == 0 {
+            self.secret.clone()
+        } else {
+            self.period_secret(self.period(now))
+        }
+    }
+
+    fn period(&self, now: DateTime<Utc>) -> i64 {
+        now.timestamp().div_euclid(self.rotation_hours as i64 * 3600)
+    }
+
+    fn period_start(&self, period: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(period * self.rotation_hours as i64 * 3600, 0).unwrap_or(DateTime::UNIX_EPOCH)
+    }
+
+    // 各副本用配置的密钥对周期序号做 HMAC，得到相同的周期密钥，无需协调
+    fn period_secret(&self, period: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(format!("jwt-signing-key:{}", period).as_bytes());
+        mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// 按 kid 查找校验密钥，没有 kid 的旧令牌使用当前密钥
+    fn find(&self, kid: Option<&str>) -> Option<&JwtKey> {
+        match kid {
+            Some(kid) => self.keys.iter().find(|key| key.kid == kid),
+            None => self.keys.first(),
+        }
+    }
+}
+
+/// 密钥 ID：密钥 SHA-256 摘要的前 8 字节（十六进制）
+fn key_id(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes()).iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+static JWT_KEYS: LazyLock<RwLock<JwtKeyRing>> =
+    LazyLock::new(|| RwLock::new(JwtKeyRing::new(&AppConfig::default().jwt)));
+
+/// 使用配置中的签名密钥和有效期初始化密钥环（启动时调用）
+pub fn init(config: &JwtConfig) {
+    *JWT_KEYS.write().unwrap_or_else(|e| e.into_inner()) = JwtKeyRing::new(config);
+}
+
+/// 令牌有效期（小时）
+pub fn expiration_hours() -> u64 {
+    JWT_KEYS.read().unwrap_or_else(|e| e.into_inner()).expiration_hours
+}
+
+/// 使用当前签名密钥签发令牌，头部携带 kid
+pub fn sign<T: Serialize>(claims: &T) -> Result<String, JwtError> {
+    JWT_KEYS.read().unwrap_or_else(|e| e.into_inner()).sign(claims)
+}
+
+/// 按令牌的 kid 选择密钥校验并解析令牌（包含过期校验）
+pub fn verify<T: DeserializeOwned>(token: &str) -> Result<T, JwtError> {
+    JWT_KEYS.read().unwrap_or_else(|e| e.into_inner()).verify(token)
+}
+
+/// 定期轮换签名密钥：启用 `key_refresh_secs` 时重新加载配置，签名密钥变化（如密钥提供者中的密钥被轮换）时切换；
+/// 启用 `rotation_hours` 时每个周期切换到新派生的密钥。两者都未启用时不启动
+pub fn spawn_key_refresh(config: &JwtConfig, shutdown: Shutdown) {
+    let reload = config.key_refresh_secs > 0;
+    let interval = match (reload, config.rotation_hours > 0) {
+        (true, _) => Duration::from_secs(config.key_refresh_secs),
+        (false, true) => AUTO_ROTATION_CHECK_INTERVAL,
+        (false, false) => return,
+    };
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown.wait() => break,
+            }
+
+            let secret = if reload {
+                match echo_shared::load_config().await {
+                    Ok(config) => Some(config.jwt.secret),
+                    Err(e) => {
+                        warn!("Failed to refresh JWT signing key: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            let now = Utc::now();
+            let mut keys = JWT_KEYS.write().unwrap_or_else(|e| e.into_inner());
+            let rotated = secret.is_some_and(|secret| keys.rotate(&secret, now));
+            if rotated || keys.rotate_if_due(now) {
+                info!("JWT signing key rotated, active key id: {}", keys.active_kid());
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    fn config(secret: &str, rotation_hours: u64) -> JwtConfig {
+        JwtConfig {
+            secret: secret.to_string(),
+            expiration_hours: 24,
+            key_refresh_secs: 0,
+            rotation_hours,
+        }
+    }
+
+    fn claims() -> TestClaims {
+        TestClaims { sub: "user-1".to_string(), exp: (Utc::now() + chrono::Duration::days(30)).timestamp() }
+    }
+
+    fn kid(token: &str) -> String {
+        decode_header(token).unwrap().kid.unwrap()
+    }
+
+    #[test]
+    fn test_rotate_keeps_previous_key_within_grace_window() {
+        let now = Utc::now();
+        let mut ring = JwtKeyRing::at(&config("secret-a", 0), now);
+        let old_token = ring.sign(&claims()).unwrap();
+
+        assert!(!ring.rotate("secret-a", now));
+        assert!(ring.rotate("secret-b", now));
+        let new_token = ring.sign(&claims()).unwrap();
+        assert_ne!(kid(&old_token), kid(&new_token));
+        assert_eq!(kid(&new_token), ring.active_kid());
+
+        // 旧密钥在令牌有效期内仍可校验
+        assert_eq!(ring.verify::<TestClaims>(&old_token).unwrap().sub, "user-1");
+        assert!(ring.verify::<TestClaims>(&new_token).is_ok());
+    }
+
+    #[test]
+    fn test_retired_key_expires_after_token_lifetime() {
+        let now = Utc::now();
+        let mut ring = JwtKeyRing::at(&config("secret-a", 0), now);
+        let old_token = ring.sign(&claims()).unwrap();
+
+        ring.rotate("secret-b", now);
+        ring.rotate("secret-c", now + chrono::Duration::hours(25));
+
+        let error = ring.verify::<TestClaims>(&old_token).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn test_unknown_kid_is_rejected() {
+        let ring = JwtKeyRing::at(&config("secret-a", 0), Utc::now());
+        let other = JwtKeyRing::at(&config("secret-other", 0), Utc::now());
+        let token = other.sign(&claims()).unwrap();
+
+        let error = ring.verify::<TestClaims>(&token).unwrap_err();
+        assert_eq!(error.kind(), &ErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn test_automatic_rotation_is_consistent_across_replicas() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut ring = JwtKeyRing::at(&config("secret-a", 6), now);
+        let replica = JwtKeyRing::at(&config("secret-a", 6), now + chrono::Duration::minutes(5));
+        assert_eq!(ring.active_kid(), replica.active_kid());
+
+        let old_token = ring.sign(&claims()).unwrap();
+        assert!(!ring.rotate_if_due(now + chrono::Duration::minutes(1)));
+        assert!(ring.rotate_if_due(now + chrono::Duration::hours(6)));
+        assert_ne!(ring.active_kid(), kid(&old_token));
+        assert!(ring.verify::<TestClaims>(&old_token).is_ok());
+
+        // 重启后的副本仍能校验上个周期签发的令牌
+        let restarted = JwtKeyRing::at(&config("secret-a", 6), now + chrono::Duration::hours(6));
+        assert_eq!(restarted.active_kid(), ring.active_kid());
+        assert!(restarted.verify::<TestClaims>(&old_token).is_ok());
+    }
+}
@@ -0,0 +1,493 @@
+// 网关不使用 unsafe 代码，共享状态统一放在 AppState 中
+#![deny(unsafe_code)]
+
+use anyhow::Result;
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::get,
+    Router,
+};
+use std::net::SocketAddr;
+use tower_http::{
+    cors::{Any, CorsLayer},
+};
+use tracing::{info, warn};
+use tokio::sync::broadcast;
+use serde_json::json;
+use chrono;
+
+// 逐步重新启用模块
+// mod auth;
+mod error;
+mod handlers;
+mod middleware;
+// mod models;
+// mod utils;
+mod validation;
+mod http_cache;
+mod websocket;
+// mod mqtt;
+// mod storage;
+mod database;
+mod cache;
+mod notifier;
+mod bridge_client;
+mod rollup_job;
+mod summary_job;
+mod partition_job;
+mod backup_job;
+mod live_events;
+mod home_assistant;
+mod webhooks;
+mod notifications;
+mod recurrence;
+mod announcements;
+mod cache_invalidation;
+mod job_worker;
+mod shutdown;
+mod config_reload;
+mod audit;
+mod rate_limit;
+mod jwt_keys;
+mod cookie_auth;
+mod ip_filter;
+mod tls;
+mod telemetry;
+mod build_info;
+#[cfg(feature = "grpc")]
+mod grpc_server;
+// mod device_service;
+// mod user_service;
+mod app_state;
+pub mod seed;
+
+// 启用基础的handlers
+use handlers::health::health_routes;
+use handlers::auth::auth_routes;
+use handlers::devices::device_routes;
+use handlers::users::user_routes;
+use handlers::sessions::session_routes;
+use handlers::echokit_servers::echokit_server_routes;
+use handlers::admin::admin_routes;
+use handlers::webhooks::webhook_routes;
+use handlers::notifications::notification_routes;
+use handlers::announcements::announcement_routes;
+use app_state::AppState;
+use middleware::{auth_middleware, body_limit_middleware, rate_limit_middleware, request_logging, security_headers_middleware};
+use websocket::websocket_handler;
+// use mqtt::{ApiGatewayMqttClient, mqtt_routes};
+// use storage::{Storage, StorageConfig};
+// use device_service::DeviceService;
+// use user_service::UserService;
+// use app_state::AppState;
+
+pub use telemetry::{metrics_buckets, use_metrics_handle};
+
+/// 独立运行 API Gateway，初始化日志和 Prometheus 指标记录器
+pub async fn run(config: echo_shared::AppConfig) -> Result<()> {
+    // 初始化日志（日志级别可热加载）
+    let log_handle = echo_shared::init_tracing(&config.log, None);
+    telemetry::init()?;
+    serve(config, Some(log_handle), None).await
+}
+
+/// 在 etch-all 单进程模式中运行 API Gateway
+///
+/// 日志和指标记录器已由调用方初始化，日志级别随 Bridge 热加载；数据库连接池与 Bridge 共用，
+/// Bridge 的会话实时事件从进程内事件总线读取。
+pub async fn run_in_process(config: echo_shared::AppConfig, in_process: echo_shared::InProcess) -> Result<()> {
+    serve(config, None, Some(in_process)).await
+}
+
+async fn serve(
+    config: echo_shared::AppConfig,
+    log_handle: Option<echo_shared::LogReloadHandle>,
+    in_process: Option<echo_shared::InProcess>,
+) -> Result<()> {
+    config.bridge.check_internal_token()?;
+    if config.bridge.uses_dev_internal_token() {
+        warn!("bridge.allow_dev_internal_token is enabled, using the development token");
+    }
+    jwt_keys::init(&config.jwt);
+    cookie_auth::init(&config.cookie_auth);
+    let config_watcher = echo_shared::ConfigWatcher::new(&config);
+    if let Some(log_handle) = log_handle {
+        echo_shared::spawn_log_level_watcher(log_handle, config_watcher.subscribe());
+    }
+    info!("Configuration loaded successfully");
+
+    // TODO: 临时禁用存储层和MQTT以修复编译问题
+    // 初始化存储层
+    // let storage_config = StorageConfig::default();
+    // info!("Initializing storage layer...");
+    // let storage = Arc::new(Storage::new(storage_config).await?);
+    // info!("Storage layer initialized successfully");
+
+    // 创建 WebSocket 广播器，MQTT 事件经此推送给 WebSocket 订阅者
+    let (websocket_tx, _) = broadcast::channel::<echo_shared::WebSocketMessage>(1000);
+
+    // TODO: 临时禁用 MQTT 客户端
+    // 创建 MQTT 配置
+    // let mqtt_config = MqttConfig {
+    //     broker_host: std::env::var("MQTT_BROKER_HOST")
+    //         .unwrap_or_else(|_| "localhost".to_string()),
+    //     broker_port: std::env::var("MQTT_BROKER_PORT")
+    //         .unwrap_or_else(|_| "1883".to_string())
+    //         .parse()
+    //         .unwrap_or(1883),
+    //     client_id: format!("api-gateway-{}", uuid::Uuid::new_v4()),
+    //     username: std::env::var("MQTT_USERNAME").ok(),
+    //     password: std::env::var("MQTT_PASSWORD").ok(),
+    //     keep_alive: 60,
+    //     clean_session: true,
+    //     max_reconnect_attempts: 10,
+    //     reconnect_interval_ms: 5000,
+    // };
+
+    // 创建 MQTT 客户端
+    // let mqtt_client = Arc::new(ApiGatewayMqttClient::new(
+    //     mqtt_config.clone(),
+    //     websocket_tx,
+    // )?);
+
+    // 启动 MQTT 客户端
+    // info!("Starting MQTT client...");
+    // mqtt_client.start().await?;
+
+    // 订阅主题
+    // mqtt_client.subscribe(&TopicFilter::all_device_status()).await?;
+    // mqtt_client.subscribe(&TopicFilter::all_device_wake()).await?;
+    // mqtt_client.subscribe(&TopicFilter::system_status()).await?;
+
+    // info!("MQTT client started and subscribed to topics");
+
+    // TODO: 临时禁用服务层
+    // 创建服务层
+    // let device_service = Arc::new(DeviceService::new(storage.db.clone(), storage.cache.clone()));
+    // let user_service = Arc::new(UserService::new(storage.db.clone(), storage.cache.clone()));
+
+    // 暂时跳过完整的应用状态创建，直接使用简化的路由
+    // TODO: 实现完整的应用状态初始化
+
+    // 创建应用（使用真正的handlers和AppState）
+    let storage = in_process.as_ref().map(|in_process| in_process.storage.clone());
+    let app_state = AppState::new(websocket_tx.clone(), &config, config_watcher.clone(), storage).await?;
+
+    // 定期检查 JWT 签名密钥是否已轮换，启用自动轮换时按周期切换密钥
+    jwt_keys::spawn_key_refresh(&config.jwt, app_state.shutdown.clone());
+
+    // 定期读取管理接口添加的 IP 过滤规则
+    ip_filter::spawn_refresh(
+        app_state.ip_filter.clone(),
+        app_state.database.clone(),
+        config_watcher.subscribe(),
+        app_state.shutdown.clone(),
+    );
+
+    // 收到 SIGHUP 时重新加载配置
+    config_reload::spawn_sighup_listener(config_watcher, app_state.database.clone(), app_state.shutdown.clone());
+
+    // 启动内部 gRPC 服务（可选），接收 Bridge 推送的会话事件和设备上下线
+    #[cfg(feature = "grpc")]
+    let grpc_server = {
+        let events = websocket_tx.clone();
+        let token = config.bridge.internal_token.clone();
+        let shutdown = app_state.shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = grpc_server::serve(events, token, shutdown).await {
+                tracing::error!("Internal gRPC server error: {}", e);
+            }
+        })
+    };
+
+    // 单进程模式下 Bridge 的会话进度和 ASR 识别结果经进程内事件总线送达
+    if let Some(in_process) = &in_process {
+        live_events::spawn_local_event_listener(&in_process.events, websocket_tx.clone(), app_state.shutdown.clone());
+    }
+
+    // 启动 MQTT 实时事件监听（设备状态、会话进度、ASR 识别结果）
+    let mqtt_listener = if app_state.config.features.websocket_enabled {
+        Some(live_events::spawn_mqtt_event_listener(
+            &config.mqtt,
+            websocket_tx,
+            app_state.mqtt_connected.clone(),
+            app_state.shutdown.clone(),
+        ))
+    } else {
+        None
+    };
+
+    // Home Assistant MQTT discovery 集成（可选）
+    if config.home_assistant.enabled {
+        home_assistant::spawn_home_assistant_integration(app_state.clone(), &config.mqtt, config.home_assistant.clone());
+    }
+
+    // 以下后台任务只有 PostgreSQL 实现，SQLite 存储下不启动
+    let postgres = app_state.database.is_postgres();
+    if !postgres {
+        let unsupported: Vec<&str> = [
+            ("webhooks", config.webhooks.enabled),
+            ("notifications", config.notifications.enabled),
+            ("announcements", config.announcements.enabled),
+            ("redis.jobs", config.redis.jobs.enabled),
+            ("database.partitions", config.database.partitions.enabled),
+            ("database.backup", config.database.backup.enabled),
+            ("session rollups", app_state.config.features.session_rollups),
+            ("session summaries", app_state.config.features.session_summaries),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+        if !unsupported.is_empty() {
+            warn!("SQLite storage does not support {}; these features are disabled", unsupported.join(", "));
+        }
+    }
+
+    // Webhook 投递（可选）
+    if postgres && config.webhooks.enabled {
+        webhooks::spawn_webhook_dispatcher(app_state.clone(), config.webhooks.clone());
+    }
+
+    // 邮件 / Slack / Telegram 通知（可选）
+    if postgres && config.notifications.enabled {
+        notifications::spawn_notification_dispatcher(app_state.clone(), config.notifications.clone());
+    }
+
+    // 定时语音播报（可选）
+    if postgres && config.announcements.enabled {
+        announcements::parse_timezone(&config.announcements.default_timezone)
+            .map_err(|e| anyhow::anyhow!("Invalid announcements.default_timezone: {}", e))?;
+        announcements::spawn_announcement_scheduler(app_state.clone(), config.announcements.clone());
+    }
+
+    // 执行其他服务发布的缓存失效通知
+    cache_invalidation::spawn_invalidation_listener(app_state.cache.clone(), app_state.shutdown.clone());
+
+    // Redis 降级期间定期探测，恢复后重新使用缓存
+    cache::spawn_degradation_probe(app_state.cache.clone(), app_state.shutdown.clone());
+
+    // 处理 Redis Streams 任务队列中的后处理任务
+    if postgres && config.redis.jobs.enabled {
+        match job_worker::default_handlers(app_state.database.clone(), app_state.config.features.session_summaries) {
+            Ok(handlers) => job_worker::spawn_job_worker(app_state.cache.clone(), handlers, app_state.shutdown.clone()),
+            Err(e) => tracing::error!("Failed to create job handlers, job worker disabled: {}", e),
+        }
+    }
+
+    // 采样获取数据库连接的等待时间
+    if let Ok(pool_monitor) = app_state.database.pool_monitor(&config.database.pool) {
+        let shutdown = app_state.shutdown.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(pool_monitor.interval());
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.wait() => break,
+                }
+                pool_monitor.sample().await;
+            }
+        });
+    }
+
+    // 会话表分区维护
+    if postgres && config.database.partitions.enabled {
+        partition_job::spawn_partition_maintenance_job(
+            app_state.database.clone(),
+            config.database.partitions.clone(),
+            app_state.shutdown.clone(),
+        );
+    }
+
+    // 每日数据库备份
+    if postgres && config.database.backup.enabled {
+        backup_job::spawn_backup_job(
+            app_state.database.clone(),
+            config.database.url.clone(),
+            config.database.backup.clone(),
+            app_state.shutdown.clone(),
+        );
+    }
+
+    // 启动会话统计汇总任务（可选）
+    if postgres && app_state.config.features.session_rollups {
+        rollup_job::spawn_session_rollup_job(app_state.database.clone(), app_state.shutdown.clone());
+    }
+
+    // 启动会话摘要任务（可选）
+    if postgres && app_state.config.features.session_summaries {
+        summary_job::spawn_session_summary_job(app_state.database.clone(), app_state.shutdown.clone());
+    }
+
+    // 创建 API v1 路由组合（需要认证）
+    let api_v1_routes = Router::new()
+        .nest("/auth", auth_routes())
+        .nest("/devices", device_routes())
+        .nest("/users", user_routes())
+        .nest("/sessions", session_routes())
+        .nest("/echokit-servers", echokit_server_routes())
+        .nest("/webhooks", webhook_routes())
+        .nest("/notifications", notification_routes())
+        .nest("/announcements", announcement_routes())
+        .layer(axum::middleware::from_fn(auth_middleware))
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), rate_limit_middleware));
+
+    // 管理接口（需要管理员身份）
+    let admin_routes = admin_routes()
+        .layer(axum::middleware::from_fn(auth_middleware));
+
+    let security_headers = std::sync::Arc::new(
+        echo_shared::SecurityHeaders::new(&config.security_headers).with_tls(config.server.tls.enabled),
+    );
+
+    let app = Router::new()
+        // 健康检查路由（无需认证）
+        .nest("/health", health_routes())
+
+        // Prometheus 指标（无需认证）
+        .route("/metrics", get(telemetry::metrics_handler))
+
+        // WebSocket 路由（无需认证）
+        .route("/ws", get(websocket_handler))
+
+        // API v1 路由（需要认证）
+        .nest("/api/v1", api_v1_routes)
+
+        // 管理接口（配置热加载、审计日志、备份记录、加密主密钥轮换、IP 过滤规则）
+        .nest("/admin", admin_routes)
+
+        .with_state(app_state.clone())
+        .layer(DefaultBodyLimit::max(config.limits.max_http_body_bytes))
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), body_limit_middleware))
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+        .layer(axum::middleware::from_fn(request_logging))
+        .layer(axum::middleware::from_fn_with_state(security_headers, security_headers_middleware))
+        // 最外层，在鉴权和其他检查之前拒绝被过滤的来源地址
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), ip_filter::ip_filter_middleware));
+
+    // 启动服务器
+    let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port).parse()?;
+    let shutdown = app_state.shutdown.clone();
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let mut server = if config.server.tls.enabled {
+        let tls_config = tls::rustls_config(&config.server.tls).await?;
+        info!("API Gateway listening on {} (TLS)", addr);
+        // 收到关闭信号后停止接受新连接，进行中的请求由下方的排空超时兜底
+        let handle = axum_server::Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            shutdown.wait().await;
+            shutdown_handle.graceful_shutdown(None);
+        });
+        tokio::spawn(async move { axum_server::bind_rustls(addr, tls_config).handle(handle).serve(service).await })
+    } else {
+        info!("API Gateway listening on {}", addr);
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tokio::spawn(async move {
+            axum::serve(listener, service)
+                .with_graceful_shutdown(async move { shutdown.wait().await })
+                .await
+        })
+    };
+
+    // 等待退出信号；服务异常退出时直接返回错误
+    tokio::select! {
+        result = &mut server => {
+            result??;
+            return Ok(());
+        }
+        _ = shutdown::wait_for_signal() => {}
+    }
+
+    // 停止接受新连接，通知 WebSocket/SSE 长连接退出，并等待进行中的请求完成
+    let drain_timeout = std::time::Duration::from_secs(config.server.shutdown_timeout_secs);
+    info!("Shutting down, draining connections (timeout: {:?})", drain_timeout);
+    app_state.shutdown.trigger();
+
+    let drained = tokio::time::timeout(drain_timeout, async {
+        let result = (&mut server).await;
+        app_state.shutdown.drained().await;
+        result
+    })
+    .await;
+
+    match drained {
+        Ok(result) => {
+            result??;
+            info!("All connections drained");
+        }
+        Err(_) => {
+            warn!(
+                "Drain timeout elapsed with {} long-lived connections still open, closing them",
+                app_state.shutdown.active_connections()
+            );
+            server.abort();
+        }
+    }
+
+    // 关闭外部连接（Redis 连接按请求创建，无需显式关闭）
+    if let Some(mqtt_listener) = mqtt_listener {
+        if let Err(e) = mqtt_listener.await {
+            warn!("Live event listener did not stop cleanly: {}", e);
+        }
+    }
+    #[cfg(feature = "grpc")]
+    if let Err(e) = grpc_server.await {
+        warn!("Internal gRPC server did not stop cleanly: {}", e);
+    }
+    // 单进程模式下存储由 etch-all 持有
+    if in_process.is_none() {
+        app_state.database.close().await;
+    }
+    info!("API Gateway stopped");
+
+    Ok(())
+}
+
+// 简单的健康检查端点
+async fn health_check_simple() -> axum::response::Json<serde_json::Value> {
+    axum::response::Json(json!({
+        "status": "healthy",
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "service": "echo-api-gateway",
+        "version": "0.1.0-simplified"
+    }))
+}
+
+// 注释掉复杂的 app 创建函数
+/*
+async fn create_app(config: AppConfig, app_state: Arc<AppState>) -> Result<Router> {
+    // 创建中间件层
+    let middleware_layer = ServiceBuilder::new()
+        .layer(TraceLayer::new_for_http())
+        .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+        .layer(axum_middleware::from_fn(request_logging))
+        .into_inner();
+
+    // 创建 API v1 路由组合
+    let api_v1_routes = Router::new()
+        .nest("/auth", auth_routes())
+        .nest("/health", health_routes())
+        .nest("/devices", device_routes())
+        .nest("/sessions", session_routes())
+        .nest("/mqtt", mqtt_routes());
+
+    // 创建主路由
+    let app = Router::new()
+        // 健康检查路由（无需认证）
+        .route("/health", get(handlers::health::health_check))
+
+        // API v1 路由（需要认证）
+        .nest("/api/v1", api_v1_routes)
+
+        // WebSocket 路由
+        .route("/ws", get(websocket::websocket_handler))
+
+        // 应用中间件
+        .layer(middleware_layer)
+
+        // 添加状态
+        .with_state(app_state.as_ref().clone());
+
+    Ok(app)
+}
+*/
\ No newline at end of file
@@ -0,0 +1,156 @@
+// 实时事件监听：订阅 MQTT 设备状态与会话事件，转发到 WebSocket 广播通道
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use echo_shared::{DeviceStatus, EventBus, MqttConfig, MqttPayload, TopicFilter, WebSocketMessage};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, QoS};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use crate::shutdown::Shutdown;
+
+/// 断线后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 关闭时等待 DISCONNECT 报文发出的最长时间
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 启动 MQTT 事件监听任务
+///
+/// 连接状态写入 `connected`（供就绪检查使用），收到关闭信号后向 broker 发送 DISCONNECT 并退出
+pub fn spawn_mqtt_event_listener(
+    config: &MqttConfig,
+    events: broadcast::Sender<WebSocketMessage>,
+    connected: Arc<AtomicBool>,
+    shutdown: Shutdown,
+) -> JoinHandle<()> {
+    let mut options = MqttOptions::new(
+        config.client_id_or("api-gateway-events"),
+        config.broker.clone(),
+        config.port,
+    );
+    options.set_keep_alive(Duration::from_secs(config.keep_alive_secs));
+    options.set_clean_session(config.clean_session);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        options.set_credentials(username.clone(), password.clone());
+    }
+
+    let (client, mut event_loop) = AsyncClient::new(options, 10);
+    info!("Live event listener connecting to MQTT broker {}:{}", config.broker, config.port);
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = event_loop.poll() => event,
+                _ = shutdown.wait() => {
+                    connected.store(false, Ordering::Relaxed);
+                    info!("Disconnecting live event listener from MQTT broker");
+                    if client.disconnect().await.is_ok() {
+                        // 继续驱动事件循环，直到 DISCONNECT 报文发出
+                        let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, async {
+                            loop {
+                                match event_loop.poll().await {
+                                    Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_) => break,
+                                    Ok(_) => {}
+                                }
+                            }
+                        })
+                        .await;
+                    }
+                    break;
+                }
+            };
+
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    connected.store(true, Ordering::Relaxed);
+                    info!("Live event listener connected to MQTT broker");
+                    // clean session 模式下每次重连都需要重新订阅
+                    for filter in [TopicFilter::all_device_status(), TopicFilter::all_device_session()] {
+                        if let Err(e) = client.subscribe(filter.topic_pattern.as_str(), QoS::AtMostOnce).await {
+                            warn!("Failed to subscribe to {}: {}", filter.topic_pattern, e);
+                        }
+                    }
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let payload = match serde_json::from_slice::<MqttPayload>(&publish.payload) {
+                        Ok(payload) => payload,
+                        Err(e) => {
+                            debug!("Ignoring unparseable MQTT message on {}: {}", publish.topic, e);
+                            continue;
+                        }
+                    };
+
+                    // 没有 WebSocket 订阅者时发送失败属于正常情况
+                    if let Some(message) = to_websocket_message(payload) {
+                        let _ = events.send(message);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    connected.store(false, Ordering::Relaxed);
+                    warn!("MQTT event listener connection error: {}, retrying in {:?}", e, RECONNECT_INTERVAL);
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                }
+            }
+        }
+    })
+}
+
+/// 启动进程内事件监听任务（单进程模式），把 Bridge 发布到事件总线的实时事件转发到 WebSocket 广播通道
+pub fn spawn_local_event_listener(
+    bus: &EventBus,
+    events: broadcast::Sender<WebSocketMessage>,
+    shutdown: Shutdown,
+) -> JoinHandle<()> {
+    let mut receiver = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let payload = tokio::select! {
+                payload = receiver.recv() => payload,
+                _ = shutdown.wait() => break,
+            };
+
+            match payload {
+                Ok(payload) => {
+                    if let Some(message) = to_websocket_message(payload) {
+                        let _ = events.send(message);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Local event listener lagged behind, {} events dropped", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// 将 MQTT 消息转换为推送给前端的 WebSocket 消息
+pub fn to_websocket_message(payload: MqttPayload) -> Option<WebSocketMessage> {
+    match payload {
+        MqttPayload::DeviceStatus { device_id, status, last_seen, .. } => {
+            Some(WebSocketMessage::DeviceStatusUpdate {
+                device_id,
+                status,
+                timestamp: last_seen,
+            })
+        }
+        MqttPayload::SessionEvent { event, .. } => match event {
+            // 音频数据量大且前端不需要，不转发
+            WebSocketMessage::EchoKitAudioData { .. } => None,
+            event => Some(event),
+        },
+        _ => None,
+    }
+}
+
+/// 记录设备的最新状态，返回设备是否从已知的非离线状态变为离线
+///
+/// 设备状态是周期性重发的 retain 消息，重新订阅时会收到所有设备的当前状态，
+/// 因此首次收到的离线状态不视为下线
+pub fn device_went_offline(known: &mut HashMap<String, DeviceStatus>, device_id: &str, status: &DeviceStatus) -> bool {
+    let previous = known.insert(device_id.to_string(), status.clone());
+    *status == DeviceStatus::Offline && previous.is_some_and(|previous| previous != DeviceStatus::Offline)
+}
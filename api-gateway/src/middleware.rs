@@ -1,12 +1,16 @@
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use echo_shared::{EchoError, SecurityHeaders};
 use tracing::{info, warn, error};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use crate::app_state::AppState;
+use crate::error::ApiError;
+use crate::telemetry;
 
 pub async fn request_logging(
     req: Request,
@@ -15,6 +19,12 @@ pub async fn request_logging(
     let start = Instant::now();
     let method = req.method().clone();
     let uri = req.uri().clone();
+    // 按路由模板聚合耗时，避免路径中的 ID 导致标签膨胀
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
 
     info!("Incoming request: {} {}", method, uri);
 
@@ -22,8 +32,13 @@ pub async fn request_logging(
     let status = response.status();
     let duration = start.elapsed();
 
-    // TODO: 在实际应用中更新统计信息
-    // 由于Axum中间件签名限制，暂时跳过统计更新
+    metrics::histogram!(
+        telemetry::HTTP_REQUEST_DURATION,
+        "method" => method.to_string(),
+        "path" => path,
+        "status" => status.as_u16().to_string()
+    )
+    .record(duration.as_secs_f64());
 
     if status.is_success() {
         info!("Request completed: {} {} - {}ms", method, uri, duration.as_millis());
@@ -39,7 +54,7 @@ pub async fn request_logging(
 pub async fn auth_middleware(
     req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     // Skip auth in test mode
     if std::env::var("RUST_ENV").unwrap_or_default() == "test" {
         info!("Test mode enabled - bypassing authentication");
@@ -73,23 +88,99 @@ pub async fn auth_middleware(
         return Ok(next.run(req).await);
     }
 
-    Err(StatusCode::UNAUTHORIZED)
+    // 浏览器的 Cookie 会话，修改状态的请求同时校验 CSRF 令牌
+    if crate::cookie_auth::session_token(req.headers()).is_some() {
+        crate::handlers::auth::authenticate_request(req.method(), req.headers())?;
+        return Ok(next.run(req).await);
+    }
+
+    Err(ApiError::unauthorized("Missing bearer token"))
 }
 
-pub async fn rate_limit_middleware(
+/// 补充安全响应头（HSTS、nosniff、frame-ancestors 等），处理函数已设置的头不覆盖
+pub async fn security_headers_middleware(
+    State(security_headers): State<Arc<SecurityHeaders>>,
     req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    // 简单的速率限制实现
-    // 实际生产环境中应该使用更复杂的限流算法
-    let client_ip = req
+) -> Response {
+    let forwarded_https = req
         .headers()
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("https"));
+    let mut response = next.run(req).await;
+    for (name, value) in security_headers.headers(forwarded_https) {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response.headers_mut().entry(name).or_insert(value);
+        }
+    }
+    response
+}
+
+/// 从 X-Forwarded-For（取第一个地址）或 X-Real-IP 中获取客户端 IP
+pub fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
         .get("x-forwarded-for")
         .and_then(|h| h.to_str().ok())
-        .unwrap_or("unknown");
+        .and_then(|v| v.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|h| h.to_str().ok()))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// 拒绝超过 limits.max_http_body_bytes 的请求体并计数
+///
+/// 声明了 Content-Length 的请求在读取请求体之前直接拒绝；未声明长度的请求体由 `DefaultBodyLimit`
+/// 在读取时拒绝，这里按 413 响应计数。
+pub async fn body_limit_middleware(
+    State(app_state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let max_bytes = app_state.config.limits.max_http_body_bytes;
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if let Some(length) = declared.filter(|&length| length > max_bytes as u64) {
+        warn!("Rejected {} {}: body of {} bytes exceeds {} bytes", req.method(), req.uri().path(), length, max_bytes);
+        telemetry::record_ingress_rejected("http");
+        return ApiError::from(EchoError::PayloadTooLarge(format!(
+            "Request body must not exceed {} bytes",
+            max_bytes
+        )))
+        .into_response();
+    }
 
-    // TODO: 实现 Redis 基础的速率限制
-    info!("Rate limiting check for IP: {}", client_ip);
+    let response = next.run(req).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        telemetry::record_ingress_rejected("http");
+    }
+    response
+}
 
-    Ok(next.run(req).await)
-}
\ No newline at end of file
+/// 按客户端 IP 限流，超出限额时返回 429 并附带 Retry-After
+pub async fn rate_limit_middleware(
+    State(app_state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client_ip = client_ip(req.headers()).unwrap_or_else(|| "unknown".to_string());
+
+    match app_state.rate_limiter.check(&client_ip) {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            warn!("Rate limit exceeded for IP: {}", client_ip);
+            let mut response = ApiError::from(EchoError::RateLimited(format!(
+                "Too many requests, retry after {} seconds",
+                retry_after.as_secs().max(1)
+            )))
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+            response
+        }
+    }
+}
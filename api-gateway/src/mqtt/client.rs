@@ -24,9 +24,9 @@ pub struct ApiGatewayMqttClient {
 impl ApiGatewayMqttClient {
     pub fn new(config: MqttConfig, websocket_broadcaster: broadcast::Sender<WebSocketMessage>) -> Result<Self> {
         let mut mqtt_options = rumqttc::MqttOptions::new(
-            config.client_id.clone(),
-            &config.broker_host,
-            config.broker_port,
+            config.client_id_or("api-gateway"),
+            &config.broker,
+            config.port,
         );
 
         // 设置认证信息
@@ -35,7 +35,7 @@ impl ApiGatewayMqttClient {
         }
 
         // 设置保持连接
-        mqtt_options.set_keep_alive(config.keep_alive);
+        mqtt_options.set_keep_alive(std::time::Duration::from_secs(config.keep_alive_secs));
 
         // 设置清理会话
         mqtt_options.set_clean_session(config.clean_session);
@@ -233,17 +233,14 @@ impl ApiGatewayMqttClient {
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-            let mut uptime_seconds = 0u64;
-
             loop {
                 interval.tick().await;
-                uptime_seconds += 30;
 
                 let heartbeat_message = echo_shared::MqttMessageBuilder::system_heartbeat(
                     service_name.clone(),
                     instance_id.clone(),
                     ServiceStatus::Healthy,
-                    uptime_seconds,
+                    echo_shared::process_uptime_seconds(),
                 );
 
                 if let Err(e) = Self::publish_heartbeat(&client, heartbeat_message).await {
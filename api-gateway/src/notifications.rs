@@ -0,0 +1,431 @@
+// 通知：按接收地址（notification_destinations）的订阅发送每日会话摘要、设备离线提醒和会话失败通知，
+// 消息经 AppState 的发送者按通道投递（SMTP 邮件、Slack incoming webhook、Telegram 机器人）
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Timelike, Utc};
+use echo_shared::{DeliveryChannel, DeviceId, NotificationConfig, OutboundMessage, WebSocketMessage};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use crate::app_state::AppState;
+use crate::database::{DeviceDigest, FailedSession, NotificationDestination};
+use crate::live_events;
+use crate::telemetry;
+use crate::webhooks;
+
+/// 不是领导者时重新检查领导权的间隔
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 每次检查最多读取的失败会话数
+const FAILED_SESSION_BATCH: u32 = 200;
+
+/// 一条会话失败通知中最多列出的会话数
+const MAX_FAILED_SESSIONS_PER_MESSAGE: usize = 10;
+
+/// 每日摘要中最多列出的设备数
+const MAX_DIGEST_DEVICES: usize = 20;
+
+/// 可订阅的通知类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    /// 每日会话摘要
+    DailyDigest,
+    /// 设备从在线变为离线
+    DeviceOffline,
+    /// 会话失败或超时
+    SessionFailed,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 3] =
+        [NotificationKind::DailyDigest, NotificationKind::DeviceOffline, NotificationKind::SessionFailed];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::DailyDigest => "daily_digest",
+            NotificationKind::DeviceOffline => "device_offline",
+            NotificationKind::SessionFailed => "session_failed",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// 接收地址的通道名对应的投递通道
+pub fn delivery_channel(channel: &str) -> Option<DeliveryChannel> {
+    match channel {
+        "email" => Some(DeliveryChannel::Email),
+        "slack" => Some(DeliveryChannel::Slack),
+        "telegram" => Some(DeliveryChannel::Telegram),
+        _ => None,
+    }
+}
+
+/// 检查订阅的通知类型，返回去重后的列表
+pub fn validate_notifications(notifications: &[String]) -> Result<Vec<String>, String> {
+    if notifications.is_empty() {
+        return Err("At least one notification type is required".to_string());
+    }
+    let mut validated: Vec<String> = Vec::new();
+    for name in notifications {
+        let kind = NotificationKind::parse(name.trim()).ok_or_else(|| {
+            let supported: Vec<&str> = NotificationKind::ALL.iter().map(|kind| kind.as_str()).collect();
+            format!("Unknown notification type {:?}, supported types: {}", name, supported.join(", "))
+        })?;
+        if !validated.iter().any(|existing| existing == kind.as_str()) {
+            validated.push(kind.as_str().to_string());
+        }
+    }
+    Ok(validated)
+}
+
+/// 检查接收地址：邮件地址、Slack incoming webhook 地址（https）或 Telegram chat ID / @频道名
+pub fn validate_target(channel: DeliveryChannel, target: &str) -> Result<(), String> {
+    match channel {
+        DeliveryChannel::Email => target
+            .parse::<lettre::Address>()
+            .map(|_| ())
+            .map_err(|e| format!("Invalid email address: {}", e)),
+        DeliveryChannel::Slack => webhooks::validate_url(target, false),
+        DeliveryChannel::Telegram => {
+            let chat_id = target.strip_prefix('-').unwrap_or(target);
+            let numeric = !chat_id.is_empty() && chat_id.chars().all(|c| c.is_ascii_digit());
+            let username = target.strip_prefix('@').is_some_and(|name| {
+                name.len() >= 5 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if numeric || username {
+                Ok(())
+            } else {
+                Err("Telegram target must be a chat ID or an @channel username".to_string())
+            }
+        }
+        DeliveryChannel::Sms => Err("SMS notifications are not supported".to_string()),
+    }
+}
+
+fn device_label(name: Option<&str>, device_id: &str) -> String {
+    match name {
+        Some(name) if !name.is_empty() && name != device_id => format!("{}（{}）", name, device_id),
+        _ => device_id.to_string(),
+    }
+}
+
+/// 会话失败通知
+fn failed_sessions_message(sessions: &[&FailedSession]) -> (String, String) {
+    let subject = match sessions {
+        [session] => format!("Echo 会话失败：{}", device_label(session.device_name.as_deref(), &session.device_id)),
+        _ => format!("Echo 会话失败：{} 个会话", sessions.len()),
+    };
+    let mut lines: Vec<String> = sessions
+        .iter()
+        .take(MAX_FAILED_SESSIONS_PER_MESSAGE)
+        .map(|session| {
+            let outcome = if session.status == "timeout" { "超时" } else { "失败" };
+            format!(
+                "- 设备 {} 的会话 {} 于 {} {}",
+                device_label(session.device_name.as_deref(), &session.device_id),
+                session.id,
+                session.end_time.format("%Y-%m-%d %H:%M:%S UTC"),
+                outcome
+            )
+        })
+        .collect();
+    if sessions.len() > MAX_FAILED_SESSIONS_PER_MESSAGE {
+        lines.push(format!("……另有 {} 个会话", sessions.len() - MAX_FAILED_SESSIONS_PER_MESSAGE));
+    }
+    (subject, lines.join("\n"))
+}
+
+/// 每日会话摘要
+fn digest_message(day: chrono::NaiveDate, devices: &[DeviceDigest]) -> (String, String) {
+    let total: i64 = devices.iter().map(|device| device.sessions).sum();
+    let failed: i64 = devices.iter().map(|device| device.failed_sessions).sum();
+    let offline = devices.iter().filter(|device| device.device_status == "offline").count();
+
+    let mut lines = vec![format!(
+        "过去 24 小时共 {} 次会话，失败 {} 次；{} 台设备中 {} 台离线。",
+        total,
+        failed,
+        devices.len(),
+        offline
+    )];
+    let active: Vec<&DeviceDigest> = devices.iter().filter(|device| device.sessions > 0).collect();
+    if !active.is_empty() {
+        lines.push(String::new());
+        lines.push("设备会话：".to_string());
+        for device in active.iter().take(MAX_DIGEST_DEVICES) {
+            lines.push(format!(
+                "- {}：{} 次会话，失败 {} 次",
+                device_label(Some(&device.device_name), &device.device_id),
+                device.sessions,
+                device.failed_sessions
+            ));
+        }
+        if active.len() > MAX_DIGEST_DEVICES {
+            lines.push(format!("……另有 {} 台设备", active.len() - MAX_DIGEST_DEVICES));
+        }
+    }
+    (format!("Echo 每日会话摘要（{}）", day), lines.join("\n"))
+}
+
+/// 启动通知任务
+///
+/// 多副本部署时只有领导者副本发送；失败会话从成为领导者时开始检查，每日摘要按接收地址记录的发送日期补发
+pub fn spawn_notification_dispatcher(app_state: AppState, config: NotificationConfig) {
+    tokio::spawn(async move {
+        info!(
+            "Notification dispatcher started (digest at {:02}:00 UTC, sender: {})",
+            config.digest_hour_utc,
+            app_state.message_sender.name()
+        );
+
+        let leader = app_state.database.leader_election("notifications");
+        let shutdown = app_state.shutdown.clone();
+        loop {
+            if leader.is_leader().await {
+                let dispatcher = Dispatcher {
+                    app_state: app_state.clone(),
+                    config: config.clone(),
+                    device_status: HashMap::new(),
+                    offline_alerts: HashMap::new(),
+                };
+                if let Exit::Shutdown = dispatcher.run(&leader).await {
+                    break;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(LEADER_RETRY_INTERVAL) => {}
+                _ = shutdown.wait() => break,
+            }
+        }
+
+        leader.release().await;
+        info!("Notification dispatcher stopped");
+    });
+}
+
+enum Exit {
+    Shutdown,
+    LostLeadership,
+}
+
+struct Dispatcher {
+    app_state: AppState,
+    config: NotificationConfig,
+    /// 设备的最新状态，用于判断设备下线
+    device_status: HashMap<String, echo_shared::DeviceStatus>,
+    /// 设备最近一次离线提醒的时间
+    offline_alerts: HashMap<String, Instant>,
+}
+
+impl Dispatcher {
+    async fn run(mut self, leader: &echo_shared::LeaderElection) -> Exit {
+        let mut events = self.app_state.events.subscribe();
+        let mut check = tokio::time::interval(Duration::from_secs(self.config.check_interval_seconds));
+        check.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut failed_since = Utc::now();
+
+        loop {
+            tokio::select! {
+                message = events.recv() => match message {
+                    Ok(WebSocketMessage::DeviceStatusUpdate { device_id, status, timestamp }) => {
+                        if live_events::device_went_offline(&mut self.device_status, &device_id, &status) {
+                            self.notify_device_offline(&device_id, timestamp).await;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Notification dispatcher lagged behind, {} events dropped", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Exit::Shutdown,
+                },
+                _ = check.tick() => {
+                    if !leader.is_leader().await {
+                        return Exit::LostLeadership;
+                    }
+                    failed_since = self.notify_failed_sessions(failed_since).await;
+                    let now = Utc::now();
+                    if now.hour() >= self.config.digest_hour_utc {
+                        self.send_digests(now).await;
+                    }
+                }
+                _ = self.app_state.shutdown.wait() => return Exit::Shutdown,
+            }
+        }
+    }
+
+    async fn notify_device_offline(&mut self, device_id: &str, last_seen: DateTime<Utc>) {
+        let cooldown = Duration::from_secs(self.config.offline_alert_cooldown_seconds);
+        if self.offline_alerts.get(device_id).is_some_and(|alerted| alerted.elapsed() < cooldown) {
+            debug!("Skipping offline notification for device {} during cooldown", device_id);
+            return;
+        }
+        self.offline_alerts.insert(device_id.to_string(), Instant::now());
+
+        let kind = NotificationKind::DeviceOffline;
+        let destinations = match self.app_state.database.notification_destinations_for_device(kind.as_str(), device_id).await {
+            Ok(destinations) => destinations,
+            Err(e) => {
+                warn!("Failed to load notification destinations for device {}: {}", device_id, e);
+                return;
+            }
+        };
+        if destinations.is_empty() {
+            return;
+        }
+
+        let name = match self.app_state.database.get_device_by_id(&DeviceId::new(device_id)).await {
+            Ok(device) => device.map(|device| device.name),
+            Err(e) => {
+                debug!("Failed to load device {} for offline notification: {}", device_id, e);
+                None
+            }
+        };
+        let label = device_label(name.as_deref(), device_id);
+        let subject = format!("Echo 设备离线：{}", label);
+        let body = format!("设备 {} 于 {} 离线。", label, last_seen.format("%Y-%m-%d %H:%M:%S UTC"));
+        for destination in &destinations {
+            self.send(destination, kind, &subject, &body).await;
+        }
+    }
+
+    /// 通知 `since` 之后结束的失败会话，同一接收地址的多个会话合并为一条消息；返回下一次检查的起点
+    async fn notify_failed_sessions(&self, since: DateTime<Utc>) -> DateTime<Utc> {
+        let sessions = match self.app_state.database.failed_sessions_since(since, FAILED_SESSION_BATCH).await {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                warn!("Failed to load failed sessions for notifications: {}", e);
+                return since;
+            }
+        };
+        let Some(last) = sessions.last() else {
+            return since;
+        };
+        let next_since = last.end_time;
+
+        let kind = NotificationKind::SessionFailed;
+        let mut device_destinations: HashMap<&str, Vec<NotificationDestination>> = HashMap::new();
+        let mut pending: HashMap<i64, (NotificationDestination, Vec<&FailedSession>)> = HashMap::new();
+        for session in &sessions {
+            if !device_destinations.contains_key(session.device_id.as_str()) {
+                let destinations = self
+                    .app_state
+                    .database
+                    .notification_destinations_for_device(kind.as_str(), &session.device_id)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to load notification destinations for device {}: {}", session.device_id, e);
+                        Vec::new()
+                    });
+                device_destinations.insert(&session.device_id, destinations);
+            }
+            for destination in &device_destinations[session.device_id.as_str()] {
+                pending
+                    .entry(destination.id)
+                    .or_insert_with(|| (destination.clone(), Vec::new()))
+                    .1
+                    .push(session);
+            }
+        }
+
+        for (destination, sessions) in pending.values() {
+            let (subject, body) = failed_sessions_message(sessions);
+            self.send(destination, kind, &subject, &body).await;
+        }
+        next_since
+    }
+
+    /// 发送当天尚未发送的每日摘要
+    async fn send_digests(&self, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        let destinations = match self.app_state.database.notification_destinations_due_for_digest(today).await {
+            Ok(destinations) => destinations,
+            Err(e) => {
+                warn!("Failed to load notification destinations for daily digest: {}", e);
+                return;
+            }
+        };
+
+        let since = now - chrono::Duration::hours(24);
+        for destination in &destinations {
+            let user_id = (!destination.all_devices).then_some(destination.user_id.as_str());
+            let devices = match self.app_state.database.device_session_digest(user_id, since).await {
+                Ok(devices) => devices,
+                Err(e) => {
+                    warn!("Failed to build daily digest for notification destination {}: {}", destination.id, e);
+                    continue;
+                }
+            };
+            // 没有设备时不发送，当天不再重试
+            if !devices.is_empty() {
+                let (subject, body) = digest_message(today, &devices);
+                if !self.send(destination, NotificationKind::DailyDigest, &subject, &body).await {
+                    continue;
+                }
+            }
+            if let Err(e) = self.app_state.database.mark_notification_digest_sent(destination.id, today).await {
+                warn!("Failed to record daily digest for notification destination {}: {}", destination.id, e);
+            }
+        }
+    }
+
+    /// 发送一条通知并记录结果，返回是否发送成功
+    async fn send(&self, destination: &NotificationDestination, kind: NotificationKind, subject: &str, body: &str) -> bool {
+        let Some(channel) = delivery_channel(&destination.channel) else {
+            warn!("Notification destination {} has unknown channel {}", destination.id, destination.channel);
+            return false;
+        };
+        let message = OutboundMessage::new(channel, destination.target.as_str(), subject, body);
+        let error = self.app_state.message_sender.send(&message).await.err().map(|e| e.to_string());
+        match &error {
+            None => {
+                debug!("Sent {} notification to destination {}", kind.as_str(), destination.id);
+                telemetry::record_notification(kind.as_str(), &destination.channel, "sent");
+            }
+            Some(e) => {
+                warn!("Failed to send {} notification to destination {}: {}", kind.as_str(), destination.id, e);
+                telemetry::record_notification(kind.as_str(), &destination.channel, "failed");
+            }
+        }
+        if let Err(e) = self.app_state.database.record_notification_result(destination.id, error.as_deref()).await {
+            warn!("Failed to record notification result for destination {}: {}", destination.id, e);
+        }
+        error.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_targets_per_channel() {
+        assert!(validate_target(DeliveryChannel::Email, "ops@example.com").is_ok());
+        assert!(validate_target(DeliveryChannel::Email, "not-an-address").is_err());
+        assert!(validate_target(DeliveryChannel::Slack, "https://hooks.slack.com/services/T/B/X").is_ok());
+        assert!(validate_target(DeliveryChannel::Slack, "http://hooks.slack.com/services/T/B/X").is_err());
+        assert!(validate_target(DeliveryChannel::Telegram, "-1001234567890").is_ok());
+        assert!(validate_target(DeliveryChannel::Telegram, "@etch_alerts").is_ok());
+        assert!(validate_target(DeliveryChannel::Telegram, "@ab").is_err());
+        assert!(validate_target(DeliveryChannel::Telegram, "-").is_err());
+    }
+
+    #[test]
+    fn digest_lists_devices_with_sessions() {
+        let device = |id: &str, status: &str, sessions, failed_sessions| DeviceDigest {
+            device_id: id.to_string(),
+            device_name: format!("{} speaker", id),
+            device_status: status.to_string(),
+            sessions,
+            failed_sessions,
+        };
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 12, 1).unwrap();
+        let (subject, body) = digest_message(day, &[device("a", "online", 3, 1), device("b", "offline", 0, 0)]);
+
+        assert_eq!(subject, "Echo 每日会话摘要（2025-12-01）");
+        assert!(body.starts_with("过去 24 小时共 3 次会话，失败 1 次；2 台设备中 1 台离线。"));
+        assert!(body.contains("- a speaker（a）：3 次会话，失败 1 次"));
+        assert!(!body.contains("b speaker"));
+    }
+}
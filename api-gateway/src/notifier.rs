@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::time::Duration;
+use echo_shared::{
+    DeliveryChannel, MessageSender, NotificationConfig, NotifyError, OutboundMessage, RoutingSender, SmtpConfig, SmtpTls,
+    TelegramConfig,
+};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde_json::json;
+use tracing::info;
+
+/// 日志发送者：未配置实际投递通道时使用，只把消息写入日志
+pub struct LogMessageSender;
+
+#[async_trait::async_trait]
+impl MessageSender for LogMessageSender {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn supports(&self, _channel: DeliveryChannel) -> bool {
+        true
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        info!(
+            "Outbound {:?} message to {}: {} - {}",
+            message.channel, message.recipient, message.subject, message.body
+        );
+        Ok(())
+    }
+}
+
+/// 按通知配置创建发送者：配置了 SMTP 服务器时经 SMTP 发送邮件，配置了机器人令牌时发送 Telegram 消息，
+/// Slack 消息直接 POST 到接收地址；其他通道（短信、未配置的邮件）只写入日志
+pub fn message_sender(config: &NotificationConfig) -> anyhow::Result<Arc<dyn MessageSender>> {
+    let timeout = Duration::from_secs(config.request_timeout_seconds);
+    let http = reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    let mut sender = RoutingSender::new().with_sender(Arc::new(SlackSender { http: http.clone() }));
+    if !config.smtp.host.is_empty() {
+        sender = sender.with_sender(Arc::new(SmtpEmailSender::new(&config.smtp, timeout)?));
+    }
+    if !config.telegram.bot_token.is_empty() {
+        sender = sender.with_sender(Arc::new(TelegramSender::new(&config.telegram, http)));
+    }
+    Ok(Arc::new(sender.with_fallback(Arc::new(LogMessageSender))))
+}
+
+/// SMTP 邮件发送者
+pub struct SmtpEmailSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpEmailSender {
+    pub fn new(config: &SmtpConfig, timeout: Duration) -> anyhow::Result<Self> {
+        let builder = match config.tls {
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+        };
+        let mut builder = builder.port(config.port).timeout(Some(timeout));
+        if !config.username.is_empty() {
+            builder = builder.credentials(Credentials::new(config.username.clone(), config.password.clone()));
+        }
+        let from = config
+            .from
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid notifications.smtp.from {:?}: {}", config.from, e))?;
+        Ok(Self { transport: builder.build(), from })
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender for SmtpEmailSender {
+    fn name(&self) -> &str {
+        "smtp"
+    }
+
+    fn supports(&self, channel: DeliveryChannel) -> bool {
+        channel == DeliveryChannel::Email
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        let to: Mailbox = message
+            .recipient
+            .parse()
+            .map_err(|_| NotifyError::InvalidRecipient(message.recipient.clone()))?;
+        let email = lettre::Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&message.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| NotifyError::DeliveryFailed(e.to_string()))?;
+        self.transport
+            .send(email)
+            .await
+            .map_err(|e| NotifyError::DeliveryFailed(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Slack 发送者：收件人为 incoming webhook 地址
+pub struct SlackSender {
+    http: reqwest::Client,
+}
+
+#[async_trait::async_trait]
+impl MessageSender for SlackSender {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn supports(&self, channel: DeliveryChannel) -> bool {
+        channel == DeliveryChannel::Slack
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        let text = format!("*{}*\n{}", message.subject, message.body);
+        // 错误信息中不包含 webhook 地址
+        self.http
+            .post(&message.recipient)
+            .json(&json!({ "text": text }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| NotifyError::DeliveryFailed(e.without_url().to_string()))?;
+        Ok(())
+    }
+}
+
+/// Telegram 机器人发送者：收件人为 chat ID（或公开频道的 @用户名）
+pub struct TelegramSender {
+    http: reqwest::Client,
+    send_message_url: String,
+}
+
+impl TelegramSender {
+    pub fn new(config: &TelegramConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            send_message_url: format!("{}/bot{}/sendMessage", config.api_url.trim_end_matches('/'), config.bot_token),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender for TelegramSender {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    fn supports(&self, channel: DeliveryChannel) -> bool {
+        channel == DeliveryChannel::Telegram
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        let text = format!("{}\n\n{}", message.subject, message.body);
+        // 请求地址中包含机器人令牌，错误信息中去掉地址
+        self.http
+            .post(&self.send_message_url)
+            .json(&json!({ "chat_id": message.recipient, "text": text, "disable_web_page_preview": true }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| NotifyError::DeliveryFailed(e.without_url().to_string()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use echo_shared::{plan_partitions, DatabasePartitionConfig};
+use tracing::{info, warn};
+use crate::database::Database;
+use crate::shutdown::Shutdown;
+
+/// 按月分区的会话表（见 database/init/08-session-partitions.sql）
+const SESSIONS_TABLE: &str = "sessions";
+
+/// 启动分区维护后台任务
+///
+/// 每隔 check_interval_seconds 秒为会话表提前创建之后几个月的分区，并删除数据全部早于保留期的分区，
+/// 删除分区时一并清理其中会话的音频文件。多副本部署时只有领导者副本执行维护
+pub fn spawn_partition_maintenance_job(database: Arc<Database>, config: DatabasePartitionConfig, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        info!(
+            "Partition maintenance job started (interval: {}s, months ahead: {}, session retention: {} months)",
+            config.check_interval_seconds, config.months_ahead, config.session_retention_months
+        );
+
+        let leader = database.leader_election("session_partitions");
+        let mut interval = tokio::time::interval(Duration::from_secs(config.check_interval_seconds));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+            if !leader.is_leader().await {
+                continue;
+            }
+
+            maintain_session_partitions(&database, &config).await;
+        }
+
+        leader.release().await;
+    });
+}
+
+async fn maintain_session_partitions(database: &Database, config: &DatabasePartitionConfig) {
+    let existing = match database.list_partitions(SESSIONS_TABLE).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            warn!("Failed to list {} partitions: {}", SESSIONS_TABLE, e);
+            return;
+        }
+    };
+    if existing.is_empty() {
+        warn!("Table {} is not partitioned, skipping partition maintenance", SESSIONS_TABLE);
+        return;
+    }
+
+    let plan = plan_partitions(
+        SESSIONS_TABLE,
+        &existing,
+        Utc::now(),
+        config.months_ahead,
+        config.session_retention_months,
+    );
+
+    for partition in &plan.create {
+        match database.create_partition(SESSIONS_TABLE, partition).await {
+            Ok(()) => info!("Created partition {} ({} to {})", partition.name, partition.from, partition.to),
+            // 默认分区中已有该月数据时无法创建，这些会话留在默认分区中
+            Err(e) => warn!("Failed to create partition {}: {}", partition.name, e),
+        }
+    }
+
+    for name in &plan.drop {
+        let audio_files = match database.drop_session_partition(name).await {
+            Ok(audio_files) => audio_files,
+            Err(e) => {
+                warn!("Failed to drop expired partition {}: {}", name, e);
+                continue;
+            }
+        };
+
+        let mut audio_files_deleted = 0;
+        for path in &audio_files {
+            match tokio::fs::remove_file(path).await {
+                Ok(()) => audio_files_deleted += 1,
+                // 文件已不存在，视为已删除
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => audio_files_deleted += 1,
+                Err(e) => warn!("Failed to delete audio file {}: {}", path, e),
+            }
+        }
+        info!(
+            "Dropped expired partition {} ({}/{} audio files deleted)",
+            name,
+            audio_files_deleted,
+            audio_files.len()
+        );
+    }
+}
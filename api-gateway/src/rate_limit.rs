@@ -0,0 +1,60 @@
+// API 限流：按客户端 IP 在固定的一分钟窗口内计数，限额来自可热加载的 rate_limit 配置
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use echo_shared::RuntimeConfig;
+use tokio::sync::watch;
+
+/// 限流窗口长度
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// 计数表超过该大小时清理过期窗口
+const PRUNE_THRESHOLD: usize = 10_000;
+
+pub struct RateLimiter {
+    config: watch::Receiver<RuntimeConfig>,
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: watch::Receiver<RuntimeConfig>) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 当前是否启用限流
+    pub fn enabled(&self) -> bool {
+        self.config.borrow().rate_limit.enabled
+    }
+
+    /// 记录一次请求，超出限额时返回距离窗口重置的时间
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let limit = {
+            let config = self.config.borrow();
+            if !config.rate_limit.enabled {
+                return Ok(());
+            }
+            config.rate_limit.requests_per_minute
+        };
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        if windows.len() > PRUNE_THRESHOLD {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+
+        let (start, count) = windows.entry(client.to_string()).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count >= limit {
+            return Err(WINDOW.saturating_sub(now.duration_since(*start)));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+// 定时播报的重复规则：cron 表达式或 RRULE（RFC 5545 的常用子集），按计划所在时区计算触发时间
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// 计算下一次触发时间时最多展开的周期数，防止永远不满足的规则陷入长循环
+const MAX_PERIODS: u32 = 100_000;
+
+/// 重复规则
+#[derive(Debug, Clone)]
+pub enum Recurrence {
+    Cron(Box<croner::Cron>),
+    Rule(RRule),
+}
+
+impl Recurrence {
+    /// 解析重复规则：以 `RRULE:` 开头或包含 `FREQ=` 的按 RRULE 解析，其余按 cron 表达式解析
+    ///
+    /// cron 表达式为标准的 5 个字段（分 时 日 月 周，0 和 7 都表示周日），也可在前面加上秒字段
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = expression.trim();
+        if expression.is_empty() {
+            return Err("recurrence must not be empty".to_string());
+        }
+        if expression.starts_with("RRULE:") || expression.contains("FREQ=") {
+            return RRule::parse(expression).map(Recurrence::Rule);
+        }
+
+        croner::Cron::new(expression)
+            .with_seconds_optional()
+            .parse()
+            .map(|cron| Recurrence::Cron(Box::new(cron)))
+            .map_err(|e| format!("invalid cron expression: {}", e))
+    }
+
+    /// `after` 之后（不含）的下一次触发时间；规则从 `starts_at` 开始生效，在时区 `tz` 中计算
+    pub fn next_after(&self, starts_at: DateTime<Utc>, tz: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Cron(cron) => {
+                let from = after.max(starts_at - Duration::seconds(1));
+                cron.find_next_occurrence(&from.with_timezone(&tz), false).ok().map(|next| next.with_timezone(&Utc))
+            }
+            Recurrence::Rule(rule) => rule.next_after(starts_at.with_timezone(&tz), after),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// RRULE 规则，支持 FREQ（HOURLY / DAILY / WEEKLY / MONTHLY）、INTERVAL、BYDAY、BYMONTHDAY、
+/// BYHOUR、BYMINUTE、COUNT 和 UNTIL；未指定的时间部分取开始时间
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub by_month_day: Vec<u32>,
+    pub by_hour: Vec<u32>,
+    pub by_minute: Vec<u32>,
+    pub count: Option<u32>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl RRule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = expression.trim().strip_prefix("RRULE:").unwrap_or(expression.trim());
+        let mut frequency = None;
+        let mut rule = RRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_hour: Vec::new(),
+            by_minute: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in expression.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| format!("invalid RRULE part {:?}", part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => return Err(format!("unsupported RRULE FREQ {:?}", other)),
+                    })
+                }
+                "INTERVAL" => rule.interval = parse_number(key, value, 1, 1000)?,
+                "BYDAY" => rule.by_day = value.split(',').map(parse_weekday).collect::<Result<_, _>>()?,
+                "BYMONTHDAY" => rule.by_month_day = parse_list(key, value, 1, 31)?,
+                "BYHOUR" => rule.by_hour = parse_list(key, value, 0, 23)?,
+                "BYMINUTE" => rule.by_minute = parse_list(key, value, 0, 59)?,
+                "COUNT" => rule.count = Some(parse_number(key, value, 1, u32::MAX)?),
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                other => return Err(format!("unsupported RRULE part {:?}", other)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or("RRULE requires FREQ")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("RRULE must not contain both COUNT and UNTIL".to_string());
+        }
+        Ok(rule)
+    }
+
+    /// `after` 之后（不含）的下一次触发时间
+    pub fn next_after(&self, start: DateTime<Tz>, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let tz = start.timezone();
+        let local_start = start.naive_local();
+        // 有 COUNT 时需要从第一次开始计数，否则直接跳到 after 附近的周期
+        let first_period = match self.count {
+            Some(_) => 0,
+            None => self.period_index(local_start, after.with_timezone(&tz).naive_local()),
+        };
+
+        let mut occurrences = 0;
+        for period in first_period..first_period.saturating_add(MAX_PERIODS) {
+            let candidates = self.period_candidates(local_start, period)?;
+            for candidate in candidates {
+                if candidate < local_start {
+                    continue;
+                }
+                // 夏令时跳过的本地时间不触发
+                let Some(candidate) = tz.from_local_datetime(&candidate).earliest() else {
+                    continue;
+                };
+                let candidate = candidate.with_timezone(&Utc);
+                if self.until.is_some_and(|until| candidate > until) {
+                    return None;
+                }
+                occurrences += 1;
+                if self.count.is_some_and(|count| occurrences > count) {
+                    return None;
+                }
+                if candidate > after {
+                    return Some(candidate);
+                }
+            }
+        }
+        None
+    }
+
+    /// `at` 所在周期之前的一个周期序号（按 INTERVAL 计），从该周期开始查找不会漏掉触发时间
+    fn period_index(&self, start: NaiveDateTime, at: NaiveDateTime) -> u32 {
+        let periods = match self.frequency {
+            Frequency::Hourly => (at - start).num_hours(),
+            Frequency::Daily => (at.date() - start.date()).num_days(),
+            Frequency::Weekly => (week_start(at.date()) - week_start(start.date())).num_weeks(),
+            Frequency::Monthly => {
+                (at.year() as i64 * 12 + at.month0() as i64) - (start.year() as i64 * 12 + start.month0() as i64)
+            }
+        };
+        (periods / self.interval as i64 - 1).clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// 第 `period` 个周期内的候选时间（升序）；超出日期范围时返回 None
+    fn period_candidates(&self, start: NaiveDateTime, period: u32) -> Option<Vec<NaiveDateTime>> {
+        let step = period as i64 * self.interval as i64;
+        let second = start.second();
+        let minutes = or_default(&self.by_minute, start.minute());
+
+        if self.frequency == Frequency::Hourly {
+            let hour = start.checked_add_signed(Duration::try_hours(step)?)?;
+            if !self.matches_day(hour.date()) || !(self.by_hour.is_empty() || self.by_hour.contains(&hour.hour())) {
+                return Some(Vec::new());
+            }
+            return Some(
+                minutes
+                    .iter()
+                    .filter_map(|&minute| hour.date().and_hms_opt(hour.hour(), minute, second))
+                    .collect(),
+            );
+        }
+
+        let days: Vec<NaiveDate> = match self.frequency {
+            Frequency::Daily => {
+                let day = start.date().checked_add_signed(Duration::try_days(step)?)?;
+                if self.matches_day(day) { vec![day] } else { Vec::new() }
+            }
+            Frequency::Weekly => {
+                let week = week_start(start.date()).checked_add_signed(Duration::try_weeks(step)?)?;
+                let mut weekdays = if self.by_day.is_empty() { vec![start.weekday()] } else { self.by_day.clone() };
+                weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+                weekdays
+                    .into_iter()
+                    .map(|weekday| week + Duration::days(weekday.num_days_from_monday() as i64))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = start.year() as i64 * 12 + start.month0() as i64 + step;
+                let (year, month) = (i32::try_from(months.div_euclid(12)).ok()?, months.rem_euclid(12) as u32 + 1);
+                (1..=31)
+                    .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+                    .filter(|day| match (self.by_month_day.is_empty(), self.by_day.is_empty()) {
+                        (true, true) => day.day() == start.day(),
+                        _ => self.matches_day(*day),
+                    })
+                    .collect()
+            }
+            Frequency::Hourly => unreachable!(),
+        };
+
+        let hours = or_default(&self.by_hour, start.hour());
+        let times: Vec<NaiveTime> = hours
+            .iter()
+            .flat_map(|&hour| minutes.iter().filter_map(move |&minute| NaiveTime::from_hms_opt(hour, minute, second)))
+            .collect();
+        Some(days.into_iter().flat_map(|day| times.iter().map(move |time| day.and_time(*time))).collect())
+    }
+
+    fn matches_day(&self, day: NaiveDate) -> bool {
+        (self.by_day.is_empty() || self.by_day.contains(&day.weekday()))
+            && (self.by_month_day.is_empty() || self.by_month_day.contains(&day.day()))
+    }
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+}
+
+fn or_default(values: &[u32], default: u32) -> Vec<u32> {
+    let mut values = if values.is_empty() { vec![default] } else { values.to_vec() };
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+fn parse_number(key: &str, value: &str, min: u32, max: u32) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|number| (min..=max).contains(number))
+        .ok_or_else(|| format!("RRULE {} must be between {} and {}, got {:?}", key, min, max, value))
+}
+
+fn parse_list(key: &str, value: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    value.split(',').map(|item| parse_number(key, item, min, max)).collect()
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("unsupported RRULE BYDAY value {:?}", value)),
+    }
+}
+
+/// UNTIL 为 UTC 时间（20251231T235959Z）或日期（20251231，当天结束前有效）
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(until) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(until.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(23, 59, 59))
+        .map(|until| until.and_utc())
+        .ok_or_else(|| format!("invalid RRULE UNTIL {:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_cron_in_timezone() {
+        let recurrence = Recurrence::parse("0 8 * * 1-5").unwrap();
+        let tz: Tz = "Asia/Shanghai".parse().unwrap();
+        let starts_at = utc("2025-12-01T00:00:00Z");
+
+        // 周五 08:00（UTC 00:00）之后是下周一 08:00
+        let next = recurrence.next_after(starts_at, tz, utc("2025-12-05T00:00:00Z")).unwrap();
+        assert_eq!(next, utc("2025-12-08T00:00:00Z"));
+        assert!(Recurrence::parse("0 8 * *").is_err());
+        assert!(Recurrence::parse("0 25 * * *").is_err());
+    }
+
+    #[test]
+    fn test_weekly_rrule_with_count() {
+        let recurrence = Recurrence::parse("RRULE:FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=30;COUNT=3").unwrap();
+        // 2025-12-03 是周三
+        let starts_at = utc("2025-12-03T00:00:00Z");
+        let mut occurrences = Vec::new();
+        let mut after = starts_at;
+        while let Some(next) = recurrence.next_after(starts_at, Tz::UTC, after) {
+            occurrences.push(next);
+            after = next;
+        }
+        assert_eq!(
+            occurrences,
+            vec![utc("2025-12-03T09:30:00Z"), utc("2025-12-08T09:30:00Z"), utc("2025-12-10T09:30:00Z")]
+        );
+    }
+
+    #[test]
+    fn test_monthly_rrule_skips_missing_days() {
+        let recurrence = Recurrence::parse("FREQ=MONTHLY;BYMONTHDAY=31;UNTIL=20260331").unwrap();
+        let starts_at = utc("2026-01-01T07:00:00Z");
+        let next = recurrence.next_after(starts_at, Tz::UTC, utc("2026-01-31T07:00:00Z")).unwrap();
+        assert_eq!(next, utc("2026-03-31T07:00:00Z"));
+        assert!(recurrence.next_after(starts_at, Tz::UTC, next).is_none());
+        assert!(Recurrence::parse("FREQ=YEARLY").is_err());
+    }
+}
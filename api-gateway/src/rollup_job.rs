@@ -0,0 +1,61 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use tracing::{error, info, warn};
+use crate::database::Database;
+use crate::shutdown::Shutdown;
+
+/// 每次刷新时重新聚合的天数（覆盖跨天结束的会话）
+const REFRESH_LOOKBACK_DAYS: i64 = 2;
+
+/// 启动会话统计汇总后台任务
+///
+/// 首次运行时全量聚合历史数据，之后每隔 SESSION_ROLLUP_INTERVAL_SECS 秒刷新最近几天的数据。
+/// 多副本部署时只有领导者副本执行刷新
+pub fn spawn_session_rollup_job(database: Arc<Database>, shutdown: Shutdown) {
+    let interval_secs = env::var("SESSION_ROLLUP_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(900);
+
+    tokio::spawn(async move {
+        if let Err(e) = database.ensure_session_rollups().await {
+            error!("Failed to create session rollup table, rollup job disabled: {}", e);
+            return;
+        }
+
+        info!("Session rollup job started (interval: {}s)", interval_secs);
+
+        let leader = database.leader_election("session_rollups");
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        let mut backfilled = false;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+            if !leader.is_leader().await {
+                continue;
+            }
+
+            let since = if backfilled {
+                Utc::now().date_naive() - chrono::Duration::days(REFRESH_LOOKBACK_DAYS)
+            } else {
+                chrono::DateTime::<Utc>::UNIX_EPOCH.date_naive()
+            };
+
+            match database.refresh_session_rollups(since).await {
+                Ok(rows) => {
+                    backfilled = true;
+                    info!("Session rollups refreshed since {} ({} rows)", since, rows);
+                }
+                Err(e) => warn!("Failed to refresh session rollups: {}", e),
+            }
+        }
+
+        leader.release().await;
+    });
+}
@@ -0,0 +1,578 @@
+//! 演示数据生成
+//!
+//! 向数据库写入演示用户、设备、待配对设备的注册令牌和历史会话，全新部署后即可直接查看仪表盘、
+//! 统计图表和分页效果。会话按白天多、夜间少的时段分布在最近若干天内，少数设备承担大部分会话。
+//! 演示数据使用固定前缀（用户名 `demo_`、设备 ID `ECHO_DEMO`），已存在时需指定 reset 先删除；
+//! 指定随机种子时除 ID 和时间基准外生成的数据可复现。写入完成后立即刷新会话统计汇总，不必等待汇总任务。
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Timelike, Utc};
+use echo_shared::FieldEncryptor;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde_json::json;
+use sqlx::{PgConnection, Postgres, QueryBuilder};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::database::Database;
+
+/// 演示用户名前缀
+pub const USERNAME_PREFIX: &str = "demo_";
+/// 演示设备 ID 前缀（ECHO_<序列号>_<MAC>，序列号以 DEMO 开头）
+pub const DEVICE_ID_PREFIX: &str = "ECHO_DEMO";
+
+// 每条 INSERT 语句写入的行数
+const INSERT_BATCH: usize = 1000;
+// 注册令牌有效期，便于演示待配对列表
+const REGISTRATION_TOKEN_TTL_HOURS: i64 = 24;
+// 超时会话的时长（秒）
+const TIMEOUT_SESSION_SECONDS: i32 = 60;
+
+// 各小时的会话权重（0 点到 23 点）
+const HOUR_WEIGHTS: [u32; 24] = [1, 1, 0, 0, 0, 1, 3, 6, 8, 7, 6, 7, 8, 6, 5, 5, 6, 8, 10, 12, 11, 9, 5, 2];
+
+const USER_NAMES: &[&str] = &[
+    "lihua", "wangfang", "zhangwei", "liuyang", "chenjing", "yangli", "zhaolei", "huangmin", "alice", "bob", "carol",
+    "david", "emma", "frank",
+];
+
+const LOCATIONS: &[&str] = &["客厅", "主卧", "次卧", "书房", "厨房", "儿童房", "餐厅", "办公室", "会议室", "前台"];
+
+// 固件版本及权重
+const FIRMWARE_VERSIONS: &[(&str, u32)] = &[("1.0.0", 1), ("1.1.0", 2), ("1.2.3", 5), ("2.0.0", 3)];
+
+// 转写和回复
+const CONVERSATIONS: &[(&str, &str)] = &[
+    ("今天天气怎么样", "今天晴，气温 18 到 26 度，适合出门。"),
+    ("播放一首轻音乐", "好的，为你播放《River Flows in You》。"),
+    ("明天早上七点叫我起床", "好的，已设置明天早上 7 点的闹钟。"),
+    ("定一个十分钟的计时器", "十分钟计时开始。"),
+    ("把音量调大一点", "音量已调到 60%。"),
+    ("讲个笑话", "为什么数学书总是很忧郁？因为它有太多的问题。"),
+    ("北京现在几点", "北京时间现在是下午三点二十分。"),
+    ("帮我把客厅的灯关掉", "好的，客厅的灯已关闭。"),
+    ("一公里等于多少英里", "一公里约等于 0.62 英里。"),
+    ("提醒我下午三点开会", "好的，下午 3 点提醒你开会。"),
+    ("给我讲一个睡前故事", "从前有一只小兔子，它最喜欢在月光下散步……"),
+    ("What's the weather like tomorrow?", "Tomorrow will be cloudy with a high of 22 degrees."),
+    ("Play some jazz", "Sure, playing a jazz playlist."),
+    ("Set a timer for five minutes", "Five-minute timer started."),
+    ("How do you say thank you in Japanese?", "In Japanese, thank you is \"arigatou\"."),
+];
+
+const FAILURE_REASONS: &[&str] = &["EchoKit server unavailable", "ASR returned empty result", "Device disconnected"];
+
+/// 生成数量等参数
+#[derive(Debug, Clone)]
+pub struct SeedOptions {
+    pub users: usize,
+    /// 设备总数，包含待配对设备
+    pub devices: usize,
+    /// 待配对（带注册令牌）的设备数
+    pub pending_devices: usize,
+    pub sessions: usize,
+    /// 会话分布在最近多少天内
+    pub days: u32,
+    /// 所有演示用户共用的登录密码
+    pub password: String,
+    /// 随机种子，未指定时每次生成不同的数据
+    pub seed: Option<u64>,
+    /// 先删除之前生成的演示数据
+    pub reset: bool,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        Self {
+            users: 10,
+            devices: 50,
+            pending_devices: 5,
+            sessions: 5000,
+            days: 30,
+            password: "demo123456".to_string(),
+            seed: None,
+            reset: false,
+        }
+    }
+}
+
+impl SeedOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.users == 0 {
+            bail!("users must be at least 1");
+        }
+        if self.pending_devices > self.devices {
+            bail!("pending devices ({}) must not exceed devices ({})", self.pending_devices, self.devices);
+        }
+        if self.sessions > 0 && self.devices == self.pending_devices {
+            bail!("sessions require at least one registered (non-pending) device");
+        }
+        if self.days == 0 {
+            bail!("days must be at least 1");
+        }
+        if self.password.len() < 8 {
+            bail!("password must be at least 8 characters");
+        }
+        Ok(())
+    }
+}
+
+/// 写入结果
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub users: usize,
+    pub devices: usize,
+    pub registration_tokens: usize,
+    pub sessions: usize,
+    /// 删除的旧演示用户和设备数
+    pub removed_users: u64,
+    pub removed_devices: u64,
+    pub rollup_rows: u64,
+}
+
+struct DemoUser {
+    id: Uuid,
+    username: String,
+    email: String,
+    role: &'static str,
+    created_at: DateTime<Utc>,
+}
+
+struct DemoDevice {
+    id: String,
+    name: String,
+    serial_number: String,
+    mac_address: String,
+    owner: Uuid,
+    location: &'static str,
+    status: &'static str,
+    firmware_version: &'static str,
+    battery_level: i32,
+    volume_level: i32,
+    last_seen: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    /// 待配对设备的配对码和二维码令牌
+    pairing: Option<(String, String)>,
+}
+
+struct DemoSession {
+    id: String,
+    device_id: String,
+    user_id: String,
+    status: &'static str,
+    transcription: Option<String>,
+    response: Option<String>,
+    confidence_score: Option<f64>,
+    processing_time_ms: Option<i32>,
+    duration: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    metadata: serde_json::Value,
+}
+
+// 演示数据生成器，数据只取决于随机种子和生成时间
+struct Generator {
+    rng: StdRng,
+    now: DateTime<Utc>,
+    days: u32,
+}
+
+impl Generator {
+    fn new(seed: Option<u64>, days: u32, now: DateTime<Utc>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { rng, now, days }
+    }
+
+    // 会话时间范围之前的某个时间，用户和设备的创建时间
+    fn before_history(&mut self) -> DateTime<Utc> {
+        self.now - Duration::days(self.days as i64) - Duration::seconds(self.rng.gen_range(0..30 * 86_400))
+    }
+
+    fn users(&mut self, count: usize) -> Vec<DemoUser> {
+        (0..count)
+            .map(|i| {
+                let name = USER_NAMES[i % USER_NAMES.len()];
+                let username = format!("{}{}{:03}", USERNAME_PREFIX, name, i + 1);
+                // 第一个用户为管理员，约五分之一为设备管理员
+                let role = match i {
+                    0 => "Admin",
+                    _ if self.rng.gen_bool(0.2) => "Manager",
+                    _ => "Viewer",
+                };
+                DemoUser {
+                    id: Uuid::new_v4(),
+                    email: format!("{}@example.com", username),
+                    username,
+                    role,
+                    created_at: self.before_history(),
+                }
+            })
+            .collect()
+    }
+
+    fn devices(&mut self, count: usize, pending: usize, users: &[DemoUser]) -> Vec<DemoDevice> {
+        let firmware = WeightedIndex::new(FIRMWARE_VERSIONS.iter().map(|(_, weight)| weight)).expect("valid weights");
+        (0..count)
+            .map(|i| {
+                let serial_number = format!("DEMO{:06}", i + 1);
+                // 本地管理的单播 MAC，后三个字节为序号保证唯一
+                let mac_address = format!(
+                    "02:EC:{:02X}:{:02X}:{:02X}:{:02X}",
+                    self.rng.gen::<u8>(),
+                    (i >> 16) & 0xff,
+                    (i >> 8) & 0xff,
+                    i & 0xff
+                );
+                let location = *LOCATIONS.choose(&mut self.rng).expect("non-empty");
+                let is_pending = i >= count - pending;
+                let status = match self.rng.gen_range(0..100) {
+                    _ if is_pending => "pending",
+                    0..=59 => "online",
+                    60..=94 => "offline",
+                    _ => "maintenance",
+                };
+                let last_seen = if status == "online" {
+                    self.now - Duration::seconds(self.rng.gen_range(0..60))
+                } else {
+                    self.now - Duration::seconds(self.rng.gen_range(60..self.days as i64 * 86_400))
+                };
+                let pairing = is_pending.then(|| {
+                    let code: String = (0..6)
+                        .map(|_| *b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789".choose(&mut self.rng).expect("non-empty") as char)
+                        .collect();
+                    (code, Uuid::new_v4().simple().to_string())
+                });
+                DemoDevice {
+                    id: format!("ECHO_{}_{}", serial_number, mac_address.replace(':', "")),
+                    name: format!("{}音箱-{:03}", location, i + 1),
+                    serial_number,
+                    mac_address,
+                    owner: users.choose(&mut self.rng).expect("at least one user").id,
+                    location,
+                    status,
+                    firmware_version: FIRMWARE_VERSIONS[firmware.sample(&mut self.rng)].0,
+                    battery_level: self.rng.gen_range(5..=100),
+                    volume_level: self.rng.gen_range(20..=80),
+                    last_seen,
+                    created_at: self.before_history(),
+                    pairing,
+                }
+            })
+            .collect()
+    }
+
+    fn sessions(&mut self, count: usize, devices: &[DemoDevice]) -> Vec<DemoSession> {
+        let devices: Vec<&DemoDevice> = devices.iter().filter(|device| device.pairing.is_none()).collect();
+        if devices.is_empty() {
+            return Vec::new();
+        }
+        // 设备活跃度差异较大，少数设备承担大部分会话
+        let activity = WeightedIndex::new(devices.iter().map(|_| self.rng.gen_range(1u32..=10).pow(2))).expect("valid weights");
+        let hours = WeightedIndex::new(HOUR_WEIGHTS).expect("valid weights");
+
+        let mut sessions: Vec<DemoSession> = (0..count)
+            .map(|_| {
+                let device = devices[activity.sample(&mut self.rng)];
+                let start_time = self.start_time(&hours);
+                let (status, duration) = match self.rng.gen_range(0..100) {
+                    0..=87 => ("completed", self.rng.gen_range(3..=40)),
+                    88..=94 => ("failed", self.rng.gen_range(1..=10)),
+                    _ => ("timeout", TIMEOUT_SESSION_SECONDS),
+                };
+                let (transcription, response) = *CONVERSATIONS.choose(&mut self.rng).expect("non-empty");
+                let mut metadata = json!({ "seed": true, "transport": if self.rng.gen_bool(0.7) { "websocket" } else { "udp" } });
+                let (transcription, response, confidence_score, processing_time_ms) = match status {
+                    "completed" => (
+                        Some(transcription.to_string()),
+                        Some(response.to_string()),
+                        Some((self.rng.gen_range(0.70..0.99_f64) * 100.0).round() / 100.0),
+                        Some(self.rng.gen_range(300..=2500)),
+                    ),
+                    "failed" => {
+                        metadata["error"] = json!(FAILURE_REASONS.choose(&mut self.rng).expect("non-empty"));
+                        (Some(transcription.to_string()), None, None, Some(self.rng.gen_range(100..=5000)))
+                    }
+                    _ => (None, None, None, None),
+                };
+                DemoSession {
+                    id: Uuid::new_v4().to_string(),
+                    device_id: device.id.clone(),
+                    user_id: device.owner.to_string(),
+                    status,
+                    transcription,
+                    response,
+                    confidence_score,
+                    processing_time_ms,
+                    duration,
+                    start_time,
+                    end_time: start_time + Duration::seconds(duration as i64),
+                    metadata,
+                }
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.start_time);
+        sessions
+    }
+
+    // 最近 days 天内按时段权重取一个开始时间，不晚于当前时间一分钟前
+    fn start_time(&mut self, hours: &WeightedIndex<u32>) -> DateTime<Utc> {
+        let day = self.now - Duration::days(self.rng.gen_range(0..self.days as i64));
+        let hour = hours.sample(&mut self.rng) as u32;
+        let start = day
+            .with_hour(hour)
+            .and_then(|t| t.with_minute(self.rng.gen_range(0..60)))
+            .and_then(|t| t.with_second(self.rng.gen_range(0..60)))
+            .unwrap_or(day);
+        if start > self.now - Duration::minutes(1) {
+            start - Duration::days(1)
+        } else {
+            start
+        }
+    }
+}
+
+/// 生成并写入演示数据
+pub async fn seed(config: &echo_shared::AppConfig, options: &SeedOptions) -> Result<SeedReport> {
+    options.validate()?;
+
+    // 与 Bridge 写入会话时一致：启用静态加密时转写和回复以密文写入
+    let encryptor = FieldEncryptor::from_config(&config.encryption)?;
+    if let Some(encryptor) = &encryptor {
+        info!("Encrypting seeded transcripts with master key {}", encryptor.current_key_id());
+    }
+
+    let database = Database::new(&config.database).await?;
+    let result = seed_database(&database, options, &config.bridge.echokit_websocket_url, encryptor.as_ref()).await;
+    database.close().await;
+    result
+}
+
+async fn seed_database(
+    database: &Database,
+    options: &SeedOptions,
+    echokit_server_url: &str,
+    encryptor: Option<&FieldEncryptor>,
+) -> Result<SeedReport> {
+    database.run_migrations().await?;
+    database.ensure_session_rollups().await?;
+
+    let mut report = SeedReport::default();
+    let mut tx = database.pool()?.begin().await?;
+
+    if options.reset {
+        (report.removed_users, report.removed_devices) = delete_demo_data(&mut tx).await?;
+    } else {
+        let existing: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM users WHERE starts_with(username, $1))
+                  + (SELECT COUNT(*) FROM devices WHERE starts_with(id, $2))",
+        )
+        .bind(USERNAME_PREFIX)
+        .bind(DEVICE_ID_PREFIX)
+        .fetch_one(&mut *tx)
+        .await?;
+        if existing > 0 {
+            bail!("Demo data already exists ({} users and devices), rerun with --reset to replace it", existing);
+        }
+    }
+
+    let mut generator = Generator::new(options.seed, options.days, Utc::now());
+    let users = generator.users(options.users);
+    let devices = generator.devices(options.devices, options.pending_devices, &users);
+    let sessions = generator.sessions(options.sessions, &devices);
+
+    let password_hash = echo_shared::hash_password(&options.password)?;
+    insert_users(&mut tx, &users, &password_hash).await?;
+    insert_devices(&mut tx, &devices, echokit_server_url).await?;
+    report.registration_tokens = insert_registration_tokens(&mut tx, &devices).await?;
+    insert_sessions(&mut tx, &sessions, encryptor).await?;
+    tx.commit().await?;
+
+    report.users = users.len();
+    report.devices = devices.len();
+    report.sessions = sessions.len();
+    if let Some(first) = sessions.first() {
+        report.rollup_rows = database.refresh_session_rollups(first.start_time.date_naive()).await?;
+    }
+    Ok(report)
+}
+
+// 删除之前生成的演示用户和设备（会话和注册令牌随设备级联删除），返回删除的用户数和设备数
+async fn delete_demo_data(conn: &mut PgConnection) -> Result<(u64, u64)> {
+    let devices = sqlx::query("DELETE FROM devices WHERE starts_with(id, $1)")
+        .bind(DEVICE_ID_PREFIX)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    sqlx::query("DELETE FROM session_daily_rollups WHERE starts_with(device_id, $1)")
+        .bind(DEVICE_ID_PREFIX)
+        .execute(&mut *conn)
+        .await?;
+    let users = sqlx::query("DELETE FROM users WHERE starts_with(username, $1)")
+        .bind(USERNAME_PREFIX)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    Ok((users, devices))
+}
+
+async fn insert_users(conn: &mut PgConnection, users: &[DemoUser], password_hash: &str) -> Result<()> {
+    for chunk in users.chunks(INSERT_BATCH) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO users (id, username, email, password_hash, role, is_active, created_at, updated_at) ",
+        );
+        query.push_values(chunk, |mut row, user| {
+            row.push_bind(user.id)
+                .push_bind(&user.username)
+                .push_bind(&user.email)
+                .push_bind(password_hash)
+                .push_bind(user.role)
+                .push_bind(true)
+                .push_bind(user.created_at)
+                .push_bind(user.created_at);
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+async fn insert_devices(conn: &mut PgConnection, devices: &[DemoDevice], echokit_server_url: &str) -> Result<()> {
+    for chunk in devices.chunks(INSERT_BATCH) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO devices (
+                id, name, device_type, status, firmware_version, battery_level, volume_level, location, last_seen,
+                is_online, owner, pairing_code, registration_token, registered_at, serial_number, mac_address,
+                echokit_server_url, created_at, updated_at
+            ) ",
+        );
+        query.push_values(chunk, |mut row, device| {
+            let (pairing_code, qr_token) = device.pairing.clone().unzip();
+            row.push_bind(&device.id)
+                .push_bind(&device.name)
+                .push_bind("speaker")
+                .push_bind(device.status)
+                .push_bind(device.firmware_version)
+                .push_bind(device.battery_level)
+                .push_bind(device.volume_level)
+                .push_bind(device.location)
+                .push_bind(device.last_seen)
+                .push_bind(device.status == "online")
+                .push_bind(device.owner.to_string())
+                .push_bind(pairing_code)
+                .push_bind(qr_token)
+                .push_bind(device.pairing.is_none().then_some(device.created_at))
+                .push_bind(&device.serial_number)
+                .push_bind(&device.mac_address)
+                .push_bind(echokit_server_url)
+                .push_bind(device.created_at)
+                .push_bind(device.last_seen);
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+// 为待配对设备写入注册令牌，返回写入的条数
+async fn insert_registration_tokens(conn: &mut PgConnection, devices: &[DemoDevice]) -> Result<usize> {
+    let pending: Vec<(&str, &(String, String))> =
+        devices.iter().filter_map(|device| Some((device.id.as_str(), device.pairing.as_ref()?))).collect();
+    let expires_at = Utc::now() + Duration::hours(REGISTRATION_TOKEN_TTL_HOURS);
+    for chunk in pending.chunks(INSERT_BATCH) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO device_registration_tokens (device_id, pairing_code, qr_token, expires_at, created_at) ",
+        );
+        query.push_values(chunk, |mut row, (device_id, (pairing_code, qr_token))| {
+            row.push_bind(*device_id)
+                .push_bind(pairing_code)
+                .push_bind(qr_token)
+                .push_bind(expires_at)
+                .push_bind(Utc::now());
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+    Ok(pending.len())
+}
+
+async fn insert_sessions(
+    conn: &mut PgConnection,
+    sessions: &[DemoSession],
+    encryptor: Option<&FieldEncryptor>,
+) -> Result<()> {
+    for chunk in sessions.chunks(INSERT_BATCH) {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "INSERT INTO sessions (
+                id, device_id, user_id, session_type, status, transcription, response, confidence_score,
+                processing_time_ms, duration, metadata, start_time, end_time
+            ) ",
+        );
+        query.push_values(chunk, |mut row, session| {
+            // 同一会话的转写和回复共用一个数据密钥
+            let key = encryptor.map(FieldEncryptor::data_key);
+            let seal = |text: &Option<String>| match &key {
+                Some(key) => text.as_deref().map(|text| key.encrypt_text(text)),
+                None => text.clone(),
+            };
+            let (transcription, response) = (seal(&session.transcription), seal(&session.response));
+            row.push_bind(&session.id)
+                .push_bind(&session.device_id)
+                .push_bind(&session.user_id)
+                .push_bind("voice")
+                .push_bind(session.status)
+                .push_bind(transcription)
+                .push_bind(response)
+                .push_bind(session.confidence_score)
+                .push_bind(session.processing_time_ms)
+                .push_bind(session.duration)
+                .push_bind(&session.metadata)
+                .push_bind(session.start_time)
+                .push_bind(session.end_time);
+        });
+        query.build().execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_generator_is_reproducible_and_consistent() {
+        let now = Utc::now();
+        let generate = || {
+            let mut generator = Generator::new(Some(7), 14, now);
+            let users = generator.users(5);
+            let devices = generator.devices(20, 3, &users);
+            let sessions = generator.sessions(500, &devices);
+            (users, devices, sessions)
+        };
+        let (users, devices, sessions) = generate();
+        let (_, again, _) = generate();
+        assert_eq!(
+            devices.iter().map(|d| &d.mac_address).collect::<Vec<_>>(),
+            again.iter().map(|d| &d.mac_address).collect::<Vec<_>>()
+        );
+
+        assert_eq!(users[0].role, "Admin");
+        assert!(users.iter().all(|u| u.username.starts_with(USERNAME_PREFIX)));
+        assert!(devices.iter().all(|d| d.id.starts_with(DEVICE_ID_PREFIX)));
+        assert_eq!(devices.iter().map(|d| &d.mac_address).collect::<HashSet<_>>().len(), 20);
+        let pending: Vec<_> = devices.iter().filter(|d| d.pairing.is_some()).collect();
+        assert_eq!(pending.len(), 3);
+        assert!(pending.iter().all(|d| d.status == "pending"));
+
+        assert_eq!(sessions.len(), 500);
+        let earliest = now - Duration::days(14);
+        for session in &sessions {
+            assert!(session.start_time > earliest && session.start_time < now);
+            assert!(pending.iter().all(|d| d.id != session.device_id));
+            assert_eq!(session.response.is_some(), session.status == "completed");
+        }
+        assert!(sessions.windows(2).all(|w| w[0].start_time <= w[1].start_time));
+    }
+}
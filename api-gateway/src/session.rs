@@ -122,6 +122,9 @@ pub struct BridgeStats {
     pub audio_sessions: usize,
     pub online_devices: usize,
     pub uptime_seconds: u64,
+    /// 旧版本 Bridge 不返回构建信息
+    #[serde(default)]
+    pub build: Option<echo_shared::BuildInfo>,
 }
 
 impl SessionManager {
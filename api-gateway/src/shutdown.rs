@@ -0,0 +1,107 @@
+// 优雅关闭：处理退出信号，通知长连接退出并等待排空
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::info;
+
+/// 关闭信号
+///
+/// 收到退出信号后通知 WebSocket、SSE 等长连接和后台任务退出，
+/// 并跟踪仍在进行中的长连接数量，用于等待连接排空
+#[derive(Clone)]
+pub struct Shutdown {
+    triggered: Arc<watch::Sender<bool>>,
+    connections: Arc<watch::Sender<usize>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            triggered: Arc::new(watch::channel(false).0),
+            connections: Arc::new(watch::channel(0).0),
+        }
+    }
+
+    /// 触发关闭
+    pub fn trigger(&self) {
+        self.triggered.send_replace(true);
+    }
+
+    /// 是否已开始关闭
+    pub fn is_triggered(&self) -> bool {
+        *self.triggered.borrow()
+    }
+
+    /// 等待关闭触发
+    pub async fn wait(&self) {
+        let mut rx = self.triggered.subscribe();
+        // Sender 与 self 同生命周期，不会提前关闭
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+
+    /// 登记一个长连接，返回的 guard 释放时自动注销
+    pub fn track_connection(&self) -> ConnectionGuard {
+        self.connections.send_modify(|count| *count += 1);
+        ConnectionGuard {
+            connections: self.connections.clone(),
+        }
+    }
+
+    /// 当前仍在进行中的长连接数量
+    pub fn active_connections(&self) -> usize {
+        *self.connections.borrow()
+    }
+
+    /// 等待所有长连接关闭
+    pub async fn drained(&self) {
+        let mut rx = self.connections.subscribe();
+        let _ = rx.wait_for(|count| *count == 0).await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 长连接登记，释放时注销
+pub struct ConnectionGuard {
+    connections: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.connections.send_modify(|count| *count = count.saturating_sub(1));
+    }
+}
+
+/// 等待 Ctrl+C 或 SIGTERM
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
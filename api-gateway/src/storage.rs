@@ -2,7 +2,7 @@
 use std::sync::Arc;
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use redis::Client as RedisClient;
-use echo_shared::{DatabaseError, CacheError, RedisCache, CacheOperations};
+use echo_shared::{DatabaseError, CacheError, CacheKey, RedisCache, CacheOperations};
 use anyhow::Result;
 
 // 存储层配置
@@ -205,15 +205,17 @@ impl CacheStrategy {
         cache: &RedisCache,
         user_id: &str,
     ) -> Result<u64, CacheError> {
+        let device_lists = CacheKey::device_list(user_id, None);
         let patterns = vec![
-            &format!("devices:list:{}", user_id),
-            &format!("user:session:{}", user_id),
-            &format!("user:token:*"), // 需要模式匹配
+            device_lists.to_string(),
+            format!("{}:*", device_lists), // 带过滤条件的设备列表
+            CacheKey::user_session(user_id).to_string(),
+            format!("{}*", echo_shared::keys::USER_TOKEN_PREFIX), // 需要模式匹配
         ];
 
         let mut deleted_count = 0;
         for pattern in patterns {
-            deleted_count += cache.delete_pattern(pattern).await?;
+            deleted_count += cache.delete_pattern(&pattern).await?;
         }
 
         Ok(deleted_count)
@@ -225,13 +227,13 @@ impl CacheStrategy {
         device_id: &str,
     ) -> Result<u64, CacheError> {
         let patterns = vec![
-            &format!("device:status:{}", device_id),
-            &format!("device:config:{}", device_id),
+            CacheKey::device_status(device_id).to_string(),
+            CacheKey::device_config(device_id).to_string(),
         ];
 
         let mut deleted_count = 0;
         for pattern in patterns {
-            deleted_count += cache.delete_pattern(pattern).await?;
+            deleted_count += cache.delete_pattern(&pattern).await?;
         }
 
         Ok(deleted_count)
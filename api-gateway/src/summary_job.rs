@@ -0,0 +1,240 @@
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{debug, error, info, warn};
+use crate::database::{Database, PendingSummary};
+use crate::shutdown::Shutdown;
+
+/// 每轮处理的会话数量
+const SUMMARY_BATCH_SIZE: u32 = 20;
+
+/// 发送给 LLM 的对话内容最大字符数
+const MAX_CONVERSATION_CHARS: usize = 8000;
+
+/// 每个会话最多保留的主题标签数量
+const MAX_TAGS: usize = 5;
+
+/// 单个会话摘要失败的最大次数，超过后不再摘要
+const MAX_SUMMARY_ATTEMPTS: u32 = 8;
+
+/// 摘要失败后的重试等待时间，随失败次数翻倍
+const SUMMARY_RETRY_BASE_DELAY: Duration = Duration::from_secs(60);
+const SUMMARY_RETRY_MAX_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// 本轮连续失败的会话数达到该值时认为 LLM 服务不可用，提前结束本轮
+const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+const SUMMARY_PROMPT: &str = "你是会话摘要助手。请用一句话（不超过 100 字）总结下面用户与语音助手的对话，\
+并给出 1 到 5 个简短的主题标签。只输出 JSON，格式为：{\"summary\": \"...\", \"tags\": [\"...\"]}";
+
+/// LLM 摘要服务配置
+#[derive(Debug, Clone)]
+pub struct SummaryConfig {
+    /// OpenAI 兼容的 chat completions 接口地址
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub interval: Duration,
+}
+
+impl SummaryConfig {
+    /// 从环境变量读取配置，未设置 SESSION_SUMMARY_LLM_URL 时返回 None
+    pub fn from_env() -> Option<Self> {
+        let endpoint = env::var("SESSION_SUMMARY_LLM_URL").ok().filter(|v| !v.is_empty())?;
+        let interval_secs = env::var("SESSION_SUMMARY_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(60);
+
+        Some(Self {
+            endpoint,
+            api_key: env::var("SESSION_SUMMARY_LLM_API_KEY").ok().filter(|v| !v.is_empty()),
+            model: env::var("SESSION_SUMMARY_LLM_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string()),
+            interval: Duration::from_secs(interval_secs),
+        })
+    }
+}
+
+/// LLM 返回的摘要结果
+#[derive(Debug, Deserialize)]
+struct SessionSummary {
+    summary: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// 启动会话摘要后台任务
+///
+/// 每隔 SESSION_SUMMARY_INTERVAL_SECS 秒为已完成的会话调用 LLM 生成摘要和主题标签。
+/// 多副本部署时只有领导者副本执行，避免同一会话被重复摘要
+pub fn spawn_session_summary_job(database: Arc<Database>, shutdown: Shutdown) {
+    let config = match SummaryConfig::from_env() {
+        Some(config) => config,
+        None => {
+            warn!("SESSION_SUMMARY_ENABLED is set but SESSION_SUMMARY_LLM_URL is missing, summary job disabled");
+            return;
+        }
+    };
+
+    let http = match reqwest::Client::builder().timeout(Duration::from_secs(30)).build() {
+        Ok(http) => http,
+        Err(e) => {
+            error!("Failed to create HTTP client, summary job disabled: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        info!(
+            "Session summary job started (model: {}, interval: {:?})",
+            config.model, config.interval
+        );
+
+        let leader = database.leader_election("session_summaries");
+        let mut interval = tokio::time::interval(config.interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+            if !leader.is_leader().await {
+                continue;
+            }
+
+            let pending = match database.sessions_pending_summary(SUMMARY_BATCH_SIZE, MAX_SUMMARY_ATTEMPTS).await {
+                Ok(pending) => pending,
+                Err(e) => {
+                    warn!("Failed to load sessions pending summary: {}", e);
+                    continue;
+                }
+            };
+
+            let mut consecutive_failures = 0;
+            for session in pending {
+                let summary = match summarize(&http, &config, &session).await {
+                    Ok(summary) => summary,
+                    Err(e) => {
+                        // 失败的会话按次数退避，不阻塞队列中之后的会话
+                        record_failure(&database, &session, &e).await;
+                        consecutive_failures += 1;
+                        if consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                            warn!("{} consecutive summary failures, ending this round early", consecutive_failures);
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                consecutive_failures = 0;
+
+                match database
+                    .save_session_summary(&session.session_id, &summary.summary, &summary.tags)
+                    .await
+                {
+                    Ok(_) => debug!("Session {} summarized with tags {:?}", session.session_id, summary.tags),
+                    Err(e) => warn!("Failed to save summary for session {}: {}", session.session_id, e),
+                }
+            }
+        }
+
+        leader.release().await;
+    });
+}
+
+/// 记录摘要失败并安排重试，等待时间随失败次数翻倍（1 分钟起，最长 6 小时）
+async fn record_failure(database: &Database, session: &PendingSummary, error: &anyhow::Error) {
+    match database
+        .record_summary_failure(&session.session_id, SUMMARY_RETRY_BASE_DELAY, SUMMARY_RETRY_MAX_DELAY)
+        .await
+    {
+        Ok(attempts) if attempts >= MAX_SUMMARY_ATTEMPTS => warn!(
+            "Failed to summarize session {} ({} attempts), giving up: {}",
+            session.session_id, attempts, error
+        ),
+        Ok(attempts) => warn!(
+            "Failed to summarize session {} (attempt {}), will retry later: {}",
+            session.session_id, attempts, error
+        ),
+        Err(e) => warn!(
+            "Failed to summarize session {}: {}; failed to record the attempt: {}",
+            session.session_id, error, e
+        ),
+    }
+}
+
+/// 生成并保存单个会话的摘要（任务队列的会话摘要任务调用）
+pub async fn summarize_and_save(
+    database: &Database,
+    http: &reqwest::Client,
+    config: &SummaryConfig,
+    session: &PendingSummary,
+) -> Result<()> {
+    let summary = summarize(http, config, session).await?;
+    database.save_session_summary(&session.session_id, &summary.summary, &summary.tags).await?;
+    debug!("Session {} summarized with tags {:?}", session.session_id, summary.tags);
+    Ok(())
+}
+
+/// 调用 LLM 生成单个会话的摘要
+async fn summarize(
+    http: &reqwest::Client,
+    config: &SummaryConfig,
+    session: &PendingSummary,
+) -> Result<SessionSummary> {
+    let mut conversation = format!("用户：{}", session.transcription);
+    if let Some(response) = session.response.as_deref().filter(|r| !r.is_empty()) {
+        conversation.push_str(&format!("\n助手：{}", response));
+    }
+    let conversation: String = conversation.chars().take(MAX_CONVERSATION_CHARS).collect();
+
+    let body = json!({
+        "model": config.model,
+        "temperature": 0.2,
+        "messages": [
+            { "role": "system", "content": SUMMARY_PROMPT },
+            { "role": "user", "content": conversation }
+        ]
+    });
+
+    let mut request = http.post(&config.endpoint).json(&body);
+    if let Some(api_key) = &config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+    let content = response["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| anyhow!("LLM response has no message content"))?;
+
+    Ok(parse_summary(content))
+}
+
+/// 解析 LLM 输出；不是合法 JSON 时把整段输出作为摘要
+fn parse_summary(content: &str) -> SessionSummary {
+    let content = content.trim();
+    let json_text = content
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    let mut summary = serde_json::from_str::<SessionSummary>(json_text).unwrap_or_else(|_| SessionSummary {
+        summary: content.to_string(),
+        tags: Vec::new(),
+    });
+
+    summary.summary = summary.summary.trim().to_string();
+    let mut tags: Vec<String> = Vec::new();
+    for tag in summary.tags.iter().map(|t| t.trim().trim_start_matches('#').to_string()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.truncate(MAX_TAGS);
+    summary.tags = tags;
+
+    summary
+}
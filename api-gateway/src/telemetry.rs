@@ -0,0 +1,170 @@
+//! Prometheus 指标
+//!
+//! 请求耗时由 `request_logging` 中间件记录，长连接数和数据库连接池在抓取时采样，
+//! 获取数据库连接的等待时间由连接池监控任务定期采样。
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use echo_shared::{CacheDegradationEvent, LocalCacheEvent, PoolStats};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::app_state::AppState;
+
+/// HTTP 请求耗时（method、path 为路由模板、status）
+pub const HTTP_REQUEST_DURATION: &str = "echo_gateway_http_request_duration_seconds";
+/// 进行中的 WebSocket/SSE 长连接数
+pub const ACTIVE_CONNECTIONS: &str = "echo_gateway_active_connections";
+/// 数据库连接池中的连接数（state: idle / active）
+pub const DB_POOL_CONNECTIONS: &str = "echo_gateway_db_pool_connections";
+/// 数据库连接池上限
+pub const DB_POOL_MAX_CONNECTIONS: &str = "echo_gateway_db_pool_max_connections";
+/// 采样获取数据库连接的等待时间
+pub const DB_POOL_ACQUIRE_WAIT: &str = "echo_gateway_db_pool_acquire_wait_seconds";
+/// 采样时获取数据库连接失败（如超时）的次数
+pub const DB_POOL_ACQUIRE_ERRORS: &str = "echo_gateway_db_pool_acquire_errors_total";
+/// 等待时间超过阈值时自动扩容新建的连接数
+pub const DB_POOL_GROWN_CONNECTIONS: &str = "echo_gateway_db_pool_grown_connections_total";
+/// 进程内缓存的访问结果（cache、result: hit / miss / coalesced / eviction）
+pub const LOCAL_CACHE: &str = "echo_gateway_local_cache_total";
+/// 任务队列的任务处理结果（kind、result: completed / retried / dead_letter）
+pub const JOBS: &str = "echo_gateway_jobs_total";
+/// Redis 缓存是否处于降级模式（1 降级，0 正常）
+pub const CACHE_DEGRADED: &str = "echo_gateway_cache_degraded";
+/// 降级期间跳过的缓存操作数（operation: read / write）
+pub const CACHE_SKIPPED: &str = "echo_gateway_cache_skipped_total";
+/// 超过大小限制被拒绝的外部输入数（ingress: websocket / http）
+pub const INGRESS_REJECTED: &str = "echo_gateway_ingress_rejected_total";
+/// 被 IP 过滤规则拒绝的请求数（reason: denied / not_allowed / country）
+pub const IP_BLOCKED: &str = "echo_gateway_ip_blocked_total";
+/// 未通过授权检查的设备命令数（command: restart / volume / ota / announcement / other、reason）
+pub const COMMAND_DENIED: &str = "echo_gateway_command_denied_total";
+/// Webhook 投递请求的结果（event、result: delivered / retried / failed）
+pub const WEBHOOK_DELIVERIES: &str = "echo_gateway_webhook_deliveries_total";
+/// 发送的通知数（kind: daily_digest / device_offline / session_failed、channel、result: sent / failed）
+pub const NOTIFICATIONS: &str = "echo_gateway_notifications_total";
+/// 定时播报下发到设备的结果（result: queued / denied / failed / missed）
+pub const ANNOUNCEMENTS: &str = "echo_gateway_announcements_total";
+
+// 请求耗时直方图的分桶（秒）
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// 直方图数据的清理间隔
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// 为 API Gateway 的直方图指标设置分桶
+pub fn metrics_buckets(builder: PrometheusBuilder) -> Result<PrometheusBuilder> {
+    Ok(builder
+        .set_buckets_for_metric(Matcher::Full(HTTP_REQUEST_DURATION.to_string()), LATENCY_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(DB_POOL_ACQUIRE_WAIT.to_string()), LATENCY_BUCKETS)?)
+}
+
+/// 安装全局指标记录器，必须在 Tokio 运行时中调用
+pub fn init() -> Result<()> {
+    let handle = metrics_buckets(PrometheusBuilder::new())?.install_recorder()?;
+    use_metrics_handle(handle);
+    Ok(())
+}
+
+/// 使用已安装的全局指标记录器（单进程模式下由 etch-all 安装），必须在 Tokio 运行时中调用
+pub fn use_metrics_handle(handle: PrometheusHandle) {
+    let upkeep_handle = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep_handle.run_upkeep();
+        }
+    });
+
+    let _ = HANDLE.set(handle);
+}
+
+/// GET /metrics
+pub async fn metrics_handler(State(app_state): State<AppState>) -> Response {
+    let Some(handle) = HANDLE.get() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "metrics recorder not installed").into_response();
+    };
+
+    metrics::gauge!(ACTIVE_CONNECTIONS).set(app_state.shutdown.active_connections() as f64);
+
+    // SQLite 存储没有连接池指标
+    if let Ok(pool) = app_state.database.pool() {
+        let pool = PoolStats::snapshot(pool);
+        metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "idle").set(pool.idle as f64);
+        metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "active").set(pool.in_use as f64);
+        metrics::gauge!(DB_POOL_MAX_CONNECTIONS).set(pool.max as f64);
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// 记录一次数据库连接池采样（获取连接的等待时间和自动扩容新建的连接）
+pub fn record_db_pool_sample(stats: &PoolStats) {
+    metrics::histogram!(DB_POOL_ACQUIRE_WAIT).record(stats.acquire_wait.as_secs_f64());
+    if stats.acquire_error.is_some() {
+        metrics::counter!(DB_POOL_ACQUIRE_ERRORS).increment(1);
+    }
+    metrics::counter!(DB_POOL_GROWN_CONNECTIONS).increment(stats.grown as u64);
+}
+
+/// 记录一次进程内缓存访问结果
+pub fn record_local_cache(cache: &str, event: LocalCacheEvent) {
+    metrics::counter!(LOCAL_CACHE, "cache" => cache.to_string(), "result" => event.as_str()).increment(1);
+}
+
+/// 记录缓存降级状态变化和降级期间跳过的缓存操作
+pub fn record_cache_degradation(event: CacheDegradationEvent) {
+    match event {
+        CacheDegradationEvent::Degraded => metrics::gauge!(CACHE_DEGRADED).set(1.0),
+        CacheDegradationEvent::Recovered => metrics::gauge!(CACHE_DEGRADED).set(0.0),
+        CacheDegradationEvent::Skipped(operation) => {
+            metrics::counter!(CACHE_SKIPPED, "operation" => operation.as_str()).increment(1)
+        }
+    }
+}
+
+/// 记录一次超过大小限制被拒绝的外部输入
+pub fn record_ingress_rejected(ingress: &'static str) {
+    metrics::counter!(INGRESS_REJECTED, "ingress" => ingress).increment(1);
+}
+
+/// 记录一次被 IP 过滤规则拒绝的请求
+pub fn record_ip_blocked(reason: &'static str) {
+    metrics::counter!(IP_BLOCKED, "reason" => reason).increment(1);
+}
+
+/// 记录一次未通过授权检查的设备命令
+pub fn record_command_denied(command: &'static str, reason: &'static str) {
+    metrics::counter!(COMMAND_DENIED, "command" => command, "reason" => reason).increment(1);
+}
+
+/// 记录一次 Webhook 投递请求的结果
+pub fn record_webhook_delivery(event: &str, result: &'static str) {
+    metrics::counter!(WEBHOOK_DELIVERIES, "event" => event.to_string(), "result" => result).increment(1);
+}
+
+/// 记录一次通知发送结果
+pub fn record_notification(kind: &'static str, channel: &str, result: &'static str) {
+    metrics::counter!(NOTIFICATIONS, "kind" => kind, "channel" => channel.to_string(), "result" => result).increment(1);
+}
+
+/// 记录一次定时播报的下发结果
+pub fn record_announcement(result: &'static str) {
+    metrics::counter!(ANNOUNCEMENTS, "result" => result).increment(1);
+}
+
+/// 记录一次任务处理结果
+pub fn record_job(kind: &str, result: &'static str) {
+    metrics::counter!(JOBS, "kind" => kind.to_string(), "result" => result).increment(1);
+}
@@ -0,0 +1,13 @@
+// HTTPS / WSS（server.tls）：使用 rustls 直接终止 TLS，证书和私钥在启动时读取
+use anyhow::{Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use echo_shared::TlsConfig;
+
+/// 读取 PEM 格式的证书链和私钥
+pub async fn rustls_config(config: &TlsConfig) -> Result<RustlsConfig> {
+    // 使用 ring 作为加密实现；进程内已安装时忽略
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    RustlsConfig::from_pem_file(&config.cert_path, &config.key_path)
+        .await
+        .with_context(|| format!("Failed to load TLS certificate {} and key {}", config.cert_path, config.key_path))
+}
@@ -0,0 +1,30 @@
+// 请求体校验：反序列化 JSON 后按 DTO 上声明的规则校验
+use axum::{
+    async_trait,
+    extract::{FromRequest, Json, Request},
+};
+use echo_shared::EchoError;
+use serde::de::DeserializeOwned;
+use validator::Validate;
+use crate::error::ApiError;
+
+/// 带校验的 JSON 请求体，校验失败时返回 422 及字段级错误
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| ApiError::bad_request(rejection.body_text()))?;
+
+        value.validate().map_err(EchoError::from)?;
+        Ok(Self(value))
+    }
+}
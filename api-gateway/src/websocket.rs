@@ -1,81 +1,224 @@
 use axum::{
     extract::{
-        ws::{WebSocket, Message},
-        WebSocketUpgrade,
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        Query, State, WebSocketUpgrade,
     },
-    response::Response,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
 };
-use echo_shared::{WebSocketMessage, DeviceStatus, SessionStage};
+use echo_shared::{UserRole, WebSocketMessage};
 use echo_shared::types::NotificationLevel;
-use futures::{sink::SinkExt, stream::StreamExt};
-use serde_json;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use futures::{sink::SinkExt, stream::{SplitSink, SplitStream, StreamExt}};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
 use tracing::{info, warn, error};
+use crate::app_state::AppState;
+use crate::error::ApiError;
+use crate::cookie_auth;
+use crate::handlers::auth::{authenticate, bearer_token, decode_jwt_token, ensure_token_current, Claims};
+use crate::telemetry;
 
 // 广播通道类型
 type BroadcastReceiver = broadcast::Receiver<WebSocketMessage>;
-type Broadcaster = broadcast::Sender<WebSocketMessage>;
 
-// WebSocket 连接管理器
-#[derive(Clone)]
-struct ConnectionManager {
-    connections: Arc<RwLock<HashMap<String, Broadcaster>>>,
+/// 设备访问范围的刷新间隔（用户新绑定设备后无需重连即可收到事件）
+const SCOPE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 未在连接参数中携带 token 时，等待首条认证消息的超时时间
+const AUTH_MESSAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// WebSocket 认证参数（浏览器无法设置 Authorization header，通过 query 传递 token）
+#[derive(Debug, Deserialize)]
+pub struct WebSocketAuthParams {
+    pub token: Option<String>,
 }
 
-impl ConnectionManager {
-    fn new() -> Self {
-        Self {
-            connections: Arc::new(RwLock::new(HashMap::new())),
+/// 客户端发送的消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Auth { token: String },
+    Ping {},
+    Subscribe { device_id: String },
+    Unsubscribe { device_id: String },
+}
+
+/// 接收任务转交给发送任务处理的命令
+enum ClientCommand {
+    Reply(WebSocketMessage),
+    Subscribe(String),
+    Unsubscribe(String),
+}
+
+/// 连接可接收事件的设备范围
+enum DeviceScope {
+    All,
+    Devices(HashSet<String>),
+}
+
+impl DeviceScope {
+    async fn load(app_state: &AppState, claims: &Claims) -> Self {
+        if claims.role == UserRole::Admin {
+            return DeviceScope::All;
+        }
+
+        match app_state.database.get_device_ids_for_user(&claims.user_id()).await {
+            Ok(device_ids) => DeviceScope::Devices(device_ids.into_iter().collect()),
+            Err(e) => {
+                warn!("Failed to load devices for user {}: {}", claims.sub, e);
+                DeviceScope::Devices(HashSet::new())
+            }
         }
     }
 
-    async fn add_connection(&self, user_id: String) -> Broadcaster {
-        let (tx, _rx) = broadcast::channel(1000);
-        let mut connections = self.connections.write().await;
-        connections.insert(user_id, tx.clone());
-        tx
+    fn contains(&self, device_id: &str) -> bool {
+        match self {
+            DeviceScope::All => true,
+            DeviceScope::Devices(device_ids) => device_ids.contains(device_id),
+        }
     }
+}
 
-    async fn remove_connection(&self, user_id: &str) {
-        let mut connections = self.connections.write().await;
-        connections.remove(user_id);
+/// 连接的事件过滤条件：用户有权访问的设备范围 + 客户端订阅的设备
+struct EventFilter {
+    scope: DeviceScope,
+    /// 为 None 时接收范围内所有设备的事件
+    subscriptions: Option<HashSet<String>>,
+}
+
+impl EventFilter {
+    fn allows(&self, message: &WebSocketMessage) -> bool {
+        match event_device_id(message) {
+            // 不属于特定设备的消息（如系统通知）推送给所有连接
+            None => true,
+            Some(device_id) => {
+                self.scope.contains(device_id)
+                    && self
+                        .subscriptions
+                        .as_ref()
+                        .is_none_or(|subscriptions| subscriptions.contains(device_id))
+            }
+        }
     }
 
-    async fn broadcast(&self, message: WebSocketMessage) {
-        let connections = self.connections.read().await;
-        for (_, tx) in connections.iter() {
-            if let Err(e) = tx.send(message.clone()) {
-                warn!("Failed to send message to connection: {}", e);
+    /// 处理客户端命令，返回需要回复给客户端的消息
+    fn apply(&mut self, command: ClientCommand) -> WebSocketMessage {
+        match command {
+            ClientCommand::Reply(message) => message,
+            ClientCommand::Subscribe(device_id) => {
+                if !self.scope.contains(&device_id) {
+                    return notification(
+                        NotificationLevel::Error,
+                        "订阅失败",
+                        format!("无权访问设备 {}", device_id),
+                    );
+                }
+                let message = format!("已订阅设备 {}", device_id);
+                self.subscriptions.get_or_insert_with(HashSet::new).insert(device_id);
+                notification(NotificationLevel::Success, "订阅成功", message)
+            }
+            ClientCommand::Unsubscribe(device_id) => {
+                if let Some(subscriptions) = self.subscriptions.as_mut() {
+                    subscriptions.remove(&device_id);
+                }
+                notification(NotificationLevel::Info, "已取消订阅", format!("已取消订阅设备 {}", device_id))
             }
         }
     }
 }
 
-pub async fn websocket_handler(ws: WebSocketUpgrade) -> Response {
-    ws.on_upgrade(handle_websocket)
+/// 获取消息关联的设备ID
+fn event_device_id(message: &WebSocketMessage) -> Option<&str> {
+    match message {
+        WebSocketMessage::DeviceStatusUpdate { device_id, .. }
+        | WebSocketMessage::SessionProgress { device_id, .. }
+        | WebSocketMessage::DeviceRegistrationCreated { device_id, .. }
+        | WebSocketMessage::DeviceRegistrationVerified { device_id, .. }
+        | WebSocketMessage::DeviceRegistrationFailed { device_id, .. }
+        | WebSocketMessage::DeviceRegistrationExpired { device_id, .. }
+        | WebSocketMessage::RegistrationProgress { device_id, .. }
+        | WebSocketMessage::EchoKitSessionStart { device_id, .. }
+        | WebSocketMessage::EchoKitSessionEnd { device_id, .. }
+        | WebSocketMessage::EchoKitAudioData { device_id, .. }
+        | WebSocketMessage::EchoKitTranscription { device_id, .. }
+        | WebSocketMessage::EchoKitResponse { device_id, .. }
+        | WebSocketMessage::EchoKitError { device_id, .. } => Some(device_id),
+        WebSocketMessage::SystemNotification { .. } => None,
+    }
 }
 
-async fn handle_websocket(socket: WebSocket) {
-    let connection_manager = ConnectionManager::new();
+fn notification(level: NotificationLevel, title: &str, message: String) -> WebSocketMessage {
+    WebSocketMessage::SystemNotification {
+        level,
+        title: title.to_string(),
+        message,
+    }
+}
 
-    // TODO: 从 JWT token 中解析用户ID
-    let user_id = "user001".to_string();
-    info!("WebSocket connection established for user: {}", user_id);
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    State(app_state): State<AppState>,
+    Query(params): Query<WebSocketAuthParams>,
+    headers: HeaderMap,
+) -> Response {
+    // 关闭过程中不再接受新连接
+    if app_state.shutdown.is_triggered() {
+        return ApiError::service_unavailable("Server is shutting down").into_response();
+    }
 
-    let broadcaster = connection_manager.add_connection(user_id.clone()).await;
-    let mut rx = broadcaster.subscribe();
+    // Cookie 会话只接受同源页面发起的握手，防止跨站 WebSocket 劫持
+    let session_cookie = cookie_auth::session_token(&headers).filter(|_| cookie_auth::is_same_origin(&headers));
+    let token = params.token.as_deref().or_else(|| bearer_token(&headers)).or(session_cookie.as_deref());
 
+    // 携带了 token 时在握手阶段校验，否则等待客户端发送认证消息
+    let claims = match (token, authenticate(token)) {
+        (_, Ok(claims)) => Some(claims),
+        (Some(_), Err(status)) => {
+            warn!("Rejected WebSocket connection with invalid token");
+            return status.into_response();
+        }
+        (None, Err(_)) => None,
+    };
+    if let Some(claims) = &claims {
+        if let Err(status) = ensure_token_current(&app_state, claims).await {
+            warn!("Rejected WebSocket connection with revoked token");
+            return status.into_response();
+        }
+    }
+
+    // 升级前订阅，避免丢失握手期间的事件
+    let events = app_state.events.subscribe();
+    let limits = &app_state.config.limits;
+    let ws = ws
+        .max_message_size(limits.max_websocket_message_bytes)
+        .max_frame_size(limits.max_websocket_frame_bytes);
+    ws.on_upgrade(move |socket| handle_websocket(socket, app_state, claims, events))
+}
+
+async fn handle_websocket(socket: WebSocket, app_state: AppState, claims: Option<Claims>, mut events: BroadcastReceiver) {
+    // 登记长连接，关闭时等待其退出
+    let _connection = app_state.shutdown.track_connection();
     let (mut sender, mut receiver) = socket.split();
 
-    // 发送欢迎消息
-    let welcome_message = WebSocketMessage::SystemNotification {
-        level: NotificationLevel::Info,
-        title: "连接成功".to_string(),
-        message: "WebSocket 连接已建立，开始接收实时更新".to_string(),
+    let claims = match claims {
+        Some(claims) => claims,
+        None => match wait_for_auth_message(&app_state, &mut sender, &mut receiver).await {
+            Some(claims) => claims,
+            None => return,
+        },
     };
 
+    info!("WebSocket connection established for user: {} ({})", claims.username, claims.sub);
+
+    // 发送欢迎消息
+    let welcome_message = notification(
+        NotificationLevel::Info,
+        "连接成功",
+        "WebSocket 连接已建立，开始接收实时更新".to_string(),
+    );
+
     if let Ok(text) = serde_json::to_string(&welcome_message) {
         if let Err(e) = sender.send(Message::Text(text)).await {
             warn!("Failed to send welcome message: {}", e);
@@ -83,9 +226,47 @@ async fn handle_websocket(socket: WebSocket) {
         }
     }
 
+    // 接收任务转交的命令（pong 回复、订阅变更）
+    let (command_tx, mut command_rx) = mpsc::unbounded_channel::<ClientCommand>();
+
     // 启动消息发送任务
     let mut sender_task = tokio::spawn(async move {
-        while let Ok(message) = rx.recv().await {
+        let mut filter = EventFilter {
+            scope: DeviceScope::load(&app_state, &claims).await,
+            subscriptions: None,
+        };
+        let mut refresh = tokio::time::interval(SCOPE_REFRESH_INTERVAL);
+        refresh.tick().await;
+
+        loop {
+            let message = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(message) if filter.allows(&message) => message,
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket subscriber {} lagged, skipped {} events", claims.sub, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                command = command_rx.recv() => match command {
+                    Some(command) => filter.apply(command),
+                    None => break,
+                },
+                _ = refresh.tick() => {
+                    filter.scope = DeviceScope::load(&app_state, &claims).await;
+                    continue;
+                }
+                _ = app_state.shutdown.wait() => {
+                    let frame = CloseFrame {
+                        code: close_code::AWAY,
+                        reason: "server shutting down".into(),
+                    };
+                    let _ = sender.send(Message::Close(Some(frame))).await;
+                    break;
+                }
+            };
+
             if let Ok(text) = serde_json::to_string(&message) {
                 if sender.send(Message::Text(text)).await.is_err() {
                     break;
@@ -95,13 +276,11 @@ async fn handle_websocket(socket: WebSocket) {
     });
 
     // 处理接收到的消息
-    let connection_manager_clone = connection_manager.clone();
-    let user_id_clone = user_id.clone();
     let mut receiver_task = tokio::spawn(async move {
         while let Some(msg) = receiver.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
-                    if let Err(e) = handle_client_message(&text, &connection_manager_clone).await {
+                    if let Err(e) = handle_client_message(&text, &command_tx) {
                         error!("Error handling client message: {}", e);
                     }
                 }
@@ -110,6 +289,9 @@ async fn handle_websocket(socket: WebSocket) {
                     break;
                 }
                 Err(e) => {
+                    if echo_shared::is_websocket_capacity_error(&e) {
+                        telemetry::record_ingress_rejected("websocket");
+                    }
                     warn!("WebSocket error: {}", e);
                     break;
                 }
@@ -122,79 +304,83 @@ async fn handle_websocket(socket: WebSocket) {
     tokio::select! {
         _ = (&mut sender_task) => {
             info!("Sender task completed");
+            receiver_task.abort();
         }
         _ = (&mut receiver_task) => {
             info!("Receiver task completed");
+            sender_task.abort();
         }
     }
+}
 
-    connection_manager.remove_connection(&user_id_clone).await;
-
-    // 模拟发送一些实时更新
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
-
+/// 等待客户端发送认证消息 {"type": "auth", "token": "..."}，失败时关闭连接
+async fn wait_for_auth_message(
+    app_state: &AppState,
+    sender: &mut SplitSink<WebSocket, Message>,
+    receiver: &mut SplitStream<WebSocket>,
+) -> Option<Claims> {
+    let first_message = tokio::time::timeout(AUTH_MESSAGE_TIMEOUT, async {
         loop {
-            interval.tick().await;
-
-            // 模拟设备状态更新
-            let device_status_update = WebSocketMessage::DeviceStatusUpdate {
-                device_id: format!("dev{:03}", rand::random::<u32>() % 3 + 1),
-                status: if rand::random::<f32>() > 0.5 {
-                    DeviceStatus::Online
-                } else {
-                    DeviceStatus::Offline
-                },
-                timestamp: chrono::Utc::now(),
-            };
-
-            if let Err(e) = broadcaster.send(device_status_update) {
-                warn!("Failed to broadcast device status update: {}", e);
+            match receiver.next().await {
+                Some(Ok(Message::Text(text))) => return Some(text),
+                Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => continue,
+                _ => return None,
             }
+        }
+    })
+    .await;
 
-            // 模拟会话进度更新
-            if rand::random::<f32>() > 0.7 {
-                let session_progress = WebSocketMessage::SessionProgress {
-                    session_id: format!("sess{:03}", rand::random::<u32>() % 100 + 1),
-                    device_id: format!("dev{:03}", rand::random::<u32>() % 3 + 1),
-                    stage: SessionStage::Processing,
-                    progress: rand::random::<f32>() * 100.0,
-                    message: "正在处理语音命令...".to_string(),
-                };
-
-                if let Err(e) = broadcaster.send(session_progress) {
-                    warn!("Failed to broadcast session progress: {}", e);
-                }
-            }
+    let claims = match first_message {
+        Ok(Some(text)) => match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(ClientMessage::Auth { token }) => match decode_jwt_token(&token) {
+                Ok(claims) => ensure_token_current(app_state, &claims).await.ok().map(|_| claims),
+                Err(_) => None,
+            },
+            _ => None,
+        },
+        // 客户端在认证前断开
+        Ok(None) => return None,
+        Err(_) => {
+            warn!("WebSocket client did not authenticate within {:?}", AUTH_MESSAGE_TIMEOUT);
+            None
         }
-    });
+    };
+
+    if claims.is_none() {
+        warn!("Rejected unauthenticated WebSocket connection");
+        let rejection = notification(NotificationLevel::Error, "认证失败", "无效或缺失的 token".to_string());
+        if let Ok(text) = serde_json::to_string(&rejection) {
+            let _ = sender.send(Message::Text(text)).await;
+        }
+        let _ = sender.send(Message::Close(None)).await;
+    }
+
+    claims
 }
 
-async fn handle_client_message(
+fn handle_client_message(
     message: &str,
-    connection_manager: &ConnectionManager,
+    command_tx: &mpsc::UnboundedSender<ClientCommand>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let parsed: serde_json::Value = serde_json::from_str(message)?;
-
-    if let Some(msg_type) = parsed.get("type").and_then(|v| v.as_str()) {
-        match msg_type {
-            "ping" => {
-                // 响应客户端 ping
-                let pong_message = WebSocketMessage::SystemNotification {
-                    level: NotificationLevel::Info,
-                    title: "Pong".to_string(),
-                    message: "服务器响应".to_string(),
-                };
-
-                // 广播 pong 消息（实际生产环境中应该只发送给特定客户端）
-                // 这里简化为广播给所有客户端
-                connection_manager.broadcast(pong_message).await;
-            }
-            _ => {
-                warn!("Unknown message type: {}", msg_type);
-            }
+    let command = match serde_json::from_str::<ClientMessage>(message) {
+        // 响应客户端 ping
+        Ok(ClientMessage::Ping {}) => ClientCommand::Reply(notification(
+            NotificationLevel::Info,
+            "Pong",
+            "服务器响应".to_string(),
+        )),
+        Ok(ClientMessage::Subscribe { device_id }) => ClientCommand::Subscribe(device_id),
+        Ok(ClientMessage::Unsubscribe { device_id }) => ClientCommand::Unsubscribe(device_id),
+        Ok(ClientMessage::Auth { .. }) => {
+            warn!("Ignoring auth message on an authenticated connection");
+            return Ok(());
         }
-    }
+        Err(e) => {
+            warn!("Unknown client message: {}", e);
+            return Ok(());
+        }
+    };
 
+    command_tx.send(command)?;
     Ok(())
-}
\ No newline at end of file
+}
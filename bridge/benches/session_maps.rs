@@ -0,0 +1,112 @@
+//! 会话映射表并发吞吐对比
+//!
+//! 模拟每个会话一个任务、每帧查询一次映射、偶尔注册/注销会话的访问模式，
+//! 对比 `RwLock<HashMap>` 与 `DashMap` 在 500 / 1000 / 2000 个并发会话下的每秒查询数。
+//!
+//! 运行：`cargo bench -p echo-bridge --bench session_maps`
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use tokio::sync::RwLock;
+
+// 每个会话处理的帧数
+const FRAMES_PER_SESSION: usize = 2_000;
+// 每隔多少帧做一次写操作（模拟会话注册/注销）
+const WRITE_EVERY: usize = 200;
+const SESSION_COUNTS: &[usize] = &[500, 1_000, 2_000];
+
+type Mapping = (String, String);
+
+fn session_entry(i: usize) -> (String, Mapping) {
+    (format!("bridge_{}", i), (format!("device_{}", i), format!("ek_{}", i)))
+}
+
+async fn run_rwlock(sessions: usize) -> Duration {
+    let map: Arc<RwLock<HashMap<String, Mapping>>> =
+        Arc::new(RwLock::new((0..sessions).map(session_entry).collect()));
+    let start = Instant::now();
+
+    let tasks: Vec<_> = (0..sessions)
+        .map(|i| {
+            let map = map.clone();
+            tokio::spawn(async move {
+                let (key, value) = session_entry(i);
+                for frame in 0..FRAMES_PER_SESSION {
+                    if frame % WRITE_EVERY == 0 {
+                        map.write().await.insert(key.clone(), value.clone());
+                    } else {
+                        let device = map.read().await.get(&key).map(|(device, _)| device.clone());
+                        std::hint::black_box(device);
+                    }
+                    if frame % 64 == 0 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("benchmark task panicked");
+    }
+
+    start.elapsed()
+}
+
+async fn run_dashmap(sessions: usize) -> Duration {
+    let map: Arc<DashMap<String, Mapping>> = Arc::new((0..sessions).map(session_entry).collect());
+    let start = Instant::now();
+
+    let tasks: Vec<_> = (0..sessions)
+        .map(|i| {
+            let map = map.clone();
+            tokio::spawn(async move {
+                let (key, value) = session_entry(i);
+                for frame in 0..FRAMES_PER_SESSION {
+                    if frame % WRITE_EVERY == 0 {
+                        map.insert(key.clone(), value.clone());
+                    } else {
+                        let device = map.get(&key).map(|entry| entry.value().0.clone());
+                        std::hint::black_box(device);
+                    }
+                    if frame % 64 == 0 {
+                        tokio::task::yield_now().await;
+                    }
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("benchmark task panicked");
+    }
+
+    start.elapsed()
+}
+
+fn ops_per_second(sessions: usize, elapsed: Duration) -> f64 {
+    (sessions * FRAMES_PER_SESSION) as f64 / elapsed.as_secs_f64()
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build Tokio runtime");
+
+    println!("{:>8}  {:>18}  {:>18}  {:>8}", "sessions", "RwLock<HashMap>", "DashMap", "speedup");
+    for &sessions in SESSION_COUNTS {
+        let rwlock = runtime.block_on(run_rwlock(sessions));
+        let dashmap = runtime.block_on(run_dashmap(sessions));
+        let rwlock_ops = ops_per_second(sessions, rwlock);
+        let dashmap_ops = ops_per_second(sessions, dashmap);
+        println!(
+            "{:>8}  {:>14.0} op/s  {:>14.0} op/s  {:>7.2}x",
+            sessions,
+            rwlock_ops,
+            dashmap_ops,
+            dashmap_ops / rwlock_ops
+        );
+    }
+}
@@ -0,0 +1,4 @@
+// 构建脚本：生成构建信息（版本号、git 提交、构建时间），由 build_info 模块引用
+fn main() {
+    built::write_built_file().expect("failed to acquire build-time information");
+}
@@ -0,0 +1,104 @@
+//! 告警
+//!
+//! 按 `alerts.check_interval_seconds` 采集 EchoKit 连接、会话失败数和数据库连接池状态，
+//! 由 `AlertEvaluator` 判定规则，触发或恢复时 POST 到配置的 Webhook 并发布到
+//! MQTT `system/bridge/alert`。
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use echo_shared::{Alert, AlertConfig, AlertEvaluator, AlertSample, AlertState, MqttMessageBuilder};
+use sqlx::PgPool;
+use tracing::{error, info, warn};
+
+use crate::echokit::EchoKitConnectionPool;
+use crate::mqtt_client::BridgeMqttClient;
+use crate::telemetry;
+
+// Webhook 请求超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 告警采集的数据来源
+pub struct AlertSources {
+    pub echokit_connection_pool: Arc<EchoKitConnectionPool>,
+    /// SQLite 存储时为 None，连接池使用率按 0 计算
+    pub db_pool: Option<PgPool>,
+    pub mqtt_client: Arc<BridgeMqttClient>,
+}
+
+/// 启动告警检查任务，`alerts.enabled` 为 false 时不启动
+pub fn spawn(config: AlertConfig, sources: AlertSources) {
+    if !config.enabled || config.rules.is_empty() {
+        info!("Alerting disabled");
+        return;
+    }
+
+    info!(
+        "Alerting enabled: {} rules, {} webhooks, MQTT: {}",
+        config.rules.len(),
+        config.webhook_urls.len(),
+        config.mqtt
+    );
+
+    tokio::spawn(async move {
+        let http = match reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build() {
+            Ok(http) => http,
+            Err(e) => {
+                error!("Failed to create alert webhook client: {}", e);
+                return;
+            }
+        };
+        let mut evaluator = AlertEvaluator::new("echo-bridge", &config);
+        let mut interval = tokio::time::interval(Duration::from_secs(config.check_interval_seconds));
+
+        loop {
+            interval.tick().await;
+            let sample = collect(&sources).await;
+            for alert in evaluator.evaluate(Instant::now(), &sample) {
+                notify(&config, &sources, &http, alert).await;
+            }
+        }
+    });
+}
+
+async fn collect(sources: &AlertSources) -> AlertSample {
+    let (sessions_created, sessions_failed) = telemetry::session_totals();
+    let (db_pool_active, db_pool_max) = sources.db_pool.as_ref().map_or((0, 0), |pool| {
+        (pool.size().saturating_sub(pool.num_idle() as u32), pool.options().get_max_connections())
+    });
+
+    AlertSample {
+        echokit_connections: sources.echokit_connection_pool.get_connection_count().await,
+        echokit_connected: sources.echokit_connection_pool.get_connected_count().await,
+        sessions_created,
+        sessions_failed,
+        db_pool_active,
+        db_pool_max,
+    }
+}
+
+async fn notify(config: &AlertConfig, sources: &AlertSources, http: &reqwest::Client, alert: Alert) {
+    match alert.state {
+        AlertState::Firing => warn!("Alert {} firing: {}", alert.rule, alert.message),
+        AlertState::Resolved => info!("Alert {} resolved", alert.rule),
+    }
+
+    for url in &config.webhook_urls {
+        let result = http
+            .post(url)
+            .json(&alert)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            error!("Failed to deliver alert {} to webhook {}: {}", alert.rule, url, e);
+        }
+    }
+
+    if config.mqtt {
+        let message = MqttMessageBuilder::system_alert("bridge".to_string(), alert);
+        if let Err(e) = sources.mqtt_client.publish(message).await {
+            error!("Failed to publish alert to MQTT: {}", e);
+        }
+    }
+}
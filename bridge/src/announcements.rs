@@ -0,0 +1,131 @@
+//! 语音播报
+//!
+//! 收到播报命令（`announce`）时，如果目标设备连接在本副本，请求 EchoKit Server 所用的 TTS 服务
+//! （OpenAI 兼容的 /v1/audio/speech 接口）合成文本，转为 16kHz 16-bit 单声道 PCM 后按
+//! StartAudio → AudioChunk → EndAudio → EndResponse 的顺序发送给设备，与 EchoKit 的回复相同。
+//! 音频按播放速度发送（最多提前 [`PLAYBACK_LEAD`]），避免设备缓冲区溢出。
+//! 同一设备同时只播放一条播报，播放期间收到的播报被丢弃。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use echo_shared::AnnouncementTtsConfig;
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use crate::audio_import::decode_recording;
+use crate::telemetry;
+use crate::websocket::connection_manager::DeviceConnectionManager;
+use crate::websocket::protocol::ServerEvent;
+
+// 16kHz 16-bit 单声道 PCM 每秒的字节数
+const BYTES_PER_SECOND: usize = 32_000;
+/// 音频最多比实际播放进度提前发送的时长
+const PLAYBACK_LEAD: Duration = Duration::from_secs(1);
+
+/// 语音播报播放器
+pub struct AnnouncementPlayer {
+    config: AnnouncementTtsConfig,
+    http: reqwest::Client,
+    connections: Arc<DeviceConnectionManager>,
+    /// 正在播报的设备
+    playing: Mutex<HashSet<String>>,
+}
+
+impl AnnouncementPlayer {
+    pub fn new(config: AnnouncementTtsConfig, connections: Arc<DeviceConnectionManager>) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .build()
+            .context("Failed to create TTS HTTP client")?;
+        Ok(Self {
+            config,
+            http,
+            connections,
+            playing: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// 在后台播报，不阻塞命令处理；设备没有连接在本副本时忽略（由连接该设备的副本播报）
+    pub fn spawn_announce(self: &Arc<Self>, device_id: &str, text: &str) {
+        let player = self.clone();
+        let device_id = device_id.to_string();
+        let text = text.to_string();
+        tokio::spawn(async move {
+            if !player.connections.is_device_online(&device_id).await {
+                debug!("Announcement for device {} ignored: device is not connected to this bridge", device_id);
+                return;
+            }
+            if !player.playing.lock().unwrap().insert(device_id.clone()) {
+                warn!("Dropped announcement for device {}: another announcement is playing", device_id);
+                telemetry::record_announcement("busy");
+                return;
+            }
+
+            let result = player.announce(&device_id, &text).await;
+            player.playing.lock().unwrap().remove(&device_id);
+            match result {
+                Ok(seconds) => {
+                    info!("Played {:.1}s announcement on device {}", seconds, device_id);
+                    telemetry::record_announcement("played");
+                }
+                Err(e) => {
+                    warn!("Failed to play announcement on device {}: {:#}", device_id, e);
+                    telemetry::record_announcement("failed");
+                }
+            }
+        });
+    }
+
+    /// 合成并播放，返回播放的音频时长（秒）
+    async fn announce(&self, device_id: &str, text: &str) -> Result<f64> {
+        let mut pcm = self.synthesize(text).await?;
+        pcm.truncate(self.config.max_audio_seconds as usize * BYTES_PER_SECOND);
+
+        let session_id = format!("announcement-{}", uuid::Uuid::new_v4());
+        let send = |event: ServerEvent| self.connections.send_server_event(device_id, &session_id, event);
+
+        send(ServerEvent::StartAudio { text: text.to_string() }).await?;
+        let started = Instant::now();
+        let mut sent = 0;
+        for chunk in pcm.chunks(self.config.chunk_bytes) {
+            let position = Duration::from_secs_f64(sent as f64 / BYTES_PER_SECOND as f64);
+            if let Some(ahead) = position.checked_sub(started.elapsed() + PLAYBACK_LEAD) {
+                tokio::time::sleep(ahead).await;
+            }
+            send(ServerEvent::AudioChunk { data: chunk.to_vec() }).await?;
+            sent += chunk.len();
+        }
+        send(ServerEvent::EndAudio).await?;
+        send(ServerEvent::EndResponse).await?;
+
+        Ok(sent as f64 / BYTES_PER_SECOND as f64)
+    }
+
+    /// 请求 TTS 服务合成 WAV 音频，解码为 16kHz 16-bit 单声道 PCM
+    async fn synthesize(&self, text: &str) -> Result<Vec<u8>> {
+        let mut request = self.http.post(&self.config.tts_url).json(&json!({
+            "model": self.config.model,
+            "voice": self.config.voice,
+            "input": text,
+            "response_format": "wav",
+        }));
+        if !self.config.api_key.is_empty() {
+            request = request.bearer_auth(&self.config.api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("TTS request failed")?;
+        let audio = response.bytes().await.context("Failed to read TTS response")?;
+
+        let recording = decode_recording(&audio).context("Failed to decode TTS audio")?;
+        if recording.pcm.is_empty() {
+            bail!("TTS returned no audio");
+        }
+        Ok(recording.pcm)
+    }
+}
@@ -2,13 +2,13 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     response::Json,
 };
-use echo_shared::{ApiResponse, Session};
+use echo_shared::{ApiResponse, DeviceId, EchoError, Session, UserId};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::{info, error};
+use crate::error::ApiError;
 use crate::session::SessionManager;
 
 // API State
@@ -20,8 +20,8 @@ pub struct ApiState {
 // Request/Response types
 #[derive(Debug, Deserialize)]
 pub struct CreateSessionRequest {
-    pub device_id: String,
-    pub user_id: String,
+    pub device_id: DeviceId,
+    pub user_id: UserId,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,6 +33,8 @@ pub struct UpdateTranscriptionRequest {
 pub struct CompleteSessionRequest {
     pub transcription: String,
     pub response: String,
+    #[serde(default)]
+    pub audio_url: Option<String>,
 }
 
 // ========================================================================
@@ -43,7 +45,7 @@ pub struct CompleteSessionRequest {
 pub async fn create_session(
     State(state): State<ApiState>,
     Json(payload): Json<CreateSessionRequest>,
-) -> Result<Json<ApiResponse<Session>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<Session>>, ApiError> {
     info!("API: Creating session for device: {}, user: {}",
           payload.device_id, payload.user_id);
 
@@ -54,8 +56,7 @@ pub async fn create_session(
         }
         Err(e) => {
             error!("API: Failed to create session: {}", e);
-            let response = ApiResponse::error(format!("Failed to create session: {}", e));
-            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
+            Err(e.into())
         }
     }
 }
@@ -65,7 +66,7 @@ pub async fn update_transcription(
     Path(session_id): Path<String>,
     State(state): State<ApiState>,
     Json(payload): Json<UpdateTranscriptionRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     info!("API: Updating transcription for session: {}", session_id);
 
     // Check if session exists
@@ -79,15 +80,13 @@ pub async fn update_transcription(
                 }
                 Err(e) => {
                     error!("API: Failed to update transcription: {}", e);
-                    let response = ApiResponse::error(format!("Failed to update transcription: {}", e));
-                    Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
+                    Err(e.into())
                 }
             }
         }
         None => {
             error!("API: Session not found: {}", session_id);
-            let response = ApiResponse::error("Session not found".to_string());
-            Err((StatusCode::NOT_FOUND, Json(response)))
+            Err(EchoError::SessionNotFound(session_id).into())
         }
     }
 }
@@ -97,7 +96,7 @@ pub async fn complete_session(
     Path(session_id): Path<String>,
     State(state): State<ApiState>,
     Json(payload): Json<CompleteSessionRequest>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<()>>, ApiError> {
     info!("API: Completing session: {}", session_id);
 
     // Check if session exists
@@ -107,7 +106,8 @@ pub async fn complete_session(
             match state.session_manager.complete_session(
                 &session_id,
                 payload.transcription,
-                payload.response
+                payload.response,
+                payload.audio_url,
             ).await {
                 Ok(_) => {
                     info!("API: Session completed successfully: {}", session_id);
@@ -115,15 +115,13 @@ pub async fn complete_session(
                 }
                 Err(e) => {
                     error!("API: Failed to complete session: {}", e);
-                    let response = ApiResponse::error(format!("Failed to complete session: {}", e));
-                    Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
+                    Err(e.into())
                 }
             }
         }
         None => {
             error!("API: Session not found: {}", session_id);
-            let response = ApiResponse::error("Session not found".to_string());
-            Err((StatusCode::NOT_FOUND, Json(response)))
+            Err(EchoError::SessionNotFound(session_id).into())
         }
     }
 }
@@ -132,7 +130,7 @@ pub async fn complete_session(
 pub async fn get_session(
     Path(session_id): Path<String>,
     State(state): State<ApiState>,
-) -> Result<Json<ApiResponse<Session>>, (StatusCode, Json<ApiResponse<()>>)> {
+) -> Result<Json<ApiResponse<Session>>, ApiError> {
     info!("API: Getting session: {}", session_id);
 
     match state.session_manager.get_session(&session_id).await {
@@ -142,8 +140,7 @@ pub async fn get_session(
         }
         None => {
             error!("API: Session not found: {}", session_id);
-            let response = ApiResponse::error("Session not found".to_string());
-            Err((StatusCode::NOT_FOUND, Json(response)))
+            Err(EchoError::SessionNotFound(session_id).into())
         }
     }
 }
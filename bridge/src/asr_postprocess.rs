@@ -0,0 +1,339 @@
+//! ASR 文本后处理
+//!
+//! EchoKit 返回的识别结果在保存到会话记录和转发给设备之前依次经过一条处理链。处理器实现
+//! [`AsrPostProcessor`]，内置自定义词汇替换、脏话屏蔽和标点恢复三种，按配置的顺序组成 [`AsrPipeline`]。
+//! 每个设备所属账号（组织）可以配置各自的处理链，设备所属用户从数据库查询并缓存
+//! `owner_cache_seconds` 秒；查询失败或未单独配置的账号使用默认处理链。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use echo_shared::{AsrPipelineConfig, AsrPostProcessingConfig, AsrProcessorKind};
+use sqlx::PgPool;
+use tracing::{debug, warn};
+
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+
+/// ASR 文本处理器
+pub trait AsrPostProcessor: Send + Sync {
+    /// 处理器名称，用于指标标签
+    fn name(&self) -> &'static str;
+
+    /// 处理一条识别结果，返回处理后的文本
+    fn process(&self, text: &str) -> String;
+}
+
+/// 自定义词汇替换：把识别结果中的常见误识别写法替换为正确写法（产品名、人名等）
+pub struct VocabularySubstitution {
+    // 按长度降序排列，较长的短语优先匹配
+    entries: Vec<(String, String)>,
+}
+
+impl VocabularySubstitution {
+    pub fn new<'a>(vocabulary: impl IntoIterator<Item = (&'a String, &'a String)>) -> Self {
+        let mut entries: Vec<(String, String)> = vocabulary
+            .into_iter()
+            .map(|(phrase, replacement)| (phrase.trim().to_string(), replacement.clone()))
+            .filter(|(phrase, _)| !phrase.is_empty())
+            .collect();
+        entries.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.len()));
+        Self { entries }
+    }
+}
+
+impl AsrPostProcessor for VocabularySubstitution {
+    fn name(&self) -> &'static str {
+        "vocabulary"
+    }
+
+    fn process(&self, text: &str) -> String {
+        replace_phrases(text, self.entries.iter().map(|(phrase, _)| phrase.as_str()), |index, _| {
+            self.entries[index].1.clone()
+        })
+    }
+}
+
+/// 脏话屏蔽：把屏蔽词的每个字符替换为屏蔽字符
+pub struct ProfanityFilter {
+    words: Vec<String>,
+    mask: char,
+}
+
+impl ProfanityFilter {
+    pub fn new(words: &[String], mask: char) -> Self {
+        let mut words: Vec<String> =
+            words.iter().map(|word| word.trim().to_string()).filter(|word| !word.is_empty()).collect();
+        words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+        Self { words, mask }
+    }
+}
+
+impl AsrPostProcessor for ProfanityFilter {
+    fn name(&self) -> &'static str {
+        "profanity"
+    }
+
+    fn process(&self, text: &str) -> String {
+        replace_phrases(text, self.words.iter().map(String::as_str), |_, matched| {
+            std::iter::repeat_n(self.mask, matched.chars().count()).collect()
+        })
+    }
+}
+
+/// 标点恢复：识别结果通常不带句末标点，按最后一个字符补全中文或英文句号，疑问句补问号；英文句首字母大写
+pub struct PunctuationRestorer;
+
+// 已有的句末标点
+const SENTENCE_ENDINGS: &[char] = &['.', '!', '?', '。', '！', '？', '…', ',', '，', ';', '；', ':', '：', '~', '～'];
+// 中文疑问句的句末语气词
+const CHINESE_QUESTION_PARTICLES: &[char] = &['吗', '呢', '么'];
+// 英文疑问句的句首词
+const ENGLISH_QUESTION_WORDS: &[&str] = &[
+    "what", "who", "whom", "whose", "where", "when", "why", "how", "which", "is", "are", "am", "was", "were", "do",
+    "does", "did", "can", "could", "will", "would", "should", "shall", "may", "might", "have", "has",
+];
+
+impl AsrPostProcessor for PunctuationRestorer {
+    fn name(&self) -> &'static str {
+        "punctuation"
+    }
+
+    fn process(&self, text: &str) -> String {
+        let text = text.trim();
+        let Some(last) = text.chars().next_back() else {
+            return String::new();
+        };
+
+        let mut output = String::with_capacity(text.len() + 3);
+        let mut chars = text.chars();
+        if let Some(first) = chars.next() {
+            output.push(first.to_ascii_uppercase());
+            output.push_str(chars.as_str());
+        }
+        if SENTENCE_ENDINGS.contains(&last) {
+            return output;
+        }
+
+        if is_cjk(last) {
+            output.push(if CHINESE_QUESTION_PARTICLES.contains(&last) { '？' } else { '。' });
+        } else {
+            let first_word = text.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or_default();
+            let question = ENGLISH_QUESTION_WORDS.iter().any(|word| word.eq_ignore_ascii_case(first_word));
+            output.push(if question { '?' } else { '.' });
+        }
+        output
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+
+/// 按给定顺序（较长的短语在前）替换文本中出现的短语
+///
+/// 英文不区分大小写；以字母或数字开头或结尾的短语只在单词边界处匹配，避免替换单词的一部分
+fn replace_phrases<'a>(
+    text: &str,
+    phrases: impl Iterator<Item = &'a str> + Clone,
+    replace: impl Fn(usize, &str) -> String,
+) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut position = 0;
+    'scan: while let Some(c) = text[position..].chars().next() {
+        let previous = text[..position].chars().next_back();
+        for (index, phrase) in phrases.clone().enumerate() {
+            let end = position + phrase.len();
+            let Some(candidate) = text.get(position..end) else {
+                continue;
+            };
+            if !candidate.eq_ignore_ascii_case(phrase) {
+                continue;
+            }
+            let starts_word = phrase.chars().next().is_some_and(is_word_char);
+            let ends_word = phrase.chars().next_back().is_some_and(is_word_char);
+            if (starts_word && previous.is_some_and(is_word_char))
+                || (ends_word && text[end..].chars().next().is_some_and(is_word_char))
+            {
+                continue;
+            }
+            output.push_str(&replace(index, candidate));
+            position = end;
+            continue 'scan;
+        }
+        output.push(c);
+        position += c.len_utf8();
+    }
+    output
+}
+
+/// 按顺序执行的处理链
+pub struct AsrPipeline {
+    processors: Vec<Box<dyn AsrPostProcessor>>,
+}
+
+impl AsrPipeline {
+    pub fn new(processors: Vec<Box<dyn AsrPostProcessor>>) -> Self {
+        Self { processors }
+    }
+
+    /// 按配置创建内置处理器组成的处理链
+    pub fn from_config(config: &AsrPipelineConfig) -> Self {
+        let mask = config.profanity_mask.chars().next().unwrap_or('*');
+        let processors = config
+            .processors
+            .iter()
+            .map(|kind| -> Box<dyn AsrPostProcessor> {
+                match kind {
+                    AsrProcessorKind::Vocabulary => Box::new(VocabularySubstitution::new(&config.vocabulary)),
+                    AsrProcessorKind::Profanity => Box::new(ProfanityFilter::new(&config.profanity_words, mask)),
+                    AsrProcessorKind::Punctuation => Box::new(PunctuationRestorer),
+                }
+            })
+            .collect();
+        Self::new(processors)
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for processor in &self.processors {
+            let processed = processor.process(&text);
+            telemetry::record_asr_postprocess(processor.name(), processed != text);
+            text = processed;
+        }
+        text
+    }
+}
+
+/// 按设备所属账号选择处理链的 ASR 文本后处理
+pub struct AsrPostProcessing {
+    default: AsrPipeline,
+    owners: HashMap<String, AsrPipeline>,
+    db: Arc<PgPool>,
+    owner_cache_ttl: Duration,
+    // device_id -> (所属用户, 查询时间)
+    device_owners: DashMap<String, (Option<String>, Instant)>,
+}
+
+impl AsrPostProcessing {
+    pub fn new(config: &AsrPostProcessingConfig, db: Arc<PgPool>) -> Self {
+        Self {
+            default: AsrPipeline::from_config(&config.default),
+            owners: config
+                .owners
+                .iter()
+                .map(|(owner, pipeline)| (owner.clone(), AsrPipeline::from_config(pipeline)))
+                .collect(),
+            db,
+            owner_cache_ttl: Duration::from_secs(config.owner_cache_seconds),
+            device_owners: DashMap::new(),
+        }
+    }
+
+    /// 用设备所属账号的处理链处理识别结果
+    pub async fn process(&self, device_id: &str, text: String) -> String {
+        let pipeline = match self.owners.is_empty() {
+            true => &self.default,
+            false => self
+                .device_owner(device_id)
+                .await
+                .and_then(|owner| self.owners.get(&owner))
+                .unwrap_or(&self.default),
+        };
+        let processed = pipeline.apply(&text);
+        if processed != text {
+            debug!(
+                "ASR text for device {} post-processed: {} -> {}",
+                device_id,
+                echo_shared::redact_for_log(&text),
+                echo_shared::redact_for_log(&processed)
+            );
+        }
+        processed
+    }
+
+    // 查询设备所属用户，查询失败时不缓存，下次重试
+    async fn device_owner(&self, device_id: &str) -> Option<String> {
+        if let Some(entry) = self.device_owners.get(device_id) {
+            let (owner, fetched_at) = entry.value();
+            if fetched_at.elapsed() < self.owner_cache_ttl {
+                return owner.clone();
+            }
+        }
+
+        let result = timed_query(
+            "asr_postprocess_device_owner",
+            sqlx::query_scalar::<_, Option<String>>("SELECT owner FROM devices WHERE id = $1")
+                .bind(device_id)
+                .fetch_optional(self.db.as_ref()),
+        )
+        .await;
+        match result {
+            Ok(owner) => {
+                let owner = owner.flatten();
+                self.device_owners.insert(device_id.to_string(), (owner.clone(), Instant::now()));
+                owner
+            }
+            Err(e) => {
+                warn!("Failed to look up owner of device {} for ASR post-processing: {}", device_id, e);
+                None
+            }
+        }
+    }
+}
+
+/// 启用后处理时处理识别结果，否则原样返回
+pub async fn apply(postprocessing: Option<&AsrPostProcessing>, device_id: &str, text: String) -> String {
+    match postprocessing {
+        Some(postprocessing) => postprocessing.process(device_id, text).await,
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_vocabulary_matches_whole_words_ignoring_case() {
+        let vocabulary = BTreeMap::from([
+            ("echo kit".to_string(), "EchoKit".to_string()),
+            ("echo".to_string(), "Echo".to_string()),
+            ("艾口".to_string(), "Echo".to_string()),
+        ]);
+        let processor = VocabularySubstitution::new(&vocabulary);
+
+        assert_eq!(processor.process("open ECHO KIT and echoes"), "open EchoKit and echoes");
+        assert_eq!(processor.process("打开艾口设置"), "打开Echo设置");
+    }
+
+    #[test]
+    fn test_profanity_filter_masks_each_character() {
+        let processor = ProfanityFilter::new(&["damn".to_string(), "笨蛋".to_string()], '*');
+
+        assert_eq!(processor.process("Damn, you 笨蛋"), "****, you **");
+        assert_eq!(processor.process("amsterdamn"), "amsterdamn");
+    }
+
+    #[test]
+    fn test_pipeline_runs_processors_in_order() {
+        let config = AsrPipelineConfig {
+            processors: vec![AsrProcessorKind::Vocabulary, AsrProcessorKind::Punctuation],
+            vocabulary: BTreeMap::from([("echo kit".to_string(), "EchoKit".to_string())]),
+            ..AsrPipelineConfig::default()
+        };
+        let pipeline = AsrPipeline::from_config(&config);
+
+        assert_eq!(pipeline.apply(" what time is it "), "What time is it?");
+        assert_eq!(pipeline.apply("echo kit turn on the light"), "EchoKit turn on the light.");
+        assert_eq!(pipeline.apply("今天天气怎么样呢"), "今天天气怎么样呢？");
+        assert_eq!(pipeline.apply("打开灯。"), "打开灯。");
+    }
+}
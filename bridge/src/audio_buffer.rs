@@ -0,0 +1,211 @@
+//! 音频缓冲区复用与增量解码
+//!
+//! 长 TTS 回复会连续到达大量音频帧，逐帧分配临时缓冲区会造成频繁的分配和释放：
+//!
+//! - `BufferPool`：少量可复用的音频缓冲区，取用时清空，归还时超过容量上限的缓冲区直接释放
+//! - `Base64Decoder`：增量解码 Base64 音频，输入可以按任意边界分段，直接写入调用方的缓冲区，
+//!   不生成中间字符串或向量
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{DecodeSliceError, Engine};
+
+use crate::telemetry;
+
+// 池中最多保留的缓冲区数
+const POOL_MAX_BUFFERS: usize = 16;
+// 超过该容量的缓冲区归还时直接释放，避免偶发的大帧长期占用内存
+const POOL_MAX_CAPACITY: usize = 256 * 1024;
+
+// 标准字母表，尾部分组有无填充均可
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+static AUDIO_POOL: BufferPool = BufferPool::new(POOL_MAX_BUFFERS, POOL_MAX_CAPACITY);
+
+/// 进程内共享的音频缓冲池
+pub fn audio_pool() -> &'static BufferPool {
+    &AUDIO_POOL
+}
+
+/// 可复用的字节缓冲池
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    max_capacity: usize,
+}
+
+impl BufferPool {
+    pub const fn new(max_buffers: usize, max_capacity: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            max_buffers,
+            max_capacity,
+        }
+    }
+
+    /// 取出一个空缓冲区，离开作用域时自动归还
+    pub fn take(&self) -> PooledBuffer<'_> {
+        let buffer = self.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        telemetry::record_buffer_pool(buffer.is_some());
+        PooledBuffer { buffer: buffer.unwrap_or_default(), pool: self }
+    }
+
+    fn put(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 || buffer.capacity() > self.max_capacity {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+}
+
+/// 从 `BufferPool` 取出的缓冲区
+pub struct PooledBuffer<'a> {
+    buffer: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        self.pool.put(std::mem::take(&mut self.buffer));
+    }
+}
+
+/// 增量 Base64 解码器
+///
+/// 每次只解码完整的 4 字符分组，不完整的尾部留到下一段输入或 `finish` 时处理。
+#[derive(Debug, Default)]
+pub struct Base64Decoder {
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+impl Base64Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 解码一段输入并追加到 `output`
+    pub fn push(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<(), DecodeSliceError> {
+        // 先补齐上一段留下的不完整分组
+        if self.pending_len > 0 {
+            let needed = (4 - self.pending_len).min(input.len());
+            self.pending[self.pending_len..self.pending_len + needed].copy_from_slice(&input[..needed]);
+            self.pending_len += needed;
+            input = &input[needed..];
+            if self.pending_len < 4 {
+                return Ok(());
+            }
+            self.pending_len = 0;
+            decode_into(&self.pending, output)?;
+        }
+
+        let whole = input.len() / 4 * 4;
+        decode_into(&input[..whole], output)?;
+
+        let rest = &input[whole..];
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+        Ok(())
+    }
+
+    /// 结束输入，解码剩余的无填充尾部分组
+    pub fn finish(self, output: &mut Vec<u8>) -> Result<(), DecodeSliceError> {
+        decode_into(&self.pending[..self.pending_len], output)
+    }
+}
+
+// 直接解码到 output 的尾部，失败时恢复 output 原长度
+fn decode_into(input: &[u8], output: &mut Vec<u8>) -> Result<(), DecodeSliceError> {
+    if input.is_empty() {
+        return Ok(());
+    }
+    let start = output.len();
+    output.resize(start + base64::decoded_len_estimate(input.len()), 0);
+    match BASE64.decode_slice(input, &mut output[start..]) {
+        Ok(written) => {
+            output.truncate(start + written);
+            Ok(())
+        }
+        Err(e) => {
+            output.truncate(start);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::STANDARD;
+
+    #[test]
+    fn test_base64_decoder_handles_arbitrary_split_points() {
+        let pcm: Vec<u8> = (0..=255u8).cycle().take(1001).collect();
+        let encoded = STANDARD.encode(&pcm);
+
+        for split in [1, 3, 4, 7, 64, 333] {
+            let mut decoder = Base64Decoder::new();
+            let mut output = Vec::new();
+            for chunk in encoded.as_bytes().chunks(split) {
+                decoder.push(chunk, &mut output).unwrap();
+            }
+            decoder.finish(&mut output).unwrap();
+            assert_eq!(output, pcm, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_base64_decoder_rejects_invalid_input() {
+        let mut decoder = Base64Decoder::new();
+        let mut output = vec![1, 2, 3];
+        assert!(decoder.push(b"AA*A", &mut output).is_err());
+        // 解码失败不破坏已有数据
+        assert_eq!(output, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_buffer_pool_reuses_released_buffers() {
+        let pool = BufferPool::new(1, 1024);
+        let capacity = {
+            let mut buffer = pool.take();
+            buffer.extend_from_slice(&[0u8; 512]);
+            buffer.capacity()
+        };
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), capacity);
+
+        // 超过容量上限的缓冲区不回收
+        drop(buffer);
+        {
+            let mut buffer = pool.take();
+            buffer.reserve(4096);
+        }
+        assert_eq!(pool.take().capacity(), 0);
+    }
+}
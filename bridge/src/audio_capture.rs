@@ -0,0 +1,264 @@
+//! 设备上行音频抓取
+//!
+//! 通过管理 API 对指定设备开启抓取后，该设备接下来 N 秒的上行音频（WebSocket 或 UDP）
+//! 按原样保存，并封装为 16kHz 16-bit 单声道 WAV 供下载，便于现场核对麦克风质量和音频格式，
+//! 无需下发新固件。抓取完成或等待超时后保留一段时间，过期后自动删除。
+//! 未开启任何抓取时记录调用只读取一个原子变量。
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// 默认抓取时长（秒）
+pub const DEFAULT_CAPTURE_SECONDS: u64 = 10;
+/// 最长抓取时长（秒）
+pub const MAX_CAPTURE_SECONDS: u64 = 120;
+// 抓取时假定的设备音频格式：16kHz 16-bit 单声道
+const SAMPLE_RATE: u32 = 16_000;
+const BYTES_PER_SECOND: usize = SAMPLE_RATE as usize * 2;
+// 开启后设备一直未送满音频时，最长等待时间
+const CAPTURE_WAIT: Duration = Duration::from_secs(10 * 60);
+// 抓取结束后保留供下载的时间
+const CAPTURE_RETENTION: Duration = Duration::from_secs(60 * 60);
+// 最多保留的抓取数，超出时丢弃最早开启的
+const MAX_CAPTURES: usize = 16;
+
+static CAPTURE: LazyLock<AudioCapture> = LazyLock::new(AudioCapture::default);
+
+// 单个设备的抓取缓冲区
+struct DeviceCapture {
+    requested_at: DateTime<Utc>,
+    started: Instant,
+    seconds: u64,
+    target_bytes: usize,
+    capturing: bool,
+    /// 抓取中为停止等待的时间，结束后为过期时间
+    deadline: Instant,
+    pcm: Vec<u8>,
+    frames: u64,
+    odd_frames: u64,
+    transports: BTreeSet<&'static str>,
+}
+
+impl DeviceCapture {
+    fn new(seconds: u64, now: Instant) -> Self {
+        Self {
+            requested_at: Utc::now(),
+            started: now,
+            seconds,
+            target_bytes: seconds as usize * BYTES_PER_SECOND,
+            capturing: true,
+            deadline: now + CAPTURE_WAIT,
+            pcm: Vec::new(),
+            frames: 0,
+            odd_frames: 0,
+            transports: BTreeSet::new(),
+        }
+    }
+
+    fn finish(&mut self, now: Instant) {
+        self.capturing = false;
+        self.deadline = now + CAPTURE_RETENTION;
+    }
+
+    // 等待超时的抓取转为结束状态，返回是否已过期
+    fn expire(&mut self, now: Instant) -> bool {
+        if now < self.deadline {
+            return false;
+        }
+        if self.capturing {
+            self.finish(now);
+            return false;
+        }
+        true
+    }
+
+    fn push(&mut self, transport: &'static str, data: &[u8], now: Instant) {
+        let remaining = self.target_bytes.saturating_sub(self.pcm.len());
+        self.pcm.extend_from_slice(&data[..data.len().min(remaining)]);
+        self.frames += 1;
+        if !data.len().is_multiple_of(2) {
+            self.odd_frames += 1;
+        }
+        self.transports.insert(transport);
+        if self.pcm.len() >= self.target_bytes {
+            self.finish(now);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Targets {
+    /// device_id -> 抓取缓冲区
+    devices: HashMap<String, DeviceCapture>,
+}
+
+impl Targets {
+    fn prune(&mut self, now: Instant) {
+        self.devices.retain(|_, capture| !capture.expire(now));
+    }
+
+    fn any_capturing(&self) -> bool {
+        self.devices.values().any(|capture| capture.capturing)
+    }
+}
+
+/// 抓取导出结果
+pub struct AudioCaptureExport {
+    /// 16kHz 16-bit 单声道 WAV
+    pub wav: Vec<u8>,
+    pub requested_at: DateTime<Utc>,
+    /// 请求抓取的秒数
+    pub seconds: u64,
+    /// 已抓取的音频字节数
+    pub audio_bytes: usize,
+    /// 收到的音频帧数
+    pub frames: u64,
+    /// 长度为奇数（不是完整 16-bit 样本）的帧数
+    pub odd_frames: u64,
+    /// 音频来源，如 websocket、udp
+    pub transports: Vec<&'static str>,
+    pub capturing: bool,
+}
+
+/// 音频抓取登记表
+#[derive(Default)]
+pub struct AudioCapture {
+    active: AtomicBool,
+    targets: Mutex<Targets>,
+}
+
+impl AudioCapture {
+    fn update<R>(&self, f: impl FnOnce(&mut Targets, Instant) -> R) -> R {
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        targets.prune(now);
+        let result = f(&mut targets, now);
+        self.active.store(targets.any_capturing(), Ordering::Relaxed);
+        result
+    }
+
+    /// 开始抓取指定设备接下来 `seconds` 秒的上行音频，已有的抓取会被替换
+    pub fn enable(&self, device_id: &str, seconds: u64) {
+        self.update(|targets, now| {
+            if !targets.devices.contains_key(device_id) && targets.devices.len() >= MAX_CAPTURES {
+                let oldest = targets
+                    .devices
+                    .iter()
+                    .min_by_key(|(_, capture)| capture.started)
+                    .map(|(id, _)| id.clone());
+                if let Some(oldest) = oldest {
+                    targets.devices.remove(&oldest);
+                }
+            }
+            targets.devices.insert(device_id.to_string(), DeviceCapture::new(seconds, now));
+        });
+    }
+
+    /// 停止抓取并丢弃已抓取的音频，不存在时返回 false
+    pub fn discard(&self, device_id: &str) -> bool {
+        self.update(|targets, _| targets.devices.remove(device_id).is_some())
+    }
+
+    /// 记录一段设备上行音频，设备未开启抓取时忽略
+    pub fn record(&self, device_id: &str, transport: &'static str, data: &[u8]) {
+        if !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+        self.update(|targets, now| {
+            if let Some(capture) = targets.devices.get_mut(device_id) {
+                if capture.capturing {
+                    capture.push(transport, data, now);
+                }
+            }
+        });
+    }
+
+    /// 导出指定设备抓取的音频，不存在或已过期时返回 None
+    pub fn export(&self, device_id: &str) -> Option<AudioCaptureExport> {
+        self.update(|targets, _| {
+            let capture = targets.devices.get(device_id)?;
+            Some(AudioCaptureExport {
+                wav: wav_file(&capture.pcm),
+                requested_at: capture.requested_at,
+                seconds: capture.seconds,
+                audio_bytes: capture.pcm.len(),
+                frames: capture.frames,
+                odd_frames: capture.odd_frames,
+                transports: capture.transports.iter().copied().collect(),
+                capturing: capture.capturing,
+            })
+        })
+    }
+}
+
+// 为原始 PCM 加上 WAV 文件头，奇数长度按 RIFF 规范补齐一个字节
+fn wav_file(pcm: &[u8]) -> Vec<u8> {
+    let data_len = pcm.len() as u32;
+    let padding = pcm.len() % 2;
+    let mut wav = Vec::with_capacity(44 + pcm.len() + padding);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len + padding as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // 单声道
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(BYTES_PER_SECOND as u32).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(pcm);
+    wav.resize(wav.len() + padding, 0);
+    wav
+}
+
+/// 全局音频抓取登记表
+pub fn capture() -> &'static AudioCapture {
+    &CAPTURE
+}
+
+/// 记录一段设备上行音频到全局登记表
+pub fn record(device_id: &str, transport: &'static str, data: &[u8]) {
+    CAPTURE.record(device_id, transport, data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_stops_after_requested_seconds() {
+        let capture = AudioCapture::default();
+        capture.record("d1", "websocket", &[0; 640]);
+        assert!(capture.export("d1").is_none());
+
+        capture.enable("d1", 1);
+        capture.record("d1", "websocket", &[1; 20_001]);
+        capture.record("d2", "udp", &[2; 640]);
+        assert!(capture.export("d1").unwrap().capturing);
+        capture.record("d1", "udp", &[3; 20_000]);
+        capture.record("d1", "udp", &[4; 640]);
+
+        let export = capture.export("d1").unwrap();
+        assert!(!export.capturing);
+        assert!(!capture.active.load(Ordering::Relaxed));
+        assert_eq!(export.audio_bytes, BYTES_PER_SECOND);
+        assert_eq!((export.frames, export.odd_frames), (2, 1));
+        assert_eq!(export.transports, vec!["udp", "websocket"]);
+        assert_eq!(&export.wav[..4], b"RIFF");
+        assert_eq!(export.wav.len(), 44 + BYTES_PER_SECOND);
+        assert_eq!(&export.wav[40..44], &(BYTES_PER_SECOND as u32).to_le_bytes());
+        assert!(capture.export("d2").is_none());
+
+        // 抓取结束后超过保留时间即过期
+        let later = Instant::now() + CAPTURE_RETENTION;
+        capture.update(|targets, _| targets.prune(later));
+        assert!(capture.export("d1").is_none());
+    }
+}
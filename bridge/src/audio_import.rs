@@ -0,0 +1,586 @@
+//! 录音批量导入
+//!
+//! 管理接口上传的 WAV / Ogg Opus 录音先解码为 16kHz 16-bit 单声道 PCM，再按设备的 EchoKit Server
+//! 走一遍与设备会话相同的识别流程（StartChat → 音频 → Submit），收到识别结果后作为已完成的会话写入历史，
+//! 用于补录在系统之外录制的对话。每个录音使用独立的 EchoKit 连接，识别结果不会混入其他会话。
+//! 导入任务在后台执行，进度只保存在处理该请求的 Bridge 实例内存中，结束后保留一段时间供查询。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use echo_shared::{
+    AudioFormat, AudioImportConfig, AudioImportFile, AudioImportJob, AudioImportStatus, EchoError, IngressLimitsConfig,
+};
+use futures::StreamExt;
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::audio_processor::AudioFormatDetector;
+use crate::asr_postprocess::{self, AsrPostProcessing};
+use crate::channels;
+use crate::echokit_client::EchoKitClient;
+use crate::session_service::{ImportedSession, SessionService};
+use crate::slow_ops::timed_query;
+
+// EchoKit 要求的音频格式：16kHz 16-bit 单声道
+const SAMPLE_RATE: u32 = 16_000;
+// 每次发送 1 秒音频
+const CHUNK_BYTES: usize = SAMPLE_RATE as usize * 2;
+// 16kHz 下一个 Opus 包最长 120ms
+const MAX_OPUS_FRAME_SAMPLES: usize = 1920;
+// 同时进行中的导入任务上限，任务的录音在处理完之前都保存在内存中
+const MAX_ACTIVE_JOBS: usize = 8;
+// 最多保留的导入任务数，超出时丢弃最早结束的
+const MAX_JOBS: usize = 64;
+
+/// 一个上传的录音文件
+#[derive(Debug)]
+pub struct ImportUpload {
+    pub name: String,
+    pub data: Vec<u8>,
+    /// 录音时间，未提供时使用导入任务的创建时间
+    pub recorded_at: Option<DateTime<Utc>>,
+}
+
+/// 解码后的录音：16kHz 16-bit 单声道 PCM
+#[derive(Debug)]
+pub struct Recording {
+    pub pcm: Vec<u8>,
+    pub duration_seconds: f64,
+}
+
+// 导入目标设备
+#[derive(Debug, Clone)]
+struct ImportDevice {
+    id: String,
+    owner: Option<String>,
+    echokit_url: String,
+}
+
+struct JobEntry {
+    job: AudioImportJob,
+    /// 所有文件处理完成后的过期时间
+    expires_at: Option<Instant>,
+}
+
+/// 录音导入任务管理
+pub struct AudioImporter {
+    config: AudioImportConfig,
+    db: Arc<PgPool>,
+    session_service: Arc<SessionService>,
+    ingress_limits: IngressLimitsConfig,
+    asr_postprocessing: Option<Arc<AsrPostProcessing>>,
+    // 所有任务共用的识别并发数
+    permits: Arc<Semaphore>,
+    jobs: Mutex<HashMap<String, JobEntry>>,
+}
+
+impl AudioImporter {
+    pub fn new(config: AudioImportConfig, db: Arc<PgPool>, session_service: Arc<SessionService>) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(config.max_concurrent_files)),
+            config,
+            db,
+            session_service,
+            ingress_limits: IngressLimitsConfig::default(),
+            asr_postprocessing: None,
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 接收 EchoKit 消息时的大小限制，与设备会话的 EchoKit 连接相同
+    pub fn with_ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
+    /// 识别结果保存前经过的 ASR 文本后处理
+    pub fn with_asr_postprocessing(mut self, asr_postprocessing: Arc<AsrPostProcessing>) -> Self {
+        self.asr_postprocessing = Some(asr_postprocessing);
+        self
+    }
+
+    /// 创建导入任务并在后台处理，返回任务的初始状态
+    pub async fn start(self: &Arc<Self>, device_id: &str, uploads: Vec<ImportUpload>) -> Result<AudioImportJob, EchoError> {
+        if uploads.is_empty() {
+            return Err(EchoError::InvalidInput("At least one audio file is required".to_string()));
+        }
+        let device = self
+            .find_device(device_id)
+            .await?
+            .ok_or_else(|| EchoError::DeviceNotFound(device_id.to_string()))?;
+
+        let created_at = Utc::now();
+        let job = AudioImportJob {
+            id: format!("import_{}", uuid::Uuid::new_v4()),
+            device_id: device.id.clone(),
+            created_at,
+            finished_at: None,
+            files: uploads
+                .iter()
+                .map(|upload| AudioImportFile {
+                    name: upload.name.clone(),
+                    status: AudioImportStatus::Pending,
+                    duration_seconds: None,
+                    session_id: None,
+                    transcript: None,
+                    error: None,
+                })
+                .collect(),
+        };
+
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            prune_jobs(&mut jobs, Instant::now());
+            let active = jobs.values().filter(|entry| entry.job.finished_at.is_none()).count();
+            if active >= MAX_ACTIVE_JOBS {
+                return Err(EchoError::RateLimited(format!(
+                    "{} audio imports are already in progress",
+                    active
+                )));
+            }
+            jobs.insert(job.id.clone(), JobEntry { job: job.clone(), expires_at: None });
+        }
+        info!("Audio import {} started for device {} ({} files)", job.id, device.id, uploads.len());
+
+        let importer = self.clone();
+        let job_id = job.id.clone();
+        tokio::spawn(async move {
+            importer.run(&job_id, &device, uploads, created_at).await;
+        });
+        Ok(job)
+    }
+
+    /// 查询导入任务
+    pub fn job(&self, job_id: &str) -> Option<AudioImportJob> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        prune_jobs(&mut jobs, Instant::now());
+        jobs.get(job_id).map(|entry| entry.job.clone())
+    }
+
+    async fn find_device(&self, device_id: &str) -> Result<Option<ImportDevice>, EchoError> {
+        let row = timed_query("audio_import_device", sqlx::query_as::<_, (String, Option<String>, String)>(
+            "SELECT id, owner, echokit_server_url FROM devices WHERE id = $1",
+        )
+        .bind(device_id)
+        .fetch_optional(self.db.as_ref()))
+        .await
+        .map_err(|e| EchoError::Database(e.to_string()))?;
+
+        Ok(row.map(|(id, owner, echokit_url)| ImportDevice { id, owner, echokit_url }))
+    }
+
+    async fn run(&self, job_id: &str, device: &ImportDevice, uploads: Vec<ImportUpload>, created_at: DateTime<Utc>) {
+        futures::stream::iter(uploads.into_iter().enumerate())
+            .for_each_concurrent(None, |(index, upload)| async move {
+                let Ok(_permit) = self.permits.acquire().await else {
+                    return;
+                };
+                self.update_file(job_id, index, |file| file.status = AudioImportStatus::Running);
+
+                let recorded_at = upload.recorded_at.unwrap_or(created_at);
+                match self.import_file(job_id, index, device, upload, recorded_at).await {
+                    Ok((session_id, transcript)) => self.update_file(job_id, index, |file| {
+                        file.status = AudioImportStatus::Completed;
+                        file.session_id = Some(session_id);
+                        file.transcript = Some(transcript);
+                    }),
+                    Err(e) => {
+                        warn!("Audio import {} file {} failed: {:#}", job_id, index, e);
+                        self.update_file(job_id, index, |file| {
+                            file.status = AudioImportStatus::Failed;
+                            file.error = Some(format!("{:#}", e));
+                        });
+                    }
+                }
+            })
+            .await;
+
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = jobs.get_mut(job_id) {
+            entry.job.finished_at = Some(Utc::now());
+            entry.expires_at = Some(Instant::now() + Duration::from_secs(self.config.job_retention_seconds));
+            let completed = entry.job.files.iter().filter(|file| file.status == AudioImportStatus::Completed).count();
+            info!("Audio import {} finished: {}/{} files imported", job_id, completed, entry.job.files.len());
+        }
+    }
+
+    // 解码、识别并写入一个录音，返回会话 ID 和转写
+    async fn import_file(
+        &self,
+        job_id: &str,
+        index: usize,
+        device: &ImportDevice,
+        upload: ImportUpload,
+        recorded_at: DateTime<Utc>,
+    ) -> Result<(String, String)> {
+        let recording = tokio::task::spawn_blocking(move || decode_recording(&upload.data))
+            .await
+            .context("Audio decoding task failed")??;
+        self.update_file(job_id, index, |file| file.duration_seconds = Some(recording.duration_seconds));
+        if recording.duration_seconds > self.config.max_recording_seconds as f64 {
+            bail!(
+                "Recording is {:.0}s long, the limit is {}s",
+                recording.duration_seconds,
+                self.config.max_recording_seconds
+            );
+        }
+
+        let session_id = format!("session_{}", uuid::Uuid::new_v4());
+        let transcript = transcribe(
+            &device.echokit_url,
+            &device.id,
+            &session_id,
+            &recording.pcm,
+            &self.ingress_limits,
+            Duration::from_secs(self.config.asr_timeout_seconds),
+        )
+        .await?;
+        let transcript = asr_postprocess::apply(self.asr_postprocessing.as_deref(), &device.id, transcript).await;
+
+        let name = self.file_name(job_id, index);
+        self.session_service
+            .import_session(&ImportedSession {
+                session_id: session_id.clone(),
+                device_id: device.id.clone(),
+                user_id: device.owner.clone(),
+                started_at: recorded_at,
+                duration_seconds: recording.duration_seconds,
+                transcript: transcript.clone(),
+                source: serde_json::json!({ "job_id": job_id, "file": name }),
+            })
+            .await?;
+        Ok((session_id, transcript))
+    }
+
+    fn file_name(&self, job_id: &str, index: usize) -> Option<String> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.get(job_id).and_then(|entry| entry.job.files.get(index)).map(|file| file.name.clone())
+    }
+
+    fn update_file(&self, job_id: &str, index: usize, update: impl FnOnce(&mut AudioImportFile)) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(file) = jobs.get_mut(job_id).and_then(|entry| entry.job.files.get_mut(index)) {
+            update(file);
+        }
+    }
+}
+
+// 删除过期的任务；任务数超出上限时再丢弃最早结束的
+fn prune_jobs(jobs: &mut HashMap<String, JobEntry>, now: Instant) {
+    jobs.retain(|_, entry| entry.expires_at.is_none_or(|expires_at| expires_at > now));
+    while jobs.len() > MAX_JOBS {
+        let oldest = jobs
+            .iter()
+            .filter_map(|(id, entry)| entry.expires_at.map(|expires_at| (expires_at, id.clone())))
+            .min();
+        let Some((_, id)) = oldest else {
+            break;
+        };
+        jobs.remove(&id);
+    }
+}
+
+/// 通过独立的 EchoKit 连接识别一段 16kHz 16-bit 单声道 PCM，返回第一个非空的识别结果
+pub async fn transcribe(
+    echokit_url: &str,
+    device_id: &str,
+    session_id: &str,
+    pcm: &[u8],
+    ingress_limits: &IngressLimitsConfig,
+    timeout: Duration,
+) -> Result<String> {
+    let (asr_tx, mut asr_rx) = channels::control_channel("audio_import_asr", 16);
+    let client = EchoKitClient::builder(echokit_url)
+        .asr_callback(asr_tx)
+        .ingress_limits(ingress_limits.clone())
+        .build()?;
+    client.connect_with_device_id(Some(device_id)).await?;
+
+    let result = async {
+        client.pre_register_session(session_id.to_string(), device_id.to_string()).await;
+        client.send_start_chat_command().await?;
+        for chunk in pcm.chunks(CHUNK_BYTES) {
+            client
+                .send_audio_data(session_id.to_string(), device_id.to_string(), chunk.to_vec(), AudioFormat::PCM16, false)
+                .await?;
+        }
+        client.send_submit_command().await?;
+
+        // 静音片段可能先返回空的识别结果，继续等待直到超时
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match tokio::time::timeout_at(deadline, asr_rx.recv()).await {
+                Ok(Some((_, text))) if !text.trim().is_empty() => return Ok(text.trim().to_string()),
+                Ok(Some(_)) => continue,
+                Ok(None) => bail!("EchoKit connection closed before returning a transcript"),
+                Err(_) => bail!("No transcript from EchoKit within {}s", timeout.as_secs()),
+            }
+        }
+    }
+    .await;
+
+    if let Err(e) = client.disconnect().await {
+        warn!("Failed to disconnect import session {} from EchoKit: {}", session_id, e);
+    }
+    result
+}
+
+/// 解码 WAV 或 Ogg Opus 录音，转换为 16kHz 16-bit 单声道 PCM
+pub fn decode_recording(data: &[u8]) -> Result<Recording> {
+    let (sample_rate, samples) = match AudioFormatDetector::detect_format(data) {
+        AudioFormat::WAV => decode_wav(data)?,
+        AudioFormat::Opus => decode_ogg_opus(data)?,
+        _ => bail!("Unsupported audio format, expected WAV or Ogg Opus"),
+    };
+    if samples.is_empty() {
+        bail!("Recording contains no audio");
+    }
+
+    let samples = resample(&samples, sample_rate, SAMPLE_RATE);
+    Ok(Recording {
+        duration_seconds: samples.len() as f64 / SAMPLE_RATE as f64,
+        pcm: samples
+            .iter()
+            .flat_map(|sample| ((sample.clamp(-1.0, 1.0) * 32767.0).round() as i16).to_le_bytes())
+            .collect(),
+    })
+}
+
+// WAV 的样本编码
+#[derive(Debug, Clone, Copy)]
+struct WavFormat {
+    float: bool,
+    channels: usize,
+    sample_rate: u32,
+    bytes_per_sample: usize,
+}
+
+// 解析 RIFF 块，返回采样率和混为单声道的样本（-1.0 ~ 1.0）
+fn decode_wav(data: &[u8]) -> Result<(u32, Vec<f32>)> {
+    let mut format = None;
+    let mut pos = 12;
+    while let Some(header) = data.get(pos..pos + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let body_start = pos + 8;
+        // 录音中断时 data 块的长度可能大于实际数据，按实际长度读取
+        let body = &data[body_start..body_start.saturating_add(size).min(data.len())];
+        match &header[..4] {
+            b"fmt " => format = Some(parse_wav_format(body)?),
+            b"data" => {
+                let format = format.context("WAV data chunk appears before the fmt chunk")?;
+                return Ok((format.sample_rate, wav_samples(body, format)));
+            }
+            _ => {}
+        }
+        // 块按偶数字节对齐
+        pos = body_start.saturating_add(size).saturating_add(size & 1);
+    }
+    bail!("WAV file has no data chunk")
+}
+
+fn parse_wav_format(body: &[u8]) -> Result<WavFormat> {
+    if body.len() < 16 {
+        bail!("WAV fmt chunk is too short");
+    }
+    let u16_at = |offset: usize| u16::from_le_bytes([body[offset], body[offset + 1]]);
+    let mut tag = u16_at(0);
+    // WAVE_FORMAT_EXTENSIBLE：子格式 GUID 的前两个字节是实际的编码
+    if tag == 0xFFFE && body.len() >= 26 {
+        tag = u16_at(24);
+    }
+    let channels = u16_at(2) as usize;
+    let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+    let bits = u16_at(14);
+
+    let float = match (tag, bits) {
+        (1, 8 | 16 | 24 | 32) => false,
+        (3, 32 | 64) => true,
+        _ => bail!("Unsupported WAV encoding (format {}, {} bits), expected PCM or IEEE float", tag, bits),
+    };
+    if channels == 0 || sample_rate == 0 {
+        bail!("Invalid WAV format: {} channels at {}Hz", channels, sample_rate);
+    }
+    Ok(WavFormat { float, channels, sample_rate, bytes_per_sample: bits as usize / 8 })
+}
+
+fn wav_samples(body: &[u8], format: WavFormat) -> Vec<f32> {
+    let frame_bytes = format.channels * format.bytes_per_sample;
+    body.chunks_exact(frame_bytes)
+        .map(|frame| {
+            let sum: f32 = frame.chunks_exact(format.bytes_per_sample).map(|b| wav_sample(b, format.float)).sum();
+            sum / format.channels as f32
+        })
+        .collect()
+}
+
+fn wav_sample(b: &[u8], float: bool) -> f32 {
+    match (b.len(), float) {
+        (1, _) => (b[0] as f32 - 128.0) / 128.0,
+        (2, _) => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (3, _) => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+        (4, false) => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (4, true) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        _ => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32,
+    }
+}
+
+// 解码 Ogg 封装的 Opus 流，直接输出 16kHz 单声道
+fn decode_ogg_opus(data: &[u8]) -> Result<(u32, Vec<f32>)> {
+    let packets = ogg_packets(data)?;
+    let head = packets
+        .first()
+        .filter(|packet| packet.len() >= 19 && packet.starts_with(b"OpusHead"))
+        .context("Ogg stream is not Opus (missing OpusHead)")?;
+    if head[9] > 2 {
+        bail!("Opus streams with {} channels are not supported", head[9]);
+    }
+    // pre-skip 以 48kHz 样本计
+    let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize * SAMPLE_RATE as usize / 48_000;
+
+    let mut decoder = opus::Decoder::new(SAMPLE_RATE, opus::Channels::Mono).context("Failed to create Opus decoder")?;
+    let mut frame = vec![0i16; MAX_OPUS_FRAME_SAMPLES];
+    let mut samples = Vec::new();
+    // 第二个包是 OpusTags
+    for packet in packets.iter().skip(2).filter(|packet| !packet.is_empty()) {
+        let decoded = decoder.decode(packet, &mut frame, false).context("Invalid Opus packet")?;
+        samples.extend(frame[..decoded].iter().map(|&sample| sample as f32 / 32768.0));
+    }
+    samples.drain(..pre_skip.min(samples.len()));
+    Ok((SAMPLE_RATE, samples))
+}
+
+// 按页面的分段表拼出第一个逻辑流的数据包，忽略其他逻辑流和截断的最后一页
+fn ogg_packets(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut serial = None;
+    let mut pos = 0;
+    while pos < data.len() {
+        let Some(header) = data.get(pos..pos + 27) else {
+            break;
+        };
+        if &header[..4] != b"OggS" {
+            bail!("Invalid Ogg page at byte {}", pos);
+        }
+        let page_serial = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+        let segments = header[26] as usize;
+        let Some(lacing) = data.get(pos + 27..pos + 27 + segments) else {
+            break;
+        };
+        let mut body = pos + 27 + segments;
+        let end = body + lacing.iter().map(|&len| len as usize).sum::<usize>();
+        if end > data.len() {
+            break;
+        }
+        pos = end;
+        if *serial.get_or_insert(page_serial) != page_serial {
+            continue;
+        }
+        for &len in lacing {
+            packet.extend_from_slice(&data[body..body + len as usize]);
+            body += len as usize;
+            // 长度小于 255 的分段结束一个数据包
+            if len < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+    }
+    Ok(packets)
+}
+
+// 重采样：降采样时对每个输出样本覆盖的输入样本取平均，升采样时线性插值
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let len = (samples.len() as f64 / ratio) as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position as usize;
+            if ratio > 1.0 {
+                let end = (((i + 1) as f64 * ratio) as usize).clamp(index + 1, samples.len());
+                samples[index..end].iter().sum::<f32>() / (end - index) as f32
+            } else {
+                let next = samples.get(index + 1).copied().unwrap_or(samples[index]);
+                samples[index] + (next - samples[index]) * (position - index as f64) as f32
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::echokit::mock_server::{MockEchoKitServer, MockScript};
+
+    fn wav(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+        wav.extend_from_slice(&(channels * 2).to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        // 解码时应跳过未知的块
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&3u32.to_le_bytes());
+        wav.extend_from_slice(&[0, 0, 0, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+        wav
+    }
+
+    #[test]
+    fn test_decode_wav_downmixes_and_resamples() {
+        // 48kHz 立体声 0.5 秒，左右声道相反，混音后为静音
+        let samples: Vec<i16> = (0..24_000).flat_map(|i| [(i % 1000) as i16, -((i % 1000) as i16)]).collect();
+        let recording = decode_recording(&wav(48_000, 2, &samples)).unwrap();
+        assert_eq!(recording.pcm.len(), 8_000 * 2);
+        assert!((recording.duration_seconds - 0.5).abs() < 1e-9);
+        assert!(recording.pcm.iter().all(|&b| b == 0));
+
+        // 16kHz 单声道原样保留
+        let recording = decode_recording(&wav(16_000, 1, &[1000, -1000, 2000])).unwrap();
+        let pcm: Vec<i16> = recording.pcm.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+        assert_eq!(pcm, vec![1000, -1000, 2000]);
+
+        assert!(decode_recording(b"ID3\x03\x00\x00\x00\x00\x00\x00\x00\x00").is_err());
+        assert!(decode_recording(&wav(16_000, 1, &[])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_returns_first_asr_result() {
+        let mock = MockEchoKitServer::start(MockScript::conversation("打开客厅的灯", "好的", 1)).await.unwrap();
+        let pcm = vec![0u8; CHUNK_BYTES * 2 + 100];
+
+        let transcript = transcribe(
+            &mock.url(),
+            "ECHO_IMPORT",
+            "session_import",
+            &pcm,
+            &IngressLimitsConfig::default(),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(transcript, "打开客厅的灯");
+        let stats = mock.stats();
+        assert_eq!(stats.paths, vec!["/ws/ECHO_IMPORT".to_string()]);
+        assert_eq!(stats.audio_bytes, pcm.len());
+        assert_eq!(stats.submits, 1);
+    }
+}
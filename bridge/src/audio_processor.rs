@@ -3,8 +3,9 @@ use echo_shared::{AudioFormat, AudioChunk};
 use echo_shared::utils::now_utc;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::RwLock;
 use tracing::{info, warn, error, debug};
+use crate::channels::AudioSender;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Cursor, Read, Write};
 
@@ -12,7 +13,7 @@ use std::io::{Cursor, Read, Write};
 pub struct AudioProcessor {
     device_sessions: Arc<RwLock<HashMap<String, DeviceAudioSession>>>,
     echokit_client: Arc<crate::echokit_client::EchoKitClient>,
-    output_sender: mpsc::UnboundedSender<(String, Vec<u8>)>, // (device_id, audio_data)
+    output_sender: AudioSender<(String, Vec<u8>)>, // (device_id, audio_data)
 }
 
 // 设备音频会话
@@ -32,7 +33,7 @@ struct DeviceAudioSession {
 impl AudioProcessor {
     pub fn new(
         echokit_client: Arc<crate::echokit_client::EchoKitClient>,
-        output_sender: mpsc::UnboundedSender<(String, Vec<u8>)>,
+        output_sender: AudioSender<(String, Vec<u8>)>,
     ) -> Self {
         Self {
             device_sessions: Arc::new(RwLock::new(HashMap::new())),
@@ -157,7 +158,9 @@ impl AudioProcessor {
                 error!("Failed to send audio to device {}: {}", device_id, e);
             }
 
-            info!("Sent {} bytes of audio to device {}", output_audio.len(), device_id);
+            if echo_shared::log_sampled!() {
+                info!("Sent {} bytes of audio to device {}", output_audio.len(), device_id);
+            }
         } else {
             warn!("No active session found for device: {}", device_id);
         }
@@ -307,6 +310,11 @@ impl AudioProcessor {
         self.device_sessions.read().await.len()
     }
 
+    // 获取设备当前的会话 ID
+    pub async fn get_session_id(&self, device_id: &str) -> Option<String> {
+        self.device_sessions.read().await.get(device_id).map(|session| session.session_id.clone())
+    }
+
     // 获取设备会话信息
     pub async fn get_session_info(&self, device_id: &str) -> Option<DeviceAudioSession> {
         self.device_sessions.read().await.get(device_id).cloned()
@@ -0,0 +1,440 @@
+//! Bridge 压测工具
+//!
+//! 模拟 N 个并发设备：每个设备连接 Bridge 的 `/ws/{device_id}`，发送 StartChat 后按实时速率推送录制的 PCM，
+//! 提交（Submit）后等待 EchoKit 回复直到 EndResponse；指定 `--udp-addr` 时每个设备同时按相同速率推送 UDP 音频包。
+//! 结束时输出会话成功率和延迟分位数，用于衡量音频链路的性能回归。
+//!
+//! 运行：
+//!
+//! ```text
+//! cargo run --release -p echo-bridge --bin bridge-loadgen -- \
+//!     --devices 200 --ws-url ws://localhost:10031 --udp-addr 127.0.0.1:8083 \
+//!     --pcm tests/echokit-server/hello.wav
+//! ```
+//!
+//! PCM 文件为 16-bit、16000Hz、单声道的 WAV 或裸 PCM；未指定时使用 3 秒 440Hz 正弦波。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+use echo_shared::{ClientCommand, EchoKitEvent};
+use futures::{SinkExt, StreamExt};
+use tokio::net::UdpSocket;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+const SAMPLE_RATE: usize = 16_000;
+// 每帧 20ms（16-bit 单声道）
+const FRAME_DURATION: Duration = Duration::from_millis(20);
+const FRAME_BYTES: usize = SAMPLE_RATE * 2 / 50;
+
+const USAGE: &str = "\
+Usage: bridge-loadgen [OPTIONS]
+
+Options:
+  --devices <N>                 Concurrent simulated devices [default: 10]
+  --rounds <N>                  Conversation rounds per device [default: 1]
+  --ws-url <URL>                Bridge WebSocket base URL [default: ws://localhost:10031]
+  --udp-addr <ADDR>             Also stream audio to the bridge UDP server at ADDR
+  --pcm <FILE>                  16-bit 16kHz mono WAV or raw PCM [default: 3s 440Hz tone]
+  --ramp-up-ms <MS>             Spread device start-up over this period [default: 1000]
+  --response-timeout-secs <S>   Time to wait for EndResponse after Submit [default: 30]
+  --device-prefix <PREFIX>      Simulated device ID prefix [default: loadgen]
+  --min-success-rate <RATE>     Exit with an error below this session success rate (0-1)
+  -h, --help                    Print this help";
+
+// 命令行参数
+#[derive(Debug, Clone)]
+struct Options {
+    devices: usize,
+    rounds: usize,
+    ws_url: String,
+    udp_addr: Option<SocketAddr>,
+    pcm: Option<PathBuf>,
+    ramp_up: Duration,
+    response_timeout: Duration,
+    device_prefix: String,
+    min_success_rate: Option<f64>,
+}
+
+impl Options {
+    fn parse() -> Result<Self> {
+        let mut options = Options {
+            devices: 10,
+            rounds: 1,
+            ws_url: "ws://localhost:10031".to_string(),
+            udp_addr: None,
+            pcm: None,
+            ramp_up: Duration::from_millis(1000),
+            response_timeout: Duration::from_secs(30),
+            device_prefix: "loadgen".to_string(),
+            min_success_rate: None,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            if flag == "-h" || flag == "--help" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            let value = args.next().with_context(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+            let invalid = || format!("Invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--devices" => options.devices = value.parse().with_context(invalid)?,
+                "--rounds" => options.rounds = value.parse().with_context(invalid)?,
+                "--ws-url" => options.ws_url = value.trim_end_matches('/').to_string(),
+                "--udp-addr" => options.udp_addr = Some(value.parse().with_context(invalid)?),
+                "--pcm" => options.pcm = Some(PathBuf::from(value)),
+                "--ramp-up-ms" => options.ramp_up = Duration::from_millis(value.parse().with_context(invalid)?),
+                "--response-timeout-secs" => {
+                    options.response_timeout = Duration::from_secs(value.parse().with_context(invalid)?)
+                }
+                "--device-prefix" => options.device_prefix = value,
+                "--min-success-rate" => options.min_success_rate = Some(value.parse().with_context(invalid)?),
+                _ => bail!("Unknown option: {}\n\n{}", flag, USAGE),
+            }
+        }
+
+        if options.devices == 0 || options.rounds == 0 {
+            bail!("--devices and --rounds must be greater than 0");
+        }
+        Ok(options)
+    }
+}
+
+// 单个 WebSocket 设备的结果
+#[derive(Debug, Default)]
+struct DeviceReport {
+    connect_latency: Option<Duration>,
+    sessions: usize,
+    succeeded: usize,
+    // Submit 到第一个回复事件（ASR / StartAudio / AudioChunk）
+    first_response: Vec<Duration>,
+    // Submit 到 EndResponse
+    full_response: Vec<Duration>,
+    errors: Vec<String>,
+}
+
+// 单个 UDP 设备的结果
+#[derive(Debug, Default)]
+struct UdpReport {
+    packets: u64,
+    errors: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    let options = Arc::new(Options::parse()?);
+    let pcm = Arc::new(match &options.pcm {
+        Some(path) => load_pcm(path)?,
+        None => sine_tone(Duration::from_secs(3)),
+    });
+    let audio_seconds = pcm.len() as f64 / (SAMPLE_RATE * 2) as f64;
+
+    println!(
+        "Simulating {} devices x {} rounds against {} ({:.1}s of audio per round{})",
+        options.devices,
+        options.rounds,
+        options.ws_url,
+        audio_seconds,
+        options.udp_addr.map(|addr| format!(", UDP to {}", addr)).unwrap_or_default()
+    );
+
+    let started = Instant::now();
+    let stagger = options.ramp_up / options.devices as u32;
+    let mut ws_tasks = Vec::with_capacity(options.devices);
+    let mut udp_tasks = Vec::new();
+    for index in 0..options.devices {
+        let device_id = format!("{}-{:05}", options.device_prefix, index);
+        let delay = stagger * index as u32;
+
+        ws_tasks.push(tokio::spawn(run_ws_device(device_id.clone(), delay, options.clone(), pcm.clone())));
+        if let Some(addr) = options.udp_addr {
+            udp_tasks.push(tokio::spawn(run_udp_device(device_id, addr, delay, options.rounds, pcm.clone())));
+        }
+    }
+
+    let mut reports = Vec::with_capacity(ws_tasks.len());
+    for task in ws_tasks {
+        reports.push(task.await.context("device task panicked")?);
+    }
+    let mut udp = UdpReport::default();
+    for task in udp_tasks {
+        let report = task.await.context("UDP device task panicked")?;
+        udp.packets += report.packets;
+        udp.errors += report.errors;
+    }
+
+    let success_rate = print_report(&reports, options.udp_addr.is_some().then_some(&udp), started.elapsed());
+    if let Some(min) = options.min_success_rate {
+        if success_rate < min {
+            bail!("Session success rate {:.1}% is below the required {:.1}%", success_rate * 100.0, min * 100.0);
+        }
+    }
+    Ok(())
+}
+
+// 模拟一个 WebSocket 设备：连接后执行若干轮对话
+async fn run_ws_device(device_id: String, delay: Duration, options: Arc<Options>, pcm: Arc<Vec<u8>>) -> DeviceReport {
+    tokio::time::sleep(delay).await;
+    // 连接失败或中途断开时，未执行的轮次同样计为失败会话
+    let mut report = DeviceReport { sessions: options.rounds, ..Default::default() };
+
+    let url = format!("{}/ws/{}", options.ws_url, device_id);
+    let connect_start = Instant::now();
+    let mut socket = match tokio_tungstenite::connect_async(url.as_str()).await {
+        Ok((socket, _)) => socket,
+        Err(e) => {
+            report.errors.push(format!("connect: {}", e));
+            return report;
+        }
+    };
+    report.connect_latency = Some(connect_start.elapsed());
+
+    for round in 0..options.rounds {
+        match run_round(&mut socket, &pcm, options.response_timeout).await {
+            Ok((first, full)) => {
+                report.succeeded += 1;
+                report.first_response.extend(first);
+                report.full_response.push(full);
+            }
+            Err(e) => {
+                debug!("Device {} round {} failed: {:#}", device_id, round, e);
+                report.errors.push(format!("{:#}", e));
+                // 连接已不可用时不再继续后续轮次
+                if e.to_string().starts_with("connection") {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = socket.close(None).await;
+    report
+}
+
+// 一轮对话：StartChat → 实时推送音频 → Submit → 等待 EndResponse
+async fn run_round<S>(socket: &mut S, pcm: &[u8], response_timeout: Duration) -> Result<(Option<Duration>, Duration)>
+where
+    S: futures::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>>
+        + futures::Sink<Message, Error = tokio_tungstenite::tungstenite::Error>
+        + Unpin,
+{
+    send_command(socket, &ClientCommand::StartChat { language: None }).await?;
+
+    // 按实时速率推送，同时读取并丢弃期间收到的消息（如问候语）
+    let mut interval = tokio::time::interval(FRAME_DURATION);
+    let mut frames = pcm.chunks(FRAME_BYTES);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let Some(frame) = frames.next() else { break };
+                socket.send(Message::Binary(frame.to_vec())).await.context("connection closed while streaming")?;
+            }
+            message = socket.next() => {
+                match message {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => bail!("connection error while streaming: {}", e),
+                    None => bail!("connection closed while streaming"),
+                }
+            }
+        }
+    }
+
+    send_command(socket, &ClientCommand::Submit).await?;
+    let submitted = Instant::now();
+    let mut first_response = None;
+
+    let wait = async {
+        while let Some(message) = socket.next().await {
+            let data = match message.context("connection error while waiting for response")? {
+                Message::Binary(data) => data,
+                Message::Close(_) => bail!("connection closed by bridge before EndResponse"),
+                _ => continue,
+            };
+            // 非 EchoKit 事件的二进制帧（如 JSON 提示）忽略
+            let Ok(event) = EchoKitEvent::from_messagepack(&data) else { continue };
+            match event {
+                EchoKitEvent::ASR { .. } | EchoKitEvent::StartAudio { .. } | EchoKitEvent::AudioChunk { .. } => {
+                    first_response.get_or_insert_with(|| submitted.elapsed());
+                }
+                EchoKitEvent::EndResponse => return Ok(submitted.elapsed()),
+                _ => {}
+            }
+        }
+        bail!("connection closed by bridge before EndResponse")
+    };
+
+    let full = tokio::time::timeout(response_timeout, wait)
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out waiting for EndResponse"))??;
+    Ok((first_response, full))
+}
+
+async fn send_command<S>(socket: &mut S, command: &ClientCommand) -> Result<()>
+where
+    S: futures::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    let text = serde_json::to_string(command)?;
+    socket.send(Message::Text(text)).await.context("connection closed while sending command")
+}
+
+// 模拟一个 UDP 设备：按实时速率推送音频包（格式见 udp_server::parse_udp_packet）
+async fn run_udp_device(device_id: String, addr: SocketAddr, delay: Duration, rounds: usize, pcm: Arc<Vec<u8>>) -> UdpReport {
+    tokio::time::sleep(delay).await;
+    let mut report = UdpReport::default();
+
+    let bind = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Failed to bind UDP socket for {}: {}", device_id, e);
+            report.errors += 1;
+            return report;
+        }
+    };
+
+    let mut interval = tokio::time::interval(FRAME_DURATION);
+    let mut sequence: u32 = 0;
+    for _ in 0..rounds {
+        let frames: Vec<&[u8]> = pcm.chunks(FRAME_BYTES).collect();
+        for (index, frame) in frames.iter().enumerate() {
+            interval.tick().await;
+            let packet = udp_packet(&device_id, sequence, frame, index + 1 == frames.len());
+            sequence = sequence.wrapping_add(1);
+            match socket.send_to(&packet, addr).await {
+                Ok(_) => report.packets += 1,
+                Err(e) => {
+                    debug!("UDP send from {} failed: {}", device_id, e);
+                    report.errors += 1;
+                }
+            }
+        }
+    }
+    report
+}
+
+// [设备 ID 长度 u8][设备 ID][序列号 u32 LE][时间戳 u64 LE][标志 u8][音频长度 u16 LE][音频]
+fn udp_packet(device_id: &str, sequence: u32, audio: &[u8], is_final: bool) -> Vec<u8> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    let mut packet = Vec::with_capacity(1 + device_id.len() + 15 + audio.len());
+    packet.push(device_id.len() as u8);
+    packet.extend_from_slice(device_id.as_bytes());
+    packet.extend_from_slice(&sequence.to_le_bytes());
+    packet.extend_from_slice(&timestamp.to_le_bytes());
+    packet.push(u8::from(is_final));
+    packet.extend_from_slice(&(audio.len() as u16).to_le_bytes());
+    packet.extend_from_slice(audio);
+    packet
+}
+
+// 读取 PCM：WAV 文件取 data 块并校验格式，其他文件视为裸 PCM
+fn load_pcm(path: &PathBuf) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !bytes.starts_with(b"RIFF") {
+        return Ok(bytes);
+    }
+
+    let mut offset = 12;
+    let mut format = None;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes(body[4..8].try_into()?);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = Some((channels, sample_rate, bits));
+            }
+            b"data" => {
+                if format != Some((1, SAMPLE_RATE as u32, 16)) {
+                    bail!("{} must be 16-bit 16kHz mono, got {:?} (channels, rate, bits)", path.display(), format);
+                }
+                return Ok(body.to_vec());
+            }
+            _ => {}
+        }
+        // 块按偶数字节对齐
+        offset += 8 + size + (size & 1);
+    }
+    bail!("{} has no data chunk", path.display())
+}
+
+fn sine_tone(duration: Duration) -> Vec<u8> {
+    let samples = (SAMPLE_RATE as f64 * duration.as_secs_f64()) as usize;
+    (0..samples)
+        .flat_map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let sample = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 0.3 * i16::MAX as f64) as i16;
+            sample.to_le_bytes()
+        })
+        .collect()
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn latency_line(label: &str, mut values: Vec<Duration>) -> String {
+    if values.is_empty() {
+        return format!("{:<22} no samples", label);
+    }
+    values.sort();
+    format!(
+        "{:<22} p50 {:>8.1}ms  p90 {:>8.1}ms  p99 {:>8.1}ms  max {:>8.1}ms  (n={})",
+        label,
+        percentile(&values, 50.0).as_secs_f64() * 1000.0,
+        percentile(&values, 90.0).as_secs_f64() * 1000.0,
+        percentile(&values, 99.0).as_secs_f64() * 1000.0,
+        values[values.len() - 1].as_secs_f64() * 1000.0,
+        values.len()
+    )
+}
+
+// 输出汇总报告，返回会话成功率
+fn print_report(reports: &[DeviceReport], udp: Option<&UdpReport>, elapsed: Duration) -> f64 {
+    let connected = reports.iter().filter(|r| r.connect_latency.is_some()).count();
+    let sessions: usize = reports.iter().map(|r| r.sessions).sum();
+    let succeeded: usize = reports.iter().map(|r| r.succeeded).sum();
+    let success_rate = if sessions == 0 { 0.0 } else { succeeded as f64 / sessions as f64 };
+
+    println!();
+    println!("Finished in {:.1}s", elapsed.as_secs_f64());
+    println!("Connections:           {}/{} established", connected, reports.len());
+    println!("Sessions:              {}/{} succeeded ({:.1}%)", succeeded, sessions, success_rate * 100.0);
+    println!("{}", latency_line("Connect latency:", reports.iter().filter_map(|r| r.connect_latency).collect()));
+    println!("{}", latency_line("First response:", reports.iter().flat_map(|r| r.first_response.iter().copied()).collect()));
+    println!("{}", latency_line("Full response:", reports.iter().flat_map(|r| r.full_response.iter().copied()).collect()));
+    if let Some(udp) = udp {
+        println!("UDP:                   {} packets sent, {} errors", udp.packets, udp.errors);
+    }
+
+    let mut errors: HashMap<&str, usize> = HashMap::new();
+    for error in reports.iter().flat_map(|r| r.errors.iter()) {
+        *errors.entry(error.as_str()).or_default() += 1;
+    }
+    if !errors.is_empty() {
+        let mut errors: Vec<_> = errors.into_iter().collect();
+        errors.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        println!("Errors:");
+        for (error, count) in errors.into_iter().take(10) {
+            println!("  {:>6}  {}", count, error);
+        }
+    }
+
+    success_rate
+}
@@ -0,0 +1,443 @@
+//! 会话重放工具
+//!
+//! 读取录制的会话——管理 API 导出的抓帧记录（`GET /admin/sessions/{id}/trace`，NDJSON）和会话上传的音频——
+//! 按录制时的时间间隔把设备发送的命令和音频重放到 Bridge，并把每轮对话的 ASR 结果和回复延迟与录制时对比，
+//! 用于确定性地复现用户反馈的识别或延迟问题。
+//!
+//! 运行：
+//!
+//! ```text
+//! curl -o trace.ndjson http://localhost:10032/admin/sessions/<session_id>/trace
+//! cargo run --release -p echo-bridge --bin session-replay -- \
+//!     --trace trace.ndjson --audio session.wav --ws-url ws://localhost:10031
+//! ```
+//!
+//! 音频为 16-bit、16000Hz、单声道的 WAV 或裸 PCM，按抓帧记录中每帧的大小依次切分；未指定时以同样大小的静音代替，
+//! 只复现时序。抓帧在会话创建后才开始记录，开头缺少 StartChat 时在第一帧之前补发。
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use echo_shared::{ClientCommand, EchoKitEvent};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+const SAMPLE_RATE: usize = 16_000;
+
+const USAGE: &str = "\
+Usage: session-replay --trace <FILE> [OPTIONS]
+
+Options:
+  --trace <FILE>                Session trace downloaded from /admin/sessions/{id}/trace (NDJSON)
+  --audio <FILE>                16-bit 16kHz mono WAV or raw PCM uploaded in the session [default: silence]
+  --ws-url <URL>                Bridge WebSocket base URL [default: ws://localhost:10031]
+  --device-id <ID>              Device ID to connect as [default: session-replay]
+  --speed <FACTOR>              Playback speed, 2 replays twice as fast [default: 1]
+  --response-timeout-secs <S>   Time to wait for outstanding EndResponse events [default: 30]
+  -h, --help                    Print this help";
+
+// 命令行参数
+#[derive(Debug, Clone)]
+struct Options {
+    trace: PathBuf,
+    audio: Option<PathBuf>,
+    ws_url: String,
+    device_id: String,
+    speed: f64,
+    response_timeout: Duration,
+}
+
+impl Options {
+    fn parse() -> Result<Self> {
+        let mut trace = None;
+        let mut options = Options {
+            trace: PathBuf::new(),
+            audio: None,
+            ws_url: "ws://localhost:10031".to_string(),
+            device_id: "session-replay".to_string(),
+            speed: 1.0,
+            response_timeout: Duration::from_secs(30),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            if flag == "-h" || flag == "--help" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            let value = args.next().with_context(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+            let invalid = || format!("Invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--trace" => trace = Some(PathBuf::from(value)),
+                "--audio" => options.audio = Some(PathBuf::from(value)),
+                "--ws-url" => options.ws_url = value.trim_end_matches('/').to_string(),
+                "--device-id" => options.device_id = value,
+                "--speed" => options.speed = value.parse().with_context(invalid)?,
+                "--response-timeout-secs" => {
+                    options.response_timeout = Duration::from_secs(value.parse().with_context(invalid)?)
+                }
+                _ => bail!("Unknown option: {}\n\n{}", flag, USAGE),
+            }
+        }
+
+        options.trace = trace.with_context(|| format!("--trace is required\n\n{}", USAGE))?;
+        if !(options.speed > 0.0 && options.speed.is_finite()) {
+            bail!("--speed must be greater than 0");
+        }
+        Ok(options)
+    }
+}
+
+// 抓帧记录中的一帧（trace_capture::TraceFrame）
+#[derive(Debug, Deserialize)]
+struct RecordedFrame {
+    elapsed_us: u64,
+    direction: String,
+    event: String,
+    size: usize,
+}
+
+// 设备发送的一条消息
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Command(ClientCommand),
+    Audio(Vec<u8>),
+}
+
+// 一轮对话的回复，延迟从 Submit 开始计算
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Round {
+    submitted: Duration,
+    asr: Option<String>,
+    first_response: Option<Duration>,
+    full_response: Option<Duration>,
+}
+
+impl Round {
+    fn new(submitted: Duration) -> Self {
+        Self { submitted, ..Default::default() }
+    }
+}
+
+// 把设备收到的一个事件计入最早未结束的一轮，at 为距开始的时间
+fn observe(rounds: &mut [Round], at: Duration, event: &str) {
+    let Some(round) = rounds.iter_mut().find(|round| round.full_response.is_none()) else {
+        return;
+    };
+    let since_submit = at.saturating_sub(round.submitted);
+    match event {
+        "ASR" | "StartAudio" | "AudioChunk" => {
+            round.first_response.get_or_insert(since_submit);
+        }
+        "EndResponse" => round.full_response = Some(since_submit),
+        _ => {}
+    }
+}
+
+// 重放计划：设备消息及其相对第一帧的发送时间，以及录制时每轮的回复
+#[derive(Debug, Default)]
+struct Timeline {
+    steps: Vec<(Duration, Step)>,
+    recorded: Vec<Round>,
+    // 无法重放的设备消息（如未记录内容的 Text 命令）
+    skipped: Vec<String>,
+    audio_bytes: usize,
+    // 音频文件不足时补齐的静音字节数
+    padded_bytes: usize,
+}
+
+impl Timeline {
+    fn build(frames: &[RecordedFrame], pcm: Option<&[u8]>) -> Self {
+        let mut timeline = Timeline::default();
+        let Some(start) = frames.iter().map(|frame| frame.elapsed_us).min() else {
+            return timeline;
+        };
+
+        let mut cursor = 0;
+        for frame in frames {
+            let at = Duration::from_micros(frame.elapsed_us - start);
+            match frame.direction.as_str() {
+                "device_to_bridge" => {}
+                "bridge_to_device" => {
+                    observe(&mut timeline.recorded, at, &frame.event);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let step = match frame.event.as_str() {
+                "audio" => {
+                    let end = cursor + frame.size;
+                    let mut audio = Vec::with_capacity(frame.size);
+                    if let Some(pcm) = pcm {
+                        audio.extend_from_slice(&pcm[cursor.min(pcm.len())..end.min(pcm.len())]);
+                        timeline.padded_bytes += frame.size - audio.len();
+                    }
+                    audio.resize(frame.size, 0);
+                    cursor = end;
+                    timeline.audio_bytes += frame.size;
+                    Step::Audio(audio)
+                }
+                "StartChat" => Step::Command(ClientCommand::StartChat { language: None }),
+                "StartRecord" => Step::Command(ClientCommand::StartRecord),
+                "Submit" => {
+                    timeline.recorded.push(Round::new(at));
+                    Step::Command(ClientCommand::Submit)
+                }
+                other => {
+                    timeline.skipped.push(other.to_string());
+                    continue;
+                }
+            };
+            timeline.steps.push((at, step));
+        }
+
+        let starts_session = matches!(
+            timeline.steps.first(),
+            Some((_, Step::Command(command))) if command.is_session_start()
+        );
+        if !starts_session {
+            timeline.steps.insert(0, (Duration::ZERO, Step::Command(ClientCommand::StartChat { language: None })));
+        }
+        timeline
+    }
+
+    fn duration(&self) -> Duration {
+        self.steps.last().map(|(at, _)| *at).unwrap_or_default()
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .init();
+
+    let options = Options::parse()?;
+    let frames = load_trace(&options.trace)?;
+    let pcm = options.audio.as_ref().map(load_pcm).transpose()?;
+    let timeline = Timeline::build(&frames, pcm.as_deref());
+    if timeline.recorded.is_empty() {
+        bail!("{} contains no Submit from the device, nothing to compare", options.trace.display());
+    }
+
+    println!(
+        "Replaying {} rounds ({:.1}s, {:.1}s of audio) from {} against {} as {}",
+        timeline.recorded.len(),
+        timeline.duration().as_secs_f64(),
+        timeline.audio_bytes as f64 / (SAMPLE_RATE * 2) as f64,
+        options.trace.display(),
+        options.ws_url,
+        options.device_id
+    );
+    if !timeline.skipped.is_empty() {
+        println!("Skipped {} device messages that cannot be replayed: {:?}", timeline.skipped.len(), timeline.skipped);
+    }
+    if timeline.padded_bytes > 0 {
+        println!("Audio file is {} bytes shorter than the trace, padded with silence", timeline.padded_bytes);
+    }
+    if let Some(pcm) = &pcm {
+        if pcm.len() > timeline.audio_bytes {
+            println!("Audio file has {} bytes more than the trace, extra audio is not sent", pcm.len() - timeline.audio_bytes);
+        }
+    }
+
+    let replayed = replay(&options, &timeline).await?;
+    let unfinished = print_report(&timeline.recorded, &replayed);
+    if unfinished > 0 {
+        bail!("{} of {} rounds did not receive EndResponse", unfinished, replayed.len());
+    }
+    Ok(())
+}
+
+// 按录制时间发送设备消息，同时记录收到的回复
+async fn replay(options: &Options, timeline: &Timeline) -> Result<Vec<Round>> {
+    let url = format!("{}/ws/{}", options.ws_url, options.device_id);
+    let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+
+    let started = Instant::now();
+    let mut rounds = Vec::new();
+    let mut steps = timeline.steps.iter().peekable();
+    while let Some((at, step)) = steps.peek() {
+        let deadline = started + at.div_f64(options.speed);
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => {
+                let message = match step {
+                    Step::Command(command) => {
+                        if *command == ClientCommand::Submit {
+                            rounds.push(Round::new(started.elapsed()));
+                        }
+                        Message::Text(serde_json::to_string(command)?)
+                    }
+                    Step::Audio(audio) => Message::Binary(audio.clone()),
+                };
+                socket.send(message).await.context("Connection closed while replaying")?;
+                steps.next();
+            }
+            message = socket.next() => receive(message, started, &mut rounds)?,
+        }
+    }
+
+    let wait = async {
+        while rounds.iter().any(|round| round.full_response.is_none()) {
+            receive(socket.next().await, started, &mut rounds)?;
+        }
+        anyhow::Ok(())
+    };
+    match tokio::time::timeout(options.response_timeout, wait).await {
+        Ok(result) => result?,
+        Err(_) => warn!("Timed out waiting for EndResponse"),
+    }
+
+    let _ = socket.close(None).await;
+    Ok(rounds)
+}
+
+fn receive(
+    message: Option<Result<Message, tokio_tungstenite::tungstenite::Error>>,
+    started: Instant,
+    rounds: &mut [Round],
+) -> Result<()> {
+    let data = match message {
+        Some(Ok(Message::Binary(data))) => data,
+        Some(Ok(Message::Close(_))) | None => bail!("Connection closed by bridge"),
+        Some(Ok(_)) => return Ok(()),
+        Some(Err(e)) => bail!("Connection error: {}", e),
+    };
+    // 非 EchoKit 事件的二进制帧（如 JSON 提示、原始 PCM）忽略
+    let Ok(event) = EchoKitEvent::from_messagepack(&data) else {
+        return Ok(());
+    };
+    debug!("Received {}", event.name());
+    if let EchoKitEvent::ASR { text } = &event {
+        if let Some(round) = rounds.iter_mut().find(|round| round.full_response.is_none()) {
+            round.asr.get_or_insert_with(|| text.clone());
+        }
+    }
+    observe(rounds, started.elapsed(), event.name());
+    Ok(())
+}
+
+fn load_trace(path: &PathBuf) -> Result<Vec<RecordedFrame>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| format!("{} line {} is not a trace frame", path.display(), index + 1))
+        })
+        .collect()
+}
+
+// 读取 PCM：WAV 文件取 data 块并校验格式，其他文件视为裸 PCM
+fn load_pcm(path: &PathBuf) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !bytes.starts_with(b"RIFF") {
+        return Ok(bytes);
+    }
+
+    let mut offset = 12;
+    let mut format = None;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes(body[4..8].try_into()?);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                format = Some((channels, sample_rate, bits));
+            }
+            b"data" => {
+                if format != Some((1, SAMPLE_RATE as u32, 16)) {
+                    bail!("{} must be 16-bit 16kHz mono, got {:?} (channels, rate, bits)", path.display(), format);
+                }
+                return Ok(body.to_vec());
+            }
+            _ => {}
+        }
+        // 块按偶数字节对齐
+        offset += 8 + size + (size & 1);
+    }
+    bail!("{} has no data chunk", path.display())
+}
+
+fn millis(value: Option<Duration>) -> String {
+    value.map(|value| format!("{:.1}ms", value.as_secs_f64() * 1000.0)).unwrap_or_else(|| "-".to_string())
+}
+
+fn latency_line(label: &str, recorded: Option<Duration>, replayed: Option<Duration>) -> String {
+    let diff = match (recorded, replayed) {
+        (Some(recorded), Some(replayed)) => {
+            format!("  ({:+.1}ms)", (replayed.as_secs_f64() - recorded.as_secs_f64()) * 1000.0)
+        }
+        _ => String::new(),
+    };
+    format!("  {:<16} recorded {:>10}  replay {:>10}{}", label, millis(recorded), millis(replayed), diff)
+}
+
+// 逐轮对比录制和重放的结果，返回没有收到 EndResponse 的轮数
+fn print_report(recorded: &[Round], replayed: &[Round]) -> usize {
+    println!();
+    for (index, round) in replayed.iter().enumerate() {
+        let original = recorded.get(index).cloned().unwrap_or_default();
+        match &round.asr {
+            Some(text) => println!("Round {}: ASR {:?}", index + 1, text),
+            None => println!("Round {}: no ASR", index + 1),
+        }
+        println!("{}", latency_line("First response:", original.first_response, round.first_response));
+        println!("{}", latency_line("Full response:", original.full_response, round.full_response));
+    }
+    replayed.iter().filter(|round| round.full_response.is_none()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(elapsed_ms: u64, direction: &str, event: &str, size: usize) -> RecordedFrame {
+        RecordedFrame { elapsed_us: elapsed_ms * 1000, direction: direction.to_string(), event: event.to_string(), size }
+    }
+
+    #[test]
+    fn test_timeline_from_trace() {
+        let frames = vec![
+            frame(100, "device_to_bridge", "audio", 4),
+            frame(120, "bridge_to_echokit", "audio", 4),
+            frame(140, "device_to_bridge", "audio", 4),
+            frame(200, "device_to_bridge", "Submit", 17),
+            frame(700, "bridge_to_device", "ASR", 9),
+            frame(900, "bridge_to_device", "AudioChunk", 640),
+            frame(1500, "bridge_to_device", "EndResponse", 12),
+            frame(1600, "device_to_bridge", "text", 30),
+        ];
+        let pcm = [1u8, 2, 3, 4, 5, 6];
+        let timeline = Timeline::build(&frames, Some(&pcm));
+
+        // 补发 StartChat，音频按帧大小切分，不足部分补静音
+        assert_eq!(
+            timeline.steps,
+            vec![
+                (Duration::ZERO, Step::Command(ClientCommand::StartChat { language: None })),
+                (Duration::ZERO, Step::Audio(vec![1, 2, 3, 4])),
+                (Duration::from_millis(40), Step::Audio(vec![5, 6, 0, 0])),
+                (Duration::from_millis(100), Step::Command(ClientCommand::Submit)),
+            ]
+        );
+        assert_eq!(timeline.padded_bytes, 2);
+        assert_eq!(timeline.skipped, vec!["text".to_string()]);
+        assert_eq!(timeline.recorded.len(), 1);
+        assert_eq!(timeline.recorded[0].first_response, Some(Duration::from_millis(500)));
+        assert_eq!(timeline.recorded[0].full_response, Some(Duration::from_millis(1300)));
+    }
+}
@@ -0,0 +1,192 @@
+//! 有界内部通道
+//!
+//! EchoKit 回调和设备音频输出原先使用无界通道，消费端变慢时内存会无限增长。
+//! 这里提供两种有界通道，队列深度通过 `/metrics` 导出（channel: 通道名）：
+//!
+//! - `audio_channel`：队列满时丢弃最旧的帧，发送端永不阻塞，丢弃数计入指标。
+//!   音频延迟堆积时旧帧已无播放价值，保留最新的数据更重要。
+//! - `control_channel`：队列满时发送端等待，ASR 文本、AI 回复等控制消息不丢失。
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::{self, error::SendError};
+use tokio::sync::Notify;
+
+use crate::telemetry;
+
+struct AudioShared<T> {
+    name: &'static str,
+    capacity: usize,
+    queue: Mutex<VecDeque<T>>,
+    notify: Notify,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+}
+
+/// 创建丢弃最旧帧的有界音频通道
+pub fn audio_channel<T>(name: &'static str, capacity: usize) -> (AudioSender<T>, AudioReceiver<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(AudioShared {
+        name,
+        capacity,
+        queue: Mutex::new(VecDeque::with_capacity(capacity)),
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+    });
+    telemetry::record_channel_depth(name, 0);
+    (AudioSender { shared: shared.clone() }, AudioReceiver { shared })
+}
+
+/// 音频通道发送端
+pub struct AudioSender<T> {
+    shared: Arc<AudioShared<T>>,
+}
+
+impl<T> AudioSender<T> {
+    /// 发送一帧，队列已满时丢弃最旧的一帧；接收端已关闭时返回错误
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(SendError(value));
+        }
+
+        let depth = {
+            let mut queue = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+            if queue.len() >= self.shared.capacity {
+                queue.pop_front();
+                telemetry::record_channel_dropped(self.shared.name);
+            }
+            queue.push_back(value);
+            queue.len()
+        };
+        telemetry::record_channel_depth(self.shared.name, depth);
+        self.shared.notify.notify_one();
+        Ok(())
+    }
+}
+
+impl<T> Clone for AudioSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for AudioSender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // 最后一个发送端关闭，唤醒接收端结束
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+/// 音频通道接收端
+pub struct AudioReceiver<T> {
+    shared: Arc<AudioShared<T>>,
+}
+
+impl<T> AudioReceiver<T> {
+    /// 接收一帧，所有发送端关闭且队列为空时返回 `None`
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            let popped = {
+                let mut queue = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+                queue.pop_front().map(|value| (value, queue.len()))
+            };
+            if let Some((value, depth)) = popped {
+                telemetry::record_channel_depth(self.shared.name, depth);
+                return Some(value);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            self.shared.notify.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for AudioReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+    }
+}
+
+/// 创建队列满时阻塞发送端的有界控制通道
+pub fn control_channel<T>(name: &'static str, capacity: usize) -> (ControlSender<T>, ControlReceiver<T>) {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    telemetry::record_channel_depth(name, 0);
+    (ControlSender { name, sender }, ControlReceiver { name, receiver })
+}
+
+/// 控制通道发送端
+pub struct ControlSender<T> {
+    name: &'static str,
+    sender: mpsc::Sender<T>,
+}
+
+impl<T> ControlSender<T> {
+    /// 发送一条消息，队列已满时等待消费端腾出空间
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        self.sender.send(value).await?;
+        telemetry::record_channel_depth(self.name, self.sender.max_capacity() - self.sender.capacity());
+        Ok(())
+    }
+}
+
+impl<T> Clone for ControlSender<T> {
+    fn clone(&self) -> Self {
+        Self { name: self.name, sender: self.sender.clone() }
+    }
+}
+
+/// 控制通道接收端
+pub struct ControlReceiver<T> {
+    name: &'static str,
+    receiver: mpsc::Receiver<T>,
+}
+
+impl<T> ControlReceiver<T> {
+    /// 接收一条消息，所有发送端关闭且队列为空时返回 `None`
+    pub async fn recv(&mut self) -> Option<T> {
+        let value = self.receiver.recv().await;
+        telemetry::record_channel_depth(self.name, self.receiver.len());
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_audio_channel_drops_oldest_when_full() {
+        let (tx, mut rx) = audio_channel("test_audio", 2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        drop(tx);
+
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(rx.recv().await, Some(3));
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_audio_channel_send_fails_after_receiver_dropped() {
+        let (tx, rx) = audio_channel("test_audio_closed", 2);
+        drop(rx);
+        assert!(tx.send(1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_audio_channel_wakes_waiting_receiver() {
+        let (tx, mut rx) = audio_channel("test_audio_wake", 4);
+        let receiver = tokio::spawn(async move { rx.recv().await });
+        tokio::task::yield_now().await;
+        tx.send(7).unwrap();
+        assert_eq!(receiver.await.unwrap(), Some(7));
+    }
+}
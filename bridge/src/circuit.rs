@@ -0,0 +1,83 @@
+//! 外部依赖的熔断
+//!
+//! EchoKit、PostgreSQL、Redis 各使用一个熔断器（参数来自 `bridge.circuit_breaker`），
+//! 状态切换时记录日志和 `echo_bridge_circuit_breaker_state` 指标。
+//! EchoKit 熔断期间设备的请求直接失败，设备收到 `error` 事件。
+//! 数据库写操作在短暂错误时按 `database.retry` 重试，每次尝试都经过熔断器。
+
+use std::future::Future;
+use std::sync::Arc;
+
+use echo_shared::{retry, CircuitBreaker, CircuitBreakerConfig, CircuitOpen, CircuitState, DatabaseError, RetryPolicy};
+use tracing::{info, warn};
+
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+
+/// 按配置创建熔断器，状态切换时记录日志和指标
+pub fn breaker(name: &str, config: &CircuitBreakerConfig) -> Arc<CircuitBreaker> {
+    let breaker = CircuitBreaker::from_config(name, config).with_listener(|name, state| {
+        match state {
+            CircuitState::Open => warn!("Circuit breaker {} opened, failing fast", name),
+            CircuitState::HalfOpen => info!("Circuit breaker {} half-open, probing", name),
+            CircuitState::Closed => info!("Circuit breaker {} closed, {} recovered", name, name),
+        }
+        telemetry::record_circuit_state(name, state);
+    });
+    telemetry::record_circuit_state(name, CircuitState::Closed);
+    Arc::new(breaker)
+}
+
+/// 经熔断器执行一次数据库查询并记录耗时，只有连接类错误计入熔断
+pub async fn guarded_query<T, F>(breaker: &CircuitBreaker, name: &'static str, query: F) -> Result<T, DatabaseError>
+where
+    F: Future<Output = Result<T, sqlx::Error>>,
+{
+    breaker
+        .call_with(
+            async { timed_query(name, query).await.map_err(DatabaseError::Connection) },
+            DatabaseError::is_transient,
+        )
+        .await
+}
+
+/// 经熔断器执行数据库写操作，遇到短暂错误时按重试策略重试
+///
+/// `query` 每次尝试生成新的查询，写操作必须可以安全地重复执行（幂等的 UPDATE，或带 ON CONFLICT 的 INSERT）。
+pub async fn retried_query<T, F, Fut>(
+    breaker: &CircuitBreaker,
+    policy: &RetryPolicy,
+    name: &'static str,
+    mut query: F,
+) -> Result<T, DatabaseError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    retry(
+        policy,
+        || guarded_query(breaker, name, query()),
+        |e, attempt| {
+            if !e.is_retryable() {
+                return false;
+            }
+            warn!("Database write {} failed (attempt {}/{}), retrying: {}", name, attempt, policy.max_attempts, e);
+            telemetry::record_db_retry(name);
+            true
+        },
+    )
+    .await
+}
+
+/// 错误由熔断引起时，生成发给设备的 `error` 事件
+pub fn unavailable_event(error: &anyhow::Error) -> Option<serde_json::Value> {
+    let open = error.downcast_ref::<CircuitOpen>()?;
+    Some(serde_json::json!({
+        "event": "error",
+        "code": "service_unavailable",
+        "service": open.name,
+        "message": open.to_string(),
+        "retry_after_ms": open.retry_after.as_millis() as u64,
+        "timestamp": chrono::Utc::now().timestamp()
+    }))
+}
@@ -0,0 +1,513 @@
+//! 多副本部署的共享状态
+//!
+//! 设备归属、会话归属和副本地址保存在 Redis，所有记录带过期时间，由各副本定期刷新：
+//!
+//! - `{prefix}instances`：哈希，副本 ID -> 副本信息（内部 API 地址、UDP 转发地址、最近刷新时间）
+//! - `{prefix}device:{device_id}`：设备所在的副本 ID。WebSocket 连接时直接接管，
+//!   只走 UDP 的设备在没有归属（或归属副本已失效）时由收到数据包的副本认领
+//! - `{prefix}session:{session_id}`：Bridge 会话所在的副本 ID
+//!
+//! 数据包或内部 API 请求到达非归属副本时转发：UDP 数据包发往归属副本的 UDP 地址，
+//! 内部 API 请求携带内部令牌转发给归属副本。Redis 不可用时按单副本处理，不影响本地设备。
+//! 转发的 UDP 数据包带有由内部令牌派生的 HMAC 签名，接收方只接受签名有效的转发数据包。
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use echo_shared::utils::now_utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use echo_shared::{BridgeConfig, ClusterConfig};
+use redis::{AsyncCommands, RedisResult, Script};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::redis_client::RedisClient;
+use crate::telemetry;
+use crate::udp_server::UdpAudioServer;
+use crate::websocket::connection_manager::DeviceConnectionManager;
+use crate::websocket::session_manager::SessionManager;
+
+// 转发内部 API 请求的超时
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 归属为空、属于自己或属于 ARGV[3] 指定的失效副本时认领，返回认领后的归属
+const CLAIM_SCRIPT: &str = r"
+local owner = redis.call('GET', KEYS[1])
+if (not owner) or owner == ARGV[1] or owner == ARGV[3] then
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+    return ARGV[1]
+end
+return owner
+";
+
+// 只删除仍属于自己的记录，避免覆盖已被其他副本接管的设备或会话
+const RELEASE_SCRIPT: &str = r"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+// 刷新一批记录的过期时间，已被其他副本接管的记录保持不变
+const REFRESH_SCRIPT: &str = r"
+for _, key in ipairs(KEYS) do
+    local owner = redis.call('GET', key)
+    if (not owner) or owner == ARGV[1] then
+        redis.call('SET', key, ARGV[1], 'EX', ARGV[2])
+    end
+end
+return #KEYS
+";
+
+/// 副本信息（保存在 `{prefix}instances` 哈希中）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub instance_id: String,
+    /// 内部 API 地址
+    pub internal_url: String,
+    /// 接收转发 UDP 数据包的地址（host:port）
+    pub udp_address: String,
+    pub heartbeat_at: DateTime<Utc>,
+}
+
+/// UDP 数据包的处理位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpRoute {
+    Local,
+    Forward(SocketAddr),
+}
+
+/// 转发 UDP 数据包签名的长度（截断的 HMAC-SHA256）
+pub const UDP_FORWARD_TAG_LEN: usize = 16;
+
+/// 转发 UDP 数据包的签名密钥，由各副本共用的内部令牌派生
+#[derive(Clone)]
+pub struct UdpForwardKey {
+    mac: Hmac<Sha256>,
+}
+
+impl UdpForwardKey {
+    pub fn new(secret: &str) -> Self {
+        // 不直接使用内部令牌作为密钥，与内部 API 的鉴权区分开
+        let mut derive = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+        derive.update(b"echo-bridge udp forward");
+        let key = derive.finalize().into_bytes();
+        Self { mac: Hmac::new_from_slice(&key).expect("HMAC accepts keys of any length") }
+    }
+
+    /// 数据的签名
+    pub fn sign(&self, data: &[u8]) -> [u8; UDP_FORWARD_TAG_LEN] {
+        let mut mac = self.mac.clone();
+        mac.update(data);
+        let mut tag = [0u8; UDP_FORWARD_TAG_LEN];
+        tag.copy_from_slice(&mac.finalize().into_bytes()[..UDP_FORWARD_TAG_LEN]);
+        tag
+    }
+
+    /// 以常数时间校验签名
+    pub fn verify(&self, data: &[u8], tag: &[u8]) -> bool {
+        let mut mac = self.mac.clone();
+        mac.update(data);
+        tag.len() == UDP_FORWARD_TAG_LEN && mac.verify_truncated_left(tag).is_ok()
+    }
+}
+
+/// 刷新记录时读取的本地状态
+pub struct LocalState {
+    pub connection_manager: Arc<DeviceConnectionManager>,
+    pub session_manager: Arc<SessionManager>,
+    pub udp_server: Arc<UdpAudioServer>,
+}
+
+/// 本副本的 ID：配置的 instance_id，留空时使用 HOSTNAME 环境变量，仍为空时随机生成
+pub fn instance_id(config: &ClusterConfig) -> String {
+    Some(config.instance_id.clone())
+        .filter(|id| !id.is_empty())
+        .or_else(hostname)
+        .unwrap_or_else(|| format!("bridge-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]))
+}
+
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME").ok().filter(|name| !name.is_empty())
+}
+
+/// 基于 Redis 的副本注册表
+pub struct ClusterRegistry {
+    config: ClusterConfig,
+    instance_id: String,
+    internal_url: String,
+    udp_address: String,
+    redis: Arc<RedisClient>,
+    http: reqwest::Client,
+    token: String,
+    claim: Script,
+    release: Script,
+    refresh: Script,
+    // 设备 UDP 路由的本地缓存：device_id -> (路由, 缓存时间)
+    routes: DashMap<String, (UdpRoute, Instant)>,
+}
+
+impl ClusterRegistry {
+    /// 创建集群注册表，不连接 Redis（连接在首次使用时建立）
+    pub fn new(config: &BridgeConfig, redis: Arc<RedisClient>) -> Result<Arc<Self>> {
+        let cluster = &config.cluster;
+        let instance_id = instance_id(cluster);
+        let host = hostname().unwrap_or_else(|| "localhost".to_string());
+        let internal_url = if cluster.advertise_url.is_empty() {
+            format!("http://{}:{}", host, config.websocket_port)
+        } else {
+            cluster.advertise_url.trim_end_matches('/').to_string()
+        };
+        let udp_address = if cluster.advertise_udp_address.is_empty() {
+            let port = config.udp_bind_address.parse::<SocketAddr>().map(|addr| addr.port()).unwrap_or(8083);
+            format!("{}:{}", host, port)
+        } else {
+            cluster.advertise_udp_address.clone()
+        };
+
+        let registry = Arc::new(Self {
+            config: cluster.clone(),
+            instance_id,
+            internal_url,
+            udp_address,
+            redis,
+            http: reqwest::Client::builder()
+                .timeout(FORWARD_TIMEOUT)
+                .build()
+                .context("Failed to build cluster forwarding HTTP client")?,
+            token: config.internal_token.clone(),
+            claim: Script::new(CLAIM_SCRIPT),
+            release: Script::new(RELEASE_SCRIPT),
+            refresh: Script::new(REFRESH_SCRIPT),
+            routes: DashMap::new(),
+        });
+
+        Ok(registry)
+    }
+
+    /// 转发 UDP 数据包的签名密钥
+    pub fn udp_forward_key(&self) -> UdpForwardKey {
+        UdpForwardKey::new(&self.token)
+    }
+
+    /// 在 Redis 中注册本副本，失败时由心跳稍后重新注册
+    pub async fn register(&self) -> Result<()> {
+        self.register_instance().await.context("Failed to register bridge instance in Redis")?;
+        info!(
+            "Cluster mode enabled: instance {} (internal API {}, UDP {})",
+            self.instance_id, self.internal_url, self.udp_address
+        );
+        Ok(())
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// 定期刷新本副本、本地设备和会话的记录
+    pub fn spawn_heartbeat(self: &Arc<Self>, local: LocalState) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(registry.config.heartbeat_interval_seconds));
+            loop {
+                interval.tick().await;
+                if let Err(e) = registry.heartbeat(&local).await {
+                    warn!("Cluster heartbeat failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 注销本副本（停止服务时调用），其设备在记录过期前仍指向本副本
+    pub async fn deregister(&self) {
+        let key = self.instances_key();
+        let instance_id = self.instance_id.clone();
+        let result = self
+            .redis
+            .run(|mut conn| async move { conn.hdel::<_, _, ()>(key, instance_id).await })
+            .await;
+        if let Err(e) = result {
+            warn!("Failed to deregister bridge instance {}: {}", self.instance_id, e);
+        }
+    }
+
+    /// 检查 Redis 是否可用
+    pub async fn ping(&self) -> Result<()> {
+        self.redis.ping().await
+    }
+
+    /// 设备通过 WebSocket 连接到本副本，接管设备（后台执行）
+    pub fn device_connected(self: &Arc<Self>, device_id: &str) {
+        self.routes.insert(device_id.to_string(), (UdpRoute::Local, Instant::now()));
+        let key = self.device_key(device_id);
+        self.spawn_set(key);
+    }
+
+    /// 设备断开，删除仍属于本副本的归属记录（后台执行）
+    pub fn device_disconnected(self: &Arc<Self>, device_id: &str) {
+        self.routes.remove(device_id);
+        let key = self.device_key(device_id);
+        self.spawn_release(key);
+    }
+
+    /// 会话在本副本创建（后台执行）
+    pub fn session_started(self: &Arc<Self>, session_id: &str) {
+        let key = self.session_key(session_id);
+        self.spawn_set(key);
+    }
+
+    /// 会话在本副本结束（后台执行）
+    pub fn session_ended(self: &Arc<Self>, session_id: &str) {
+        let key = self.session_key(session_id);
+        self.spawn_release(key);
+    }
+
+    /// 确定设备 UDP 数据包的处理位置
+    ///
+    /// 设备没有归属、属于本副本或归属副本已失效时在本地处理（并认领设备），
+    /// 否则转发给归属副本。Redis 不可用时在本地处理。
+    pub async fn route_udp(&self, device_id: &str) -> UdpRoute {
+        let cache_ttl = Duration::from_millis(self.config.route_cache_ms);
+        if let Some(entry) = self.routes.get(device_id) {
+            if entry.1.elapsed() < cache_ttl {
+                return entry.0;
+            }
+        }
+
+        let route = match self.resolve_udp_route(device_id).await {
+            Ok(route) => route,
+            Err(e) => {
+                debug!("Failed to resolve owner of device {}, handling locally: {:#}", device_id, e);
+                UdpRoute::Local
+            }
+        };
+        self.routes.insert(device_id.to_string(), (route, Instant::now()));
+        route
+    }
+
+    /// 会话所在的其他副本，会话不在 Redis 中、属于本副本或归属副本已失效时返回 None
+    pub async fn session_owner(&self, session_id: &str) -> Result<Option<InstanceInfo>> {
+        let key = self.session_key(session_id);
+        let owner: Option<String> = self.redis.run(|mut conn| async move { conn.get(key).await }).await?;
+        match owner {
+            Some(owner) if owner != self.instance_id => self.live_instance(&owner).await,
+            _ => Ok(None),
+        }
+    }
+
+    /// 其他存活的副本
+    pub async fn peers(&self) -> Result<Vec<InstanceInfo>> {
+        let key = self.instances_key();
+        let entries: Vec<String> = self.redis.run(|mut conn| async move { conn.hvals(key).await }).await?;
+        Ok(entries
+            .iter()
+            .filter_map(|entry| serde_json::from_str::<InstanceInfo>(entry).ok())
+            .filter(|instance| instance.instance_id != self.instance_id && self.is_alive(instance))
+            .collect())
+    }
+
+    /// 向其他副本的内部 API 转发请求
+    pub async fn forward(
+        &self,
+        peer: &InstanceInfo,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<reqwest::Response> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", peer.internal_url, path))
+            .bearer_auth(&self.token);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let result = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to forward {} to bridge instance {}", path, peer.instance_id));
+        telemetry::record_cluster_forward("http", result.is_ok());
+        result
+    }
+
+    async fn resolve_udp_route(&self, device_id: &str) -> Result<UdpRoute> {
+        let owner = self.claim_device(device_id, "").await?;
+        if owner == self.instance_id {
+            return Ok(UdpRoute::Local);
+        }
+
+        match self.live_instance(&owner).await? {
+            Some(instance) => {
+                let address = tokio::net::lookup_host(&instance.udp_address)
+                    .await
+                    .with_context(|| format!("Failed to resolve {}", instance.udp_address))?
+                    .next()
+                    .with_context(|| format!("No address for {}", instance.udp_address))?;
+                Ok(UdpRoute::Forward(address))
+            }
+            None => {
+                // 归属副本已失效，接管设备；被其他副本抢先接管时，缓存过期后再转发
+                if self.claim_device(device_id, &owner).await? == self.instance_id {
+                    info!("Took over device {} from stale bridge instance {}", device_id, owner);
+                }
+                Ok(UdpRoute::Local)
+            }
+        }
+    }
+
+    // 认领设备，返回认领后的归属副本
+    async fn claim_device(&self, device_id: &str, stale_owner: &str) -> Result<String> {
+        let key = self.device_key(device_id);
+        let owner = self
+            .redis
+            .run(|mut conn| async move {
+                self.claim
+                    .key(key)
+                    .arg(&self.instance_id)
+                    .arg(self.config.presence_ttl_seconds)
+                    .arg(stale_owner)
+                    .invoke_async(&mut conn)
+                    .await
+            })
+            .await?;
+        Ok(owner)
+    }
+
+    // 副本存活时返回其信息
+    async fn live_instance(&self, instance_id: &str) -> Result<Option<InstanceInfo>> {
+        let key = self.instances_key();
+        let field = instance_id.to_string();
+        let entry: Option<String> = self.redis.run(|mut conn| async move { conn.hget(key, field).await }).await?;
+        Ok(entry
+            .and_then(|entry| serde_json::from_str::<InstanceInfo>(&entry).ok())
+            .filter(|instance| self.is_alive(instance)))
+    }
+
+    fn is_alive(&self, instance: &InstanceInfo) -> bool {
+        now_utc().signed_duration_since(instance.heartbeat_at).num_seconds() < self.config.presence_ttl_seconds as i64
+    }
+
+    async fn register_instance(&self) -> RedisResult<()> {
+        let info = InstanceInfo {
+            instance_id: self.instance_id.clone(),
+            internal_url: self.internal_url.clone(),
+            udp_address: self.udp_address.clone(),
+            heartbeat_at: now_utc(),
+        };
+        let key = self.instances_key();
+        let value = serde_json::to_string(&info).unwrap_or_default();
+        let field = self.instance_id.clone();
+        self.redis.run(|mut conn| async move { conn.hset::<_, _, _, ()>(key, field, value).await })
+            .await
+    }
+
+    async fn heartbeat(&self, local: &LocalState) -> RedisResult<()> {
+        self.register_instance().await?;
+
+        let mut devices: Vec<String> = local
+            .connection_manager
+            .get_online_devices()
+            .await
+            .into_iter()
+            .map(|(device_id, _)| device_id)
+            .collect();
+        devices.extend(local.udp_server.get_registered_devices().await);
+        let sessions = local.session_manager.get_active_session_states().await;
+
+        let keys: Vec<String> = devices
+            .iter()
+            .map(|device_id| self.device_key(device_id))
+            .chain(sessions.iter().map(|session| self.session_key(&session.session_id)))
+            .collect();
+        if !keys.is_empty() {
+            self.redis.run(|mut conn| async move {
+                self.refresh
+                    .key(keys)
+                    .arg(&self.instance_id)
+                    .arg(self.config.presence_ttl_seconds)
+                    .invoke_async::<_, ()>(&mut conn)
+                    .await
+            })
+            .await?;
+        }
+
+        // 清理过期的路由缓存
+        let cache_ttl = Duration::from_millis(self.config.route_cache_ms);
+        self.routes.retain(|_, (_, cached_at)| cached_at.elapsed() < cache_ttl);
+
+        // 清理已失效的副本
+        let key = self.instances_key();
+        let entries: Vec<(String, String)> = self
+            .redis
+            .run(|mut conn| async move { conn.hgetall(key).await })
+            .await?;
+        let stale: Vec<String> = entries
+            .into_iter()
+            .filter(|(_, entry)| {
+                serde_json::from_str::<InstanceInfo>(entry).map_or(true, |instance| !self.is_alive(&instance))
+            })
+            .map(|(instance_id, _)| instance_id)
+            .collect();
+        if !stale.is_empty() {
+            debug!("Removing stale bridge instances: {:?}", stale);
+            let key = self.instances_key();
+            self.redis.run(|mut conn| async move { conn.hdel::<_, _, ()>(key, stale).await })
+                .await?;
+        }
+        Ok(())
+    }
+
+    // 后台写入归属记录（直接覆盖）
+    fn spawn_set(self: &Arc<Self>, key: String) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let instance_id = registry.instance_id.clone();
+            let ttl = registry.config.presence_ttl_seconds;
+            let result = registry
+                .redis
+                .run(|mut conn| async move { conn.set_ex::<_, _, ()>(&key, instance_id, ttl).await })
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to update cluster ownership: {}", e);
+            }
+        });
+    }
+
+    // 后台删除属于本副本的归属记录
+    fn spawn_release(self: &Arc<Self>, key: String) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let registry = &registry;
+            let result = registry
+                .redis
+                .run(|mut conn| async move {
+                    registry
+                        .release
+                        .key(key)
+                        .arg(&registry.instance_id)
+                        .invoke_async::<_, ()>(&mut conn)
+                        .await
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to release cluster ownership: {}", e);
+            }
+        });
+    }
+
+    fn instances_key(&self) -> String {
+        format!("{}instances", self.config.key_prefix)
+    }
+
+    fn device_key(&self, device_id: &str) -> String {
+        format!("{}device:{}", self.config.key_prefix, device_id)
+    }
+
+    fn session_key(&self, session_id: &str) -> String {
+        format!("{}session:{}", self.config.key_prefix, session_id)
+    }
+}
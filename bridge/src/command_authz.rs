@@ -0,0 +1,41 @@
+//! 设备命令授权
+//!
+//! API Gateway 已按 `[command_authorization]` 检查过用户下发的命令，这里在转发给设备之前再次检查命令中的
+//! `issued_by`，拒绝绕过 API Gateway 直接发布到 MQTT 或内部接口的越权命令。被拒绝的命令记录到审计日志。
+
+use echo_shared::{CommandAuthorizer, CommandIssuer, DeviceCommandKind};
+use tracing::{error, warn};
+
+use crate::telemetry;
+
+/// 是否允许执行命令，transport 为命令来源（mqtt / grpc）
+pub async fn allow(
+    authorizer: &CommandAuthorizer,
+    transport: &'static str,
+    device_id: &str,
+    kind: DeviceCommandKind,
+    issuer: Option<&CommandIssuer>,
+) -> bool {
+    let Err(denied) = authorizer.check(device_id, kind, issuer).await else {
+        return true;
+    };
+    // 系统命令不会被拒绝，issuer 一定存在
+    let Some(issuer) = issuer else {
+        return false;
+    };
+
+    warn!(
+        "Denied {} command for device {} from {} via {}: {}",
+        kind.as_str(),
+        device_id,
+        issuer.username,
+        transport,
+        denied
+    );
+    telemetry::record_command_rejected(transport, denied.reason());
+    let source = format!("bridge_{}", transport);
+    if let Err(e) = authorizer.record_denial(device_id, kind, issuer, &denied, &source, None).await {
+        error!("Failed to write audit log for denied command on device {}: {}", device_id, e);
+    }
+    false
+}
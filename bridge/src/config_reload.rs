@@ -0,0 +1,15 @@
+// 配置热加载：SIGHUP 或 POST /admin/reload 触发重新加载，日志级别、会话超时和 Hello 缓存策略通过 watch 通道生效
+use echo_shared::{LogConfig, LogReloadHandle};
+
+/// 初始化日志（文本或 JSON 格式），返回用于调整日志级别的句柄
+///
+/// 启用 `tokio-console` 特性时同时注册 console-subscriber（默认监听 127.0.0.1:6669）
+pub fn init_tracing(config: &LogConfig) -> LogReloadHandle {
+    // tokio-console 需要接收全部任务事件，不受日志级别影响
+    #[cfg(feature = "tokio-console")]
+    let console_layer: Option<echo_shared::ExtraLayer> = Some(Box::new(console_subscriber::spawn()));
+    #[cfg(not(feature = "tokio-console"))]
+    let console_layer = None;
+
+    echo_shared::init_tracing(config, console_layer)
+}
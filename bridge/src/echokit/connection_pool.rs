@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, watch};
 use tracing::{debug, error, info, warn};
-use sqlx::PgPool;
+use echo_shared::{DeviceId, IngressLimitsConfig, RuntimeConfig, Storage};
 
-use crate::echokit_client::EchoKitConnectionManager;
+use crate::echokit_client::{EchoKitClient, EchoKitConnectionManager};
+use crate::hello_store::HelloStore;
+use crate::channels::{AudioSender, ControlSender};
 
 /// EchoKit 连接池 - 管理多个 EchoKit Server 的连接
 ///
@@ -18,37 +20,62 @@ pub struct EchoKitConnectionPool {
     /// 核心存储：echokit_server_url -> EchoKitConnectionManager
     connections: Arc<RwLock<HashMap<String, Arc<EchoKitConnectionManager>>>>,
 
-    /// 数据库连接池，用于查询设备的 echokit_server_url
-    db_pool: Arc<PgPool>,
+    /// 存储层，用于查询设备的 echokit_server_url
+    storage: Arc<dyn Storage>,
 
     /// 回调通道（从 main.rs 传入，所有连接共享）
-    audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-    asr_callback: mpsc::UnboundedSender<(String, String)>,
-    response_callback: mpsc::UnboundedSender<(String, String)>,
-    raw_message_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
+    audio_callback: AudioSender<(String, Vec<u8>)>,
+    asr_callback: ControlSender<(String, String)>,
+    response_callback: ControlSender<(String, String)>,
+    raw_message_callback: ControlSender<(String, Vec<u8>)>,
+
+    /// 可热加载的配置（传给每个连接，用于 Hello 缓存策略）
+    runtime_config: watch::Receiver<RuntimeConfig>,
+
+    /// 欢迎语的 Redis 持久化（传给每个连接），未启用时为 None
+    hello_store: Option<Arc<HelloStore>>,
+
+    /// 接收 EchoKit 消息时的大小限制（传给每个连接）
+    ingress_limits: IngressLimitsConfig,
 }
 
 impl EchoKitConnectionPool {
     /// 创建新的连接池（HashMap 初始为空，懒加载）
     pub fn new(
-        db_pool: Arc<PgPool>,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-        asr_callback: mpsc::UnboundedSender<(String, String)>,
-        response_callback: mpsc::UnboundedSender<(String, String)>,
-        raw_message_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
+        storage: Arc<dyn Storage>,
+        audio_callback: AudioSender<(String, Vec<u8>)>,
+        asr_callback: ControlSender<(String, String)>,
+        response_callback: ControlSender<(String, String)>,
+        raw_message_callback: ControlSender<(String, Vec<u8>)>,
+        runtime_config: watch::Receiver<RuntimeConfig>,
     ) -> Self {
         info!("🔧 Creating EchoKitConnectionPool (lazy loading mode)");
 
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
-            db_pool,
+            storage,
             audio_callback,
             asr_callback,
             response_callback,
             raw_message_callback,
+            runtime_config,
+            hello_store: None,
+            ingress_limits: IngressLimitsConfig::default(),
         }
     }
 
+    /// 新建的连接从 Redis 恢复其他副本保存的欢迎语，并在收到新的欢迎语后保存
+    pub fn with_hello_store(mut self, hello_store: Arc<HelloStore>) -> Self {
+        self.hello_store = Some(hello_store);
+        self
+    }
+
+    /// 新建的连接按配置限制 EchoKit 消息的帧大小和 MessagePack 嵌套深度
+    pub fn with_ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
     /// 根据设备 ID 获取对应的 EchoKit 连接管理器
     pub async fn get_connection_for_device(
         &self,
@@ -94,13 +121,22 @@ impl EchoKitConnectionPool {
         // 🆕 创建新的连接管理器
         info!("🔌 Creating new EchoKit connection for {}", echokit_url);
 
-        let manager = Arc::new(EchoKitConnectionManager::new_with_all_callbacks(
-            echokit_url.to_string(),
-            self.audio_callback.clone(),
-            self.asr_callback.clone(),
-            self.response_callback.clone(),
-            self.raw_message_callback.clone(),
-        ));
+        let mut builder = EchoKitClient::builder(echokit_url)
+            .audio_callback(self.audio_callback.clone())
+            .asr_callback(self.asr_callback.clone())
+            .response_callback(self.response_callback.clone())
+            .raw_message_callback(self.raw_message_callback.clone())
+            .runtime_config(self.runtime_config.clone())
+            .ingress_limits(self.ingress_limits.clone());
+        if let Some(hello_store) = &self.hello_store {
+            builder = builder.hello_store(hello_store.clone());
+        }
+        let client = builder
+            .build()
+            .with_context(|| format!("Failed to create EchoKit client for {}", echokit_url))?;
+        // 🎁 恢复其他副本保存的欢迎语，首个会话即可立即回放
+        client.restore_hello_cache().await;
+        let manager = Arc::new(EchoKitConnectionManager::new(client));
 
         // 🚀 启动连接（后台异步连接）
         manager.start().await
@@ -125,20 +161,19 @@ impl EchoKitConnectionPool {
 
     /// 从数据库查询设备的 echokit_server_url
     ///
-    /// 注意：数据库约束保证 echokit_server_url 字段不会是 NULL
+    /// 注意：数据库约束保证 echokit_server_url 字段不会是 NULL，存储层把空字符串读作 None
     async fn get_device_echokit_url(&self, device_id: &str) -> Result<String> {
-        let result = sqlx::query!(
-            "SELECT echokit_server_url FROM devices WHERE id = $1",
-            device_id
-        )
-        .fetch_optional(&*self.db_pool)
-        .await
-        .with_context(|| format!("Failed to query device {} from database", device_id))?;
+        let result = self
+            .storage
+            .get_device(&DeviceId::new(device_id))
+            .await
+            .with_context(|| format!("Failed to query device {} from database", device_id))?;
 
         match result {
-            Some(record) => {
-                // 数据库字段有 NOT NULL 约束，直接使用
-                let url = record.echokit_server_url;
+            Some(device) => {
+                let url = device
+                    .echokit_server_url
+                    .with_context(|| format!("Device {} has no EchoKit server URL", device_id))?;
                 info!("📍 Device {} using EchoKit URL: {}", device_id, url);
                 Ok(url)
             }
@@ -154,6 +189,30 @@ impl EchoKitConnectionPool {
         self.connections.read().await.len()
     }
 
+    /// 已连接的 EchoKit Server 数量（用于就绪检查）
+    pub async fn get_connected_count(&self) -> usize {
+        let managers: Vec<_> = self.connections.read().await.values().cloned().collect();
+        let mut connected = 0;
+        for manager in managers {
+            if manager.get_client().is_connected().await {
+                connected += 1;
+            }
+        }
+        connected
+    }
+
+    /// 各 EchoKit Server 上报的识别语言的并集（排序去重），都未上报时为空
+    pub async fn supported_languages(&self) -> Vec<String> {
+        let managers: Vec<_> = self.connections.read().await.values().cloned().collect();
+        let mut languages = std::collections::BTreeSet::new();
+        for manager in managers {
+            if let Some(status) = manager.get_client().get_service_status().await {
+                languages.extend(status.supported_languages);
+            }
+        }
+        languages.into_iter().collect()
+    }
+
     /// 获取所有连接的 URL 列表（用于调试）
     pub async fn get_connection_urls(&self) -> Vec<String> {
         self.connections.read().await.keys().cloned().collect()
@@ -0,0 +1,291 @@
+//! 进程内的模拟 EchoKit Server
+//!
+//! 测试（以及启用 `mock-echokit` 特性的构建）中代替外部 EchoKit Server：连接建立后发送脚本中的欢迎语
+//! （HelloStart / HelloChunk / HelloEnd），收到 `{"event": "Submit"}` 后按脚本回复 ASR、StartAudio、
+//! AudioChunk、EndAudio 和 EndResponse。所有事件都按 EchoKit 的 MessagePack 格式编码，Bridge 的适配、
+//! 欢迎语缓存和按设备路由逻辑无需外部依赖即可在 CI 中测试。
+//!
+//! 启用 `mock-echokit` 特性时，设置 `ECHOKIT_MOCK_ADDR`（如 `127.0.0.1:9988`）会在启动时监听该地址，
+//! 把 `bridge.echokit_websocket_url` 配置为 `ws://127.0.0.1:9988/ws/{device_id}` 即可。
+
+// 启用 mock-echokit 特性的构建只用到其中一部分接口
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use echo_shared::EchoKitEvent;
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, warn};
+
+// 20ms 的 16-bit、16000Hz 单声道静音
+const SILENCE_CHUNK_BYTES: usize = 640;
+
+/// 模拟服务器发送的事件序列
+#[derive(Debug, Clone)]
+pub struct MockScript {
+    /// 连接建立后发送
+    pub hello: Vec<EchoKitEvent>,
+    /// 每次收到 Submit 后发送
+    pub response: Vec<EchoKitEvent>,
+    /// 相邻两个事件之间的间隔
+    pub frame_delay: Duration,
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self::conversation("你好", "你好，我是 EchoKit", 3)
+    }
+}
+
+impl MockScript {
+    /// 欢迎语和一轮对话，音频为 audio_chunks 个静音块
+    pub fn conversation(asr: &str, reply: &str, audio_chunks: usize) -> Self {
+        let silence = || vec![0u8; SILENCE_CHUNK_BYTES];
+        let mut hello = vec![EchoKitEvent::HelloStart];
+        hello.extend((0..audio_chunks).map(|_| EchoKitEvent::HelloChunk { data: silence() }));
+        hello.push(EchoKitEvent::HelloEnd);
+
+        let mut response = vec![
+            EchoKitEvent::ASR { text: asr.to_string() },
+            EchoKitEvent::StartAudio { text: reply.to_string() },
+        ];
+        response.extend((0..audio_chunks).map(|_| EchoKitEvent::AudioChunk { data: silence() }));
+        response.extend([EchoKitEvent::EndAudio, EchoKitEvent::EndResponse]);
+
+        Self { hello, response, frame_delay: Duration::ZERO }
+    }
+
+    pub fn with_hello(mut self, hello: Vec<EchoKitEvent>) -> Self {
+        self.hello = hello;
+        self
+    }
+
+    pub fn with_response(mut self, response: Vec<EchoKitEvent>) -> Self {
+        self.response = response;
+        self
+    }
+
+    /// 按实际速率发送音频时设置为 20ms
+    pub fn with_frame_delay(mut self, frame_delay: Duration) -> Self {
+        self.frame_delay = frame_delay;
+        self
+    }
+}
+
+/// 模拟服务器收到的内容
+#[derive(Debug, Clone, Default)]
+pub struct MockStats {
+    /// 已建立的连接数
+    pub connections: usize,
+    /// 每个连接的请求路径（如 `/ws/device-1`）
+    pub paths: Vec<String>,
+    /// 收到的二进制（音频）数据总字节数
+    pub audio_bytes: usize,
+    /// 收到的 Submit 次数
+    pub submits: usize,
+    /// 收到的文本消息
+    pub text_messages: Vec<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    connections: AtomicUsize,
+    audio_bytes: AtomicUsize,
+    submits: AtomicUsize,
+    paths: Mutex<Vec<String>>,
+    text_messages: Mutex<Vec<String>>,
+}
+
+/// 模拟 EchoKit Server，drop 时停止监听
+pub struct MockEchoKitServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<()>,
+}
+
+impl MockEchoKitServer {
+    /// 监听 127.0.0.1 上的随机端口
+    pub async fn start(script: MockScript) -> Result<Self> {
+        Self::bind("127.0.0.1:0".parse().expect("valid address"), script).await
+    }
+
+    pub async fn bind(addr: SocketAddr, script: MockScript) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind mock EchoKit server on {}", addr))?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(MockState::default());
+        let script = Arc::new(script);
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                loop {
+                    let (stream, peer) = match listener.accept().await {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!("Mock EchoKit server failed to accept connection: {}", e);
+                            continue;
+                        }
+                    };
+                    let (state, script) = (state.clone(), script.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(stream, &state, &script).await {
+                            debug!("Mock EchoKit connection from {} closed: {}", peer, e);
+                        }
+                    });
+                }
+            }
+        });
+
+        Ok(Self { addr, state, task })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 与 `bridge.echokit_websocket_url` 格式相同的地址，`{device_id}` 由客户端替换
+    pub fn url(&self) -> String {
+        format!("ws://{}/ws/{{device_id}}", self.addr)
+    }
+
+    pub fn stats(&self) -> MockStats {
+        MockStats {
+            connections: self.state.connections.load(Ordering::Relaxed),
+            paths: self.state.paths.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            audio_bytes: self.state.audio_bytes.load(Ordering::Relaxed),
+            submits: self.state.submits.load(Ordering::Relaxed),
+            text_messages: self.state.text_messages.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+}
+
+impl Drop for MockEchoKitServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+// 握手回调的错误类型由 tungstenite 决定
+#[allow(clippy::result_large_err)]
+async fn serve_connection(stream: TcpStream, state: &MockState, script: &MockScript) -> Result<()> {
+    let mut path = String::new();
+    let mut ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        Ok(response)
+    })
+    .await?;
+    state.connections.fetch_add(1, Ordering::Relaxed);
+    state.paths.lock().unwrap_or_else(|e| e.into_inner()).push(path);
+
+    send_events(&mut ws, &script.hello, script.frame_delay).await?;
+
+    while let Some(message) = ws.next().await {
+        match message? {
+            Message::Binary(data) => {
+                state.audio_bytes.fetch_add(data.len(), Ordering::Relaxed);
+            }
+            Message::Text(text) => {
+                let is_submit = serde_json::from_str::<serde_json::Value>(&text)
+                    .is_ok_and(|value| value["event"] == "Submit");
+                state.text_messages.lock().unwrap_or_else(|e| e.into_inner()).push(text);
+                if is_submit {
+                    state.submits.fetch_add(1, Ordering::Relaxed);
+                    send_events(&mut ws, &script.response, script.frame_delay).await?;
+                }
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+async fn send_events(
+    ws: &mut tokio_tungstenite::WebSocketStream<TcpStream>,
+    events: &[EchoKitEvent],
+    frame_delay: Duration,
+) -> Result<()> {
+    for event in events {
+        if !frame_delay.is_zero() {
+            tokio::time::sleep(frame_delay).await;
+        }
+        ws.send(Message::Binary(event.to_messagepack()?)).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channels::{audio_channel, control_channel};
+    use crate::echokit_client::EchoKitClient;
+    use echo_shared::AudioFormat;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn test_conversation_against_mock() {
+        let mock = MockEchoKitServer::start(MockScript::conversation("打开灯", "好的", 2)).await.unwrap();
+        let (audio_tx, mut audio_rx) = audio_channel("test_audio", 64);
+        let (asr_tx, mut asr_rx) = control_channel("test_asr", 8);
+        let (raw_tx, mut raw_rx) = control_channel("test_raw", 16);
+        let client = EchoKitClient::builder(mock.url())
+            .audio_callback(audio_tx)
+            .asr_callback(asr_tx)
+            .raw_message_callback(raw_tx)
+            .build()
+            .unwrap();
+
+        client.pre_register_session("s1".to_string(), "device-1".to_string()).await;
+        client.connect_with_device_id(Some("device-1")).await.unwrap();
+
+        // 欢迎语转发给已注册的会话
+        let mut hello = Vec::new();
+        while hello.last() != Some(&EchoKitEvent::HelloEnd) {
+            let (session_id, frame) = tokio::time::timeout(TIMEOUT, audio_rx.recv()).await.unwrap().unwrap();
+            assert_eq!(session_id, "s1");
+            hello.push(EchoKitEvent::from_messagepack(&frame).unwrap());
+        }
+        assert_eq!(hello[0], EchoKitEvent::HelloStart);
+        assert_eq!(hello.iter().filter(|event| matches!(event, EchoKitEvent::HelloChunk { .. })).count(), 2);
+
+        client
+            .send_audio_data("s1".to_string(), "device-1".to_string(), vec![0; 1280], AudioFormat::PCM16, false)
+            .await
+            .unwrap();
+        client.send_submit_command().await.unwrap();
+        let (session_id, text) = tokio::time::timeout(TIMEOUT, asr_rx.recv()).await.unwrap().unwrap();
+        assert_eq!((session_id.as_str(), text.as_str()), ("s1", "打开灯"));
+        loop {
+            let (_, frame) = tokio::time::timeout(TIMEOUT, audio_rx.recv()).await.unwrap().unwrap();
+            // 客户端还会转发 JSON 格式的 StartAudio 通知和原始 PCM，这里只关心 MessagePack 事件
+            if EchoKitEvent::from_messagepack(&frame).is_ok_and(|event| event == EchoKitEvent::EndResponse) {
+                break;
+            }
+        }
+
+        // 后加入的会话从缓存中收到完整的欢迎语
+        client.pre_register_session("s2".to_string(), "device-1".to_string()).await;
+        client.check_and_send_cached_hello("s2").await;
+        let (session_id, frame) = tokio::time::timeout(TIMEOUT, raw_rx.recv()).await.unwrap().unwrap();
+        assert_eq!(session_id, "s2");
+        assert_eq!(EchoKitEvent::from_messagepack(&frame).unwrap(), EchoKitEvent::HelloStart);
+
+        let stats = mock.stats();
+        assert_eq!(stats.connections, 1);
+        assert_eq!(stats.paths, vec!["/ws/device-1".to_string()]);
+        assert_eq!(stats.audio_bytes, 1280);
+        assert_eq!(stats.submits, 1);
+        assert!(stats.text_messages.iter().any(|text| text.contains("Submit")));
+    }
+}
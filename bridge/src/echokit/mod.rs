@@ -1,5 +1,8 @@
 pub mod websocket_adapter;
 pub mod connection_pool;
+pub mod session_index;
+#[cfg(any(test, feature = "mock-echokit"))]
+pub mod mock_server;
 
 pub use websocket_adapter::EchoKitSessionAdapter;
 pub use connection_pool::EchoKitConnectionPool;
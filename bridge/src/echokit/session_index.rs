@@ -0,0 +1,102 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use echo_shared::{DeviceId, SessionId};
+
+/// Bridge 会话与 EchoKit 会话的双向索引
+///
+/// 正向：bridge_session_id -> (device_id, echokit_session_id)
+/// 反向：echokit_session_id -> (bridge_session_id, device_id)，用于 EchoKit 回调按 O(1) 路由
+///
+/// 写操作在持有正向条目锁期间更新反向索引，同一 Bridge 会话的写入互斥，两个方向保持一致。
+/// 多个 Bridge 会话复用同一 EchoKit 会话时，反向索引指向最近注册的会话。
+#[derive(Debug, Default)]
+pub struct SessionIndex {
+    by_bridge: DashMap<SessionId, (DeviceId, String)>,
+    by_echokit: DashMap<String, (SessionId, DeviceId)>,
+}
+
+impl SessionIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 建立或更新 Bridge 会话到 EchoKit 会话的映射
+    pub fn insert(&self, bridge_session_id: SessionId, device_id: DeviceId, echokit_session_id: String) {
+        let reverse = (bridge_session_id.clone(), device_id.clone());
+        match self.by_bridge.entry(bridge_session_id) {
+            Entry::Occupied(mut entry) => {
+                let (_, previous) = entry.insert((device_id, echokit_session_id.clone()));
+                if previous != echokit_session_id {
+                    self.by_echokit.remove_if(&previous, |_, (bridge_id, _)| bridge_id == entry.key());
+                }
+                self.by_echokit.insert(echokit_session_id, reverse);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((device_id, echokit_session_id.clone()));
+                self.by_echokit.insert(echokit_session_id, reverse);
+            }
+        }
+    }
+
+    /// 移除 Bridge 会话，返回 (device_id, echokit_session_id)
+    pub fn remove(&self, bridge_session_id: &SessionId) -> Option<(DeviceId, String)> {
+        match self.by_bridge.entry(bridge_session_id.clone()) {
+            Entry::Occupied(entry) => {
+                // 反向索引可能已指向复用同一 EchoKit 会话的新 Bridge 会话，只移除指向自己的条目
+                self.by_echokit.remove_if(&entry.get().1, |_, (bridge_id, _)| bridge_id == bridge_session_id);
+                Some(entry.remove())
+            }
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// 根据 Bridge Session ID 查找 (device_id, echokit_session_id)
+    pub fn get(&self, bridge_session_id: &SessionId) -> Option<(DeviceId, String)> {
+        self.by_bridge.get(bridge_session_id).map(|entry| entry.value().clone())
+    }
+
+    /// 根据 EchoKit Session ID 查找 (bridge_session_id, device_id)
+    pub fn find_by_echokit_session(&self, echokit_session_id: &str) -> Option<(SessionId, DeviceId)> {
+        self.by_echokit.get(echokit_session_id).map(|entry| entry.value().clone())
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.by_bridge.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverse_lookup_follows_forward_map() {
+        let index = SessionIndex::new();
+        let bridge: SessionId = "bridge_1".into();
+        index.insert(bridge.clone(), "dev_1".into(), "ek_1".to_string());
+
+        assert_eq!(index.find_by_echokit_session("ek_1"), Some((bridge.clone(), "dev_1".into())));
+
+        // 重新映射到新的 EchoKit 会话后，旧的反向条目被清除
+        index.insert(bridge.clone(), "dev_1".into(), "ek_2".to_string());
+        assert_eq!(index.find_by_echokit_session("ek_1"), None);
+        assert_eq!(index.find_by_echokit_session("ek_2"), Some((bridge.clone(), "dev_1".into())));
+
+        assert_eq!(index.remove(&bridge), Some(("dev_1".into(), "ek_2".to_string())));
+        assert_eq!(index.find_by_echokit_session("ek_2"), None);
+        assert_eq!(index.session_count(), 0);
+    }
+
+    #[test]
+    fn test_reused_echokit_session_keeps_latest_bridge_session() {
+        let index = SessionIndex::new();
+        let first: SessionId = "bridge_1".into();
+        let second: SessionId = "bridge_2".into();
+        index.insert(first.clone(), "dev_1".into(), "ek_1".to_string());
+        index.insert(second.clone(), "dev_1".into(), "ek_1".to_string());
+
+        // 移除旧会话不影响复用同一 EchoKit 会话的新会话
+        index.remove(&first);
+        assert_eq!(index.find_by_echokit_session("ek_1"), Some((second, "dev_1".into())));
+    }
+}
@@ -1,14 +1,18 @@
 use anyhow::{Context, Result};
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
+use crate::asr_postprocess::{self, AsrPostProcessing};
 use crate::echokit_client::EchoKitClient;
+use crate::websocket::audio_spool::AudioSpool;
 use crate::websocket::connection_manager::DeviceConnectionManager;
 use crate::websocket::session_manager::SessionManager;
 use crate::websocket::protocol::ServerEvent;
-use echo_shared::{AudioFormat, EchoKitConfig};
+use crate::channels::{AudioReceiver, ControlReceiver};
+use crate::echokit::session_index::SessionIndex;
+use crate::trace_capture::{self, TraceDirection};
+use echo_shared::{AudioFormat, CircuitBreaker, DeviceId, EchoKitConfig, SessionId};
 
 /// EchoKit 会话适配器 - 负责 Bridge Session 和 EchoKit 的集成
 pub struct EchoKitSessionAdapter {
@@ -19,15 +23,21 @@ pub struct EchoKitSessionAdapter {
     /// 🔧 会话管理器（用于保存 ASR 转录文本到内存）
     session_manager: Arc<SessionManager>,
     /// Session 映射: bridge_session_id -> (device_id, echokit_session_id)
-    session_mapping: Arc<RwLock<HashMap<String, (String, String)>>>,
+    session_mapping: Arc<SessionIndex>,
     /// 音频接收通道
-    audio_receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<(String, Vec<u8>)>>>>,
+    audio_receiver: Arc<RwLock<Option<AudioReceiver<(String, Vec<u8>)>>>>,
     /// ASR 接收通道
-    asr_receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<(String, String)>>>>,
+    asr_receiver: Arc<RwLock<Option<ControlReceiver<(String, String)>>>>,
     /// AI 回复接收通道
-    response_receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<(String, String)>>>>,
+    response_receiver: Arc<RwLock<Option<ControlReceiver<(String, String)>>>>,
     /// 原始消息接收通道（用于直接转发 MessagePack 数据）
-    raw_message_receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<(String, Vec<u8>)>>>>,
+    raw_message_receiver: Arc<RwLock<Option<ControlReceiver<(String, Vec<u8>)>>>>,
+    /// EchoKit 熔断器，EchoKit 不可用时发往 EchoKit 的调用直接失败
+    breaker: Arc<CircuitBreaker>,
+    /// 回复音频暂存，设备断线期间的音频在重连后补发
+    audio_spool: Option<Arc<AudioSpool>>,
+    /// ASR 文本后处理，识别结果保存和转发给设备之前执行
+    asr_postprocessing: Option<Arc<AsrPostProcessing>>,
 }
 
 impl EchoKitSessionAdapter {
@@ -36,28 +46,74 @@ impl EchoKitSessionAdapter {
         echokit_client: Arc<EchoKitClient>,
         connection_manager: Arc<DeviceConnectionManager>,
         session_manager: Arc<SessionManager>,
-        audio_receiver: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
-        asr_receiver: mpsc::UnboundedReceiver<(String, String)>,
-        response_receiver: mpsc::UnboundedReceiver<(String, String)>,
-        raw_message_receiver: mpsc::UnboundedReceiver<(String, Vec<u8>)>,
+        audio_receiver: AudioReceiver<(String, Vec<u8>)>,
+        asr_receiver: ControlReceiver<(String, String)>,
+        response_receiver: ControlReceiver<(String, String)>,
+        raw_message_receiver: ControlReceiver<(String, Vec<u8>)>,
     ) -> Self {
         Self {
             echokit_client,
             connection_manager,
             session_manager,
-            session_mapping: Arc::new(RwLock::new(HashMap::new())),
+            session_mapping: Arc::new(SessionIndex::new()),
             audio_receiver: Arc::new(RwLock::new(Some(audio_receiver))),
             asr_receiver: Arc::new(RwLock::new(Some(asr_receiver))),
             response_receiver: Arc::new(RwLock::new(Some(response_receiver))),
             raw_message_receiver: Arc::new(RwLock::new(Some(raw_message_receiver))),
+            breaker: Arc::new(CircuitBreaker::disabled("echokit")),
+            audio_spool: None,
+            asr_postprocessing: None,
         }
     }
 
+    /// 设置 EchoKit 熔断器
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// 设置回复音频暂存
+    pub fn with_audio_spool(mut self, audio_spool: Arc<AudioSpool>) -> Self {
+        self.audio_spool = Some(audio_spool);
+        self
+    }
+
+    /// 设置 ASR 文本后处理
+    pub fn with_asr_postprocessing(mut self, asr_postprocessing: Arc<AsrPostProcessing>) -> Self {
+        self.asr_postprocessing = Some(asr_postprocessing);
+        self
+    }
+
+    /// 设备重连后补发暂存的回复音频，返回补发的帧数
+    pub async fn replay_spooled_audio(&self, device_id: &str) -> usize {
+        match &self.audio_spool {
+            Some(spool) => spool.replay(device_id, &self.connection_manager).await,
+            None => 0,
+        }
+    }
+
+    // 转发回复音频，设备不可达时暂存；会话已有暂存的音频时直接追加，保证补发顺序
+    async fn forward_or_spool(&self, spool: &AudioSpool, session_id: &str, device_id: &str, data: Vec<u8>) -> Result<()> {
+        let data = axum::body::Bytes::from(data);
+        if !spool.is_spooling(session_id).await {
+            match self.connection_manager.try_send_binary(device_id, data.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("Send to device {} failed, spooling: {}", device_id, e),
+            }
+        }
+
+        if let Err(failure) = spool.push(session_id, device_id, data).await {
+            self.session_manager.mark_delivery_failed(session_id).await;
+            anyhow::bail!("response audio for session {} not delivered ({})", session_id, failure.as_str());
+        }
+        Ok(())
+    }
+
     /// 创建 EchoKit 会话
     pub async fn create_echokit_session(
         &self,
-        bridge_session_id: String,
-        device_id: String,
+        bridge_session_id: SessionId,
+        device_id: DeviceId,
         config: EchoKitConfig,
     ) -> Result<String> {
         let start_time = std::time::Instant::now();
@@ -74,16 +130,20 @@ impl EchoKitSessionAdapter {
         // 如果尚未连接或需要重新连接到不同的 device_id，则重新连接
         if !self.echokit_client.is_connected().await {
             info!("EchoKit not connected, connecting with device_id: {}", device_id);
-            self.echokit_client
-                .connect_with_device_id(Some(&device_id))
-                .await
-                .with_context(|| format!("Failed to connect to EchoKit with device_id: {}", device_id))?;
+            self.breaker
+                .call(async {
+                    self.echokit_client
+                        .connect_with_device_id(Some(&device_id))
+                        .await
+                        .with_context(|| format!("Failed to connect to EchoKit with device_id: {}", device_id))
+                })
+                .await?;
         }
 
         // 🔑 关键修复：在调用 start_session 之前，立即在 active_sessions 中预注册
         // 这样可以确保当 EchoKit Server 返回 HelloChunk 时，转发循环能找到 session
         self.echokit_client
-            .pre_register_session(echokit_session_id.clone(), device_id.clone())
+            .pre_register_session(echokit_session_id.clone(), device_id.to_string())
             .await;
 
         let pre_register_elapsed = start_time.elapsed();
@@ -91,19 +151,23 @@ impl EchoKitSessionAdapter {
 
         // 调用 EchoKit 客户端启动会话
         let session_start_time = std::time::Instant::now();
-        self.echokit_client
-            .start_session(echokit_session_id.clone(), device_id.clone(), config)
-            .await
-            .with_context(|| "Failed to start EchoKit session")?;
+        self.breaker
+            .call(async {
+                self.echokit_client
+                    .start_session(echokit_session_id.clone(), device_id.to_string(), config)
+                    .await
+                    .with_context(|| "Failed to start EchoKit session")
+            })
+            .await?;
 
         let session_start_elapsed = session_start_time.elapsed();
         info!("⏱️ start_session took: {:.3}s", session_start_elapsed.as_secs_f64());
 
         // 保存映射关系
-        let mut mapping = self.session_mapping.write().await;
-        mapping.insert(
+        self.session_mapping.insert(
             bridge_session_id.clone(),
-            (device_id.clone(), echokit_session_id.clone()),
+            device_id.clone(),
+            echokit_session_id.clone(),
         );
 
         let total_elapsed = start_time.elapsed();
@@ -126,8 +190,8 @@ impl EchoKitSessionAdapter {
     /// 注册 Bridge 会话到现有的 EchoKit 会话（复用 EchoKit 会话）
     pub async fn register_bridge_session(
         &self,
-        bridge_session_id: String,
-        device_id: String,
+        bridge_session_id: SessionId,
+        device_id: DeviceId,
         echokit_session_id: String,
     ) -> Result<()> {
         info!(
@@ -136,17 +200,16 @@ impl EchoKitSessionAdapter {
         );
 
         // 保存映射关系
-        let mut mapping = self.session_mapping.write().await;
-        mapping.insert(
+        self.session_mapping.insert(
             bridge_session_id.clone(),
-            (device_id.clone(), echokit_session_id.clone()),
+            device_id.clone(),
+            echokit_session_id.clone(),
         );
-        drop(mapping);
 
         // 🔑 重新注册 EchoKit Session ID 到 active_sessions
         // 确保 ASR 等消息可以正确转发
         self.echokit_client
-            .pre_register_session(echokit_session_id.clone(), device_id.clone())
+            .pre_register_session(echokit_session_id.clone(), device_id.to_string())
             .await;
 
         // 🎁 修复：复用会话时也要发送缓存的 Hello 消息给新客户端
@@ -165,16 +228,14 @@ impl EchoKitSessionAdapter {
     /// 转发音频到 EchoKit
     pub async fn forward_audio(
         &self,
-        bridge_session_id: &str,
+        bridge_session_id: &SessionId,
         audio_data: Vec<u8>,
     ) -> Result<()> {
         // 获取映射信息
-        let mapping = self.session_mapping.read().await;
-        let (device_id, echokit_session_id) = mapping
+        let (device_id, echokit_session_id) = self
+            .session_mapping
             .get(bridge_session_id)
-            .ok_or_else(|| anyhow::anyhow!("Session {} not found", bridge_session_id))?
-            .clone();
-        drop(mapping);
+            .ok_or_else(|| anyhow::anyhow!("Session {} not found", bridge_session_id))?;
 
         debug!(
             "Forwarding {} bytes audio from bridge session {} to EchoKit session {}",
@@ -183,30 +244,34 @@ impl EchoKitSessionAdapter {
             echokit_session_id
         );
 
+        trace_capture::record(bridge_session_id, &device_id, TraceDirection::BridgeToEchokit, "audio", audio_data.len());
+
         // 发送音频到 EchoKit（StartChat已在会话创建时发送）
-        self.echokit_client
-            .send_audio_data(
-                echokit_session_id,
-                device_id,
-                audio_data,
-                AudioFormat::PCM16, // PCM 16-bit format
-                false,
-            )
-            .await
-            .with_context(|| "Failed to send audio to EchoKit")?;
+        self.breaker
+            .call(async {
+                self.echokit_client
+                    .send_audio_data(
+                        echokit_session_id,
+                        device_id.into_inner(),
+                        audio_data,
+                        AudioFormat::PCM16, // PCM 16-bit format
+                        false,
+                    )
+                    .await
+                    .with_context(|| "Failed to send audio to EchoKit")
+            })
+            .await?;
 
         Ok(())
     }
 
     /// 提交音频进行处理（发送Submit消息到EchoKit）
-    pub async fn submit_audio_for_processing(&self, bridge_session_id: &str) -> Result<()> {
+    pub async fn submit_audio_for_processing(&self, bridge_session_id: &SessionId) -> Result<()> {
         // 获取映射信息
-        let mapping = self.session_mapping.read().await;
-        let (device_id, echokit_session_id) = mapping
+        let (device_id, echokit_session_id) = self
+            .session_mapping
             .get(bridge_session_id)
-            .ok_or_else(|| anyhow::anyhow!("Session {} not found", bridge_session_id))?
-            .clone();
-        drop(mapping);
+            .ok_or_else(|| anyhow::anyhow!("Session {} not found", bridge_session_id))?;
 
         info!(
             "📤 Submitting audio for processing: bridge={}, echokit={}",
@@ -214,23 +279,32 @@ impl EchoKitSessionAdapter {
         );
 
         // 发送Submit命令到EchoKit
-        self.echokit_client
-            .send_submit_command()
-            .await
-            .with_context(|| "Failed to send submit command to EchoKit")?;
+        self.breaker
+            .call(async {
+                self.echokit_client
+                    .send_submit_command()
+                    .await
+                    .with_context(|| "Failed to send submit command to EchoKit")
+            })
+            .await?;
 
         info!("✅ Submit command sent successfully to EchoKit");
         Ok(())
     }
 
     /// 发送StartChat命令到EchoKit（开始新的对话会话）
-    pub async fn send_start_chat(&self, echokit_session_id: &str) -> Result<()> {
+    /// 指定 `language` 时 EchoKit 按该语言识别本轮对话
+    pub async fn send_start_chat(&self, echokit_session_id: &str, language: Option<&str>) -> Result<()> {
         info!("📤 Sending StartChat command to EchoKit for session {}", echokit_session_id);
 
-        self.echokit_client
-            .send_start_chat_command()
-            .await
-            .with_context(|| "Failed to send StartChat command to EchoKit")?;
+        self.breaker
+            .call(async {
+                self.echokit_client
+                    .send_start_chat_command_with_language(language)
+                    .await
+                    .with_context(|| "Failed to send StartChat command to EchoKit")
+            })
+            .await?;
 
         info!("✅ StartChat command sent successfully to EchoKit for session {}", echokit_session_id);
 
@@ -243,17 +317,12 @@ impl EchoKitSessionAdapter {
 
     /// 根据 Bridge Session ID 发送 StartChat 命令
     /// 这个方法会查找对应的 EchoKit Session 并发送 StartChat
-    pub async fn send_start_chat_for_session(&self, bridge_session_id: &str) -> Result<()> {
+    pub async fn send_start_chat_for_session(&self, bridge_session_id: &SessionId) -> Result<()> {
         // 首先获取 EchoKit session ID（作用域结束后自动释放锁）
-        let echokit_session_id = {
-            let session_mapping = self.session_mapping.read().await;
-
-            if let Some((_, echokit_session_id)) = session_mapping.get(bridge_session_id) {
-                echokit_session_id.clone()
-            } else {
-                anyhow::bail!("Bridge session {} not found in session mapping", bridge_session_id);
-            }
-        }; // session_mapping 锁在此释放
+        let echokit_session_id = match self.session_mapping.get(bridge_session_id) {
+            Some((_, echokit_session_id)) => echokit_session_id,
+            None => anyhow::bail!("Bridge session {} not found in session mapping", bridge_session_id),
+        };
 
         debug!(
             "Sending StartChat for bridge session {} -> EchoKit session {}",
@@ -261,7 +330,7 @@ impl EchoKitSessionAdapter {
         );
 
         // 调用原有的 send_start_chat 方法
-        self.send_start_chat(&echokit_session_id).await
+        self.send_start_chat(&echokit_session_id, None).await
     }
 
     /// 启动音频接收器（从 EchoKit 接收原始 MessagePack 数据并直接转发到设备）
@@ -274,18 +343,12 @@ impl EchoKitSessionAdapter {
     pub async fn start_audio_receiver(self: Arc<Self>) {
         info!("🎧 Starting EchoKit MessagePack data receiver (direct forwarding mode)");
 
-        // 获取音频接收通道
-        let mut audio_rx = {
-            let mut receiver_guard = self.audio_receiver.write().await;
-            receiver_guard.take()
-        };
-
-        if audio_rx.is_none() {
+        // 获取音频接收通道（持有到退出，任务异常退出后重启时可以继续接收）
+        let mut receiver_guard = self.audio_receiver.write().await;
+        let Some(audio_rx) = receiver_guard.as_mut() else {
             error!("❌ Audio receiver channel not available");
             return;
-        }
-
-        let mut audio_rx = audio_rx.unwrap();
+        };
         info!("✅ Audio receiver channel acquired, waiting for MessagePack data...");
 
         // 持续监听 MessagePack 数据
@@ -296,18 +359,22 @@ impl EchoKitSessionAdapter {
                 raw_messagepack_data.len()
             );
 
-            // 根据 echokit_session_id 找到对应的 device_id
-            let device_id = {
-                let mapping = self.session_mapping.read().await;
-                mapping
-                    .iter()
-                    .find(|(_, (_, ek_id))| ek_id == &echokit_session_id)
-                    .map(|(_, (dev_id, _))| dev_id.clone())
-            };
-
-            if let Some(device_id) = device_id {
-                // 直接转发原始 MessagePack 数据到设备，不做任何处理
-                match self.connection_manager.send_binary(&device_id, raw_messagepack_data.clone()).await {
+            // 根据 echokit_session_id 找到对应的 bridge_session_id 和 device_id
+            let target = self.find_by_echokit_session(&echokit_session_id);
+
+            if let Some((bridge_session_id, device_id)) = target {
+                trace_capture::record_echokit_frame(&bridge_session_id, &device_id, TraceDirection::EchokitToBridge, &raw_messagepack_data);
+
+                // 直接转发原始 MessagePack 数据到设备，不做任何处理（v2 设备加上带会话 ID 的帧头）
+                trace_capture::record_echokit_frame(&bridge_session_id, &device_id, TraceDirection::BridgeToDevice, &raw_messagepack_data);
+                let forwarded = match self.connection_manager.encode_event(&device_id, &bridge_session_id, raw_messagepack_data.clone()).await {
+                    Ok(frame) => match &self.audio_spool {
+                        Some(spool) => self.forward_or_spool(spool, &bridge_session_id, &device_id, frame).await,
+                        None => self.connection_manager.send_binary(&device_id, frame).await,
+                    },
+                    Err(e) => Err(e),
+                };
+                match forwarded {
                     Ok(_) => {
                         debug!(
                             "✅ Successfully forwarded {} bytes MessagePack data to device {}",
@@ -338,17 +405,11 @@ impl EchoKitSessionAdapter {
         info!("🎙️ Starting EchoKit ASR receiver");
 
         // 获取 ASR 接收通道
-        let mut asr_rx = {
-            let mut receiver_guard = self.asr_receiver.write().await;
-            receiver_guard.take()
-        };
-
-        if asr_rx.is_none() {
+        let mut receiver_guard = self.asr_receiver.write().await;
+        let Some(asr_rx) = receiver_guard.as_mut() else {
             error!("❌ ASR receiver channel not available");
             return;
-        }
-
-        let mut asr_rx = asr_rx.unwrap();
+        };
         info!("✅ ASR receiver channel acquired, waiting for messages...");
 
         // 持续监听 ASR 数据
@@ -359,45 +420,34 @@ impl EchoKitSessionAdapter {
             );
 
             // 根据 echokit_session_id 找到对应的 device_id
-            let device_id = {
-                let mapping = self.session_mapping.read().await;
-                let device_id = mapping
-                    .iter()
-                    .find(|(_, (_, ek_id))| ek_id == &echokit_session_id)
-                    .map(|(_, (dev_id, _))| dev_id.clone());
-
-                if device_id.is_none() {
-                    warn!("⚠️ No device found for EchoKit session {} in mapping", echokit_session_id);
-                    debug!("Current session mapping: {:?}", *mapping);
-                }
-                device_id
-            };
+            let target = self.find_by_echokit_session(&echokit_session_id);
+            if target.is_none() {
+                warn!("⚠️ No device found for EchoKit session {} in mapping", echokit_session_id);
+                debug!(
+                    "Current session mapping ({} sessions): {:?}",
+                    self.session_mapping.session_count(),
+                    self.session_mapping
+                );
+            }
 
-            if let Some(device_id) = device_id {
+            if let Some((bridge_session_id, device_id)) = target {
                 info!("🎯 Found device {} for ASR, forwarding...", device_id);
+                let asr_text = asr_postprocess::apply(self.asr_postprocessing.as_deref(), device_id.as_str(), asr_text).await;
 
-                // 🔧 方案B：先保存 ASR 文本到内存（找到对应的 bridge_session_id）
-                let bridge_session_id = {
-                    let mapping = self.session_mapping.read().await;
-                    mapping
-                        .iter()
-                        .find(|(_, (_, ek_id))| ek_id == &echokit_session_id)
-                        .map(|(bridge_id, _)| bridge_id.clone())
-                };
+                // 🔧 方案B：先保存 ASR 文本到内存
+                trace_capture::record(&bridge_session_id, &device_id, TraceDirection::EchokitToBridge, "ASR", asr_text.len());
 
-                if let Some(bridge_session_id) = bridge_session_id {
-                    // 将 ASR 文本追加到会话的转录记录中
-                    self.session_manager.append_transcript(&bridge_session_id, asr_text.clone()).await;
-                    info!("💾 Saved ASR text to session {} memory", bridge_session_id);
-                } else {
-                    warn!("⚠️ Could not find bridge session for EchoKit session {}", echokit_session_id);
-                }
+                // 将 ASR 文本追加到会话的转录记录中
+                self.session_manager.append_transcript(&bridge_session_id, asr_text.clone()).await;
+                info!("💾 Saved ASR text to session {} memory", bridge_session_id);
 
                 // 发送 ASR 事件到设备
+                trace_capture::record(&bridge_session_id, &device_id, TraceDirection::BridgeToDevice, "ASR", asr_text.len());
                 match self
                     .connection_manager
                     .send_server_event(
                         &device_id,
+                        &bridge_session_id,
                         ServerEvent::ASR {
                             text: asr_text.clone(),
                         },
@@ -433,17 +483,11 @@ impl EchoKitSessionAdapter {
         info!("🤖 Starting EchoKit AI response receiver");
 
         // 获取 AI 回复接收通道
-        let mut response_rx = {
-            let mut receiver_guard = self.response_receiver.write().await;
-            receiver_guard.take()
-        };
-
-        if response_rx.is_none() {
+        let mut receiver_guard = self.response_receiver.write().await;
+        let Some(response_rx) = receiver_guard.as_mut() else {
             error!("❌ AI response receiver channel not available");
             return;
-        }
-
-        let mut response_rx = response_rx.unwrap();
+        };
         info!("✅ AI response receiver channel acquired, waiting for messages...");
 
         // 持续监听 AI 回复数据
@@ -454,13 +498,9 @@ impl EchoKitSessionAdapter {
             );
 
             // 根据 echokit_session_id 找到对应的 bridge_session_id
-            let bridge_session_id = {
-                let mapping = self.session_mapping.read().await;
-                mapping
-                    .iter()
-                    .find(|(_, (_, ek_id))| ek_id == &echokit_session_id)
-                    .map(|(bridge_id, _)| bridge_id.clone())
-            };
+            let bridge_session_id = self
+                .find_by_echokit_session(&echokit_session_id)
+                .map(|(bridge_session_id, _)| bridge_session_id);
 
             if let Some(bridge_session_id) = bridge_session_id {
                 // 🔧 检测 EndResponse 特殊标记
@@ -486,17 +526,11 @@ impl EchoKitSessionAdapter {
         info!("📦 Starting EchoKit raw message receiver");
 
         // 获取原始消息接收通道
-        let mut raw_msg_rx = {
-            let mut receiver_guard = self.raw_message_receiver.write().await;
-            receiver_guard.take()
-        };
-
-        if raw_msg_rx.is_none() {
+        let mut receiver_guard = self.raw_message_receiver.write().await;
+        let Some(raw_msg_rx) = receiver_guard.as_mut() else {
             error!("❌ Raw message receiver channel not available");
             return;
-        }
-
-        let mut raw_msg_rx = raw_msg_rx.unwrap();
+        };
         info!("✅ Raw message receiver channel acquired, waiting for messages...");
 
         // 持续监听原始消息数据
@@ -507,18 +541,19 @@ impl EchoKitSessionAdapter {
                 raw_data.len()
             );
 
-            // 根据 echokit_session_id 找到对应的 device_id
-            let device_id = {
-                let mapping = self.session_mapping.read().await;
-                mapping
-                    .iter()
-                    .find(|(_, (_, ek_id))| ek_id == &echokit_session_id)
-                    .map(|(_, (dev_id, _))| dev_id.clone())
-            };
-
-            if let Some(device_id) = device_id {
-                // 直接发送原始二进制数据到设备
-                match self.connection_manager.send_binary(&device_id, raw_data).await {
+            // 根据 echokit_session_id 找到对应的 bridge_session_id 和 device_id
+            let target = self.find_by_echokit_session(&echokit_session_id);
+
+            if let Some((bridge_session_id, device_id)) = target {
+                trace_capture::record_echokit_frame(&bridge_session_id, &device_id, TraceDirection::EchokitToBridge, &raw_data);
+
+                // 直接发送原始二进制数据到设备（v2 设备加上带会话 ID 的帧头）
+                trace_capture::record_echokit_frame(&bridge_session_id, &device_id, TraceDirection::BridgeToDevice, &raw_data);
+                let forwarded = match self.connection_manager.encode_event(&device_id, &bridge_session_id, raw_data).await {
+                    Ok(frame) => self.connection_manager.send_binary(&device_id, frame).await,
+                    Err(e) => Err(e),
+                };
+                match forwarded {
                     Ok(_) => {
                         debug!(
                             "✅ Successfully forwarded raw message to device {}",
@@ -544,10 +579,10 @@ impl EchoKitSessionAdapter {
     }
 
     /// 关闭 EchoKit 会话
-    pub async fn close_echokit_session(&self, bridge_session_id: &str) -> Result<()> {
+    pub async fn close_echokit_session(&self, bridge_session_id: &SessionId) -> Result<()> {
         // 获取映射信息
-        let mut mapping = self.session_mapping.write().await;
-        let (device_id, echokit_session_id) = mapping
+        let (device_id, echokit_session_id) = self
+            .session_mapping
             .remove(bridge_session_id)
             .ok_or_else(|| anyhow::anyhow!("Session {} not found", bridge_session_id))?;
 
@@ -558,7 +593,7 @@ impl EchoKitSessionAdapter {
 
         // 结束 EchoKit 会话
         self.echokit_client
-            .end_session(echokit_session_id, device_id, "session_closed".to_string())
+            .end_session(echokit_session_id, device_id.into_inner(), "session_closed".to_string())
             .await
             .with_context(|| "Failed to end EchoKit session")?;
 
@@ -566,33 +601,28 @@ impl EchoKitSessionAdapter {
     }
 
     /// 获取 Bridge Session ID（从 EchoKit Session ID）
-    pub async fn get_bridge_session(&self, echokit_session_id: &str) -> Option<String> {
-        let mapping = self.session_mapping.read().await;
-
-        for (bridge_id, (_, ek_id)) in mapping.iter() {
-            if ek_id == echokit_session_id {
-                return Some(bridge_id.clone());
-            }
-        }
+    pub async fn get_bridge_session(&self, echokit_session_id: &str) -> Option<SessionId> {
+        self.find_by_echokit_session(echokit_session_id)
+            .map(|(bridge_session_id, _)| bridge_session_id)
+    }
 
-        None
+    // 根据 EchoKit Session ID 查找 Bridge Session ID 和设备 ID（反向索引，O(1)）
+    fn find_by_echokit_session(&self, echokit_session_id: &str) -> Option<(SessionId, DeviceId)> {
+        self.session_mapping.find_by_echokit_session(echokit_session_id)
     }
 
     /// 获取设备 ID（从 Bridge Session ID）
-    pub async fn get_device_id(&self, bridge_session_id: &str) -> Option<String> {
-        let mapping = self.session_mapping.read().await;
-        mapping.get(bridge_session_id).map(|(device_id, _)| device_id.clone())
+    pub async fn get_device_id(&self, bridge_session_id: &SessionId) -> Option<DeviceId> {
+        self.session_mapping.get(bridge_session_id).map(|(device_id, _)| device_id)
     }
 
     /// 获取活跃会话数量
     pub async fn get_active_sessions_count(&self) -> usize {
-        let mapping = self.session_mapping.read().await;
-        mapping.len()
+        self.session_mapping.session_count()
     }
 
     /// 检查会话是否存在
-    pub async fn has_session(&self, bridge_session_id: &str) -> bool {
-        let mapping = self.session_mapping.read().await;
-        mapping.contains_key(bridge_session_id)
+    pub async fn has_session(&self, bridge_session_id: &SessionId) -> bool {
+        self.session_mapping.get(bridge_session_id).is_some()
     }
 }
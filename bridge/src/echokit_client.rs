@@ -1,137 +1,167 @@
 use anyhow::{Context, Result};
 use echo_shared::{
-    EchoKitClientMessage, EchoKitServerMessage, EchoKitConfig, EchoKitServiceStatus,
-    WebSocketMessage, AudioFormat
+    EchoKitEvent, EchoKitClientMessage, EchoKitServerMessage, EchoKitConfig, EchoKitServiceStatus,
+    WebSocketMessage, AudioFormat, RuntimeConfig, IngressLimitsConfig, MessagePackRejected
 };
 use chrono::Utc;
 use futures::{SinkExt, StreamExt};
 use serde_json;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio::sync::{Mutex, RwLock, mpsc, oneshot, watch};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use crate::audio_buffer::{self, Base64Decoder};
+use crate::channels::{AudioSender, ControlSender};
+use futures::stream::{SplitSink, SplitStream};
+use tokio_tungstenite::{
+    connect_async_tls_with_config, tungstenite::protocol::WebSocketConfig, tungstenite::Message, MaybeTlsStream,
+    WebSocketStream
+};
 use tracing::{info, warn, error, debug};
 use url::Url;
+use crate::hello_store::HelloStore;
+use crate::telemetry;
+
+type EchoKitStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+// OpenAI 格式的 response.audio 事件，借用原始文本中的 Base64 音频，不复制为 String
+#[derive(serde::Deserialize)]
+struct ResponseAudioFrame<'a> {
+    #[serde(rename = "type", borrow)]
+    kind: std::borrow::Cow<'a, str>,
+    #[serde(default, borrow)]
+    session_id: Option<std::borrow::Cow<'a, str>>,
+    #[serde(default, borrow)]
+    audio: Option<std::borrow::Cow<'a, str>>,
+}
+
+// 写任务队列容量，队列满时发送方等待（背压）
+const WRITER_QUEUE_CAPACITY: usize = 256;
+// 心跳间隔
+const HEARTBEAT_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_secs(30);
 
 // EchoKit WebSocket 客户端
 #[derive(Clone)]
 pub struct EchoKitClient {
     websocket_url: String,
-    ws_stream: Arc<RwLock<Option<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>>>,
+    // 发往 EchoKit 的帧由每个连接独立的写任务发送，发送方只需入队，不再争用流上的锁
+    ws_writer: mpsc::Sender<Message>,
+    ws_writer_queue: Arc<Mutex<mpsc::Receiver<Message>>>,
     is_connected: Arc<RwLock<bool>>,
     service_status: Arc<RwLock<Option<EchoKitServiceStatus>>>,
-    message_sender: mpsc::UnboundedSender<EchoKitClientMessage>,
-    message_receiver: Arc<RwLock<Option<mpsc::UnboundedReceiver<EchoKitClientMessage>>>>,
-    active_sessions: Arc<RwLock<HashMap<String, String>>>, // session_id -> device_id
-    audio_callback: Option<mpsc::UnboundedSender<(String, Vec<u8>)>>, // (session_id, audio_data)
-    asr_callback: Option<mpsc::UnboundedSender<(String, String)>>, // (session_id, asr_text)
-    response_callback: Option<mpsc::UnboundedSender<(String, String)>>, // (session_id, ai_response_text) - 也用于发送 EndResponse 标记
-    raw_message_callback: Option<mpsc::UnboundedSender<(String, Vec<u8>)>>, // (session_id, raw_messagepack_data)
+    active_sessions: Arc<DashMap<String, String>>, // session_id -> device_id
+    audio_callback: Option<AudioSender<(String, Vec<u8>)>>, // (session_id, audio_data)
+    asr_callback: Option<ControlSender<(String, String)>>, // (session_id, asr_text)
+    response_callback: Option<ControlSender<(String, String)>>, // (session_id, ai_response_text) - 也用于发送 EndResponse 标记
+    raw_message_callback: Option<ControlSender<(String, Vec<u8>)>>, // (session_id, raw_messagepack_data)
     cached_hello_messages: Arc<RwLock<Vec<Vec<u8>>>>, // 缓存 HelloChunk 消息，用于新会话
     pending_hello_sessions: Arc<RwLock<Vec<String>>>, // 等待发送缓存 Hello 的会话列表
     hello_caching_enabled: Arc<RwLock<bool>>, // 控制是否继续缓存 Hello 消息（HelloEnd 后停止）
+    runtime_config: watch::Receiver<RuntimeConfig>, // 可热加载的配置（Hello 缓存策略）
+    hello_store: Option<Arc<HelloStore>>, // 欢迎语的 Redis 持久化，多个 Bridge 副本共享
+    ingress_limits: IngressLimitsConfig, // EchoKit 消息的帧大小和 MessagePack 嵌套深度限制
 }
 
-impl EchoKitClient {
-    pub fn new(websocket_url: String) -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+/// EchoKitClient 构建器
+///
+/// 回调均为可选，未设置的回调对应的事件不会被转发。
+pub struct EchoKitClientBuilder {
+    websocket_url: String,
+    audio_callback: Option<AudioSender<(String, Vec<u8>)>>,
+    asr_callback: Option<ControlSender<(String, String)>>,
+    response_callback: Option<ControlSender<(String, String)>>,
+    raw_message_callback: Option<ControlSender<(String, Vec<u8>)>>,
+    runtime_config: Option<watch::Receiver<RuntimeConfig>>,
+    hello_store: Option<Arc<HelloStore>>,
+    ingress_limits: IngressLimitsConfig,
+}
 
+impl EchoKitClientBuilder {
+    fn new(websocket_url: String) -> Self {
         Self {
             websocket_url,
-            ws_stream: Arc::new(RwLock::new(None)),
-            is_connected: Arc::new(RwLock::new(false)),
-            service_status: Arc::new(RwLock::new(None)),
-            message_sender: tx,
-            message_receiver: Arc::new(RwLock::new(Some(rx))),
-            active_sessions: Arc::new(RwLock::new(HashMap::new())),
             audio_callback: None,
             asr_callback: None,
             response_callback: None,
             raw_message_callback: None,
-            cached_hello_messages: Arc::new(RwLock::new(Vec::new())),
-            pending_hello_sessions: Arc::new(RwLock::new(Vec::new())),
-            hello_caching_enabled: Arc::new(RwLock::new(true)), // 初始启用缓存
+            runtime_config: None,
+            hello_store: None,
+            ingress_limits: IngressLimitsConfig::default(),
         }
     }
 
-    /// Create a new EchoKitClient with audio callback support
-    pub fn new_with_audio_callback(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-    ) -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+    /// 音频数据回调 (session_id, audio_data)
+    pub fn audio_callback(mut self, callback: AudioSender<(String, Vec<u8>)>) -> Self {
+        self.audio_callback = Some(callback);
+        self
+    }
 
-        Self {
-            websocket_url,
-            ws_stream: Arc::new(RwLock::new(None)),
-            is_connected: Arc::new(RwLock::new(false)),
-            service_status: Arc::new(RwLock::new(None)),
-            message_sender: tx,
-            message_receiver: Arc::new(RwLock::new(Some(rx))),
-            active_sessions: Arc::new(RwLock::new(HashMap::new())),
-            audio_callback: Some(audio_callback),
-            asr_callback: None,
-            response_callback: None,
-            raw_message_callback: None,
-            cached_hello_messages: Arc::new(RwLock::new(Vec::new())),
-            pending_hello_sessions: Arc::new(RwLock::new(Vec::new())),
-            hello_caching_enabled: Arc::new(RwLock::new(true)), // 初始启用缓存
-        }
+    /// 语音识别结果回调 (session_id, asr_text)
+    pub fn asr_callback(mut self, callback: ControlSender<(String, String)>) -> Self {
+        self.asr_callback = Some(callback);
+        self
     }
 
-    /// Create a new EchoKitClient with both audio and ASR callback support
-    pub fn new_with_callbacks(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-        asr_callback: mpsc::UnboundedSender<(String, String)>,
-        response_callback: mpsc::UnboundedSender<(String, String)>,
-    ) -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+    /// AI 回复文本回调 (session_id, ai_response_text)，也用于发送 EndResponse 标记
+    pub fn response_callback(mut self, callback: ControlSender<(String, String)>) -> Self {
+        self.response_callback = Some(callback);
+        self
+    }
 
-        Self {
-            websocket_url,
-            ws_stream: Arc::new(RwLock::new(None)),
-            is_connected: Arc::new(RwLock::new(false)),
-            service_status: Arc::new(RwLock::new(None)),
-            message_sender: tx,
-            message_receiver: Arc::new(RwLock::new(Some(rx))),
-            active_sessions: Arc::new(RwLock::new(HashMap::new())),
-            audio_callback: Some(audio_callback),
-            asr_callback: Some(asr_callback),
-            response_callback: Some(response_callback),
-            raw_message_callback: None,
-            cached_hello_messages: Arc::new(RwLock::new(Vec::new())),
-            pending_hello_sessions: Arc::new(RwLock::new(Vec::new())),
-            hello_caching_enabled: Arc::new(RwLock::new(true)), // 初始启用缓存
-        }
+    /// 原始 MessagePack 数据回调 (session_id, raw_messagepack_data)
+    pub fn raw_message_callback(mut self, callback: ControlSender<(String, Vec<u8>)>) -> Self {
+        self.raw_message_callback = Some(callback);
+        self
     }
 
-    /// Create a new EchoKitClient with audio, ASR, response, and raw message callback support
-    pub fn new_with_all_callbacks(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-        asr_callback: mpsc::UnboundedSender<(String, String)>,
-        response_callback: mpsc::UnboundedSender<(String, String)>,
-        raw_message_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-    ) -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+    /// 使用可热加载的配置（Hello 缓存策略随配置变化生效）
+    pub fn runtime_config(mut self, runtime_config: watch::Receiver<RuntimeConfig>) -> Self {
+        self.runtime_config = Some(runtime_config);
+        self
+    }
 
-        Self {
-            websocket_url,
-            ws_stream: Arc::new(RwLock::new(None)),
+    /// 把欢迎语保存到 Redis，并在本地缓存为空时从 Redis 恢复
+    pub fn hello_store(mut self, hello_store: Arc<HelloStore>) -> Self {
+        self.hello_store = Some(hello_store);
+        self
+    }
+
+    /// 接收 EchoKit 消息时的大小限制，未设置时使用默认限制
+    pub fn ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
+    pub fn build(self) -> Result<EchoKitClient> {
+        let (tx, rx) = mpsc::channel(WRITER_QUEUE_CAPACITY);
+
+        Ok(EchoKitClient {
+            websocket_url: self.websocket_url,
+            ws_writer: tx,
+            ws_writer_queue: Arc::new(Mutex::new(rx)),
             is_connected: Arc::new(RwLock::new(false)),
             service_status: Arc::new(RwLock::new(None)),
-            message_sender: tx,
-            message_receiver: Arc::new(RwLock::new(Some(rx))),
-            active_sessions: Arc::new(RwLock::new(HashMap::new())),
-            audio_callback: Some(audio_callback),
-            asr_callback: Some(asr_callback),
-            response_callback: Some(response_callback),
-            raw_message_callback: Some(raw_message_callback),
+            active_sessions: Arc::new(DashMap::new()),
+            audio_callback: self.audio_callback,
+            asr_callback: self.asr_callback,
+            response_callback: self.response_callback,
+            raw_message_callback: self.raw_message_callback,
             cached_hello_messages: Arc::new(RwLock::new(Vec::new())),
             pending_hello_sessions: Arc::new(RwLock::new(Vec::new())),
             hello_caching_enabled: Arc::new(RwLock::new(true)), // 初始启用缓存
-        }
+            runtime_config: self
+                .runtime_config
+                .unwrap_or_else(|| watch::channel(RuntimeConfig::default()).1),
+            hello_store: self.hello_store,
+            ingress_limits: self.ingress_limits,
+        })
+    }
+}
+
+impl EchoKitClient {
+    pub fn builder(websocket_url: impl Into<String>) -> EchoKitClientBuilder {
+        EchoKitClientBuilder::new(websocket_url.into())
     }
 
     // 连接到 EchoKit Server
@@ -154,21 +184,30 @@ impl EchoKitClient {
 
         info!("Connecting to EchoKit Server at: {}", url);
 
-        match connect_async(url).await {
+        let ws_config = WebSocketConfig {
+            max_message_size: Some(self.ingress_limits.max_websocket_message_bytes),
+            max_frame_size: Some(self.ingress_limits.max_websocket_frame_bytes),
+            ..WebSocketConfig::default()
+        };
+        match connect_async_tls_with_config(url, Some(ws_config), false, None).await {
             Ok((ws_stream, response)) => {
                 info!("Connected to EchoKit Server successfully");
                 debug!("Response status: {}", response.status());
 
-                *self.ws_stream.write().await = Some(ws_stream);
                 *self.is_connected.write().await = true;
 
+                // 读写分离：读任务处理服务端消息，写任务独占发送端
+                let (sink, stream) = ws_stream.split();
+                let (reader_done_tx, reader_done_rx) = oneshot::channel();
+                self.start_writer(sink, reader_done_rx);
+
                 // 发送服务就绪消息
                 if let Err(e) = self.send_service_ready().await {
                     warn!("Failed to send service ready message: {}", e);
                 }
 
                 // 启动消息处理任务
-                self.start_message_handler().await?;
+                self.start_message_handler(stream, reader_done_tx).await?;
 
                 Ok(())
             }
@@ -183,10 +222,11 @@ impl EchoKitClient {
     pub async fn disconnect(&self) -> Result<()> {
         info!("Disconnecting from EchoKit Server");
 
-        *self.is_connected.write().await = false;
+        let was_connected = std::mem::replace(&mut *self.is_connected.write().await, false);
 
-        if let Some(mut ws_stream) = self.ws_stream.write().await.take() {
-            let _ = ws_stream.close(None).await;
+        // 写任务发送 Close 帧后退出；未连接时不入队，避免下一个连接一建立就被关闭
+        if was_connected {
+            let _ = self.ws_writer.send(Message::Close(None)).await;
         }
 
         Ok(())
@@ -210,12 +250,16 @@ impl EchoKitClient {
 
         // 记录会话信息（仅当尚未注册时才插入，避免覆盖 pre_register_session 的注册）
         if let EchoKitClientMessage::StartSession { session_id, device_id, .. } = &message {
-            let mut sessions = self.active_sessions.write().await;
-            if !sessions.contains_key(session_id) {
+            let inserted = match self.active_sessions.entry(session_id.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(device_id.clone());
+                    true
+                }
+                Entry::Occupied(_) => false,
+            };
+            if inserted {
                 info!("🔑 Registering session {} in active_sessions (from send_message)", session_id);
-                sessions.insert(session_id.clone(), device_id.clone());
-                let count = sessions.len();
-                info!("📊 Active sessions count after insert: {}", count);
+                info!("📊 Active sessions count after insert: {}", self.active_sessions.len());
             } else {
                 info!("✅ Session {} already registered (pre-registered)", session_id);
             }
@@ -227,18 +271,11 @@ impl EchoKitClient {
 
         info!("📤 Sending message to EchoKit Server: {}", json_message);
 
-        // 获取WebSocket流并发送消息
-        let mut ws_stream_guard = self.ws_stream.write().await;
-        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-            if let Err(e) = ws_stream.send(Message::Text(json_message)).await {
-                error!("Failed to send message to EchoKit Server: {}", e);
-                *self.is_connected.write().await = false;
-                return Err(anyhow::anyhow!("WebSocket send error: {}", e));
-            }
-            debug!("Message sent to EchoKit Server successfully");
-        } else {
-            return Err(anyhow::anyhow!("WebSocket stream not available"));
+        if let Err(e) = self.send_frame(Message::Text(json_message)).await {
+            error!("Failed to send message to EchoKit Server: {}", e);
+            return Err(e);
         }
+        debug!("Message sent to EchoKit Server successfully");
 
         Ok(())
     }
@@ -256,8 +293,8 @@ impl EchoKitClient {
             "🔑 Pre-registering session {} for device {} in active_sessions",
             session_id, device_id
         );
-        self.active_sessions.write().await.insert(session_id.clone(), device_id);
-        let count = self.active_sessions.read().await.len();
+        self.active_sessions.insert(session_id.clone(), device_id);
+        let count = self.active_sessions.len();
         info!("📊 Active sessions count after pre-register: {}", count);
 
         // 🎁 将会话加入待发送缓存 Hello 的列表
@@ -266,6 +303,37 @@ impl EchoKitClient {
         info!("📝 Session {} added to pending hello list", session_id);
     }
 
+    /// 本地还没有缓存欢迎语时，从 Redis 恢复其他副本保存的欢迎语
+    ///
+    /// 恢复的序列以 HelloEnd 结束，恢复后停止缓存，EchoKit 发送新的 HelloStart 时重新缓存
+    pub async fn restore_hello_cache(&self) {
+        let Some(store) = &self.hello_store else {
+            return;
+        };
+        let max_messages = match &self.runtime_config.borrow().hello_cache {
+            hello_cache if hello_cache.enabled => hello_cache.max_messages,
+            _ => return,
+        };
+        if !self.cached_hello_messages.read().await.is_empty() {
+            return;
+        }
+
+        let mut messages = store.load(&self.websocket_url).await;
+        if messages.is_empty() {
+            return;
+        }
+        messages.truncate(max_messages);
+
+        let mut cached = self.cached_hello_messages.write().await;
+        // 读取期间 EchoKit 已经发送了新的欢迎语
+        if !cached.is_empty() {
+            return;
+        }
+        info!("🎁 Restored {} persisted Hello messages for {}", messages.len(), self.websocket_url);
+        *cached = messages;
+        *self.hello_caching_enabled.write().await = false;
+    }
+
     // 🎁 检查并发送缓存的 Hello 消息给指定会话（如果是首次）
     pub async fn check_and_send_cached_hello(&self, session_id: &str) {
         // 检查是否在待发送列表中
@@ -277,6 +345,11 @@ impl EchoKitClient {
 
             info!("🎁 Session {} ready for cached Hello messages", session_id);
 
+            if !self.runtime_config.borrow().hello_cache.enabled {
+                info!("⏹️ Hello cache is disabled, skipping cached Hello messages for session {}", session_id);
+                return;
+            }
+
             let cached_messages = self.cached_hello_messages.read().await;
             if cached_messages.is_empty() {
                 info!("⚠️ No cached Hello messages to send to session {}", session_id);
@@ -288,7 +361,7 @@ impl EchoKitClient {
             if let Some(callback) = &self.raw_message_callback {
                 for (i, data) in cached_messages.iter().enumerate() {
                     info!("📤 Forwarding cached Hello message {} ({} bytes) to session {}", i + 1, data.len(), session_id);
-                    if let Err(e) = callback.send((session_id.to_string(), data.clone())) {
+                    if let Err(e) = callback.send((session_id.to_string(), data.clone())).await {
                         error!("❌ Failed to send cached Hello message to session {}: {}", session_id, e);
                     } else {
                         info!("✅ Cached Hello message {} forwarded successfully", i + 1);
@@ -329,7 +402,7 @@ impl EchoKitClient {
         reason: String,
     ) -> Result<()> {
         // 从活跃会话中移除
-        self.active_sessions.write().await.remove(&session_id);
+        self.active_sessions.remove(&session_id);
 
         let message = EchoKitClientMessage::EndSession {
             session_id,
@@ -363,16 +436,12 @@ impl EchoKitClient {
 
         // 直接发送二进制音频数据（不使用JSON）
         // EchoKit Server期望16-bit PCM音频作为Binary WebSocket消息
-        let mut ws_stream_guard = self.ws_stream.write().await;
-        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-            if let Err(e) = ws_stream.send(Message::Binary(audio_data.clone())).await {
-                error!("Failed to send audio data to EchoKit Server: {}", e);
-                *self.is_connected.write().await = false;
-                return Err(anyhow::anyhow!("WebSocket send error: {}", e));
-            }
-            info!("✅ Audio data sent successfully to EchoKit Server");
-        } else {
-            return Err(anyhow::anyhow!("WebSocket stream not available"));
+        if let Err(e) = self.send_frame(Message::Binary(audio_data)).await {
+            error!("Failed to send audio data to EchoKit Server: {}", e);
+            return Err(e);
+        }
+        if echo_shared::log_sampled!() {
+            info!("✅ Audio data queued for EchoKit Server");
         }
 
         Ok(())
@@ -380,28 +449,30 @@ impl EchoKitClient {
 
     // 发送StartChat命令（通知EchoKit开始对话）
     pub async fn send_start_chat_command(&self) -> Result<()> {
+        self.send_start_chat_command_with_language(None).await
+    }
+
+    /// 发送 StartChat 命令并指定本轮对话的识别语言，未指定时 EchoKit 使用会话创建时的语言
+    pub async fn send_start_chat_command_with_language(&self, language: Option<&str>) -> Result<()> {
         if !self.is_connected().await {
             return Err(anyhow::anyhow!("Not connected to EchoKit Server"));
         }
 
-        info!("📤 Sending StartChat command to EchoKit Server");
+        info!("📤 Sending StartChat command to EchoKit Server (language: {})", language.unwrap_or("default"));
 
         // 发送StartChat JSON消息
-        let start_chat_message = serde_json::json!({"event": "StartChat"});
+        let mut start_chat_message = serde_json::json!({"event": "StartChat"});
+        if let Some(language) = language {
+            start_chat_message["language"] = serde_json::Value::from(language);
+        }
         let json_message = serde_json::to_string(&start_chat_message)
             .with_context(|| "Failed to serialize StartChat message")?;
 
-        let mut ws_stream_guard = self.ws_stream.write().await;
-        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-            if let Err(e) = ws_stream.send(Message::Text(json_message)).await {
-                error!("Failed to send StartChat command to EchoKit Server: {}", e);
-                *self.is_connected.write().await = false;
-                return Err(anyhow::anyhow!("WebSocket send error: {}", e));
-            }
-            info!("✅ StartChat command sent successfully to EchoKit Server");
-        } else {
-            return Err(anyhow::anyhow!("WebSocket stream not available"));
+        if let Err(e) = self.send_frame(Message::Text(json_message)).await {
+            error!("Failed to send StartChat command to EchoKit Server: {}", e);
+            return Err(e);
         }
+        info!("✅ StartChat command sent successfully to EchoKit Server");
 
         Ok(())
     }
@@ -419,17 +490,11 @@ impl EchoKitClient {
         let json_message = serde_json::to_string(&submit_message)
             .with_context(|| "Failed to serialize Submit message")?;
 
-        let mut ws_stream_guard = self.ws_stream.write().await;
-        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-            if let Err(e) = ws_stream.send(Message::Text(json_message)).await {
-                error!("Failed to send Submit command to EchoKit Server: {}", e);
-                *self.is_connected.write().await = false;
-                return Err(anyhow::anyhow!("WebSocket send error: {}", e));
-            }
-            info!("✅ Submit command sent successfully to EchoKit Server");
-        } else {
-            return Err(anyhow::anyhow!("WebSocket stream not available"));
+        if let Err(e) = self.send_frame(Message::Text(json_message)).await {
+            error!("Failed to send Submit command to EchoKit Server: {}", e);
+            return Err(e);
         }
+        info!("✅ Submit command sent successfully to EchoKit Server");
 
         Ok(())
     }
@@ -461,25 +526,71 @@ impl EchoKitClient {
 
         debug!("Sending OpenAI session update: {}", json_message);
 
-        // 获取WebSocket流并发送消息
-        let mut ws_stream_guard = self.ws_stream.write().await;
-        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-            if let Err(e) = ws_stream.send(Message::Text(json_message)).await {
-                error!("Failed to send session update: {}", e);
-                *self.is_connected.write().await = false;
-                return Err(anyhow::anyhow!("WebSocket send error: {}", e));
-            }
-            info!("OpenAI session update sent successfully");
-        } else {
-            return Err(anyhow::anyhow!("WebSocket stream not available"));
+        if let Err(e) = self.send_frame(Message::Text(json_message)).await {
+            error!("Failed to send session update: {}", e);
+            return Err(e);
         }
+        info!("OpenAI session update sent successfully");
 
         Ok(())
     }
 
+    // 将一帧交给写任务发送，写任务队列满时等待
+    async fn send_frame(&self, message: Message) -> Result<()> {
+        self.ws_writer
+            .send(message)
+            .await
+            .map_err(|_| anyhow::anyhow!("WebSocket writer not available"))
+    }
+
+    // 启动写任务：独占发送端，依次发送队列中的帧，并负责定期心跳
+    // 读任务结束（reader_done 被丢弃）或发送失败时退出
+    fn start_writer(&self, mut sink: SplitSink<EchoKitStream, Message>, mut reader_done: oneshot::Receiver<()>) {
+        let queue = self.ws_writer_queue.clone();
+        let is_connected = self.is_connected.clone();
+
+        tokio::spawn(async move {
+            // 上一个连接的写任务退出后才会拿到队列
+            let mut queue = queue.lock().await;
+            let mut heartbeat = tokio::time::interval_at(
+                tokio::time::Instant::now() + HEARTBEAT_INTERVAL,
+                HEARTBEAT_INTERVAL,
+            );
+
+            loop {
+                let message = tokio::select! {
+                    message = queue.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    _ = heartbeat.tick() => {
+                        debug!("Sending heartbeat to EchoKit Server");
+                        Message::Ping(vec![])
+                    }
+                    _ = &mut reader_done => break,
+                };
+
+                let is_close = matches!(message, Message::Close(_));
+                if let Err(e) = sink.send(message).await {
+                    error!("Failed to send frame to EchoKit Server: {}", e);
+                    *is_connected.write().await = false;
+                    break;
+                }
+                if is_close {
+                    break;
+                }
+            }
+            debug!("EchoKit writer task stopped");
+        });
+    }
+
     // 启动消息处理任务
-    async fn start_message_handler(&self) -> Result<()> {
-        let ws_stream = self.ws_stream.clone();
+    async fn start_message_handler(
+        &self,
+        mut stream: SplitStream<EchoKitStream>,
+        reader_done: oneshot::Sender<()>,
+    ) -> Result<()> {
+        let ws_writer = self.ws_writer.clone();
         let is_connected = self.is_connected.clone();
         let service_status = self.service_status.clone();
         let active_sessions = self.active_sessions.clone();
@@ -490,151 +601,157 @@ impl EchoKitClient {
         let cached_hello_messages = self.cached_hello_messages.clone();
         let pending_hello_sessions = self.pending_hello_sessions.clone();
         let hello_caching_enabled = self.hello_caching_enabled.clone();
-
-        // 为每个连接创建独立的消息通道
-        let (tx, mut rx) = mpsc::unbounded_channel::<EchoKitClientMessage>();
+        let runtime_config = self.runtime_config.clone();
+        let hello_store = self.hello_store.clone();
+        let websocket_url = self.websocket_url.clone();
+        let max_messagepack_depth = self.ingress_limits.max_messagepack_depth;
 
         tokio::spawn(async move {
+            // 读任务结束时通知写任务退出
+            let _reader_done = reader_done;
             loop {
-                tokio::select! {
-                    // 处理来自 EchoKit Server 的消息
-                    message_result = async {
-                        let mut ws_stream_guard = ws_stream.write().await;
-                        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-                            ws_stream.next().await
-                        } else {
-                            None
+                // 处理来自 EchoKit Server 的消息
+                let message_result = stream.next().await;
+                match message_result {
+                    Some(Ok(Message::Text(text))) => {
+                        info!("📩 Received text message from EchoKit Server ({} bytes)", text.len());
+                        if let Err(e) = Self::handle_server_message(
+                            text,
+                            &service_status,
+                            &active_sessions,
+                            &audio_callback,
+                            &asr_callback,
+                            &hello_caching_enabled,
+                        ).await {
+                            error!("Error handling server message: {}", e);
                         }
-                    } => {
-                        match message_result {
-                            Some(Ok(Message::Text(text))) => {
-                                info!("📩 Received text message from EchoKit Server: {}", text);
-                                if let Err(e) = Self::handle_server_message(
-                                    text,
-                                    &service_status,
-                                    &active_sessions,
-                                    &asr_callback,
-                                    &hello_caching_enabled,
-                                ).await {
-                                    error!("Error handling server message: {}", e);
-                                }
+                    }
+                    Some(Ok(Message::Binary(data))) => {
+                        if echo_shared::log_sampled!() {
+                            info!("📦 Received binary data from EchoKit Server: {} bytes", data.len());
+                        }
+
+                        // 嵌套过深的 MessagePack 在解码前丢弃，避免耗尽解码器的栈
+                        if let Err(MessagePackRejected::TooDeep(max_depth)) =
+                            echo_shared::check_messagepack_depth(&data, max_messagepack_depth)
+                        {
+                            warn!("Dropping MessagePack frame from EchoKit Server nested deeper than {}", max_depth);
+                            telemetry::record_ingress_rejected("messagepack");
+                            continue;
+                        }
+
+                        // 首先按 EchoKit 事件解码；未定义的 MessagePack 事件仍原样转发给客户端
+                        let event = EchoKitEvent::from_messagepack(&data).ok();
+                        let is_messagepack = event.is_some()
+                            || rmp_serde::from_slice::<serde::de::IgnoredAny>(&data).is_ok();
+                        if is_messagepack {
+                            match &event {
+                                Some(event) => info!("📦 Parsed EchoKit event: {}", event.name()),
+                                None => debug!("📦 Unrecognized MessagePack event ({} bytes)", data.len()),
                             }
-                            Some(Ok(Message::Binary(data))) => {
-                                info!("📦 Received binary data from EchoKit Server: {} bytes", data.len());
-
-                                // 首先尝试作为MessagePack解析
-                                match rmpv::decode::read_value(&mut &data[..]) {
-                                    Ok(msgpack_value) => {
-                                        info!("📦 Parsed as MessagePack: {:?}", msgpack_value);
-
-                                        // 🎁 检查是否是 Hello 相关消息，如果是则缓存
-                                        let should_cache = Self::should_cache_hello_message(&msgpack_value);
-                                        if should_cache && *hello_caching_enabled.read().await {
-                                            info!("🎁 Caching Hello-related message ({} bytes)", data.len());
-                                            cached_hello_messages.write().await.push(data.clone());
-                                            let cache_size = cached_hello_messages.read().await.len();
-                                            info!("📦 Cached messages count: {}", cache_size);
-                                        } else if should_cache {
-                                            info!("⏹️ Skipping Hello message caching (disabled after HelloEnd)");
-                                        }
-
-                                        // 对于所有MessagePack消息，直接转发原始数据给所有活跃会话
-                                        // 客户端会自己解析MessagePack
-                                        let sessions = active_sessions.read().await;
-                                        info!("📊 Active sessions count: {}", sessions.len());
-                                        for (session_id, _) in sessions.iter() {
-                                            // 直接发送当前消息（Hello 消息已在 register_bridge_session 时发送）
-                                            if let Some(callback) = &audio_callback {
-                                                info!("📤 Forwarding MessagePack data to session: {}", session_id);
-                                                if let Err(e) = callback.send((session_id.clone(), data.clone())) {
-                                                    error!("❌ Failed to forward MessagePack to session {}: {}", session_id, e);
-                                                } else {
-                                                    info!("✅ MessagePack forwarded successfully to session {}", session_id);
-                                                }
-                                            } else {
-                                                warn!("⚠️ No audio callback available for forwarding");
-                                            }
-                                        }
-
-                                        // 额外处理ASR事件和AI回复事件，用于日志记录和其他内部逻辑
-                                        if let Err(e) = Self::handle_messagepack_data(
-                                            msgpack_value,
-                                            &active_sessions,
-                                            &audio_callback,
-                                            &asr_callback,
-                                            &response_callback,
-                                            &cached_hello_messages,
-                                            &hello_caching_enabled,
-                                        ).await {
-                                            warn!("Error handling MessagePack data: {}", e);
-                                        }
-                                    }
-                                    Err(_) => {
-                                        // 不是MessagePack，当作原始音频数据处理
-                                        if let Err(e) = Self::handle_binary_audio_data(
-                                            data,
-                                            &service_status,
-                                            &active_sessions,
-                                            &audio_callback,
-                                        ).await {
-                                            error!("Error handling binary audio data: {}", e);
-                                        }
-                                    }
+
+                            // 🎁 检查是否是 Hello 相关消息，如果是则缓存
+                            let should_cache = event.as_ref().is_some_and(EchoKitEvent::is_hello);
+                            let hello_cache = runtime_config.borrow().hello_cache.clone();
+                            if should_cache && !hello_cache.enabled {
+                                info!("⏹️ Skipping Hello message caching (disabled by configuration)");
+                            } else if should_cache && *hello_caching_enabled.read().await {
+                                let mut cached = cached_hello_messages.write().await;
+                                if cached.len() < hello_cache.max_messages {
+                                    info!("🎁 Caching Hello-related message ({} bytes)", data.len());
+                                    cached.push(data.clone());
+                                    info!("📦 Cached messages count: {}", cached.len());
+                                } else {
+                                    warn!("⚠️ Hello cache is full ({} messages), skipping message", cached.len());
                                 }
+                            } else if should_cache {
+                                info!("⏹️ Skipping Hello message caching (disabled after HelloEnd)");
                             }
-                            Some(Ok(Message::Close(close_frame))) => {
-                                info!("EchoKit Server closed connection: {:?}", close_frame);
-                                *is_connected.write().await = false;
-                                break;
-                            }
-                            Some(Ok(Message::Ping(payload))) => {
-                                debug!("Received ping from EchoKit Server");
-                                // 自动回复pong
-                                let mut ws_stream_guard = ws_stream.write().await;
-                                if let Some(ws_stream) = ws_stream_guard.as_mut() {
-                                    if let Err(e) = ws_stream.send(Message::Pong(payload)).await {
-                                        error!("Failed to send pong: {}", e);
-                                        *is_connected.write().await = false;
-                                        break;
+
+                            // 对于所有MessagePack消息，直接转发原始数据给所有活跃会话
+                            // 客户端会自己解析MessagePack
+                            let sessions = Self::session_ids(&active_sessions);
+                            info!("📊 Active sessions count: {}", sessions.len());
+                            if let Some(callback) = &audio_callback {
+                                // 直接发送当前消息（Hello 消息已在 register_bridge_session 时发送）
+                                for (session_id, frame) in Self::fan_out(&sessions, data) {
+                                    info!("📤 Forwarding MessagePack data to session: {}", session_id);
+                                    if let Err(e) = callback.send((session_id.clone(), frame)) {
+                                        error!("❌ Failed to forward MessagePack to session {}: {}", session_id, e);
+                                    } else {
+                                        info!("✅ MessagePack forwarded successfully to session {}", session_id);
                                     }
                                 }
+                            } else if !sessions.is_empty() {
+                                warn!("⚠️ No audio callback available for forwarding");
                             }
-                            Some(Ok(Message::Pong(_))) => {
-                                debug!("Received pong from EchoKit Server");
-                            }
-                            Some(Ok(Message::Frame(_))) => {
-                                debug!("Received WebSocket frame from EchoKit Server");
-                                // WebSocket frames are handled internally by tungstenite
+
+                            // 额外处理ASR事件和AI回复事件，用于日志记录和其他内部逻辑
+                            let is_hello_end = matches!(event, Some(EchoKitEvent::HelloEnd));
+                            if let Some(event) = event {
+                                if let Err(e) = Self::handle_echokit_event(
+                                    event,
+                                    &active_sessions,
+                                    &audio_callback,
+                                    &asr_callback,
+                                    &response_callback,
+                                    &cached_hello_messages,
+                                    &hello_caching_enabled,
+                                ).await {
+                                    warn!("Error handling EchoKit event: {}", e);
+                                }
                             }
-                            Some(Err(e)) => {
-                                error!("WebSocket error from EchoKit Server: {}", e);
-                                *is_connected.write().await = false;
-                                break;
+
+                            // 欢迎语已完整缓存，保存到 Redis 供其他副本使用
+                            if let Some(store) = hello_store.as_ref().filter(|_| is_hello_end && hello_cache.enabled) {
+                                store.spawn_save(&websocket_url, cached_hello_messages.read().await.clone());
                             }
-                            None => {
-                                warn!("WebSocket stream ended");
-                                *is_connected.write().await = false;
-                                break;
+                        } else {
+                            // 不是MessagePack，当作原始音频数据处理
+                            if let Err(e) = Self::handle_binary_audio_data(
+                                data,
+                                &service_status,
+                                &active_sessions,
+                                &audio_callback,
+                            ).await {
+                                error!("Error handling binary audio data: {}", e);
                             }
                         }
                     }
-
-
-                    // 定期心跳
-                    _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
-                        debug!("Sending heartbeat to EchoKit Server");
-                        let mut ws_stream_guard = ws_stream.write().await;
-                        if let Some(ws_stream) = ws_stream_guard.as_mut() {
-                            if let Err(e) = ws_stream.send(Message::Ping(vec![])).await {
-                                error!("Failed to send ping to EchoKit Server: {}", e);
-                                *is_connected.write().await = false;
-                                break;
-                            }
-                            debug!("Heartbeat sent successfully");
-                        } else {
-                            warn!("WebSocket not available for heartbeat");
+                    Some(Ok(Message::Close(close_frame))) => {
+                        info!("EchoKit Server closed connection: {:?}", close_frame);
+                        *is_connected.write().await = false;
+                        break;
+                    }
+                    Some(Ok(Message::Ping(payload))) => {
+                        debug!("Received ping from EchoKit Server");
+                        // 自动回复pong
+                        if ws_writer.send(Message::Pong(payload)).await.is_err() {
+                            error!("Failed to queue pong: writer not available");
+                            *is_connected.write().await = false;
+                            break;
                         }
                     }
+                    Some(Ok(Message::Pong(_))) => {
+                        debug!("Received pong from EchoKit Server");
+                    }
+                    Some(Ok(Message::Frame(_))) => {
+                        debug!("Received WebSocket frame from EchoKit Server");
+                        // WebSocket frames are handled internally by tungstenite
+                    }
+                    Some(Err(e)) => {
+                        if echo_shared::is_websocket_capacity_error(&e) {
+                            telemetry::record_ingress_rejected("websocket");
+                        }
+                        error!("WebSocket error from EchoKit Server: {}", e);
+                        *is_connected.write().await = false;
+                        break;
+                    }
+                    None => {
+                        warn!("WebSocket stream ended");
+                        *is_connected.write().await = false;
+                        break;
+                    }
                 }
             }
         });
@@ -646,10 +763,18 @@ impl EchoKitClient {
     async fn handle_server_message(
         text: String,
         service_status: &Arc<RwLock<Option<EchoKitServiceStatus>>>,
-        active_sessions: &Arc<RwLock<HashMap<String, String>>>,
-        asr_callback: &Option<mpsc::UnboundedSender<(String, String)>>,
+        active_sessions: &Arc<DashMap<String, String>>,
+        audio_callback: &Option<AudioSender<(String, Vec<u8>)>>,
+        asr_callback: &Option<ControlSender<(String, String)>>,
         hello_caching_enabled: &Arc<RwLock<bool>>,
     ) -> Result<()> {
+        // response.audio 携带整段 Base64 音频，直接从原始文本增量解码，避免复制音频字符串
+        if let Ok(frame) = serde_json::from_str::<ResponseAudioFrame>(&text) {
+            if frame.kind == "response.audio" {
+                return Self::handle_response_audio(frame, active_sessions, audio_callback);
+            }
+        }
+
         let server_message: EchoKitServerMessage = serde_json::from_str(&text)
             .with_context(|| format!("Failed to parse server message: {}", text))?;
 
@@ -659,13 +784,13 @@ impl EchoKitClient {
                 info!("OpenAI session created: {} (event_id: {})", session.id, event_id);
                 info!("Session details: model={}, modalities={:?}", session.model, session.modalities);
                 // 存储session ID 映射到设备ID（这里暂时用session.id作为key）
-                active_sessions.write().await.insert(session.id.clone(), "bridge_device".to_string());
+                active_sessions.insert(session.id.clone(), "bridge_device".to_string());
             }
             EchoKitServerMessage::ConversationCreated { event_id, conversation } => {
                 info!("OpenAI conversation created: {} (event_id: {})", conversation.id, event_id);
             }
             EchoKitServerMessage::ResponseText { event_id, session_id, text } => {
-                info!("OpenAI text response for session {}: {} (event_id: {})", session_id, text, event_id);
+                info!("OpenAI text response for session {}: {} (event_id: {})", session_id, echo_shared::redact_for_log(&text), event_id);
                 // 这里可以转发文本响应到设备或其他服务
             }
             EchoKitServerMessage::ResponseAudio { event_id, session_id, audio } => {
@@ -680,11 +805,11 @@ impl EchoKitClient {
             // 原有格式消息处理（向后兼容）
             EchoKitServerMessage::SessionStarted { session_id, device_id, timestamp } => {
                 info!("Session started: {} for device: {} at {}", session_id, device_id, timestamp);
-                active_sessions.write().await.insert(session_id.clone(), device_id);
+                active_sessions.insert(session_id.clone(), device_id);
             }
             EchoKitServerMessage::SessionEnded { session_id, device_id, reason, timestamp } => {
                 info!("Session ended: {} for device: {} (reason: {}) at {}", session_id, device_id, reason, timestamp);
-                active_sessions.write().await.remove(&session_id);
+                active_sessions.remove(&session_id);
             }
             EchoKitServerMessage::Transcription {
                 session_id,
@@ -695,12 +820,12 @@ impl EchoKitClient {
                 timestamp: _
             } => {
                 info!("📝 Received Transcription for session {}: {} (confidence: {:.2}, final: {})",
-                      session_id, text, confidence, is_final);
+                      session_id, echo_shared::redact_for_log(&text), confidence, is_final);
 
                 // Forward ASR results via callback if available
                 if let Some(callback) = asr_callback {
                     info!("Attempting to forward ASR via callback...");
-                    if let Err(e) = callback.send((session_id.clone(), text.clone())) {
+                    if let Err(e) = callback.send((session_id.clone(), text.clone())).await {
                         error!("❌ Failed to send ASR result via callback: {}", e);
                     } else {
                         info!("✅ Successfully forwarded ASR result for session {} to callback", session_id);
@@ -717,7 +842,7 @@ impl EchoKitClient {
                 is_complete,
                 timestamp: _
             } => {
-                info!("Response for session {}: {} (complete: {})", session_id, text, is_complete);
+                info!("Response for session {}: {} (complete: {})", session_id, echo_shared::redact_for_log(&text), is_complete);
                 if let Some(audio) = audio_data {
                     debug!("Received audio data: {} bytes", audio.len());
                 }
@@ -741,12 +866,15 @@ impl EchoKitClient {
 
     // 获取活跃会话数量
     pub async fn get_active_sessions_count(&self) -> usize {
-        self.active_sessions.read().await.len()
+        self.active_sessions.len()
     }
 
     // 获取所有活跃会话
     pub async fn get_active_sessions(&self) -> HashMap<String, String> {
-        self.active_sessions.read().await.clone()
+        self.active_sessions
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
     }
 }
 
@@ -758,56 +886,9 @@ pub struct EchoKitConnectionManager {
 }
 
 impl EchoKitConnectionManager {
-    pub fn new(websocket_url: String) -> Self {
-        Self {
-            client: Arc::new(EchoKitClient::new(websocket_url)),
-            reconnect_interval: tokio::time::Duration::from_secs(5),
-            max_reconnect_attempts: 10,
-        }
-    }
-
-    /// Create a new connection manager with audio callback support
-    pub fn new_with_audio_callback(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-    ) -> Self {
-        Self {
-            client: Arc::new(EchoKitClient::new_with_audio_callback(websocket_url, audio_callback)),
-            reconnect_interval: tokio::time::Duration::from_secs(5),
-            max_reconnect_attempts: 10,
-        }
-    }
-
-    /// Create a new connection manager with audio, ASR, and response callback support
-    pub fn new_with_callbacks(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-        asr_callback: mpsc::UnboundedSender<(String, String)>,
-        response_callback: mpsc::UnboundedSender<(String, String)>,
-    ) -> Self {
-        Self {
-            client: Arc::new(EchoKitClient::new_with_callbacks(websocket_url, audio_callback, asr_callback, response_callback)),
-            reconnect_interval: tokio::time::Duration::from_secs(5),
-            max_reconnect_attempts: 10,
-        }
-    }
-
-    /// Create a new connection manager with audio, ASR, response, and raw message callback support
-    pub fn new_with_all_callbacks(
-        websocket_url: String,
-        audio_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-        asr_callback: mpsc::UnboundedSender<(String, String)>,
-        response_callback: mpsc::UnboundedSender<(String, String)>,
-        raw_message_callback: mpsc::UnboundedSender<(String, Vec<u8>)>,
-    ) -> Self {
+    pub fn new(client: EchoKitClient) -> Self {
         Self {
-            client: Arc::new(EchoKitClient::new_with_all_callbacks(
-                websocket_url,
-                audio_callback,
-                asr_callback,
-                response_callback,
-                raw_message_callback
-            )),
+            client: Arc::new(client),
             reconnect_interval: tokio::time::Duration::from_secs(5),
             max_reconnect_attempts: 10,
         }
@@ -843,6 +924,7 @@ impl EchoKitConnectionManager {
                 // 如果连接断开，尝试重连
                 if reconnect_attempts < max_reconnect_attempts {
                     reconnect_attempts += 1;
+                    metrics::counter!(telemetry::ECHOKIT_RECONNECTS).increment(1);
                     info!("Attempting to reconnect to EchoKit (attempt {}/{})",
                           reconnect_attempts, max_reconnect_attempts);
                     tokio::time::sleep(reconnect_interval).await;
@@ -863,255 +945,213 @@ impl EchoKitConnectionManager {
 }
 
 impl EchoKitClient {
-    // 判断是否应该缓存 Hello 相关消息
-    fn should_cache_hello_message(value: &rmpv::Value) -> bool {
-        use rmpv::Value;
-
-        match value {
-            Value::String(s) => {
-                let event_str = s.as_str().unwrap_or("");
-                matches!(event_str, "HelloStart" | "HelloEnd")
+    // 活跃会话 ID 快照，转发（可能等待）期间不持有 DashMap 分片锁
+    fn session_ids(active_sessions: &DashMap<String, String>) -> Vec<String> {
+        active_sessions.iter().map(|entry| entry.key().clone()).collect()
+    }
+
+    // 为每个会话生成一份帧数据，最后一个会话直接使用原缓冲区，单会话时不产生复制
+    fn fan_out(sessions: &[String], data: Vec<u8>) -> impl Iterator<Item = (&String, Vec<u8>)> {
+        let last = sessions.len().saturating_sub(1);
+        let mut data = Some(data);
+        sessions.iter().enumerate().map(move |(index, session_id)| {
+            let frame = if index == last { data.take() } else { data.clone() };
+            (session_id, frame.unwrap_or_default())
+        })
+    }
+
+    // 解码 OpenAI 格式的 Base64 音频并以 AudioChunk 事件转发给对应会话
+    fn handle_response_audio(
+        frame: ResponseAudioFrame<'_>,
+        active_sessions: &DashMap<String, String>,
+        audio_callback: &Option<AudioSender<(String, Vec<u8>)>>,
+    ) -> Result<()> {
+        let (Some(session_id), Some(audio)) = (frame.session_id, frame.audio) else {
+            warn!("response.audio event without session_id or audio, ignoring");
+            return Ok(());
+        };
+
+        // 解码到复用的缓冲区，只有最终转发的帧需要新分配
+        let mut pcm = audio_buffer::audio_pool().take();
+        let mut decoder = Base64Decoder::new();
+        decoder.push(audio.as_bytes(), &mut pcm)
+            .and_then(|_| decoder.finish(&mut pcm))
+            .map_err(|e| anyhow::anyhow!("Invalid base64 audio for session {}: {}", session_id, e))?;
+
+        if echo_shared::log_sampled!() {
+            info!("OpenAI audio response for session {}: {} bytes decoded", session_id, pcm.len());
+        }
+
+        if !active_sessions.contains_key(session_id.as_ref()) {
+            debug!("No active session {} for response.audio, dropping {} bytes", session_id, pcm.len());
+            return Ok(());
+        }
+        if let Some(callback) = audio_callback {
+            let event_bytes = EchoKitEvent::encode_audio_chunk(&pcm)?;
+            if let Err(e) = callback.send((session_id.into_owned(), event_bytes)) {
+                error!("❌ Failed to forward response.audio: {}", e);
             }
-            Value::Map(entries) => {
-                for (key, _) in entries {
-                    if let Value::String(key_str) = key {
-                        let event_type = key_str.as_str().unwrap_or("");
-                        if event_type == "HelloChunk" {
-                            return true;
-                        }
-                    }
+        }
+
+        Ok(())
+    }
+
+    // 将已编码的事件转发到所有活跃会话
+    async fn forward_event_to_sessions(
+        event_name: &str,
+        event_bytes: &[u8],
+        active_sessions: &Arc<DashMap<String, String>>,
+        audio_callback: &Option<AudioSender<(String, Vec<u8>)>>,
+    ) {
+        let sessions = Self::session_ids(active_sessions);
+        for session_id in &sessions {
+            if let Some(callback) = audio_callback {
+                info!("📤 Forwarding {} event to session: {}", event_name, session_id);
+                if let Err(e) = callback.send((session_id.clone(), event_bytes.to_vec())) {
+                    error!("❌ Failed to send {} event to session {}: {}", event_name, session_id, e);
+                } else {
+                    info!("✅ Successfully forwarded {} event to session {}", event_name, session_id);
                 }
-                false
             }
-            _ => false,
         }
     }
 
-    // 处理MessagePack格式的数据（可能包含ASR等事件）
-    async fn handle_messagepack_data(
-        value: rmpv::Value,
-        active_sessions: &Arc<RwLock<HashMap<String, String>>>,
-        audio_callback: &Option<mpsc::UnboundedSender<(String, Vec<u8>)>>,
-        asr_callback: &Option<mpsc::UnboundedSender<(String, String)>>,
-        response_callback: &Option<mpsc::UnboundedSender<(String, String)>>,
+    // 处理 EchoKit 事件（ASR、AI 回复、问候语缓存等）
+    async fn handle_echokit_event(
+        event: EchoKitEvent,
+        active_sessions: &Arc<DashMap<String, String>>,
+        audio_callback: &Option<AudioSender<(String, Vec<u8>)>>,
+        asr_callback: &Option<ControlSender<(String, String)>>,
+        response_callback: &Option<ControlSender<(String, String)>>,
         cached_hello_messages: &Arc<RwLock<Vec<Vec<u8>>>>,
         hello_caching_enabled: &Arc<RwLock<bool>>,
     ) -> Result<()> {
-        use rmpv::Value;
-
-        // MessagePack可能是字符串事件或对象事件
-        match value {
-            Value::String(s) => {
-                let event_str = s.into_str().unwrap_or_default();
-                info!("📦 MessagePack string event: {}", event_str);
-
-                // 处理字符串事件如 "HelloStart", "HelloEnd", "EndAudio" 等
-                // 这些事件需要通过特定的格式发送给客户端
-                match event_str.as_str() {
-                    "HelloStart" => {
-                        info!("🎯 Received HelloStart - clearing cached Hello messages");
-                        // 清空之前的缓存，准备缓存新的 Hello 序列
-                        cached_hello_messages.write().await.clear();
-
-                        // 🔓 启用缓存（新的问候序列开始）
-                        *hello_caching_enabled.write().await = true;
-
-                        info!("🎯 Forwarding event to clients: {}", event_str);
-                        // ✅ 使用 MessagePack 编码（保持与 EchoKit 原始格式一致）
-                        // 直接编码字符串 "HelloStart"，与 EchoKit Server 发送的格式相同
-                        let event_bytes = rmp_serde::to_vec(&event_str)
-                            .expect("Failed to serialize HelloStart to MessagePack");
-
-                        // 缓存 HelloStart
-                        cached_hello_messages.write().await.push(event_bytes.clone());
-
-                        // 转发到所有活跃会话
-                        let sessions = active_sessions.read().await;
-                        for (session_id, _) in sessions.iter() {
-                            if let Some(callback) = audio_callback {
-                                info!("📤 Forwarding {} event to session: {}", event_str, session_id);
-                                if let Err(e) = callback.send((session_id.clone(), event_bytes.clone())) {
-                                    error!("❌ Failed to send {} event to session {}: {}", event_str, session_id, e);
-                                } else {
-                                    info!("✅ Successfully forwarded {} event to session {}", event_str, session_id);
-                                }
-                            }
+        let event_name = event.name();
+
+        match event {
+            EchoKitEvent::HelloStart => {
+                info!("🎯 Received HelloStart - clearing cached Hello messages");
+                // 清空之前的缓存，准备缓存新的 Hello 序列
+                cached_hello_messages.write().await.clear();
+
+                // 🔓 启用缓存（新的问候序列开始）
+                *hello_caching_enabled.write().await = true;
+
+                info!("🎯 Forwarding event to clients: {}", event_name);
+                // ✅ 使用 MessagePack 编码（保持与 EchoKit 原始格式一致）
+                let event_bytes = EchoKitEvent::HelloStart.to_messagepack()?;
+
+                // 缓存 HelloStart
+                cached_hello_messages.write().await.push(event_bytes.clone());
+
+                Self::forward_event_to_sessions(event_name, &event_bytes, active_sessions, audio_callback).await;
+            }
+            EchoKitEvent::HelloEnd => {
+                info!("🎯 Received HelloEnd - finalizing cached Hello messages");
+
+                // ✅ HelloEnd 已经在接收循环的通用缓存逻辑中被缓存，这里不需要重复缓存
+                // 只需要记录日志和转发给活跃会话即可
+                let event_bytes = EchoKitEvent::HelloEnd.to_messagepack()?;
+
+                let cached_messages = cached_hello_messages.read().await;
+                let cache_size = cached_messages.len();
+                let total_bytes: usize = cached_messages.iter().map(|msg| msg.len()).sum();
+                let estimated_seconds = total_bytes as f64 / (16000.0 * 2.0); // 16kHz, 16-bit
+                info!("🎁 Greeting cached: {} chunks (including HelloEnd), ~{:.1} seconds audio, {} bytes total, ready for instant delivery",
+                    cache_size, estimated_seconds, total_bytes);
+                drop(cached_messages);
+
+                // 🔒 禁用缓存（问候序列已结束，不再缓存后续的 Hello 消息）
+                *hello_caching_enabled.write().await = false;
+                info!("⏹️ Hello message caching disabled after HelloEnd");
+
+                info!("🎯 Forwarding event to clients: {}", event_name);
+                Self::forward_event_to_sessions(event_name, &event_bytes, active_sessions, audio_callback).await;
+            }
+            EchoKitEvent::EndAudio | EchoKitEvent::EndResponse => {
+                info!("🎯 Forwarding event to clients: {}", event_name);
+
+                let is_end_response = matches!(event, EchoKitEvent::EndResponse);
+                let event_bytes = event.to_messagepack()?;
+                Self::forward_event_to_sessions(event_name, &event_bytes, active_sessions, audio_callback).await;
+
+                // 🔧 EndResponse 特殊处理：通知合并当前轮次的 AI 回复
+                if let (true, Some(callback)) = (is_end_response, response_callback) {
+                    let sessions = Self::session_ids(active_sessions);
+                    for session_id in &sessions {
+                        // 发送特殊标记，表示一轮对话结束，需要合并 AI 回复
+                        info!("🔔 Sending EndResponse signal for session: {}", session_id);
+                        if let Err(e) = callback.send((session_id.clone(), "__END_RESPONSE__".to_string())).await {
+                            error!("❌ Failed to send EndResponse signal for session {}: {}", session_id, e);
                         }
                     }
-                    "HelloEnd" => {
-                        info!("🎯 Received HelloEnd - finalizing cached Hello messages");
-
-                        // ✅ HelloEnd 已经在前面的通用缓存逻辑中被缓存了（line 507），这里不需要重复缓存
-                        // 只需要记录日志和转发给活跃会话即可
-
-                        // ✅ 使用 MessagePack 编码（保持与 EchoKit 原始格式一致）
-                        let event_bytes = rmp_serde::to_vec(&event_str)
-                            .expect("Failed to serialize HelloEnd to MessagePack");
-
-                        let cached_messages = cached_hello_messages.read().await;
-                        let cache_size = cached_messages.len();
-                        let total_bytes: usize = cached_messages.iter().map(|msg| msg.len()).sum();
-                        let estimated_seconds = total_bytes as f64 / (16000.0 * 2.0); // 16kHz, 16-bit
-                        info!("🎁 Greeting cached: {} chunks (including HelloEnd), ~{:.1} seconds audio, {} bytes total, ready for instant delivery",
-                            cache_size, estimated_seconds, total_bytes);
-
-                        // 🔒 禁用缓存（问候序列已结束，不再缓存后续的 Hello 消息）
-                        *hello_caching_enabled.write().await = false;
-                        info!("⏹️ Hello message caching disabled after HelloEnd");
-
-                        info!("🎯 Forwarding event to clients: {}", event_str);
-
-                        // 转发到所有活跃会话
-                        let sessions = active_sessions.read().await;
-                        for (session_id, _) in sessions.iter() {
-                            if let Some(callback) = audio_callback {
-                                info!("📤 Forwarding {} event to session: {}", event_str, session_id);
-                                if let Err(e) = callback.send((session_id.clone(), event_bytes.clone())) {
-                                    error!("❌ Failed to send {} event to session {}: {}", event_str, session_id, e);
-                                } else {
-                                    info!("✅ Successfully forwarded {} event to session {}", event_str, session_id);
-                                }
-                            }
+                }
+            }
+            EchoKitEvent::ASR { text } => {
+                // ASR 数据已经通过 audio_callback 作为原始 MessagePack 转发给客户端（用于 WebUI 显示）
+                // 这里同时通过 asr_callback 发送给 websocket_adapter（用于保存到数据库）
+                info!("📝 Received ASR from EchoKit: {}", echo_shared::redact_for_log(&text));
+
+                if let Some(callback) = asr_callback {
+                    // 发送到所有活跃会话（通常一个 EchoKit 连接对应一个会话）
+                    let sessions = Self::session_ids(active_sessions);
+                    for session_id in &sessions {
+                        if let Err(e) = callback.send((session_id.clone(), text.clone())).await {
+                            error!("❌ Failed to send ASR to callback for session {}: {}", session_id, e);
+                        } else {
+                            debug!("✅ ASR sent to callback for session {}", session_id);
                         }
                     }
-                    "EndAudio" | "EndResponse" => {
-                        info!("🎯 Forwarding event to clients: {}", event_str);
-
-                        // ✅ 使用 MessagePack 编码（保持与 EchoKit 原始格式一致）
-                        let event_bytes = rmp_serde::to_vec(&event_str)
-                            .expect(&format!("Failed to serialize {} to MessagePack", event_str));
-
-                        // 转发到所有活跃会话
-                        let sessions = active_sessions.read().await;
-                        for (session_id, _) in sessions.iter() {
-                            if let Some(callback) = audio_callback {
-                                info!("📤 Forwarding {} event to session: {}", event_str, session_id);
-                                if let Err(e) = callback.send((session_id.clone(), event_bytes.clone())) {
-                                    error!("❌ Failed to send {} event to session {}: {}", event_str, session_id, e);
-                                } else {
-                                    info!("✅ Successfully forwarded {} event to session {}", event_str, session_id);
-                                }
-                            }
+                }
+            }
+            EchoKitEvent::HelloChunk { data } | EchoKitEvent::AudioChunk { data } => {
+                if echo_shared::log_sampled!() {
+                    info!("👋 Received {} from EchoKit: {} bytes", event_name, data.len());
+                }
 
-                            // 🔧 EndResponse 特殊处理：通知合并当前轮次的 AI 回复
-                            if event_str == "EndResponse" {
-                                if let Some(callback) = response_callback {
-                                    // 发送特殊标记，表示一轮对话结束，需要合并 AI 回复
-                                    info!("🔔 Sending EndResponse signal for session: {}", session_id);
-                                    if let Err(e) = callback.send((session_id.clone(), "__END_RESPONSE__".to_string())) {
-                                        error!("❌ Failed to send EndResponse signal for session {}: {}", session_id, e);
-                                    }
-                                }
-                            }
+                // 转发音频数据到所有活跃会话
+                let sessions = Self::session_ids(active_sessions);
+                if let Some(callback) = audio_callback {
+                    for (session_id, chunk) in Self::fan_out(&sessions, data) {
+                        info!("🔊 Forwarding {} to session: {}", event_name, session_id);
+                        if let Err(e) = callback.send((session_id.clone(), chunk)) {
+                            error!("❌ Failed to send {} to session {}: {}", event_name, session_id, e);
+                        } else {
+                            debug!("✅ Successfully forwarded {} to session {}", event_name, session_id);
                         }
                     }
-                    _ => {
-                        debug!("📦 Unhandled string event: {}", event_str);
-                    }
                 }
             }
-            Value::Map(entries) => {
-                // 对象事件，如 {ASR: ["转录文本"]}, {HelloChunk: [音频数据]}
-                for (key, val) in entries {
-                    if let Value::String(key_str) = key {
-                        let event_type = key_str.into_str().unwrap_or_default();
-                        info!("📦 MessagePack object event: {}", event_type);
-
-                        match event_type.as_str() {
-                            "ASR" => {
-                                // ASR事件：提取文本并通过 asr_callback 发送
-                                // 注意：ASR 数据已经通过 audio_callback 作为原始 MessagePack 转发给客户端（用于 WebUI 显示）
-                                // 这里同时通过 asr_callback 发送给 websocket_adapter（用于保存到数据库）
-                                if let Value::Array(arr) = val {
-                                    if let Some(Value::String(text_val)) = arr.first() {
-                                        let asr_text = text_val.as_str().unwrap_or("");
-                                        info!("📝 Received ASR from EchoKit: {}", asr_text);
-
-                                        // 🔧 方案B：发送 ASR 文本到 asr_callback 通道，供 SessionManager 保存
-                                        if let Some(callback) = asr_callback {
-                                            // 发送到所有活跃会话（通常一个 EchoKit 连接对应一个会话）
-                                            let sessions = active_sessions.read().await;
-                                            for (session_id, _) in sessions.iter() {
-                                                if let Err(e) = callback.send((session_id.clone(), asr_text.to_string())) {
-                                                    error!("❌ Failed to send ASR to callback for session {}: {}", session_id, e);
-                                                } else {
-                                                    debug!("✅ ASR sent to callback for session {}", session_id);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            "HelloChunk" | "AudioChunk" => {
-                                // 音频块事件：提取音频数据
-                                if let Value::Array(arr) = val {
-                                    if let Some(Value::Binary(audio_data)) = arr.first() {
-                                                                                info!("👋 Received {} from EchoKit: {} bytes", event_type, audio_data.len());
-
-                                        // 注意：音频数据已经通过 audio_callback 作为原始 MessagePack 转发
-                                        // 这里不再重复转发，仅保留日志记录
-
-                                        // 转发音频数据到所有活跃会话
-                                        let sessions = active_sessions.read().await;
-                                        for (session_id, _) in sessions.iter() {
-                                            if let Some(callback) = audio_callback {
-                                                info!("� Forwarding {} to session: {}", event_type, session_id);
-                                                if let Err(e) = callback.send((session_id.clone(), audio_data.clone())) {
-                                                    error!("❌ Failed to send {} to session {}: {}", event_type, session_id, e);
-                                                } else {
-                                                    debug!("✅ Successfully forwarded {} to session {}", event_type, session_id);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            "StartAudio" => {
-                                // StartAudio事件：提取AI回复文本并通过 response_callback 发送
-                                // 注意：StartAudio 数据已经通过 audio_callback 作为原始 MessagePack 转发给客户端（用于 WebUI 显示）
-                                // 这里同时通过 response_callback 发送给 websocket_adapter（用于保存到数据库）
-                                if let Value::Array(arr) = val {
-                                    if let Some(Value::String(text_val)) = arr.first() {
-                                        let response_text = text_val.as_str().unwrap_or("");
-                                        info!("🤖 Received AI response from EchoKit: {}", response_text);
-
-                                        // 🔧 方案B：发送 AI 回复文本到 response_callback 通道，供 SessionManager 保存
-                                        if let Some(callback) = response_callback {
-                                            // 发送到所有活跃会话（通常一个 EchoKit 连接对应一个会话）
-                                            let sessions = active_sessions.read().await;
-                                            for (session_id, _) in sessions.iter() {
-                                                if let Err(e) = callback.send((session_id.clone(), response_text.to_string())) {
-                                                    error!("❌ Failed to send AI response to callback for session {}: {}", session_id, e);
-                                                } else {
-                                                    debug!("✅ AI response sent to callback for session {}", session_id);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+            EchoKitEvent::StartAudio { text } => {
+                // StartAudio 数据已经通过 audio_callback 作为原始 MessagePack 转发给客户端（用于 WebUI 显示）
+                // 这里同时通过 response_callback 发送给 websocket_adapter（用于保存到数据库）
+                info!("🤖 Received AI response from EchoKit: {}", echo_shared::redact_for_log(&text));
+
+                let sessions = Self::session_ids(active_sessions);
+                if let Some(callback) = response_callback {
+                    // 发送到所有活跃会话（通常一个 EchoKit 连接对应一个会话）
+                    for session_id in &sessions {
+                        if let Err(e) = callback.send((session_id.clone(), text.clone())).await {
+                            error!("❌ Failed to send AI response to callback for session {}: {}", session_id, e);
+                        } else {
+                            debug!("✅ AI response sent to callback for session {}", session_id);
+                        }
+                    }
+                }
 
-                                // 同时转发 StartAudio 事件（用于客户端显示）
-                                let event_json = serde_json::json!({
-                                    "event": "StartAudio"
-                                }).to_string();
-                                let event_bytes = event_json.as_bytes().to_vec();
+                // 同时转发 StartAudio 事件（用于客户端显示）
+                let event_bytes = serde_json::json!({
+                    "event": "StartAudio"
+                }).to_string().into_bytes();
 
-                                let sessions = active_sessions.read().await;
-                                for (session_id, _) in sessions.iter() {
-                                    if let Some(callback) = audio_callback {
-                                        let _ = callback.send((session_id.clone(), event_bytes.clone()));
-                                    }
-                                }
-                            }
-                            _ => {
-                                debug!("📦 Unhandled MessagePack event: {}", event_type);
-                            }
-                        }
+                for session_id in &sessions {
+                    if let Some(callback) = audio_callback {
+                        let _ = callback.send((session_id.clone(), event_bytes.clone()));
                     }
                 }
             }
             _ => {
-                debug!("📦 Unexpected MessagePack value type: {:?}", value);
+                debug!("📦 Unhandled EchoKit event: {}", event_name);
             }
         }
 
@@ -1122,8 +1162,8 @@ impl EchoKitClient {
     async fn handle_binary_audio_data(
         data: Vec<u8>,
         _service_status: &Arc<RwLock<Option<EchoKitServiceStatus>>>,
-        active_sessions: &Arc<RwLock<HashMap<String, String>>>,
-        audio_callback: &Option<mpsc::UnboundedSender<(String, Vec<u8>)>>,
+        active_sessions: &Arc<DashMap<String, String>>,
+        audio_callback: &Option<AudioSender<(String, Vec<u8>)>>,
     ) -> Result<()> {
         debug!("Processing binary audio data: {} bytes", data.len());
 
@@ -1148,22 +1188,25 @@ impl EchoKitClient {
         debug!("Detected audio format: {}", audio_format);
 
         // 如果有音频回调，将音频数据路由到相应的会话
+        let size = data.len();
         if let Some(callback) = audio_callback {
             // 获取所有活跃会话（这里需要从数据中确定session_id）
             // 由于当前没有在二进制数据中包含session_id，我们需要从活跃会话中找到
             // 这是一个临时方案，实际应该在数据中包含session_id
-            let sessions = active_sessions.read().await;
+            let sessions = Self::session_ids(active_sessions);
 
             // 暂时发送给所有活跃会话（需要优化）
-            for (session_id, _device_id) in sessions.iter() {
-                if let Err(e) = callback.send((session_id.clone(), data.clone())) {
+            for (session_id, frame) in Self::fan_out(&sessions, data) {
+                if let Err(e) = callback.send((session_id.clone(), frame)) {
                     error!("Failed to send audio to session {}: {}", session_id, e);
                 }
             }
         }
 
-        info!("Audio data processed successfully (format: {}, size: {} bytes)",
-              audio_format, data.len());
+        if echo_shared::log_sampled!() {
+            info!("Audio data processed successfully (format: {}, size: {} bytes)",
+                  audio_format, size);
+        }
 
         Ok(())
     }
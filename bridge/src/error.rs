@@ -0,0 +1,2 @@
+// HTTP 错误响应：ApiError 在 echo-shared 中实现（axum08 feature）
+pub use echo_shared::ApiError;
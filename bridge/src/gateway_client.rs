@@ -0,0 +1,165 @@
+//! API Gateway HTTP 客户端
+//!
+//! 所有调用共享一个 reqwest 客户端（keep-alive 连接池，可选 HTTP/2 复用），每个接口使用独立的超时，
+//! 幂等请求在连接错误、超时和 502/503/504 时按指数退避重试。
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use echo_shared::{ApiResponse, Device, DeviceId, GatewayClientConfig, UserRole};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::telemetry;
+
+// 健康检查超时（就绪检查外层另有超时）
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// 接口名，用于超时选择、日志和指标标签
+#[derive(Debug, Clone, Copy)]
+enum Endpoint {
+    DeviceLookup,
+    AuthValidation,
+    Health,
+}
+
+impl Endpoint {
+    fn name(self) -> &'static str {
+        match self {
+            Endpoint::DeviceLookup => "device_lookup",
+            Endpoint::AuthValidation => "auth_validation",
+            Endpoint::Health => "health",
+        }
+    }
+}
+
+/// API Gateway 返回的当前用户信息
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct GatewayUser {
+    pub id: String,
+    pub username: String,
+    pub role: UserRole,
+}
+
+/// API Gateway 客户端
+pub struct GatewayClient {
+    base_url: String,
+    // 设备查询等服务间调用使用的令牌
+    token: String,
+    http: reqwest::Client,
+    config: GatewayClientConfig,
+}
+
+impl GatewayClient {
+    /// 创建客户端，服务间调用携带 `token` 作为 Bearer 令牌
+    pub fn new(config: &GatewayClientConfig, token: impl Into<String>) -> Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_seconds))
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .tcp_nodelay(true);
+        if config.http2 {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        Ok(Self {
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            token: token.into(),
+            http: builder.build().context("Failed to build API Gateway HTTP client")?,
+            config: config.clone(),
+        })
+    }
+
+    /// 查询设备信息，设备不存在时返回 None
+    // 目前只有就绪检查调用 Gateway，设备查询和令牌校验留给后续的设备鉴权使用
+    #[allow(dead_code)]
+    pub async fn get_device(&self, device_id: &DeviceId) -> Result<Option<Device>> {
+        let path = format!("/api/v1/devices/{}", device_id);
+        let response = self.get(Endpoint::DeviceLookup, &path, &self.token).await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Self::read_data(response).await.map(Some)
+    }
+
+    /// 校验用户令牌，令牌无效或已过期时返回 None
+    #[allow(dead_code)]
+    pub async fn validate_user_token(&self, user_token: &str) -> Result<Option<GatewayUser>> {
+        let response = self.get(Endpoint::AuthValidation, "/api/v1/auth/me", user_token).await?;
+
+        if matches!(response.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+            return Ok(None);
+        }
+        Self::read_data(response).await.map(Some)
+    }
+
+    /// 检查 API Gateway 是否存活
+    pub async fn health(&self) -> Result<()> {
+        self.get(Endpoint::Health, "/health/live", &self.token)
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn timeout(&self, endpoint: Endpoint) -> Duration {
+        match endpoint {
+            Endpoint::DeviceLookup => Duration::from_millis(self.config.device_lookup_timeout_ms),
+            Endpoint::AuthValidation => Duration::from_millis(self.config.auth_timeout_ms),
+            Endpoint::Health => HEALTH_TIMEOUT,
+        }
+    }
+
+    // 发送 GET 请求，可重试的失败按指数退避重试，返回最后一次的响应
+    async fn get(&self, endpoint: Endpoint, path: &str, bearer: &str) -> Result<reqwest::Response> {
+        let url = format!("{}{}", self.base_url, path);
+        let timeout = self.timeout(endpoint);
+        let mut attempt = 0;
+
+        loop {
+            let start = Instant::now();
+            let result = self.http.get(&url).bearer_auth(bearer).timeout(timeout).send().await;
+            let outcome = match &result {
+                Ok(response) => response.status().as_str().to_string(),
+                Err(e) if e.is_timeout() => "timeout".to_string(),
+                Err(_) => "error".to_string(),
+            };
+            telemetry::record_gateway_request(endpoint.name(), outcome, start.elapsed());
+
+            let retryable = match &result {
+                Ok(response) => matches!(
+                    response.status(),
+                    StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+                ),
+                Err(e) => e.is_connect() || e.is_timeout(),
+            };
+            if !retryable || attempt >= self.config.max_retries {
+                return result.with_context(|| format!("API Gateway request {} failed", endpoint.name()));
+            }
+
+            let delay = Duration::from_millis(self.config.retry_backoff_ms.saturating_mul(1 << attempt.min(16)));
+            match &result {
+                Ok(response) => warn!(
+                    "API Gateway {} returned {}, retrying in {:?} (attempt {}/{})",
+                    endpoint.name(), response.status(), delay, attempt + 1, self.config.max_retries
+                ),
+                Err(e) => warn!(
+                    "API Gateway {} failed: {}, retrying in {:?} (attempt {}/{})",
+                    endpoint.name(), e, delay, attempt + 1, self.config.max_retries
+                ),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    // 解析 ApiResponse 包装中的数据
+    async fn read_data<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+        let body: ApiResponse<T> = response.error_for_status()?.json().await?;
+        body.data
+            .ok_or_else(|| anyhow::anyhow!("API Gateway response has no data: {}", body.message))
+    }
+}
@@ -0,0 +1,346 @@
+// 通过 gRPC 传输设备音频流（通过 `grpc` feature 和 bridge.grpc_audio.enabled 启用）
+//
+// 通过 AudioStream 连接的设备与 WebSocket 设备注册到同一个连接管理器，按 v2 协议协商，
+// 会话、EchoKit 转发、死信队列和内部 API 无需改动即可使用。发往设备的 v2 帧在这里转换为 StreamMessage。
+
+use axum::extract::ws::Message;
+use axum::http::StatusCode;
+use echo_shared::grpc::audio::{
+    audio_stream_server::{AudioStream, AudioStreamServer},
+    stream_message, AudioFrame, ControlEvent, StreamMessage,
+};
+use echo_shared::protocol::{ClientCommand, Frame, FrameType, ProtocolVersion, ServerEvent};
+use echo_shared::{DeviceId, GrpcAudioConfig, SessionId};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tracing::{debug, error, info, warn};
+use crate::trace_capture::{self, TraceDirection};
+use crate::websocket::audio_handler::{self, AppState};
+use crate::websocket::connection_manager::DeviceSink;
+
+const TRANSPORT: &str = "grpc";
+
+pub struct AudioStreamService {
+    state: AppState,
+    config: GrpcAudioConfig,
+    streams: Arc<Semaphore>,
+}
+
+impl AudioStreamService {
+    pub fn new(state: AppState, config: GrpcAudioConfig) -> Self {
+        let streams = Arc::new(Semaphore::new(config.max_streams));
+        Self { state, config, streams }
+    }
+}
+
+#[tonic::async_trait]
+impl AudioStream for AudioStreamService {
+    type StreamAudioStream = ReceiverStream<Result<StreamMessage, Status>>;
+
+    async fn stream_audio(
+        &self,
+        request: Request<Streaming<StreamMessage>>,
+    ) -> Result<Response<Self::StreamAudioStream>, Status> {
+        let permit = self
+            .streams
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Status::resource_exhausted("Too many audio streams"))?;
+
+        let metadata = request.metadata();
+        let device_id = metadata
+            .get("device-id")
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty())
+            .map(DeviceId::from)
+            .ok_or_else(|| Status::invalid_argument("Missing device-id metadata"))?;
+        let ticket = metadata.get("ticket").and_then(|value| value.to_str().ok()).map(str::to_string);
+        let record_mode = metadata.get("record").and_then(|value| value.to_str().ok()) == Some("true");
+        let remote_addr = request.remote_addr();
+
+        audio_handler::authorize_ticket(&self.state, &device_id, ticket.as_deref(), "gRPC")
+            .await
+            .map_err(|(status, message)| match status {
+                StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+                _ => Status::unavailable(message),
+            })?;
+
+        let (sink, mut outgoing) = mpsc::channel::<Message>(self.config.send_buffer);
+        let (responses, response_stream) = mpsc::channel(self.config.send_buffer);
+        audio_handler::register_device_connection(
+            &device_id,
+            DeviceSink::Channel(sink),
+            remote_addr,
+            ProtocolVersion::V2,
+            &self.state,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to register device {}: {}", device_id, e);
+            Status::internal("Failed to register device")
+        })?;
+
+        info!(
+            "Device {} gRPC audio stream connected from {} (record_mode: {})",
+            device_id,
+            remote_addr.map(|addr| addr.to_string()).unwrap_or_else(|| "unknown".to_string()),
+            record_mode
+        );
+
+        // 通过连接管理器推送的消息；设备被移除或客户端停止读取响应流时结束
+        let outgoing_device_id = device_id.clone();
+        tokio::spawn(async move {
+            while let Some(message) = outgoing.recv().await {
+                let Some(message) = to_stream_message(message) else {
+                    continue;
+                };
+                if responses.send(Ok(message)).await.is_err() {
+                    debug!("gRPC audio stream for device {} closed by client", outgoing_device_id);
+                    break;
+                }
+            }
+        });
+
+        let inbound = request.into_inner();
+        tokio::spawn(handle_device_stream(inbound, device_id, record_mode, self.state.clone(), permit));
+
+        Ok(Response::new(ReceiverStream::new(response_stream)))
+    }
+}
+
+/// 读取设备消息直到流结束，然后像 WebSocket 断开一样结束会话
+async fn handle_device_stream(
+    mut inbound: Streaming<StreamMessage>,
+    device_id: DeviceId,
+    record_mode: bool,
+    state: AppState,
+    _permit: OwnedSemaphorePermit,
+) {
+    let mut active_session: Option<SessionId> = None;
+    let mut device_echokit_session: Option<String> = None;
+    let mut echokit_unavailable_notified = false;
+
+    loop {
+        let message = match inbound.message().await {
+            Ok(Some(message)) => message,
+            Ok(None) => {
+                info!("Device {} closed gRPC audio stream", device_id);
+                break;
+            }
+            Err(status) => {
+                warn!("gRPC audio stream error for device {}: {}", device_id, status);
+                break;
+            }
+        };
+
+        // 会话可能已通过内部 API 被强制结束
+        audio_handler::clear_ended_session(&mut active_session, &state).await;
+
+        match message.message {
+            Some(stream_message::Message::Audio(frame)) => {
+                state.connection_manager.record_received(&device_id, frame.pcm.len()).await;
+                if active_session.as_deref() != Some(frame.session_id.as_str()) {
+                    debug!(
+                        "Dropping audio for session {:?} from device {} (active session: {:?})",
+                        frame.session_id, device_id, active_session
+                    );
+                    continue;
+                }
+                if !audio_handler::handle_device_audio(
+                    &frame.pcm,
+                    TRANSPORT,
+                    &device_id,
+                    &active_session,
+                    &mut echokit_unavailable_notified,
+                    &state,
+                )
+                .await
+                {
+                    break;
+                }
+            }
+            Some(stream_message::Message::Control(event)) => {
+                state.connection_manager.record_received(&device_id, event.payload_json.len()).await;
+                let cmd = match parse_command(&event) {
+                    Ok(cmd) => cmd,
+                    Err(e) => {
+                        warn!("Invalid control event {:?} from device {}: {}", event.name, device_id, e);
+                        continue;
+                    }
+                };
+                if !cmd.is_session_start() && active_session.as_deref() != Some(event.session_id.as_str()) {
+                    warn!(
+                        "Ignoring {} for session {:?} from device {} (active session: {:?})",
+                        cmd.name(), event.session_id, device_id, active_session
+                    );
+                    continue;
+                }
+
+                if let Some(session_id) = active_session.as_ref() {
+                    trace_capture::record(session_id, &device_id, TraceDirection::DeviceToBridge, cmd.name(), event.payload_json.len());
+                }
+                if let Err(e) = audio_handler::handle_client_command(
+                    cmd,
+                    &device_id,
+                    record_mode,
+                    &mut active_session,
+                    &mut device_echokit_session,
+                    &state,
+                )
+                .await
+                {
+                    error!("Failed to handle control event: {}", e);
+                }
+            }
+            // 空消息用于保持连接
+            None => state.connection_manager.update_heartbeat(&device_id).await,
+        }
+    }
+
+    audio_handler::close_device_connection(&device_id, active_session, &state).await;
+}
+
+/// 只带名称的命令（StartChat、Submit 等）可以不填 payload_json
+fn parse_command(event: &ControlEvent) -> Result<ClientCommand, serde_json::Error> {
+    if event.payload_json.is_empty() {
+        ClientCommand::from_json(&serde_json::json!({ "event": event.name }).to_string())
+    } else {
+        ClientCommand::from_json(&event.payload_json)
+    }
+}
+
+/// 将发往 v2 设备的消息转换为 gRPC 格式
+///
+/// 音频块（回复、问候和背景音频）转换为 AudioFrame，由前后的 StartAudio / HelloStart / BGStart 事件界定；
+/// 其他消息转换为 ControlEvent。
+fn to_stream_message(message: Message) -> Option<StreamMessage> {
+    let message = match message {
+        Message::Binary(data) => match Frame::decode(&data) {
+            Ok(frame) if frame.frame_type == FrameType::Event => event_message(frame.session_id, frame.payload),
+            Ok(frame) if frame.frame_type == FrameType::Control => {
+                let notice: serde_json::Value = rmp_serde::from_slice(frame.payload).ok()?;
+                control_message(frame.session_id, &notice)
+            }
+            Ok(frame) => {
+                debug!("Not forwarding {:?} frame to gRPC device", frame.frame_type);
+                return None;
+            }
+            Err(e) => {
+                warn!("Malformed v2 frame ({} bytes) queued for gRPC device: {}", data.len(), e);
+                return None;
+            }
+        },
+        Message::Text(text) => {
+            let notice: serde_json::Value = serde_json::from_str(&text).ok()?;
+            let session_id = notice.get("session_id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            control_message(&session_id, &notice)
+        }
+        // Ping/Pong/Close 只存在于 WebSocket 连接
+        _ => return None,
+    };
+    Some(StreamMessage { message: Some(message) })
+}
+
+fn event_message(session_id: &str, payload: &[u8]) -> stream_message::Message {
+    let event: serde_json::Value = match ServerEvent::from_messagepack(payload) {
+        Ok(ServerEvent::AudioChunk { data })
+        | Ok(ServerEvent::HelloChunk { data })
+        | Ok(ServerEvent::BGChunk { data }) => {
+            return stream_message::Message::Audio(AudioFrame { session_id: session_id.to_string(), pcm: data });
+        }
+        Ok(event) => serde_json::to_value(&event).unwrap_or_default(),
+        // 本 Bridge 构建之后 EchoKit 新增的事件原样透传
+        Err(_) => rmp_serde::from_slice(payload).unwrap_or_default(),
+    };
+    control_message(session_id, &event)
+}
+
+/// 通知的名称放在 `event` 中；外部标记的 ServerEvent 是字符串或只有一个键的映射
+fn control_message(session_id: &str, value: &serde_json::Value) -> stream_message::Message {
+    let name = match value {
+        serde_json::Value::String(name) => name.clone(),
+        serde_json::Value::Object(map) => match map.get("event").and_then(|v| v.as_str()) {
+            Some(name) => name.to_string(),
+            None => map.keys().next().cloned().unwrap_or_default(),
+        },
+        _ => String::new(),
+    };
+    stream_message::Message::Control(ControlEvent {
+        session_id: session_id.to_string(),
+        name,
+        payload_json: value.to_string(),
+    })
+}
+
+/// 在 bridge.grpc_audio.port 上提供设备音频流 API，直到进程退出
+pub async fn serve(state: AppState, config: GrpcAudioConfig) -> anyhow::Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    // 每条消息的大小限制与 WebSocket 设备相同
+    let max_message_bytes = state.limits.max_websocket_message_bytes;
+    let service = AudioStreamServer::new(AudioStreamService::new(state, config))
+        .max_decoding_message_size(max_message_bytes);
+
+    info!("gRPC audio stream server listening on: {}", addr);
+    Server::builder().add_service(service).serve(addr).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+
+    fn event_frame(session_id: &str, event: &ServerEvent) -> Message {
+        let payload = event.to_messagepack().unwrap();
+        Message::Binary(Bytes::from(Frame::new(FrameType::Event, session_id, &payload).encode().unwrap()))
+    }
+
+    #[test]
+    fn test_outgoing_frames_become_stream_messages() {
+        let audio = to_stream_message(event_frame("session_1", &ServerEvent::AudioChunk { data: vec![1, 2, 3, 4] }));
+        match audio.and_then(|message| message.message) {
+            Some(stream_message::Message::Audio(frame)) => {
+                assert_eq!(frame.session_id, "session_1");
+                assert_eq!(frame.pcm, vec![1, 2, 3, 4]);
+            }
+            other => panic!("expected audio frame, got {:?}", other),
+        }
+
+        let asr = to_stream_message(event_frame("session_1", &ServerEvent::ASR { text: "hello".to_string() }));
+        match asr.and_then(|message| message.message) {
+            Some(stream_message::Message::Control(event)) => {
+                assert_eq!(event.name, "ASR");
+                assert_eq!(event.payload_json, r#"{"ASR":{"text":"hello"}}"#);
+            }
+            other => panic!("expected control event, got {:?}", other),
+        }
+
+        let notice = rmp_serde::to_vec_named(&serde_json::json!({"event": "session_started", "session_id": "session_2"})).unwrap();
+        let notice = Message::Binary(Bytes::from(Frame::new(FrameType::Control, "session_2", &notice).encode().unwrap()));
+        match to_stream_message(notice).and_then(|message| message.message) {
+            Some(stream_message::Message::Control(event)) => {
+                assert_eq!((event.session_id.as_str(), event.name.as_str()), ("session_2", "session_started"));
+            }
+            other => panic!("expected control event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_command_accepts_name_only() {
+        let event = |name: &str, payload_json: &str| ControlEvent {
+            session_id: String::new(),
+            name: name.to_string(),
+            payload_json: payload_json.to_string(),
+        };
+
+        assert_eq!(parse_command(&event("StartChat", "")).unwrap(), ClientCommand::StartChat { language: None });
+        assert_eq!(
+            parse_command(&event("Text", r#"{"event":"Text","input":"hi"}"#)).unwrap(),
+            ClientCommand::Text { input: "hi".to_string() }
+        );
+        assert!(parse_command(&event("Unknown", "")).is_err());
+    }
+}
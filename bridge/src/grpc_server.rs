@@ -0,0 +1,128 @@
+// 供 API Gateway 调用的内部 gRPC 服务（通过 `grpc` feature 启用）
+
+use echo_shared::grpc::{
+    bridge_service_server::{BridgeService, BridgeServiceServer},
+    require_internal_token, DispatchCommandRequest, DispatchCommandResponse, EndSessionRequest,
+    EndSessionResponse, ListOnlineDevicesRequest, ListOnlineDevicesResponse, OnlineDevice,
+};
+use echo_shared::{EchoError, SessionId};
+use std::net::SocketAddr;
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::{debug, info, warn};
+use crate::internal_api::{self, InternalApiState};
+use crate::telemetry;
+
+/// 内部 gRPC 服务的默认端口
+const DEFAULT_GRPC_PORT: u16 = 10040;
+
+pub struct BridgeGrpcService {
+    state: InternalApiState,
+}
+
+#[tonic::async_trait]
+impl BridgeService for BridgeGrpcService {
+    async fn list_online_devices(
+        &self,
+        _request: Request<ListOnlineDevicesRequest>,
+    ) -> Result<Response<ListOnlineDevicesResponse>, Status> {
+        let devices = internal_api::cluster_online_devices(&self.state)
+            .await
+            .into_iter()
+            .map(|device| OnlineDevice {
+                device_id: device.device_id,
+                last_heartbeat_ms: device.last_heartbeat.timestamp_millis(),
+                active_session_id: device.active_session_id,
+            })
+            .collect();
+
+        Ok(Response::new(ListOnlineDevicesResponse { devices }))
+    }
+
+    async fn dispatch_command(
+        &self,
+        request: Request<DispatchCommandRequest>,
+    ) -> Result<Response<DispatchCommandResponse>, Status> {
+        let command = request.into_inner();
+        if serde_json::from_str::<serde_json::Value>(&command.payload_json).is_err() {
+            return Err(Status::invalid_argument("payload_json must be valid JSON"));
+        }
+        // 启用命令签名时，只有网关签名的命令才会下发到设备
+        if let Some(verifier) = &self.state.command_verifier {
+            let envelope = match verifier.verify_json(&command.payload_json, &command.device_id) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    warn!("gRPC: rejected command for device {}: {}", command.device_id, e);
+                    telemetry::record_command_rejected("grpc", e.reason());
+                    return Err(Status::permission_denied(format!("command signature rejected: {}", e)));
+                }
+            };
+            // 发起用户包含在签名中，权限检查可以信任该字段
+            let kind = echo_shared::DeviceCommandKind::of_value(&envelope.command);
+            let allowed = crate::command_authz::allow(
+                &self.state.command_authorizer,
+                "grpc",
+                &command.device_id,
+                kind,
+                envelope.issued_by.as_ref(),
+            )
+            .await;
+            if !allowed {
+                return Err(Status::permission_denied(format!("not authorized to send {} commands", kind.as_str())));
+            }
+        }
+
+        match self.state.connection_manager.send_text(&command.device_id, &command.payload_json).await {
+            Ok(()) => {
+                debug!("gRPC: dispatched command to device {}", command.device_id);
+                Ok(Response::new(DispatchCommandResponse {
+                    delivered: true,
+                    message: "Command delivered".to_string(),
+                }))
+            }
+            // 设备未连接或其 socket 正在关闭
+            Err(e) => {
+                debug!("gRPC: failed to dispatch command to device {}: {}", command.device_id, e);
+                Ok(Response::new(DispatchCommandResponse {
+                    delivered: false,
+                    message: e.to_string(),
+                }))
+            }
+        }
+    }
+
+    async fn end_session(
+        &self,
+        request: Request<EndSessionRequest>,
+    ) -> Result<Response<EndSessionResponse>, Status> {
+        let request = request.into_inner();
+        let reason = if request.reason.is_empty() {
+            "terminated".to_string()
+        } else {
+            request.reason
+        };
+
+        match internal_api::force_end_session(&self.state, &SessionId::from(request.session_id.as_str()), &reason).await {
+            Ok(()) => Ok(Response::new(EndSessionResponse { ended: true })),
+            Err(EchoError::SessionNotFound(_)) | Err(EchoError::Conflict(_)) => {
+                Ok(Response::new(EndSessionResponse { ended: false }))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+}
+
+/// 在 GRPC_PORT 上提供内部 gRPC API，直到进程退出
+pub async fn serve(state: InternalApiState) -> anyhow::Result<()> {
+    let port = std::env::var("GRPC_PORT")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_GRPC_PORT);
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    let interceptor = require_internal_token(state.token.to_string());
+    let service = BridgeServiceServer::with_interceptor(BridgeGrpcService { state }, interceptor);
+
+    info!("Internal gRPC server listening on: {}", addr);
+    Server::builder().add_service(service).serve(addr).await?;
+    Ok(())
+}
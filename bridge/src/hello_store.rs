@@ -0,0 +1,79 @@
+//! EchoKit 欢迎语的 Redis 持久化
+//!
+//! 欢迎语（HelloStart 到 HelloEnd 的 MessagePack 消息序列）按 EchoKit Server 地址保存在 Redis 列表
+//! `echokit:hello:v{version}:{server_url}` 中，每条消息一个元素。EchoKit 客户端收到 HelloEnd 后覆盖保存，
+//! 新建的客户端在本地缓存为空时读取，新启动的副本无需等待 EchoKit 重新发送即可回放欢迎语。
+//! 保存格式变化时递增 `FORMAT_VERSION`，旧格式的记录不再读取并在过期后消失。
+
+use std::sync::Arc;
+
+use echo_shared::{CacheKey, HelloPersistenceConfig};
+use redis::RedisResult;
+use tracing::{debug, info, warn};
+
+use crate::redis_client::RedisClient;
+
+// 保存格式的版本
+const FORMAT_VERSION: u32 = 1;
+
+pub struct HelloStore {
+    config: HelloPersistenceConfig,
+    redis: Arc<RedisClient>,
+}
+
+impl HelloStore {
+    pub fn new(config: HelloPersistenceConfig, redis: Arc<RedisClient>) -> Arc<Self> {
+        Arc::new(Self { config, redis })
+    }
+
+    /// 读取 EchoKit Server 保存的欢迎语，没有记录或 Redis 不可用时返回空列表
+    pub async fn load(&self, server_url: &str) -> Vec<Vec<u8>> {
+        let key = CacheKey::echokit_hello(server_url, FORMAT_VERSION);
+        let result: RedisResult<Vec<Vec<u8>>> = self
+            .redis
+            .run(|mut conn| async move { redis::cmd("LRANGE").arg(&key).arg(0).arg(-1).query_async(&mut conn).await })
+            .await;
+        match result {
+            Ok(messages) => {
+                if !messages.is_empty() {
+                    info!("Loaded {} persisted Hello messages for {}", messages.len(), server_url);
+                }
+                messages
+            }
+            Err(e) => {
+                warn!("Failed to load persisted Hello messages for {}: {}", server_url, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// 后台覆盖保存 EchoKit Server 的欢迎语，失败时只记录日志
+    pub fn spawn_save(self: &Arc<Self>, server_url: &str, messages: Vec<Vec<u8>>) {
+        if messages.is_empty() {
+            return;
+        }
+        let store = self.clone();
+        let server_url = server_url.to_string();
+        tokio::spawn(async move {
+            let key = CacheKey::echokit_hello(&server_url, FORMAT_VERSION);
+            let count = messages.len();
+            let ttl_seconds = store.config.ttl_seconds;
+            let result: RedisResult<()> = store
+                .redis
+                .run(|mut conn| async move {
+                    redis::pipe()
+                        .atomic()
+                        .cmd("DEL").arg(&key).ignore()
+                        .cmd("RPUSH").arg(&key).arg(messages).ignore()
+                        .cmd("EXPIRE").arg(&key).arg(ttl_seconds).ignore()
+                        .query_async(&mut conn)
+                        .await
+                })
+                .await;
+            match result {
+                Ok(()) => debug!("Persisted {} Hello messages for {}", count, server_url),
+                Err(e) => warn!("Failed to persist Hello messages for {}: {}", server_url, e),
+            }
+        });
+    }
+}
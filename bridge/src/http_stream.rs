@@ -0,0 +1,573 @@
+//! HTTP 流式音频接口
+//!
+//! 给无法使用 WebSocket 的瘦客户端：先用 `POST /api/sessions` 创建会话，每一轮对话以 chunked 请求体
+//! `POST /api/stream/{session_id}` 上传 16kHz 16-bit 单声道 PCM，请求体结束即提交识别；
+//! `GET /api/stream/{session_id}/response` 以 chunked `audio/wav` 返回本轮 EchoKit 的回复音频，回复结束时响应结束。
+//! 回复可以在上传开始后立即请求，与上传并行进行。
+//!
+//! 启用连接票据（`redis.ws_tickets`）时，每个上传和回复请求都要带上 API Gateway 为会话设备签发的一次性票据
+//! （`Authorization: Bearer <票据>` 或 `?ticket=<票据>`），与设备 WebSocket 和 gRPC 接入的校验规则相同。
+//!
+//! 每个会话使用独立的 EchoKit 连接，与设备 WebSocket 会话一样登记在会话管理器中，转写、回复和轮数在
+//! 会话结束时写入数据库。回复结束后一段时间内没有新的上传时会话结束。流的状态只保存在本实例内存中。
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use echo_shared::types::SessionStatus as StoredSessionStatus;
+use echo_shared::{
+    ApiResponse, AudioFormat, DeviceId, EchoError, EchoKitEvent, HttpStreamConfig, IngressLimitsConfig, SessionId, WsTickets,
+};
+use futures::StreamExt;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, info, warn};
+
+use crate::asr_postprocess::{self, AsrPostProcessing};
+use crate::channels::{self, AudioReceiver, ControlReceiver};
+use crate::echokit_client::EchoKitClient;
+use crate::error::ApiError;
+use crate::redis_client::RedisClient;
+use crate::session;
+use crate::session_service::SessionService;
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+use crate::websocket::audio_handler;
+use crate::websocket::session_manager::{SessionManager, SessionStatus};
+
+// EchoKit 要求的音频格式：16kHz 16-bit 单声道
+const SAMPLE_RATE: u32 = 16_000;
+const BYTES_PER_SECOND: usize = SAMPLE_RATE as usize * 2;
+// 每 100ms 向 EchoKit 发送一次音频
+const SEND_BYTES: usize = BYTES_PER_SECOND / 10;
+// 回复音频在被取走前最多缓存的数据块数，超出时丢弃
+const RESPONSE_CHANNEL_CAPACITY: usize = 1024;
+const AUDIO_CALLBACK_CAPACITY: usize = 256;
+// 刷新会话活动时间、检查超时和会话是否已在别处结束的间隔
+const ACTIVITY_INTERVAL: Duration = Duration::from_secs(5);
+// EchoKit 回复结束标记（见 EchoKitClient 的 response_callback）
+const END_RESPONSE_MARKER: &str = "__END_RESPONSE__";
+
+// 会话对应的设备
+struct StreamDevice {
+    id: DeviceId,
+    echokit_url: String,
+}
+
+// EchoKit 连接的回调接收端
+struct Callbacks {
+    audio: AudioReceiver<(String, Vec<u8>)>,
+    asr: ControlReceiver<(String, String)>,
+    response: ControlReceiver<(String, String)>,
+}
+
+// 一个会话的流
+struct Stream {
+    session_id: SessionId,
+    device_id: DeviceId,
+    client: EchoKitClient,
+    turn: Mutex<Turn>,
+}
+
+// 当前轮次的状态
+struct Turn {
+    uploading: bool,
+    // 本轮回复音频的发送端，回复结束（或超时）时关闭，GET 的响应随之结束
+    response_tx: Option<mpsc::Sender<Bytes>>,
+    // 尚未被 GET 取走的接收端
+    response_rx: Option<mpsc::Receiver<Bytes>>,
+    // 提交的时间，回复结束后清空
+    submitted_at: Option<Instant>,
+    last_activity: Instant,
+}
+
+impl Stream {
+    fn turn(&self) -> std::sync::MutexGuard<'_, Turn> {
+        self.turn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // 开始新一轮上传；上一轮仍在上传或回复中时拒绝
+    fn begin_upload(&self) -> Result<(), EchoError> {
+        let mut turn = self.turn();
+        if turn.uploading {
+            return Err(EchoError::Conflict(format!("Session {} is already uploading audio", self.session_id)));
+        }
+        if turn.response_tx.is_some() {
+            return Err(EchoError::Conflict(format!("Session {} is still responding", self.session_id)));
+        }
+        let (tx, rx) = mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        turn.uploading = true;
+        turn.response_tx = Some(tx);
+        turn.response_rx = Some(rx);
+        turn.last_activity = Instant::now();
+        Ok(())
+    }
+
+    // 上传失败时丢弃本轮
+    fn abort_upload(&self) {
+        let mut turn = self.turn();
+        turn.uploading = false;
+        turn.response_tx = None;
+        turn.response_rx = None;
+        turn.last_activity = Instant::now();
+    }
+
+    fn finish_upload(&self) {
+        let mut turn = self.turn();
+        turn.uploading = false;
+        turn.submitted_at = Some(Instant::now());
+        turn.last_activity = Instant::now();
+    }
+
+    fn push_response(&self, pcm: Vec<u8>) {
+        let turn = self.turn();
+        let Some(tx) = &turn.response_tx else {
+            return;
+        };
+        if tx.try_send(Bytes::from(pcm)).is_err() {
+            debug!("Response audio for session {} is not being consumed, dropping chunk", self.session_id);
+        }
+    }
+
+    fn end_response(&self) {
+        let mut turn = self.turn();
+        turn.response_tx = None;
+        turn.submitted_at = None;
+        turn.last_activity = Instant::now();
+    }
+
+    fn take_response(&self) -> Option<mpsc::Receiver<Bytes>> {
+        self.turn().response_rx.take()
+    }
+}
+
+/// HTTP 流管理
+pub struct HttpStreams {
+    config: HttpStreamConfig,
+    db: Arc<PgPool>,
+    // POST /api/sessions 创建的会话
+    sessions: Arc<session::SessionManager>,
+    session_manager: Arc<SessionManager>,
+    session_service: Arc<SessionService>,
+    ingress_limits: IngressLimitsConfig,
+    asr_postprocessing: Option<Arc<AsrPostProcessing>>,
+    // 一次性连接票据，未启用时不校验
+    tickets: Option<(Arc<WsTickets>, Arc<RedisClient>)>,
+    streams: Mutex<HashMap<String, Arc<Stream>>>,
+    // 串行建立新流，避免同一会话并发的首个请求各自连接 EchoKit
+    opening: tokio::sync::Mutex<()>,
+}
+
+impl HttpStreams {
+    pub fn new(
+        config: HttpStreamConfig,
+        db: Arc<PgPool>,
+        sessions: Arc<session::SessionManager>,
+        session_manager: Arc<SessionManager>,
+        session_service: Arc<SessionService>,
+    ) -> Self {
+        Self {
+            config,
+            db,
+            sessions,
+            session_manager,
+            session_service,
+            ingress_limits: IngressLimitsConfig::default(),
+            asr_postprocessing: None,
+            tickets: None,
+            streams: Mutex::new(HashMap::new()),
+            opening: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 接收 EchoKit 消息时的大小限制，与设备会话的 EchoKit 连接相同
+    pub fn with_ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
+    /// 识别结果保存前经过的 ASR 文本后处理
+    pub fn with_asr_postprocessing(mut self, asr_postprocessing: Arc<AsrPostProcessing>) -> Self {
+        self.asr_postprocessing = Some(asr_postprocessing);
+        self
+    }
+
+    /// 上传和回复请求需要出示的一次性连接票据
+    pub fn with_tickets(mut self, tickets: Arc<WsTickets>, redis: Arc<RedisClient>) -> Self {
+        self.tickets = Some((tickets, redis));
+        self
+    }
+
+    /// 一次上传的请求体上限
+    pub fn max_upload_bytes(&self) -> usize {
+        self.config.max_upload_seconds as usize * BYTES_PER_SECOND
+    }
+
+    fn stream(&self, session_id: &str) -> Option<Arc<Stream>> {
+        self.streams.lock().unwrap_or_else(|e| e.into_inner()).get(session_id).cloned()
+    }
+
+    // 校验请求出示的票据是否签发给会话所属的设备，在建立 EchoKit 连接之前进行
+    async fn authorize(&self, session_id: &str, headers: &HeaderMap, query: &HashMap<String, String>) -> Result<(), ApiError> {
+        let Some((tickets, redis)) = &self.tickets else {
+            return Ok(());
+        };
+        let device_id = match self.stream(session_id) {
+            Some(stream) => stream.device_id.clone(),
+            None => {
+                let session = self
+                    .sessions
+                    .get_session(session_id)
+                    .await
+                    .ok_or_else(|| EchoError::SessionNotFound(session_id.to_string()))?;
+                DeviceId::from(session.device_id)
+            }
+        };
+        let ticket = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| query.get("ticket").map(String::as_str));
+
+        audio_handler::redeem_connection_ticket(Some(tickets.as_ref()), redis, &device_id, ticket, "HTTP stream")
+            .await
+            .map_err(|(status, message)| match status {
+                StatusCode::UNAUTHORIZED => EchoError::Authentication(message).into(),
+                _ => EchoError::ServiceUnavailable(message).into(),
+            })
+    }
+
+    // 返回会话已有的流，没有时为会话建立 EchoKit 连接
+    async fn open(self: &Arc<Self>, session_id: &str) -> Result<Arc<Stream>, EchoError> {
+        if let Some(stream) = self.stream(session_id) {
+            return Ok(stream);
+        }
+        let _opening = self.opening.lock().await;
+        if let Some(stream) = self.stream(session_id) {
+            return Ok(stream);
+        }
+
+        let active = self.streams.lock().unwrap_or_else(|e| e.into_inner()).len();
+        if active >= self.config.max_streams {
+            return Err(EchoError::RateLimited(format!("{} audio streams are already in progress", active)));
+        }
+
+        let session = self
+            .sessions
+            .get_session(session_id)
+            .await
+            .ok_or_else(|| EchoError::SessionNotFound(session_id.to_string()))?;
+        if !matches!(session.status, StoredSessionStatus::Active) {
+            return Err(EchoError::Conflict(format!("Session {} has already ended", session_id)));
+        }
+        if self.session_manager.is_active(session_id).await {
+            return Err(EchoError::Conflict(format!("Session {} is in use by a device connection", session_id)));
+        }
+        let device = self
+            .find_device(&session.device_id)
+            .await?
+            .ok_or_else(|| EchoError::DeviceNotFound(session.device_id.clone()))?;
+
+        let session_id = SessionId::new(session_id);
+        if let Err(e) = self.session_manager.create_session(session_id.clone(), device.id.clone()).await {
+            error!("Failed to register streaming session {}: {}", session_id, e);
+        }
+        let (client, callbacks) = match self.connect_echokit(&device, &session_id).await {
+            Ok(connected) => connected,
+            Err(e) => {
+                error!("Failed to connect streaming session {} to EchoKit: {:#}", session_id, e);
+                telemetry::record_session_failed("echokit");
+                self.end_session(&session_id, SessionStatus::Failed).await;
+                return Err(EchoError::ServiceUnavailable("Failed to connect to EchoKit".to_string()));
+            }
+        };
+
+        let stream = Arc::new(Stream {
+            session_id: session_id.clone(),
+            device_id: device.id,
+            client,
+            turn: Mutex::new(Turn {
+                uploading: false,
+                response_tx: None,
+                response_rx: None,
+                submitted_at: None,
+                last_activity: Instant::now(),
+            }),
+        });
+        self.streams
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id.to_string(), stream.clone());
+        info!("HTTP audio stream opened for session {} (device {})", session_id, stream.device_id);
+
+        let streams = self.clone();
+        let driven = stream.clone();
+        tokio::spawn(async move { streams.drive(driven, callbacks).await });
+        Ok(stream)
+    }
+
+    async fn find_device(&self, device_id: &str) -> Result<Option<StreamDevice>, EchoError> {
+        let row = timed_query("http_stream_device", sqlx::query_as::<_, (String, String)>(
+            "SELECT id, echokit_server_url FROM devices WHERE id = $1",
+        )
+        .bind(device_id)
+        .fetch_optional(self.db.as_ref()))
+        .await
+        .map_err(|e| EchoError::Database(e.to_string()))?;
+
+        Ok(row.map(|(id, echokit_url)| StreamDevice { id: DeviceId::from(id), echokit_url }))
+    }
+
+    // 先登记会话再连接，EchoKit 的事件才会转发到该会话
+    async fn connect_echokit(&self, device: &StreamDevice, session_id: &SessionId) -> Result<(EchoKitClient, Callbacks)> {
+        let (audio_tx, audio) = channels::audio_channel("http_stream_audio", AUDIO_CALLBACK_CAPACITY);
+        let (asr_tx, asr) = channels::control_channel("http_stream_asr", 16);
+        let (response_tx, response) = channels::control_channel("http_stream_response", 64);
+        let client = EchoKitClient::builder(&device.echokit_url)
+            .audio_callback(audio_tx)
+            .asr_callback(asr_tx)
+            .response_callback(response_tx)
+            .ingress_limits(self.ingress_limits.clone())
+            .build()?;
+        client.pre_register_session(session_id.to_string(), device.id.to_string()).await;
+        client.connect_with_device_id(Some(device.id.as_str())).await?;
+        Ok((client, Callbacks { audio, asr, response }))
+    }
+
+    // 转发 EchoKit 的回复并维护会话，返回时会话已结束
+    async fn drive(&self, stream: Arc<Stream>, mut callbacks: Callbacks) {
+        let response_timeout = Duration::from_secs(self.config.response_timeout_seconds);
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_seconds);
+        let mut activity = tokio::time::interval(ACTIVITY_INTERVAL);
+
+        let reason = loop {
+            tokio::select! {
+                Some((_, data)) = callbacks.audio.recv() => {
+                    // 音频回调中的 MessagePack 事件与回复音频保持顺序，以 EndResponse 为本轮结束；问候音频不返回给客户端
+                    match EchoKitEvent::from_messagepack(&data) {
+                        Ok(EchoKitEvent::AudioChunk { data }) => {
+                            telemetry::record_audio_bytes("out", "http", data.len());
+                            stream.push_response(data);
+                        }
+                        Ok(EchoKitEvent::EndResponse) => stream.end_response(),
+                        _ => {}
+                    }
+                }
+                Some((_, text)) = callbacks.asr.recv() => {
+                    if !text.trim().is_empty() {
+                        let text = asr_postprocess::apply(self.asr_postprocessing.as_deref(), stream.device_id.as_str(), text).await;
+                        self.session_manager.append_transcript(&stream.session_id, text).await;
+                    }
+                }
+                Some((_, text)) = callbacks.response.recv() => {
+                    if text == END_RESPONSE_MARKER {
+                        self.session_manager.finalize_current_round_response(&stream.session_id).await;
+                    } else {
+                        self.session_manager.append_response(&stream.session_id, text).await;
+                    }
+                }
+                _ = activity.tick() => {
+                    // 会话被管理员或超时清理结束
+                    if !self.session_manager.is_active(&stream.session_id).await {
+                        break "session_ended";
+                    }
+                    if let Err(e) = self.session_manager.update_activity(&stream.session_id).await {
+                        debug!("Failed to update activity of session {}: {}", stream.session_id, e);
+                    }
+
+                    let (uploading, submitted_at, last_activity) = {
+                        let turn = stream.turn();
+                        (turn.uploading, turn.submitted_at, turn.last_activity)
+                    };
+                    if submitted_at.is_some_and(|at| at.elapsed() >= response_timeout) {
+                        warn!("No response from EchoKit for session {} within {:?}", stream.session_id, response_timeout);
+                        stream.end_response();
+                    } else if !uploading && submitted_at.is_none() && last_activity.elapsed() >= idle_timeout {
+                        break "idle";
+                    }
+                }
+            }
+        };
+
+        info!("HTTP audio stream for session {} ended: {}", stream.session_id, reason);
+        self.streams.lock().unwrap_or_else(|e| e.into_inner()).remove(stream.session_id.as_str());
+        stream.end_response();
+        if let Err(e) = stream.client.disconnect().await {
+            warn!("Failed to disconnect streaming session {} from EchoKit: {}", stream.session_id, e);
+        }
+        self.end_session(&stream.session_id, SessionStatus::Completed).await;
+    }
+
+    async fn end_session(&self, session_id: &SessionId, status: SessionStatus) {
+        if let Err(e) = self.session_manager.finalize(session_id, status, &self.session_service).await {
+            error!("Failed to finalize session {}: {}", session_id, e);
+        }
+    }
+
+    // 把请求体中的 PCM 按 100ms 分段发给 EchoKit，请求体结束后提交，返回收到的字节数
+    async fn upload(&self, stream: &Stream, body: Body) -> Result<usize, EchoError> {
+        let chunk_timeout = Duration::from_secs(self.config.chunk_timeout_seconds);
+        let max_bytes = self.max_upload_bytes();
+        let mut data = body.into_data_stream();
+        let mut pending: Vec<u8> = Vec::with_capacity(SEND_BYTES * 2);
+        let mut received = 0;
+
+        stream
+            .client
+            .send_start_chat_command()
+            .await
+            .map_err(|e| EchoError::ServiceUnavailable(format!("Failed to start chat: {}", e)))?;
+        loop {
+            let chunk = match tokio::time::timeout(chunk_timeout, data.next()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(Some(Err(e))) => return Err(EchoError::InvalidInput(format!("Failed to read audio: {}", e))),
+                Ok(None) => break,
+                Err(_) => {
+                    return Err(EchoError::InvalidInput(format!("No audio received for {}s", chunk_timeout.as_secs())))
+                }
+            };
+            received += chunk.len();
+            if received > max_bytes {
+                return Err(EchoError::PayloadTooLarge(format!(
+                    "Uploads are limited to {}s of audio",
+                    self.config.max_upload_seconds
+                )));
+            }
+            telemetry::record_audio_bytes("in", "http", chunk.len());
+
+            pending.extend_from_slice(&chunk);
+            if pending.len() >= SEND_BYTES {
+                // 保持 16-bit 样本对齐，奇数字节留到下一段
+                let len = pending.len() - pending.len() % 2;
+                let pcm: Vec<u8> = pending.drain(..len).collect();
+                self.send_audio(stream, pcm).await?;
+            }
+        }
+
+        if received < 2 {
+            return Err(EchoError::InvalidInput("Request body contains no audio".to_string()));
+        }
+        pending.truncate(pending.len() - pending.len() % 2);
+        if !pending.is_empty() {
+            self.send_audio(stream, pending).await?;
+        }
+        stream
+            .client
+            .send_submit_command()
+            .await
+            .map_err(|e| EchoError::ServiceUnavailable(format!("Failed to submit audio: {}", e)))?;
+        self.session_manager.mark_submitted(&stream.session_id).await;
+        Ok(received)
+    }
+
+    async fn send_audio(&self, stream: &Stream, pcm: Vec<u8>) -> Result<(), EchoError> {
+        stream
+            .client
+            .send_audio_data(stream.session_id.to_string(), stream.device_id.to_string(), pcm, AudioFormat::PCM16, false)
+            .await
+            .map_err(|e| EchoError::ServiceUnavailable(format!("Failed to send audio to EchoKit: {}", e)))
+    }
+}
+
+/// POST /api/stream/{session_id} - Upload one utterance as raw PCM (16 kHz, 16-bit, mono)
+///
+/// The body is usually sent with chunked transfer encoding and forwarded to EchoKit as it
+/// arrives; the end of the body submits the utterance. Returns once the audio has been
+/// submitted. Fetch the spoken reply from `GET /api/stream/{session_id}/response`.
+pub async fn upload_audio(
+    Path(session_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    State(streams): State<Arc<HttpStreams>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+    streams.authorize(&session_id, &headers, &query).await?;
+    let stream = streams.open(&session_id).await?;
+    stream.begin_upload()?;
+
+    let received = match streams.upload(&stream, body).await {
+        Ok(received) => received,
+        Err(e) => {
+            warn!("HTTP audio upload for session {} failed: {}", session_id, e);
+            stream.abort_upload();
+            return Err(e.into());
+        }
+    };
+    stream.finish_upload();
+
+    let duration_seconds = received as f64 / BYTES_PER_SECOND as f64;
+    info!("HTTP audio upload for session {} submitted ({:.1}s)", session_id, duration_seconds);
+    Ok(Json(ApiResponse::success(json!({
+        "session_id": session_id,
+        "bytes_received": received,
+        "duration_seconds": duration_seconds,
+        "response_url": format!("/api/stream/{}/response", session_id),
+    }))))
+}
+
+/// GET /api/stream/{session_id}/response - Stream the reply to the current upload as audio/wav
+///
+/// The WAV header declares an unknown length; the response ends when EchoKit finishes the
+/// reply. Each upload's reply can be fetched once.
+pub async fn stream_response(
+    Path(session_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    State(streams): State<Arc<HttpStreams>>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    streams.authorize(&session_id, &headers, &query).await?;
+    let stream = streams
+        .stream(&session_id)
+        .ok_or_else(|| EchoError::NotFound(format!("No audio stream for session {}", session_id)))?;
+    let receiver = stream
+        .take_response()
+        .ok_or_else(|| EchoError::NotFound(format!("No pending response for session {}", session_id)))?;
+
+    let header = futures::stream::once(async { Bytes::from(wav_header().to_vec()) });
+    let body = header.chain(ReceiverStream::new(receiver)).map(Ok::<_, Infallible>);
+    Ok(([(header::CONTENT_TYPE, "audio/wav"), (header::CACHE_CONTROL, "no-store")], Body::from_stream(body)).into_response())
+}
+
+// 流式 WAV 头：16kHz 16-bit 单声道 PCM，长度未知时 RIFF 和 data 块大小填 0xFFFFFFFF
+fn wav_header() -> [u8; 44] {
+    let mut header = [0u8; 44];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&1u16.to_le_bytes());
+    header[24..28].copy_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header[28..32].copy_from_slice(&(BYTES_PER_SECOND as u32).to_le_bytes());
+    header[32..34].copy_from_slice(&2u16.to_le_bytes());
+    header[34..36].copy_from_slice(&16u16.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let header = wav_header();
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(u32::from_le_bytes(header[24..28].try_into().unwrap()), 16_000);
+        assert_eq!(u32::from_le_bytes(header[28..32].try_into().unwrap()), 32_000);
+        assert_eq!(u16::from_le_bytes(header[34..36].try_into().unwrap()), 16);
+    }
+}
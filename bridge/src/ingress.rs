@@ -0,0 +1,32 @@
+//! HTTP 请求体大小限制
+//!
+//! 声明了 Content-Length 的超大请求在读取请求体之前直接返回 413；未声明长度（分块传输）的请求体
+//! 由路由上的 `DefaultBodyLimit` 在读取时拒绝。两种情况都计入 ingress_rejected 指标。
+
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::warn;
+
+use crate::telemetry;
+
+/// 请求体大小限制中间件，状态为最大字节数
+pub async fn limit_body(State(max_bytes): State<usize>, req: Request, next: Next) -> Response {
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if let Some(length) = declared.filter(|&length| length > max_bytes as u64) {
+        warn!("Rejected {} {}: body of {} bytes exceeds {} bytes", req.method(), req.uri().path(), length, max_bytes);
+        telemetry::record_ingress_rejected("http");
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response();
+    }
+
+    let response = next.run(req).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        telemetry::record_ingress_rejected("http");
+    }
+    response
+}
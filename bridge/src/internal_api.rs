@@ -0,0 +1,611 @@
+// 供 API Gateway 调用的内部 HTTP API（不对外暴露）
+
+use axum::{
+    extract::{multipart::MultipartError, Multipart, Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use echo_shared::{
+    AudioImportJob, BridgeOnlineDevice, BridgeSessionState, ConfigReloadResult, ConfigWatcher, DeadLetterFlushResult, DeadLetterQueueInfo,
+    DeadLetterSummary, DeviceId, EchoError, SessionId,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use crate::audio_capture::{self, DEFAULT_CAPTURE_SECONDS, MAX_CAPTURE_SECONDS};
+use crate::audio_import::{AudioImporter, ImportUpload};
+use crate::cluster::{ClusterRegistry, InstanceInfo};
+use crate::echokit::EchoKitSessionAdapter;
+use echo_shared::config_reload;
+use crate::error::ApiError;
+use crate::session_service::SessionService;
+use crate::trace_capture::{self, DEFAULT_TRACE_CAPACITY, MAX_TRACE_CAPACITY};
+use crate::websocket::connection_manager::DeviceConnectionManager;
+use crate::websocket::dead_letter::DeadLetterQueue;
+use crate::websocket::session_manager::{SessionManager, SessionStatus};
+
+// 内部 API 状态
+#[derive(Clone)]
+pub struct InternalApiState {
+    pub session_manager: Arc<SessionManager>,
+    pub connection_manager: Arc<DeviceConnectionManager>,
+    pub echokit_adapter: Arc<EchoKitSessionAdapter>,
+    pub session_service: Arc<SessionService>,
+    /// 网关需要以 Bearer 令牌出示的共享密钥
+    pub token: Arc<String>,
+    /// 向 bridge 各组件发布可热加载的配置
+    pub config_watcher: ConfigWatcher,
+    /// 多个 bridge 实例部署在负载均衡之后时共享的路由状态
+    pub cluster: Option<Arc<ClusterRegistry>>,
+    /// 在命令到达设备前校验网关签名（未启用签名时为 None）
+    #[cfg(feature = "grpc")]
+    pub command_verifier: Option<Arc<echo_shared::CommandVerifier>>,
+    /// 下发命令前检查发起用户对设备的权限
+    #[cfg(feature = "grpc")]
+    pub command_authorizer: Arc<echo_shared::CommandAuthorizer>,
+    /// 将上传的录音转写为会话（未启用音频导入时为 None）
+    pub audio_importer: Option<Arc<AudioImporter>>,
+}
+
+/// 中间件：拒绝没有出示共享 Bearer 令牌的内部 API 请求
+pub async fn require_internal_token(
+    State(state): State<InternalApiState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented {
+        Some(token) if echo_shared::constant_time_eq(token.as_bytes(), state.token.as_bytes()) => Ok(next.run(request).await),
+        Some(_) => {
+            warn!("Internal API: rejected request to {} with invalid token", request.uri().path());
+            Err(EchoError::Authentication("Invalid internal API token".to_string()).into())
+        }
+        None => Err(EchoError::Authentication("Missing internal API token".to_string()).into()),
+    }
+}
+
+/// 集群模式下请求的范围
+#[derive(Debug, Default, Deserialize)]
+pub struct ClusterScope {
+    /// 只返回本实例的结果；实例之间转发的请求会设置该参数
+    #[serde(default)]
+    pub local: bool,
+}
+
+// 强制结束会话的请求体
+#[derive(Debug, Deserialize)]
+pub struct EndSessionRequest {
+    pub reason: Option<String>,
+}
+
+// 帧捕获选项
+#[derive(Debug, Deserialize)]
+pub struct TraceOptions {
+    /// 每个会话保留的帧数，超出时丢弃最早的帧
+    pub capacity: Option<usize>,
+}
+
+impl TraceOptions {
+    fn capacity(&self) -> Result<usize, EchoError> {
+        match self.capacity.unwrap_or(DEFAULT_TRACE_CAPACITY) {
+            capacity @ 1..=MAX_TRACE_CAPACITY => Ok(capacity),
+            _ => Err(EchoError::InvalidInput(format!(
+                "capacity must be between 1 and {}",
+                MAX_TRACE_CAPACITY
+            ))),
+        }
+    }
+}
+
+/// 开始音频捕获的查询参数
+#[derive(Debug, Default, Deserialize)]
+pub struct AudioCaptureOptions {
+    /// 保留的入站音频秒数
+    pub seconds: Option<u64>,
+}
+
+impl AudioCaptureOptions {
+    fn seconds(&self) -> Result<u64, EchoError> {
+        match self.seconds.unwrap_or(DEFAULT_CAPTURE_SECONDS) {
+            seconds @ 1..=MAX_CAPTURE_SECONDS => Ok(seconds),
+            _ => Err(EchoError::InvalidInput(format!(
+                "seconds must be between 1 and {}",
+                MAX_CAPTURE_SECONDS
+            ))),
+        }
+    }
+}
+
+/// GET /internal/devices/online - 当前连接到 bridge 的设备
+///
+/// 每一项附带设备的活跃会话（如果有），调用方据此判断设备当前是否在推流。
+/// 集群模式下列出所有实例的设备，除非指定 `local=true`。
+pub async fn list_online_devices(
+    Query(scope): Query<ClusterScope>,
+    State(state): State<InternalApiState>,
+) -> Json<Vec<BridgeOnlineDevice>> {
+    if scope.local {
+        return Json(online_devices(&state).await);
+    }
+    Json(cluster_online_devices(&state).await)
+}
+
+/// 本实例以及（集群模式下）其他所有实例的在线设备
+pub async fn cluster_online_devices(state: &InternalApiState) -> Vec<BridgeOnlineDevice> {
+    let mut devices = online_devices(state).await;
+    devices.extend(collect_from_peers(state, "/internal/devices/online?local=true").await);
+    devices
+}
+
+/// 已连接的设备及其最新的活跃会话
+pub async fn online_devices(state: &InternalApiState) -> Vec<BridgeOnlineDevice> {
+    let active_sessions = state.session_manager.get_active_session_states().await;
+    state
+        .connection_manager
+        .get_online_devices()
+        .await
+        .into_iter()
+        .map(|(device_id, last_heartbeat)| {
+            // 会话按创建时间排序，最后一个匹配的就是最新的
+            let active_session_id = active_sessions
+                .iter()
+                .rev()
+                .find(|session| session.device_id == device_id)
+                .map(|session| session.session_id.clone());
+            BridgeOnlineDevice {
+                device_id,
+                last_heartbeat,
+                active_session_id,
+            }
+        })
+        .collect()
+}
+
+/// GET /internal/sessions/active - 所有活跃 bridge 会话的实时状态
+///
+/// 集群模式下列出所有实例的会话，除非指定 `local=true`。
+pub async fn list_active_sessions(
+    Query(scope): Query<ClusterScope>,
+    State(state): State<InternalApiState>,
+) -> Json<Vec<BridgeSessionState>> {
+    let mut sessions = state.session_manager.get_active_session_states().await;
+    if !scope.local {
+        sessions.extend(collect_from_peers::<BridgeSessionState>(&state, "/internal/sessions/active?local=true").await);
+        sessions.sort_by_key(|session| session.created_at);
+    }
+    Json(sessions)
+}
+
+/// GET /internal/sessions/{id} - bridge 会话的实时状态
+///
+/// 由其他实例持有的会话从该实例获取。
+pub async fn get_session_state(
+    Path(session_id): Path<String>,
+    Query(scope): Query<ClusterScope>,
+    State(state): State<InternalApiState>,
+) -> Result<Json<BridgeSessionState>, ApiError> {
+    if let Some(session_state) = state.session_manager.get_session_state(&session_id).await {
+        return Ok(Json(session_state));
+    }
+
+    if !scope.local {
+        if let Some((cluster, owner)) = remote_session_owner(&state, &session_id).await {
+            let path = format!("/internal/sessions/{}?local=true", session_id);
+            let response = cluster.forward(&owner, reqwest::Method::GET, &path, None).await?;
+            if response.status().is_success() {
+                let session_state = response
+                    .json()
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Invalid session state from instance {}: {}", owner.instance_id, e))?;
+                return Ok(Json(session_state));
+            }
+        }
+    }
+
+    debug!("Internal API: session {} not tracked by bridge", session_id);
+    Err(EchoError::SessionNotFound(session_id).into())
+}
+
+/// POST /internal/sessions/{id}/end - 强制结束活跃的 bridge 会话
+pub async fn end_session(
+    Path(session_id): Path<SessionId>,
+    Query(scope): Query<ClusterScope>,
+    State(state): State<InternalApiState>,
+    Json(payload): Json<EndSessionRequest>,
+) -> Result<StatusCode, ApiError> {
+    let reason = payload.reason.unwrap_or_else(|| "terminated".to_string());
+    if scope.local {
+        end_local_session(&state, &session_id, &reason).await?;
+    } else {
+        force_end_session(&state, &session_id, &reason).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 强制结束活跃的 bridge 会话
+///
+/// 关闭 EchoKit 会话，保存到目前为止的对话并通知设备。
+/// 由其他实例持有的会话在该实例上结束。HTTP 和 gRPC 内部 API 共用。
+pub async fn force_end_session(
+    state: &InternalApiState,
+    session_id: &SessionId,
+    reason: &str,
+) -> Result<(), EchoError> {
+    match end_local_session(state, session_id, reason).await {
+        Err(EchoError::SessionNotFound(_)) => {}
+        result => return result,
+    }
+
+    let Some((cluster, owner)) = remote_session_owner(state, session_id).await else {
+        return Err(EchoError::SessionNotFound(session_id.to_string()));
+    };
+    info!("Internal API: forwarding end of session {} to instance {}", session_id, owner.instance_id);
+    let path = format!("/internal/sessions/{}/end?local=true", session_id);
+    let body = serde_json::json!({ "reason": reason });
+    let response = cluster.forward(&owner, reqwest::Method::POST, &path, Some(&body)).await?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::NOT_FOUND => Err(EchoError::SessionNotFound(session_id.to_string())),
+        reqwest::StatusCode::CONFLICT => Err(EchoError::Conflict(format!("Session {} is not active", session_id))),
+        status => Err(anyhow::anyhow!("Instance {} failed to end session {}: {}", owner.instance_id, session_id, status).into()),
+    }
+}
+
+/// 会话不在本地时，持有该会话的其他实例
+async fn remote_session_owner(
+    state: &InternalApiState,
+    session_id: &str,
+) -> Option<(Arc<ClusterRegistry>, InstanceInfo)> {
+    let cluster = state.cluster.as_ref()?;
+    match cluster.session_owner(session_id).await {
+        Ok(owner) => owner.map(|owner| (cluster.clone(), owner)),
+        Err(e) => {
+            warn!("Failed to look up the instance holding session {}: {:#}", session_id, e);
+            None
+        }
+    }
+}
+
+/// 查询其他所有实例并合并结果，跳过无法访问的实例
+async fn collect_from_peers<T: DeserializeOwned>(state: &InternalApiState, path: &str) -> Vec<T> {
+    let Some(cluster) = &state.cluster else {
+        return Vec::new();
+    };
+    let peers = match cluster.peers().await {
+        Ok(peers) => peers,
+        Err(e) => {
+            warn!("Failed to list bridge instances: {:#}", e);
+            return Vec::new();
+        }
+    };
+
+    let responses = futures::future::join_all(peers.iter().map(|peer| async move {
+        let response = cluster.forward(peer, reqwest::Method::GET, path, None).await?;
+        let items: Vec<T> = response.error_for_status()?.json().await?;
+        anyhow::Ok(items)
+    }))
+    .await;
+
+    let mut items = Vec::new();
+    for (peer, response) in peers.iter().zip(responses) {
+        match response {
+            Ok(peer_items) => items.extend(peer_items),
+            Err(e) => warn!("Failed to query bridge instance {}: {:#}", peer.instance_id, e),
+        }
+    }
+    items
+}
+
+// 强制结束本实例上的会话
+async fn end_local_session(
+    state: &InternalApiState,
+    session_id: &SessionId,
+    reason: &str,
+) -> Result<(), EchoError> {
+    let session = match state.session_manager.get_session(session_id).await {
+        Some(session) => session,
+        None => {
+            debug!("Internal API: session {} not tracked by bridge", session_id);
+            return Err(EchoError::SessionNotFound(session_id.to_string()));
+        }
+    };
+
+    if session.status != SessionStatus::Active {
+        return Err(EchoError::Conflict(format!("Session {} is not active", session_id)));
+    }
+
+    info!("Internal API: force-ending session {} on device {} (reason: {})", session_id, session.device_id, reason);
+
+    // 结束流程先在内存中标记会话已结束，设备连接循环随之停止转发该会话的音频，
+    // 然后在一个事务中保存会话
+    match state.session_manager
+        .finalize(session_id, SessionStatus::Completed, &state.session_service)
+        .await
+    {
+        Ok(true) => {}
+        // 会话已被其他途径（断开连接、超时）结束
+        Ok(false) => return Err(EchoError::Conflict(format!("Session {} is not active", session_id))),
+        Err(e) => error!("Failed to persist force-ended session {}: {}", session_id, e),
+    }
+
+    if let Err(e) = state.echokit_adapter.close_echokit_session(session_id).await {
+        warn!("Failed to close EchoKit session for {}: {}", session_id, e);
+    }
+    if let Err(e) = state.connection_manager.unbind_session(session_id).await {
+        warn!("Failed to unbind session {}: {}", session_id, e);
+    }
+
+    let notice = serde_json::json!({
+        "event": "session_ended",
+        "session_id": session_id,
+        "reason": reason,
+        "forced": true
+    });
+    if let Err(e) = state.connection_manager.send_text(&session.device_id, &notice.to_string()).await {
+        debug!("Could not notify device {} about ended session: {}", session.device_id, e);
+    }
+
+    Ok(())
+}
+
+/// GET /internal/dead-letters - 本实例上有无法投递消息的设备
+pub async fn list_dead_letters(
+    State(state): State<InternalApiState>,
+) -> Result<Json<Vec<DeadLetterSummary>>, ApiError> {
+    Ok(Json(dead_letter_queue(&state)?.summaries()))
+}
+
+/// GET /internal/devices/{id}/dead-letters - 为设备保留的无法投递的消息
+pub async fn get_dead_letters(
+    Path(device_id): Path<DeviceId>,
+    State(state): State<InternalApiState>,
+) -> Result<Json<DeadLetterQueueInfo>, ApiError> {
+    let info = dead_letter_queue(&state)?
+        .inspect(&device_id)
+        .ok_or_else(|| EchoError::NotFound(format!("No dead letters for device {}", device_id)))?;
+    Ok(Json(info))
+}
+
+/// POST /internal/devices/{id}/dead-letters/flush - 按顺序重新投递设备的死信
+///
+/// 设备必须连接在本实例上。遇到第一个投递失败时停止，未投递的消息继续保留在队列中。
+pub async fn flush_dead_letters(
+    Path(device_id): Path<DeviceId>,
+    State(state): State<InternalApiState>,
+) -> Result<Json<DeadLetterFlushResult>, ApiError> {
+    let result = state.connection_manager.redeliver_dead_letters(&device_id).await?;
+    info!(
+        "Internal API: redelivered {} dead letters to device {} ({} remaining)",
+        result.delivered, device_id, result.remaining
+    );
+    Ok(Json(result))
+}
+
+/// DELETE /internal/devices/{id}/dead-letters - 丢弃设备的死信
+pub async fn discard_dead_letters(
+    Path(device_id): Path<DeviceId>,
+    State(state): State<InternalApiState>,
+) -> Result<StatusCode, ApiError> {
+    let discarded = dead_letter_queue(&state)?.discard(&device_id);
+    if discarded == 0 {
+        return Err(EchoError::NotFound(format!("No dead letters for device {}", device_id)).into());
+    }
+    info!("Internal API: discarded {} dead letters for device {}", discarded, device_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn dead_letter_queue(state: &InternalApiState) -> Result<&Arc<DeadLetterQueue>, EchoError> {
+    state
+        .connection_manager
+        .dead_letters()
+        .ok_or_else(|| EchoError::ServiceUnavailable("Dead-letter queue is disabled".to_string()))
+}
+
+/// POST /admin/reload - 重新加载配置并应用运行时设置
+pub async fn reload_config(
+    State(state): State<InternalApiState>,
+) -> Result<Json<ConfigReloadResult>, ApiError> {
+    let changed = config_reload::reload(&state.config_watcher)
+        .await
+        .map_err(|e| EchoError::InvalidInput(format!("Configuration reload failed: {}", e)))?;
+
+    Ok(Json(ConfigReloadResult {
+        changed: changed.into_iter().map(String::from).collect(),
+        config: state.config_watcher.current(),
+    }))
+}
+
+/// POST /admin/sessions/{id}/trace - 开始捕获会话的每一帧
+///
+/// 重新开始捕获会清空已记录的帧。
+pub async fn start_session_trace(
+    Path(session_id): Path<SessionId>,
+    Query(options): Query<TraceOptions>,
+) -> Result<StatusCode, ApiError> {
+    let capacity = options.capacity()?;
+    trace_capture::capture().enable_session(&session_id, capacity);
+    info!("Internal API: trace capture enabled for session {} (capacity: {})", session_id, capacity);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /admin/sessions/{id}/trace - 停止捕获会话并丢弃已记录的帧
+pub async fn stop_session_trace(Path(session_id): Path<SessionId>) -> Result<StatusCode, ApiError> {
+    if !trace_capture::capture().disable_session(&session_id) {
+        return Err(EchoError::NotFound(format!("No trace for session {}", session_id)).into());
+    }
+    info!("Internal API: trace capture removed for session {}", session_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/sessions/{id}/trace - 以 NDJSON 格式下载捕获的帧
+pub async fn download_session_trace(Path(session_id): Path<SessionId>) -> Result<Response, ApiError> {
+    let export = trace_capture::capture()
+        .export(&session_id)
+        .ok_or_else(|| EchoError::NotFound(format!("No trace for session {}", session_id)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"trace-{}.ndjson\"", session_id),
+            ),
+            (header::HeaderName::from_static("x-trace-dropped-frames"), export.dropped.to_string()),
+            (header::HeaderName::from_static("x-trace-capturing"), export.capturing.to_string()),
+        ],
+        export.ndjson,
+    )
+        .into_response())
+}
+
+/// POST /admin/devices/{id}/trace - 捕获设备的每个会话
+pub async fn start_device_trace(
+    Path(device_id): Path<DeviceId>,
+    Query(options): Query<TraceOptions>,
+) -> Result<StatusCode, ApiError> {
+    let capacity = options.capacity()?;
+    trace_capture::capture().enable_device(&device_id, capacity);
+    info!("Internal API: trace capture enabled for device {} (capacity: {})", device_id, capacity);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /admin/devices/{id}/trace - 停止捕获设备
+///
+/// 已捕获的会话在被删除前仍可下载。
+pub async fn stop_device_trace(Path(device_id): Path<DeviceId>) -> Result<StatusCode, ApiError> {
+    if !trace_capture::capture().disable_device(&device_id) {
+        return Err(EchoError::NotFound(format!("No trace for device {}", device_id)).into());
+    }
+    info!("Internal API: trace capture disabled for device {}", device_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /admin/devices/{id}/audio-capture - 捕获设备接下来若干秒的入站音频
+///
+/// 只捕获到达本 bridge 实例的音频。重新开始捕获会丢弃已记录的音频。
+pub async fn start_audio_capture(
+    Path(device_id): Path<DeviceId>,
+    Query(options): Query<AudioCaptureOptions>,
+) -> Result<StatusCode, ApiError> {
+    let seconds = options.seconds()?;
+    audio_capture::capture().enable(&device_id, seconds);
+    info!("Internal API: audio capture enabled for device {} ({}s)", device_id, seconds);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /admin/devices/{id}/audio-capture - 停止捕获设备并丢弃已记录的音频
+pub async fn discard_audio_capture(Path(device_id): Path<DeviceId>) -> Result<StatusCode, ApiError> {
+    if !audio_capture::capture().discard(&device_id) {
+        return Err(EchoError::NotFound(format!("No audio capture for device {}", device_id)).into());
+    }
+    info!("Internal API: audio capture removed for device {}", device_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /admin/devices/{id}/audio-capture - 以 16kHz 单声道 WAV 下载捕获的音频
+///
+/// 捕获仍在进行时也可以下载（`x-capture-capturing: true`）。
+/// 达到请求的时长或设备静默过久时捕获停止，一小时后过期。
+pub async fn download_audio_capture(Path(device_id): Path<DeviceId>) -> Result<Response, ApiError> {
+    let export = audio_capture::capture()
+        .export(&device_id)
+        .ok_or_else(|| EchoError::NotFound(format!("No audio capture for device {}", device_id)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "audio/wav".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"capture-{}-{}.wav\"",
+                    device_id,
+                    export.requested_at.format("%Y%m%dT%H%M%SZ")
+                ),
+            ),
+            (header::HeaderName::from_static("x-capture-seconds"), export.seconds.to_string()),
+            (header::HeaderName::from_static("x-capture-audio-bytes"), export.audio_bytes.to_string()),
+            (header::HeaderName::from_static("x-capture-frames"), export.frames.to_string()),
+            (header::HeaderName::from_static("x-capture-odd-frames"), export.odd_frames.to_string()),
+            (header::HeaderName::from_static("x-capture-transports"), export.transports.join(",")),
+            (header::HeaderName::from_static("x-capture-capturing"), export.capturing.to_string()),
+        ],
+        export.wav,
+    )
+        .into_response())
+}
+
+/// POST /admin/imports - 将系统外录制的录音导入为已完成的会话
+///
+/// multipart 表单，先是 `device_id` 字段，然后是一个或多个 `file` 部分（WAV 或 Ogg Opus）。
+/// `recorded_at` 字段（RFC 3339）设置下一个文件的开始时间，没有该字段的文件以导入时间为准。
+/// 文件在后台通过设备的 EchoKit 服务器转写；在同一个 bridge 实例上轮询
+/// `GET /admin/imports/{id}` 获取结果。
+pub async fn create_audio_import(
+    State(state): State<InternalApiState>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<AudioImportJob>), ApiError> {
+    let importer = audio_importer(&state)?;
+
+    let mut device_id = None;
+    let mut recorded_at = None;
+    let mut uploads = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        match field.name() {
+            Some("device_id") => device_id = Some(field.text().await.map_err(multipart_error)?),
+            Some("recorded_at") => {
+                let value = field.text().await.map_err(multipart_error)?;
+                let parsed = chrono::DateTime::parse_from_rfc3339(value.trim())
+                    .map_err(|e| EchoError::InvalidInput(format!("Invalid recorded_at {:?}: {}", value, e)))?;
+                recorded_at = Some(parsed.with_timezone(&chrono::Utc));
+            }
+            Some("file") => {
+                let name = field.file_name().unwrap_or("recording").to_string();
+                let data = field.bytes().await.map_err(multipart_error)?;
+                uploads.push(ImportUpload { name, data: data.to_vec(), recorded_at: recorded_at.take() });
+            }
+            other => debug!("Internal API: ignoring import form field {:?}", other),
+        }
+    }
+    let device_id = device_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| EchoError::InvalidInput("device_id is required".to_string()))?;
+
+    let job = importer.start(&device_id, uploads).await?;
+    info!("Internal API: audio import {} accepted for device {}", job.id, device_id);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+/// GET /admin/imports/{id} - 音频导入的进度和结果
+pub async fn get_audio_import(
+    Path(job_id): Path<String>,
+    State(state): State<InternalApiState>,
+) -> Result<Json<AudioImportJob>, ApiError> {
+    audio_importer(&state)?
+        .job(&job_id)
+        .map(Json)
+        .ok_or_else(|| EchoError::NotFound(format!("No audio import {}", job_id)).into())
+}
+
+fn audio_importer(state: &InternalApiState) -> Result<&Arc<AudioImporter>, EchoError> {
+    state
+        .audio_importer
+        .as_ref()
+        .ok_or_else(|| EchoError::ServiceUnavailable("Audio import is disabled".to_string()))
+}
+
+// 超过导入大小限制的请求体返回 413，格式错误的表单返回 400
+fn multipart_error(error: MultipartError) -> EchoError {
+    if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        EchoError::PayloadTooLarge(error.body_text())
+    } else {
+        EchoError::InvalidInput(error.body_text())
+    }
+}
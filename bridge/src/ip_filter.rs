@@ -0,0 +1,80 @@
+//! 来源 IP 过滤
+//!
+//! 内部接口和管理接口（`/internal/*`、`/admin/*`）在校验令牌之前按 `[ip_filter]` 规则检查来源地址，
+//! UDP 音频入口在解析数据包之前检查（见 `UdpAudioServer::with_ip_filter`）。管理员通过 API Gateway
+//! 添加的规则保存在 `ip_filter_rules` 表中，这里定期或配置热加载后重新读取。
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use echo_shared::{IpFilterHandle, RuntimeConfig};
+use sqlx::PgPool;
+use tokio::sync::watch;
+use tracing::{debug, warn};
+
+use crate::telemetry;
+
+/// 每 refresh_seconds 秒或配置热加载后重新读取规则，SQLite 存储时只使用配置中的规则
+pub async fn run(handle: IpFilterHandle, db_pool: Option<PgPool>, mut config: watch::Receiver<RuntimeConfig>) {
+    loop {
+        let settings = config.borrow_and_update().ip_filter.clone();
+        match handle.refresh(&settings, db_pool.as_ref()).await {
+            Ok(errors) => {
+                for e in errors {
+                    warn!("Skipping invalid IP filter rule: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to load IP filter rules: {}", e),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(settings.refresh_seconds.max(1))) => {}
+            changed = config.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 拒绝被过滤规则拦截的 HTTP 请求，状态为当前规则
+pub async fn require_allowed_ip(State(handle): State<IpFilterHandle>, req: Request, next: Next) -> Response {
+    let filter = handle.current();
+    if !filter.is_enabled() {
+        return next.run(req).await;
+    }
+
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip());
+    let ip = if filter.trust_proxy_headers() {
+        forwarded_ip(&req).or(peer)
+    } else {
+        peer
+    };
+    let country = filter
+        .country_header()
+        .and_then(|header| req.headers().get(header))
+        .and_then(|value| value.to_str().ok());
+
+    if let Err(blocked) = filter.check_ip(ip).and_then(|()| filter.check_country(country)) {
+        debug!("Blocked {} {} from {:?}: {:?}", req.method(), req.uri().path(), ip, blocked);
+        telemetry::record_ip_blocked("http", blocked.reason());
+        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    }
+    next.run(req).await
+}
+
+// X-Forwarded-For 中的第一个地址或 X-Real-IP
+fn forwarded_ip(req: &Request) -> Option<IpAddr> {
+    let headers = req.headers();
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|value| value.to_str().ok()))
+        .and_then(|value| value.trim().parse().ok())
+}
@@ -0,0 +1,1471 @@
+mod echokit_client;
+mod echokit;
+mod audio_processor;
+mod udp_server;
+mod udp_batch;
+mod mqtt_client;
+mod websocket;
+mod session_service;
+mod session;
+mod api_handlers;
+mod error;
+mod internal_api;
+mod config_reload;
+mod telemetry;
+mod build_info;
+mod trace_capture;
+mod audio_capture;
+mod audio_import;
+mod announcements;
+mod asr_postprocess;
+mod sip;
+mod http_stream;
+mod mqtt_audio;
+mod alerting;
+mod slow_ops;
+mod channels;
+mod audio_buffer;
+mod gateway_client;
+mod cluster;
+mod circuit;
+mod supervisor;
+mod startup;
+mod outbox_relay;
+mod redis_client;
+mod presence;
+mod hello_store;
+mod ingress;
+mod ip_filter;
+mod command_authz;
+mod tls;
+mod security_headers;
+#[cfg(feature = "grpc")]
+mod grpc_server;
+#[cfg(feature = "grpc")]
+mod grpc_audio;
+
+use anyhow::{Context, Result};
+use echo_shared::{
+    AppConfig, BridgeConfig, ConfigWatcher, RuntimeConfig, EchoKitConfig, AudioFormat, WebSocketMessage,
+    generate_session_id, DeviceStatus, TopicFilter, QoS, WakeReason, ComponentHealth, ReadinessReport
+};
+use echo_shared::utils::now_utc;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use slow_ops::TimedRwLock;
+use tracing::{info, warn, error, debug};
+use axum::{extract::State, http::StatusCode, response::Json, routing::get, Router};
+use std::collections::HashMap;
+
+// Bridge 服务主结构
+struct BridgeService {
+    config: BridgeConfig,
+    runtime_config: tokio::sync::watch::Receiver<RuntimeConfig>,
+    config_watcher: ConfigWatcher,
+    echokit_manager: Arc<echokit_client::EchoKitConnectionManager>,
+    echokit_connection_pool: Arc<echokit::EchoKitConnectionPool>,  // 🎯 新增：连接池
+    audio_processor: Arc<audio_processor::AudioProcessor>,
+    udp_server: Arc<udp_server::UdpAudioServer>,
+    mqtt_client: Arc<mqtt_client::BridgeMqttClient>,
+    active_sessions: Arc<TimedRwLock<std::collections::HashMap<String, SessionInfo>>>,
+    device_audio_output: channels::AudioSender<(String, Vec<u8>)>,
+    // WebSocket 组件
+    connection_manager: Arc<websocket::connection_manager::DeviceConnectionManager>,
+    session_manager: Arc<websocket::session_manager::SessionManager>,
+    heartbeat_monitor: Arc<websocket::heartbeat::HeartbeatMonitor>,
+    flow_controller: Arc<websocket::flow_control::FlowController>,
+    echokit_adapter: Arc<echokit::EchoKitSessionAdapter>,
+    // 数据库持久化
+    session_service: Arc<session_service::SessionService>,
+    // 录音批量导入（未启用时为 None）
+    audio_importer: Option<Arc<audio_import::AudioImporter>>,
+    // HTTP 流式接口（未启用时为 None）
+    http_streams: Option<Arc<http_stream::HttpStreams>>,
+    db_session_manager: Arc<session::SessionManager>,
+    // PostgreSQL 连接池，SQLite 存储时为 None
+    db_pool: Option<sqlx::PgPool>,
+    // 存储层（PostgreSQL 或 SQLite），就绪检查使用
+    storage: Arc<dyn echo_shared::Storage>,
+    // API Gateway HTTP 客户端（共享连接池）
+    gateway_client: Arc<gateway_client::GatewayClient>,
+    // 多副本部署时的共享路由状态
+    cluster: Option<Arc<cluster::ClusterRegistry>>,
+    // 就绪检查中视为关键的依赖
+    critical_dependencies: Arc<Vec<String>>,
+    // 后台任务监管
+    supervisor: Arc<supervisor::Supervisor>,
+    // 共用的 Redis 连接
+    redis: Arc<redis_client::RedisClient>,
+    // WebSocket 一次性连接票据，未启用时为 None
+    ws_tickets: Option<Arc<echo_shared::WsTickets>>,
+    // 外部输入的大小限制
+    limits: Arc<echo_shared::IngressLimitsConfig>,
+    // 设备命令签名验证（未启用时为 None），供内部 gRPC 服务使用
+    #[cfg(feature = "grpc")]
+    command_verifier: Option<Arc<echo_shared::CommandVerifier>>,
+    // 设备命令授权检查，供内部 gRPC 服务使用
+    #[cfg(feature = "grpc")]
+    command_authorizer: Arc<echo_shared::CommandAuthorizer>,
+    // HTTP 响应的安全头
+    security_headers: Arc<echo_shared::SecurityHeaders>,
+    // 内部接口、管理接口和 UDP 入口的来源 IP 过滤
+    ip_filter: echo_shared::IpFilterHandle,
+}
+
+// 会话信息
+#[derive(Debug, Clone)]
+struct SessionInfo {
+    session_id: String,
+    device_id: String,
+    user_id: String,
+    config: EchoKitConfig,
+    start_time: chrono::DateTime<chrono::Utc>,
+    last_activity: chrono::DateTime<chrono::Utc>,
+    is_active: bool,
+}
+
+pub use config_reload::init_tracing;
+pub use echo_shared::LogReloadHandle;
+pub use telemetry::{metrics_buckets, use_metrics_handle};
+
+/// 独立运行 Bridge 服务，初始化日志和 Prometheus 指标记录器
+pub async fn run(app_config: AppConfig) -> Result<()> {
+    // 初始化日志（日志级别可热加载）
+    let log_handle = config_reload::init_tracing(&app_config.log);
+    log_startup();
+
+    // 安装 Prometheus 指标记录器（GET /metrics）
+    telemetry::init().context("Failed to install metrics recorder")?;
+
+    serve(app_config, log_handle, None).await
+}
+
+/// 在 etch-all 单进程模式中运行 Bridge
+///
+/// 日志和指标记录器已由调用方初始化；数据库连接池与 API Gateway 共用，会话实时事件发布到
+/// 进程内事件总线，启动时也不再等待 API Gateway 可用。
+pub async fn run_in_process(app_config: AppConfig, log_handle: LogReloadHandle, in_process: echo_shared::InProcess) -> Result<()> {
+    log_startup();
+    serve(app_config, log_handle, Some(in_process)).await
+}
+
+fn log_startup() {
+    let build = build_info::build_info();
+    info!(
+        "Starting Echo Bridge Service v{} (commit: {})...",
+        build.version,
+        build.git_commit.as_deref().unwrap_or("unknown")
+    );
+}
+
+async fn serve(app_config: AppConfig, log_handle: LogReloadHandle, in_process: Option<echo_shared::InProcess>) -> Result<()> {
+    // 可热加载的配置：SIGHUP 或 POST /admin/reload 触发重新加载
+    let config_watcher = ConfigWatcher::new(&app_config);
+    echo_shared::spawn_log_level_watcher(log_handle, config_watcher.subscribe());
+    let watcher = config_watcher.clone();
+    echo_shared::spawn_sighup_listener(
+        move || {
+            let watcher = watcher.clone();
+            async move {
+                let _ = echo_shared::config_reload::reload(&watcher).await;
+            }
+        },
+        std::future::pending(),
+    );
+
+    // 模拟 EchoKit Server，仅用于 CI 和本地调试
+    #[cfg(feature = "mock-echokit")]
+    let _mock_echokit = match std::env::var("ECHOKIT_MOCK_ADDR") {
+        Ok(addr) => {
+            let mock = echokit::mock_server::MockEchoKitServer::bind(
+                addr.parse().context("Invalid ECHOKIT_MOCK_ADDR")?,
+                echokit::mock_server::MockScript::default(),
+            )
+            .await?;
+            warn!("Mock EchoKit server listening on {}", mock.url());
+            Some(mock)
+        }
+        Err(_) => None,
+    };
+
+    let config = app_config.bridge.clone();
+    info!("Bridge configuration: {:?}", config);
+    info!(
+        "Tokio runtime: {} worker threads, max {} blocking threads",
+        tokio::runtime::Handle::current().metrics().num_workers(),
+        config.runtime.max_blocking_threads
+    );
+    slow_ops::configure(config.slow_query_threshold_ms, config.slow_lock_threshold_ms);
+    config.check_internal_token()?;
+    if config.uses_dev_internal_token() {
+        warn!("bridge.allow_dev_internal_token is enabled, internal API is using the development token");
+    }
+
+    // 初始化存储，按 bridge.startup.database 策略等待数据库可用；单进程模式下使用共享连接
+    let storage = match &in_process {
+        Some(in_process) => in_process.storage.clone(),
+        None => connect_database(&app_config).await?,
+    };
+    // 以下可选功能只有 PostgreSQL 实现，SQLite 存储下不启动
+    let db_pool = storage.postgres_pool().cloned();
+    if db_pool.is_none() {
+        let unsupported: Vec<&str> = [
+            ("bridge.sip", config.sip.enabled),
+            ("bridge.http_stream", config.http_stream.enabled),
+            ("bridge.mqtt_audio", config.mqtt_audio.enabled),
+            ("bridge.asr_postprocessing", config.asr_postprocessing.enabled),
+            ("bridge.audio_import", config.audio_import.enabled),
+            ("bridge.outbox", config.outbox.enabled),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+        if !unsupported.is_empty() {
+            warn!("SQLite storage does not support {}; these features are disabled", unsupported.join(", "));
+        }
+    }
+
+    // 集群注册表、设备在线状态和缓存失效通知共用一条 Redis 连接和一个熔断器，首次使用时才连接
+    let redis = redis_client::RedisClient::new(&app_config.redis.url, &config.circuit_breaker)?;
+
+    // 创建 SessionService
+    // PostgreSQL 熔断器和写操作重试策略，SessionService 和 SessionManager 共用
+    let db_breaker = circuit::breaker("database", &config.circuit_breaker);
+    let db_retry = echo_shared::RetryPolicy::from_config(&app_config.database.retry);
+    // 转写和回复的脱敏规则，写入数据库时按会话所属用户的设置生效，日志脱敏不区分用户
+    let redactor = Arc::new(echo_shared::Redactor::from_config(&app_config.redaction)?);
+    if app_config.redaction.logs {
+        echo_shared::install_log_redactor(redactor.clone());
+        info!("Transcript redaction enabled for logs");
+    }
+    // 转写和回复的静态加密，主密钥由密钥提供者解析
+    let encryptor = echo_shared::FieldEncryptor::from_config(&app_config.encryption)?.map(Arc::new);
+    // 下发给设备的命令在转发前验证 API Gateway 的签名
+    let command_verifier = echo_shared::CommandVerifier::from_config(&app_config.command_signing)?.map(Arc::new);
+    if command_verifier.is_some() {
+        info!("Device command signature verification enabled");
+    }
+    let command_authorizer = Arc::new(echo_shared::CommandAuthorizer::new(
+        app_config.command_authorization.clone(),
+        storage.clone(),
+    ));
+    if let Some(encryptor) = &encryptor {
+        info!("Transcript encryption at rest enabled (master key {})", encryptor.current_key_id());
+    }
+    let mut session_service = session_service::SessionService::new(storage.clone())
+        .with_circuit_breaker(db_breaker.clone())
+        .with_retry_policy(db_retry)
+        .with_cache_invalidation(redis.clone())
+        .with_redactor(redactor.clone());
+    if let Some(encryptor) = &encryptor {
+        session_service = session_service.with_encryption(encryptor.clone());
+    }
+    if let Some(limits) = echo_shared::SessionLimits::from_config(&app_config.redis.session_limits) {
+        info!(
+            "Session limits enabled ({} per user per day, {} concurrent per device)",
+            limits.config().sessions_per_user_per_day,
+            limits.config().concurrent_sessions_per_device
+        );
+        session_service = session_service.with_session_limits(Arc::new(limits));
+    }
+    let session_service = Arc::new(session_service);
+    info!("SessionService initialized");
+
+    // ASR 文本后处理（可选）：识别结果保存和转发之前按设备所属账号的处理链处理
+    let asr_postprocessing = db_pool.clone().filter(|_| config.asr_postprocessing.enabled).map(|db_pool| {
+        info!("ASR post-processing enabled ({} owner-specific pipelines)", config.asr_postprocessing.owners.len());
+        Arc::new(asr_postprocess::AsrPostProcessing::new(&config.asr_postprocessing, Arc::new(db_pool)))
+    });
+
+    // 录音批量导入：上传的录音经 EchoKit 识别后写入会话历史
+    let audio_importer = db_pool.clone().filter(|_| config.audio_import.enabled).map(|db_pool| {
+        let mut importer =
+            audio_import::AudioImporter::new(config.audio_import.clone(), Arc::new(db_pool), session_service.clone())
+                .with_ingress_limits(app_config.limits.clone());
+        if let Some(asr_postprocessing) = &asr_postprocessing {
+            importer = importer.with_asr_postprocessing(asr_postprocessing.clone());
+        }
+        Arc::new(importer)
+    });
+
+    // 创建数据库支持的 SessionManager
+    let mut db_session_manager = session::SessionManager::new(storage.clone())
+        .with_circuit_breaker(db_breaker)
+        .with_retry_policy(db_retry)
+        .with_cache_invalidation(redis.clone())
+        .with_redactor(redactor);
+    if let Some(encryptor) = &encryptor {
+        db_session_manager = db_session_manager.with_encryption(encryptor.clone());
+    }
+    let db_session_manager = Arc::new(db_session_manager);
+    info!("Database-backed SessionManager initialized");
+
+    // 创建设备音频输出通道
+    let (audio_output_tx, audio_output_rx) = channels::audio_channel("device_audio_output", config.audio_channel_capacity);
+
+    // MQTT 配置（每个连接使用独立生成的客户端 ID）
+    let mqtt_config = app_config.mqtt.clone();
+
+    // 创建音频回调通道（用于 EchoKit -> Adapter -> Device 的音频路由）
+    let (audio_callback_tx, audio_callback_rx) = channels::audio_channel("echokit_audio", config.audio_channel_capacity);
+
+    // 创建 ASR 回调通道（用于 EchoKit -> Adapter -> Device 的 ASR 结果路由）
+    let (asr_callback_tx, asr_callback_rx) = channels::control_channel("echokit_asr", config.control_channel_capacity);
+
+    // 创建 AI 回复回调通道（用于 EchoKit -> Adapter -> SessionManager 的 AI 回复路由）
+    let (response_callback_tx, response_callback_rx) = channels::control_channel("echokit_response", config.control_channel_capacity);
+
+    // 创建原始消息回调通道（用于直接转发 MessagePack 数据）
+    let (raw_message_tx, raw_message_rx) = channels::control_channel("echokit_raw_message", config.control_channel_capacity);
+
+    // 🎯 创建 EchoKit 连接池（支持多个 EchoKit Server）
+    info!("🔧 Creating EchoKit Connection Pool...");
+    let mut echokit_connection_pool = echokit::EchoKitConnectionPool::new(
+        storage.clone(),
+        audio_callback_tx.clone(),
+        asr_callback_tx.clone(),
+        response_callback_tx.clone(),
+        raw_message_tx.clone(),
+        config_watcher.subscribe(),
+    );
+    // 欢迎语保存到 Redis，新启动的副本直接回放
+    if config.hello_persistence.enabled {
+        echokit_connection_pool =
+            echokit_connection_pool.with_hello_store(hello_store::HelloStore::new(config.hello_persistence.clone(), redis.clone()));
+    }
+    let echokit_connection_pool = echokit_connection_pool.with_ingress_limits(app_config.limits.clone());
+    let echokit_connection_pool = Arc::new(echokit_connection_pool);
+
+    // ❌ 已移除预连接逻辑：按照新设计，仅在设备首次连接时才创建 EchoKit 连接
+    // 使用懒加载模式，根据每个设备注册时指定的 echokit_server_url 按需连接
+    info!("📋 EchoKit connection pool initialized (lazy loading mode)");
+
+    // TODO: 重构 AudioProcessor 以移除对单一 EchoKit client 的依赖
+    // 临时方案：创建一个 placeholder manager 用于 AudioProcessor
+    // ⚠️ 重要：必须配置回调，因为 EchoKitSessionAdapter 会使用这个 client 处理消息
+    let placeholder_client = echokit_client::EchoKitClient::builder(config.echokit_websocket_url.clone())
+        .audio_callback(audio_callback_tx.clone())
+        .asr_callback(asr_callback_tx.clone())
+        .response_callback(response_callback_tx.clone())
+        .raw_message_callback(raw_message_tx.clone())
+        .runtime_config(config_watcher.subscribe())
+        .build()?;
+    let placeholder_manager = echokit_client::EchoKitConnectionManager::new(placeholder_client);
+
+    // 创建音频处理器
+    let audio_processor = Arc::new(audio_processor::AudioProcessor::new(
+        placeholder_manager.get_client(),
+        audio_output_tx.clone(),
+    ));
+
+    // 多副本部署：设备和会话归属保存在 Redis
+    let cluster = if config.cluster.enabled {
+        let registry = cluster::ClusterRegistry::new(&config, redis.clone())?;
+        startup::wait_for("redis", &config.startup.redis, || registry.register()).await?;
+        Some(registry)
+    } else {
+        None
+    };
+
+    // 设备实时在线状态写入 Redis，Redis 不可用时只记录警告
+    let presence = config.presence.enabled.then(|| {
+        let instance_id = match &cluster {
+            Some(cluster) => cluster.instance_id().to_string(),
+            None => cluster::instance_id(&config.cluster),
+        };
+        presence::PresenceTracker::new(config.presence.clone(), instance_id, redis.clone())
+    });
+
+    // MQTT 和 EchoKit 在后台按需连接，这里只检查能否连通
+    startup::wait_for("mqtt", &config.startup.mqtt, || {
+        startup::probe_tcp(&mqtt_config.broker, mqtt_config.port)
+    })
+    .await?;
+    startup::wait_for("echokit", &config.startup.echokit, || {
+        startup::probe_url(&config.echokit_websocket_url)
+    })
+    .await?;
+
+    // 来源 IP 过滤：先按配置文件中的规则生效，数据库中的规则由刷新任务加载
+    let ip_filter = echo_shared::IpFilterHandle::new(echo_shared::IpFilter::new(&app_config.ip_filter, &[]).0);
+
+    // 创建 UDP 服务器
+    let mut udp_server = udp_server::UdpAudioServer::new(
+        &config.udp_bind_address,
+        config.udp.clone(),
+        audio_processor.clone(),
+    ).await?
+    .with_max_datagram_bytes(app_config.limits.max_udp_datagram_bytes)
+    .with_ip_filter(ip_filter.clone());
+    if let Some(cluster) = &cluster {
+        udp_server = udp_server.with_cluster(cluster.clone());
+    }
+    let udp_server = Arc::new(udp_server);
+
+    // 后台循环由监管器启动，异常退出后按策略重启
+    let supervisor = Arc::new(supervisor::Supervisor::new(
+        supervisor::RESTART_BACKOFF,
+        supervisor::MAX_RESTART_BACKOFF,
+    ));
+
+    // 创建 MQTT 客户端
+    let (mqtt_client, mqtt_event_loop) = mqtt_client::BridgeMqttClient::new(mqtt_config)?;
+    let mqtt_client_arc = Arc::new(mqtt_client);
+
+    // 驱动发布客户端的事件循环，否则通过 mqtt_client 发布的消息不会真正发出
+    let mqtt_event_loop = Arc::new(tokio::sync::Mutex::new(mqtt_event_loop));
+    let mqtt_publisher = mqtt_client_arc.clone();
+    supervisor.spawn("mqtt_publisher", supervisor::RestartPolicy::Always, move || {
+        let mqtt_event_loop = mqtt_event_loop.clone();
+        let mqtt_publisher = mqtt_publisher.clone();
+        async move {
+            let mut mqtt_event_loop = mqtt_event_loop.lock().await;
+            loop {
+                match mqtt_event_loop.poll().await {
+                    Ok(rumqttc::Event::Incoming(rumqttc::Packet::ConnAck(_))) => {
+                        mqtt_publisher.set_connected(true).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        mqtt_publisher.set_connected(false).await;
+                        warn!("MQTT publisher connection error: {}, retrying in 5s", e);
+                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        }
+    });
+
+    // 会话实时事件（阶段变化、ASR 结果）发布到 MQTT，由 API Gateway 推送给前端；
+    // 单进程模式下直接交给进程内事件总线
+    let (session_event_tx, session_event_rx) = mpsc::unbounded_channel::<echo_shared::mqtt::MqttMessage>();
+    let session_event_rx = Arc::new(tokio::sync::Mutex::new(session_event_rx));
+    let session_event_publisher = mqtt_client_arc.clone();
+    let session_event_bus = in_process.as_ref().map(|in_process| in_process.events.clone());
+    supervisor.spawn("session_event_publisher", supervisor::RestartPolicy::OnPanic, move || {
+        let session_event_rx = session_event_rx.clone();
+        let session_event_publisher = session_event_publisher.clone();
+        let session_event_bus = session_event_bus.clone();
+        async move {
+            let mut session_event_rx = session_event_rx.lock().await;
+            while let Some(message) = session_event_rx.recv().await {
+                if let Some(bus) = &session_event_bus {
+                    bus.publish(message.payload);
+                } else if let Err(e) = session_event_publisher.publish(message).await {
+                    debug!("Failed to publish session event: {}", e);
+                }
+            }
+        }
+    });
+
+    // 创建 WebSocket 组件
+    let mut connection_manager = websocket::connection_manager::DeviceConnectionManager::new();
+    if config.dead_letter.enabled {
+        connection_manager = connection_manager
+            .with_dead_letters(Arc::new(websocket::dead_letter::DeadLetterQueue::new(config.dead_letter.clone())));
+    }
+    let mut session_manager = websocket::session_manager::SessionManager::new().with_event_sink(session_event_tx);
+    if let Some(cluster) = &cluster {
+        connection_manager = connection_manager.with_cluster(cluster.clone());
+        session_manager = session_manager.with_cluster(cluster.clone());
+    }
+    if let Some(presence) = &presence {
+        connection_manager = connection_manager.with_presence(presence.clone());
+        session_manager = session_manager.with_presence(presence.clone());
+    }
+    let connection_manager = Arc::new(connection_manager);
+    let session_manager = Arc::new(session_manager);
+
+    // 创建 EchoKit 适配器（带音频、ASR、AI回复 和原始消息接收器）
+    // TODO: EchoKitSessionAdapter 也需要重构以移除对单一 client 的依赖
+    let mut echokit_adapter = echokit::EchoKitSessionAdapter::new(
+        placeholder_manager.get_client(),
+        connection_manager.clone(),
+        session_manager.clone(), // 🔧 传入 session_manager 用于保存 ASR 文本和 AI 回复
+        audio_callback_rx,
+        asr_callback_rx,
+        response_callback_rx,
+        raw_message_rx,
+    ).with_circuit_breaker(circuit::breaker("echokit", &config.circuit_breaker));
+    if let Some(asr_postprocessing) = &asr_postprocessing {
+        echokit_adapter = echokit_adapter.with_asr_postprocessing(asr_postprocessing.clone());
+    }
+    if config.audio_spool.enabled {
+        let mut audio_spool = websocket::audio_spool::AudioSpool::new(config.audio_spool.clone());
+        if let Some(encryptor) = &encryptor {
+            audio_spool = audio_spool.with_encryption(encryptor.clone());
+        }
+        let audio_spool = Arc::new(audio_spool);
+        let expiry_spool = audio_spool.clone();
+        let expiry_session_manager = session_manager.clone();
+        supervisor.spawn("audio_spool_expiry", supervisor::RestartPolicy::Always, move || {
+            expiry_spool.clone().run_expiry(expiry_session_manager.clone())
+        });
+        echokit_adapter = echokit_adapter.with_audio_spool(audio_spool);
+    }
+    let echokit_adapter = Arc::new(echokit_adapter);
+
+    // 启动 EchoKit 音频接收器
+    let echokit_adapter_clone = echokit_adapter.clone();
+    supervisor.spawn("echokit_audio_receiver", supervisor::RestartPolicy::OnPanic, move || {
+        echokit_adapter_clone.clone().start_audio_receiver()
+    });
+
+    // 启动 EchoKit ASR 接收器
+    let echokit_adapter_clone = echokit_adapter.clone();
+    supervisor.spawn("echokit_asr_receiver", supervisor::RestartPolicy::OnPanic, move || {
+        echokit_adapter_clone.clone().start_asr_receiver()
+    });
+
+    // 启动 EchoKit AI 回复接收器
+    let echokit_adapter_clone = echokit_adapter.clone();
+    supervisor.spawn("echokit_response_receiver", supervisor::RestartPolicy::OnPanic, move || {
+        echokit_adapter_clone.clone().start_response_receiver()
+    });
+
+    // 启动 EchoKit 原始消息接收器
+    let echokit_adapter_clone = echokit_adapter.clone();
+    supervisor.spawn("echokit_raw_message_receiver", supervisor::RestartPolicy::OnPanic, move || {
+        echokit_adapter_clone.clone().start_raw_message_receiver()
+    });
+
+    // SIP 电话接入：接听来电并把通话接入会话流程
+    let sip_gateway = if let (Some(db_pool), true) = (&db_pool, config.sip.enabled) {
+        let mut gateway = sip::SipGateway::bind(
+            config.sip.clone(),
+            Arc::new(db_pool.clone()),
+            session_service.clone(),
+            session_manager.clone(),
+        )
+        .await?
+        .with_ingress_limits(app_config.limits.clone())
+        .with_ip_filter(ip_filter.clone());
+        if let Some(asr_postprocessing) = &asr_postprocessing {
+            gateway = gateway.with_asr_postprocessing(asr_postprocessing.clone());
+        }
+        let gateway = Arc::new(gateway);
+        let sip_task = gateway.clone();
+        supervisor.spawn("sip_gateway", supervisor::RestartPolicy::OnPanic, move || sip_task.clone().run());
+        Some(gateway)
+    } else {
+        None
+    };
+
+    // 设备接入（WebSocket、gRPC、HTTP 流式接口）校验的一次性连接票据
+    let ws_tickets = echo_shared::WsTickets::from_config(&app_config.redis.ws_tickets).map(Arc::new);
+
+    // HTTP 流式接口：不支持 WebSocket 的客户端以 chunked 请求上传 PCM、接收 TTS 音频
+    let http_streams = db_pool.clone().filter(|_| config.http_stream.enabled).map(|db_pool| {
+        let mut streams = http_stream::HttpStreams::new(
+            config.http_stream.clone(),
+            Arc::new(db_pool),
+            db_session_manager.clone(),
+            session_manager.clone(),
+            session_service.clone(),
+        )
+        .with_ingress_limits(app_config.limits.clone());
+        if let Some(asr_postprocessing) = &asr_postprocessing {
+            streams = streams.with_asr_postprocessing(asr_postprocessing.clone());
+        }
+        if let Some(ws_tickets) = &ws_tickets {
+            streams = streams.with_tickets(ws_tickets.clone(), redis.clone());
+        }
+        Arc::new(streams)
+    });
+
+    // MQTT 音频接入：只能使用 MQTT 的受限设备分块发布音频，重组后接入会话流程
+    let mqtt_audio = if let (Some(db_pool), true) = (&db_pool, config.mqtt_audio.enabled) {
+        let mut gateway = mqtt_audio::MqttAudioGateway::new(
+            config.mqtt_audio.clone(),
+            &app_config.mqtt,
+            Arc::new(db_pool.clone()),
+            session_service.clone(),
+            session_manager.clone(),
+        )
+        .with_ingress_limits(app_config.limits.clone());
+        if let Some(asr_postprocessing) = &asr_postprocessing {
+            gateway = gateway.with_asr_postprocessing(asr_postprocessing.clone());
+        }
+        let gateway = Arc::new(gateway);
+        let mqtt_audio_task = gateway.clone();
+        supervisor.spawn("mqtt_audio", supervisor::RestartPolicy::OnPanic, move || mqtt_audio_task.clone().run());
+        Some(gateway)
+    } else {
+        None
+    };
+
+    // 创建心跳监控
+    let heartbeat_config = websocket::heartbeat::HeartbeatConfig::default();
+    let heartbeat_monitor = Arc::new(
+        websocket::heartbeat::HeartbeatMonitor::new(
+            connection_manager.clone(),
+            session_manager.clone(),
+            heartbeat_config,
+        )
+        .with_session_service(session_service.clone()),
+    );
+
+    // 创建流控管理器
+    let flow_config = websocket::flow_control::FlowControlConfig::default();
+    let flow_controller = Arc::new(websocket::flow_control::FlowController::new(flow_config));
+
+    // API Gateway HTTP 客户端，服务间调用使用内部令牌鉴权
+    let gateway_client = Arc::new(gateway_client::GatewayClient::new(&config.gateway, config.internal_token.clone())?);
+    info!("API Gateway client: {}", config.gateway.base_url);
+    // 单进程模式下 API Gateway 与 Bridge 同时启动，不需要等待
+    if in_process.is_none() {
+        startup::wait_for("gateway", &config.startup.gateway, || gateway_client.health()).await?;
+    }
+
+    // 定期刷新本副本、本地设备和会话在 Redis 中的记录
+    if let Some(cluster) = &cluster {
+        cluster.spawn_heartbeat(cluster::LocalState {
+            connection_manager: connection_manager.clone(),
+            session_manager: session_manager.clone(),
+            udp_server: udp_server.clone(),
+        });
+    }
+
+    // 定期读取管理接口添加的 IP 过滤规则
+    {
+        let ip_filter = ip_filter.clone();
+        let db_pool = db_pool.clone();
+        let runtime_config = config_watcher.subscribe();
+        supervisor.spawn("ip_filter_refresh", supervisor::RestartPolicy::Always, move || {
+            ip_filter::run(ip_filter.clone(), db_pool.clone(), runtime_config.clone())
+        });
+    }
+    // 定期刷新在线设备的最近心跳
+    if let Some(presence) = &presence {
+        let presence = presence.clone();
+        let connection_manager = connection_manager.clone();
+        supervisor.spawn("device_presence", supervisor::RestartPolicy::Always, move || {
+            presence.clone().run(connection_manager.clone())
+        });
+    }
+
+    // 创建 Bridge 服务
+    let bridge_service = BridgeService {
+        config: config.clone(),
+        runtime_config: config_watcher.subscribe(),
+        config_watcher: config_watcher.clone(),
+        echokit_manager: Arc::new(placeholder_manager),  // TODO: 移除此字段，完全使用连接池
+        echokit_connection_pool: echokit_connection_pool.clone(),  // 🎯 连接池（主要使用）
+        audio_processor: audio_processor.clone(),
+        udp_server: udp_server.clone(),
+        mqtt_client: mqtt_client_arc.clone(),
+        active_sessions: Arc::new(TimedRwLock::new("active_sessions", std::collections::HashMap::new())),
+        device_audio_output: audio_output_tx,
+        connection_manager: connection_manager.clone(),
+        session_manager: session_manager.clone(),
+        heartbeat_monitor: heartbeat_monitor.clone(),
+        flow_controller: flow_controller.clone(),
+        echokit_adapter: echokit_adapter.clone(),
+        session_service: session_service.clone(),
+        audio_importer,
+        http_streams,
+        db_session_manager: db_session_manager.clone(),
+        db_pool: db_pool.clone(),
+        storage: storage.clone(),
+        gateway_client,
+        cluster: cluster.clone(),
+        critical_dependencies: Arc::new(app_config.health.bridge_critical.clone()),
+        supervisor: supervisor.clone(),
+        redis: redis.clone(),
+        ws_tickets,
+        limits: Arc::new(app_config.limits.clone()),
+        #[cfg(feature = "grpc")]
+        command_verifier: command_verifier.clone(),
+        #[cfg(feature = "grpc")]
+        command_authorizer: command_authorizer.clone(),
+        security_headers: Arc::new(
+            echo_shared::SecurityHeaders::new(&app_config.security_headers).with_tls(app_config.bridge.tls.enabled),
+        ),
+        ip_filter,
+    };
+
+    // 启动告警检查
+    alerting::spawn(
+        app_config.alerts.clone(),
+        alerting::AlertSources {
+            echokit_connection_pool: echokit_connection_pool.clone(),
+            db_pool: db_pool.clone(),
+            mqtt_client: mqtt_client_arc.clone(),
+        },
+    );
+
+    // 采样获取数据库连接的等待时间，启用自动扩容时等待过长会提前建立连接
+    if let Some(db_pool) = &db_pool {
+        let pool_monitor = Arc::new(echo_shared::PoolMonitor::new(db_pool.clone(), &app_config.database.pool).with_listener(
+            |stats| {
+                if let Some(error) = &stats.acquire_error {
+                    warn!("Failed to acquire a database connection after {:?}: {} ({})", stats.acquire_wait, error, stats);
+                } else if stats.grown > 0 {
+                    info!("Database pool grew by {} connections after waiting {:?} ({})", stats.grown, stats.acquire_wait, stats);
+                }
+                telemetry::record_db_pool_sample(stats);
+            },
+        ));
+        supervisor.spawn("db_pool_monitor", supervisor::RestartPolicy::Always, move || {
+            let pool_monitor = pool_monitor.clone();
+            async move {
+                let mut interval = tokio::time::interval(pool_monitor.interval());
+                loop {
+                    interval.tick().await;
+                    pool_monitor.sample().await;
+                }
+            }
+        });
+    }
+
+    // 启动 MQTT 事件循环
+    // 由于 start() 方法需要消费 self，我们需要创建一个新的客户端实例来运行事件循环
+    // 这个实例与第一个客户端共享同一个 broker 连接配置
+    let mqtt_config_for_event_loop = echo_shared::MqttConfig {
+        client_id: None,
+        ..app_config.mqtt.clone()
+    };
+    let (mqtt_client_for_event_loop, mqtt_event_loop_for_start) =
+        mqtt_client::BridgeMqttClient::new(mqtt_config_for_event_loop)?;
+    // 语音播报（可选）：播报命令由连接设备的副本合成语音后播放
+    let announcements = if config.announcements.enabled {
+        Some(Arc::new(announcements::AnnouncementPlayer::new(config.announcements.clone(), connection_manager.clone())?))
+    } else {
+        None
+    };
+    let mqtt_client_for_event_loop = mqtt_client_for_event_loop.with_command_delivery(
+        connection_manager.clone(),
+        command_verifier,
+        command_authorizer,
+        announcements,
+    );
+
+    info!("Starting MQTT client event loop...");
+    tokio::spawn(async move {
+        if let Err(e) = mqtt_client_for_event_loop.start(mqtt_event_loop_for_start).await {
+            error!("MQTT client event loop error: {}", e);
+        }
+    });
+
+    // 启动各个组件
+    bridge_service.start(audio_output_rx).await?;
+
+    // 打印服务端口信息
+    let websocket_port = config.websocket_port;
+
+    info!("========================================");
+    info!("Echo Bridge Service started successfully!");
+    info!("========================================");
+    info!("UDP Audio Server:    {}", config.udp_bind_address);
+    info!("HTTP/WebSocket:      0.0.0.0:{}", websocket_port);
+    info!("  - Health check:    http://localhost:{}/health", websocket_port);
+    info!("  - Metrics:         http://localhost:{}/metrics", websocket_port);
+    info!("  - WebSocket:       ws://localhost:{}/ws/audio", websocket_port);
+    info!("  - Session API:     http://localhost:{}/api/sessions", websocket_port);
+    info!("  - Web UI:          http://localhost:{}/bridge_webui.html", websocket_port);
+    info!("MQTT Broker:         {}:{}", app_config.mqtt.broker, app_config.mqtt.port);
+    info!("EchoKit WebSocket:   {}", config.echokit_websocket_url);
+    if let Some(sip_gateway) = &sip_gateway {
+        info!("SIP Gateway:         {} (udp)", sip_gateway.local_addr());
+    }
+    if let Some(mqtt_audio) = &mqtt_audio {
+        info!("MQTT Audio:          {} (leader only)", mqtt_audio.subscription());
+    }
+    if cfg!(feature = "grpc") && config.grpc_audio.enabled {
+        info!("gRPC Audio Stream:   0.0.0.0:{}", config.grpc_audio.port);
+    }
+    if let Some(cluster) = &cluster {
+        info!("Cluster instance:    {}", cluster.instance_id());
+    }
+    info!("========================================");
+
+    // 保持服务运行
+    tokio::signal::ctrl_c().await?;
+    info!("Received shutdown signal, stopping Bridge Service...");
+    supervisor.shutdown();
+    if let Some(cluster) = &cluster {
+        cluster.deregister().await;
+    }
+    if let Some(presence) = &presence {
+        presence.release_all().await;
+    }
+
+    Ok(())
+}
+
+// 按 database.url 的协议选择存储后端；PostgreSQL 按 bridge.startup.database 策略连接，
+// 可选依赖不可用时首次查询再建立连接
+async fn connect_database(app_config: &AppConfig) -> Result<Arc<dyn echo_shared::Storage>> {
+    info!("Initializing database connection...");
+    if echo_shared::StorageBackend::from_url(&app_config.database.url)? == echo_shared::StorageBackend::Sqlite {
+        let storage = echo_shared::connect_storage(&app_config.database).await?;
+        info!("SQLite storage opened");
+        return Ok(storage);
+    }
+    let db_pool_options = echo_shared::pool_options(&app_config.database);
+    let db_pool = match startup::wait_for("database", &app_config.bridge.startup.database, || async {
+        db_pool_options
+            .clone()
+            .connect(&app_config.database.url)
+            .await
+            .with_context(|| "Failed to connect to database")
+    })
+    .await?
+    {
+        Some(pool) => {
+            info!("Database connected successfully");
+            pool
+        }
+        None => db_pool_options
+            .connect_lazy(&app_config.database.url)
+            .with_context(|| "Invalid database URL")?,
+    };
+    Ok(Arc::new(echo_shared::PostgresStorage::new(db_pool)))
+}
+
+impl BridgeService {
+    // 启动 Bridge 服务
+    async fn start(
+        &self,
+        audio_output_rx: channels::AudioReceiver<(String, Vec<u8>)>,
+    ) -> Result<()> {
+        // MQTT 客户端已在 main 中启动
+
+        // ❌ 已移除：不再预启动 EchoKit 连接，使用懒加载模式
+        // EchoKit 连接将在设备首次连接时按需创建（通过 echokit_connection_pool）
+
+        // 启动 UDP 服务器
+        self.udp_server.start().await
+            .with_context(|| "Failed to start UDP server")?;
+
+        // 启动音频输出处理器
+        self.start_audio_output_handler(audio_output_rx).await?;
+
+        // 启动会话超时检查
+        self.start_session_timeout_check().await?;
+
+        // 转发 outbox 中的事件到 MQTT
+        if let (true, Some(db_pool)) = (self.config.outbox.enabled, self.db_pool.clone()) {
+            let mqtt_client = self.mqtt_client.clone();
+            let outbox_config = self.config.outbox.clone();
+            self.supervisor.spawn("outbox_relay", supervisor::RestartPolicy::Always, move || {
+                outbox_relay::run(db_pool.clone(), mqtt_client.clone(), outbox_config.clone())
+            });
+        }
+
+        // 启动心跳监控
+        let heartbeat_monitor = self.heartbeat_monitor.clone();
+        self.supervisor.spawn("heartbeat_monitor", supervisor::RestartPolicy::Always, move || {
+            heartbeat_monitor.clone().start()
+        });
+
+        // 启动流控管理器
+        let flow_controller = self.flow_controller.clone();
+        self.supervisor.spawn("flow_controller", supervisor::RestartPolicy::Always, move || {
+            flow_controller.clone().start()
+        });
+
+        // 启动会话清理任务（每 5 分钟清理一次已完成的会话）
+        let db_session_manager = self.db_session_manager.clone();
+        self.supervisor.spawn("session_cleanup", supervisor::RestartPolicy::Always, move || {
+            let db_session_manager = db_session_manager.clone();
+            async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300)); // 5 minutes
+                loop {
+                    interval.tick().await;
+                    db_session_manager.cleanup_completed_sessions().await;
+                }
+            }
+        });
+
+        // 启动健康检查服务
+        self.start_health_check_service().await?;
+
+        info!("All Bridge Service components started successfully");
+        Ok(())
+    }
+
+    // 启动音频输出处理器
+    async fn start_audio_output_handler(&self, audio_output_rx: channels::AudioReceiver<(String, Vec<u8>)>) -> Result<()> {
+        let udp_server = self.udp_server.clone();
+        let audio_output_rx = Arc::new(tokio::sync::Mutex::new(audio_output_rx));
+
+        self.supervisor.spawn("audio_output", supervisor::RestartPolicy::OnPanic, move || {
+            let udp_server = udp_server.clone();
+            let audio_output_rx = audio_output_rx.clone();
+            async move {
+                let mut audio_output_rx = audio_output_rx.lock().await;
+                while let Some((device_id, audio_data)) = audio_output_rx.recv().await {
+                    if let Err(e) = udp_server.send_to_device(&device_id, audio_data).await {
+                        error!("Failed to send audio output to device {}: {}", device_id, e);
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    // 启动会话超时检查
+    async fn start_session_timeout_check(&self) -> Result<()> {
+        let active_sessions = self.active_sessions.clone();
+        let audio_processor = self.audio_processor.clone();
+        let session_service = self.session_service.clone();
+        let runtime_config = self.runtime_config.clone();
+
+        self.supervisor.spawn("session_timeout_check", supervisor::RestartPolicy::Always, move || {
+            let active_sessions = active_sessions.clone();
+            let audio_processor = audio_processor.clone();
+            let session_service = session_service.clone();
+            let runtime_config = runtime_config.clone();
+            async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+
+                loop {
+                    interval.tick().await;
+
+                    // 每次检查时读取最新的超时配置（可热加载）
+                    let timeout_seconds = runtime_config.borrow().session_timeout_seconds;
+                    let now = now_utc();
+                    let mut sessions_to_end = Vec::new();
+
+                    {
+                        let sessions = active_sessions.read().await;
+                        for (session_id, session_info) in sessions.iter() {
+                            let duration = now.signed_duration_since(session_info.last_activity);
+                            if duration.num_seconds() > timeout_seconds {
+                                sessions_to_end.push(session_id.clone());
+                            }
+                        }
+                    }
+
+                    // 结束超时的会话
+                    for session_id in sessions_to_end {
+                        warn!("Ending session {} due to timeout", session_id);
+                        if let Err(e) = Self::end_session_internal(
+                            active_sessions.clone(),
+                            audio_processor.clone(),
+                            &session_service,
+                            &session_id,
+                            "timeout"
+                        ).await {
+                            error!("Failed to end timeout session {}: {}", session_id, e);
+                        }
+                    }
+                }
+            }
+        });
+
+        // 数据库中长时间未结束的会话（如异常退出的副本遗留的会话）只由领导者副本统一结束
+        let session_service = self.session_service.clone();
+        let runtime_config = self.runtime_config.clone();
+        // SQLite 存储只支持单实例部署，本实例总是领导者
+        let election = match &self.db_pool {
+            Some(db_pool) => echo_shared::LeaderElection::new("session_timeout_sweep", db_pool.clone()),
+            None => echo_shared::LeaderElection::single_instance("session_timeout_sweep"),
+        };
+        let leader = Arc::new(election.with_listener(|job, leader| {
+            if leader {
+                info!("This instance is now the leader for {}", job);
+            } else {
+                warn!("This instance is no longer the leader for {}", job);
+            }
+            telemetry::record_leader(job, leader);
+        }));
+        self.supervisor.spawn("session_timeout_sweep", supervisor::RestartPolicy::Always, move || {
+            let session_service = session_service.clone();
+            let runtime_config = runtime_config.clone();
+            let leader = leader.clone();
+            async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+
+                loop {
+                    interval.tick().await;
+                    if !leader.is_leader().await {
+                        continue;
+                    }
+
+                    // 超过两倍超时时间仍为 active 的会话不会再有副本结束它
+                    let timeout_seconds = runtime_config.borrow().session_timeout_seconds;
+                    let timeout_minutes = (timeout_seconds * 2 + 59) / 60;
+                    match session_service.timeout_sessions(timeout_minutes).await {
+                        Ok(0) => {}
+                        Ok(count) => info!("Marked {} stale sessions as timed out", count),
+                        Err(e) => warn!("Failed to time out stale sessions: {}", e),
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    // 启动健康检查服务
+    async fn start_health_check_service(&self) -> Result<()> {
+        let websocket_port = self.config.websocket_port;
+        let internal_token = self.config.internal_token.clone();
+        let config_watcher = self.config_watcher.clone();
+        // 健康检查、WebSocket 和静态文件服务使用同一个端口
+        let bind_address = format!("0.0.0.0:{}", websocket_port);
+        let echokit_manager = self.echokit_manager.clone();
+        let echokit_connection_pool = self.echokit_connection_pool.clone();
+        let mqtt_client = self.mqtt_client.clone();
+        let db_pool = self.db_pool.clone();
+        let storage = self.storage.clone();
+        let critical_dependencies = self.critical_dependencies.clone();
+        let gateway_client = self.gateway_client.clone();
+        let cluster = self.cluster.clone();
+        let supervisor = self.supervisor.clone();
+        let udp_server = self.udp_server.clone();
+        let active_sessions = self.active_sessions.clone();
+        let audio_processor = self.audio_processor.clone();
+        let connection_manager = self.connection_manager.clone();
+        let connection_manager_for_stats = self.connection_manager.clone();
+        let session_manager = self.session_manager.clone();
+        let session_manager_for_internal = self.session_manager.clone();
+        let connection_manager_for_internal = self.connection_manager.clone();
+        let echokit_adapter_for_internal = self.echokit_adapter.clone();
+        let session_service_for_internal = self.session_service.clone();
+        let audio_importer = self.audio_importer.clone();
+        let max_import_bytes = self.config.audio_import.max_request_bytes;
+        let http_streams = self.http_streams.clone();
+        let stream_enabled = http_streams.is_some();
+        #[cfg(feature = "grpc")]
+        let command_verifier = self.command_verifier.clone();
+        #[cfg(feature = "grpc")]
+        let command_authorizer = self.command_authorizer.clone();
+        let security_headers = self.security_headers.clone();
+        // 证书读取失败时启动失败，而不是在后台任务中静默退出
+        let rustls_config = match self.config.tls.enabled {
+            true => Some(tls::rustls_config(&self.config.tls).await?),
+            false => None,
+        };
+        let ip_filter = self.ip_filter.clone();
+        let echokit_adapter = self.echokit_adapter.clone();
+        let echokit_connection_pool_for_ws = self.echokit_connection_pool.clone();  // 🎯 在 spawn 外部 clone
+        let redis_for_ws = self.redis.clone();
+        let ws_tickets = self.ws_tickets.clone();
+        let limits = self.limits.clone();
+        let grpc_audio = self.config.grpc_audio.clone();
+        let max_http_body_bytes = self.limits.max_http_body_bytes;
+        let metrics_state = telemetry::MetricsState {
+            db_pool: self.db_pool.clone(),
+            connection_manager: self.connection_manager.clone(),
+            udp_server: self.udp_server.clone(),
+        };
+
+        // 启动统一的 HTTP/WebSocket 服务器（健康检查、WebSocket、静态文件、API）
+        let session_service_for_ws = self.session_service.clone();
+        let db_session_manager_for_api = self.db_session_manager.clone();
+        tokio::spawn(async move {
+            use axum::{
+                routing::{get, post},
+                Router,
+            };
+            use tower_http::services::ServeDir;
+
+            // 健康检查路由
+            let health_router = Router::new()
+                .route("/health", get(health_check))
+                .route("/health/live", get(liveness_check))
+                .route("/health/ready", get(readiness_check))
+                .route("/stats", get(get_stats))
+                .route("/stats/connections", get(get_connection_stats))
+                .route("/api/echokit/languages", get(get_supported_languages))
+                .with_state(AppState {
+                    echokit_manager,
+                    echokit_connection_pool,
+                    mqtt_client,
+                    db_pool,
+                    storage,
+                    gateway_client,
+                    cluster: cluster.clone(),
+                    critical_dependencies,
+                    supervisor,
+                    udp_server,
+                    active_sessions,
+                    audio_processor,
+                    connection_manager: connection_manager_for_stats,
+                });
+
+            // Prometheus 指标路由
+            let metrics_router = Router::new()
+                .route("/metrics", get(telemetry::metrics_handler))
+                .with_state(metrics_state);
+
+            // 设备接入状态，WebSocket 和 gRPC 音频流共用
+            let device_state = websocket::audio_handler::AppState {
+                connection_manager,
+                session_manager,
+                echokit_adapter,
+                session_service: session_service_for_ws,
+                echokit_connection_pool: echokit_connection_pool_for_ws,  // 🎯 新增：连接池
+                redis: redis_for_ws,
+                ws_tickets,
+                limits,
+            };
+
+            // gRPC 音频流（可选），作为 WebSocket 之外的设备接入方式
+            if grpc_audio.enabled {
+                #[cfg(feature = "grpc")]
+                {
+                    let grpc_state = device_state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = grpc_audio::serve(grpc_state, grpc_audio).await {
+                            error!("gRPC audio stream server error: {}", e);
+                        }
+                    });
+                }
+                #[cfg(not(feature = "grpc"))]
+                warn!("bridge.grpc_audio is enabled but the Bridge was built without the grpc feature");
+            }
+
+            // WebSocket 路由
+            let ws_router = Router::new()
+                .route("/ws/audio", get(websocket::audio_handler::websocket_handler))
+                .route("/ws/{id}", get(websocket::audio_handler::websocket_handler_with_id))
+                .with_state(device_state);
+
+            // Session API 路由
+            let api_router = Router::new()
+                .route("/api/sessions", post(api_handlers::create_session))
+                .route("/api/sessions/{id}", get(api_handlers::get_session))
+                .route("/api/sessions/{id}/transcription", post(api_handlers::update_transcription))
+                .route("/api/sessions/{id}/complete", post(api_handlers::complete_session))
+                .with_state(api_handlers::ApiState {
+                    session_manager: db_session_manager_for_api,
+                });
+
+            // 内部 API 路由（供 API Gateway 查询实时状态）
+            let internal_state = internal_api::InternalApiState {
+                session_manager: session_manager_for_internal,
+                connection_manager: connection_manager_for_internal,
+                echokit_adapter: echokit_adapter_for_internal,
+                session_service: session_service_for_internal,
+                token: Arc::new(internal_token),
+                config_watcher,
+                cluster,
+                #[cfg(feature = "grpc")]
+                command_verifier,
+                #[cfg(feature = "grpc")]
+                command_authorizer,
+                audio_importer,
+            };
+            // 内部 gRPC 服务（可选），与内部 HTTP API 共享状态和鉴权令牌
+            #[cfg(feature = "grpc")]
+            {
+                let grpc_state = internal_state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = grpc_server::serve(grpc_state).await {
+                        error!("Internal gRPC server error: {}", e);
+                    }
+                });
+            }
+
+            // 录音导入的请求体上限单独配置（bridge.audio_import.max_request_bytes），不受 limits.max_http_body_bytes 限制
+            let import_router = Router::new()
+                .route("/admin/imports", post(internal_api::create_audio_import))
+                .route("/admin/imports/{id}", get(internal_api::get_audio_import))
+                .layer(axum::extract::DefaultBodyLimit::max(max_import_bytes))
+                .layer(axum::middleware::from_fn_with_state(max_import_bytes, ingress::limit_body))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    internal_state.clone(),
+                    internal_api::require_internal_token,
+                ))
+                .route_layer(axum::middleware::from_fn_with_state(ip_filter.clone(), ip_filter::require_allowed_ip))
+                .with_state(internal_state.clone());
+
+            // 流式上传的请求体上限按 bridge.http_stream.max_upload_seconds 计算，不受 limits.max_http_body_bytes 限制
+            let stream_router = match http_streams {
+                Some(http_streams) => {
+                    let max_upload_bytes = http_streams.max_upload_bytes();
+                    Router::new()
+                        .route("/api/stream/{id}", post(http_stream::upload_audio))
+                        .route("/api/stream/{id}/response", get(http_stream::stream_response))
+                        .layer(axum::middleware::from_fn_with_state(max_upload_bytes, ingress::limit_body))
+                        .with_state(http_streams)
+                }
+                None => Router::new(),
+            };
+
+            let internal_router = Router::new()
+                .route("/internal/devices/online", get(internal_api::list_online_devices))
+                .route("/internal/sessions/active", get(internal_api::list_active_sessions))
+                .route("/internal/sessions/{id}", get(internal_api::get_session_state))
+                .route("/internal/sessions/{id}/end", post(internal_api::end_session))
+                .route("/internal/dead-letters", get(internal_api::list_dead_letters))
+                .route(
+                    "/internal/devices/{id}/dead-letters",
+                    get(internal_api::get_dead_letters).delete(internal_api::discard_dead_letters),
+                )
+                .route("/internal/devices/{id}/dead-letters/flush", post(internal_api::flush_dead_letters))
+                .route("/admin/reload", post(internal_api::reload_config))
+                .route(
+                    "/admin/sessions/{id}/trace",
+                    get(internal_api::download_session_trace)
+                        .post(internal_api::start_session_trace)
+                        .delete(internal_api::stop_session_trace),
+                )
+                .route(
+                    "/admin/devices/{id}/trace",
+                    post(internal_api::start_device_trace).delete(internal_api::stop_device_trace),
+                )
+                .route(
+                    "/admin/devices/{id}/audio-capture",
+                    get(internal_api::download_audio_capture)
+                        .post(internal_api::start_audio_capture)
+                        .delete(internal_api::discard_audio_capture),
+                )
+                .route_layer(axum::middleware::from_fn_with_state(
+                    internal_state.clone(),
+                    internal_api::require_internal_token,
+                ))
+                // 在校验令牌之前拒绝被过滤的来源地址
+                .route_layer(axum::middleware::from_fn_with_state(ip_filter, ip_filter::require_allowed_ip))
+                .with_state(internal_state);
+
+            // 合并所有路由
+            let app = Router::new()
+                .merge(health_router)
+                .merge(metrics_router)
+                .merge(ws_router)
+                .merge(api_router)
+                .merge(internal_router)
+                .layer(axum::extract::DefaultBodyLimit::max(max_http_body_bytes))
+                .layer(axum::middleware::from_fn_with_state(max_http_body_bytes, ingress::limit_body))
+                .merge(import_router)
+                .merge(stream_router)
+                .layer(axum::middleware::from_fn(telemetry::track_requests))
+                .fallback_service(ServeDir::new("resources"))
+                // 静态文件（Web UI）同样需要安全头
+                .layer(axum::middleware::from_fn_with_state(security_headers, security_headers::add_security_headers));
+
+            let (http, ws) = if rustls_config.is_some() { ("https", "wss") } else { ("http", "ws") };
+            info!("HTTP/WebSocket server listening on: {}", bind_address);
+            info!("  - Health check: {}://{}/health (probes: /health/live, /health/ready)", http, bind_address);
+            info!("  - Metrics: {}://{}/metrics", http, bind_address);
+            info!("  - Connections: {}://{}/stats/connections", http, bind_address);
+            info!("  - WebSocket: {}://{}/ws/audio", ws, bind_address);
+            info!("  - Session API: {}://{}/api/sessions", http, bind_address);
+            if stream_enabled {
+                info!("  - Audio streaming: {}://{}/api/stream/{{session_id}}", http, bind_address);
+            }
+            info!("  - Internal API: {}://{}/internal (bearer token required)", http, bind_address);
+            info!("  - Config reload: POST {}://{}/admin/reload (bearer token required)", http, bind_address);
+            info!("  - Audio import: POST {}://{}/admin/imports (bearer token required)", http, bind_address);
+            info!("  - Static files: {}://{}/bridge_webui.html", http, bind_address);
+
+            // 记录设备的远端地址（用于 /stats/connections）
+            let service = app.into_make_service_with_connect_info::<std::net::SocketAddr>();
+            if let Some(rustls_config) = rustls_config {
+                let addr: std::net::SocketAddr = bind_address.parse().unwrap();
+                if let Err(e) = axum_server::bind_rustls(addr, rustls_config).serve(service).await {
+                    error!("HTTPS/WebSocket server error: {}", e);
+                }
+            } else {
+                let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
+                if let Err(e) = axum::serve(listener, service).await {
+                    error!("HTTP/WebSocket server error: {}", e);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    // 内部方法：结束会话
+    async fn end_session_internal(
+        active_sessions: Arc<TimedRwLock<std::collections::HashMap<String, SessionInfo>>>,
+        audio_processor: Arc<audio_processor::AudioProcessor>,
+        session_service: &session_service::SessionService,
+        session_id: &str,
+        reason: &str,
+    ) -> Result<()> {
+        // 先从活跃会话中移除，并发的结束请求只有一个会继续处理
+        let Some(session) = active_sessions.write().await.remove(session_id) else {
+            return Ok(());
+        };
+
+        // 结束音频处理会话
+        if let Err(e) = audio_processor.end_session(&session.device_id, reason).await {
+            error!("Failed to end audio session for device {}: {}", session.device_id, e);
+        }
+
+        // 与 WebSocket 会话和 HTTP complete 接口使用同一个结束流程持久化
+        let status = if reason == "timeout" {
+            echo_shared::database::SessionStatus::Timeout
+        } else {
+            echo_shared::database::SessionStatus::Completed
+        };
+        session_service
+            .finalize_session(session_id, &session_service::SessionFinalization::new(status))
+            .await?;
+
+        info!("Ended session {} for device {} (reason: {})", session_id, session.device_id, reason);
+        Ok(())
+    }
+}
+
+// 应用状态（用于健康检查服务）
+#[derive(Clone)]
+struct AppState {
+    echokit_manager: Arc<echokit_client::EchoKitConnectionManager>,
+    echokit_connection_pool: Arc<echokit::EchoKitConnectionPool>,
+    mqtt_client: Arc<mqtt_client::BridgeMqttClient>,
+    // SQLite 存储时为 None
+    db_pool: Option<sqlx::PgPool>,
+    storage: Arc<dyn echo_shared::Storage>,
+    gateway_client: Arc<gateway_client::GatewayClient>,
+    cluster: Option<Arc<cluster::ClusterRegistry>>,
+    critical_dependencies: Arc<Vec<String>>,
+    supervisor: Arc<supervisor::Supervisor>,
+    udp_server: Arc<udp_server::UdpAudioServer>,
+    active_sessions: Arc<TimedRwLock<std::collections::HashMap<String, SessionInfo>>>,
+    audio_processor: Arc<audio_processor::AudioProcessor>,
+    connection_manager: Arc<websocket::connection_manager::DeviceConnectionManager>,
+}
+
+// 健康检查端点
+async fn health_check(State(state): State<AppState>) -> Json<serde_json::Value> {
+    // 使用懒加载模式，不再预连接 EchoKit Server
+    // echokit_connected 表示是否有任何活跃的 EchoKit 连接
+    let echokit_connected = false;  // TODO: 从连接池获取聚合状态
+    let active_sessions = state.active_sessions.read().await.len();
+
+    // 修改健康检查逻辑：只要服务启动就认为是健康的，不依赖外部 EchoKit Server
+    Json(serde_json::json!({
+        "status": "healthy",
+        "service": "echo-bridge",
+        "echokit_connected": echokit_connected,
+        "active_sessions": active_sessions,
+        "uptime_seconds": echo_shared::process_uptime_seconds(),
+        "build": build_info::build_info(),
+        "timestamp": now_utc()
+    }))
+}
+
+// 就绪检查中单个依赖的超时时间
+const READINESS_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+// 存活探针：进程在运行即返回 200
+async fn liveness_check() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "alive",
+        "service": "echo-bridge",
+        "timestamp": now_utc()
+    }))
+}
+
+// 就绪探针：逐项检查依赖，关键依赖不可用时返回 503
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<ReadinessReport>) {
+    // 连接池用尽时获取连接会超时，附带连接池状态便于区分数据库故障和连接池用尽；SQLite 存储附带后端名称
+    let detail = match &state.db_pool {
+        Some(db_pool) => echo_shared::PoolStats::snapshot(db_pool).to_string(),
+        None => state.storage.backend().to_string(),
+    };
+    let database = match tokio::time::timeout(READINESS_CHECK_TIMEOUT, state.storage.health_check()).await {
+        Ok(Ok(())) => ComponentHealth::up("database").with_detail(detail),
+        Ok(Err(e)) => ComponentHealth::down("database", format!("{} ({})", e, detail)),
+        Err(_) => ComponentHealth::down("database", format!("timed out ({})", detail)),
+    };
+
+    let mqtt = if state.mqtt_client.is_connected().await {
+        ComponentHealth::up("mqtt")
+    } else {
+        ComponentHealth::down("mqtt", "not connected to broker")
+    };
+
+    // EchoKit 连接按需创建，没有设备在线时没有连接属于正常情况
+    let echokit_total = state.echokit_connection_pool.get_connection_count().await;
+    let echokit_connected = state.echokit_connection_pool.get_connected_count().await;
+    let echokit_detail = format!("{}/{} connections established", echokit_connected, echokit_total);
+    let echokit = if echokit_connected > 0 {
+        ComponentHealth::up("echokit").with_detail(echokit_detail)
+    } else {
+        ComponentHealth::down("echokit", echokit_detail)
+    };
+
+    let udp = match state.udp_server.local_addr() {
+        Ok(addr) => ComponentHealth::up("udp").with_detail(addr.to_string()),
+        Err(e) => ComponentHealth::down("udp", e.to_string()),
+    };
+
+    let gateway = match tokio::time::timeout(READINESS_CHECK_TIMEOUT, state.gateway_client.health()).await {
+        Ok(Ok(())) => ComponentHealth::up("gateway"),
+        Ok(Err(e)) => ComponentHealth::down("gateway", format!("{:#}", e)),
+        Err(_) => ComponentHealth::down("gateway", "timed out"),
+    };
+
+    let mut components = vec![database, mqtt, echokit, udp, gateway, state.supervisor.health()];
+    // 只有多副本部署时 Bridge 才使用 Redis
+    if let Some(cluster) = &state.cluster {
+        components.push(match tokio::time::timeout(READINESS_CHECK_TIMEOUT, cluster.ping()).await {
+            Ok(Ok(())) => ComponentHealth::up("redis").with_detail(format!("instance {}", cluster.instance_id())),
+            Ok(Err(e)) => ComponentHealth::down("redis", format!("{:#}", e)),
+            Err(_) => ComponentHealth::down("redis", "timed out"),
+        });
+    }
+
+    let report = ReadinessReport::new("echo-bridge", components, &state.critical_dependencies);
+    let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
+
+// 统计信息端点
+async fn get_stats(State(state): State<AppState>) -> Json<BridgeServiceStats> {
+    // 使用懒加载模式，统计信息从连接池获取
+    let echokit_connected = false;  // TODO: 从连接池获取聚合状态
+    let echokit_sessions = 0;  // TODO: 从连接池聚合所有连接的会话数
+    let active_sessions = state.active_sessions.read().await.len();
+    let audio_sessions = state.audio_processor.get_active_sessions_count().await;
+    let udp_stats = state.udp_server.get_stats().await;
+
+    Json(BridgeServiceStats {
+        echokit_connected,
+        echokit_sessions,
+        bridge_sessions: active_sessions,
+        audio_sessions,
+        online_devices: udp_stats.online_devices,
+        uptime_seconds: udp_stats.uptime_seconds,
+        build: build_info::build_info(),
+    })
+}
+
+// 设备连接统计：列出每个 WebSocket 和 UDP 设备连接
+async fn get_connection_stats(State(state): State<AppState>) -> Json<echo_shared::DeviceConnectionStats> {
+    let mut connections = state.connection_manager.get_connections().await;
+    connections.extend(state.udp_server.get_connections().await);
+
+    Json(echo_shared::DeviceConnectionStats::new(connections))
+}
+
+// EchoKit 支持的识别语言，设备和 Web UI 据此在 StartChat 中指定会话语言
+#[derive(serde::Serialize)]
+struct SupportedLanguages {
+    default_language: String,
+    // EchoKit 未上报时为空，此时不限制会话语言
+    languages: Vec<String>,
+}
+
+async fn get_supported_languages(State(state): State<AppState>) -> Json<SupportedLanguages> {
+    let mut languages = state.echokit_connection_pool.supported_languages().await;
+    if let Some(status) = state.echokit_manager.get_client().get_service_status().await {
+        languages.extend(status.supported_languages);
+        languages.sort();
+        languages.dedup();
+    }
+
+    Json(SupportedLanguages { default_language: echo_shared::EchoKitConfig::default().asr_language, languages })
+}
+
+// Bridge 服务统计信息
+#[derive(serde::Serialize)]
+struct BridgeServiceStats {
+    echokit_connected: bool,
+    echokit_sessions: usize,
+    bridge_sessions: usize,
+    audio_sessions: usize,
+    online_devices: usize,
+    uptime_seconds: u64,
+    build: echo_shared::BuildInfo,
+}
\ No newline at end of file
@@ -0,0 +1,717 @@
+//! MQTT 音频接入
+//!
+//! 给只能使用 MQTT 的受限设备：设备把 16kHz 16-bit 单声道 PCM 分块发布到 `{topic_prefix}/{device_id}/audio/in`，
+//! Bridge 按序号重组后转发给 EchoKit，收到结束标记即提交识别；EchoKit 的回复音频分块发布到
+//! `{topic_prefix}/{device_id}/audio/out`，会话开始/结束、识别结果和错误以 JSON 发布到 `{topic_prefix}/{device_id}/audio/event`。
+//!
+//! 音频消息的负载为 5 字节头加 PCM：1 字节标志（bit0 为结束标记）+ 4 字节大端序号。每句话的序号从 0 开始，
+//! 序号 0 开始新的一句，结束标记所在的消息可以不带音频；乱序到达的块在重组时排序，重复的块被丢弃。
+//! 回复音频使用相同格式，每轮回复的序号从 0 开始，最后一条消息带结束标记且不带音频。回复期间设备发来的语音被丢弃。
+//!
+//! 每个设备使用独立的 EchoKit 连接，与设备 WebSocket 会话一样登记在会话管理器中，转写、回复和会话配额都按设备所有者记录；
+//! 回复结束后一段时间内没有新的语音时会话结束。多副本部署时只有领导者副本订阅音频主题，同一设备的音频块总由同一副本重组。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use echo_shared::{
+    AudioFormat, DeviceId, EchoKitEvent, IngressLimitsConfig, LeaderElection, MqttAudioConfig, MqttConfig, SessionId,
+    SessionLimitExceeded, UserId,
+};
+use rumqttc::{AsyncClient, Event, MqttOptions, Outgoing, Packet, Publish, QoS};
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
+
+use crate::asr_postprocess::{self, AsrPostProcessing};
+use crate::channels::{self, AudioReceiver, ControlReceiver};
+use crate::echokit_client::EchoKitClient;
+use crate::session_service::SessionService;
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+use crate::websocket::session_manager::{SessionManager, SessionStatus};
+
+// 音频块的头：1 字节标志 + 4 字节大端序号
+const HEADER_LEN: usize = 5;
+const FLAG_END: u8 = 0x01;
+// 16kHz 16-bit 单声道
+const BYTES_PER_SECOND: usize = 32_000;
+// 一句话中最多暂存的乱序音频块
+const MAX_REORDER_CHUNKS: usize = 64;
+// 每个设备尚未处理的音频块
+const DEVICE_QUEUE_CAPACITY: usize = 256;
+const AUDIO_CALLBACK_CAPACITY: usize = 256;
+// 刷新会话活动时间、检查超时和会话是否已在别处结束的间隔
+const ACTIVITY_INTERVAL: Duration = Duration::from_secs(1);
+// 不是领导者时重新检查领导权的间隔
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+// 断线后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+// EchoKit 回复结束标记（见 EchoKitClient 的 response_callback）
+const END_RESPONSE_MARKER: &str = "__END_RESPONSE__";
+
+/// 音频块
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    seq: u32,
+    end: bool,
+    pcm: Vec<u8>,
+}
+
+impl Chunk {
+    fn decode(payload: &[u8]) -> Option<Self> {
+        if payload.len() < HEADER_LEN {
+            return None;
+        }
+        let seq = u32::from_be_bytes(payload[1..HEADER_LEN].try_into().ok()?);
+        Some(Self { seq, end: payload[0] & FLAG_END != 0, pcm: payload[HEADER_LEN..].to_vec() })
+    }
+
+    fn encode(seq: u32, end: bool, pcm: &[u8]) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(HEADER_LEN + pcm.len());
+        payload.push(if end { FLAG_END } else { 0 });
+        payload.extend_from_slice(&seq.to_be_bytes());
+        payload.extend_from_slice(pcm);
+        payload
+    }
+}
+
+/// 按序号重组一句话
+struct Reassembler {
+    next_seq: u32,
+    pending: BTreeMap<u32, Chunk>,
+    received_bytes: usize,
+    last_chunk: Instant,
+}
+
+impl Reassembler {
+    fn new() -> Self {
+        Self { next_seq: 0, pending: BTreeMap::new(), received_bytes: 0, last_chunk: Instant::now() }
+    }
+
+    /// 加入一个音频块，返回可以按顺序发送的块；暂存的乱序块过多时返回 None
+    fn push(&mut self, chunk: Chunk) -> Option<Vec<Chunk>> {
+        self.last_chunk = Instant::now();
+        if chunk.seq < self.next_seq || self.pending.contains_key(&chunk.seq) {
+            debug!("Dropping duplicate audio chunk {}", chunk.seq);
+            return Some(Vec::new());
+        }
+        self.received_bytes += chunk.pcm.len();
+        self.pending.insert(chunk.seq, chunk);
+        if self.pending.len() > MAX_REORDER_CHUNKS {
+            return None;
+        }
+
+        let mut ready = Vec::new();
+        while let Some(chunk) = self.pending.remove(&self.next_seq) {
+            self.next_seq = self.next_seq.wrapping_add(1);
+            let end = chunk.end;
+            ready.push(chunk);
+            if end {
+                break;
+            }
+        }
+        Some(ready)
+    }
+}
+
+// 会话对应的设备
+struct AudioDevice {
+    id: DeviceId,
+    owner: Option<UserId>,
+    echokit_url: String,
+}
+
+// EchoKit 连接的回调接收端
+struct Callbacks {
+    audio: AudioReceiver<(String, Vec<u8>)>,
+    asr: ControlReceiver<(String, String)>,
+    response: ControlReceiver<(String, String)>,
+}
+
+// 设备的会话和 EchoKit 连接
+struct DeviceStream {
+    device_id: DeviceId,
+    session_id: SessionId,
+    client: EchoKitClient,
+}
+
+// 会话中的对话状态
+struct Turn {
+    utterance: Option<Reassembler>,
+    // 提交的时间，回复结束后清空
+    submitted_at: Option<Instant>,
+    // 下一条回复音频消息的序号
+    response_seq: u32,
+    last_activity: Instant,
+}
+
+/// 会话建立失败的原因，以 `error` 事件通知设备
+struct OpenError {
+    code: &'static str,
+    message: String,
+}
+
+/// MQTT 音频网关：订阅设备音频并为每个设备启动会话任务
+pub struct MqttAudioGateway {
+    config: MqttAudioConfig,
+    options: MqttOptions,
+    db: Arc<PgPool>,
+    session_service: Arc<SessionService>,
+    session_manager: Arc<SessionManager>,
+    ingress_limits: IngressLimitsConfig,
+    asr_postprocessing: Option<Arc<AsrPostProcessing>>,
+    // 设备 ID -> 会话任务的音频块队列
+    devices: Mutex<HashMap<String, mpsc::Sender<Chunk>>>,
+}
+
+impl MqttAudioGateway {
+    pub fn new(
+        config: MqttAudioConfig,
+        mqtt: &MqttConfig,
+        db: Arc<PgPool>,
+        session_service: Arc<SessionService>,
+        session_manager: Arc<SessionManager>,
+    ) -> Self {
+        let client_id = format!("{}-audio", mqtt.client_id_or("bridge"));
+        let mut options = MqttOptions::new(client_id, mqtt.broker.clone(), mqtt.port);
+        options.set_keep_alive(Duration::from_secs(mqtt.keep_alive_secs));
+        options.set_clean_session(true);
+        if let (Some(username), Some(password)) = (&mqtt.username, &mqtt.password) {
+            options.set_credentials(username.clone(), password.clone());
+        }
+        // 主题和固定头之外留出余量
+        let max_packet = config.max_chunk_bytes + HEADER_LEN + 1024;
+        options.set_max_packet_size(max_packet, max_packet);
+
+        Self {
+            config,
+            options,
+            db,
+            session_service,
+            session_manager,
+            ingress_limits: IngressLimitsConfig::default(),
+            asr_postprocessing: None,
+            devices: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 接收 EchoKit 消息时的大小限制，与设备会话的 EchoKit 连接相同
+    pub fn with_ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
+    /// 识别结果保存前经过的 ASR 文本后处理
+    pub fn with_asr_postprocessing(mut self, asr_postprocessing: Arc<AsrPostProcessing>) -> Self {
+        self.asr_postprocessing = Some(asr_postprocessing);
+        self
+    }
+
+    /// 设备上行音频的订阅主题
+    pub fn subscription(&self) -> String {
+        format!("{}/+/audio/in", self.config.topic_prefix)
+    }
+
+    fn topic(&self, device_id: &str, suffix: &str) -> String {
+        format!("{}/{}/audio/{}", self.config.topic_prefix, device_id, suffix)
+    }
+
+    // 从 `{topic_prefix}/{device_id}/audio/in` 中取出设备 ID
+    fn device_of<'a>(&self, topic: &'a str) -> Option<&'a str> {
+        topic
+            .strip_prefix(self.config.topic_prefix.as_str())?
+            .strip_prefix('/')?
+            .strip_suffix("/audio/in")
+            .filter(|device_id| !device_id.is_empty() && !device_id.contains('/'))
+    }
+
+    /// 作为领导者订阅设备音频，失去领导权后等待重新当选
+    pub async fn run(self: Arc<Self>) {
+        let leader = LeaderElection::new("mqtt_audio", self.db.as_ref().clone()).with_listener(|job, leader| {
+            if leader {
+                info!("This instance is now the leader for {}", job);
+            } else {
+                warn!("This instance is no longer the leader for {}", job);
+            }
+            telemetry::record_leader(job, leader);
+        });
+
+        loop {
+            if leader.is_leader().await {
+                self.serve(&leader).await;
+            }
+            tokio::time::sleep(LEADER_RETRY_INTERVAL).await;
+        }
+    }
+
+    // 连接 broker 并分发设备音频，直到失去领导权
+    async fn serve(self: &Arc<Self>, leader: &LeaderElection) {
+        let (client, mut event_loop) = AsyncClient::new(self.options.clone(), 64);
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+        info!("MQTT audio ingestion connecting to MQTT broker (subscription: {})", self.subscription());
+
+        // 会话任务发布回复时可能等待事件循环腾出队列，事件循环放在单独的任务中驱动
+        let poller = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        let _ = events_tx.send(None);
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let _ = events_tx.send(Some(publish));
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        if events_tx.is_closed() {
+                            break;
+                        }
+                        warn!("MQTT audio connection error: {}, retrying in {:?}", e, RECONNECT_INTERVAL);
+                        tokio::time::sleep(RECONNECT_INTERVAL).await;
+                    }
+                }
+            }
+        });
+
+        let mut leadership = tokio::time::interval(LEADER_RETRY_INTERVAL);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    // clean session 模式下每次重连都需要重新订阅
+                    Some(None) => {
+                        if let Err(e) = client.subscribe(self.subscription(), QoS::AtLeastOnce).await {
+                            warn!("Failed to subscribe to {}: {}", self.subscription(), e);
+                        }
+                    }
+                    Some(Some(publish)) => self.dispatch(&client, publish).await,
+                    None => break,
+                },
+                _ = leadership.tick() => {
+                    if !leader.is_leader().await {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // 关闭所有设备的队列，会话任务随之结束
+        info!("MQTT audio ingestion disconnecting from MQTT broker");
+        self.devices.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        drop(events);
+        if client.disconnect().await.is_ok() {
+            let _ = tokio::time::timeout(RECONNECT_INTERVAL, poller).await;
+        }
+    }
+
+    // 把音频块交给设备的会话任务，没有任务时启动一个
+    async fn dispatch(self: &Arc<Self>, client: &AsyncClient, publish: Publish) {
+        let Some(device_id) = self.device_of(&publish.topic) else {
+            debug!("Ignoring MQTT audio on unexpected topic {}", publish.topic);
+            return;
+        };
+        let Some(chunk) = Chunk::decode(&publish.payload) else {
+            warn!("Malformed MQTT audio chunk ({} bytes) from device {}", publish.payload.len(), device_id);
+            return;
+        };
+        if chunk.pcm.len() > self.config.max_chunk_bytes {
+            warn!("MQTT audio chunk of {} bytes from device {} exceeds the limit", chunk.pcm.len(), device_id);
+            telemetry::record_ingress_rejected("mqtt");
+            return;
+        }
+        telemetry::record_audio_bytes("in", "mqtt", chunk.pcm.len());
+
+        let rejected = {
+            let mut devices = self.devices.lock().unwrap_or_else(|e| e.into_inner());
+            let chunk = match devices.get(device_id) {
+                Some(queue) => match queue.try_send(chunk) {
+                    Ok(()) => return,
+                    Err(mpsc::error::TrySendError::Full(_)) => {
+                        warn!("Audio queue of device {} is full, dropping chunk", device_id);
+                        return;
+                    }
+                    // 会话任务刚刚结束，启动新的任务
+                    Err(mpsc::error::TrySendError::Closed(chunk)) => {
+                        devices.remove(device_id);
+                        chunk
+                    }
+                },
+                None => chunk,
+            };
+            if devices.len() >= self.config.max_devices {
+                Some(chunk)
+            } else {
+                let (queue, chunks) = mpsc::channel(DEVICE_QUEUE_CAPACITY);
+                let _ = queue.try_send(chunk);
+                devices.insert(device_id.to_string(), queue);
+                let gateway = self.clone();
+                let device_id = DeviceId::from(device_id);
+                let client = client.clone();
+                tokio::spawn(async move { gateway.run_device(device_id, chunks, client).await });
+                None
+            }
+        };
+
+        if let Some(chunk) = rejected {
+            // 只在一句话开始时通知设备，避免每个音频块都发布错误
+            if chunk.seq == 0 {
+                warn!("Rejecting MQTT audio from device {}: {} devices are already streaming", device_id, self.config.max_devices);
+                self.publish_event(client, device_id, error_event(None, "busy", "Too many devices are streaming audio"))
+                    .await;
+            }
+        }
+    }
+
+    // 设备的会话任务：建立会话后处理音频块和 EchoKit 回复，返回时会话已结束
+    async fn run_device(self: Arc<Self>, device_id: DeviceId, mut chunks: mpsc::Receiver<Chunk>, client: AsyncClient) {
+        let (stream, mut callbacks) = match self.open(&device_id).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                warn!("Failed to start MQTT audio session for device {}: {}", device_id, e.message);
+                self.publish_event(&client, &device_id, error_event(None, e.code, &e.message)).await;
+                // 丢弃这句话剩余的音频块，避免每个块都重新尝试建立会话
+                self.discard_utterance(&mut chunks).await;
+                self.close_queue(&device_id, chunks);
+                return;
+            }
+        };
+        self.publish_event(&client, &device_id, session_event("session_started", &stream.session_id)).await;
+
+        let chunk_timeout = Duration::from_secs(self.config.chunk_timeout_seconds);
+        let response_timeout = Duration::from_secs(self.config.response_timeout_seconds);
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_seconds);
+        let mut turn = Turn { utterance: None, submitted_at: None, response_seq: 0, last_activity: Instant::now() };
+        let mut activity = tokio::time::interval(ACTIVITY_INTERVAL);
+
+        let reason = loop {
+            tokio::select! {
+                chunk = chunks.recv() => match chunk {
+                    Some(chunk) => self.on_chunk(&client, &stream, &mut turn, chunk).await,
+                    None => break "shutdown",
+                },
+                Some((_, data)) = callbacks.audio.recv() => {
+                    // 音频回调中的 MessagePack 事件与回复音频保持顺序，以 EndResponse 为本轮结束；问候音频不发送给设备
+                    match EchoKitEvent::from_messagepack(&data) {
+                        Ok(EchoKitEvent::AudioChunk { data }) if turn.submitted_at.is_some() => {
+                            self.publish_response(&client, &stream, &mut turn, &data).await;
+                        }
+                        Ok(EchoKitEvent::EndResponse) => self.end_response(&client, &stream, &mut turn).await,
+                        _ => {}
+                    }
+                }
+                Some((_, text)) = callbacks.asr.recv() => {
+                    if !text.trim().is_empty() {
+                        let text = asr_postprocess::apply(self.asr_postprocessing.as_deref(), device_id.as_str(), text).await;
+                        let event = json!({
+                            "event": "asr",
+                            "session_id": stream.session_id,
+                            "text": text,
+                            "timestamp": chrono::Utc::now().timestamp()
+                        });
+                        self.publish_event(&client, &device_id, event).await;
+                        self.session_manager.append_transcript(&stream.session_id, text).await;
+                    }
+                }
+                Some((_, text)) = callbacks.response.recv() => {
+                    if text == END_RESPONSE_MARKER {
+                        self.session_manager.finalize_current_round_response(&stream.session_id).await;
+                    } else {
+                        self.session_manager.append_response(&stream.session_id, text).await;
+                    }
+                }
+                _ = activity.tick() => {
+                    // 会话被管理员或超时清理结束
+                    if !self.session_manager.is_active(&stream.session_id).await {
+                        break "session_ended";
+                    }
+                    if let Err(e) = self.session_manager.update_activity(&stream.session_id).await {
+                        debug!("Failed to update activity of session {}: {}", stream.session_id, e);
+                    }
+
+                    if turn.utterance.as_ref().is_some_and(|utterance| utterance.last_chunk.elapsed() >= chunk_timeout) {
+                        warn!("Incomplete utterance from device {} timed out", device_id);
+                        turn.utterance = None;
+                        turn.last_activity = Instant::now();
+                        let event = error_event(Some(&stream.session_id), "incomplete_utterance", "Audio chunks are missing or the end marker was not received");
+                        self.publish_event(&client, &device_id, event).await;
+                    } else if turn.submitted_at.is_some_and(|at| at.elapsed() >= response_timeout) {
+                        warn!("No response from EchoKit for session {} within {:?}", stream.session_id, response_timeout);
+                        self.end_response(&client, &stream, &mut turn).await;
+                    } else if turn.utterance.is_none() && turn.submitted_at.is_none() && turn.last_activity.elapsed() >= idle_timeout {
+                        break "idle";
+                    }
+                }
+            }
+        };
+
+        info!("MQTT audio session {} for device {} ended: {}", stream.session_id, device_id, reason);
+        self.close_queue(&device_id, chunks);
+        if turn.submitted_at.is_some() {
+            self.end_response(&client, &stream, &mut turn).await;
+        }
+        if let Err(e) = stream.client.disconnect().await {
+            warn!("Failed to disconnect MQTT audio session {} from EchoKit: {}", stream.session_id, e);
+        }
+        self.end_session(&stream.session_id, SessionStatus::Completed).await;
+        self.publish_event(&client, &device_id, session_event("session_ended", &stream.session_id)).await;
+    }
+
+    async fn on_chunk(&self, client: &AsyncClient, stream: &DeviceStream, turn: &mut Turn, chunk: Chunk) {
+        if chunk.seq == 0 {
+            if turn.submitted_at.is_some() {
+                // 不支持打断，设备应等待回复的结束标记
+                let event = error_event(Some(&stream.session_id), "busy", "Still responding to the previous utterance");
+                self.publish_event(client, &stream.device_id, event).await;
+                return;
+            }
+            if turn.utterance.is_some() {
+                warn!("Device {} started a new utterance before finishing the previous one", stream.device_id);
+            }
+            if let Err(e) = stream.client.send_start_chat_command().await {
+                self.fail_utterance(client, stream, turn, e).await;
+                return;
+            }
+            turn.utterance = Some(Reassembler::new());
+        }
+
+        let Some(utterance) = turn.utterance.as_mut() else {
+            debug!("Dropping audio chunk {} from device {} outside of an utterance", chunk.seq, stream.device_id);
+            return;
+        };
+        let Some(ready) = utterance.push(chunk) else {
+            warn!("Too many out-of-order audio chunks from device {}", stream.device_id);
+            turn.utterance = None;
+            let event = error_event(Some(&stream.session_id), "incomplete_utterance", "Too many audio chunks are missing");
+            self.publish_event(client, &stream.device_id, event).await;
+            return;
+        };
+        if utterance.received_bytes > self.config.max_utterance_seconds as usize * BYTES_PER_SECOND {
+            turn.utterance = None;
+            let message = format!("Utterances are limited to {}s of audio", self.config.max_utterance_seconds);
+            self.publish_event(client, &stream.device_id, error_event(Some(&stream.session_id), "utterance_too_long", &message))
+                .await;
+            return;
+        }
+
+        for chunk in ready {
+            if !chunk.pcm.is_empty() {
+                let sent = stream
+                    .client
+                    .send_audio_data(stream.session_id.to_string(), stream.device_id.to_string(), chunk.pcm, AudioFormat::PCM16, false)
+                    .await;
+                if let Err(e) = sent {
+                    self.fail_utterance(client, stream, turn, e).await;
+                    return;
+                }
+            }
+            if chunk.end {
+                if let Err(e) = stream.client.send_submit_command().await {
+                    self.fail_utterance(client, stream, turn, e).await;
+                    return;
+                }
+                self.session_manager.mark_submitted(&stream.session_id).await;
+                turn.utterance = None;
+                turn.submitted_at = Some(Instant::now());
+                turn.response_seq = 0;
+                debug!("MQTT audio utterance from device {} submitted", stream.device_id);
+                return;
+            }
+        }
+    }
+
+    async fn fail_utterance(&self, client: &AsyncClient, stream: &DeviceStream, turn: &mut Turn, e: anyhow::Error) {
+        error!("Failed to forward MQTT audio from device {} to EchoKit: {}", stream.device_id, e);
+        turn.utterance = None;
+        turn.last_activity = Instant::now();
+        let event = error_event(Some(&stream.session_id), "echokit_unavailable", "Failed to forward audio to EchoKit");
+        self.publish_event(client, &stream.device_id, event).await;
+    }
+
+    // 按 max_chunk_bytes 分块发布回复音频
+    async fn publish_response(&self, client: &AsyncClient, stream: &DeviceStream, turn: &mut Turn, pcm: &[u8]) {
+        telemetry::record_audio_bytes("out", "mqtt", pcm.len());
+        let topic = self.topic(&stream.device_id, "out");
+        for part in pcm.chunks(self.config.max_chunk_bytes) {
+            let payload = Chunk::encode(turn.response_seq, false, part);
+            turn.response_seq = turn.response_seq.wrapping_add(1);
+            if let Err(e) = client.publish(topic.as_str(), QoS::AtLeastOnce, false, payload).await {
+                warn!("Failed to publish response audio to device {}: {}", stream.device_id, e);
+                return;
+            }
+        }
+    }
+
+    // 发布带结束标记的空音频块，结束本轮回复
+    async fn end_response(&self, client: &AsyncClient, stream: &DeviceStream, turn: &mut Turn) {
+        if turn.submitted_at.take().is_none() {
+            return;
+        }
+        let payload = Chunk::encode(turn.response_seq, true, &[]);
+        if let Err(e) = client.publish(self.topic(&stream.device_id, "out"), QoS::AtLeastOnce, false, payload).await {
+            warn!("Failed to publish end of response to device {}: {}", stream.device_id, e);
+        }
+        turn.response_seq = 0;
+        turn.last_activity = Instant::now();
+    }
+
+    async fn publish_event(&self, client: &AsyncClient, device_id: &str, event: serde_json::Value) {
+        if let Err(e) = client.publish(self.topic(device_id, "event"), QoS::AtLeastOnce, false, event.to_string()).await {
+            warn!("Failed to publish MQTT audio event to device {}: {}", device_id, e);
+        }
+    }
+
+    // 建立失败后丢弃本句话剩余的音频块，直到结束标记或超时
+    async fn discard_utterance(&self, chunks: &mut mpsc::Receiver<Chunk>) {
+        let chunk_timeout = Duration::from_secs(self.config.chunk_timeout_seconds);
+        while let Ok(Some(chunk)) = tokio::time::timeout(chunk_timeout, chunks.recv()).await {
+            if chunk.end {
+                break;
+            }
+        }
+    }
+
+    // 关闭设备的队列并移除登记，之后的音频块会启动新的会话任务
+    fn close_queue(&self, device_id: &DeviceId, mut chunks: mpsc::Receiver<Chunk>) {
+        chunks.close();
+        let mut devices = self.devices.lock().unwrap_or_else(|e| e.into_inner());
+        if devices.get(device_id.as_str()).is_some_and(|queue| queue.is_closed()) {
+            devices.remove(device_id.as_str());
+        }
+    }
+
+    // 查找设备、创建会话（同时检查会话配额）并建立 EchoKit 连接
+    async fn open(&self, device_id: &DeviceId) -> Result<(DeviceStream, Callbacks), OpenError> {
+        let device = match self.find_device(device_id).await {
+            Ok(Some(device)) => device,
+            Ok(None) => {
+                return Err(OpenError { code: "device_not_found", message: format!("Device {} not found", device_id) });
+            }
+            Err(e) => {
+                error!("Failed to look up device {} for MQTT audio: {:#}", device_id, e);
+                return Err(OpenError { code: "internal_error", message: "Failed to look up device".to_string() });
+            }
+        };
+
+        let session_id = SessionId::new(format!("session_{}", uuid::Uuid::new_v4()));
+        if let Err(e) = self
+            .session_service
+            .create_session(&session_id, &device.id, device.owner.as_ref(), Some("mqtt_audio".to_string()), None)
+            .await
+        {
+            if let Some(exceeded) = e.downcast_ref::<SessionLimitExceeded>() {
+                return Err(OpenError { code: "session_limit_exceeded", message: exceeded.to_string() });
+            }
+            error!("Failed to persist session {} to database: {}", session_id, e);
+        }
+        if let Err(e) = self.session_manager.create_session(session_id.clone(), device.id.clone()).await {
+            error!("Failed to register MQTT audio session {}: {}", session_id, e);
+        }
+
+        match self.connect_echokit(&device, &session_id).await {
+            Ok((client, callbacks)) => {
+                info!("MQTT audio session {} started for device {}", session_id, device.id);
+                Ok((DeviceStream { device_id: device.id, session_id, client }, callbacks))
+            }
+            Err(e) => {
+                error!("Failed to connect MQTT audio session {} to EchoKit: {:#}", session_id, e);
+                telemetry::record_session_failed("echokit");
+                self.end_session(&session_id, SessionStatus::Failed).await;
+                Err(OpenError { code: "echokit_unavailable", message: "Failed to connect to EchoKit".to_string() })
+            }
+        }
+    }
+
+    async fn find_device(&self, device_id: &DeviceId) -> Result<Option<AudioDevice>> {
+        let row = timed_query("mqtt_audio_device", sqlx::query_as::<_, (String, Option<String>, String)>(
+            "SELECT id, owner, echokit_server_url FROM devices WHERE id = $1",
+        )
+        .bind(device_id.as_str())
+        .fetch_optional(self.db.as_ref()))
+        .await
+        .context("Failed to query device")?;
+
+        Ok(row.map(|(id, owner, echokit_url)| AudioDevice {
+            id: DeviceId::from(id),
+            owner: owner.map(UserId::from),
+            echokit_url,
+        }))
+    }
+
+    // 先登记会话再连接，EchoKit 的事件才会转发到该会话
+    async fn connect_echokit(&self, device: &AudioDevice, session_id: &SessionId) -> Result<(EchoKitClient, Callbacks)> {
+        let (audio_tx, audio) = channels::audio_channel("mqtt_audio", AUDIO_CALLBACK_CAPACITY);
+        let (asr_tx, asr) = channels::control_channel("mqtt_audio_asr", 16);
+        let (response_tx, response) = channels::control_channel("mqtt_audio_response", 64);
+        let client = EchoKitClient::builder(&device.echokit_url)
+            .audio_callback(audio_tx)
+            .asr_callback(asr_tx)
+            .response_callback(response_tx)
+            .ingress_limits(self.ingress_limits.clone())
+            .build()?;
+        client.pre_register_session(session_id.to_string(), device.id.to_string()).await;
+        client.connect_with_device_id(Some(device.id.as_str())).await?;
+        Ok((client, Callbacks { audio, asr, response }))
+    }
+
+    async fn end_session(&self, session_id: &SessionId, status: SessionStatus) {
+        if let Err(e) = self.session_manager.finalize(session_id, status, &self.session_service).await {
+            error!("Failed to finalize session {}: {}", session_id, e);
+        }
+    }
+}
+
+fn session_event(event: &str, session_id: &SessionId) -> serde_json::Value {
+    json!({
+        "event": event,
+        "session_id": session_id,
+        "timestamp": chrono::Utc::now().timestamp()
+    })
+}
+
+fn error_event(session_id: Option<&SessionId>, code: &str, message: &str) -> serde_json::Value {
+    json!({
+        "event": "error",
+        "session_id": session_id,
+        "code": code,
+        "message": message,
+        "timestamp": chrono::Utc::now().timestamp()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(seq: u32, end: bool) -> Chunk {
+        Chunk { seq, end, pcm: vec![seq as u8; 4] }
+    }
+
+    #[test]
+    fn test_chunk_roundtrip() {
+        let payload = Chunk::encode(258, true, &[1, 2]);
+        assert_eq!(payload, vec![FLAG_END, 0, 0, 1, 2, 1, 2]);
+        assert_eq!(Chunk::decode(&payload), Some(Chunk { seq: 258, end: true, pcm: vec![1, 2] }));
+        assert_eq!(Chunk::decode(&[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_reassembler_orders_chunks() {
+        let mut utterance = Reassembler::new();
+        assert_eq!(utterance.push(chunk(0, false)).unwrap(), vec![chunk(0, false)]);
+        assert!(utterance.push(chunk(2, true)).unwrap().is_empty());
+        // 重复的块被丢弃
+        assert!(utterance.push(chunk(0, false)).unwrap().is_empty());
+        assert_eq!(utterance.push(chunk(1, false)).unwrap(), vec![chunk(1, false), chunk(2, true)]);
+        assert_eq!(utterance.received_bytes, 12);
+
+        let mut utterance = Reassembler::new();
+        for seq in 1..=MAX_REORDER_CHUNKS as u32 {
+            assert!(utterance.push(chunk(seq, false)).is_some());
+        }
+        assert!(utterance.push(chunk(MAX_REORDER_CHUNKS as u32 + 1, false)).is_none());
+    }
+}
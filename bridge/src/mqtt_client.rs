@@ -3,13 +3,16 @@ use echo_shared::{
     MqttTopic, MqttPayload, MqttError, TopicFilter,
     DeviceStatus, WakeReason, ServiceStatus, QoS
 };
-use echo_shared::mqtt::{MqttConfig, MqttMessage};
+use echo_shared::{mqtt::MqttMessage, CommandAuthorizer, CommandVerifier, DeviceCommandKind, MqttConfig, SignedCommand};
 use echo_shared::utils::now_utc;
 use rumqttc::{AsyncClient, Event, EventLoop, Incoming, Outgoing, Packet, QoS as RumqttQoS};
 use std::time::Duration as StdDuration;
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc};
 use tracing::{info, warn, error, debug};
+use crate::announcements::AnnouncementPlayer;
+use crate::telemetry;
+use crate::websocket::connection_manager::DeviceConnectionManager;
 
 // Bridge MQTT 客户端
 pub struct BridgeMqttClient {
@@ -20,6 +23,18 @@ pub struct BridgeMqttClient {
     registered_devices: Arc<RwLock<std::collections::HashMap<String, DeviceInfo>>>,
     is_connected: Arc<RwLock<bool>>,
     reconnect_count: Arc<RwLock<u32>>,
+    // 收到的设备命令经 WebSocket 转发给连接在本副本的设备
+    command_delivery: Option<CommandDelivery>,
+}
+
+// 设备命令的转发目标、签名验证和授权检查
+#[derive(Clone)]
+struct CommandDelivery {
+    connections: Arc<DeviceConnectionManager>,
+    verifier: Option<Arc<CommandVerifier>>,
+    authorizer: Arc<CommandAuthorizer>,
+    // 播报命令由 Bridge 合成语音后播放，未启用时按普通命令转发
+    announcements: Option<Arc<AnnouncementPlayer>>,
 }
 
 // 设备信息
@@ -35,9 +50,9 @@ pub struct DeviceInfo {
 impl BridgeMqttClient {
     pub fn new(config: MqttConfig) -> Result<(Self, EventLoop)> {
         let mut mqtt_options = rumqttc::MqttOptions::new(
-            config.client_id.clone(),
-            &config.broker_host,
-            config.broker_port,
+            config.client_id_or("bridge"),
+            &config.broker,
+            config.port,
         );
 
         // 设置认证信息
@@ -46,7 +61,7 @@ impl BridgeMqttClient {
         }
 
         // 设置保持连接
-        mqtt_options.set_keep_alive(StdDuration::from_secs(config.keep_alive));
+        mqtt_options.set_keep_alive(StdDuration::from_secs(config.keep_alive_secs));
         mqtt_options.set_clean_session(config.clean_session);
 
         let (client, event_loop) = AsyncClient::new(mqtt_options, 10);
@@ -61,11 +76,25 @@ impl BridgeMqttClient {
             registered_devices: Arc::new(RwLock::new(std::collections::HashMap::new())),
             is_connected: Arc::new(RwLock::new(false)),
             reconnect_count: Arc::new(RwLock::new(0)),
+            command_delivery: None,
         };
 
         Ok((mqtt_client, event_loop))
     }
 
+    /// 把收到的签名命令转发给连接在本副本的设备；启用命令签名时未签名或验证失败的命令被丢弃，
+    /// 下发用户没有相应设备权限的命令也被丢弃。提供 `announcements` 时播报命令由本副本合成语音播放
+    pub fn with_command_delivery(
+        mut self,
+        connections: Arc<DeviceConnectionManager>,
+        verifier: Option<Arc<CommandVerifier>>,
+        authorizer: Arc<CommandAuthorizer>,
+        announcements: Option<Arc<AnnouncementPlayer>>,
+    ) -> Self {
+        self.command_delivery = Some(CommandDelivery { connections, verifier, authorizer, announcements });
+        self
+    }
+
     // 启动 MQTT 客户端
     pub async fn start(self, mut event_loop: EventLoop) -> Result<()> {
         info!("Starting MQTT client for Bridge service");
@@ -107,6 +136,7 @@ impl BridgeMqttClient {
         self.client
             .publish(&message.topic, qos, message.retain, payload)
             .await
+            .inspect_err(|_| metrics::counter!(telemetry::MQTT_PUBLISH_ERRORS).increment(1))
             .with_context(|| format!("Failed to publish MQTT message to topic: {}", message.topic))?;
 
         debug!("Published MQTT message to topic: {}", message.topic);
@@ -228,14 +258,20 @@ impl BridgeMqttClient {
         *self.is_connected.read().await
     }
 
+    // 更新连接状态（事件循环由调用方驱动时使用）
+    pub async fn set_connected(&self, connected: bool) {
+        *self.is_connected.write().await = connected;
+    }
+
     // 启动消息处理器
     async fn start_message_processor(&self) -> Result<()> {
         let mut receiver = self.message_receiver.write().await.take()
             .ok_or_else(|| anyhow::anyhow!("Message receiver already taken"))?;
+        let command_delivery = self.command_delivery.clone();
 
         tokio::spawn(async move {
             while let Some(message) = receiver.recv().await {
-                if let Err(e) = Self::process_received_message(message).await {
+                if let Err(e) = Self::process_received_message(message, command_delivery.as_ref()).await {
                     error!("Error processing MQTT message: {}", e);
                 }
             }
@@ -341,17 +377,14 @@ impl BridgeMqttClient {
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-            let mut uptime_seconds = 0u64;
-
             loop {
                 interval.tick().await;
-                uptime_seconds += 30;
 
                 let heartbeat_message = echo_shared::MqttMessageBuilder::system_heartbeat(
                     service_name.clone(),
                     instance_id.clone(),
                     ServiceStatus::Healthy,
-                    uptime_seconds,
+                    echo_shared::process_uptime_seconds(),
                 );
 
                 if let Err(e) = Self::publish_heartbeat(&client, heartbeat_message).await {
@@ -484,13 +517,13 @@ impl BridgeMqttClient {
 
         // 订阅设备配置主题（所有设备）
         client
-            .subscribe("echo/device/+/config", RumqttQoS::AtLeastOnce)
+            .subscribe("device/+/config", RumqttQoS::AtLeastOnce)
             .await
             .with_context(|| "Failed to subscribe to device config topic")?;
 
         // 订阅设备控制主题（所有设备）
         client
-            .subscribe("echo/device/+/control", RumqttQoS::AtLeastOnce)
+            .subscribe("device/+/control", RumqttQoS::AtLeastOnce)
             .await
             .with_context(|| "Failed to subscribe to device control topic")?;
 
@@ -525,8 +558,16 @@ impl BridgeMqttClient {
     }
 
     // 处理接收到的消息
-    async fn process_received_message(message: MqttMessage) -> Result<()> {
+    async fn process_received_message(message: MqttMessage, command_delivery: Option<&CommandDelivery>) -> Result<()> {
+        let verifier = command_delivery.and_then(|delivery| delivery.verifier.as_ref());
         match message.payload {
+            // 启用命令签名时未签名的配置和控制命令可能是伪造的
+            MqttPayload::DeviceConfig { device_id, .. } | MqttPayload::DeviceControl { device_id, .. }
+                if verifier.is_some() =>
+            {
+                warn!("Rejected unsigned command for device {} on topic {}", device_id, message.topic);
+                telemetry::record_command_rejected("mqtt", "unsigned");
+            }
             MqttPayload::DeviceConfig {
                 device_id,
                 config,
@@ -540,10 +581,30 @@ impl BridgeMqttClient {
                 device_id,
                 command,
                 timestamp: _,
+                issued_by,
             } => {
+                if let Some(delivery) = command_delivery {
+                    let kind = DeviceCommandKind::of(&command);
+                    if !crate::command_authz::allow(&delivery.authorizer, "mqtt", &device_id, kind, issued_by.as_ref()).await {
+                        return Ok(());
+                    }
+                    if let (Some(player), Some(text)) = (&delivery.announcements, command.announcement_text()) {
+                        player.spawn_announce(&device_id, text);
+                        return Ok(());
+                    }
+                }
                 info!("Received device control command for {}: {:?}", device_id, command);
                 // TODO: 执行设备控制命令
             }
+            MqttPayload::SignedCommand {
+                device_id,
+                command,
+                timestamp: _,
+            } => {
+                if let Some(delivery) = command_delivery {
+                    Self::deliver_signed_command(delivery, &device_id, &command).await;
+                }
+            }
             MqttPayload::SystemStatus {
                 service,
                 status,
@@ -562,6 +623,46 @@ impl BridgeMqttClient {
         Ok(())
     }
 
+    // 验证签名后把命令原样转发给设备，设备可用注册时下发的公钥再次验证
+    async fn deliver_signed_command(delivery: &CommandDelivery, device_id: &str, command: &SignedCommand) {
+        let Some(verifier) = &delivery.verifier else {
+            warn!("Dropped signed command for device {}: command signing is not enabled on this bridge", device_id);
+            return;
+        };
+        let envelope = match verifier.verify(command, device_id) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                warn!("Rejected command for device {}: {}", device_id, e);
+                telemetry::record_command_rejected("mqtt", e.reason());
+                return;
+            }
+        };
+        let kind = DeviceCommandKind::of_value(&envelope.command);
+        if !crate::command_authz::allow(&delivery.authorizer, "mqtt", device_id, kind, envelope.issued_by.as_ref()).await {
+            return;
+        }
+        if let Some(player) = &delivery.announcements {
+            let command = serde_json::from_value::<echo_shared::DeviceCommand>(envelope.command.clone()).ok();
+            if let Some(text) = command.as_ref().and_then(|command| command.announcement_text()) {
+                player.spawn_announce(device_id, text);
+                return;
+            }
+        }
+
+        let text = match serde_json::to_string(command) {
+            Ok(text) => text,
+            Err(e) => {
+                error!("Failed to serialize signed command for device {}: {}", device_id, e);
+                return;
+            }
+        };
+        // 设备可能连接在其他副本上或直接订阅 MQTT
+        match delivery.connections.send_text(device_id, &text).await {
+            Ok(()) => info!("Delivered signed command {} to device {}", envelope.nonce, device_id),
+            Err(e) => debug!("Signed command {} not delivered to device {}: {}", envelope.nonce, device_id, e),
+        }
+    }
+
     // 发布心跳消息
     async fn publish_heartbeat(
         client: &AsyncClient,
@@ -579,6 +680,7 @@ impl BridgeMqttClient {
         client
             .publish(&message.topic, qos, false, payload)
             .await
+            .inspect_err(|_| metrics::counter!(telemetry::MQTT_PUBLISH_ERRORS).increment(1))
             .with_context(|| "Failed to publish heartbeat message")?;
 
         Ok(())
@@ -601,6 +703,7 @@ impl BridgeMqttClient {
         client
             .publish(&message.topic, qos, message.retain, payload)
             .await
+            .inspect_err(|_| metrics::counter!(telemetry::MQTT_PUBLISH_ERRORS).increment(1))
             .with_context(|| "Failed to publish device status message")?;
 
         Ok(())
@@ -0,0 +1,107 @@
+//! outbox 事件转发
+//!
+//! 按 id 顺序领取 outbox 表中未投递的事件（设备注册、会话结束等），发布到 MQTT 后在同一事务中
+//! 标记为已投递。MQTT 未连接时不领取事件；发布失败时记录错误并结束本轮，保证同一副本按顺序发布。
+//! 发布成功但标记事务提交失败时事件会再次发布，订阅方需要按事件内容去重（至少一次投递）。
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use echo_shared::{outbox, OutboxRelayConfig};
+use sqlx::PgPool;
+use tracing::{debug, info, warn};
+
+use crate::mqtt_client::BridgeMqttClient;
+use crate::telemetry;
+
+// 清理已投递事件的间隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 持续转发 outbox 中的事件，由 supervisor 监管
+pub async fn run(db_pool: PgPool, mqtt_client: Arc<BridgeMqttClient>, config: OutboxRelayConfig) {
+    let mut interval = tokio::time::interval(Duration::from_millis(config.poll_interval_ms));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_purge: Option<Instant> = None;
+
+    loop {
+        interval.tick().await;
+
+        if config.delivered_retention_hours > 0 && last_purge.is_none_or(|at| at.elapsed() >= PURGE_INTERVAL) {
+            last_purge = Some(Instant::now());
+            let before = Utc::now() - chrono::Duration::hours(config.delivered_retention_hours as i64);
+            match outbox::purge_delivered(&db_pool, before).await {
+                Ok(0) => {}
+                Ok(count) => info!("Purged {} delivered outbox events", count),
+                Err(e) => warn!("Failed to purge delivered outbox events: {}", e),
+            }
+        }
+
+        // MQTT 断线时事件留在表中，重连后补发
+        if !mqtt_client.is_connected().await {
+            continue;
+        }
+
+        // 一批发满说明还有积压，不等待下一轮继续发布
+        loop {
+            match relay_batch(&db_pool, &mqtt_client, &config).await {
+                Ok(published) if published == config.batch_size as usize => continue,
+                Ok(_) => break,
+                Err(e) => {
+                    warn!("Failed to relay outbox events: {}", e);
+                    break;
+                }
+            }
+        }
+
+        match outbox::pending_count(&db_pool).await {
+            Ok(count) => telemetry::record_outbox_pending(count),
+            Err(e) => debug!("Failed to count pending outbox events: {}", e),
+        }
+    }
+}
+
+// 发布一批事件，返回发布成功的事件数
+async fn relay_batch(
+    db_pool: &PgPool,
+    mqtt_client: &BridgeMqttClient,
+    config: &OutboxRelayConfig,
+) -> Result<usize, sqlx::Error> {
+    let mut tx = db_pool.begin().await?;
+    let entries = outbox::claim_pending(&mut tx, config.batch_size as i64, config.max_attempts as i32).await?;
+
+    let mut delivered = Vec::with_capacity(entries.len());
+    let mut failed = 0;
+    for entry in &entries {
+        let result = match entry.to_message() {
+            Ok(message) => mqtt_client.publish(message).await.map_err(|e| e.to_string()),
+            Err(e) => Err(format!("Invalid outbox payload: {}", e)),
+        };
+        match result {
+            Ok(()) => delivered.push(entry.id),
+            Err(e) => {
+                warn!(
+                    "Failed to publish outbox event {} to {} (attempt {}/{}): {}",
+                    entry.id,
+                    entry.topic,
+                    entry.attempts + 1,
+                    config.max_attempts,
+                    e
+                );
+                outbox::mark_failed(&mut tx, entry.id, &e).await?;
+                failed += 1;
+                // 后面的事件留到下一轮，保证发布顺序
+                break;
+            }
+        }
+    }
+
+    outbox::mark_delivered(&mut tx, &delivered).await?;
+    tx.commit().await?;
+
+    telemetry::record_outbox_published(delivered.len(), failed);
+    if !delivered.is_empty() {
+        debug!("Relayed {} outbox events", delivered.len());
+    }
+    Ok(delivered.len())
+}
@@ -0,0 +1,182 @@
+//! 设备实时在线状态
+//!
+//! 本副本的 WebSocket 在线设备写入 Redis 键 `device:presence:{device_id}`（JSON，带过期时间），
+//! 内容为最近心跳时间、当前会话和所在副本，API Gateway 的设备接口读取后覆盖数据库中的在线状态。
+//! 连接、断开、会话开始和结束时立即写入（后台执行），心跳时间只在本地更新，按刷新间隔批量写入，
+//! 避免每个数据帧都访问 Redis。副本异常退出时记录在过期后消失，设备随之显示为离线。
+//! 连接、断开和会话变化写入后发布缓存失效通知，API Gateway 的设备接口缓存随之失效。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use echo_shared::utils::now_utc;
+use echo_shared::{response_scopes, CacheInvalidation, DevicePresence, DevicePresenceConfig};
+use redis::{AsyncCommands, RedisResult, Script};
+use tracing::{debug, warn};
+
+use crate::redis_client::RedisClient;
+use crate::websocket::connection_manager::DeviceConnectionManager;
+
+// 只删除本副本写入的记录，设备已重连到其他副本时保持不变
+const RELEASE_SCRIPT: &str = r"
+local current = redis.call('GET', KEYS[1])
+if current and cjson.decode(current).instance_id == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+pub struct PresenceTracker {
+    config: DevicePresenceConfig,
+    instance_id: String,
+    redis: Arc<RedisClient>,
+    release: Script,
+    // 本副本在线设备的状态：device_id -> 在线状态
+    devices: DashMap<String, DevicePresence>,
+}
+
+impl PresenceTracker {
+    pub fn new(config: DevicePresenceConfig, instance_id: String, redis: Arc<RedisClient>) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            instance_id,
+            redis,
+            release: Script::new(RELEASE_SCRIPT),
+            devices: DashMap::new(),
+        })
+    }
+
+    /// 设备连接到本副本（后台写入）
+    pub fn device_connected(self: &Arc<Self>, device_id: &str) {
+        let presence = DevicePresence {
+            device_id: device_id.to_string(),
+            last_heartbeat: now_utc(),
+            session_id: None,
+            instance_id: self.instance_id.clone(),
+        };
+        self.devices.insert(device_id.to_string(), presence.clone());
+        self.spawn_write(presence);
+    }
+
+    /// 设备断开，删除本副本写入的记录（后台执行）
+    pub fn device_disconnected(self: &Arc<Self>, device_id: &str) {
+        self.devices.remove(device_id);
+        self.spawn_release(device_id.to_string());
+    }
+
+    /// 设备开始会话（后台写入）
+    pub fn session_started(self: &Arc<Self>, device_id: &str, session_id: &str) {
+        let presence = self.devices.get_mut(device_id).map(|mut presence| {
+            presence.session_id = Some(session_id.to_string());
+            presence.clone()
+        });
+        if let Some(presence) = presence {
+            self.spawn_write(presence);
+        }
+    }
+
+    /// 设备的会话结束（后台写入），设备已开始新会话时不变
+    pub fn session_ended(self: &Arc<Self>, device_id: &str, session_id: &str) {
+        let presence = self.devices.get_mut(device_id).and_then(|mut presence| {
+            if presence.session_id.as_deref() != Some(session_id) {
+                return None;
+            }
+            presence.session_id = None;
+            Some(presence.clone())
+        });
+        if let Some(presence) = presence {
+            self.spawn_write(presence);
+        }
+    }
+
+    /// 按刷新间隔把在线设备的最近心跳写入 Redis，由 supervisor 监管
+    pub async fn run(self: Arc<Self>, connection_manager: Arc<DeviceConnectionManager>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.refresh_interval_seconds));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.refresh(&connection_manager).await {
+                warn!("Failed to refresh device presence: {}", e);
+            }
+        }
+    }
+
+    /// 删除本副本写入的所有记录（停止服务时调用），设备立即显示为离线
+    pub async fn release_all(&self) {
+        let device_ids: Vec<String> = self.devices.iter().map(|entry| entry.key().clone()).collect();
+        for device_id in device_ids {
+            if let Err(e) = self.release_device(&device_id).await {
+                warn!("Failed to release device presence: {}", e);
+                return;
+            }
+        }
+    }
+
+    async fn refresh(&self, connection_manager: &DeviceConnectionManager) -> RedisResult<()> {
+        let online = connection_manager.get_online_devices().await;
+        self.devices.retain(|device_id, _| online.iter().any(|(id, _)| id == device_id));
+        if online.is_empty() {
+            return Ok(());
+        }
+
+        let count = online.len();
+        let mut pipe = redis::pipe();
+        for (device_id, last_heartbeat) in online {
+            let mut presence = self.devices.entry(device_id.clone()).or_insert_with(|| DevicePresence {
+                device_id,
+                last_heartbeat,
+                session_id: None,
+                instance_id: self.instance_id.clone(),
+            });
+            presence.last_heartbeat = last_heartbeat;
+            pipe.set_ex(DevicePresence::key(&presence.device_id), encode(&presence), self.config.ttl_seconds)
+                .ignore();
+        }
+        self.redis.run(|mut conn| async move { pipe.query_async::<_, ()>(&mut conn).await }).await?;
+        debug!("Refreshed presence of {} devices", count);
+        Ok(())
+    }
+
+    // 后台写入在线状态（直接覆盖）
+    fn spawn_write(self: &Arc<Self>, presence: DevicePresence) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let key = DevicePresence::key(&presence.device_id);
+            let value = encode(&presence);
+            let ttl = tracker.config.ttl_seconds;
+            let result = tracker
+                .redis
+                .run(|mut conn| async move { conn.set_ex::<_, _, ()>(key, value, ttl).await })
+                .await;
+            match result {
+                Ok(()) => tracker.redis.spawn_invalidate(CacheInvalidation::scopes([response_scopes::DEVICES])),
+                Err(e) => warn!("Failed to update presence of device {}: {}", presence.device_id, e),
+            }
+        });
+    }
+
+    // 后台删除本副本写入的在线状态
+    fn spawn_release(self: &Arc<Self>, device_id: String) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            match tracker.release_device(&device_id).await {
+                Ok(()) => tracker.redis.spawn_invalidate(CacheInvalidation::scopes([response_scopes::DEVICES])),
+                Err(e) => warn!("Failed to release presence of device {}: {}", device_id, e),
+            }
+        });
+    }
+
+    async fn release_device(&self, device_id: &str) -> RedisResult<()> {
+        let key = DevicePresence::key(device_id);
+        self.redis
+            .run(|mut conn| async move {
+                self.release.key(key).arg(&self.instance_id).invoke_async::<_, ()>(&mut conn).await
+            })
+            .await
+    }
+}
+
+fn encode(presence: &DevicePresence) -> String {
+    serde_json::to_string(presence).unwrap_or_default()
+}
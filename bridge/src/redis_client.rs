@@ -0,0 +1,91 @@
+//! Bridge 共用的 Redis 连接
+//!
+//! 集群注册表、设备在线状态和缓存失效通知共用一条多路复用连接和一个 Redis 熔断器：
+//! 连接在首次使用时建立，出现连接类错误后丢弃并计入熔断，下次使用时重连。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use echo_shared::{CacheInvalidation, CircuitBreaker, CircuitBreakerConfig};
+use redis::aio::MultiplexedConnection;
+use redis::{ErrorKind, RedisError, RedisResult};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::circuit;
+
+pub struct RedisClient {
+    client: redis::Client,
+    // 共享的多路复用连接，出现 IO 错误后丢弃，下次使用时重连
+    connection: Mutex<Option<MultiplexedConnection>>,
+    // Redis 熔断器，Redis 不可用时命令直接失败，不再等待连接超时
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl RedisClient {
+    /// 创建客户端，不连接 Redis
+    pub fn new(redis_url: &str, circuit_breaker: &CircuitBreakerConfig) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            client: redis::Client::open(redis_url).context("Invalid Redis URL")?,
+            connection: Mutex::new(None),
+            breaker: circuit::breaker("redis", circuit_breaker),
+        }))
+    }
+
+    /// 使用共享连接执行命令，连接断开时丢弃以便下次重连
+    pub async fn run<T, F, Fut>(&self, command: F) -> RedisResult<T>
+    where
+        F: FnOnce(MultiplexedConnection) -> Fut,
+        Fut: std::future::Future<Output = RedisResult<T>>,
+    {
+        if let Err(open) = self.breaker.check() {
+            return Err(RedisError::from((ErrorKind::ClientError, "Circuit open", open.to_string())));
+        }
+
+        let result = async {
+            let connection = {
+                let mut guard = self.connection.lock().await;
+                match guard.as_ref() {
+                    Some(connection) => connection.clone(),
+                    None => {
+                        let connection = self.client.get_multiplexed_tokio_connection().await?;
+                        *guard = Some(connection.clone());
+                        connection
+                    }
+                }
+            };
+            command(connection).await
+        }
+        .await;
+
+        // 只有连接类错误计入熔断，脚本或命令错误说明 Redis 可用
+        match &result {
+            Err(e) if e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout() => {
+                *self.connection.lock().await = None;
+                self.breaker.record_failure();
+            }
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
+
+    /// 检查 Redis 是否可用
+    pub async fn ping(&self) -> Result<()> {
+        self.run(|mut conn| async move { redis::cmd("PING").query_async::<_, ()>(&mut conn).await })
+            .await?;
+        Ok(())
+    }
+
+    /// 后台发布缓存失效通知，失败时只记录调试日志，API Gateway 的缓存在 TTL 到期后失效
+    pub fn spawn_invalidate(self: &Arc<Self>, invalidation: CacheInvalidation) {
+        let redis = self.clone();
+        tokio::spawn(async move {
+            let result = redis
+                .run(|mut conn| async move { invalidation.publish(&mut conn).await })
+                .await;
+            if let Err(e) = result {
+                debug!("Failed to publish cache invalidation: {}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,31 @@
+//! 安全响应头
+//!
+//! 为 HTTP 响应（包括 Web UI 静态文件）补充 `[security_headers]` 中配置的头，处理函数已设置的头不覆盖。
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use echo_shared::SecurityHeaders;
+
+/// 安全响应头中间件，反向代理通过 X-Forwarded-Proto 标记 HTTPS 请求
+pub async fn add_security_headers(
+    State(security_headers): State<Arc<SecurityHeaders>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let forwarded_https = req
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("https"));
+    let mut response = next.run(req).await;
+    for (name, value) in security_headers.headers(forwarded_https) {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            response.headers_mut().entry(name).or_insert(value);
+        }
+    }
+    response
+}
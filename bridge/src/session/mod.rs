@@ -1,4 +1,4 @@
-use echo_shared::Session;
+use echo_shared::{DatabaseError, DeviceId, Session, SessionId, Storage, UserId};
 use echo_shared::types::SessionStatus;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -9,25 +9,79 @@ use sqlx::PgPool;
 use anyhow::Result;
 use chrono::Utc;
 
+use echo_shared::{asr_word_count, response_scopes, CacheInvalidation, CircuitBreaker, FieldEncryptor, Redactor, RetryPolicy};
+use crate::circuit::retried_query;
+use crate::redis_client::RedisClient;
+use crate::session_service::{Finalized, SessionFinalization, SessionService};
+use crate::slow_ops::timed_query;
+
 // 会话管理器
 pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<String, Session>>>,
-    db_pool: PgPool,
+    storage: Arc<dyn Storage>,
+    // PostgreSQL 连接池；SQLite 存储时为 None，读写经过 storage
+    db_pool: Option<PgPool>,
+    // 写操作经过数据库熔断器，数据库不可用时直接失败
+    breaker: Arc<CircuitBreaker>,
+    // 写操作遇到短暂错误时的重试策略
+    retry: RetryPolicy,
+    // 创建和结束会话后通知 API Gateway 的会话接口缓存失效
+    redis: Option<Arc<RedisClient>>,
+    // 写入转写和回复前按会话所属用户的设置脱敏
+    redactor: Option<Arc<Redactor>>,
+    // 转写和回复加密后写入数据库，内存中保留明文
+    encryptor: Option<Arc<FieldEncryptor>>,
 }
 
 impl SessionManager {
-    pub fn new(db_pool: PgPool) -> Self {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
-            db_pool,
+            db_pool: storage.postgres_pool().cloned(),
+            storage,
+            breaker: Arc::new(CircuitBreaker::disabled("database")),
+            retry: RetryPolicy::none(),
+            redis: None,
+            redactor: None,
+            encryptor: None,
         }
     }
 
+    /// 设置数据库熔断器
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// 设置写操作的重试策略
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 创建和结束会话后通过 Redis 发布缓存失效通知
+    pub fn with_cache_invalidation(mut self, redis: Arc<RedisClient>) -> Self {
+        self.redis = Some(redis);
+        self
+    }
+
+    /// 写入转写和回复前脱敏
+    pub fn with_redactor(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// 转写和回复加密后写入数据库
+    pub fn with_encryption(mut self, encryptor: Arc<FieldEncryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
+    }
+
     /// 创建会话 -> 同时写入数据库
     pub async fn create_session(
         &self,
-        device_id: &str,
-        user_id: &str
+        device_id: &DeviceId,
+        user_id: &UserId
     ) -> Result<Session> {
         let session = Session {
             id: Uuid::new_v4().to_string(),
@@ -39,13 +93,18 @@ impl SessionManager {
             transcription: None,
             response: None,
             status: SessionStatus::Active,
+            summary: None,
+            tags: Vec::new(),
+            language: None,
         };
 
-        // 写入数据库
-        sqlx::query!(
+        // 写入数据库（重试时上一次写入可能已经成功，冲突时忽略；重试使用同一开始时间，命中分区表主键冲突）
+        let result = match &self.db_pool {
+            Some(db_pool) => retried_query(&self.breaker, &self.retry, "create_session", || sqlx::query!(
             r#"
             INSERT INTO sessions (id, device_id, user_id, start_time, status)
             VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id, start_time) DO NOTHING
             "#,
             session.id,
             session.device_id,
@@ -53,13 +112,23 @@ impl SessionManager {
             session.start_time,
             "active"
         )
-        .execute(&self.db_pool)
+        .execute(db_pool))
         .await
-        .map_err(|e| {
+        .map(|_| ()),
+            None => match self.storage.create_session(&session).await {
+                Err(DatabaseError::DuplicateRecord(_)) => Ok(()),
+                result => result,
+            },
+        };
+        result.map_err(|e| {
             error!("Failed to insert session into database: {}", e);
             anyhow::anyhow!("Database insert failed: {}", e)
         })?;
 
+        if let Some(redis) = &self.redis {
+            redis.spawn_invalidate(CacheInvalidation::scopes([response_scopes::SESSIONS]));
+        }
+
         // 同时保存到内存（用于快速访问活跃会话）
         let mut sessions = self.sessions.write().await;
         sessions.insert(session.id.clone(), session.clone());
@@ -74,19 +143,44 @@ impl SessionManager {
         session_id: &str,
         transcription: String
     ) -> Result<()> {
+        // 按会话所属用户的设置脱敏，内存与数据库保存同一份文本
+        let transcription = match &self.redactor {
+            Some(redactor) => {
+                let user_id = self.sessions.read().await.get(session_id).and_then(|session| session.user_id.clone());
+                if redactor.applies_to(user_id.as_deref()) {
+                    redactor.redact(&transcription).text.into_owned()
+                } else {
+                    transcription
+                }
+            }
+            None => transcription,
+        };
+
+        // 词数在加密前统计
+        let word_count = asr_word_count(&transcription);
+        let stored = match &self.encryptor {
+            Some(encryptor) => encryptor.session_key(session_id).encrypt_text(&transcription),
+            None => transcription.clone(),
+        };
+
         // 更新数据库
-        sqlx::query!(
+        let result = match &self.db_pool {
+            Some(db_pool) => retried_query(&self.breaker, &self.retry, "update_transcription", || sqlx::query(
             r#"
             UPDATE sessions
-            SET transcription = $1
+            SET transcription = $1, asr_word_count = $3
             WHERE id = $2
-            "#,
-            transcription,
-            session_id
+            "#
         )
-        .execute(&self.db_pool)
+        .bind(&stored)
+        .bind(session_id)
+        .bind(word_count)
+        .execute(db_pool))
         .await
-        .map_err(|e| {
+        .map(|_| ()),
+            None => self.update_stored_transcription(session_id, stored).await,
+        };
+        result.map_err(|e| {
             error!("Failed to update transcription for session {}: {}", session_id, e);
             anyhow::anyhow!("Database update failed: {}", e)
         })?;
@@ -101,53 +195,31 @@ impl SessionManager {
         Ok(())
     }
 
-    /// 完成会话 -> 更新数据库
+    // SQLite 存储：读出会话修改转录后整条写回
+    async fn update_stored_transcription(&self, session_id: &str, stored: String) -> Result<(), DatabaseError> {
+        let id = SessionId::new(session_id);
+        if let Some(mut session) = self.storage.get_session(&id).await? {
+            session.transcription = Some(stored);
+            self.storage.update_session(&session).await?;
+        }
+        Ok(())
+    }
+
+    /// 完成会话 -> 与 WebSocket 会话使用同一个结束流程，在一个事务中更新数据库
     pub async fn complete_session(
         &self,
         session_id: &str,
         transcription: String,
-        response: String
+        response: String,
+        audio_url: Option<String>,
     ) -> Result<()> {
-        let now = Utc::now();
-
-        // 更新数据库
-        sqlx::query!(
-            r#"
-            UPDATE sessions
-            SET
-                end_time = $1,
-                transcription = $2,
-                response = $3,
-                status = $4,
-                duration = EXTRACT(EPOCH FROM ($1 - start_time))::INTEGER
-            WHERE id = $5
-            "#,
-            now,
-            transcription,
-            response,
-            "completed",
-            session_id
-        )
-        .execute(&self.db_pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to complete session {}: {}", session_id, e);
-            anyhow::anyhow!("Database update failed: {}", e)
-        })?;
-
-        // 更新内存
-        let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.end_time = Some(now);
-            session.transcription = Some(transcription);
-            session.response = Some(response);
-            session.status = SessionStatus::Completed;
-
-            if let Some(end_time) = session.end_time {
-                let duration = end_time.signed_duration_since(session.start_time);
-                session.duration = Some(duration.num_seconds() as i32);
-            }
-        }
+        let finalization = SessionFinalization {
+            transcript: Some(transcription),
+            response: Some(response),
+            audio_url,
+            ..SessionFinalization::new(echo_shared::database::SessionStatus::Completed)
+        };
+        self.finalize(session_id, &finalization).await?;
 
         info!("Completed session {} and updated DB", session_id);
         Ok(())
@@ -159,45 +231,52 @@ impl SessionManager {
         session_id: &str,
         error_message: &str
     ) -> Result<()> {
-        let now = Utc::now();
+        let finalization = SessionFinalization {
+            response: Some(error_message.to_string()),
+            ..SessionFinalization::new(echo_shared::database::SessionStatus::Failed)
+        };
+        self.finalize(session_id, &finalization).await?;
 
-        // 更新数据库
-        sqlx::query!(
-            r#"
-            UPDATE sessions
-            SET
-                end_time = $1,
-                status = $2,
-                response = $3,
-                duration = EXTRACT(EPOCH FROM ($1 - start_time))::INTEGER
-            WHERE id = $4
-            "#,
-            now,
-            "failed",
-            error_message,
-            session_id
-        )
-        .execute(&self.db_pool)
-        .await
-        .map_err(|e| {
-            error!("Failed to mark session {} as failed: {}", session_id, e);
-            anyhow::anyhow!("Database update failed: {}", e)
-        })?;
+        warn!("Marked session {} as failed: {}", session_id, error_message);
+        Ok(())
+    }
+
+    // 数据库事务提交后再以数据库中的记录更新内存，会话已被其他路径结束时内存同样以数据库为准
+    async fn finalize(&self, session_id: &str, finalization: &SessionFinalization) -> Result<()> {
+        let mut store = SessionService::new(self.storage.clone())
+            .with_circuit_breaker(self.breaker.clone())
+            .with_retry_policy(self.retry);
+        if let Some(redis) = &self.redis {
+            store = store.with_cache_invalidation(redis.clone());
+        }
+        if let Some(redactor) = &self.redactor {
+            store = store.with_redactor(redactor.clone());
+        }
+        if let Some(encryptor) = &self.encryptor {
+            store = store.with_encryption(encryptor.clone());
+        }
+        let record = match store.finalize_session(session_id, finalization).await.map_err(|e| {
+            error!("Failed to finalize session {}: {}", session_id, e);
+            e
+        })? {
+            Finalized::Ended(record) => record,
+            Finalized::AlreadyEnded(record) => {
+                warn!("Session {} was already ended as {}", session_id, record.status);
+                record
+            }
+            Finalized::NotFound => anyhow::bail!("Session {} not found in database", session_id),
+        };
 
-        // 更新内存
         let mut sessions = self.sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
-            session.end_time = Some(now);
-            session.status = SessionStatus::Failed;
-            session.response = Some(error_message.to_string());
-
-            if let Some(end_time) = session.end_time {
-                let duration = end_time.signed_duration_since(session.start_time);
-                session.duration = Some(duration.num_seconds() as i32);
-            }
+            session.status = parse_status(&record.status);
+            session.end_time = record.ended_at;
+            session.duration = record
+                .ended_at
+                .map(|end_time| end_time.signed_duration_since(record.started_at).num_seconds() as i32);
+            session.transcription = record.transcript;
+            session.response = record.response;
         }
-
-        warn!("Marked session {} as failed: {}", session_id, error_message);
         Ok(())
     }
 
@@ -211,7 +290,19 @@ impl SessionManager {
         drop(sessions);
 
         // 内存未找到，从数据库查询
-        match sqlx::query_as::<_, SessionRecord>(
+        let Some(db_pool) = &self.db_pool else {
+            return match self.storage.get_session(&SessionId::new(session_id)).await {
+                Ok(session) => session.map(|mut session| {
+                    self.decrypt(session_id, &mut session);
+                    session
+                }),
+                Err(e) => {
+                    error!("Failed to fetch session {} from database: {}", session_id, e);
+                    None
+                }
+            };
+        };
+        match timed_query("get_session", sqlx::query_as::<_, SessionRecord>(
             r#"
             SELECT id, device_id, user_id, start_time, end_time,
                    duration, transcription, response, status
@@ -220,10 +311,14 @@ impl SessionManager {
             "#
         )
         .bind(session_id)
-        .fetch_optional(&self.db_pool)
+        .fetch_optional(db_pool))
         .await
         {
-            Ok(Some(record)) => Some(record.into()),
+            Ok(Some(record)) => {
+                let mut session = Session::from(record);
+                self.decrypt(session_id, &mut session);
+                Some(session)
+            }
             Ok(None) => None,
             Err(e) => {
                 error!("Failed to fetch session {} from database: {}", session_id, e);
@@ -232,6 +327,25 @@ impl SessionManager {
         }
     }
 
+    // 解密从数据库读出的转写和回复，无法解密的字段清空
+    fn decrypt(&self, session_id: &str, session: &mut Session) {
+        let Some(encryptor) = &self.encryptor else {
+            return;
+        };
+        for field in [&mut session.transcription, &mut session.response] {
+            if let Some(value) = field.as_deref() {
+                let decrypted = encryptor.decrypt_text(value).map(std::borrow::Cow::into_owned);
+                match decrypted {
+                    Ok(plaintext) => *field = Some(plaintext),
+                    Err(e) => {
+                        error!("Failed to decrypt session {}: {}", session_id, e);
+                        *field = None;
+                    }
+                }
+            }
+        }
+    }
+
     /// 更新会话（保持向后兼容）
     pub async fn update_session(&self, session_id: &str, updates: SessionUpdate) -> Option<Session> {
         let mut sessions = self.sessions.write().await;
@@ -296,17 +410,24 @@ impl From<SessionRecord> for Session {
             duration: record.duration,
             transcription: record.transcription,
             response: record.response,
-            status: match record.status.as_str() {
-                "active" => SessionStatus::Active,
-                "completed" => SessionStatus::Completed,
-                "failed" => SessionStatus::Failed,
-                "timeout" => SessionStatus::Timeout,
-                _ => SessionStatus::Failed,
-            },
+            status: parse_status(&record.status),
+            summary: None,
+            tags: Vec::new(),
+            language: None,
         }
     }
 }
 
+fn parse_status(status: &str) -> SessionStatus {
+    match status {
+        "active" => SessionStatus::Active,
+        "completed" => SessionStatus::Completed,
+        "failed" => SessionStatus::Failed,
+        "timeout" => SessionStatus::Timeout,
+        _ => SessionStatus::Failed,
+    }
+}
+
 // 会话更新结构
 pub struct SessionUpdate {
     pub transcription: Option<String>,
@@ -344,10 +465,10 @@ mod tests {
             .await
             .expect("Failed to connect to database");
 
-        let manager = SessionManager::new(pool);
+        let manager = SessionManager::new(Arc::new(echo_shared::PostgresStorage::new(pool.clone())));
 
         // 创建会话
-        let session = manager.create_session("dev001", "user001")
+        let session = manager.create_session(&"dev001".into(), &"user001".into())
             .await
             .expect("Failed to create session");
 
@@ -363,7 +484,8 @@ mod tests {
         manager.complete_session(
             &session.id,
             "Final transcription".to_string(),
-            "Final response".to_string()
+            "Final response".to_string(),
+            None,
         )
         .await
         .expect("Failed to complete session");
@@ -374,7 +496,7 @@ mod tests {
 
         // 清理测试数据
         sqlx::query!("DELETE FROM sessions WHERE id = $1", session.id)
-            .execute(&manager.db_pool)
+            .execute(&pool)
             .await
             .expect("Failed to cleanup test data");
     }
@@ -2,9 +2,16 @@
 use std::sync::Arc;
 use anyhow::Result;
 use sqlx::{PgPool, Row, FromRow};
-use echo_shared::{DatabaseError};
+use echo_shared::{DatabaseError, DeviceId, EchoError, SessionFilter, SessionId, Storage, UserId};
 use echo_shared::database::SessionStatus;
+use echo_shared::types::{Session as StoredSession, SessionStatus as StoredStatus};
 use chrono::{DateTime, Utc};
+use echo_shared::{asr_word_count, response_scopes, CacheInvalidation, CircuitBreaker, FieldEncryptor, Redactor, RetryPolicy, SessionLimits};
+use crate::circuit::{guarded_query, retried_query};
+use crate::redis_client::RedisClient;
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+use tracing::{debug, error, info, warn};
 
 // 会话记录（对应数据库sessions表）
 // 注意：数据库使用 VARCHAR(255) 存储 ID，支持自定义格式如 "session_xxx" 和 "ECHO_ES20500101002_xxx"
@@ -25,32 +32,249 @@ pub struct SessionRecord {
     #[sqlx(rename = "audio_file_path")]
     pub audio_url: Option<String>,  // 数据库字段名为 audio_file_path
     pub metadata: Option<serde_json::Value>,
+    /// 会话的识别语言，未指定时为 None
+    pub language: Option<String>,
+}
+
+impl From<StoredSession> for SessionRecord {
+    // SQLite 存储没有 audio_file_path 和 metadata 列
+    fn from(session: StoredSession) -> Self {
+        Self {
+            status: stored_status_str(&session.status).to_string(),
+            id: session.id,
+            device_id: session.device_id,
+            user_id: session.user_id,
+            started_at: session.start_time,
+            ended_at: session.end_time,
+            transcript: session.transcription,
+            response: session.response,
+            audio_url: None,
+            metadata: None,
+            language: session.language,
+        }
+    }
+}
+
+impl SessionRecord {
+    // 解密转写和回复，无法解密的字段清空并记录错误，不把密文返回给调用方
+    fn decrypted(mut self, encryptor: Option<&FieldEncryptor>) -> Self {
+        let Some(encryptor) = encryptor else {
+            return self;
+        };
+        for (name, field) in [("transcription", &mut self.transcript), ("response", &mut self.response)] {
+            if let Some(value) = field.as_deref() {
+                let decrypted = encryptor.decrypt_text(value).map(std::borrow::Cow::into_owned);
+                match decrypted {
+                    Ok(plaintext) => *field = Some(plaintext),
+                    Err(e) => {
+                        error!("Failed to decrypt {} of session {}: {}", name, self.id, e);
+                        *field = None;
+                    }
+                }
+            }
+        }
+        self
+    }
+}
+
+// 用会话的数据密钥加密该会话写入的转写和回复
+fn encrypt_fields(
+    encryptor: Option<&FieldEncryptor>,
+    session_id: &str,
+    transcript: &mut Option<String>,
+    response: &mut Option<String>,
+) {
+    let Some(encryptor) = encryptor else {
+        return;
+    };
+    if transcript.is_none() && response.is_none() {
+        return;
+    }
+    let key = encryptor.session_key(session_id);
+    for text in [transcript, response].into_iter().flatten() {
+        *text = key.encrypt_text(text);
+    }
+}
+
+// 按会话所属用户的设置脱敏，返回是否替换过内容；该用户不需要脱敏时返回 None
+fn redact_fields(
+    redactor: Option<&Redactor>,
+    user_id: Option<&str>,
+    transcript: &mut Option<String>,
+    response: &mut Option<String>,
+) -> Option<bool> {
+    let redactor = redactor.filter(|redactor| redactor.applies_to(user_id))?;
+    let mut replaced = false;
+    for text in [transcript, response].into_iter().flatten() {
+        let result = redactor.redact(text);
+        if result.redacted {
+            *text = result.text.into_owned();
+            replaced = true;
+        }
+    }
+    Some(replaced)
+}
+
+// 释放会话配额的原因：会话结束只释放设备的并发配额，创建失败时同时退还用户当天的计数
+enum SlotRelease {
+    Ended,
+    Cancelled(Option<String>),
 }
 
 // 会话服务
 #[derive(Clone)]
 pub struct SessionService {
-    db: Arc<PgPool>,
+    storage: Arc<dyn Storage>,
+    // PostgreSQL 连接池；SQLite 存储时为 None，会话读写经过 storage，导入和统计不可用
+    db: Option<PgPool>,
+    // 写操作经过数据库熔断器，数据库不可用时直接失败
+    breaker: Arc<CircuitBreaker>,
+    // 会话写操作遇到短暂错误时的重试策略
+    retry: RetryPolicy,
+    // 创建和结束会话后通知 API Gateway 的会话接口缓存失效，会话配额计数也在这里读写
+    redis: Option<Arc<RedisClient>>,
+    // 会话配额，未设置或 Redis 不可用时不限制
+    session_limits: Option<Arc<SessionLimits>>,
+    // 结束会话时按所属用户的设置对转写和回复脱敏，未设置时原样写入
+    redactor: Option<Arc<Redactor>>,
+    // 转写和回复的静态加密，未设置时以明文写入
+    encryptor: Option<Arc<FieldEncryptor>>,
 }
 
 impl SessionService {
-    pub fn new(db: Arc<PgPool>) -> Self {
-        Self { db }
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            db: storage.postgres_pool().cloned(),
+            storage,
+            breaker: Arc::new(CircuitBreaker::disabled("database")),
+            retry: RetryPolicy::none(),
+            redis: None,
+            session_limits: None,
+            redactor: None,
+            encryptor: None,
+        }
+    }
+
+    /// 设置数据库熔断器
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// 设置会话写操作的重试策略
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 创建和结束会话后通过 Redis 发布缓存失效通知
+    pub fn with_cache_invalidation(mut self, redis: Arc<RedisClient>) -> Self {
+        self.redis = Some(redis);
+        self
+    }
+
+    /// 创建会话前检查会话配额，结束会话时释放设备的并发配额；需要同时设置 `with_cache_invalidation`
+    pub fn with_session_limits(mut self, limits: Arc<SessionLimits>) -> Self {
+        self.session_limits = Some(limits);
+        self
+    }
+
+    /// 结束会话写入对话内容前脱敏
+    pub fn with_redactor(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = Some(redactor);
+        self
+    }
+
+    /// 转写和回复加密后写入，读取时透明解密
+    pub fn with_encryption(mut self, encryptor: Arc<FieldEncryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
+    }
+
+    // 只有 PostgreSQL 存储支持的操作
+    fn pg(&self, operation: &str) -> Result<&PgPool> {
+        self.db
+            .as_ref()
+            .ok_or_else(|| EchoError::NotImplemented(format!("{} requires PostgreSQL storage", operation)).into())
+    }
+
+    // 占用会话配额，超出时返回 SessionLimitExceeded；Redis 不可用时不限制，只记录警告
+    async fn acquire_session_slot(&self, session_id: &str, device_id: &str, user_id: Option<&str>) -> Result<()> {
+        let (Some(limits), Some(redis)) = (&self.session_limits, &self.redis) else {
+            return Ok(());
+        };
+        let result = redis
+            .run(|mut conn| async move { limits.acquire(&mut conn, session_id, device_id, user_id).await })
+            .await;
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(exceeded)) => {
+                warn!("Rejecting session {}: {}", session_id, exceeded);
+                telemetry::record_session_failed("limit");
+                Err(exceeded.into())
+            }
+            Err(e) => {
+                warn!("Failed to check session limits for session {}, allowing it: {}", session_id, e);
+                Ok(())
+            }
+        }
+    }
+
+    // 后台释放会话配额，失败时由租约到期兜底
+    fn spawn_release_session_slot(&self, session_id: &str, device_id: &str, release: SlotRelease) {
+        let (Some(limits), Some(redis)) = (self.session_limits.clone(), self.redis.clone()) else {
+            return;
+        };
+        let session_id = session_id.to_string();
+        let device_id = device_id.to_string();
+        tokio::spawn(async move {
+            let result = redis
+                .run(|mut conn| async move {
+                    match release {
+                        SlotRelease::Ended => limits.release(&mut conn, &session_id, &device_id).await,
+                        SlotRelease::Cancelled(user_id) => {
+                            limits.cancel(&mut conn, &session_id, &device_id, user_id.as_deref()).await
+                        }
+                    }
+                })
+                .await;
+            if let Err(e) = result {
+                debug!("Failed to release session slot, it expires with its lease: {}", e);
+            }
+        });
+    }
+
+    // 会话数据变化，通知 API Gateway 的会话接口缓存失效
+    fn invalidate_cache(&self) {
+        if let Some(redis) = &self.redis {
+            redis.spawn_invalidate(CacheInvalidation::scopes([response_scopes::SESSIONS]));
+        }
+    }
+
+    // 存储层返回的会话转换为会话记录并解密
+    fn decrypted_records(&self, sessions: Vec<StoredSession>) -> Vec<SessionRecord> {
+        sessions
+            .into_iter()
+            .map(|session| SessionRecord::from(session).decrypted(self.encryptor.as_deref()))
+            .collect()
     }
 
     /// 创建新会话
     pub async fn create_session(
         &self,
-        session_id: &str,
-        device_id: &str,
-        user_id: Option<&str>,
+        session_id: &SessionId,
+        device_id: &DeviceId,
+        user_id: Option<&UserId>,
         _wake_reason: Option<String>, // 保留参数兼容性，但不使用
+        language: Option<&str>,
     ) -> Result<SessionRecord> {
         // 直接使用字符串 ID，不再解析为 UUID
         // 数据库 schema 已经使用 VARCHAR(255)，支持任意格式的 ID
-        let clean_session_id = session_id.to_string();
-        let clean_device_id = device_id.to_string();
-        let clean_user_id = user_id.map(|s| s.to_string());
+        let clean_session_id = session_id.as_str();
+        let clean_device_id = device_id.as_str();
+        let clean_user_id = user_id.map(UserId::as_str);
+
+        self.acquire_session_slot(clean_session_id, clean_device_id, clean_user_id).await?;
 
         let status_str = match SessionStatus::Active {
             SessionStatus::Active => "active",
@@ -59,23 +283,48 @@ impl SessionService {
             SessionStatus::Timeout => "timeout",
         };
 
-        let record = sqlx::query_as::<_, SessionRecord>(
+        // 重试时上一次写入可能已经成功：同一设备的同一会话返回已有记录，会话 ID 被其他设备占用时报错
+        // 会话表按 start_time 分区，主键为 (id, start_time)，已有记录时沿用其开始时间才能命中冲突
+        let record = match &self.db {
+            Some(db) => retried_query(&self.breaker, &self.retry, "create_session", || sqlx::query_as::<_, SessionRecord>(
             r#"
-            INSERT INTO sessions (id, device_id, user_id, status)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO sessions (id, device_id, user_id, status, language, start_time)
+            VALUES ($1, $2, $3, $4, $5, COALESCE((SELECT start_time FROM sessions WHERE id = $1 LIMIT 1), NOW()))
+            ON CONFLICT (id, start_time) DO UPDATE SET id = sessions.id
+            WHERE sessions.device_id = EXCLUDED.device_id
             RETURNING id, device_id, user_id, status,
-                      start_time, end_time, transcription, response, audio_file_path, metadata
+                      start_time, end_time, transcription, response, audio_file_path, metadata, language
             "#
         )
         .bind(clean_session_id)
         .bind(clean_device_id)
         .bind(clean_user_id)
         .bind(status_str)
-        .fetch_one(self.db.as_ref())
+        .bind(language)
+        .fetch_optional(db))
         .await
-        .map_err(DatabaseError::Connection)?;
+        .and_then(|record| {
+            record.ok_or_else(|| DatabaseError::DuplicateRecord(format!("session {} belongs to another device", clean_session_id)))
+        }),
+            None => self.create_stored_session(clean_session_id, clean_device_id, clean_user_id, language).await,
+        };
+
+        match &record {
+            Ok(_) => {
+                telemetry::record_session_created();
+                self.invalidate_cache();
+            }
+            Err(_) => {
+                telemetry::record_session_failed("database");
+                self.spawn_release_session_slot(
+                    clean_session_id,
+                    clean_device_id,
+                    SlotRelease::Cancelled(clean_user_id.map(str::to_string)),
+                );
+            }
+        }
 
-        Ok(record)
+        Ok(record?)
     }
 
     /// 更新会话状态
@@ -83,12 +332,15 @@ impl SessionService {
         &self,
         session_id: &str,
         status: SessionStatus,
-        transcript: Option<String>,
-        response: Option<String>,
+        mut transcript: Option<String>,
+        mut response: Option<String>,
         audio_url: Option<String>,
     ) -> Result<Option<SessionRecord>> {
         // 直接使用字符串 ID
         let clean_session_id = session_id.to_string();
+        // 词数在加密前统计
+        let word_count = transcript.as_deref().map(asr_word_count);
+        encrypt_fields(self.encryptor.as_deref(), session_id, &mut transcript, &mut response);
 
         let status_str = match status {
             SessionStatus::Active => "active",
@@ -97,51 +349,329 @@ impl SessionService {
             SessionStatus::Timeout => "timeout",
         };
 
-        let record = sqlx::query_as::<_, SessionRecord>(
+        let record = match &self.db {
+            Some(db) => retried_query(&self.breaker, &self.retry, "update_session", || sqlx::query_as::<_, SessionRecord>(
             r#"
             UPDATE sessions
             SET status = $1,
                 transcription = COALESCE($2, transcription),
                 response = COALESCE($3, response),
                 audio_file_path = COALESCE($4, audio_file_path),
+                asr_word_count = COALESCE($6, asr_word_count),
                 end_time = CASE WHEN $1 = 'completed' THEN NOW() ELSE end_time END,
                 duration = CASE WHEN $1 = 'completed' THEN EXTRACT(EPOCH FROM (NOW() - start_time))::INTEGER ELSE duration END
             WHERE id = $5
             RETURNING id, device_id, user_id, status,
-                      start_time, end_time, transcription, response, audio_file_path, metadata
+                      start_time, end_time, transcription, response, audio_file_path, metadata, language
             "#
         )
         .bind(status_str)
-        .bind(transcript)
-        .bind(response)
-        .bind(audio_url)
-        .bind(clean_session_id)
-        .fetch_optional(self.db.as_ref())
-        .await
-        .map_err(DatabaseError::Connection)?;
+        .bind(transcript.as_deref())
+        .bind(response.as_deref())
+        .bind(audio_url.as_deref())
+        .bind(&clean_session_id)
+        .bind(word_count)
+        .fetch_optional(db))
+        .await?,
+            None => self.update_stored_session(session_id, &status, transcript, response).await?,
+        };
+
+        if let (Some(encryptor), false) = (&self.encryptor, matches!(status, SessionStatus::Active)) {
+            encryptor.release_session_key(session_id);
+        }
+
+        Ok(record.map(|record| record.decrypted(self.encryptor.as_deref())))
+    }
+
+    /// 结束会话：在一个事务中锁定会话记录，写入结束状态、对话内容、轮数、音频路径和时长
+    ///
+    /// 设备断线、设备主动结束、超时、内部 API 强制结束和 HTTP `complete` 接口都经过这里。
+    /// 会话已被其他路径结束时不做修改，返回当前记录。
+    pub async fn finalize_session(&self, session_id: &str, finalization: &SessionFinalization) -> Result<Finalized> {
+        let finalized = match &self.db {
+            Some(db) => retried_query(&self.breaker, &self.retry, "finalize_session", || {
+                Self::finalize_in_transaction(
+                    db,
+                    session_id,
+                    finalization,
+                    self.redactor.as_deref(),
+                    self.encryptor.as_deref(),
+                )
+            })
+            .await?,
+            None => self.finalize_stored_session(session_id, finalization).await?,
+        };
+        if let Some(encryptor) = &self.encryptor {
+            encryptor.release_session_key(session_id);
+        }
+        let finalized = match finalized {
+            Finalized::Ended(record) => Finalized::Ended(record.decrypted(self.encryptor.as_deref())),
+            Finalized::AlreadyEnded(record) => Finalized::AlreadyEnded(record.decrypted(self.encryptor.as_deref())),
+            Finalized::NotFound => Finalized::NotFound,
+        };
+
+        match &finalized {
+            Finalized::Ended(record) => {
+                info!("Session {} finalized as {}", session_id, record.status);
+                self.invalidate_cache();
+                self.spawn_release_session_slot(session_id, &record.device_id, SlotRelease::Ended);
+            }
+            Finalized::AlreadyEnded(record) => {
+                debug!("Session {} already finalized as {}, skipping", session_id, record.status)
+            }
+            Finalized::NotFound => warn!("Session {} not found in database, cannot finalize", session_id),
+        }
+        Ok(finalized)
+    }
+
+    // 整个事务可以安全重试：会话已结束时第二次执行不会修改记录
+    async fn finalize_in_transaction(
+        db: &PgPool,
+        session_id: &str,
+        finalization: &SessionFinalization,
+        redactor: Option<&Redactor>,
+        encryptor: Option<&FieldEncryptor>,
+    ) -> Result<Finalized, sqlx::Error> {
+        let mut tx = db.begin().await?;
 
-        Ok(record)
+        let current = sqlx::query_as::<_, SessionRecord>(
+            r#"
+            SELECT id, device_id, user_id, status,
+                   start_time, end_time, transcription, response, audio_file_path, metadata, language
+            FROM sessions
+            WHERE id = $1
+            FOR UPDATE
+            "#
+        )
+        .bind(session_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let finalized = match current {
+            None => Finalized::NotFound,
+            Some(record) if record.status != "active" => Finalized::AlreadyEnded(record),
+            Some(current) => {
+                // 按会话所属用户的设置脱敏，metadata.redacted 记录是否替换过内容
+                let mut transcript = finalization.transcript.clone();
+                let mut response = finalization.response.clone();
+                let redacted = redact_fields(redactor, current.user_id.as_deref(), &mut transcript, &mut response);
+                let word_count = transcript.as_deref().map(asr_word_count);
+                // 脱敏之后再加密
+                encrypt_fields(encryptor, session_id, &mut transcript, &mut response);
+
+                let record = sqlx::query_as::<_, SessionRecord>(
+                    r#"
+                    UPDATE sessions
+                    SET status = $1,
+                        transcription = COALESCE($2, transcription),
+                        response = COALESCE($3, response),
+                        audio_file_path = COALESCE($4, audio_file_path),
+                        metadata = CASE
+                            WHEN $5::INTEGER IS NULL AND $7::BOOLEAN IS NULL THEN metadata
+                            ELSE COALESCE(metadata, '{}'::jsonb)
+                                || jsonb_strip_nulls(jsonb_build_object('turns', $5::INTEGER, 'redacted', $7::BOOLEAN))
+                        END,
+                        asr_word_count = COALESCE($8, asr_word_count),
+                        end_time = NOW(),
+                        duration = EXTRACT(EPOCH FROM (NOW() - start_time))::INTEGER
+                    WHERE id = $6
+                    RETURNING id, device_id, user_id, status,
+                              start_time, end_time, transcription, response, audio_file_path, metadata, language
+                    "#
+                )
+                .bind(status_str(&finalization.status))
+                .bind(transcript.as_deref())
+                .bind(response.as_deref())
+                .bind(finalization.audio_url.as_deref())
+                .bind(finalization.turns)
+                .bind(session_id)
+                .bind(redacted)
+                .bind(word_count)
+                .fetch_one(&mut *tx)
+                .await?;
+
+                // 会话结束事件与结束状态一起提交，由 outbox 转发任务发布到 MQTT
+                let event = echo_shared::MqttMessageBuilder::session_ended(
+                    record.id.clone(),
+                    record.device_id.clone(),
+                    record.user_id.clone(),
+                    record.status.clone(),
+                    record.ended_at.map(|ended_at| (ended_at - record.started_at).num_seconds()),
+                );
+                echo_shared::outbox::enqueue(&mut *tx, &event).await?;
+
+                Finalized::Ended(record)
+            }
+        };
+
+        tx.commit().await?;
+        Ok(finalized)
+    }
+
+    // SQLite 存储创建会话：与 PostgreSQL 的写法一致，同一设备的同一会话返回已有记录
+    async fn create_stored_session(
+        &self,
+        session_id: &str,
+        device_id: &str,
+        user_id: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<SessionRecord, DatabaseError> {
+        let session = StoredSession {
+            id: session_id.to_string(),
+            device_id: device_id.to_string(),
+            user_id: user_id.map(str::to_string),
+            start_time: Utc::now(),
+            end_time: None,
+            duration: None,
+            transcription: None,
+            response: None,
+            status: StoredStatus::Active,
+            summary: None,
+            tags: Vec::new(),
+            language: language.map(str::to_string),
+        };
+        match self.storage.create_session(&session).await {
+            Ok(()) => Ok(session.into()),
+            Err(DatabaseError::DuplicateRecord(_)) => match self.storage.get_session(&SessionId::new(session_id)).await? {
+                Some(existing) if existing.device_id == device_id => Ok(existing.into()),
+                _ => Err(DatabaseError::DuplicateRecord(format!("session {} belongs to another device", session_id))),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    // SQLite 存储更新会话：读出记录修改后整条写回，单实例部署没有并发写入
+    async fn update_stored_session(
+        &self,
+        session_id: &str,
+        status: &SessionStatus,
+        transcript: Option<String>,
+        response: Option<String>,
+    ) -> Result<Option<SessionRecord>, DatabaseError> {
+        let Some(mut session) = self.storage.get_session(&SessionId::new(session_id)).await? else {
+            return Ok(None);
+        };
+        session.status = stored_status(status);
+        if transcript.is_some() {
+            session.transcription = transcript;
+        }
+        if response.is_some() {
+            session.response = response;
+        }
+        if matches!(status, SessionStatus::Completed) {
+            let now = Utc::now();
+            session.end_time = Some(now);
+            session.duration = Some((now - session.start_time).num_seconds() as i32);
+        }
+        self.storage.update_session(&session).await?;
+        Ok(Some(session.into()))
+    }
+
+    // SQLite 存储结束会话：不记录轮数和脱敏标记，也不写 outbox 发布会话结束事件
+    async fn finalize_stored_session(
+        &self,
+        session_id: &str,
+        finalization: &SessionFinalization,
+    ) -> Result<Finalized, DatabaseError> {
+        let Some(mut session) = self.storage.get_session(&SessionId::new(session_id)).await? else {
+            return Ok(Finalized::NotFound);
+        };
+        if session.status != StoredStatus::Active {
+            return Ok(Finalized::AlreadyEnded(session.into()));
+        }
+        let mut transcript = finalization.transcript.clone();
+        let mut response = finalization.response.clone();
+        redact_fields(self.redactor.as_deref(), session.user_id.as_deref(), &mut transcript, &mut response);
+        encrypt_fields(self.encryptor.as_deref(), session_id, &mut transcript, &mut response);
+
+        let now = Utc::now();
+        session.status = stored_status(&finalization.status);
+        if transcript.is_some() {
+            session.transcription = transcript;
+        }
+        if response.is_some() {
+            session.response = response;
+        }
+        session.end_time = Some(now);
+        session.duration = Some((now - session.start_time).num_seconds() as i32);
+        self.storage.update_session(&session).await?;
+        Ok(Finalized::Ended(session.into()))
+    }
+
+    /// 写入一个导入的录音会话：直接以 completed 状态写入开始时间、时长和转写
+    ///
+    /// 转写按会话所属用户的设置脱敏后加密；导入的是历史录音，不占用会话配额，
+    /// 也不发布会话结束事件。
+    pub async fn import_session(&self, session: &ImportedSession) -> Result<SessionRecord> {
+        let db = self.pg("importing sessions")?;
+        let mut transcript = Some(session.transcript.clone());
+        let mut redacted = None;
+        if let Some(redactor) = self.redactor.as_deref().filter(|redactor| redactor.applies_to(session.user_id.as_deref())) {
+            let result = redactor.redact(&session.transcript);
+            if result.redacted {
+                transcript = Some(result.text.into_owned());
+            }
+            redacted = Some(result.redacted);
+        }
+        let word_count = transcript.as_deref().map(asr_word_count);
+        encrypt_fields(self.encryptor.as_deref(), &session.session_id, &mut transcript, &mut None);
+        if let Some(encryptor) = &self.encryptor {
+            encryptor.release_session_key(&session.session_id);
+        }
+
+        let ended_at = session.started_at + chrono::Duration::milliseconds((session.duration_seconds * 1000.0) as i64);
+        let record = retried_query(&self.breaker, &self.retry, "import_session", || sqlx::query_as::<_, SessionRecord>(
+            r#"
+            INSERT INTO sessions (id, device_id, user_id, status, transcription, metadata, start_time, end_time, duration, asr_word_count)
+            VALUES ($1, $2, $3, 'completed', $4,
+                    jsonb_strip_nulls(jsonb_build_object('import', $5::JSONB, 'redacted', $6::BOOLEAN)),
+                    $7, $8, $9, $10)
+            ON CONFLICT (id, start_time) DO UPDATE SET id = sessions.id
+            RETURNING id, device_id, user_id, status,
+                      start_time, end_time, transcription, response, audio_file_path, metadata, language
+            "#
+        )
+        .bind(&session.session_id)
+        .bind(&session.device_id)
+        .bind(session.user_id.as_deref())
+        .bind(transcript.as_deref())
+        .bind(&session.source)
+        .bind(redacted)
+        .bind(session.started_at)
+        .bind(ended_at)
+        .bind(session.duration_seconds.round() as i32)
+        .bind(word_count)
+        .fetch_one(db))
+        .await?;
+
+        info!("Imported session {} for device {}", record.id, record.device_id);
+        self.invalidate_cache();
+        Ok(record.decrypted(self.encryptor.as_deref()))
     }
 
     /// 获取会话详情
     pub async fn get_session(&self, session_id: &str) -> Result<Option<SessionRecord>> {
+        let Some(db) = &self.db else {
+            let session = self.storage.get_session(&SessionId::new(session_id)).await?;
+            return Ok(session.map(|session| SessionRecord::from(session).decrypted(self.encryptor.as_deref())));
+        };
         // 直接使用字符串 ID
         let clean_session_id = session_id.to_string();
 
-        let record = sqlx::query_as::<_, SessionRecord>(
+        let record = timed_query("get_session", sqlx::query_as::<_, SessionRecord>(
             r#"
             SELECT id, device_id, user_id, status,
-                   start_time, end_time, transcription, response, audio_file_path, metadata
+                   start_time, end_time, transcription, response, audio_file_path, metadata, language
             FROM sessions
             WHERE id = $1
             "#
         )
         .bind(clean_session_id)
-        .fetch_optional(self.db.as_ref())
+        .fetch_optional(db))
         .await
         .map_err(DatabaseError::Connection)?;
 
-        Ok(record)
+        Ok(record.map(|record| record.decrypted(self.encryptor.as_deref())))
     }
 
     /// 获取设备会话列表
@@ -151,27 +681,35 @@ impl SessionService {
         limit: Option<i64>,
         offset: Option<i64>,
     ) -> Result<Vec<SessionRecord>> {
+        let Some(db) = &self.db else {
+            let filter = SessionFilter {
+                device_id: Some(device_id.to_string()),
+                ..SessionFilter::default()
+            };
+            let (sessions, _) = self.storage.list_sessions(&filter, limit.unwrap_or(100), offset.unwrap_or(0)).await?;
+            return Ok(self.decrypted_records(sessions));
+        };
         // 直接使用字符串 ID
         let clean_device_id = device_id.to_string();
 
         let sql = r#"
             SELECT id, device_id, user_id, status,
-                   start_time, end_time, transcription, response, audio_file_path, metadata
+                   start_time, end_time, transcription, response, audio_file_path, metadata, language
             FROM sessions
             WHERE device_id = $1
             ORDER BY start_time DESC
             LIMIT $2 OFFSET $3
         "#;
 
-        let records = sqlx::query_as::<_, SessionRecord>(sql)
+        let records = timed_query("get_device_sessions", sqlx::query_as::<_, SessionRecord>(sql)
             .bind(clean_device_id)
             .bind(limit.unwrap_or(100))
             .bind(offset.unwrap_or(0))
-            .fetch_all(self.db.as_ref())
+            .fetch_all(db))
             .await
             .map_err(DatabaseError::Connection)?;
 
-        Ok(records)
+        Ok(records.into_iter().map(|record| record.decrypted(self.encryptor.as_deref())).collect())
     }
 
     /// 获取用户会话列表
@@ -181,50 +719,63 @@ impl SessionService {
         limit: Option<i64>,
         offset: Option<i64>,
     ) -> Result<Vec<SessionRecord>> {
+        let db = self.pg("listing sessions by user")?;
         // 直接使用字符串 ID
         let clean_user_id = user_id.to_string();
 
         let sql = r#"
             SELECT id, device_id, user_id, status,
-                   start_time, end_time, transcription, response, audio_file_path, metadata
+                   start_time, end_time, transcription, response, audio_file_path, metadata, language
             FROM sessions
             WHERE user_id = $1
             ORDER BY start_time DESC
             LIMIT $2 OFFSET $3
         "#;
 
-        let records = sqlx::query_as::<_, SessionRecord>(sql)
+        let records = timed_query("get_user_sessions", sqlx::query_as::<_, SessionRecord>(sql)
             .bind(clean_user_id)
             .bind(limit.unwrap_or(100))
             .bind(offset.unwrap_or(0))
-            .fetch_all(self.db.as_ref())
+            .fetch_all(db))
             .await
             .map_err(DatabaseError::Connection)?;
 
-        Ok(records)
+        Ok(records.into_iter().map(|record| record.decrypted(self.encryptor.as_deref())).collect())
     }
 
     /// 获取活跃会话
     pub async fn get_active_sessions(&self) -> Result<Vec<SessionRecord>> {
-        let records = sqlx::query_as::<_, SessionRecord>(
+        let Some(db) = &self.db else {
+            let filter = SessionFilter {
+                status: Some(StoredStatus::Active),
+                ..SessionFilter::default()
+            };
+            let (sessions, _) = self.storage.list_sessions(&filter, i64::MAX, 0).await?;
+            return Ok(self.decrypted_records(sessions));
+        };
+        let records = timed_query("get_active_sessions", sqlx::query_as::<_, SessionRecord>(
             r#"
             SELECT id, device_id, user_id, status,
-                   start_time, end_time, transcription, response, audio_file_path, metadata
+                   start_time, end_time, transcription, response, audio_file_path, metadata, language
             FROM sessions
             WHERE status = 'active'
             ORDER BY start_time DESC
             "#
         )
-        .fetch_all(self.db.as_ref())
+        .fetch_all(db))
         .await
         .map_err(DatabaseError::Connection)?;
 
-        Ok(records)
+        Ok(records.into_iter().map(|record| record.decrypted(self.encryptor.as_deref())).collect())
     }
 
     /// 结束超时的会话
     pub async fn timeout_sessions(&self, timeout_minutes: i64) -> Result<u64> {
-        let result = sqlx::query(
+        let Some(db) = &self.db else {
+            let started_before = Utc::now() - chrono::Duration::minutes(timeout_minutes);
+            return Ok(self.storage.timeout_sessions(started_before).await?);
+        };
+        let result = retried_query(&self.breaker, &self.retry, "timeout_sessions", || sqlx::query(
             r#"
             UPDATE sessions
             SET status = 'timeout',
@@ -234,9 +785,8 @@ impl SessionService {
             "#
         )
         .bind(timeout_minutes)
-        .execute(self.db.as_ref())
-        .await
-        .map_err(DatabaseError::Connection)?;
+        .execute(db))
+        .await?;
 
         Ok(result.rows_affected())
     }
@@ -247,6 +797,7 @@ impl SessionService {
         device_id: Option<&str>,
         hours_back: Option<i32>,
     ) -> Result<SessionStats> {
+        let db = self.pg("session statistics")?;
         let hours = hours_back.unwrap_or(24);
 
         let (sql, device_id_value) = if let Some(did) = device_id {
@@ -285,15 +836,15 @@ impl SessionService {
         };
 
         let row = if let Some(did) = device_id_value {
-            sqlx::query(sql)
+            timed_query("session_stats", sqlx::query(sql)
                 .bind(hours)
                 .bind(did)
-                .fetch_one(self.db.as_ref())
+                .fetch_one(db))
                 .await
         } else {
-            sqlx::query(sql)
+            timed_query("session_stats", sqlx::query(sql)
                 .bind(hours)
-                .fetch_one(self.db.as_ref())
+                .fetch_one(db))
                 .await
         }.map_err(DatabaseError::Connection)?;
 
@@ -318,15 +869,18 @@ impl SessionService {
         device_id: &str,
         device_name: Option<&str>,
     ) -> Result<String, DatabaseError> {
+        let db = self.db.as_ref().ok_or_else(|| {
+            DatabaseError::InvalidInput("creating WebUI devices requires PostgreSQL storage".to_string())
+        })?;
         // 直接使用字符串 ID
         let clean_device_id = device_id.to_string();
 
         // 检查设备是否已存在
-        let exists: bool = sqlx::query_scalar(
+        let exists: bool = timed_query("device_exists", sqlx::query_scalar(
             "SELECT EXISTS(SELECT 1 FROM devices WHERE id = $1)"
         )
         .bind(&clean_device_id)
-        .fetch_one(&*self.db)
+        .fetch_one(db))
         .await
         .map_err(|e| DatabaseError::InvalidInput(format!("Failed to check device existence: {}", e)))?;
 
@@ -337,14 +891,14 @@ impl SessionService {
         // 设备不存在，创建新记录
         let name = device_name.unwrap_or("WebUI 设备");
 
-        sqlx::query(
+        guarded_query(&self.breaker, "create_device", sqlx::query(
             "INSERT INTO devices (id, name, device_type, status, created_at, updated_at)
              VALUES ($1, $2, 'web_browser', 'online', NOW(), NOW())
              ON CONFLICT (id) DO NOTHING"
         )
         .bind(&clean_device_id)
         .bind(name)
-        .execute(&*self.db)
+        .execute(db))
         .await
         .map_err(|e| DatabaseError::InvalidInput(format!("Failed to create device: {}", e)))?;
 
@@ -358,6 +912,82 @@ impl SessionService {
     }
 }
 
+/// 导入的录音会话
+#[derive(Debug, Clone)]
+pub struct ImportedSession {
+    pub session_id: String,
+    pub device_id: String,
+    /// 设备所属用户，决定脱敏设置
+    pub user_id: Option<String>,
+    /// 录音时间
+    pub started_at: DateTime<Utc>,
+    pub duration_seconds: f64,
+    pub transcript: String,
+    /// 写入 metadata.import 的来源信息（导入任务和文件名）
+    pub source: serde_json::Value,
+}
+
+/// 结束会话时一次写入的内容，为 None 的字段保留数据库中的原值
+#[derive(Debug, Clone)]
+pub struct SessionFinalization {
+    /// 结束状态：completed / failed / timeout
+    pub status: SessionStatus,
+    pub transcript: Option<String>,
+    pub response: Option<String>,
+    /// 对话轮数，写入 metadata.turns
+    pub turns: Option<i32>,
+    pub audio_url: Option<String>,
+}
+
+impl SessionFinalization {
+    pub fn new(status: SessionStatus) -> Self {
+        Self {
+            status,
+            transcript: None,
+            response: None,
+            turns: None,
+            audio_url: None,
+        }
+    }
+}
+
+/// 结束会话的结果
+#[derive(Debug)]
+pub enum Finalized {
+    /// 本次调用结束了会话
+    Ended(SessionRecord),
+    /// 会话已被其他路径结束，记录未修改
+    AlreadyEnded(SessionRecord),
+    NotFound,
+}
+
+fn status_str(status: &SessionStatus) -> &'static str {
+    match status {
+        SessionStatus::Active => "active",
+        SessionStatus::Completed => "completed",
+        SessionStatus::Failed => "failed",
+        SessionStatus::Timeout => "timeout",
+    }
+}
+
+fn stored_status(status: &SessionStatus) -> StoredStatus {
+    match status {
+        SessionStatus::Active => StoredStatus::Active,
+        SessionStatus::Completed => StoredStatus::Completed,
+        SessionStatus::Failed => StoredStatus::Failed,
+        SessionStatus::Timeout => StoredStatus::Timeout,
+    }
+}
+
+fn stored_status_str(status: &StoredStatus) -> &'static str {
+    match status {
+        StoredStatus::Active => "active",
+        StoredStatus::Completed => "completed",
+        StoredStatus::Failed => "failed",
+        StoredStatus::Timeout => "timeout",
+    }
+}
+
 // 会话统计信息
 #[derive(Debug, serde::Serialize)]
 pub struct SessionStats {
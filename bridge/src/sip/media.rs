@@ -0,0 +1,422 @@
+//! 通话媒体处理
+//!
+//! RTP 打包和解析、G.711 编解码、电话音频（8kHz）与 EchoKit 音频（16kHz）之间的重采样，
+//! 以及判断来电者一句话结束的语音检测。
+
+use std::collections::VecDeque;
+
+/// 电话音频采样率（Hz）
+pub const PHONE_SAMPLE_RATE: usize = 8_000;
+/// 每个 RTP 包 20ms
+pub const FRAME_MS: u64 = 20;
+/// 每个 RTP 包的样本数（8kHz）
+pub const FRAME_SAMPLES: usize = PHONE_SAMPLE_RATE * FRAME_MS as usize / 1000;
+
+// 连续这么多帧高于阈值才认为开始说话，避免按键声、咔嗒声触发
+const SPEECH_START_FRAMES: u32 = 3;
+
+/// 支持的语音编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    /// G.711 μ-law
+    Pcmu,
+    /// G.711 A-law
+    Pcma,
+}
+
+impl Codec {
+    /// 按 SDP rtpmap 的编码名（如 `PCMU/8000`）识别
+    pub fn from_encoding(encoding: &str) -> Option<Self> {
+        let (name, rate) = encoding.split_once('/').unwrap_or((encoding, "8000"));
+        if !rate.starts_with("8000") {
+            return None;
+        }
+        match name.to_ascii_uppercase().as_str() {
+            "PCMU" => Some(Codec::Pcmu),
+            "PCMA" => Some(Codec::Pcma),
+            _ => None,
+        }
+    }
+
+    /// SDP rtpmap 中的编码名
+    pub fn encoding(self) -> &'static str {
+        match self {
+            Codec::Pcmu => "PCMU/8000",
+            Codec::Pcma => "PCMA/8000",
+        }
+    }
+
+    pub fn decode(self, payload: &[u8]) -> Vec<i16> {
+        match self {
+            Codec::Pcmu => payload.iter().map(|&b| ulaw_to_linear(b)).collect(),
+            Codec::Pcma => payload.iter().map(|&b| alaw_to_linear(b)).collect(),
+        }
+    }
+
+    pub fn encode(self, samples: &[i16]) -> Vec<u8> {
+        match self {
+            Codec::Pcmu => samples.iter().map(|&s| linear_to_ulaw(s)).collect(),
+            Codec::Pcma => samples.iter().map(|&s| linear_to_alaw(s)).collect(),
+        }
+    }
+}
+
+const ULAW_BIAS: i32 = 0x84;
+const ULAW_CLIP: i32 = 32635;
+
+fn linear_to_ulaw(sample: i16) -> u8 {
+    let sign = if sample < 0 { 0x80 } else { 0 };
+    let magnitude = (sample as i32).abs().min(ULAW_CLIP) + ULAW_BIAS;
+    let exponent = (31 - (magnitude as u32).leading_zeros()).saturating_sub(7).min(7) as i32;
+    let mantissa = (magnitude >> (exponent + 3)) & 0x0F;
+    !(sign | (exponent << 4) as u8 | mantissa as u8)
+}
+
+fn ulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let exponent = ((byte >> 4) & 0x07) as i32;
+    let mantissa = (byte & 0x0F) as i32;
+    let magnitude = (((mantissa << 3) + ULAW_BIAS) << exponent) - ULAW_BIAS;
+    if byte & 0x80 != 0 { -magnitude as i16 } else { magnitude as i16 }
+}
+
+fn linear_to_alaw(sample: i16) -> u8 {
+    let sign = if sample < 0 { 0 } else { 0x80 };
+    // A-law 使用 13 位精度
+    let magnitude = ((sample as i32).abs().min(32767) >> 3) as u32;
+    let encoded = if magnitude < 32 {
+        (magnitude >> 1) as u8
+    } else {
+        let exponent = (31 - magnitude.leading_zeros()) - 4;
+        let mantissa = (magnitude >> exponent) & 0x0F;
+        ((exponent << 4) | mantissa) as u8
+    };
+    (sign | encoded) ^ 0x55
+}
+
+fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let exponent = ((byte >> 4) & 0x07) as i32;
+    let mantissa = (byte & 0x0F) as i32;
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 8
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+    if byte & 0x80 != 0 { magnitude as i16 } else { -magnitude as i16 }
+}
+
+/// 解析后的 RTP 包
+#[derive(Debug, PartialEq)]
+pub struct RtpPacket<'a> {
+    pub payload_type: u8,
+    pub sequence: u16,
+    pub payload: &'a [u8],
+}
+
+impl<'a> RtpPacket<'a> {
+    /// 解析 RTP 包，跳过 CSRC、扩展头和填充；不是 RTP v2 时返回 None
+    pub fn parse(data: &'a [u8]) -> Option<Self> {
+        if data.len() < 12 || data[0] >> 6 != 2 {
+            return None;
+        }
+        let csrc_count = (data[0] & 0x0F) as usize;
+        let mut start = 12 + csrc_count * 4;
+        if data[0] & 0x10 != 0 {
+            let extension = data.get(start..start + 4)?;
+            start += 4 + u16::from_be_bytes([extension[2], extension[3]]) as usize * 4;
+        }
+        let mut end = data.len();
+        if data[0] & 0x20 != 0 {
+            end = end.checked_sub(*data.last()? as usize)?;
+        }
+        Some(Self {
+            payload_type: data[1] & 0x7F,
+            sequence: u16::from_be_bytes([data[2], data[3]]),
+            payload: data.get(start..end)?,
+        })
+    }
+}
+
+/// 发送方向的 RTP 打包
+pub struct RtpSender {
+    payload_type: u8,
+    ssrc: u32,
+    sequence: u16,
+    timestamp: u32,
+    first: bool,
+}
+
+impl RtpSender {
+    pub fn new(payload_type: u8) -> Self {
+        let random = uuid::Uuid::new_v4().as_u128();
+        Self {
+            payload_type,
+            ssrc: random as u32,
+            sequence: (random >> 32) as u16,
+            timestamp: (random >> 48) as u32,
+            first: true,
+        }
+    }
+
+    /// 打包一帧 G.711 负载（每字节一个样本），第一个包设置 marker 位
+    pub fn packet(&mut self, payload: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(12 + payload.len());
+        packet.push(0x80);
+        packet.push(self.payload_type | if std::mem::take(&mut self.first) { 0x80 } else { 0 });
+        packet.extend_from_slice(&self.sequence.to_be_bytes());
+        packet.extend_from_slice(&self.timestamp.to_be_bytes());
+        packet.extend_from_slice(&self.ssrc.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.sequence = self.sequence.wrapping_add(1);
+        self.timestamp = self.timestamp.wrapping_add(payload.len() as u32);
+        packet
+    }
+}
+
+/// 8kHz → 16kHz，在相邻样本之间线性插值
+#[derive(Default)]
+pub struct Upsampler {
+    last: i16,
+}
+
+impl Upsampler {
+    pub fn process(&mut self, samples: &[i16]) -> Vec<i16> {
+        let mut output = Vec::with_capacity(samples.len() * 2);
+        for &sample in samples {
+            output.push(((self.last as i32 + sample as i32) / 2) as i16);
+            output.push(sample);
+            self.last = sample;
+        }
+        output
+    }
+}
+
+/// 16kHz → 8kHz，相邻两个样本取平均；奇数个样本时保留最后一个到下次处理
+#[derive(Default)]
+pub struct Downsampler {
+    pending: Option<i16>,
+}
+
+impl Downsampler {
+    pub fn process(&mut self, samples: &[i16]) -> Vec<i16> {
+        let mut output = Vec::with_capacity(samples.len() / 2 + 1);
+        for &sample in samples {
+            match self.pending.take() {
+                Some(previous) => output.push(((previous as i32 + sample as i32) / 2) as i16),
+                None => self.pending = Some(sample),
+            }
+        }
+        output
+    }
+}
+
+/// 16-bit PCM（小端）字节转换为样本
+pub fn pcm_to_samples(pcm: &[u8]) -> Vec<i16> {
+    pcm.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect()
+}
+
+/// 样本转换为 16-bit PCM（小端）字节
+pub fn samples_to_pcm(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+}
+
+/// 一帧样本的均方根
+pub fn rms(samples: &[i16]) -> u16 {
+    if samples.is_empty() {
+        return 0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt().min(u16::MAX as f64) as u16
+}
+
+/// 语音检测结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadEvent {
+    /// 未在说话
+    Silence,
+    /// 开始说话（本帧起）
+    SpeechStart,
+    /// 正在说话
+    Speech,
+    /// 一句话结束：静音超过阈值或达到最长时长
+    SpeechEnd,
+}
+
+/// 基于帧能量的语音检测，按帧（20ms）输入
+pub struct SpeechDetector {
+    threshold: u16,
+    end_of_speech_frames: u32,
+    max_speech_frames: u32,
+    voiced_run: u32,
+    silent_run: u32,
+    speech_frames: u32,
+    speaking: bool,
+}
+
+impl SpeechDetector {
+    pub fn new(threshold: u16, end_of_speech_ms: u64, max_utterance_seconds: u64) -> Self {
+        Self {
+            threshold,
+            end_of_speech_frames: (end_of_speech_ms / FRAME_MS).max(1) as u32,
+            max_speech_frames: (max_utterance_seconds * 1000 / FRAME_MS).max(1) as u32,
+            voiced_run: 0,
+            silent_run: 0,
+            speech_frames: 0,
+            speaking: false,
+        }
+    }
+
+    pub fn push(&mut self, frame: &[i16]) -> VadEvent {
+        let voiced = rms(frame) >= self.threshold;
+        if !self.speaking {
+            self.voiced_run = if voiced { self.voiced_run + 1 } else { 0 };
+            if self.voiced_run < SPEECH_START_FRAMES {
+                return VadEvent::Silence;
+            }
+            self.speaking = true;
+            self.silent_run = 0;
+            self.speech_frames = 0;
+            return VadEvent::SpeechStart;
+        }
+
+        self.speech_frames += 1;
+        self.silent_run = if voiced { 0 } else { self.silent_run + 1 };
+        if self.silent_run >= self.end_of_speech_frames || self.speech_frames >= self.max_speech_frames {
+            self.reset();
+            return VadEvent::SpeechEnd;
+        }
+        VadEvent::Speech
+    }
+
+    /// 回到未说话状态（如等待回复期间忽略来电者的声音）
+    pub fn reset(&mut self) {
+        self.speaking = false;
+        self.voiced_run = 0;
+        self.silent_run = 0;
+    }
+}
+
+/// 播放队列：EchoKit 的回复音频（8kHz）按 20ms 一帧取出，没有音频时输出静音以保持 RTP 连续
+pub struct Playout {
+    samples: VecDeque<i16>,
+    max_samples: usize,
+}
+
+impl Playout {
+    /// `max_seconds` 为最多缓存的音频时长，超出时丢弃最早的音频
+    pub fn new(max_seconds: usize) -> Self {
+        Self { samples: VecDeque::new(), max_samples: max_seconds * PHONE_SAMPLE_RATE }
+    }
+
+    pub fn push(&mut self, samples: &[i16]) {
+        self.samples.extend(samples);
+        let overflow = self.samples.len().saturating_sub(self.max_samples);
+        self.samples.drain(..overflow);
+    }
+
+    /// 取出一帧，不足部分补静音
+    pub fn next_frame(&mut self) -> [i16; FRAME_SAMPLES] {
+        let mut frame = [0i16; FRAME_SAMPLES];
+        let len = self.samples.len().min(FRAME_SAMPLES);
+        for (slot, sample) in frame.iter_mut().zip(self.samples.drain(..len)) {
+            *slot = sample;
+        }
+        frame
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_g711_round_trip() {
+        // ITU-T G.711 参考值
+        assert_eq!(linear_to_ulaw(0), 0xFF);
+        assert_eq!(linear_to_alaw(0), 0xD5);
+        assert_eq!(ulaw_to_linear(0x00), -32124);
+        assert_eq!(alaw_to_linear(0x55), -8);
+
+        for codec in [Codec::Pcmu, Codec::Pcma] {
+            for sample in [-32768i16, -12345, -1000, -100, -1, 0, 1, 100, 1000, 12345, 32767] {
+                let decoded = codec.decode(&codec.encode(&[sample]))[0];
+                // 对数量化的误差与幅度成比例
+                let tolerance = (sample as i32).abs() / 16 + 16;
+                assert!(
+                    (decoded as i32 - sample as i32).abs() <= tolerance,
+                    "{:?}: {} decoded as {}",
+                    codec,
+                    sample,
+                    decoded
+                );
+            }
+            // 所有码字解码后再编码不变（μ-law 的 0x7F 与 0xFF 都表示 0）
+            for byte in 0..=255u8 {
+                let sample = codec.decode(&[byte]);
+                let reencoded = codec.encode(&sample)[0];
+                assert!(reencoded == byte || (codec == Codec::Pcmu && byte == 0x7F), "{:?}: {:#x}", codec, byte);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rtp_round_trip() {
+        let mut sender = RtpSender::new(8);
+        let first = sender.packet(&[1; FRAME_SAMPLES]);
+        let second = sender.packet(&[2; FRAME_SAMPLES]);
+
+        let packet = RtpPacket::parse(&first).unwrap();
+        assert_eq!(first[1] & 0x80, 0x80);
+        assert_eq!(packet.payload_type, 8);
+        assert_eq!(packet.payload, &[1; FRAME_SAMPLES]);
+        let next = RtpPacket::parse(&second).unwrap();
+        assert_eq!(next.sequence, packet.sequence.wrapping_add(1));
+        assert_eq!(second[1] & 0x80, 0);
+
+        // 带一个 CSRC 和 4 字节填充
+        let padded = [0xA1, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 9, 9, 9, 9, 5, 6, 0, 0, 0, 4];
+        assert_eq!(RtpPacket::parse(&padded).unwrap().payload, &[5, 6]);
+        assert!(RtpPacket::parse(b"INVITE sip:1000@example.com").is_none());
+    }
+
+    #[test]
+    fn test_speech_detector_ends_after_silence() {
+        let mut detector = SpeechDetector::new(500, 100, 30);
+        let loud = [3000i16; FRAME_SAMPLES * 2];
+        let quiet = [10i16; FRAME_SAMPLES * 2];
+
+        assert_eq!(detector.push(&quiet), VadEvent::Silence);
+        assert_eq!(detector.push(&loud), VadEvent::Silence);
+        assert_eq!(detector.push(&loud), VadEvent::Silence);
+        assert_eq!(detector.push(&loud), VadEvent::SpeechStart);
+        assert_eq!(detector.push(&loud), VadEvent::Speech);
+        // 100ms = 5 帧静音后结束
+        for _ in 0..4 {
+            assert_eq!(detector.push(&quiet), VadEvent::Speech);
+        }
+        assert_eq!(detector.push(&quiet), VadEvent::SpeechEnd);
+        assert_eq!(detector.push(&quiet), VadEvent::Silence);
+    }
+
+    #[test]
+    fn test_resample_and_playout() {
+        let mut upsampler = Upsampler::default();
+        assert_eq!(upsampler.process(&[100, 200]), vec![50, 100, 150, 200]);
+        let mut downsampler = Downsampler::default();
+        assert_eq!(downsampler.process(&[100, 200, 300]), vec![150]);
+        assert_eq!(downsampler.process(&[500]), vec![400]);
+
+        let mut playout = Playout::new(1);
+        playout.push(&[7; FRAME_SAMPLES + 10]);
+        assert_eq!(playout.next_frame(), [7; FRAME_SAMPLES]);
+        let frame = playout.next_frame();
+        assert_eq!(&frame[..10], &[7; 10]);
+        assert_eq!(&frame[10..], &[0; FRAME_SAMPLES - 10]);
+        assert!(playout.is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+//! SIP 消息与 SDP 的解析和生成
+//!
+//! 只实现 Bridge 作为被叫 UAS 所需的部分：解析请求（支持紧凑头名）、按请求构造响应、
+//! 构造挂断用的 BYE 请求，以及 SDP offer 的解析和 answer 的生成。
+
+use std::fmt::Write as _;
+use std::net::{IpAddr, SocketAddr};
+
+use anyhow::{bail, Context, Result};
+
+use super::media::Codec;
+
+/// SIP 请求
+#[derive(Debug, Clone)]
+pub struct SipRequest {
+    pub method: String,
+    pub uri: String,
+    // 头名统一为完整形式，保留原始顺序（多个 Via 的顺序有意义）
+    headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl SipRequest {
+    /// 解析一个 UDP 数据包中的 SIP 请求
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let text = std::str::from_utf8(data).context("SIP message is not valid UTF-8")?;
+        let (head, body) = text.split_once("\r\n\r\n").unwrap_or((text, ""));
+        let mut lines = head.split("\r\n");
+
+        let request_line = lines.next().unwrap_or_default();
+        let mut parts = request_line.split(' ');
+        let (Some(method), Some(uri), Some("SIP/2.0")) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("Invalid SIP request line: {}", request_line);
+        };
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in lines {
+            // 以空白开头的行是上一个头的续行
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            let (name, value) = line.split_once(':').with_context(|| format!("Invalid SIP header: {}", line))?;
+            headers.push((expand_header_name(name.trim()), value.trim().to_string()));
+        }
+
+        let mut request = Self { method: method.to_string(), uri: uri.to_string(), headers, body: body.to_string() };
+        if let Some(length) = request.header("Content-Length").and_then(|v| v.parse::<usize>().ok()) {
+            if length > request.body.len() {
+                bail!("SIP body is shorter than Content-Length ({} < {})", request.body.len(), length);
+            }
+            request.body.truncate(length);
+        }
+        Ok(request)
+    }
+
+    /// 第一个同名头的值（头名不区分大小写）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    /// 所有同名头的值，按出现顺序
+    pub fn headers_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn call_id(&self) -> Option<&str> {
+        self.header("Call-ID")
+    }
+
+    /// CSeq 的序号和方法
+    pub fn cseq(&self) -> Option<(u32, &str)> {
+        let (number, method) = self.header("CSeq")?.split_once(' ')?;
+        Some((number.trim().parse().ok()?, method.trim()))
+    }
+
+    /// To 头中的 tag，对话建立前的请求没有
+    pub fn to_tag(&self) -> Option<&str> {
+        self.header("To").and_then(|to| header_param(to, "tag"))
+    }
+
+    /// Request-URI 的用户部分（被叫号码），如 `sip:1000@host` 中的 `1000`
+    pub fn request_user(&self) -> Option<&str> {
+        let uri = self.uri.split_once(':').map(|(_, rest)| rest)?;
+        let (user, _) = uri.split_once('@')?;
+        Some(user.split(';').next().unwrap_or(user)).filter(|user| !user.is_empty())
+    }
+
+    /// 对端的目标 URI（Contact，缺省时使用 From），用于向对端发起 BYE
+    pub fn remote_target(&self) -> Option<String> {
+        self.header("Contact").or_else(|| self.header("From")).and_then(header_uri)
+    }
+
+    /// 按请求构造响应：复制 Via、From、Call-ID、CSeq，To 在没有 tag 时加上本端 tag
+    pub fn response(&self, status: u16, reason: &str, local_tag: Option<&str>) -> SipResponse {
+        let mut headers = Vec::new();
+        for via in self.headers_named("Via") {
+            headers.push(("Via".to_string(), via.to_string()));
+        }
+        if let Some(from) = self.header("From") {
+            headers.push(("From".to_string(), from.to_string()));
+        }
+        if let Some(to) = self.header("To") {
+            let to = match local_tag {
+                Some(tag) if self.to_tag().is_none() => format!("{};tag={}", to, tag),
+                _ => to.to_string(),
+            };
+            headers.push(("To".to_string(), to));
+        }
+        for name in ["Call-ID", "CSeq"] {
+            if let Some(value) = self.header(name) {
+                headers.push((name.to_string(), value.to_string()));
+            }
+        }
+        SipResponse { status, reason: reason.to_string(), headers, body: None }
+    }
+}
+
+/// SIP 响应
+#[derive(Debug, Clone)]
+pub struct SipResponse {
+    pub status: u16,
+    pub reason: String,
+    headers: Vec<(String, String)>,
+    body: Option<(String, String)>,
+}
+
+impl SipResponse {
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn with_body(mut self, content_type: &str, body: String) -> Self {
+        self.body = Some((content_type.to_string(), body));
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let status_line = format!("SIP/2.0 {} {}", self.status, self.reason);
+        serialize(&status_line, &self.headers, self.body.as_ref())
+    }
+}
+
+/// 已建立的对话，Bridge 主动挂断时用于构造 BYE
+#[derive(Debug, Clone)]
+pub struct Dialog {
+    pub call_id: String,
+    /// 对端的目标 URI
+    pub remote_target: String,
+    /// 对端的 From 头（BYE 中作为 To）
+    pub remote: String,
+    /// 本端的 To 头（含 tag，BYE 中作为 From）
+    pub local: String,
+}
+
+impl Dialog {
+    /// 构造 BYE 请求，`via` 为本端的信令地址
+    pub fn bye(&self, cseq: u32, via: SocketAddr) -> Vec<u8> {
+        let request_line = format!("BYE {} SIP/2.0", self.remote_target);
+        let headers = vec![
+            ("Via".to_string(), format!("SIP/2.0/UDP {};rport;branch=z9hG4bK{}", via, new_token())),
+            ("Max-Forwards".to_string(), "70".to_string()),
+            ("From".to_string(), self.local.clone()),
+            ("To".to_string(), self.remote.clone()),
+            ("Call-ID".to_string(), self.call_id.clone()),
+            ("CSeq".to_string(), format!("{} BYE", cseq)),
+        ];
+        serialize(&request_line, &headers, None)
+    }
+}
+
+/// 判断数据包是否为 SIP 响应（Bridge 不发起事务，收到的响应只有 BYE 的应答）
+pub fn is_response(data: &[u8]) -> bool {
+    data.starts_with(b"SIP/2.0 ")
+}
+
+/// 随机的 tag / branch 标识
+pub fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+// 序列化起始行、头和正文，Content-Length 按正文计算
+fn serialize(start_line: &str, headers: &[(String, String)], body: Option<&(String, String)>) -> Vec<u8> {
+    let mut message = String::with_capacity(512);
+    message.push_str(start_line);
+    message.push_str("\r\n");
+    for (name, value) in headers {
+        let _ = write!(message, "{}: {}\r\n", name, value);
+    }
+    match body {
+        Some((content_type, body)) => {
+            let _ = write!(message, "Content-Type: {}\r\nContent-Length: {}\r\n\r\n{}", content_type, body.len(), body);
+        }
+        None => message.push_str("Content-Length: 0\r\n\r\n"),
+    }
+    message.into_bytes()
+}
+
+// 紧凑头名展开为完整形式（RFC 3261 7.3.3）
+fn expand_header_name(name: &str) -> String {
+    let full = match name.to_ascii_lowercase().as_str() {
+        "i" => "Call-ID",
+        "m" => "Contact",
+        "f" => "From",
+        "t" => "To",
+        "v" => "Via",
+        "l" => "Content-Length",
+        "c" => "Content-Type",
+        "k" => "Supported",
+        _ => return name.to_string(),
+    };
+    full.to_string()
+}
+
+// 头参数（`;name=value`），不区分大小写
+fn header_param<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    // 尖括号内的是 URI 参数，不是头参数
+    let params = value.rfind('>').map_or(value, |end| &value[end + 1..]);
+    params.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+    })
+}
+
+// 从 From / To / Contact 头中取出 URI
+fn header_uri(value: &str) -> Option<String> {
+    let uri = match (value.find('<'), value.find('>')) {
+        (Some(start), Some(end)) if start < end => &value[start + 1..end],
+        _ => value.split(';').next().unwrap_or(value).trim(),
+    };
+    (!uri.is_empty()).then(|| uri.to_string())
+}
+
+/// SDP offer 中的音频媒体描述
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaOffer {
+    /// 对端的 RTP 地址
+    pub address: SocketAddr,
+    /// 按对端偏好排序的负载类型
+    pub payload_types: Vec<u8>,
+    // 动态负载类型的编码名（如 `PCMU/8000`）
+    rtpmap: Vec<(u8, String)>,
+}
+
+impl MediaOffer {
+    /// 解析 SDP，取第一个 audio 媒体行；媒体级 c= 优先于会话级
+    pub fn parse(sdp: &str) -> Result<Self> {
+        let mut session_address = None;
+        let mut media_address = None;
+        let mut media: Option<(u16, Vec<u8>)> = None;
+        let mut rtpmap = Vec::new();
+
+        for line in sdp.lines().map(str::trim) {
+            let Some((kind, value)) = line.split_once('=') else {
+                continue;
+            };
+            match kind {
+                "c" => {
+                    let address = value.split_whitespace().nth(2).and_then(|a| a.split('/').next());
+                    let address = address.and_then(|a| a.parse::<IpAddr>().ok());
+                    if media.is_some() {
+                        media_address = address;
+                    } else {
+                        session_address = address;
+                    }
+                }
+                "m" if media.is_some() => break,
+                "m" => {
+                    let mut fields = value.split_whitespace();
+                    if fields.next() != Some("audio") {
+                        continue;
+                    }
+                    let port = fields.next().and_then(|p| p.split('/').next()?.parse().ok()).context("Invalid SDP media port")?;
+                    fields.next(); // 传输协议
+                    media = Some((port, fields.filter_map(|pt| pt.parse().ok()).collect()));
+                }
+                "a" if media.is_some() => {
+                    if let Some((pt, encoding)) = value.strip_prefix("rtpmap:").and_then(|v| v.split_once(' ')) {
+                        if let Ok(pt) = pt.parse() {
+                            rtpmap.push((pt, encoding.trim().to_string()));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let (port, payload_types) = media.context("SDP has no audio media line")?;
+        let address = media_address.or(session_address).context("SDP has no connection address")?;
+        Ok(Self { address: SocketAddr::new(address, port), payload_types, rtpmap })
+    }
+
+    /// 按对端偏好选择支持的编码（G.711 μ-law / A-law）
+    pub fn choose_codec(&self) -> Option<(u8, Codec)> {
+        self.payload_types.iter().find_map(|&pt| {
+            let encoding = self.rtpmap.iter().find(|(mapped, _)| *mapped == pt).map(|(_, encoding)| encoding.as_str());
+            let codec = match (pt, encoding) {
+                (_, Some(encoding)) => Codec::from_encoding(encoding)?,
+                (0, None) => Codec::Pcmu,
+                (8, None) => Codec::Pcma,
+                _ => return None,
+            };
+            Some((pt, codec))
+        })
+    }
+}
+
+/// 生成 SDP answer：单个音频流，20ms 打包
+pub fn answer_sdp(address: IpAddr, port: u16, payload_type: u8, codec: Codec) -> String {
+    let network = if address.is_ipv4() { "IP4" } else { "IP6" };
+    let version = chrono::Utc::now().timestamp();
+    format!(
+        "v=0\r\n\
+         o=echo-bridge {version} {version} IN {network} {address}\r\n\
+         s=echo-bridge\r\n\
+         c=IN {network} {address}\r\n\
+         t=0 0\r\n\
+         m=audio {port} RTP/AVP {payload_type}\r\n\
+         a=rtpmap:{payload_type} {encoding}\r\n\
+         a=ptime:20\r\n\
+         a=sendrecv\r\n",
+        encoding = codec.encoding(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVITE: &str = "INVITE sip:1000@bridge.example.com SIP/2.0\r\n\
+        v: SIP/2.0/UDP 10.0.0.5:5060;branch=z9hG4bK776asdhds\r\n\
+        Via: SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bKproxy\r\n\
+        From: \"Alice\" <sip:alice@example.com>;tag=1928301774\r\n\
+        To: <sip:1000@bridge.example.com>\r\n\
+        i: a84b4c76e66710@10.0.0.5\r\n\
+        CSeq: 314159 INVITE\r\n\
+        Contact: <sip:alice@10.0.0.5:5060;transport=udp>\r\n\
+        Content-Type: application/sdp\r\n\
+        Content-Length: 129\r\n\
+        \r\n\
+        v=0\r\n\
+        o=- 1 1 IN IP4 10.0.0.5\r\n\
+        s=-\r\n\
+        c=IN IP4 10.0.0.5\r\n\
+        t=0 0\r\n\
+        m=audio 40000 RTP/AVP 9 8 0 101\r\n\
+        a=rtpmap:101 telephone-event/8000\r\n";
+
+    #[test]
+    fn test_parse_invite_and_build_response() {
+        let request = SipRequest::parse(INVITE.as_bytes()).unwrap();
+        assert_eq!(request.method, "INVITE");
+        assert_eq!(request.request_user(), Some("1000"));
+        assert_eq!(request.call_id(), Some("a84b4c76e66710@10.0.0.5"));
+        assert_eq!(request.cseq(), Some((314159, "INVITE")));
+        assert_eq!(request.to_tag(), None);
+        assert_eq!(request.remote_target().as_deref(), Some("sip:alice@10.0.0.5:5060;transport=udp"));
+        assert_eq!(request.body.len(), 129);
+
+        let response = String::from_utf8(request.response(200, "OK", Some("abc")).to_bytes()).unwrap();
+        assert!(response.starts_with("SIP/2.0 200 OK\r\n"));
+        let via = response.find("branch=z9hG4bK776asdhds").unwrap();
+        assert!(via < response.find("branch=z9hG4bKproxy").unwrap());
+        assert!(response.contains("To: <sip:1000@bridge.example.com>;tag=abc\r\n"));
+        assert!(response.contains("Call-ID: a84b4c76e66710@10.0.0.5\r\n"));
+        assert!(response.ends_with("Content-Length: 0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_parse_sdp_offer() {
+        let request = SipRequest::parse(INVITE.as_bytes()).unwrap();
+        let offer = MediaOffer::parse(&request.body).unwrap();
+        assert_eq!(offer.address, "10.0.0.5:40000".parse().unwrap());
+        // G.722 不支持，按对端偏好选择 PCMA
+        assert_eq!(offer.choose_codec(), Some((8, Codec::Pcma)));
+
+        let offer = MediaOffer::parse("c=IN IP4 10.0.0.5\r\nm=audio 4000 RTP/AVP 9 101\r\n").unwrap();
+        assert_eq!(offer.choose_codec(), None);
+
+        let answer = answer_sdp("192.0.2.1".parse().unwrap(), 20000, 8, Codec::Pcma);
+        assert!(answer.contains("c=IN IP4 192.0.2.1\r\n"));
+        assert!(answer.contains("m=audio 20000 RTP/AVP 8\r\na=rtpmap:8 PCMA/8000\r\n"));
+    }
+}
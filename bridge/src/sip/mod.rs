@@ -0,0 +1,783 @@
+//! SIP 电话接入
+//!
+//! Bridge 作为 SIP 终端（UAS）接听来电：被叫号码按 `bridge.sip.extensions` 映射到设备，每路通话使用
+//! 独立的 EchoKit 连接和一个 RTP 端口。来电者的 G.711 语音转换为 16kHz PCM 发给 EchoKit，由能量检测
+//! 判断一句话结束后提交；EchoKit 的问候和回复音频转换回 G.711，按 20ms 节奏播放给来电者。
+//! 通话与设备 WebSocket 会话一样登记在会话管理器中，转写、回复、轮数和会话配额都按设备所有者记录。
+//!
+//! 只支持 UDP 信令和 G.711 (PCMU/PCMA)，不支持注册、鉴权和 SRTP，应经由 SIP 中继或 PBX 转入，
+//! 信令来源由 ip_filter 规则限制。播放回复期间不处理来电者的语音（不支持打断）。
+
+mod media;
+mod message;
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use echo_shared::{
+    AudioFormat, DeviceId, EchoKitEvent, IngressLimitsConfig, IpFilterHandle, SessionId, SessionLimitExceeded, SipConfig,
+    UserId,
+};
+use sqlx::PgPool;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, error, info, warn};
+
+use crate::asr_postprocess::{self, AsrPostProcessing};
+use crate::channels::{self, AudioReceiver, ControlReceiver};
+use crate::echokit_client::EchoKitClient;
+use crate::session_service::SessionService;
+use crate::slow_ops::timed_query;
+use crate::telemetry;
+use crate::websocket::session_manager::{SessionManager, SessionStatus};
+use media::{
+    Codec, Downsampler, Playout, RtpPacket, RtpSender, SpeechDetector, Upsampler, VadEvent, FRAME_MS, FRAME_SAMPLES,
+};
+use message::{Dialog, MediaOffer, SipRequest};
+
+// 支持的 SIP 方法
+const ALLOW: &str = "INVITE, ACK, BYE, CANCEL, OPTIONS";
+// UDP 上的 SIP 消息不超过一个数据报
+const MAX_SIP_MESSAGE: usize = 65_535;
+// 200 OK 的重传间隔（RFC 3261 的 T1 / T2），收到 ACK 前按指数退避重传
+const T1: Duration = Duration::from_millis(500);
+const T2: Duration = Duration::from_secs(4);
+// 超过该时间（64*T1）仍未收到 ACK 则挂断
+const ACK_TIMEOUT: Duration = Duration::from_secs(32);
+// 提交后等待 EchoKit 回复结束的最长时间，超出后恢复收听
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+// 刷新会话活动时间、检查会话是否已在别处结束的间隔
+const ACTIVITY_INTERVAL: Duration = Duration::from_secs(5);
+// 16kHz 下一帧（20ms）的样本数，语音检测按帧进行
+const CALLER_FRAME_SAMPLES: usize = FRAME_SAMPLES * 2;
+// 开始说话前保留的帧数（300ms），避免截掉第一个字
+const PRE_ROLL_FRAMES: usize = 15;
+// 每 100ms 向 EchoKit 发送一次音频
+const SEND_SAMPLES: usize = CALLER_FRAME_SAMPLES * 5;
+// 播放队列最多缓存的回复音频（秒）
+const MAX_PLAYOUT_SECONDS: usize = 120;
+// EchoKit 音频回调的队列容量（帧），需要容纳通话接通前收到的问候音频
+const AUDIO_CALLBACK_CAPACITY: usize = 1024;
+// EchoKit 以该标记通知一轮回复结束
+const END_RESPONSE_MARKER: &str = "__END_RESPONSE__";
+
+/// SIP 网关：接收信令并为每路通话启动媒体任务
+pub struct SipGateway {
+    config: SipConfig,
+    socket: UdpSocket,
+    // 写入 SDP、Contact 和 Via 的本端地址
+    public_ip: IpAddr,
+    signaling_addr: SocketAddr,
+    db: Arc<PgPool>,
+    session_service: Arc<SessionService>,
+    session_manager: Arc<SessionManager>,
+    ingress_limits: IngressLimitsConfig,
+    asr_postprocessing: Option<Arc<AsrPostProcessing>>,
+    ip_filter: Option<IpFilterHandle>,
+    calls: Mutex<HashMap<String, CallHandle>>,
+    // 下一次分配 RTP 端口时的起点，依次轮换
+    next_rtp_port: AtomicU16,
+}
+
+// 信令循环持有的通话状态
+struct CallHandle {
+    signals: mpsc::UnboundedSender<CallSignal>,
+    local_tag: String,
+    // 最近一次对初始 INVITE 的响应，收到重传的 INVITE 时重发
+    invite_response: Option<Vec<u8>>,
+    // 已接通时的 SDP answer，用于应答对话内的 re-INVITE
+    answer_sdp: Option<String>,
+}
+
+// 信令循环转交给通话任务的请求
+enum CallSignal {
+    Ack,
+    Bye,
+    Cancel,
+}
+
+// 拒绝来电的响应和计入指标的结果
+struct Rejection {
+    status: u16,
+    reason: &'static str,
+    outcome: &'static str,
+}
+
+impl Rejection {
+    fn new(status: u16, reason: &'static str, outcome: &'static str) -> Self {
+        Self { status, reason, outcome }
+    }
+}
+
+// 被叫设备
+struct CallDevice {
+    id: DeviceId,
+    owner: Option<UserId>,
+    echokit_url: String,
+}
+
+// 已建立的通话
+struct Call {
+    call_id: String,
+    dialog: Dialog,
+    // 发送 INVITE 的信令地址，200 OK 重传和 BYE 都发往这里
+    peer: SocketAddr,
+    ok_response: Vec<u8>,
+    session_id: SessionId,
+    device_id: DeviceId,
+    client: EchoKitClient,
+    callbacks: Callbacks,
+    rtp: UdpSocket,
+    remote_rtp: SocketAddr,
+    payload_type: u8,
+    codec: Codec,
+}
+
+// EchoKit 连接的回调接收端
+struct Callbacks {
+    audio: AudioReceiver<(String, Vec<u8>)>,
+    asr: ControlReceiver<(String, String)>,
+    response: ControlReceiver<(String, String)>,
+}
+
+impl SipGateway {
+    /// 绑定信令端口
+    pub async fn bind(
+        config: SipConfig,
+        db: Arc<PgPool>,
+        session_service: Arc<SessionService>,
+        session_manager: Arc<SessionManager>,
+    ) -> Result<Self> {
+        let socket = UdpSocket::bind(&config.bind_address)
+            .await
+            .with_context(|| format!("Failed to bind SIP socket on {}", config.bind_address))?;
+        let local_addr = socket.local_addr()?;
+        let public_ip = match config.public_address.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => local_addr.ip(),
+        };
+        Ok(Self {
+            next_rtp_port: AtomicU16::new(config.rtp_port_min),
+            config,
+            socket,
+            public_ip,
+            signaling_addr: SocketAddr::new(public_ip, local_addr.port()),
+            db,
+            session_service,
+            session_manager,
+            ingress_limits: IngressLimitsConfig::default(),
+            asr_postprocessing: None,
+            ip_filter: None,
+            calls: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 接收 EchoKit 消息时的大小限制，与设备会话的 EchoKit 连接相同
+    pub fn with_ingress_limits(mut self, ingress_limits: IngressLimitsConfig) -> Self {
+        self.ingress_limits = ingress_limits;
+        self
+    }
+
+    /// 识别结果保存前经过的 ASR 文本后处理
+    pub fn with_asr_postprocessing(mut self, asr_postprocessing: Arc<AsrPostProcessing>) -> Self {
+        self.asr_postprocessing = Some(asr_postprocessing);
+        self
+    }
+
+    /// 按 ip_filter 规则过滤信令来源
+    pub fn with_ip_filter(mut self, ip_filter: IpFilterHandle) -> Self {
+        self.ip_filter = Some(ip_filter);
+        self
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.signaling_addr
+    }
+
+    /// 信令接收循环
+    pub async fn run(self: Arc<Self>) {
+        let mut buffer = vec![0u8; MAX_SIP_MESSAGE];
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(e) => {
+                    warn!("Failed to receive SIP message: {}", e);
+                    continue;
+                }
+            };
+            if let Some(ip_filter) = &self.ip_filter {
+                if let Err(blocked) = ip_filter.current().check_ip(Some(addr.ip())) {
+                    debug!("Dropping SIP message from {}: {:?}", addr, blocked);
+                    telemetry::record_ip_blocked("sip", blocked.reason());
+                    continue;
+                }
+            }
+
+            let data = &buffer[..len];
+            if message::is_response(data) {
+                debug!("Ignoring SIP response from {}", addr);
+                continue;
+            }
+            match SipRequest::parse(data) {
+                Ok(request) => self.handle_request(request, addr).await,
+                Err(e) => debug!("Dropping malformed SIP message from {}: {:#}", addr, e),
+            }
+        }
+    }
+
+    async fn handle_request(self: &Arc<Self>, request: SipRequest, addr: SocketAddr) {
+        let Some(call_id) = request.call_id().map(str::to_string) else {
+            debug!("Dropping SIP {} without Call-ID from {}", request.method, addr);
+            return;
+        };
+        if request.cseq().is_none_or(|(_, method)| method != request.method) {
+            debug!("Dropping SIP {} with invalid CSeq from {}", request.method, addr);
+            return;
+        }
+
+        match request.method.as_str() {
+            "INVITE" => self.handle_invite(request, call_id, addr).await,
+            // ACK 没有响应；对拒绝响应的 ACK 找不到通话，直接忽略
+            "ACK" => {
+                self.signal(&call_id, CallSignal::Ack);
+            }
+            "BYE" | "CANCEL" => {
+                let signal = if request.method == "BYE" { CallSignal::Bye } else { CallSignal::Cancel };
+                let response = if self.signal(&call_id, signal) {
+                    request.response(200, "OK", None)
+                } else {
+                    request.response(481, "Call/Transaction Does Not Exist", None)
+                };
+                self.send(&response.to_bytes(), addr).await;
+            }
+            "OPTIONS" => {
+                let response = request
+                    .response(200, "OK", Some(&message::new_token()))
+                    .with_header("Allow", ALLOW)
+                    .with_header("Accept", "application/sdp");
+                self.send(&response.to_bytes(), addr).await;
+            }
+            _ => {
+                let response = request.response(405, "Method Not Allowed", None).with_header("Allow", ALLOW);
+                self.send(&response.to_bytes(), addr).await;
+            }
+        }
+    }
+
+    async fn handle_invite(self: &Arc<Self>, request: SipRequest, call_id: String, addr: SocketAddr) {
+        // 已知的通话：重传的初始 INVITE 重发上次的响应，对话内的 re-INVITE（如会话刷新）沿用原来的媒体参数
+        let existing = {
+            let calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            calls.get(&call_id).map(|call| (call.invite_response.clone(), call.answer_sdp.clone(), call.local_tag.clone()))
+        };
+        if let Some((last_response, answer_sdp, local_tag)) = existing {
+            let response = match (request.to_tag(), answer_sdp) {
+                (Some(_), Some(sdp)) => self.ok_response(&request, &local_tag, sdp),
+                (Some(_), None) => request.response(491, "Request Pending", None).to_bytes(),
+                (None, _) => last_response.unwrap_or_else(|| request.response(100, "Trying", None).to_bytes()),
+            };
+            self.send(&response, addr).await;
+            return;
+        }
+        if request.to_tag().is_some() {
+            let response = request.response(481, "Call/Transaction Does Not Exist", None);
+            self.send(&response.to_bytes(), addr).await;
+            return;
+        }
+
+        self.send(&request.response(100, "Trying", None).to_bytes(), addr).await;
+
+        let local_tag = message::new_token();
+        let (signals_tx, signals_rx) = mpsc::unbounded_channel();
+        let accepted = {
+            let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            let accepted = calls.len() < self.config.max_calls;
+            if accepted {
+                calls.insert(
+                    call_id.clone(),
+                    CallHandle { signals: signals_tx, local_tag: local_tag.clone(), invite_response: None, answer_sdp: None },
+                );
+            }
+            accepted
+        };
+        if !accepted {
+            warn!("Rejecting SIP call {} from {}: {} calls in progress", call_id, addr, self.config.max_calls);
+            self.reject(&request, &local_tag, addr, Rejection::new(486, "Busy Here", "busy")).await;
+            return;
+        }
+
+        let gateway = self.clone();
+        tokio::spawn(async move {
+            gateway.run_call(request, call_id, local_tag, addr, signals_rx).await;
+        });
+    }
+
+    // 处理一路来电直到结束
+    async fn run_call(
+        self: Arc<Self>,
+        invite: SipRequest,
+        call_id: String,
+        local_tag: String,
+        addr: SocketAddr,
+        mut signals: mpsc::UnboundedReceiver<CallSignal>,
+    ) {
+        let outcome = match self.accept(&invite, &call_id, &local_tag, addr).await {
+            Ok(call) => {
+                // 接通前被取消（CANCEL）
+                let mut cancelled = false;
+                while let Ok(signal) = signals.try_recv() {
+                    cancelled |= matches!(signal, CallSignal::Cancel);
+                }
+                if cancelled {
+                    info!("SIP call {} cancelled before it was answered", call_id);
+                    self.reject(&invite, &local_tag, addr, Rejection::new(487, "Request Terminated", "cancelled"))
+                        .await;
+                    self.hang_up(call, false).await;
+                    "cancelled"
+                } else {
+                    self.answer(call, &mut signals).await;
+                    "answered"
+                }
+            }
+            Err(rejection) => {
+                let outcome = rejection.outcome;
+                self.reject(&invite, &local_tag, addr, rejection).await;
+                outcome
+            }
+        };
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).remove(&call_id);
+        telemetry::record_sip_call(outcome);
+    }
+
+    // 查找被叫设备、协商媒体并建立 EchoKit 连接和会话
+    async fn accept(&self, invite: &SipRequest, call_id: &str, local_tag: &str, addr: SocketAddr) -> Result<Call, Rejection> {
+        let extension = invite.request_user().unwrap_or_default();
+        let Some(device_id) = self.route(extension) else {
+            info!("Rejecting SIP call {} from {}: no device for extension '{}'", call_id, addr, extension);
+            return Err(Rejection::new(404, "Not Found", "not_found"));
+        };
+        let (Some(remote_target), Some(from), Some(to)) = (invite.remote_target(), invite.header("From"), invite.header("To"))
+        else {
+            return Err(Rejection::new(400, "Bad Request", "failed"));
+        };
+
+        let offer = MediaOffer::parse(&invite.body).ok();
+        let Some((offer, (payload_type, codec))) = offer.and_then(|offer| offer.choose_codec().map(|codec| (offer, codec)))
+        else {
+            info!("Rejecting SIP call {} from {}: no supported codec (PCMU/PCMA) offered", call_id, addr);
+            return Err(Rejection::new(488, "Not Acceptable Here", "unsupported_media"));
+        };
+
+        let device = match self.find_device(&device_id).await {
+            Ok(Some(device)) => device,
+            Ok(None) => {
+                warn!("Rejecting SIP call {}: device {} not found", call_id, device_id);
+                return Err(Rejection::new(404, "Not Found", "not_found"));
+            }
+            Err(e) => {
+                error!("Failed to look up device {} for SIP call {}: {:#}", device_id, call_id, e);
+                return Err(Rejection::new(500, "Server Internal Error", "failed"));
+            }
+        };
+
+        let Some(rtp) = self.allocate_rtp_socket().await else {
+            warn!("Rejecting SIP call {}: no free RTP port in {}-{}", call_id, self.config.rtp_port_min, self.config.rtp_port_max);
+            return Err(Rejection::new(503, "Service Unavailable", "failed"));
+        };
+
+        // 持久化到数据库（同时检查会话配额）
+        let session_id = SessionId::new(format!("session_{}", uuid::Uuid::new_v4()));
+        if let Err(e) = self
+            .session_service
+            .create_session(&session_id, &device.id, device.owner.as_ref(), Some("sip_call".to_string()), None)
+            .await
+        {
+            if let Some(exceeded) = e.downcast_ref::<SessionLimitExceeded>() {
+                info!("Rejecting SIP call {} to device {}: {}", call_id, device.id, exceeded);
+                return Err(Rejection::new(486, "Busy Here", "limit"));
+            }
+            error!("Failed to persist session {} to database: {}", session_id, e);
+        }
+        if let Err(e) = self.session_manager.create_session(session_id.clone(), device.id.clone()).await {
+            error!("Failed to register session {} for SIP call {}: {}", session_id, call_id, e);
+        }
+
+        let (client, callbacks) = match self.connect_echokit(&device, &session_id).await {
+            Ok(connected) => connected,
+            Err(e) => {
+                error!("Failed to connect SIP call {} to EchoKit: {:#}", call_id, e);
+                telemetry::record_session_failed("echokit");
+                self.end_session(&session_id, SessionStatus::Failed).await;
+                return Err(Rejection::new(503, "Service Unavailable", "failed"));
+            }
+        };
+
+        let rtp_port = rtp.local_addr().map(|addr| addr.port()).unwrap_or_default();
+        let sdp = message::answer_sdp(self.public_ip, rtp_port, payload_type, codec);
+        let ok_response = self.ok_response(invite, local_tag, sdp.clone());
+        if let Some(call) = self.calls.lock().unwrap_or_else(|e| e.into_inner()).get_mut(call_id) {
+            call.invite_response = Some(ok_response.clone());
+            call.answer_sdp = Some(sdp);
+        }
+
+        info!(
+            "SIP call {} from {} answered for device {} (session {}, {}, RTP port {})",
+            call_id,
+            addr,
+            device.id,
+            session_id,
+            codec.encoding(),
+            rtp_port
+        );
+        Ok(Call {
+            call_id: call_id.to_string(),
+            dialog: Dialog {
+                call_id: call_id.to_string(),
+                remote_target,
+                remote: from.to_string(),
+                local: format!("{};tag={}", to, local_tag),
+            },
+            peer: addr,
+            ok_response,
+            session_id,
+            device_id: device.id,
+            client,
+            callbacks,
+            rtp,
+            remote_rtp: offer.address,
+            payload_type,
+            codec,
+        })
+    }
+
+    // 被叫号码对应的设备，未配置的号码使用默认设备
+    fn route(&self, extension: &str) -> Option<DeviceId> {
+        self.config
+            .extensions
+            .get(extension)
+            .or(Some(&self.config.default_device_id))
+            .filter(|device_id| !device_id.is_empty())
+            .map(|device_id| DeviceId::from(device_id.as_str()))
+    }
+
+    async fn find_device(&self, device_id: &DeviceId) -> Result<Option<CallDevice>> {
+        let row = timed_query("sip_device", sqlx::query_as::<_, (String, Option<String>, String)>(
+            "SELECT id, owner, echokit_server_url FROM devices WHERE id = $1",
+        )
+        .bind(device_id.as_str())
+        .fetch_optional(self.db.as_ref()))
+        .await?;
+
+        Ok(row.map(|(id, owner, echokit_url)| CallDevice {
+            id: DeviceId::from(id),
+            owner: owner.map(UserId::from),
+            echokit_url,
+        }))
+    }
+
+    // 在配置的范围内绑定一个空闲的 RTP 端口，绑定失败说明端口已被占用
+    async fn allocate_rtp_socket(&self) -> Option<UdpSocket> {
+        let (min, max) = (self.config.rtp_port_min, self.config.rtp_port_max);
+        let bind_ip = self.socket.local_addr().ok()?.ip();
+        for _ in min..=max {
+            let port = self
+                .next_rtp_port
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |port| Some(if port >= max { min } else { port + 1 }))
+                .unwrap_or(min);
+            if let Ok(socket) = UdpSocket::bind(SocketAddr::new(bind_ip, port)).await {
+                return Some(socket);
+            }
+        }
+        None
+    }
+
+    // 为通话建立独立的 EchoKit 连接；先登记会话再连接，以便收到连接后的问候音频
+    async fn connect_echokit(&self, device: &CallDevice, session_id: &SessionId) -> Result<(EchoKitClient, Callbacks)> {
+        let (audio_tx, audio) = channels::audio_channel("sip_audio", AUDIO_CALLBACK_CAPACITY);
+        let (asr_tx, asr) = channels::control_channel("sip_asr", 16);
+        let (response_tx, response) = channels::control_channel("sip_response", 64);
+        let client = EchoKitClient::builder(&device.echokit_url)
+            .audio_callback(audio_tx)
+            .asr_callback(asr_tx)
+            .response_callback(response_tx)
+            .ingress_limits(self.ingress_limits.clone())
+            .build()?;
+        client.pre_register_session(session_id.to_string(), device.id.to_string()).await;
+        client.connect_with_device_id(Some(device.id.as_str())).await?;
+        Ok((client, Callbacks { audio, asr, response }))
+    }
+
+    // 接通后的媒体循环，返回时通话已结束
+    async fn answer(&self, mut call: Call, signals: &mut mpsc::UnboundedReceiver<CallSignal>) {
+        self.send(&call.ok_response, call.peer).await;
+
+        let answered_at = Instant::now();
+        let deadline = answered_at + Duration::from_secs(self.config.max_call_seconds);
+        let rtp_timeout = Duration::from_secs(self.config.rtp_timeout_seconds);
+        let mut acked = false;
+        let mut retransmit_interval = T1;
+        let mut next_retransmit = answered_at + T1;
+        let mut last_rtp = answered_at;
+        // 对称 RTP：以收到的第一个包的来源为准（NAT 后的地址可能与 SDP 不同）
+        let mut latched = false;
+
+        let mut rtp_sender = RtpSender::new(call.payload_type);
+        let mut upsampler = Upsampler::default();
+        let mut downsampler = Downsampler::default();
+        let mut playout = Playout::new(MAX_PLAYOUT_SECONDS);
+        let mut caller = Conversation::new(&self.config);
+        let mut caller_samples: Vec<i16> = Vec::with_capacity(CALLER_FRAME_SAMPLES * 2);
+
+        let mut ticker = tokio::time::interval(Duration::from_millis(FRAME_MS));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut activity = tokio::time::interval(ACTIVITY_INTERVAL);
+        let mut packet = [0u8; 2048];
+
+        let reason = loop {
+            tokio::select! {
+                signal = signals.recv() => match signal {
+                    Some(CallSignal::Ack) => acked = true,
+                    Some(CallSignal::Bye) | None => break "caller_hangup",
+                    // 已接通后的 CANCEL 不起作用（RFC 3261 9.2），对端会改发 BYE
+                    Some(CallSignal::Cancel) => {}
+                },
+                received = call.rtp.recv_from(&mut packet) => {
+                    let Ok((len, from)) = received else {
+                        continue;
+                    };
+                    let Some(rtp) = RtpPacket::parse(&packet[..len]) else {
+                        continue;
+                    };
+                    // 只处理协商的语音编码，忽略 DTMF 和舒适噪声
+                    if rtp.payload_type != call.payload_type || (latched && from != call.remote_rtp) {
+                        continue;
+                    }
+                    if !latched {
+                        call.remote_rtp = from;
+                        latched = true;
+                    }
+                    last_rtp = Instant::now();
+                    telemetry::record_audio_bytes("in", "sip", len);
+
+                    caller_samples.extend(upsampler.process(&call.codec.decode(rtp.payload)));
+                    while caller_samples.len() >= CALLER_FRAME_SAMPLES {
+                        let frame: Vec<i16> = caller_samples.drain(..CALLER_FRAME_SAMPLES).collect();
+                        caller.on_frame(frame, !playout.is_empty(), &call, &self.session_manager).await;
+                    }
+                }
+                Some((_, data)) = call.callbacks.audio.recv() => {
+                    if let Some(pcm) = response_audio(&data) {
+                        playout.push(&downsampler.process(&media::pcm_to_samples(&pcm)));
+                    }
+                }
+                Some((_, text)) = call.callbacks.asr.recv() => {
+                    if !text.trim().is_empty() {
+                        let text = asr_postprocess::apply(self.asr_postprocessing.as_deref(), call.device_id.as_str(), text).await;
+                        self.session_manager.append_transcript(&call.session_id, text).await;
+                    }
+                }
+                Some((_, text)) = call.callbacks.response.recv() => {
+                    if text == END_RESPONSE_MARKER {
+                        self.session_manager.finalize_current_round_response(&call.session_id).await;
+                        caller.response_ended();
+                    } else {
+                        self.session_manager.append_response(&call.session_id, text).await;
+                    }
+                }
+                _ = ticker.tick() => {
+                    let frame = playout.next_frame();
+                    let rtp = rtp_sender.packet(&call.codec.encode(&frame));
+                    match call.rtp.send_to(&rtp, call.remote_rtp).await {
+                        Ok(sent) => telemetry::record_audio_bytes("out", "sip", sent),
+                        Err(e) => debug!("Failed to send RTP for SIP call {}: {}", call.call_id, e),
+                    }
+                    caller.resume_listening(playout.is_empty());
+
+                    let now = Instant::now();
+                    if !acked && now >= next_retransmit {
+                        if now - answered_at >= ACK_TIMEOUT {
+                            break "no_ack";
+                        }
+                        self.send(&call.ok_response, call.peer).await;
+                        retransmit_interval = (retransmit_interval * 2).min(T2);
+                        next_retransmit = now + retransmit_interval;
+                    }
+                    if now - last_rtp >= rtp_timeout {
+                        break "rtp_timeout";
+                    }
+                    if now >= deadline {
+                        break "max_duration";
+                    }
+                }
+                _ = activity.tick() => {
+                    // 会话被管理员或超时清理结束时挂断
+                    if !self.session_manager.is_active(&call.session_id).await {
+                        break "session_ended";
+                    }
+                    if let Err(e) = self.session_manager.update_activity(&call.session_id).await {
+                        debug!("Failed to update activity of session {}: {}", call.session_id, e);
+                    }
+                }
+            }
+        };
+
+        info!("SIP call {} ended: {}", call.call_id, reason);
+        self.hang_up(call, reason != "caller_hangup").await;
+    }
+
+    // 结束通话：需要时向对端发送 BYE，断开 EchoKit 并结束会话
+    async fn hang_up(&self, call: Call, send_bye: bool) {
+        if send_bye {
+            // 本端在对话中没有发过其他请求，CSeq 从 1 开始
+            self.send(&call.dialog.bye(1, self.signaling_addr), call.peer).await;
+        }
+        if let Err(e) = call.client.disconnect().await {
+            warn!("Failed to disconnect SIP call {} from EchoKit: {}", call.call_id, e);
+        }
+        self.end_session(&call.session_id, SessionStatus::Completed).await;
+    }
+
+    async fn end_session(&self, session_id: &SessionId, status: SessionStatus) {
+        if let Err(e) = self.session_manager.finalize(session_id, status, &self.session_service).await {
+            error!("Failed to finalize session {}: {}", session_id, e);
+        }
+    }
+
+    fn ok_response(&self, request: &SipRequest, local_tag: &str, sdp: String) -> Vec<u8> {
+        let contact = format!("<sip:{}@{}>", request.request_user().unwrap_or("echo"), self.signaling_addr);
+        request
+            .response(200, "OK", Some(local_tag))
+            .with_header("Contact", contact)
+            .with_header("Allow", ALLOW)
+            .with_body("application/sdp", sdp)
+            .to_bytes()
+    }
+
+    async fn reject(&self, request: &SipRequest, local_tag: &str, addr: SocketAddr, rejection: Rejection) {
+        let response = request.response(rejection.status, rejection.reason, Some(local_tag));
+        self.send(&response.to_bytes(), addr).await;
+    }
+
+    // 把请求交给通话任务，通话不存在时返回 false
+    fn signal(&self, call_id: &str, signal: CallSignal) -> bool {
+        let calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+        calls.get(call_id).is_some_and(|call| call.signals.send(signal).is_ok())
+    }
+
+    async fn send(&self, data: &[u8], addr: SocketAddr) {
+        if let Err(e) = self.socket.send_to(data, addr).await {
+            warn!("Failed to send SIP message to {}: {}", addr, e);
+        }
+    }
+}
+
+// 一轮对话的阶段
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Turn {
+    /// 等待来电者开始说话
+    Listening,
+    /// 来电者正在说话，音频持续发给 EchoKit
+    Speaking,
+    /// 已提交，等待 EchoKit 回复并播放完毕
+    Responding { since: Instant, ended: bool },
+}
+
+// 来电者一侧的对话状态：语音检测、预录缓冲和待发送的音频
+struct Conversation {
+    detector: SpeechDetector,
+    pre_roll: VecDeque<Vec<i16>>,
+    outgoing: Vec<i16>,
+    turn: Turn,
+}
+
+impl Conversation {
+    fn new(config: &SipConfig) -> Self {
+        Self {
+            detector: SpeechDetector::new(config.vad_threshold, config.end_of_speech_ms, config.max_utterance_seconds),
+            pre_roll: VecDeque::with_capacity(PRE_ROLL_FRAMES),
+            outgoing: Vec::with_capacity(SEND_SAMPLES),
+            turn: Turn::Listening,
+        }
+    }
+
+    // 处理来电者的一帧 16kHz 音频；`playing` 表示正在播放问候或回复，此时不收听
+    async fn on_frame(&mut self, frame: Vec<i16>, playing: bool, call: &Call, session_manager: &SessionManager) {
+        match self.turn {
+            Turn::Responding { .. } => {}
+            Turn::Listening if playing => {}
+            Turn::Listening => {
+                let event = self.detector.push(&frame);
+                if self.pre_roll.len() == PRE_ROLL_FRAMES {
+                    self.pre_roll.pop_front();
+                }
+                self.pre_roll.push_back(frame);
+                if event == VadEvent::SpeechStart {
+                    debug!("Caller started speaking in session {}", call.session_id);
+                    if let Err(e) = call.client.send_start_chat_command().await {
+                        warn!("Failed to send StartChat for session {}: {}", call.session_id, e);
+                    }
+                    self.outgoing.extend(self.pre_roll.drain(..).flatten());
+                    self.turn = Turn::Speaking;
+                }
+            }
+            Turn::Speaking => {
+                let event = self.detector.push(&frame);
+                self.outgoing.extend(frame);
+                let ended = event == VadEvent::SpeechEnd;
+                if self.outgoing.len() >= SEND_SAMPLES || ended {
+                    self.flush(call).await;
+                }
+                if ended {
+                    debug!("Caller finished speaking in session {}, submitting", call.session_id);
+                    if let Err(e) = call.client.send_submit_command().await {
+                        warn!("Failed to submit audio for session {}: {}", call.session_id, e);
+                    }
+                    session_manager.mark_submitted(&call.session_id).await;
+                    self.turn = Turn::Responding { since: Instant::now(), ended: false };
+                }
+            }
+        }
+    }
+
+    async fn flush(&mut self, call: &Call) {
+        let pcm = media::samples_to_pcm(&self.outgoing);
+        self.outgoing.clear();
+        if let Err(e) = call
+            .client
+            .send_audio_data(call.session_id.to_string(), call.device_id.to_string(), pcm, AudioFormat::PCM16, false)
+            .await
+        {
+            warn!("Failed to send caller audio for session {}: {}", call.session_id, e);
+        }
+    }
+
+    fn response_ended(&mut self) {
+        if let Turn::Responding { ended, .. } = &mut self.turn {
+            *ended = true;
+        }
+    }
+
+    // 回复结束且播放完毕（或等待超时）后恢复收听
+    fn resume_listening(&mut self, playout_empty: bool) {
+        if let Turn::Responding { since, ended } = self.turn {
+            if (ended && playout_empty) || since.elapsed() >= RESPONSE_TIMEOUT {
+                self.detector.reset();
+                self.pre_roll.clear();
+                self.turn = Turn::Listening;
+            }
+        }
+    }
+}
+
+// EchoKit 音频回调中的问候或回复音频（16kHz PCM）；其他事件和重复转发的原始数据忽略
+fn response_audio(data: &[u8]) -> Option<Vec<u8>> {
+    match EchoKitEvent::from_messagepack(data) {
+        Ok(EchoKitEvent::AudioChunk { data } | EchoKitEvent::HelloChunk { data }) => Some(data),
+        _ => None,
+    }
+}
@@ -0,0 +1,111 @@
+//! 慢查询与锁争用检测
+//!
+//! `timed_query` 记录数据库查询耗时，`TimedRwLock` 记录锁的等待和持有时间，
+//! 超过 `bridge.slow_query_threshold_ms` / `bridge.slow_lock_threshold_ms` 时记录警告
+//! 并计入 `/metrics`（按查询名和锁名区分），用于定位争用热点。
+
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::warn;
+
+use crate::telemetry;
+
+static SLOW_QUERY_THRESHOLD_MS: AtomicU64 = AtomicU64::new(200);
+static SLOW_LOCK_THRESHOLD_MS: AtomicU64 = AtomicU64::new(50);
+
+/// 设置检测阈值（毫秒），0 表示不检测
+pub fn configure(slow_query_ms: u64, slow_lock_ms: u64) {
+    SLOW_QUERY_THRESHOLD_MS.store(slow_query_ms, Ordering::Relaxed);
+    SLOW_LOCK_THRESHOLD_MS.store(slow_lock_ms, Ordering::Relaxed);
+}
+
+fn exceeds(threshold: &AtomicU64, elapsed: Duration) -> bool {
+    let threshold = threshold.load(Ordering::Relaxed);
+    threshold > 0 && elapsed >= Duration::from_millis(threshold)
+}
+
+/// 执行一次数据库查询并记录耗时，`name` 用作指标标签
+pub async fn timed_query<F: Future>(name: &'static str, query: F) -> F::Output {
+    let start = Instant::now();
+    let output = query.await;
+    let elapsed = start.elapsed();
+
+    telemetry::record_query_duration(name, elapsed);
+    if exceeds(&SLOW_QUERY_THRESHOLD_MS, elapsed) {
+        warn!("Slow query {} took {}ms", name, elapsed.as_millis());
+        telemetry::record_slow_query(name);
+    }
+    output
+}
+
+/// 记录等待和持有时间的读写锁
+pub struct TimedRwLock<T> {
+    name: &'static str,
+    inner: RwLock<T>,
+}
+
+impl<T> TimedRwLock<T> {
+    pub fn new(name: &'static str, value: T) -> Self {
+        Self { name, inner: RwLock::new(value) }
+    }
+
+    pub async fn read(&self) -> TimedGuard<RwLockReadGuard<'_, T>> {
+        let start = Instant::now();
+        let guard = self.inner.read().await;
+        TimedGuard::acquired(self.name, "read", start, guard)
+    }
+
+    pub async fn write(&self) -> TimedGuard<RwLockWriteGuard<'_, T>> {
+        let start = Instant::now();
+        let guard = self.inner.write().await;
+        TimedGuard::acquired(self.name, "write", start, guard)
+    }
+}
+
+/// 释放时检查持有时间的锁守卫
+pub struct TimedGuard<G> {
+    guard: G,
+    name: &'static str,
+    mode: &'static str,
+    acquired: Instant,
+}
+
+impl<G> TimedGuard<G> {
+    fn acquired(name: &'static str, mode: &'static str, start: Instant, guard: G) -> Self {
+        let acquired = Instant::now();
+        let waited = acquired.duration_since(start);
+        if exceeds(&SLOW_LOCK_THRESHOLD_MS, waited) {
+            warn!("Waited {}ms to {}-lock {}", waited.as_millis(), mode, name);
+            telemetry::record_slow_lock(name, mode, "wait");
+        }
+        Self { guard, name, mode, acquired }
+    }
+}
+
+impl<G: Deref> Deref for TimedGuard<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut> DerefMut for TimedGuard<G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<G> Drop for TimedGuard<G> {
+    fn drop(&mut self) {
+        let held = self.acquired.elapsed();
+        if exceeds(&SLOW_LOCK_THRESHOLD_MS, held) {
+            warn!("Held {}-lock {} for {}ms", self.mode, self.name, held.as_millis());
+            telemetry::record_slow_lock(self.name, self.mode, "hold");
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! 启动时的依赖检查
+//!
+//! 按 `bridge.startup` 中每个依赖的策略等待依赖可用：`required` 只检查一次，`retry` 在
+//! `retry_seconds` 内以指数退避重试，二者仍不可用时启动失败，由容器编排重启；
+//! `optional` 不可用时只记录警告，启动后按需连接。
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use echo_shared::{RetryPolicy, StartupDependencyConfig, StartupPolicy};
+use tracing::{info, warn};
+
+// 单次检查的超时时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+// retry 策略下第一次重试前的等待时间，之后每次翻倍
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+/// 按策略等待依赖可用，返回检查结果
+///
+/// 可选依赖不可用时返回 `None`；必需依赖（`required` / `retry`）不可用时返回错误。
+pub async fn wait_for<T, F, Fut>(name: &str, dependency: &StartupDependencyConfig, mut probe: F) -> Result<Option<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(dependency.retry_seconds);
+    let backoff = RetryPolicy::new(u32::MAX, RETRY_BACKOFF, MAX_RETRY_BACKOFF);
+    let mut attempt = 1;
+    loop {
+        let error = match tokio::time::timeout(PROBE_TIMEOUT, probe()).await {
+            Ok(Ok(value)) => {
+                info!("Startup check: {} is available (attempt {}, {:?})", name, attempt, started.elapsed());
+                return Ok(Some(value));
+            }
+            Ok(Err(e)) => format!("{:#}", e),
+            Err(_) => format!("timed out after {:?}", PROBE_TIMEOUT),
+        };
+
+        match dependency.policy {
+            StartupPolicy::Optional => {
+                warn!("Startup check: optional dependency {} is unavailable, continuing: {}", name, error);
+                return Ok(None);
+            }
+            StartupPolicy::Retry if Instant::now() < deadline => {
+                let delay = backoff.backoff(attempt).min(deadline.saturating_duration_since(Instant::now()));
+                warn!("Startup check: {} is unavailable (attempt {}), retrying in {:?}: {}", name, attempt, delay, error);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            _ => bail!(
+                "Required dependency {} is unavailable after {} attempts ({:?}): {}",
+                name,
+                attempt,
+                started.elapsed(),
+                error
+            ),
+        }
+    }
+}
+
+/// 检查能否建立 TCP 连接，用于启动时不主动连接的依赖（MQTT broker、EchoKit Server）
+pub async fn probe_tcp(host: &str, port: u16) -> Result<()> {
+    tokio::net::TcpStream::connect((host, port))
+        .await
+        .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+    Ok(())
+}
+
+/// 检查 URL 指向的主机和端口能否建立 TCP 连接
+pub async fn probe_url(url: &str) -> Result<()> {
+    let parsed = url::Url::parse(url).with_context(|| format!("Invalid URL: {}", url))?;
+    let host = parsed.host_str().with_context(|| format!("URL has no host: {}", url))?;
+    let port = parsed.port_or_known_default().with_context(|| format!("URL has no port: {}", url))?;
+    probe_tcp(host, port).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_policies_decide_whether_startup_fails() {
+        let failing = || async { Err::<(), _>(anyhow::anyhow!("connection refused")) };
+
+        let optional = StartupDependencyConfig::new(StartupPolicy::Optional, 0);
+        assert!(wait_for("echokit", &optional, failing).await.unwrap().is_none());
+
+        let required = StartupDependencyConfig::new(StartupPolicy::Required, 0);
+        let error = wait_for("database", &required, failing).await.unwrap_err();
+        assert!(error.to_string().contains("after 1 attempts"));
+
+        // 在限定时间内恢复的依赖不会导致启动失败
+        let retry = StartupDependencyConfig::new(StartupPolicy::Retry, 5);
+        let mut attempts = 0;
+        let result = wait_for("redis", &retry, || {
+            attempts += 1;
+            let ready = attempts >= 2;
+            async move { if ready { Ok(attempts) } else { Err(anyhow::anyhow!("not ready")) } }
+        })
+        .await
+        .unwrap();
+        assert_eq!(result, Some(2));
+    }
+}
@@ -0,0 +1,270 @@
+//! 后台任务监管
+//!
+//! 心跳监控、流控、MQTT 事件循环、EchoKit 接收器等后台循环由 `Supervisor` 启动并持有
+//! JoinHandle。任务退出或 panic 时记录日志和指标，按重启策略以指数退避重新启动；
+//! 未在运行的任务会在 `/health/ready` 的 `tasks` 组件中报告。
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use echo_shared::ComponentHealth;
+use tokio::task::{AbortHandle, JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::telemetry;
+
+/// 默认的首次重启等待时间
+pub const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+/// 默认的最长重启等待时间
+pub const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+// 任务连续运行超过该时间后，重启等待时间恢复为初始值
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// 任务退出后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// 只在 panic 时重启，正常结束（如通道关闭）不重启
+    OnPanic,
+    /// 任何退出都重启，用于不应结束的循环
+    Always,
+}
+
+/// 任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Running,
+    /// 已退出，等待重启
+    Restarting,
+    /// 已退出，不再重启
+    Stopped,
+}
+
+/// 受监管任务的状态
+#[derive(Debug, Clone)]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub state: TaskState,
+    /// 已重启次数
+    pub restarts: u32,
+    /// 最近一次退出的原因
+    pub last_exit: Option<String>,
+}
+
+struct SupervisedTask {
+    status: TaskStatus,
+    // 监管循环
+    handle: JoinHandle<()>,
+    // 当前运行的任务实例
+    current: Option<AbortHandle>,
+}
+
+/// 后台任务监管器
+pub struct Supervisor {
+    backoff: Duration,
+    max_backoff: Duration,
+    tasks: Mutex<BTreeMap<&'static str, SupervisedTask>>,
+}
+
+impl Supervisor {
+    pub fn new(backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            backoff,
+            max_backoff: max_backoff.max(backoff),
+            tasks: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 启动受监管的任务，`factory` 每次（重新）启动时生成新的 Future
+    pub fn spawn<F, Fut>(self: &Arc<Self>, name: &'static str, policy: RestartPolicy, mut factory: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = Arc::downgrade(self);
+        let (initial_backoff, max_backoff) = (self.backoff, self.max_backoff);
+
+        // 先登记再启动监管循环，保证循环中的状态更新能找到该任务
+        let mut tasks = self.lock();
+        let handle = tokio::spawn(async move {
+            let mut backoff = initial_backoff;
+            loop {
+                let started = Instant::now();
+                let task = tokio::spawn(factory());
+                if !Self::update(&supervisor, name, |entry| entry.current = Some(task.abort_handle())) {
+                    task.abort();
+                    return;
+                }
+                telemetry::record_task_up(name, true);
+
+                let (exit, panicked) = match task.await {
+                    Ok(()) => ("exited".to_string(), false),
+                    // 被取消说明正在关闭
+                    Err(e) if e.is_cancelled() => return,
+                    Err(e) => (format!("panicked: {}", panic_message(e.into_panic())), true),
+                };
+                let restart = match policy {
+                    RestartPolicy::OnPanic => panicked,
+                    RestartPolicy::Always => true,
+                };
+                if started.elapsed() >= STABLE_RUN {
+                    backoff = initial_backoff;
+                }
+
+                telemetry::record_task_up(name, false);
+                if restart {
+                    error!("Background task {} {}, restarting in {:?}", name, exit, backoff);
+                } else if panicked {
+                    error!("Background task {} {}, not restarting", name, exit);
+                } else {
+                    warn!("Background task {} exited", name);
+                }
+                let recorded = Self::update(&supervisor, name, |entry| {
+                    entry.current = None;
+                    entry.status.state = if restart { TaskState::Restarting } else { TaskState::Stopped };
+                    entry.status.last_exit = Some(exit);
+                });
+                if !recorded || !restart {
+                    return;
+                }
+
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+
+                if !Self::update(&supervisor, name, |entry| {
+                    entry.status.state = TaskState::Running;
+                    entry.status.restarts += 1;
+                }) {
+                    return;
+                }
+                telemetry::record_task_restart(name);
+                info!("Restarting background task {}", name);
+            }
+        });
+
+        tasks.insert(
+            name,
+            SupervisedTask {
+                status: TaskStatus {
+                    name,
+                    state: TaskState::Running,
+                    restarts: 0,
+                    last_exit: None,
+                },
+                handle,
+                current: None,
+            },
+        );
+    }
+
+    /// 所有受监管任务的状态，按名称排序
+    pub fn statuses(&self) -> Vec<TaskStatus> {
+        self.lock().values().map(|task| task.status.clone()).collect()
+    }
+
+    /// 就绪检查中的 `tasks` 组件：有任务不在运行时为 down
+    pub fn health(&self) -> ComponentHealth {
+        let statuses = self.statuses();
+        let degraded: Vec<String> = statuses
+            .iter()
+            .filter(|status| status.state != TaskState::Running)
+            .map(|status| match &status.last_exit {
+                Some(exit) => format!("{} {}", status.name, exit),
+                None => status.name.to_string(),
+            })
+            .collect();
+
+        if degraded.is_empty() {
+            ComponentHealth::up("tasks").with_detail(format!("{} tasks running", statuses.len()))
+        } else {
+            ComponentHealth::down("tasks", format!("degraded: {}", degraded.join("; ")))
+        }
+    }
+
+    /// 停止所有任务，不再重启
+    pub fn shutdown(&self) {
+        for task in std::mem::take(&mut *self.lock()).into_values() {
+            task.handle.abort();
+            if let Some(current) = task.current {
+                current.abort();
+            }
+        }
+    }
+
+    // 更新任务记录；监管器已释放或任务已被移除（关闭中）时返回 false
+    fn update(supervisor: &Weak<Self>, name: &str, f: impl FnOnce(&mut SupervisedTask)) -> bool {
+        let Some(supervisor) = supervisor.upgrade() else {
+            return false;
+        };
+        let mut tasks = supervisor.lock();
+        match tasks.get_mut(name) {
+            Some(entry) => {
+                f(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, SupervisedTask>> {
+        self.tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// 取出 panic 信息
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_restarts_panicked_task_until_it_stays_up() {
+        let supervisor = Arc::new(Supervisor::new(Duration::from_millis(5), Duration::from_millis(10)));
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let counter = runs.clone();
+        supervisor.spawn("flaky", RestartPolicy::OnPanic, move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("boom");
+                }
+                std::future::pending::<()>().await;
+            }
+        });
+        supervisor.spawn("oneshot", RestartPolicy::OnPanic, || async {});
+
+        // panic 时打印回溯可能较慢，等待重启完成而不是固定时长
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while runs.load(Ordering::SeqCst) < 3 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let statuses = supervisor.statuses();
+        let flaky = &statuses[0];
+        assert_eq!((flaky.name, flaky.state, flaky.restarts), ("flaky", TaskState::Running, 2));
+        assert_eq!(flaky.last_exit.as_deref(), Some("panicked: boom"));
+        // 正常结束的 OnPanic 任务不重启，就绪检查报告为 down
+        assert_eq!(statuses[1].state, TaskState::Stopped);
+        let health = supervisor.health();
+        assert!(health.detail.unwrap().contains("oneshot exited"));
+
+        supervisor.shutdown();
+        assert!(supervisor.statuses().is_empty());
+    }
+}
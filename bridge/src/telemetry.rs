@@ -0,0 +1,393 @@
+//! Prometheus 指标
+//!
+//! 业务代码通过 `metrics` 宏记录指标，`GET /metrics` 以 Prometheus 文本格式导出。
+//! 连接数和数据库连接池等状态类指标在抓取时采样。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use echo_shared::{CircuitState, PoolStats};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sqlx::PgPool;
+
+use crate::udp_server::UdpAudioServer;
+use crate::websocket::connection_manager::DeviceConnectionManager;
+
+/// 成功创建的会话数
+pub const SESSIONS_CREATED: &str = "echo_bridge_sessions_created_total";
+/// 创建失败的会话数（stage: database / echokit / limit）
+pub const SESSIONS_FAILED: &str = "echo_bridge_sessions_failed_total";
+/// 在线的 WebSocket 设备连接数
+pub const WEBSOCKET_CONNECTIONS: &str = "echo_bridge_websocket_connections";
+/// 已注册的 UDP 设备数
+pub const UDP_CONNECTIONS: &str = "echo_bridge_udp_connections";
+/// EchoKit Server 重连次数
+pub const ECHOKIT_RECONNECTS: &str = "echo_bridge_echokit_reconnects_total";
+/// MQTT 发布失败次数
+pub const MQTT_PUBLISH_ERRORS: &str = "echo_bridge_mqtt_publish_errors_total";
+/// 音频字节数（direction: in / out，transport: websocket / udp / sip / http / grpc / mqtt）
+pub const AUDIO_BYTES: &str = "echo_bridge_audio_bytes_total";
+/// 数据库连接池中的连接数（state: idle / active）
+pub const DB_POOL_CONNECTIONS: &str = "echo_bridge_db_pool_connections";
+/// 数据库连接池上限
+pub const DB_POOL_MAX_CONNECTIONS: &str = "echo_bridge_db_pool_max_connections";
+/// 采样获取数据库连接的等待时间
+pub const DB_POOL_ACQUIRE_WAIT: &str = "echo_bridge_db_pool_acquire_wait_seconds";
+/// 采样时获取数据库连接失败（如超时）的次数
+pub const DB_POOL_ACQUIRE_ERRORS: &str = "echo_bridge_db_pool_acquire_errors_total";
+/// 等待时间超过阈值时自动扩容新建的连接数
+pub const DB_POOL_GROWN_CONNECTIONS: &str = "echo_bridge_db_pool_grown_connections_total";
+/// HTTP 请求耗时
+pub const HTTP_REQUEST_DURATION: &str = "echo_bridge_http_request_duration_seconds";
+/// 数据库查询耗时（query: 查询名）
+pub const DB_QUERY_DURATION: &str = "echo_bridge_db_query_duration_seconds";
+/// 数据库写操作因短暂错误重试的次数（query: 查询名）
+pub const DB_RETRIES: &str = "echo_bridge_db_retries_total";
+/// 超过阈值的慢查询次数（query: 查询名）
+pub const SLOW_QUERIES: &str = "echo_bridge_slow_queries_total";
+/// 等待或持有超过阈值的锁操作次数（lock: 锁名，mode: read / write，phase: wait / hold）
+pub const SLOW_LOCKS: &str = "echo_bridge_slow_locks_total";
+/// 内部通道的队列深度（channel: 通道名）
+pub const CHANNEL_QUEUE_DEPTH: &str = "echo_bridge_channel_queue_depth";
+/// 音频通道队列满时丢弃的帧数（channel: 通道名）
+pub const CHANNEL_DROPPED: &str = "echo_bridge_channel_dropped_total";
+/// 调用 API Gateway 的耗时（endpoint: 接口名，outcome: HTTP 状态码 / timeout / error）
+pub const GATEWAY_REQUEST_DURATION: &str = "echo_bridge_gateway_request_duration_seconds";
+/// 转发给其他 Bridge 副本的请求数（kind: udp / http，outcome: ok / error）
+pub const CLUSTER_FORWARDS: &str = "echo_bridge_cluster_forwards_total";
+/// 熔断器状态（breaker: echokit / database / redis，0 正常，1 熔断，2 探测中）
+pub const CIRCUIT_BREAKER_STATE: &str = "echo_bridge_circuit_breaker_state";
+/// 熔断器状态切换次数（breaker，state: 切换后的状态）
+pub const CIRCUIT_BREAKER_TRANSITIONS: &str = "echo_bridge_circuit_breaker_transitions_total";
+/// 发往设备失败、进入死信队列的消息数
+pub const DEAD_LETTERS: &str = "echo_bridge_dead_letters_total";
+/// 从死信队列丢弃的消息数（reason: expired / overflow / discarded）
+pub const DEAD_LETTERS_DROPPED: &str = "echo_bridge_dead_letters_dropped_total";
+/// 从死信队列重新投递成功的消息数
+pub const DEAD_LETTERS_REDELIVERED: &str = "echo_bridge_dead_letters_redelivered_total";
+/// 死信队列中的消息数（所有设备）
+pub const DEAD_LETTERS_QUEUED: &str = "echo_bridge_dead_letters_queued";
+/// 设备断线后暂存的回复音频字节数（storage: memory / disk）
+pub const AUDIO_SPOOLED_BYTES: &str = "echo_bridge_audio_spooled_bytes_total";
+/// 设备重连后补发的暂存音频帧数
+pub const AUDIO_SPOOL_REPLAYED: &str = "echo_bridge_audio_spool_replayed_frames_total";
+/// 回复音频投递失败的会话数（reason: expired / overflow）
+pub const AUDIO_SPOOL_FAILED: &str = "echo_bridge_audio_spool_failed_sessions_total";
+/// 后台任务重启次数（task: 任务名）
+pub const TASK_RESTARTS: &str = "echo_bridge_task_restarts_total";
+/// 后台任务是否在运行（task: 任务名，1 运行中，0 已退出）
+pub const TASK_UP: &str = "echo_bridge_task_up";
+/// 本副本是否为单例任务的领导者（job: 任务名，1 是，0 否）
+pub const LEADER: &str = "echo_bridge_leader";
+/// 音频缓冲池取用次数（result: hit / miss）
+pub const AUDIO_BUFFER_POOL: &str = "echo_bridge_audio_buffer_pool_total";
+/// 从 outbox 发布到 MQTT 的事件数（result: delivered / failed）
+pub const OUTBOX_PUBLISHED: &str = "echo_bridge_outbox_published_total";
+/// outbox 中未投递的事件数
+pub const OUTBOX_PENDING: &str = "echo_bridge_outbox_pending";
+/// 超过大小限制被拒绝的外部输入数（ingress: websocket / udp / http / messagepack / mqtt）
+pub const INGRESS_REJECTED: &str = "echo_bridge_ingress_rejected_total";
+/// 签名验证失败未转发给设备的命令数（transport: mqtt / grpc，reason: unsigned / signature / expired 等）
+pub const COMMANDS_REJECTED: &str = "echo_bridge_commands_rejected_total";
+/// 被 IP 过滤规则拒绝的请求和数据包数（ingress: http / udp / sip，reason: denied / not_allowed / country）
+pub const IP_BLOCKED: &str = "echo_bridge_ip_blocked_total";
+/// SIP 来电数（outcome: answered / busy / limit / not_found / unsupported_media / cancelled / failed）
+pub const SIP_CALLS: &str = "echo_bridge_sip_calls_total";
+/// 在本副本连接的设备上播放的语音播报数（result: played / busy / failed）
+pub const ANNOUNCEMENTS: &str = "echo_bridge_announcements_total";
+/// ASR 文本后处理次数（processor: vocabulary / profanity / punctuation，result: changed / unchanged）
+pub const ASR_POSTPROCESS: &str = "echo_bridge_asr_postprocess_total";
+/// Tokio 工作线程数
+pub const RUNTIME_WORKERS: &str = "echo_bridge_runtime_workers";
+/// Tokio 运行时中存活的任务数，持续增长说明有任务卡住或泄漏
+pub const RUNTIME_ALIVE_TASKS: &str = "echo_bridge_runtime_alive_tasks";
+/// Tokio 全局调度队列中等待执行的任务数
+pub const RUNTIME_GLOBAL_QUEUE_DEPTH: &str = "echo_bridge_runtime_global_queue_depth";
+
+// 请求耗时直方图的分桶（秒）
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// 直方图数据的清理间隔
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+// 会话累计数，供告警判定读取（metrics 计数器无法回读）
+static SESSIONS_CREATED_COUNT: AtomicU64 = AtomicU64::new(0);
+static SESSIONS_FAILED_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// 为 Bridge 的直方图指标设置分桶
+pub fn metrics_buckets(builder: PrometheusBuilder) -> Result<PrometheusBuilder> {
+    Ok(builder
+        .set_buckets_for_metric(Matcher::Full(HTTP_REQUEST_DURATION.to_string()), LATENCY_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(DB_POOL_ACQUIRE_WAIT.to_string()), LATENCY_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(DB_QUERY_DURATION.to_string()), LATENCY_BUCKETS)?
+        .set_buckets_for_metric(Matcher::Full(GATEWAY_REQUEST_DURATION.to_string()), LATENCY_BUCKETS)?)
+}
+
+/// 安装全局指标记录器，必须在 Tokio 运行时中调用
+pub fn init() -> Result<()> {
+    let handle = metrics_buckets(PrometheusBuilder::new())?.install_recorder()?;
+    use_metrics_handle(handle);
+    Ok(())
+}
+
+/// 使用已安装的全局指标记录器（单进程模式下由 etch-all 安装），必须在 Tokio 运行时中调用
+pub fn use_metrics_handle(handle: PrometheusHandle) {
+    let upkeep_handle = handle.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            upkeep_handle.run_upkeep();
+        }
+    });
+
+    let _ = HANDLE.set(handle);
+}
+
+/// `/metrics` 路由状态（抓取时采样的组件）
+#[derive(Clone)]
+pub struct MetricsState {
+    /// SQLite 存储时为 None，不输出连接池指标
+    pub db_pool: Option<PgPool>,
+    pub connection_manager: Arc<DeviceConnectionManager>,
+    pub udp_server: Arc<UdpAudioServer>,
+}
+
+/// GET /metrics
+pub async fn metrics_handler(State(state): State<MetricsState>) -> Response {
+    let Some(handle) = HANDLE.get() else {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, "metrics recorder not installed").into_response();
+    };
+
+    metrics::gauge!(WEBSOCKET_CONNECTIONS).set(state.connection_manager.get_online_count().await as f64);
+    metrics::gauge!(UDP_CONNECTIONS).set(state.udp_server.get_registered_devices().await.len() as f64);
+
+    if let Some(db_pool) = &state.db_pool {
+        let pool = PoolStats::snapshot(db_pool);
+        metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "idle").set(pool.idle as f64);
+        metrics::gauge!(DB_POOL_CONNECTIONS, "state" => "active").set(pool.in_use as f64);
+        metrics::gauge!(DB_POOL_MAX_CONNECTIONS).set(pool.max as f64);
+    }
+
+    let runtime = tokio::runtime::Handle::current().metrics();
+    metrics::gauge!(RUNTIME_WORKERS).set(runtime.num_workers() as f64);
+    metrics::gauge!(RUNTIME_ALIVE_TASKS).set(runtime.num_alive_tasks() as f64);
+    metrics::gauge!(RUNTIME_GLOBAL_QUEUE_DEPTH).set(runtime.global_queue_depth() as f64);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// 记录 HTTP 请求耗时（按路由模板聚合，避免路径中的 ID 导致标签膨胀）
+pub async fn track_requests(req: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(req).await;
+
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION,
+        "method" => method,
+        "path" => path,
+        "status" => response.status().as_u16().to_string()
+    )
+    .record(start.elapsed().as_secs_f64());
+
+    response
+}
+
+/// 记录音频字节数
+pub fn record_audio_bytes(direction: &'static str, transport: &'static str, bytes: usize) {
+    metrics::counter!(AUDIO_BYTES, "direction" => direction, "transport" => transport).increment(bytes as u64);
+}
+
+/// 记录数据库查询耗时
+pub fn record_query_duration(query: &'static str, elapsed: Duration) {
+    metrics::histogram!(DB_QUERY_DURATION, "query" => query).record(elapsed.as_secs_f64());
+}
+
+/// 记录一次慢查询
+pub fn record_slow_query(query: &'static str) {
+    metrics::counter!(SLOW_QUERIES, "query" => query).increment(1);
+}
+
+/// 记录一次锁等待或持有超时（phase: wait / hold）
+pub fn record_slow_lock(lock: &'static str, mode: &'static str, phase: &'static str) {
+    metrics::counter!(SLOW_LOCKS, "lock" => lock, "mode" => mode, "phase" => phase).increment(1);
+}
+
+/// 记录内部通道的当前队列深度
+pub fn record_channel_depth(channel: &'static str, depth: usize) {
+    metrics::gauge!(CHANNEL_QUEUE_DEPTH, "channel" => channel).set(depth as f64);
+}
+
+/// 记录一次音频通道丢帧
+pub fn record_channel_dropped(channel: &'static str) {
+    metrics::counter!(CHANNEL_DROPPED, "channel" => channel).increment(1);
+}
+
+/// 记录一次 API Gateway 调用（每次重试单独计数）
+pub fn record_gateway_request(endpoint: &'static str, outcome: String, elapsed: Duration) {
+    metrics::histogram!(GATEWAY_REQUEST_DURATION, "endpoint" => endpoint, "outcome" => outcome)
+        .record(elapsed.as_secs_f64());
+}
+
+/// 记录一次数据库写操作重试
+pub fn record_db_retry(query: &'static str) {
+    metrics::counter!(DB_RETRIES, "query" => query).increment(1);
+}
+
+/// 记录一次数据库连接池采样（获取连接的等待时间和自动扩容新建的连接）
+pub fn record_db_pool_sample(stats: &PoolStats) {
+    metrics::histogram!(DB_POOL_ACQUIRE_WAIT).record(stats.acquire_wait.as_secs_f64());
+    if stats.acquire_error.is_some() {
+        metrics::counter!(DB_POOL_ACQUIRE_ERRORS).increment(1);
+    }
+    metrics::counter!(DB_POOL_GROWN_CONNECTIONS).increment(stats.grown as u64);
+}
+
+/// 记录一次跨副本转发
+pub fn record_cluster_forward(kind: &'static str, ok: bool) {
+    let outcome = if ok { "ok" } else { "error" };
+    metrics::counter!(CLUSTER_FORWARDS, "kind" => kind, "outcome" => outcome).increment(1);
+}
+
+/// 记录熔断器状态切换
+pub fn record_circuit_state(breaker: &str, state: CircuitState) {
+    let label = match state {
+        CircuitState::Closed => "closed",
+        CircuitState::Open => "open",
+        CircuitState::HalfOpen => "half_open",
+    };
+    metrics::gauge!(CIRCUIT_BREAKER_STATE, "breaker" => breaker.to_string()).set(state.as_gauge());
+    metrics::counter!(CIRCUIT_BREAKER_TRANSITIONS, "breaker" => breaker.to_string(), "state" => label).increment(1);
+}
+
+/// 记录进入死信队列的消息和队列中的消息总数
+pub fn record_dead_lettered(queued: usize) {
+    metrics::counter!(DEAD_LETTERS).increment(1);
+    metrics::gauge!(DEAD_LETTERS_QUEUED).set(queued as f64);
+}
+
+/// 记录从死信队列丢弃的消息（reason: expired / overflow / discarded）
+pub fn record_dead_letters_dropped(reason: &'static str, count: usize, queued: usize) {
+    metrics::counter!(DEAD_LETTERS_DROPPED, "reason" => reason).increment(count as u64);
+    metrics::gauge!(DEAD_LETTERS_QUEUED).set(queued as f64);
+}
+
+/// 记录从死信队列重新投递成功的消息
+pub fn record_dead_letters_redelivered(count: usize, queued: usize) {
+    metrics::counter!(DEAD_LETTERS_REDELIVERED).increment(count as u64);
+    metrics::gauge!(DEAD_LETTERS_QUEUED).set(queued as f64);
+}
+
+/// 记录暂存的回复音频
+pub fn record_audio_spooled(storage: &'static str, bytes: usize) {
+    metrics::counter!(AUDIO_SPOOLED_BYTES, "storage" => storage).increment(bytes as u64);
+}
+
+/// 记录补发的暂存音频帧数
+pub fn record_audio_spool_replayed(frames: usize) {
+    metrics::counter!(AUDIO_SPOOL_REPLAYED).increment(frames as u64);
+}
+
+/// 记录一个回复音频投递失败的会话
+pub fn record_audio_spool_failed(reason: &'static str) {
+    metrics::counter!(AUDIO_SPOOL_FAILED, "reason" => reason).increment(1);
+}
+
+/// 记录后台任务启动或退出
+pub fn record_task_up(task: &'static str, up: bool) {
+    metrics::gauge!(TASK_UP, "task" => task).set(if up { 1.0 } else { 0.0 });
+}
+
+/// 记录一次后台任务重启
+pub fn record_task_restart(task: &'static str) {
+    metrics::counter!(TASK_RESTARTS, "task" => task).increment(1);
+}
+
+/// 记录单例任务的领导权
+pub fn record_leader(job: &str, leader: bool) {
+    metrics::gauge!(LEADER, "job" => job.to_string()).set(if leader { 1.0 } else { 0.0 });
+}
+
+/// 记录从 outbox 发布的事件数
+pub fn record_outbox_published(delivered: usize, failed: usize) {
+    metrics::counter!(OUTBOX_PUBLISHED, "result" => "delivered").increment(delivered as u64);
+    metrics::counter!(OUTBOX_PUBLISHED, "result" => "failed").increment(failed as u64);
+}
+
+/// 记录 outbox 中未投递的事件数
+pub fn record_outbox_pending(count: i64) {
+    metrics::gauge!(OUTBOX_PENDING).set(count as f64);
+}
+
+/// 记录一次音频缓冲池取用，miss 表示新分配了缓冲区
+pub fn record_buffer_pool(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    metrics::counter!(AUDIO_BUFFER_POOL, "result" => result).increment(1);
+}
+
+/// 记录一次超过大小限制被拒绝的外部输入
+pub fn record_ingress_rejected(ingress: &'static str) {
+    metrics::counter!(INGRESS_REJECTED, "ingress" => ingress).increment(1);
+}
+
+/// 记录一条签名验证失败的设备命令
+pub fn record_command_rejected(transport: &'static str, reason: &'static str) {
+    metrics::counter!(COMMANDS_REJECTED, "transport" => transport, "reason" => reason).increment(1);
+}
+
+/// 记录一次被 IP 过滤规则拒绝的请求或数据包
+pub fn record_ip_blocked(ingress: &'static str, reason: &'static str) {
+    metrics::counter!(IP_BLOCKED, "ingress" => ingress, "reason" => reason).increment(1);
+}
+
+/// 记录一次 SIP 来电的处理结果
+pub fn record_sip_call(outcome: &'static str) {
+    metrics::counter!(SIP_CALLS, "outcome" => outcome).increment(1);
+}
+
+/// 记录一次语音播报的结果
+pub fn record_announcement(result: &'static str) {
+    metrics::counter!(ANNOUNCEMENTS, "result" => result).increment(1);
+}
+
+/// 记录一次 ASR 文本后处理，changed 表示文本被修改
+pub fn record_asr_postprocess(processor: &'static str, changed: bool) {
+    let result = if changed { "changed" } else { "unchanged" };
+    metrics::counter!(ASR_POSTPROCESS, "processor" => processor, "result" => result).increment(1);
+}
+
+/// 记录会话创建成功
+pub fn record_session_created() {
+    SESSIONS_CREATED_COUNT.fetch_add(1, Ordering::Relaxed);
+    metrics::counter!(SESSIONS_CREATED).increment(1);
+}
+
+/// 记录会话创建失败（stage: database / echokit / limit）
+pub fn record_session_failed(stage: &'static str) {
+    SESSIONS_FAILED_COUNT.fetch_add(1, Ordering::Relaxed);
+    metrics::counter!(SESSIONS_FAILED, "stage" => stage).increment(1);
+}
+
+/// 进程启动以来创建成功和失败的会话数
+pub fn session_totals() -> (u64, u64) {
+    (SESSIONS_CREATED_COUNT.load(Ordering::Relaxed), SESSIONS_FAILED_COUNT.load(Ordering::Relaxed))
+}
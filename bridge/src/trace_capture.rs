@@ -0,0 +1,288 @@
+//! 单会话调试抓帧
+//!
+//! 通过管理 API 对指定会话或设备开启抓帧后，设备与 EchoKit 之间经过 Bridge 的每一帧
+//! （方向、大小、事件类型、时间）都记录到该会话的环形缓冲区，可按 NDJSON 下载，
+//! 无需为现场排查打开全局 debug 日志。未开启任何抓帧时记录调用只读取一个原子变量。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// 每个会话默认保留的帧数
+pub const DEFAULT_TRACE_CAPACITY: usize = 2000;
+/// 每个会话最多保留的帧数
+pub const MAX_TRACE_CAPACITY: usize = 50_000;
+// 最多保留的会话抓帧数，超出时丢弃最早开始的
+const MAX_TRACED_SESSIONS: usize = 32;
+
+static CAPTURE: LazyLock<TraceCapture> = LazyLock::new(TraceCapture::default);
+
+/// 帧的传输方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceDirection {
+    DeviceToBridge,
+    BridgeToDevice,
+    BridgeToEchokit,
+    EchokitToBridge,
+}
+
+/// 一帧的抓取记录
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceFrame {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// 距开始抓帧的微秒数
+    pub elapsed_us: u64,
+    pub direction: TraceDirection,
+    /// 事件类型，如 audio、text 或 EchoKit 事件名
+    pub event: String,
+    /// 帧大小（字节）
+    pub size: usize,
+}
+
+// 单个会话的抓帧缓冲区
+struct SessionTrace {
+    device_id: Option<String>,
+    capturing: bool,
+    capacity: usize,
+    started: Instant,
+    next_seq: u64,
+    dropped: u64,
+    frames: VecDeque<TraceFrame>,
+}
+
+impl SessionTrace {
+    fn new(capacity: usize) -> Self {
+        Self {
+            device_id: None,
+            capturing: true,
+            capacity,
+            started: Instant::now(),
+            next_seq: 0,
+            dropped: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, direction: TraceDirection, event: &str, size: usize) {
+        if self.frames.len() >= self.capacity {
+            self.frames.pop_front();
+            self.dropped += 1;
+        }
+        self.frames.push_back(TraceFrame {
+            seq: self.next_seq,
+            timestamp: Utc::now(),
+            elapsed_us: self.started.elapsed().as_micros() as u64,
+            direction,
+            event: event.to_string(),
+            size,
+        });
+        self.next_seq += 1;
+    }
+}
+
+#[derive(Default)]
+struct Targets {
+    /// session_id -> 抓帧缓冲区
+    sessions: HashMap<String, SessionTrace>,
+    /// 开启了设备级抓帧的 device_id -> 每个会话的缓冲区大小
+    devices: HashMap<String, usize>,
+}
+
+impl Targets {
+    fn any_capturing(&self) -> bool {
+        !self.devices.is_empty() || self.sessions.values().any(|trace| trace.capturing)
+    }
+
+    fn insert_session(&mut self, session_id: &str, trace: SessionTrace) {
+        if !self.sessions.contains_key(session_id) && self.sessions.len() >= MAX_TRACED_SESSIONS {
+            let oldest = self
+                .sessions
+                .iter()
+                .min_by_key(|(_, trace)| trace.started)
+                .map(|(id, _)| id.clone());
+            if let Some(oldest) = oldest {
+                self.sessions.remove(&oldest);
+            }
+        }
+        self.sessions.insert(session_id.to_string(), trace);
+    }
+}
+
+/// 抓帧导出结果
+pub struct TraceExport {
+    /// 每行一个 `TraceFrame` 的 JSON
+    pub ndjson: String,
+    /// 因缓冲区已满被丢弃的帧数
+    pub dropped: u64,
+    pub capturing: bool,
+}
+
+/// 抓帧登记表
+#[derive(Default)]
+pub struct TraceCapture {
+    active: AtomicBool,
+    targets: Mutex<Targets>,
+}
+
+impl TraceCapture {
+    fn update<R>(&self, f: impl FnOnce(&mut Targets) -> R) -> R {
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut targets);
+        self.active.store(targets.any_capturing(), Ordering::Relaxed);
+        result
+    }
+
+    /// 开始抓取指定会话，已有的缓冲区会被清空
+    pub fn enable_session(&self, session_id: &str, capacity: usize) {
+        self.update(|targets| targets.insert_session(session_id, SessionTrace::new(capacity)));
+    }
+
+    /// 停止抓取指定会话并丢弃缓冲区，会话不存在时返回 false
+    pub fn disable_session(&self, session_id: &str) -> bool {
+        self.update(|targets| targets.sessions.remove(session_id).is_some())
+    }
+
+    /// 开始抓取指定设备的所有会话
+    pub fn enable_device(&self, device_id: &str, capacity: usize) {
+        self.update(|targets| {
+            targets.devices.insert(device_id.to_string(), capacity);
+        });
+    }
+
+    /// 停止抓取指定设备，已抓取的会话缓冲区保留以供下载
+    pub fn disable_device(&self, device_id: &str) -> bool {
+        self.update(|targets| {
+            for trace in targets.sessions.values_mut() {
+                if trace.device_id.as_deref() == Some(device_id) {
+                    trace.capturing = false;
+                }
+            }
+            targets.devices.remove(device_id).is_some()
+        })
+    }
+
+    /// 记录一帧，会话或设备未开启抓帧时忽略
+    pub fn record(&self, session_id: &str, device_id: &str, direction: TraceDirection, event: &str, size: usize) {
+        if !self.active.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        if !targets.sessions.contains_key(session_id) {
+            let Some(&capacity) = targets.devices.get(device_id) else {
+                return;
+            };
+            targets.insert_session(session_id, SessionTrace::new(capacity));
+        }
+
+        if let Some(trace) = targets.sessions.get_mut(session_id) {
+            if trace.capturing {
+                trace.device_id.get_or_insert_with(|| device_id.to_string());
+                trace.push(direction, event, size);
+            }
+        }
+    }
+
+    /// 导出指定会话的抓帧记录
+    pub fn export(&self, session_id: &str) -> Option<TraceExport> {
+        let targets = self.targets.lock().unwrap_or_else(|e| e.into_inner());
+        let trace = targets.sessions.get(session_id)?;
+
+        let mut ndjson = String::new();
+        for frame in &trace.frames {
+            if let Ok(line) = serde_json::to_string(frame) {
+                ndjson.push_str(&line);
+                ndjson.push('\n');
+            }
+        }
+
+        Some(TraceExport { ndjson, dropped: trace.dropped, capturing: trace.capturing })
+    }
+}
+
+/// 全局抓帧登记表
+pub fn capture() -> &'static TraceCapture {
+    &CAPTURE
+}
+
+/// 记录一帧到全局登记表
+pub fn record(session_id: &str, device_id: &str, direction: TraceDirection, event: &str, size: usize) {
+    CAPTURE.record(session_id, device_id, direction, event, size);
+}
+
+/// 记录一条设备文本消息，能解析为客户端命令时以命令名（如 StartChat、Submit）作为事件类型，
+/// 供 session-replay 按原始时间重放
+pub fn record_device_text(session_id: &str, device_id: &str, text: &str) {
+    if !CAPTURE.active.load(Ordering::Relaxed) {
+        return;
+    }
+    let event = echo_shared::ClientCommand::from_json(text)
+        .map(|command| command.name())
+        .unwrap_or("text");
+    CAPTURE.record(session_id, device_id, TraceDirection::DeviceToBridge, event, text.len());
+}
+
+/// 记录一帧 EchoKit MessagePack 数据，事件类型从帧中解码（仅在开启抓帧时解码）
+pub fn record_echokit_frame(session_id: &str, device_id: &str, direction: TraceDirection, data: &[u8]) {
+    if !CAPTURE.active.load(Ordering::Relaxed) {
+        return;
+    }
+    let event = echo_shared::EchoKitEvent::from_messagepack(data)
+        .map(|event| event.name())
+        .unwrap_or("unknown");
+    CAPTURE.record(session_id, device_id, direction, event, data.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_capture_is_bounded() {
+        let capture = TraceCapture::default();
+        capture.record("s1", "d1", TraceDirection::DeviceToBridge, "audio", 640);
+        assert!(capture.export("s1").is_none());
+
+        capture.enable_session("s1", 2);
+        for size in [1, 2, 3] {
+            capture.record("s1", "d1", TraceDirection::DeviceToBridge, "audio", size);
+        }
+        capture.record("s2", "d2", TraceDirection::DeviceToBridge, "audio", 640);
+
+        let export = capture.export("s1").unwrap();
+        assert_eq!(export.dropped, 1);
+        let frames: Vec<serde_json::Value> =
+            export.ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0]["seq"], 1);
+        assert_eq!(frames[1]["size"], 3);
+        assert_eq!(frames[1]["direction"], "device_to_bridge");
+        assert!(capture.export("s2").is_none());
+
+        assert!(capture.disable_session("s1"));
+        assert!(!capture.active.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_device_capture_covers_new_sessions() {
+        let capture = TraceCapture::default();
+        capture.enable_device("d1", DEFAULT_TRACE_CAPACITY);
+        capture.record("s1", "d1", TraceDirection::EchokitToBridge, "HelloChunk", 320);
+        capture.record("s2", "d2", TraceDirection::EchokitToBridge, "HelloChunk", 320);
+        assert!(capture.export("s1").unwrap().capturing);
+        assert!(capture.export("s2").is_none());
+
+        // 停止后已抓取的记录仍可下载，但不再追加
+        assert!(capture.disable_device("d1"));
+        capture.record("s1", "d1", TraceDirection::BridgeToDevice, "HelloChunk", 320);
+        let export = capture.export("s1").unwrap();
+        assert!(!export.capturing);
+        assert_eq!(export.ndjson.lines().count(), 1);
+    }
+}
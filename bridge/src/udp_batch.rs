@@ -0,0 +1,202 @@
+//! UDP 批量接收
+//!
+//! - `bind_sockets`：绑定一个或多个 UDP socket，多个时开启 SO_REUSEPORT，由内核按来源地址分流到各接收任务
+//! - `RecvBatch`：一次系统调用接收多个数据包。Linux 使用 recvmmsg，其他平台在 socket 可读后
+//!   连续调用非阻塞的 `try_recv_from` 直到队列为空或批次已满
+
+use std::io;
+use std::net::SocketAddr;
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+use tracing::warn;
+
+/// 绑定 `count` 个监听同一地址的 UDP socket（需在 Tokio 运行时内调用）
+///
+/// 端口为 0 时后续 socket 复用第一个 socket 实际分配的端口。
+pub fn bind_sockets(address: SocketAddr, count: usize) -> io::Result<Vec<UdpSocket>> {
+    let count = if cfg!(unix) {
+        count.max(1)
+    } else {
+        if count > 1 {
+            warn!("SO_REUSEPORT is not supported on this platform, using a single UDP reader task");
+        }
+        1
+    };
+
+    let first = bind_socket(address, count > 1)?;
+    let address = first.local_addr()?;
+    let mut sockets = vec![first];
+    for _ in 1..count {
+        sockets.push(bind_socket(address, true)?);
+    }
+    Ok(sockets)
+}
+
+fn bind_socket(address: SocketAddr, reuse_port: bool) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(address), Type::DGRAM, Some(Protocol::UDP))?;
+    #[cfg(unix)]
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    #[cfg(not(unix))]
+    let _ = reuse_port;
+    socket.set_nonblocking(true)?;
+    socket.bind(&address.into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// 可复用的批量接收缓冲区
+pub struct RecvBatch {
+    buffers: Vec<Vec<u8>>,
+    // 每个已接收数据包的 (缓冲区下标, 长度, 来源地址)
+    received: Vec<(usize, usize, SocketAddr)>,
+}
+
+impl RecvBatch {
+    /// 每个缓冲区 `buffer_size` 字节，更长的数据包被截断为 `buffer_size` 字节，
+    /// 调用方可将缓冲区设为限制加 1 字节，据此识别超长的数据包
+    pub fn new(batch_size: usize, buffer_size: usize) -> Self {
+        let batch_size = batch_size.max(1);
+        Self {
+            buffers: vec![vec![0u8; buffer_size.max(1)]; batch_size],
+            received: Vec::with_capacity(batch_size),
+        }
+    }
+
+    /// 等待 socket 可读并接收一批数据包，返回接收到的数据包数（至少 1 个）
+    #[cfg(target_os = "linux")]
+    pub async fn recv_from(&mut self, socket: &UdpSocket) -> io::Result<usize> {
+        loop {
+            socket.readable().await?;
+            let result = socket.try_io(tokio::io::Interest::READABLE, || {
+                recv_mmsg(socket, &mut self.buffers, &mut self.received)
+            });
+            match result {
+                Ok(count) => return Ok(count),
+                // 可读事件是虚假唤醒，try_io 已清除就绪状态，继续等待
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 等待 socket 可读并接收一批数据包，返回接收到的数据包数（至少 1 个）
+    #[cfg(not(target_os = "linux"))]
+    pub async fn recv_from(&mut self, socket: &UdpSocket) -> io::Result<usize> {
+        self.received.clear();
+        let (len, addr) = socket.recv_from(&mut self.buffers[0]).await?;
+        self.received.push((0, len, addr));
+
+        while self.received.len() < self.buffers.len() {
+            let index = self.received.len();
+            match socket.try_recv_from(&mut self.buffers[index]) {
+                Ok((len, addr)) => self.received.push((index, len, addr)),
+                // 队列已空或出错时先返回已接收的数据包，错误会在下一次接收时再次出现
+                Err(_) => break,
+            }
+        }
+        Ok(self.received.len())
+    }
+
+    /// 最近一次接收到的数据包
+    pub fn packets(&self) -> impl Iterator<Item = (&[u8], SocketAddr)> + '_ {
+        self.received
+            .iter()
+            .map(|&(index, len, addr)| (&self.buffers[index][..len], addr))
+    }
+}
+
+// 非阻塞地调用 recvmmsg，没有数据时返回 WouldBlock
+#[cfg(target_os = "linux")]
+fn recv_mmsg(
+    socket: &UdpSocket,
+    buffers: &mut [Vec<u8>],
+    received: &mut Vec<(usize, usize, SocketAddr)>,
+) -> io::Result<usize> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: sockaddr_storage 是纯数据结构，全零是合法值
+    let mut addresses: Vec<libc::sockaddr_storage> = vec![unsafe { std::mem::zeroed() }; buffers.len()];
+    let mut iovecs: Vec<libc::iovec> = buffers
+        .iter_mut()
+        .map(|buffer| libc::iovec { iov_base: buffer.as_mut_ptr().cast(), iov_len: buffer.len() })
+        .collect();
+    let mut headers: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .zip(addresses.iter_mut())
+        .map(|(iovec, address)| {
+            // SAFETY: msghdr 是纯数据结构，全零是合法值
+            let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+            header.msg_name = (address as *mut libc::sockaddr_storage).cast();
+            header.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+            header.msg_iov = iovec;
+            header.msg_iovlen = 1;
+            libc::mmsghdr { msg_hdr: header, msg_len: 0 }
+        })
+        .collect();
+
+    // SAFETY: headers 中的指针指向本函数内存活的 addresses / iovecs 和调用方持有的 buffers，
+    // 数组长度与 vlen 一致
+    let count = unsafe {
+        libc::recvmmsg(
+            socket.as_raw_fd(),
+            headers.as_mut_ptr(),
+            headers.len() as libc::c_uint,
+            libc::MSG_DONTWAIT as _,
+            std::ptr::null_mut(),
+        )
+    };
+    if count < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // 所有缓冲区大小相同
+    let buffer_size = buffers.first().map_or(0, Vec::len);
+    received.clear();
+    for (index, (header, address)) in headers.iter().zip(&addresses).take(count as usize).enumerate() {
+        // SAFETY: 内核已写入 msg_namelen 字节的合法地址
+        let address = unsafe { socket2::SockAddr::new(*address, header.msg_hdr.msg_namelen) };
+        // 跳过非 IP 地址的数据包，记录缓冲区下标使其余数据包仍与各自的缓冲区对应
+        let Some(address) = address.as_socket() else {
+            continue;
+        };
+        let len = (header.msg_len as usize).min(buffer_size);
+        received.push((index, len, address));
+    }
+    Ok(received.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_recv_batch_receives_queued_datagrams() {
+        let receiver = bind_sockets("127.0.0.1:0".parse().unwrap(), 1).unwrap().remove(0);
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = receiver.local_addr().unwrap();
+        for payload in [b"one".as_slice(), b"two", b"three"] {
+            sender.send_to(payload, target).await.unwrap();
+        }
+
+        let mut batch = RecvBatch::new(8, 4096);
+        let mut payloads = Vec::new();
+        while payloads.len() < 3 {
+            batch.recv_from(&receiver).await.unwrap();
+            for (data, addr) in batch.packets() {
+                assert_eq!(addr, sender.local_addr().unwrap());
+                payloads.push(data.to_vec());
+            }
+        }
+        assert_eq!(payloads, vec![b"one".to_vec(), b"two".to_vec(), b"three".to_vec()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_sockets_shares_port_with_reuseport() {
+        let sockets = bind_sockets("127.0.0.1:0".parse().unwrap(), 2).unwrap();
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].local_addr().unwrap(), sockets[1].local_addr().unwrap());
+    }
+}
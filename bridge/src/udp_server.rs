@@ -1,20 +1,52 @@
 use anyhow::{Context, Result};
-use echo_shared::{AudioChunk, AudioFormat};
+use echo_shared::{AudioChunk, AudioFormat, DeviceConnectionInfo, DeviceTransport, IngressLimitsConfig, IpFilter, IpFilterHandle, UdpServerConfig};
 use echo_shared::utils::now_utc;
+use crate::audio_capture;
 use crate::audio_processor::AudioProcessor;
+use crate::channels::{self, AudioSender};
+use crate::cluster::{ClusterRegistry, UdpForwardKey, UdpRoute, UDP_FORWARD_TAG_LEN};
+use crate::telemetry;
+use crate::udp_batch::{self, RecvBatch};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use dashmap::DashMap;
 use tokio::net::UdpSocket;
 use tokio::sync::mpsc;
 use tracing::{info, warn, error, debug};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::{Cursor, Read};
 
+// 设备注册表，每个数据包都会访问，使用分片的 DashMap 避免全局锁争用
+type DeviceRegistry = Arc<DashMap<String, DeviceInfo>>;
+
+// 接收任务解析后交给处理任务的数据包：(数据包, 设备地址, 原始长度, 是否由其他副本转发)
+type DispatchedPacket = (UdpAudioPacket, SocketAddr, usize, bool);
+
+// 其他副本转发的数据包：[0x00][FORWARD_MAGIC][签名][地址族 4/6][设备 IP][设备端口 u16 LE][原始数据包]
+// 设备 ID 长度为 0 的数据包不是合法的音频包，用作转发标记；签名覆盖签名之后的全部内容
+const FORWARD_MAGIC: u8 = 0xEC;
+// 转发信封头的最大长度（IPv6 地址时）
+const FORWARD_ENVELOPE_MAX_LEN: usize = 21 + UDP_FORWARD_TAG_LEN;
+
 // UDP 音频服务器
 pub struct UdpAudioServer {
+    // 发送使用第一个 socket
     socket: Arc<UdpSocket>,
+    // 所有监听同一地址的 socket，每个 socket 一个接收任务
+    reader_sockets: Vec<Arc<UdpSocket>>,
+    config: UdpServerConfig,
     audio_processor: Arc<AudioProcessor>,
-    device_registry: Arc<tokio::sync::RwLock<std::collections::HashMap<String, DeviceInfo>>>,
+    device_registry: DeviceRegistry,
+    // 多副本部署时，归属其他副本的设备的数据包转发过去
+    cluster: Option<Arc<ClusterRegistry>>,
+    // 转发数据包的签名密钥，只在集群模式下设置；未设置时不接受转发格式的数据包
+    forward_key: Option<Arc<UdpForwardKey>>,
+    // 数据包的最大长度（不含转发信封），超出的数据包整包丢弃
+    max_datagram_bytes: usize,
+    // 来源 IP 过滤，被拒绝的数据包在解析之前丢弃
+    ip_filter: Option<IpFilterHandle>,
 }
 
 // 设备信息
@@ -22,7 +54,10 @@ pub struct UdpAudioServer {
 struct DeviceInfo {
     device_id: String,
     address: SocketAddr,
+    first_seen: chrono::DateTime<chrono::Utc>,
     last_seen: chrono::DateTime<chrono::Utc>,
+    bytes_received: u64,
+    bytes_sent: u64,
     audio_format: AudioFormat,
     sample_rate: u32,
     channels: u8,
@@ -39,55 +74,143 @@ struct UdpAudioPacket {
     flags: u8, // bit 0: is_final, bit 1: is_silence
 }
 
+impl UdpAudioPacket {
+    // 按 parse_udp_packet 的格式重新编码
+    fn encode_into(&self, buffer: &mut Vec<u8>) {
+        buffer.push(self.device_id.len() as u8);
+        buffer.extend_from_slice(self.device_id.as_bytes());
+        buffer.extend_from_slice(&self.sequence_number.to_le_bytes());
+        buffer.extend_from_slice(&self.timestamp.to_le_bytes());
+        buffer.push(self.flags);
+        buffer.extend_from_slice(&(self.audio_data.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(&self.audio_data);
+    }
+}
+
 impl UdpAudioServer {
     pub async fn new(
         bind_address: &str,
+        config: UdpServerConfig,
         audio_processor: Arc<AudioProcessor>,
     ) -> Result<Self> {
-        let socket = UdpSocket::bind(bind_address).await
-            .map_err(|e| anyhow::anyhow!("Failed to bind to UDP address {}: {}", bind_address, e))?;
+        let address: SocketAddr = bind_address.parse()
+            .with_context(|| format!("Invalid UDP bind address: {}", bind_address))?;
+        let reader_sockets: Vec<Arc<UdpSocket>> = udp_batch::bind_sockets(address, config.reader_tasks)
+            .map_err(|e| anyhow::anyhow!("Failed to bind to UDP address {}: {}", bind_address, e))?
+            .into_iter()
+            .map(Arc::new)
+            .collect();
 
-        info!("UDP Audio Server listening on: {}", bind_address);
+        info!("UDP Audio Server listening on: {} ({} reader tasks, batch size {}, {} dispatch workers)",
+              bind_address, reader_sockets.len(), config.batch_size, config.dispatch_workers);
 
         Ok(Self {
-            socket: Arc::new(socket),
+            socket: reader_sockets[0].clone(),
+            reader_sockets,
+            config,
             audio_processor,
-            device_registry: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
+            device_registry: Arc::new(DashMap::new()),
+            cluster: None,
+            forward_key: None,
+            max_datagram_bytes: IngressLimitsConfig::default().max_udp_datagram_bytes,
+            ip_filter: None,
         })
     }
 
+    /// 启用多副本路由
+    pub fn with_cluster(mut self, cluster: Arc<ClusterRegistry>) -> Self {
+        self.forward_key = Some(Arc::new(cluster.udp_forward_key()));
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// 数据包的最大长度（limits.max_udp_datagram_bytes）
+    pub fn with_max_datagram_bytes(mut self, max_datagram_bytes: usize) -> Self {
+        self.max_datagram_bytes = max_datagram_bytes;
+        self
+    }
+
+    /// 按 ip_filter 规则过滤来源地址和转发数据包中的设备地址（UDP 没有请求头，不按国家过滤）
+    pub fn with_ip_filter(mut self, ip_filter: IpFilterHandle) -> Self {
+        self.ip_filter = Some(ip_filter);
+        self
+    }
+
     // 启动 UDP 服务器
     pub async fn start(&self) -> Result<()> {
-        let socket = self.socket.clone();
-        let audio_processor = self.audio_processor.clone();
-        let device_registry = self.device_registry.clone();
-
         info!("Starting UDP Audio Server...");
 
-        tokio::spawn(async move {
-            let mut buf = vec![0u8; 4096]; // 4KB 缓冲区
-
-            loop {
-                match socket.recv_from(&mut buf).await {
-                    Ok((len, addr)) => {
-                        let packet_data = buf[..len].to_vec();
-
-                        if let Err(e) = Self::handle_udp_packet(
-                            packet_data,
-                            addr,
-                            audio_processor.clone(),
-                            device_registry.clone(),
-                        ).await {
-                            error!("Error handling UDP packet: {}", e);
+        // 处理任务：接收任务只做解析和分发，音频处理在这里进行，不阻塞接收
+        let workers: Arc<Vec<AudioSender<DispatchedPacket>>> = Arc::new(
+            (0..self.config.dispatch_workers.max(1))
+                .map(|index| {
+                    // 通道名用作指标标签，处理任务数在启动时固定，泄漏的字符串数量有上限
+                    let name: &'static str = Box::leak(format!("udp_dispatch_{}", index).into_boxed_str());
+                    let (sender, mut receiver) = channels::audio_channel::<DispatchedPacket>(name, self.config.dispatch_queue_capacity);
+                    let audio_processor = self.audio_processor.clone();
+                    let device_registry = self.device_registry.clone();
+                    let socket = self.socket.clone();
+                    let cluster = self.cluster.clone();
+                    let forward_key = self.forward_key.clone();
+                    tokio::spawn(async move {
+                        while let Some((packet, addr, packet_len, forwarded)) = receiver.recv().await {
+                            // 转发来的数据包已由来源副本完成路由，直接在本地处理
+                            if let (Some(cluster), Some(forward_key), false) = (&cluster, &forward_key, forwarded) {
+                                if let UdpRoute::Forward(peer) = cluster.route_udp(&packet.device_id).await {
+                                    Self::forward_packet(&socket, forward_key, &packet, addr, peer).await;
+                                    continue;
+                                }
+                            }
+                            Self::handle_udp_packet(
+                                packet,
+                                addr,
+                                packet_len,
+                                audio_processor.clone(),
+                                device_registry.clone(),
+                            ).await;
+                        }
+                    });
+                    sender
+                })
+                .collect(),
+        );
+
+        // 接收任务：每个 socket 一个，批量接收后按设备分发
+        for socket in &self.reader_sockets {
+            let socket = socket.clone();
+            let workers = workers.clone();
+            let batch_size = self.config.batch_size;
+            let max_datagram_bytes = self.max_datagram_bytes;
+            let ip_filter = self.ip_filter.clone();
+            let forward_key = self.forward_key.clone();
+            tokio::spawn(async move {
+                // 多留 1 字节，被截断的数据包长度超过限制，可以识别并丢弃
+                let mut batch = RecvBatch::new(batch_size, max_datagram_bytes + FORWARD_ENVELOPE_MAX_LEN + 1);
+
+                loop {
+                    match batch.recv_from(&socket).await {
+                        Ok(_) => {
+                            // 每批读取一次当前规则
+                            let filter = ip_filter.as_ref().map(IpFilterHandle::current);
+                            for (data, addr) in batch.packets() {
+                                Self::dispatch_packet(
+                                    data,
+                                    addr,
+                                    max_datagram_bytes,
+                                    forward_key.as_deref(),
+                                    filter.as_deref(),
+                                    &workers,
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            error!("UDP receive error: {}", e);
+                            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                         }
-                    }
-                    Err(e) => {
-                        error!("UDP receive error: {}", e);
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
                     }
                 }
-            }
-        });
+            });
+        }
 
         // 启动设备心跳检查任务
         self.start_device_heartbeat_check().await?;
@@ -95,24 +218,85 @@ impl UdpAudioServer {
         Ok(())
     }
 
+    // 解析数据包并交给设备对应的处理任务，同一设备的数据包始终进入同一队列以保持顺序
+    fn dispatch_packet(
+        data: &[u8],
+        addr: SocketAddr,
+        max_datagram_bytes: usize,
+        forward_key: Option<&UdpForwardKey>,
+        ip_filter: Option<&IpFilter>,
+        workers: &[AudioSender<DispatchedPacket>],
+    ) {
+        // 实际来源地址总是检查，集群模式下允许列表需要包含其他副本的地址
+        if let Some(filter) = ip_filter {
+            if !Self::check_ip(filter, addr) {
+                return;
+            }
+        }
+        // 只在集群模式下接受签名有效的转发数据包，其余一律按设备直接发送的数据包解析
+        let envelope = forward_key.and_then(|key| Self::parse_forward_envelope(data, key));
+        let (data, addr, forwarded) = match envelope {
+            Some((inner, device_addr)) => (inner, device_addr, true),
+            None => (data, addr, false),
+        };
+        // 签名校验通过后再检查信封中的设备地址
+        if let (Some(filter), true) = (ip_filter, forwarded) {
+            if !Self::check_ip(filter, addr) {
+                return;
+            }
+        }
+        if data.len() > max_datagram_bytes {
+            warn!("Dropping oversized UDP packet from {}: more than {} bytes", addr, max_datagram_bytes);
+            telemetry::record_ingress_rejected("udp");
+            return;
+        }
+        if data.len() < 16 {
+            warn!("Received too small UDP packet: {} bytes", data.len());
+            return;
+        }
+
+        let packet = match Self::parse_udp_packet(data) {
+            Ok(packet) => packet,
+            Err(e) => {
+                error!("Error handling UDP packet: {}", e);
+                return;
+            }
+        };
+
+        let mut hasher = DefaultHasher::new();
+        packet.device_id.hash(&mut hasher);
+        let worker = &workers[(hasher.finish() % workers.len() as u64) as usize];
+        if worker.send((packet, addr, data.len(), forwarded)).is_err() {
+            error!("UDP dispatch worker stopped, dropping packet from {}", addr);
+        }
+    }
+
+    // 被过滤规则拦截时记录并返回 false
+    fn check_ip(filter: &IpFilter, addr: SocketAddr) -> bool {
+        match filter.check_ip(Some(addr.ip())) {
+            Ok(()) => true,
+            Err(blocked) => {
+                debug!("Dropping UDP packet from {}: {:?}", addr, blocked);
+                telemetry::record_ip_blocked("udp", blocked.reason());
+                false
+            }
+        }
+    }
+
     // 处理 UDP 数据包
     async fn handle_udp_packet(
-        packet_data: Vec<u8>,
+        packet: UdpAudioPacket,
         addr: SocketAddr,
+        packet_len: usize,
         audio_processor: Arc<AudioProcessor>,
-        device_registry: Arc<tokio::sync::RwLock<std::collections::HashMap<String, DeviceInfo>>>,
-    ) -> Result<()> {
-        if packet_data.len() < 16 {
-            warn!("Received too small UDP packet: {} bytes", packet_data.len());
-            return Ok(());
-        }
-
-        // 解析 UDP 数据包
-        let packet = Self::parse_udp_packet(packet_data)?;
+        device_registry: DeviceRegistry,
+    ) {
         let device_id = packet.device_id.clone();
 
         debug!("Received UDP packet from device: {}, sequence: {}, size: {} bytes",
                device_id, packet.sequence_number, packet.audio_data.len());
+        telemetry::record_audio_bytes("in", "udp", packet.audio_data.len());
+        audio_capture::record(&device_id, "udp", &packet.audio_data);
 
         // 更新设备信息
         Self::update_device_info(
@@ -120,13 +304,11 @@ impl UdpAudioServer {
             device_id.clone(),
             addr,
             packet.sequence_number,
+            packet_len,
         ).await;
 
         // 检查设备是否已注册且有活跃会话
-        let device_info = {
-            let registry = device_registry.read().await;
-            registry.get(&device_id).cloned()
-        };
+        let device_info = device_registry.get(&device_id).map(|entry| entry.value().clone());
 
         if let Some(device_info) = device_info {
             // 创建音频块
@@ -154,13 +336,72 @@ impl UdpAudioServer {
         } else {
             warn!("Received audio from unregistered device: {}", device_id);
         }
+    }
 
-        Ok(())
+    // 将数据包连同设备地址签名后转发给归属副本
+    async fn forward_packet(
+        socket: &UdpSocket,
+        forward_key: &UdpForwardKey,
+        packet: &UdpAudioPacket,
+        device_addr: SocketAddr,
+        peer: SocketAddr,
+    ) {
+        let envelope = Self::encode_forward_envelope(packet, device_addr, forward_key);
+        let result = socket.send_to(&envelope, peer).await;
+        telemetry::record_cluster_forward("udp", result.is_ok());
+        if let Err(e) = result {
+            warn!("Failed to forward UDP packet of device {} to {}: {}", packet.device_id, peer, e);
+        }
+    }
+
+    fn encode_forward_envelope(packet: &UdpAudioPacket, device_addr: SocketAddr, forward_key: &UdpForwardKey) -> Vec<u8> {
+        let mut envelope =
+            Vec::with_capacity(FORWARD_ENVELOPE_MAX_LEN + packet.device_id.len() + 16 + packet.audio_data.len());
+        envelope.extend_from_slice(&[0x00, FORWARD_MAGIC]);
+        envelope.extend_from_slice(&[0u8; UDP_FORWARD_TAG_LEN]);
+        match device_addr.ip() {
+            std::net::IpAddr::V4(ip) => {
+                envelope.push(4);
+                envelope.extend_from_slice(&ip.octets());
+            }
+            std::net::IpAddr::V6(ip) => {
+                envelope.push(6);
+                envelope.extend_from_slice(&ip.octets());
+            }
+        }
+        envelope.extend_from_slice(&device_addr.port().to_le_bytes());
+        packet.encode_into(&mut envelope);
+        let tag = forward_key.sign(&envelope[2 + UDP_FORWARD_TAG_LEN..]);
+        envelope[2..2 + UDP_FORWARD_TAG_LEN].copy_from_slice(&tag);
+        envelope
+    }
+
+    // 解析其他副本转发的数据包，返回 (原始数据包, 设备地址)；签名无效时返回 None
+    fn parse_forward_envelope<'a>(data: &'a [u8], forward_key: &UdpForwardKey) -> Option<(&'a [u8], SocketAddr)> {
+        let rest = data.strip_prefix(&[0x00, FORWARD_MAGIC])?;
+        if rest.len() < UDP_FORWARD_TAG_LEN {
+            return None;
+        }
+        let (tag, rest) = rest.split_at(UDP_FORWARD_TAG_LEN);
+        if !forward_key.verify(rest, tag) {
+            debug!("Ignoring UDP forward envelope with an invalid signature");
+            return None;
+        }
+        let (ip, rest): (std::net::IpAddr, &[u8]) = match rest.split_first()? {
+            (4, rest) if rest.len() >= 4 => (<[u8; 4]>::try_from(&rest[..4]).ok()?.into(), &rest[4..]),
+            (6, rest) if rest.len() >= 16 => (<[u8; 16]>::try_from(&rest[..16]).ok()?.into(), &rest[16..]),
+            _ => return None,
+        };
+        if rest.len() < 2 {
+            return None;
+        }
+        let port = u16::from_le_bytes([rest[0], rest[1]]);
+        Some((&rest[2..], SocketAddr::new(ip, port)))
     }
 
     // 解析 UDP 数据包
-    fn parse_udp_packet(data: Vec<u8>) -> Result<UdpAudioPacket> {
-        let mut cursor = Cursor::new(data.clone());
+    fn parse_udp_packet(data: &[u8]) -> Result<UdpAudioPacket> {
+        let mut cursor = Cursor::new(data);
 
         // 读取设备 ID 长度和 ID
         let device_id_len = cursor.read_u8()? as usize;
@@ -203,30 +444,34 @@ impl UdpAudioServer {
 
     // 更新设备信息
     async fn update_device_info(
-        device_registry: Arc<tokio::sync::RwLock<std::collections::HashMap<String, DeviceInfo>>>,
+        device_registry: DeviceRegistry,
         device_id: String,
         address: SocketAddr,
         sequence_number: u32,
+        packet_len: usize,
     ) {
-        let mut registry = device_registry.write().await;
-
-        if let Some(device_info) = registry.get_mut(&device_id) {
+        if let Some(mut device_info) = device_registry.get_mut(&device_id) {
             device_info.last_seen = now_utc();
             device_info.address = address;
             device_info.sequence_number = sequence_number;
+            device_info.bytes_received += packet_len as u64;
         } else {
             // 新设备，添加默认配置
+            let now = now_utc();
             let device_info = DeviceInfo {
                 device_id: device_id.clone(),
                 address,
-                last_seen: now_utc(),
+                first_seen: now,
+                last_seen: now,
+                bytes_received: packet_len as u64,
+                bytes_sent: 0,
                 audio_format: AudioFormat::PCM16,
                 sample_rate: 16000,
                 channels: 1,
                 sequence_number,
             };
             info!("Registered new device: {}", device_id);
-            registry.insert(device_id, device_info);
+            device_registry.insert(device_id, device_info);
         }
     }
 
@@ -241,26 +486,15 @@ impl UdpAudioServer {
                 interval.tick().await;
 
                 let now = now_utc();
-                let mut devices_to_remove = Vec::new();
-
-                {
-                    let registry = device_registry.read().await;
-                    for (device_id, device_info) in registry.iter() {
-                        let duration = now.signed_duration_since(device_info.last_seen);
-                        if duration.num_seconds() > 60 { // 60秒无心跳认为设备离线
-                            devices_to_remove.push(device_id.clone());
-                        }
-                    }
-                }
 
-                // 移除离线设备
-                if !devices_to_remove.is_empty() {
-                    let mut registry = device_registry.write().await;
-                    for device_id in &devices_to_remove {
-                        registry.remove(device_id);
+                // 移除离线设备（60秒无心跳认为设备离线）
+                device_registry.retain(|device_id, device_info| {
+                    let online = now.signed_duration_since(device_info.last_seen).num_seconds() <= 60;
+                    if !online {
                         warn!("Device {} removed due to heartbeat timeout", device_id);
                     }
-                }
+                    online
+                });
             }
         });
 
@@ -275,19 +509,21 @@ impl UdpAudioServer {
         sample_rate: u32,
         channels: u8,
     ) -> Result<()> {
-        let mut registry = self.device_registry.write().await;
-
+        let now = now_utc();
         let device_info = DeviceInfo {
             device_id: device_id.clone(),
             address: "0.0.0.0:0".parse().unwrap(), // 占位地址
-            last_seen: now_utc(),
+            first_seen: now,
+            last_seen: now,
+            bytes_received: 0,
+            bytes_sent: 0,
             audio_format: audio_format.clone(),
             sample_rate,
             channels,
             sequence_number: 0,
         };
 
-        registry.insert(device_id.clone(), device_info);
+        self.device_registry.insert(device_id.clone(), device_info);
         info!("Registered device: {} with format: {:?}, rate: {}, channels: {}",
               device_id, audio_format, sample_rate, channels);
 
@@ -296,8 +532,7 @@ impl UdpAudioServer {
 
     // 注销设备
     pub async fn unregister_device(&self, device_id: &str) -> Result<()> {
-        let mut registry = self.device_registry.write().await;
-        if registry.remove(device_id).is_some() {
+        if self.device_registry.remove(device_id).is_some() {
             info!("Unregistered device: {}", device_id);
             Ok(())
         } else {
@@ -308,38 +543,72 @@ impl UdpAudioServer {
 
     // 获取设备列表
     pub async fn get_registered_devices(&self) -> Vec<String> {
-        self.device_registry.read().await.keys().cloned().collect()
+        self.device_registry.iter().map(|entry| entry.key().clone()).collect()
     }
 
     // 获取设备信息
     pub async fn get_device_info(&self, device_id: &str) -> Option<DeviceInfo> {
-        self.device_registry.read().await.get(device_id).cloned()
+        self.device_registry.get(device_id).map(|entry| entry.value().clone())
     }
 
-    // 发送数据到设备
-    pub async fn send_to_device(&self, device_id: &str, data: Vec<u8>) -> Result<()> {
-        let registry = self.device_registry.read().await;
+    // 获取所有设备连接的详细信息
+    pub async fn get_connections(&self) -> Vec<DeviceConnectionInfo> {
+        let devices: Vec<DeviceInfo> = self.device_registry.iter().map(|entry| entry.value().clone()).collect();
+
+        let mut connections = Vec::with_capacity(devices.len());
+        for device in devices {
+            connections.push(DeviceConnectionInfo {
+                session_id: self.audio_processor.get_session_id(&device.device_id).await,
+                device_id: device.device_id,
+                transport: DeviceTransport::Udp,
+                // 通过 register_device 注册但尚未收到数据包的设备没有地址
+                remote_addr: (device.address.port() != 0).then(|| device.address.to_string()),
+                connected_at: device.first_seen,
+                last_heartbeat: device.last_seen,
+                bytes_received: device.bytes_received,
+                bytes_sent: device.bytes_sent,
+            });
+        }
+        connections
+    }
 
-        if let Some(device_info) = registry.get(device_id) {
-            self.socket.send_to(&data, device_info.address).await
-                .with_context(|| format!("Failed to send data to device: {}", device_id))?;
+    // 获取实际绑定的地址
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().context("UDP socket is not bound")
+    }
 
-            debug!("Sent {} bytes to device: {}", data.len(), device_id);
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Device {} not found", device_id))
+    // 发送数据到设备
+    pub async fn send_to_device(&self, device_id: &str, data: Vec<u8>) -> Result<()> {
+        // 发送期间不持有分片锁
+        let address = self.device_registry.get(device_id).map(|entry| entry.address)
+            .ok_or_else(|| anyhow::anyhow!("Device {} not found", device_id))?;
+
+        self.socket.send_to(&data, address).await
+            .with_context(|| format!("Failed to send data to device: {}", device_id))?;
+        telemetry::record_audio_bytes("out", "udp", data.len());
+        if let Some(mut device_info) = self.device_registry.get_mut(device_id) {
+            device_info.bytes_sent += data.len() as u64;
         }
+
+        debug!("Sent {} bytes to device: {}", data.len(), device_id);
+        Ok(())
     }
 
     // 广播数据到所有设备
     pub async fn broadcast_to_devices(&self, data: Vec<u8>) -> Result<usize> {
-        let registry = self.device_registry.read().await;
+        let targets: Vec<(String, SocketAddr)> = self.device_registry
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.address))
+            .collect();
         let mut sent_count = 0;
 
-        for (device_id, device_info) in registry.iter() {
-            if let Err(e) = self.socket.send_to(&data, device_info.address).await {
+        for (device_id, address) in targets {
+            if let Err(e) = self.socket.send_to(&data, address).await {
                 error!("Failed to send broadcast to device {}: {}", device_id, e);
             } else {
+                if let Some(mut device_info) = self.device_registry.get_mut(&device_id) {
+                    device_info.bytes_sent += data.len() as u64;
+                }
                 sent_count += 1;
             }
         }
@@ -350,13 +619,12 @@ impl UdpAudioServer {
 
     // 获取服务器统计信息
     pub async fn get_stats(&self) -> UdpServerStats {
-        let registry = self.device_registry.read().await;
-        let online_devices = registry.len();
+        let online_devices = self.device_registry.len();
 
         UdpServerStats {
             online_devices,
             bind_address: self.socket.local_addr().unwrap().to_string(),
-            uptime_seconds: 0, // TODO: 实现运行时间统计
+            uptime_seconds: echo_shared::process_uptime_seconds(),
         }
     }
 }
@@ -461,4 +729,111 @@ impl UdpPacketBuilder {
 
         Ok(packet)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward_envelope_round_trip() {
+        let packet = UdpAudioPacket {
+            device_id: "dev_1".to_string(),
+            sequence_number: 7,
+            timestamp: 1_700_000_000_000,
+            audio_data: vec![1, 2, 3, 4],
+            flags: 0x01,
+        };
+
+        let key = UdpForwardKey::new("cluster-token");
+        for device_addr in ["10.0.0.5:40000", "[2001:db8::1]:40000"] {
+            let device_addr: SocketAddr = device_addr.parse().unwrap();
+            let envelope = UdpAudioServer::encode_forward_envelope(&packet, device_addr, &key);
+
+            let (inner, addr) = UdpAudioServer::parse_forward_envelope(&envelope, &key).unwrap();
+            assert_eq!(addr, device_addr);
+            assert!(envelope.len() - inner.len() <= FORWARD_ENVELOPE_MAX_LEN);
+            let parsed = UdpAudioServer::parse_udp_packet(inner).unwrap();
+            assert_eq!(parsed.device_id, "dev_1");
+            assert_eq!(parsed.sequence_number, 7);
+            assert_eq!(parsed.timestamp, 1_700_000_000_000);
+            assert_eq!(parsed.flags, 0x01);
+            assert_eq!(parsed.audio_data, vec![1, 2, 3, 4]);
+        }
+
+        // 设备直接发送的数据包不是转发格式
+        let mut raw = Vec::new();
+        packet.encode_into(&mut raw);
+        assert!(UdpAudioServer::parse_forward_envelope(&raw, &key).is_none());
+    }
+
+    // 分发一个数据包，返回进入处理队列的结果
+    async fn dispatched(data: &[u8], addr: SocketAddr, key: &UdpForwardKey, filter: &IpFilter) -> Option<DispatchedPacket> {
+        let (sender, mut receiver) = channels::audio_channel::<DispatchedPacket>("udp_dispatch_test", 4);
+        UdpAudioServer::dispatch_packet(data, addr, 1024, Some(key), Some(filter), &[sender]);
+        receiver.recv().await
+    }
+
+    #[tokio::test]
+    async fn test_ip_filter_checks_source_and_forwarded_device() {
+        let config = echo_shared::IpFilterConfig {
+            enabled: true,
+            deny: vec!["203.0.113.0/24".to_string()],
+            ..Default::default()
+        };
+        let (filter, _) = IpFilter::new(&config, &[]);
+        let key = UdpForwardKey::new("cluster-token");
+        let packet = UdpAudioPacket {
+            device_id: "dev_1".to_string(),
+            sequence_number: 7,
+            timestamp: 1_700_000_000_000,
+            audio_data: vec![1, 2, 3, 4],
+            flags: 0,
+        };
+        let peer: SocketAddr = "10.0.0.2:8083".parse().unwrap();
+        let device: SocketAddr = "10.0.0.5:40000".parse().unwrap();
+        let blocked: SocketAddr = "203.0.113.9:40000".parse().unwrap();
+
+        // 被拦截的来源发送的转发信封直接丢弃
+        let envelope = UdpAudioServer::encode_forward_envelope(&packet, device, &key);
+        assert!(dispatched(&envelope, blocked, &key, &filter).await.is_none());
+
+        // 签名有效时再检查信封中的设备地址
+        let envelope = UdpAudioServer::encode_forward_envelope(&packet, blocked, &key);
+        assert!(dispatched(&envelope, peer, &key, &filter).await.is_none());
+
+        let envelope = UdpAudioServer::encode_forward_envelope(&packet, device, &key);
+        let (parsed, addr, _, forwarded) = dispatched(&envelope, peer, &key, &filter).await.unwrap();
+        assert_eq!(parsed.device_id, "dev_1");
+        assert_eq!(addr, device);
+        assert!(forwarded);
+    }
+
+    #[test]
+    fn test_forward_envelope_requires_valid_signature() {
+        let packet = UdpAudioPacket {
+            device_id: "dev_1".to_string(),
+            sequence_number: 7,
+            timestamp: 1_700_000_000_000,
+            audio_data: vec![1, 2, 3, 4],
+            flags: 0,
+        };
+        let device_addr: SocketAddr = "10.0.0.5:40000".parse().unwrap();
+        let key = UdpForwardKey::new("cluster-token");
+        let envelope = UdpAudioServer::encode_forward_envelope(&packet, device_addr, &key);
+
+        // 其他令牌签名的信封不被接受
+        let other = UdpForwardKey::new("other-token");
+        assert!(UdpAudioServer::parse_forward_envelope(&envelope, &other).is_none());
+
+        // 篡改设备地址后签名失效
+        let mut tampered = envelope.clone();
+        tampered[2 + UDP_FORWARD_TAG_LEN + 1] ^= 0xFF;
+        assert!(UdpAudioServer::parse_forward_envelope(&tampered, &key).is_none());
+
+        // 伪造的无签名信封不被接受
+        let mut forged = vec![0x00, FORWARD_MAGIC, 4, 10, 0, 0, 5];
+        forged.extend_from_slice(&40000u16.to_le_bytes());
+        packet.encode_into(&mut forged);
+        assert!(UdpAudioServer::parse_forward_envelope(&forged, &key).is_none());
+    }
+}
@@ -1,19 +1,28 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State, Path, Query,
+        ConnectInfo, State, Path, Query,
     },
-    response::Response,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use tracing::{debug, error, info, warn};
 
 use crate::echokit::{EchoKitSessionAdapter, EchoKitConnectionPool};
-use super::connection_manager::DeviceConnectionManager;
-use super::session_manager::SessionManager;
+use super::connection_manager::{DeviceConnectionManager, DeviceSink};
+use super::session_manager::{SessionManager, SessionStatus};
+use crate::redis_client::RedisClient;
 use crate::session_service::SessionService;
+use echo_shared::{DeviceId, IngressLimitsConfig, SessionId, SessionLimitExceeded, WsTicketRejected, WsTickets};
+use echo_shared::protocol::{ClientCommand, Frame, FrameType, ProtocolVersion, V2_SUBPROTOCOL};
+use crate::circuit;
+use crate::audio_capture;
+use crate::telemetry;
+use crate::trace_capture::{self, TraceDirection};
 
 /// 应用状态
 #[derive(Clone)]
@@ -23,28 +32,39 @@ pub struct AppState {
     pub echokit_adapter: Arc<EchoKitSessionAdapter>,
     pub session_service: Arc<SessionService>,
     pub echokit_connection_pool: Arc<EchoKitConnectionPool>,  // 🎯 新增：连接池
+    pub redis: Arc<RedisClient>,
+    // WebSocket 一次性连接票据，未启用时为 None
+    pub ws_tickets: Option<Arc<WsTickets>>,
+    // 设备消息的大小限制
+    pub limits: Arc<IngressLimitsConfig>,
 }
 
 /// WebSocket 升级处理器
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
 ) -> Response {
     // TODO: 验证设备 Token
     // 临时：生成随机 device_id
-    let device_id = format!("device_{}", uuid::Uuid::new_v4());
+    let device_id = DeviceId::new(format!("device_{}", uuid::Uuid::new_v4()));
 
     info!("Device {} initiating WebSocket connection", device_id);
 
-    ws.on_upgrade(move |socket| handle_device_websocket(socket, device_id, false, state))
+    limit_message_size(ws, &state.limits).protocols([V2_SUBPROTOCOL]).on_upgrade(move |socket| {
+        let protocol = negotiate_protocol(&socket, None);
+        handle_device_websocket(socket, device_id, remote_addr, false, protocol, state)
+    })
 }
 
 /// WebSocket 升级处理器（简化版 - 直接使用 device_id）
 /// 新的 URL 格式：ws://localhost:10031/{device_id}?record=true
+/// 使用 v2 分帧协议的客户端请求子协议 `echo-bridge.v2` 或加上 `protocol=2`
 pub async fn websocket_handler_with_id(
     ws: WebSocketUpgrade,
-    Path(device_id): Path<String>,
+    Path(device_id): Path<DeviceId>,
     Query(params): Query<HashMap<String, String>>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
     State(state): State<AppState>,
 ) -> Response {
     // 从查询参数中提取 record 模式
@@ -53,62 +73,131 @@ pub async fn websocket_handler_with_id(
         .map(|v| v == "true")
         .unwrap_or(false);
 
+    // Web UI 携带 API Gateway 签发的一次性票据，避免把 JWT 放进 URL
+    if let Err(response) = redeem_ticket(&state, &device_id, params.get("ticket").map(String::as_str)).await {
+        return response;
+    }
+
     info!(
         "Device {} connecting (record_mode: {})",
         device_id, record_mode
     );
 
-    ws.on_upgrade(move |socket| {
-        handle_device_websocket(socket, device_id, record_mode, state)
+    let requested_protocol = params.get("protocol").cloned();
+    limit_message_size(ws, &state.limits).protocols([V2_SUBPROTOCOL]).on_upgrade(move |socket| {
+        let protocol = negotiate_protocol(&socket, requested_protocol.as_deref());
+        handle_device_websocket(socket, device_id, remote_addr, record_mode, protocol, state)
     })
 }
 
+/// 按握手选定的子协议或 `protocol` 查询参数确定协议版本，未协商的旧客户端按 v1 处理
+fn negotiate_protocol(socket: &WebSocket, query: Option<&str>) -> ProtocolVersion {
+    let subprotocol = socket.protocol().and_then(|value| value.to_str().ok());
+    ProtocolVersion::negotiate(subprotocol, query)
+}
+
+/// 超过限制的消息或帧使连接以错误结束，不会被完整读入内存
+fn limit_message_size(ws: WebSocketUpgrade, limits: &IngressLimitsConfig) -> WebSocketUpgrade {
+    ws.max_message_size(limits.max_websocket_message_bytes)
+        .max_frame_size(limits.max_websocket_frame_bytes)
+}
+
+/// 校验并消耗连接票据，未启用票据时直接放行
+async fn redeem_ticket(state: &AppState, device_id: &DeviceId, ticket: Option<&str>) -> Result<(), Response> {
+    authorize_ticket(state, device_id, ticket, "WebSocket")
+        .await
+        .map_err(|(status, message)| (status, message).into_response())
+}
+
+/// 校验并消耗连接票据，失败时返回对应的 HTTP 状态码和原因，供各设备传输共用
+pub(crate) async fn authorize_ticket(
+    state: &AppState,
+    device_id: &DeviceId,
+    ticket: Option<&str>,
+    transport: &str,
+) -> Result<(), (StatusCode, String)> {
+    redeem_connection_ticket(state.ws_tickets.as_deref(), &state.redis, device_id, ticket, transport).await
+}
+
+/// 用给定的票据存储校验并消耗票据，供没有设备接入状态的传输（HTTP 流式接口）使用
+pub(crate) async fn redeem_connection_ticket(
+    tickets: Option<&WsTickets>,
+    redis: &RedisClient,
+    device_id: &DeviceId,
+    ticket: Option<&str>,
+    transport: &str,
+) -> Result<(), (StatusCode, String)> {
+    let Some(tickets) = tickets else {
+        return Ok(());
+    };
+    let Some(ticket) = ticket else {
+        if tickets.config().required {
+            warn!("Rejected {} connection for device {}: {}", transport, device_id, WsTicketRejected::Missing);
+            return Err((StatusCode::UNAUTHORIZED, WsTicketRejected::Missing.to_string()));
+        }
+        return Ok(());
+    };
+
+    let result = redis
+        .run(|mut conn| async move { tickets.redeem(&mut conn, device_id, ticket).await })
+        .await;
+    match result {
+        Ok(Ok(ticket)) => {
+            info!("Device {} connection authorized by ticket issued to user {}", device_id, ticket.user_id);
+            Ok(())
+        }
+        Ok(Err(rejected)) => {
+            warn!("Rejected {} connection for device {}: {}", transport, device_id, rejected);
+            Err((StatusCode::UNAUTHORIZED, rejected.to_string()))
+        }
+        Err(e) => {
+            error!("Failed to verify connection ticket for device {}: {}", device_id, e);
+            Err((StatusCode::SERVICE_UNAVAILABLE, "Unable to verify connection ticket".to_string()))
+        }
+    }
+}
+
 /// 处理设备 WebSocket 连接
 async fn handle_device_websocket(
     socket: WebSocket,
-    device_id: String,
+    device_id: DeviceId,
+    remote_addr: SocketAddr,
     record_mode: bool,
+    protocol: ProtocolVersion,
     state: AppState,
 ) {
     let (sender, mut receiver) = socket.split();
 
     // 1. 注册设备连接
-    if let Err(e) = state.connection_manager
-        .register_device(device_id.clone(), sender)
-        .await
-    {
+    if let Err(e) = register_device_connection(&device_id, sender, Some(remote_addr), protocol, &state).await {
         error!("Failed to register device {}: {}", device_id, e);
         return;
     }
 
-    info!("Device {} WebSocket connected (record_mode: {})", device_id, record_mode);
-
-    // 🎯 2. 自动预加载设备的 EchoKit 连接（异步后台任务，不阻塞主流程）
-    let pool = state.echokit_connection_pool.clone();
-    let device_id_for_preload = device_id.clone();
-    tokio::spawn(async move {
-        match pool.get_connection_for_device(&device_id_for_preload).await {
-            Ok(_) => {
-                info!("✅ Pre-loaded EchoKit connection for device {}", device_id_for_preload);
-            }
-            Err(e) => {
-                warn!("⚠️ Failed to pre-load EchoKit connection for device {}: {}. Will retry on first session.", device_id_for_preload, e);
-            }
-        }
-    });
+    info!(
+        "Device {} WebSocket connected from {} (record_mode: {}, protocol: {})",
+        device_id, remote_addr, record_mode, protocol.as_str()
+    );
 
     // 2. 当前活跃会话 ID
-    let mut active_session: Option<String> = None;
+    let mut active_session: Option<SessionId> = None;
 
     // 🔧 用于跟踪设备级别的 EchoKit 会话（避免重复创建）
     let mut device_echokit_session: Option<String> = None;
 
+    // EchoKit 熔断期间每帧音频都会失败，只通知设备一次，转发恢复后重置
+    let mut echokit_unavailable_notified = false;
+
     // 3. 处理设备消息
     while let Some(msg_result) = receiver.next().await {
         match msg_result {
             Ok(Message::Text(text)) => {
                 // 更新心跳（任何客户端消息都表示连接活跃）
-                state.connection_manager.update_heartbeat(&device_id).await;
+                state.connection_manager.record_received(&device_id, text.len()).await;
+
+                if let Some(session_id) = &active_session {
+                    trace_capture::record_device_text(session_id, &device_id, &text);
+                }
 
                 // 处理控制消息
                 if let Err(e) = handle_control_message(
@@ -123,50 +212,53 @@ async fn handle_device_websocket(
                 }
             }
 
-            Ok(Message::Binary(audio_data)) => {
+            Ok(Message::Binary(data)) => {
                 // 更新心跳（音频数据也表示连接活跃）
-                state.connection_manager.update_heartbeat(&device_id).await;
-
-                // 处理音频数据
-                if let Some(session_id) = &active_session {
-                    // ✅ 检查设备是否仍然连接
-                    if !state.connection_manager.is_device_online(&device_id).await {
-                        warn!(
-                            "⚠️ Ignoring audio from disconnected device {} (session: {})",
-                            device_id, session_id
-                        );
-                        break;
-                    }
-
-                    info!(
-                        "📊 Received audio data: {} bytes for session {}",
-                        audio_data.len(),
-                        session_id
-                    );
-
-                    // 验证音频格式（16-bit PCM, 应该是偶数字节）
-                    if audio_data.len() % 2 != 0 {
-                        warn!("⚠️ Audio data length is odd: {} bytes (expecting 16-bit PCM)", audio_data.len());
-                    }
-
-                    // 采样率验证（假设1秒音频应该是32000字节 = 16000样本 * 2字节）
-                    let estimated_samples = audio_data.len() / 2;
-                    let estimated_duration_ms = (estimated_samples as f32 / 16.0) as u32; // 16样本/ms @ 16kHz
-                    info!(
-                        "📊 Audio stats: ~{} samples, ~{}ms @ 16kHz",
-                        estimated_samples,
-                        estimated_duration_ms
-                    );
+                state.connection_manager.record_received(&device_id, data.len()).await;
+
+                // 会话可能已通过内部 API 被强制结束
+                clear_ended_session(&mut active_session, &state).await;
+
+                // v1 的二进制消息都是音频；v2 按帧类型分发，音频帧必须属于当前活跃会话
+                let audio_data: &[u8] = match protocol {
+                    ProtocolVersion::V1 => &data,
+                    ProtocolVersion::V2 => match Frame::decode(&data) {
+                        Ok(frame) if frame.frame_type == FrameType::Audio => {
+                            if active_session.as_deref() != Some(frame.session_id) {
+                                debug!(
+                                    "Dropping audio for session {:?} from device {} (active session: {:?})",
+                                    frame.session_id, device_id, active_session
+                                );
+                                continue;
+                            }
+                            frame.payload
+                        }
+                        Ok(frame) if frame.frame_type == FrameType::Control => {
+                            if let Err(e) = handle_control_frame(
+                                frame,
+                                &device_id,
+                                record_mode,
+                                &mut active_session,
+                                &mut device_echokit_session,
+                                &state,
+                            ).await {
+                                error!("Failed to handle control frame: {}", e);
+                            }
+                            continue;
+                        }
+                        Ok(frame) => {
+                            warn!("Ignoring {:?} frame from device {}", frame.frame_type, device_id);
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("Malformed v2 frame ({} bytes) from device {}: {}", data.len(), device_id, e);
+                            continue;
+                        }
+                    },
+                };
 
-                    if let Err(e) = forward_audio_to_echokit(
-                        session_id,
-                        audio_data.to_vec(), // Convert Bytes to Vec<u8>
-                        &state,
-                    ).await {
-                        error!("Failed to forward audio: {}", e);
-                    }
-                } else {
-                    warn!("Received audio data without active session from device {}", device_id);
+                if !handle_device_audio(audio_data, "websocket", &device_id, &active_session, &mut echokit_unavailable_notified, &state).await {
+                    break;
                 }
             }
 
@@ -187,6 +279,10 @@ async fn handle_device_websocket(
             }
 
             Err(e) => {
+                if echo_shared::is_websocket_capacity_error(&e) {
+                    warn!("Device {} sent a WebSocket message larger than the configured limit", device_id);
+                    telemetry::record_ingress_rejected("websocket");
+                }
                 error!("WebSocket error for device {}: {}", device_id, e);
                 break;
             }
@@ -195,53 +291,125 @@ async fn handle_device_websocket(
         }
     }
 
+    // 🔧 修复：清空设备级 EchoKit 会话变量
+    // 这样下次连接时会创建新的 EchoKit 会话，而不是复用旧的
+    if device_echokit_session.is_some() {
+        info!("🧹 Clearing device-level EchoKit session for device {}", device_id);
+    }
+
     // 4. 清理连接并持久化会话数据
-    if let Some(session_id) = active_session {
-        // 🔧 方案B：从内存中获取完整的对话转录文本和 AI 回复
-        let full_transcript = state.session_manager.get_full_transcript(&session_id).await;
-        let full_response = state.session_manager.get_full_response(&session_id).await;
-
-        if let Some(transcript) = &full_transcript {
-            info!("💾 Session {} has {} characters of user transcription to save",
-                  session_id, transcript.len());
-        } else {
-            info!("ℹ️ Session {} has no user transcription content", session_id);
-        }
+    close_device_connection(&device_id, active_session, &state).await;
+}
 
-        if let Some(response) = &full_response {
-            info!("💾 Session {} has {} characters of AI responses to save",
-                  session_id, response.len());
-        } else {
-            info!("ℹ️ Session {} has no AI response content", session_id);
+/// 注册设备连接，补发断线期间暂存的回复音频并在后台预加载 EchoKit 连接
+pub(crate) async fn register_device_connection(
+    device_id: &DeviceId,
+    sender: impl Into<DeviceSink>,
+    remote_addr: Option<SocketAddr>,
+    protocol: ProtocolVersion,
+    state: &AppState,
+) -> anyhow::Result<()> {
+    state.connection_manager
+        .register_device(device_id.to_string(), sender, remote_addr, protocol)
+        .await?;
+
+    // 补发断线期间暂存的回复音频
+    state.echokit_adapter.replay_spooled_audio(device_id).await;
+
+    // 🎯 自动预加载设备的 EchoKit 连接（异步后台任务，不阻塞主流程）
+    let pool = state.echokit_connection_pool.clone();
+    let device_id_for_preload = device_id.clone();
+    tokio::spawn(async move {
+        match pool.get_connection_for_device(&device_id_for_preload).await {
+            Ok(_) => {
+                info!("✅ Pre-loaded EchoKit connection for device {}", device_id_for_preload);
+            }
+            Err(e) => {
+                warn!("⚠️ Failed to pre-load EchoKit connection for device {}: {}. Will retry on first session.", device_id_for_preload, e);
+            }
         }
+    });
 
-        // 更新内存会话状态
-        let _ = state.session_manager.end_session(&session_id).await;
+    Ok(())
+}
 
-        // 🔧 方案B：异步更新数据库（包含完整对话内容和 AI 回复）
+/// 处理设备上行的一帧音频，设备已断开时返回 false，调用方应停止读取
+pub(crate) async fn handle_device_audio(
+    audio_data: &[u8],
+    transport: &'static str,
+    device_id: &DeviceId,
+    active_session: &Option<SessionId>,
+    echokit_unavailable_notified: &mut bool,
+    state: &AppState,
+) -> bool {
+    telemetry::record_audio_bytes("in", transport, audio_data.len());
+    audio_capture::record(device_id, transport, audio_data);
+
+    let Some(session_id) = active_session else {
+        warn!("Received audio data without active session from device {}", device_id);
+        return true;
+    };
+
+    // ✅ 检查设备是否仍然连接
+    if !state.connection_manager.is_device_online(device_id).await {
+        warn!(
+            "⚠️ Ignoring audio from disconnected device {} (session: {})",
+            device_id, session_id
+        );
+        return false;
+    }
+
+    trace_capture::record(session_id, device_id, TraceDirection::DeviceToBridge, "audio", audio_data.len());
+
+    // 验证音频格式（16-bit PCM, 应该是偶数字节）
+    if audio_data.len() % 2 != 0 {
+        warn!("⚠️ Audio data length is odd: {} bytes (expecting 16-bit PCM)", audio_data.len());
+    }
+
+    // 逐帧日志按 log.sample_every 采样
+    if echo_shared::log_sampled!() {
+        // 采样率验证（假设1秒音频应该是32000字节 = 16000样本 * 2字节）
+        let estimated_samples = audio_data.len() / 2;
+        let estimated_duration_ms = (estimated_samples as f32 / 16.0) as u32; // 16样本/ms @ 16kHz
+        info!(
+            "📊 Received audio data: {} bytes for session {} (~{} samples, ~{}ms @ 16kHz)",
+            audio_data.len(),
+            session_id,
+            estimated_samples,
+            estimated_duration_ms
+        );
+    }
+
+    match forward_audio_to_echokit(session_id, audio_data.to_vec(), state).await {
+        Ok(()) => *echokit_unavailable_notified = false,
+        Err(e) => {
+            error!("Failed to forward audio: {}", e);
+            if !*echokit_unavailable_notified {
+                *echokit_unavailable_notified = notify_unavailable(device_id, &e, state).await;
+            }
+        }
+    }
+    true
+}
+
+/// 设备断开后结束活跃会话、关闭 EchoKit 会话并移除连接（已被强制结束的会话无需重复处理）
+pub(crate) async fn close_device_connection(device_id: &DeviceId, mut active_session: Option<SessionId>, state: &AppState) {
+    clear_ended_session(&mut active_session, state).await;
+    if let Some(session_id) = active_session {
+        // 🔧 方案B：结束会话并异步持久化完整的多轮对话内容，不阻塞连接关闭
+        let session_manager = state.session_manager.clone();
         let session_service = state.session_service.clone();
         let session_id_for_db = session_id.clone();
         tokio::spawn(async move {
-            match session_service
-                .update_session(
-                    &session_id_for_db,
-                    echo_shared::database::SessionStatus::Completed,
-                    full_transcript,  // 完整的多轮对话转录文本
-                    full_response,    // 完整的多轮 AI 回复文本
-                    None,             // audio_url: 暂不保存
-                )
+            if let Err(e) = session_manager
+                .finalize(&session_id_for_db, SessionStatus::Completed, &session_service)
                 .await
             {
-                Ok(_) => {
-                    info!("✅ Session {} saved to database with complete conversation and AI responses", session_id_for_db);
-                }
-                Err(e) => {
-                    error!("❌ Failed to save session {} to database: {}", session_id_for_db, e);
-                }
+                error!("❌ Failed to save session {} to database: {}", session_id_for_db, e);
             }
         });
 
-        // 🔧 修复：异步清理 EchoKit 会话，避免阻塞 WebSocket 关闭
+        // 🔧 修复：异步清理 EchoKit 会话，避免阻塞连接关闭
         // 使用 tokio::spawn 在后台执行清理，不等待完成
         let adapter = state.echokit_adapter.clone();
         let session_id_clone = session_id.clone();
@@ -254,23 +422,16 @@ async fn handle_device_websocket(
         });
     }
 
-    // 🔧 修复：清空设备级 EchoKit 会话变量
-    // 这样下次连接时会创建新的 EchoKit 会话，而不是复用旧的
-    if device_echokit_session.is_some() {
-        info!("🧹 Clearing device-level EchoKit session for device {}", device_id);
-        // device_echokit_session = None; // 这行代码不需要，因为函数即将结束
-    }
-
-    let _ = state.connection_manager.remove_device(&device_id).await;
+    let _ = state.connection_manager.remove_device(device_id).await;
     info!("Device {} disconnected", device_id);
 }
 
 /// 处理控制消息（JSON格式）
 async fn handle_control_message(
     text: &str,
-    device_id: &str,
+    device_id: &DeviceId,
     record_mode: bool,
-    active_session: &mut Option<String>,
+    active_session: &mut Option<SessionId>,
     device_echokit_session: &mut Option<String>,
     state: &AppState,
 ) -> anyhow::Result<()> {
@@ -288,42 +449,48 @@ async fn handle_control_message(
             let session_id = generate_session_id();
             info!("Device {} starting session {}", device_id, session_id);
 
-            // 绑定会话到设备（内存中）
-            state.session_manager
-                .create_session(session_id.clone(), device_id.to_string())
-                .await?;
-
-            state.connection_manager
-                .bind_session(session_id.clone(), device_id.to_string())
-                .await?;
-
-            // 持久化到数据库
+            // 持久化到数据库（同时检查会话配额）
             if let Err(e) = state.session_service
                 .create_session(
                     &session_id, // session_id
                     &device_id, // device_id
                     None, // user_id 暂时为空
                     Some("device_triggered".to_string()),
+                    None, // language: 旧格式不支持指定语言
                 )
                 .await
             {
+                if notify_limit_exceeded(device_id, &e, state).await {
+                    return Ok(());
+                }
                 error!("Failed to persist session {} to database: {}", session_id, e);
                 // 继续处理，不阻断会话创建
             } else {
                 debug!("Session {} persisted to database", session_id);
             }
 
+            // 绑定会话到设备（内存中）
+            state.session_manager
+                .create_session(session_id.clone(), device_id.clone())
+                .await?;
+
+            state.connection_manager
+                .bind_session(session_id.clone(), device_id.clone())
+                .await?;
+
             // 创建 EchoKit 会话
             let echokit_config = echo_shared::EchoKitConfig::default();
             if let Err(e) = state.echokit_adapter
                 .create_echokit_session(
                     session_id.clone(),
-                    device_id.to_string(),
+                    device_id.clone(),
                     echokit_config,
                 )
                 .await
             {
                 error!("Failed to create EchoKit session: {}", e);
+                telemetry::record_session_failed("echokit");
+                notify_unavailable(device_id, &e, state).await;
                 // 继续处理，但记录错误
             }
 
@@ -354,26 +521,15 @@ async fn handle_control_message(
                     error!("Failed to close EchoKit session: {}", e);
                 }
 
-                // 更新内存会话状态
-                state.session_manager.end_session(&session_id).await?;
-                state.connection_manager.unbind_session(&session_id).await?;
-                *active_session = None;
-
-                // 更新数据库会话状态
-                if let Err(e) = state.session_service
-                    .update_session(
-                        &session_id,
-                        echo_shared::database::SessionStatus::Completed,
-                        None,
-                        None,
-                        None,
-                    )
+                // 结束会话并持久化完整对话内容
+                if let Err(e) = state.session_manager
+                    .finalize(&session_id, SessionStatus::Completed, &state.session_service)
                     .await
                 {
                     error!("Failed to update session {} in database: {}", session_id, e);
-                } else {
-                    debug!("Session {} marked as completed in database", session_id);
                 }
+                state.connection_manager.unbind_session(&session_id).await?;
+                *active_session = None;
 
                 // 响应设备
                 let response = serde_json::json!({
@@ -411,7 +567,7 @@ async fn handle_control_message(
 
 /// 转发音频到 EchoKit
 async fn forward_audio_to_echokit(
-    session_id: &str,
+    session_id: &SessionId,
     audio_data: Vec<u8>,
     state: &AppState,
 ) -> anyhow::Result<()> {
@@ -442,26 +598,55 @@ async fn forward_audio_to_echokit(
 
     // 更新会话统计
     state.session_manager.increment_sent_frames(session_id).await;
+    state.session_manager.add_buffered_audio(session_id, data_len).await;
 
     debug!("Forwarded {} bytes audio for session {}", data_len, session_id);
     Ok(())
 }
 
-/// 处理客户端命令（Web 客户端协议）
-async fn handle_client_command(
+/// 处理 v2 `Control` 帧（MessagePack 编码的 `ClientCommand`）
+///
+/// 开始会话的命令由 Bridge 分配会话 ID；其他命令必须带上当前活跃会话的 ID，
+/// 发给已结束会话的命令直接忽略。
+async fn handle_control_frame(
+    frame: Frame<'_>,
+    device_id: &DeviceId,
+    record_mode: bool,
+    active_session: &mut Option<SessionId>,
+    device_echokit_session: &mut Option<String>,
+    state: &AppState,
+) -> anyhow::Result<()> {
+    let cmd = ClientCommand::from_messagepack(frame.payload)?;
+    if !cmd.is_session_start() && active_session.as_deref() != Some(frame.session_id) {
+        warn!(
+            "Ignoring {} for session {:?} from device {} (active session: {:?})",
+            cmd.name(), frame.session_id, device_id, active_session
+        );
+        return Ok(());
+    }
+
+    if let Some(session_id) = active_session.as_ref() {
+        trace_capture::record(session_id, device_id, TraceDirection::DeviceToBridge, cmd.name(), frame.payload.len());
+    }
+    handle_client_command(cmd, device_id, record_mode, active_session, device_echokit_session, state).await
+}
+
+/// 处理客户端命令（Web 客户端协议），各设备传输共用
+pub(crate) async fn handle_client_command(
     cmd: super::protocol::ClientCommand,
-    device_id: &str,
+    device_id: &DeviceId,
     record_mode: bool,
-    active_session: &mut Option<String>,
+    active_session: &mut Option<SessionId>,
     device_echokit_session: &mut Option<String>,
     state: &AppState,
 ) -> anyhow::Result<()> {
     use super::protocol::ClientCommand;
 
     match cmd {
-        ClientCommand::StartChat | ClientCommand::StartRecord => {
+        ClientCommand::StartChat { .. } | ClientCommand::StartRecord => {
             // 使用传入的 record_mode 参数，或从命令判断（向后兼容）
             let is_record = record_mode || cmd.is_record_mode();
+            let language = resolve_session_language(cmd.language(), device_id, state).await;
 
             // 如果已有活跃会话，先清理（支持多轮对话）
             if let Some(old_session_id) = active_session.take() {
@@ -479,7 +664,10 @@ async fn handle_client_command(
                 }
 
                 // 清理旧会话
-                if let Err(e) = state.session_manager.end_session(&old_session_id).await {
+                if let Err(e) = state.session_manager
+                    .finalize(&old_session_id, SessionStatus::Completed, &state.session_service)
+                    .await
+                {
                     error!("Failed to end old session: {}", e);
                 }
                 if let Err(e) = state.connection_manager.unbind_session(&old_session_id).await {
@@ -503,9 +691,13 @@ async fn handle_client_command(
                     device_id,
                     None, // user_id: 暂时为 None，WebUI 没有用户认证
                     None, // wake_reason: 暂时为 None
+                    language.as_deref(),
                 )
                 .await
             {
+                if notify_limit_exceeded(device_id, &e, state).await {
+                    return Ok(());
+                }
                 error!("Failed to create session {} in database: {}", session_id, e);
             } else {
                 info!("✅ Session {} saved to database", session_id);
@@ -513,16 +705,19 @@ async fn handle_client_command(
 
             // 绑定会话到内存管理器
             state.session_manager
-                .create_session(session_id.clone(), device_id.to_string())
+                .create_session(session_id.clone(), device_id.clone())
                 .await?;
 
             state.connection_manager
-                .bind_session(session_id.clone(), device_id.to_string())
+                .bind_session(session_id.clone(), device_id.clone())
                 .await?;
 
             // 只有对话模式才创建 EchoKit 会话
             if !is_record {
-                let echokit_config = echo_shared::EchoKitConfig::default();
+                let mut echokit_config = echo_shared::EchoKitConfig::default();
+                if let Some(language) = &language {
+                    echokit_config.asr_language = language.clone();
+                }
 
                 // 🔧 检查是否已有设备级别的 EchoKit 会话
                 if let Some(existing_ek_session) = &device_echokit_session {
@@ -536,7 +731,7 @@ async fn handle_client_command(
                     state.echokit_adapter
                         .register_bridge_session(
                             session_id.clone(),
-                            device_id.to_string(),
+                            device_id.clone(),
                             existing_ek_session.clone(),
                         )
                         .await?;
@@ -546,9 +741,11 @@ async fn handle_client_command(
 
                     // 🔑 关键修复：每轮对话都需要发送 StartChat 命令
                     // EchoKit Server 期望在每轮对话开始时收到 StartChat
-                    if matches!(cmd, ClientCommand::StartChat) {
-                        if let Err(e) = state.echokit_adapter.send_start_chat(&existing_ek_session).await {
+                    // 复用的 EchoKit 会话按创建时的语言识别，本轮指定的语言随 StartChat 下发
+                    if matches!(cmd, ClientCommand::StartChat { .. }) {
+                        if let Err(e) = state.echokit_adapter.send_start_chat(&existing_ek_session, language.as_deref()).await {
                             error!("Failed to send StartChat command to EchoKit: {}", e);
+                            notify_unavailable(device_id, &e, state).await;
                         } else {
                             info!("📤 StartChat command sent to EchoKit for session {}", existing_ek_session);
                         }
@@ -558,13 +755,15 @@ async fn handle_client_command(
                     match state.echokit_adapter
                         .create_echokit_session(
                             session_id.clone(),
-                            device_id.to_string(),
+                            device_id.clone(),
                             echokit_config,
                         )
                         .await
                     {
                         Err(e) => {
                             error!("Failed to create EchoKit session: {}", e);
+                            telemetry::record_session_failed("echokit");
+                            notify_unavailable(device_id, &e, state).await;
                         }
                         Ok(echokit_session_id) => {
                             // EchoKit 会话创建成功
@@ -575,9 +774,10 @@ async fn handle_client_command(
                             *device_echokit_session = Some(echokit_session_id.clone());
 
                             // 转发 StartChat 命令给 EchoKit
-                            if matches!(cmd, ClientCommand::StartChat) {
-                                if let Err(e) = state.echokit_adapter.send_start_chat(&echokit_session_id).await {
+                            if matches!(cmd, ClientCommand::StartChat { .. }) {
+                                if let Err(e) = state.echokit_adapter.send_start_chat(&echokit_session_id, language.as_deref()).await {
                                     error!("Failed to send StartChat command to EchoKit: {}", e);
+                                    notify_unavailable(device_id, &e, state).await;
                                 } else {
                                     info!("📤 StartChat command forwarded to EchoKit for session {}", echokit_session_id);
                                 }
@@ -592,8 +792,17 @@ async fn handle_client_command(
             // 更新活跃会话
             *active_session = Some(session_id.clone());
 
-            // 响应客户端（兼容 Web 客户端，不发送响应）
-            // Web 客户端不期望响应消息
+            // v1 Web 客户端不期望响应消息；v2 设备从通知中获得会话 ID，之后的音频和命令帧都要带上
+            if state.connection_manager.protocol_version(device_id).await == ProtocolVersion::V2 {
+                let response = serde_json::json!({
+                    "event": "session_started",
+                    "session_id": session_id,
+                    "timestamp": chrono::Utc::now().timestamp()
+                });
+                state.connection_manager
+                    .send_text(device_id, &response.to_string())
+                    .await?;
+            }
             info!("Session {} created successfully", session_id);
         }
 
@@ -605,9 +814,11 @@ async fn handle_client_command(
                 // EchoKit期望收到Submit消息来触发ASR处理
                 if let Err(e) = state.echokit_adapter.submit_audio_for_processing(session_id).await {
                     error!("Failed to submit audio to EchoKit for processing: {}", e);
+                    notify_unavailable(device_id, &e, state).await;
                 }
 
                 debug!("Audio submission completed for session {}", session_id);
+                state.session_manager.mark_submitted(session_id).await;
 
                 // 🔄 重置本轮对话的 StartChat 标记
                 // 下一轮对话需要重新发送 StartChat
@@ -643,15 +854,82 @@ async fn handle_client_command(
     Ok(())
 }
 
+/// 解析 StartChat 指定的识别语言
+///
+/// 语言标签先规范化；EchoKit 上报了支持的语言时只接受其中的语言（主语言相同即可），
+/// 无效或不支持的语言记录警告并返回 None，使用默认语言
+async fn resolve_session_language(requested: Option<&str>, device_id: &DeviceId, state: &AppState) -> Option<String> {
+    let requested = requested?;
+    let Some(language) = echo_shared::normalize_language_tag(requested) else {
+        warn!("Device {} requested invalid language tag {:?}, using default language", device_id, requested);
+        return None;
+    };
+
+    let supported = state.echokit_connection_pool.supported_languages().await;
+    let primary = |tag: &str| tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+    if !supported.is_empty()
+        && !supported
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(&language) || primary(tag) == primary(&language))
+    {
+        warn!(
+            "Device {} requested language {} not supported by EchoKit ({}), using default language",
+            device_id,
+            language,
+            supported.join(", ")
+        );
+        return None;
+    }
+    Some(language)
+}
+
+/// 失败由熔断引起时向设备发送 `error` 事件，返回是否已通知
+async fn notify_unavailable(device_id: &DeviceId, error: &anyhow::Error, state: &AppState) -> bool {
+    let Some(event) = circuit::unavailable_event(error) else {
+        return false;
+    };
+    if let Err(e) = state.connection_manager.send_text(device_id, &event.to_string()).await {
+        warn!("Failed to notify device {} of unavailable service: {}", device_id, e);
+    }
+    true
+}
+
+/// 会话因超出配额被拒绝时，向设备发送 `error` 事件，返回 true
+async fn notify_limit_exceeded(device_id: &DeviceId, error: &anyhow::Error, state: &AppState) -> bool {
+    let Some(exceeded) = error.downcast_ref::<SessionLimitExceeded>() else {
+        return false;
+    };
+    let event = serde_json::json!({
+        "event": "error",
+        "code": "session_limit_exceeded",
+        "message": exceeded.to_string(),
+        "timestamp": chrono::Utc::now().timestamp()
+    });
+    if let Err(e) = state.connection_manager.send_text(device_id, &event.to_string()).await {
+        warn!("Failed to notify device {} of session limit: {}", device_id, e);
+    }
+    true
+}
+
+/// 如果当前活跃会话已在别处结束，清除设备的活跃会话
+pub(crate) async fn clear_ended_session(active_session: &mut Option<SessionId>, state: &AppState) {
+    if let Some(session_id) = active_session.as_deref() {
+        if !state.session_manager.is_active(session_id).await {
+            info!("Session {} was ended externally, clearing active session", session_id);
+            *active_session = None;
+        }
+    }
+}
+
 /// 生成会话ID
-fn generate_session_id() -> String {
-    format!("session_{}", uuid::Uuid::new_v4())
+fn generate_session_id() -> SessionId {
+    SessionId::new(format!("session_{}", uuid::Uuid::new_v4()))
 }
 
 /// 设备事件消息
 #[derive(Debug, serde::Deserialize)]
 struct DeviceEvent {
     event_type: String,
-    session_id: Option<String>,
+    session_id: Option<SessionId>,
     timestamp: Option<i64>,
 }
@@ -0,0 +1,440 @@
+//! 回复音频暂存
+//!
+//! 设备在接收回复音频时断线，发送失败的音频以及该会话随后的音频按会话暂存（先放内存，
+//! 超出上限后溢出到 `bridge.audio_spool.spool_dir`），设备在 `grace_period_seconds`
+//! 内重连后按顺序补发。超时未重连或超出暂存上限的会话丢弃暂存的音频，并标记为投递失败。
+//! 启用静态加密（`encryption`）时，写入磁盘的每一帧都用暂存区为该会话单独生成的数据密钥加密，
+//! 补发前解密。这个密钥随暂存的会话一起丢弃，不占用会话结束时已释放的会话数据密钥。
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use echo_shared::encryption::DataKey;
+use echo_shared::{AudioSpoolConfig, FieldEncryptor};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::telemetry;
+use crate::websocket::connection_manager::DeviceConnectionManager;
+use crate::websocket::session_manager::SessionManager;
+
+// 过期检查间隔
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 暂存失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpoolFailure {
+    /// 设备未在宽限期内重连
+    Expired,
+    /// 超出内存和磁盘暂存上限
+    Overflow,
+}
+
+impl SpoolFailure {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpoolFailure::Expired => "expired",
+            SpoolFailure::Overflow => "overflow",
+        }
+    }
+}
+
+// 溢出到磁盘的音频，每帧以 4 字节大端长度开头，启用加密时帧内容为密文
+struct Overflow {
+    path: PathBuf,
+    file: tokio::fs::File,
+    bytes: usize,
+}
+
+// 单个会话暂存的音频
+struct SpooledSession {
+    device_id: String,
+    since: Instant,
+    frames: VecDeque<Bytes>,
+    memory_bytes: usize,
+    overflow: Option<Overflow>,
+    // 溢出文件的数据密钥，启用加密时首次写入磁盘时生成
+    key: Option<DataKey>,
+    // 已超出上限，随后的音频直接丢弃，直到过期清理
+    failed: bool,
+}
+
+impl SpooledSession {
+    fn pop_front(&mut self) -> Option<Bytes> {
+        let frame = self.frames.pop_front()?;
+        self.memory_bytes -= frame.len();
+        Some(frame)
+    }
+}
+
+/// 按会话暂存未送达的回复音频
+pub struct AudioSpool {
+    config: AudioSpoolConfig,
+    grace: Duration,
+    sessions: Mutex<HashMap<String, SpooledSession>>,
+    // 溢出到磁盘的音频加密后写入，未启用时以明文写入
+    encryptor: Option<Arc<FieldEncryptor>>,
+}
+
+impl AudioSpool {
+    /// 创建暂存区，并清理上次运行遗留的溢出文件（重启后无法补发）
+    pub fn new(config: AudioSpoolConfig) -> Self {
+        if let Some(dir) = &config.spool_dir {
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|ext| ext == "spool") {
+                        let _ = std::fs::remove_file(&path);
+                    }
+                }
+            }
+        }
+
+        Self {
+            grace: Duration::from_secs(config.grace_period_seconds),
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            encryptor: None,
+        }
+    }
+
+    /// 溢出到磁盘的音频加密后写入
+    pub fn with_encryption(mut self, encryptor: Arc<FieldEncryptor>) -> Self {
+        self.encryptor = Some(encryptor);
+        self
+    }
+
+    /// 会话是否有尚未补发的音频，此时新音频也需要暂存以保证顺序
+    pub async fn is_spooling(&self, session_id: &str) -> bool {
+        self.sessions.lock().await.contains_key(session_id)
+    }
+
+    /// 暂存一帧音频；超出上限时丢弃该会话暂存的全部音频并返回 `Overflow`
+    pub async fn push(&self, session_id: &str, device_id: &str, data: Bytes) -> Result<(), SpoolFailure> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.entry(session_id.to_string()).or_insert_with(|| {
+            info!("Device {} unreachable, spooling response audio for session {}", device_id, session_id);
+            SpooledSession {
+                device_id: device_id.to_string(),
+                since: Instant::now(),
+                frames: VecDeque::new(),
+                memory_bytes: 0,
+                overflow: None,
+                key: None,
+                failed: false,
+            }
+        });
+        if session.failed {
+            return Err(SpoolFailure::Overflow);
+        }
+
+        // 已溢出到磁盘后，新音频都写入磁盘，保证补发顺序
+        if session.overflow.is_none() && session.memory_bytes + data.len() <= self.config.max_memory_bytes_per_session {
+            session.memory_bytes += data.len();
+            telemetry::record_audio_spooled("memory", data.len());
+            session.frames.push_back(data);
+            return Ok(());
+        }
+
+        match self.write_overflow(session_id, session, &data).await {
+            Ok(true) => {
+                telemetry::record_audio_spooled("disk", data.len());
+                Ok(())
+            }
+            Ok(false) => {
+                warn!("Audio spool for session {} is full, dropping spooled audio", session_id);
+                self.fail(session);
+                Err(SpoolFailure::Overflow)
+            }
+            Err(e) => {
+                warn!("Failed to spool audio for session {} to disk: {}", session_id, e);
+                self.fail(session);
+                Err(SpoolFailure::Overflow)
+            }
+        }
+    }
+
+    /// 设备重连后按顺序补发其各会话暂存的音频，返回补发的帧数
+    ///
+    /// 补发过程中再次发送失败时停止，剩余音频继续暂存到宽限期结束。
+    pub async fn replay(&self, device_id: &str, connections: &DeviceConnectionManager) -> usize {
+        let session_ids: Vec<String> = {
+            let sessions = self.sessions.lock().await;
+            let mut pending: Vec<(&String, Instant)> = sessions
+                .iter()
+                .filter(|(_, session)| session.device_id == device_id && !session.failed)
+                .map(|(session_id, session)| (session_id, session.since))
+                .collect();
+            pending.sort_by_key(|(_, since)| *since);
+            pending.into_iter().map(|(session_id, _)| session_id.clone()).collect()
+        };
+
+        let mut replayed = 0;
+        for session_id in session_ids {
+            while let Some(frame) = self.next_frame(&session_id).await {
+                if let Err(e) = connections.try_send_binary(device_id, frame.clone()).await {
+                    warn!(
+                        "Replay to device {} interrupted after {} frames, keeping the rest: {}",
+                        device_id, replayed, e
+                    );
+                    if let Some(session) = self.sessions.lock().await.get_mut(&session_id) {
+                        session.memory_bytes += frame.len();
+                        session.frames.push_front(frame);
+                    }
+                    telemetry::record_audio_spool_replayed(replayed);
+                    return replayed;
+                }
+                replayed += 1;
+            }
+        }
+
+        if replayed > 0 {
+            info!("Replayed {} spooled audio frames to device {}", replayed, device_id);
+            telemetry::record_audio_spool_replayed(replayed);
+        }
+        replayed
+    }
+
+    /// 清理超过宽限期的会话，返回需要标记投递失败的 (会话 ID, 设备 ID)
+    pub async fn expire(&self) -> Vec<(String, String)> {
+        let mut expired = Vec::new();
+        let mut files = Vec::new();
+        {
+            let mut sessions = self.sessions.lock().await;
+            sessions.retain(|session_id, session| {
+                if session.since.elapsed() < self.grace {
+                    return true;
+                }
+                if let Some(overflow) = session.overflow.take() {
+                    files.push(overflow.path);
+                }
+                // 超出上限的会话在失败时已经上报
+                if !session.failed {
+                    expired.push((session_id.clone(), session.device_id.clone()));
+                }
+                false
+            });
+        }
+
+        for path in files {
+            remove_spool_file(&path).await;
+        }
+        for _ in &expired {
+            telemetry::record_audio_spool_failed(SpoolFailure::Expired.as_str());
+        }
+        expired
+    }
+
+    /// 定期清理过期会话并将其标记为投递失败
+    pub async fn run_expiry(self: Arc<Self>, session_manager: Arc<SessionManager>) {
+        let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (session_id, device_id) in self.expire().await {
+                warn!(
+                    "Device {} did not reconnect within {:?}, response audio for session {} was not delivered",
+                    device_id, self.grace, session_id
+                );
+                session_manager.mark_delivery_failed(&session_id).await;
+            }
+        }
+    }
+
+    // 取出会话的下一帧；内存中的音频补发完后载入磁盘上的音频，全部补发完后移除会话
+    async fn next_frame(&self, session_id: &str) -> Option<Bytes> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions.get_mut(session_id)?;
+        if session.frames.is_empty() {
+            if let Some(overflow) = session.overflow.take() {
+                match load_overflow(overflow, self.encryptor.as_deref()).await {
+                    Ok(frames) => {
+                        session.memory_bytes = frames.iter().map(|frame| frame.len()).sum();
+                        session.frames = frames;
+                    }
+                    Err(e) => warn!("Failed to read spooled audio for session {}: {}", session_id, e),
+                }
+            }
+        }
+
+        match session.pop_front() {
+            Some(frame) => Some(frame),
+            None => {
+                sessions.remove(session_id);
+                None
+            }
+        }
+    }
+
+    // 追加到磁盘，未配置磁盘或超出磁盘上限时返回 false
+    async fn write_overflow(&self, session_id: &str, session: &mut SpooledSession, data: &[u8]) -> std::io::Result<bool> {
+        let Some(dir) = &self.config.spool_dir else {
+            return Ok(false);
+        };
+        let sealed;
+        let data = match &self.encryptor {
+            Some(encryptor) => {
+                sealed = session.key.get_or_insert_with(|| encryptor.data_key()).encrypt_bytes(data);
+                sealed.as_slice()
+            }
+            None => data,
+        };
+        let written = session.overflow.as_ref().map_or(0, |overflow| overflow.bytes);
+        if written + data.len() + 4 > self.config.max_disk_bytes_per_session {
+            return Ok(false);
+        }
+
+        if session.overflow.is_none() {
+            tokio::fs::create_dir_all(dir).await?;
+            let file_name: String = session_id
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect();
+            let path = Path::new(dir).join(format!("{}.spool", file_name));
+            let file = tokio::fs::File::create(&path).await?;
+            debug!("Spooling audio for session {} to {}", session_id, path.display());
+            session.overflow = Some(Overflow { path, file, bytes: 0 });
+        }
+
+        let overflow = session.overflow.as_mut().expect("overflow file created above");
+        overflow.file.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        overflow.file.write_all(data).await?;
+        overflow.file.flush().await?;
+        overflow.bytes += data.len() + 4;
+        Ok(true)
+    }
+
+    // 丢弃会话暂存的音频并记为失败
+    fn fail(&self, session: &mut SpooledSession) {
+        session.failed = true;
+        session.frames.clear();
+        session.memory_bytes = 0;
+        if let Some(overflow) = session.overflow.take() {
+            tokio::spawn(async move { remove_spool_file(&overflow.path).await });
+        }
+        telemetry::record_audio_spool_failed(SpoolFailure::Overflow.as_str());
+    }
+}
+
+// 读取溢出文件中的全部帧（启用加密时逐帧解密）并删除文件
+async fn load_overflow(overflow: Overflow, encryptor: Option<&FieldEncryptor>) -> std::io::Result<VecDeque<Bytes>> {
+    let Overflow { path, file, .. } = overflow;
+    drop(file);
+    let data = Bytes::from(tokio::fs::read(&path).await?);
+    remove_spool_file(&path).await;
+
+    let mut frames = VecDeque::new();
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let len = u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize;
+        offset += 4;
+        if offset + len > data.len() {
+            break;
+        }
+        let frame = data.slice(offset..offset + len);
+        let frame = match encryptor {
+            Some(encryptor) => encryptor
+                .decrypt_bytes(&frame)
+                .map(|plaintext| Bytes::from(plaintext.into_owned()))
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+            None => frame,
+        };
+        frames.push_back(frame);
+        offset += len;
+    }
+    Ok(frames)
+}
+
+async fn remove_spool_file(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        debug!("Failed to remove audio spool file {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spool(max_memory: usize, spool_dir: Option<String>, grace_period_seconds: u64) -> AudioSpool {
+        AudioSpool::new(AudioSpoolConfig {
+            enabled: true,
+            grace_period_seconds,
+            max_memory_bytes_per_session: max_memory,
+            spool_dir,
+            max_disk_bytes_per_session: 64,
+        })
+    }
+
+    async fn drain(spool: &AudioSpool, session_id: &str) -> Vec<u8> {
+        let mut frames = Vec::new();
+        while let Some(frame) = spool.next_frame(session_id).await {
+            frames.push(frame[0]);
+        }
+        frames
+    }
+
+    #[tokio::test]
+    async fn test_spool_overflows_to_disk_in_order() {
+        let dir = std::env::temp_dir().join(format!("echo-audio-spool-{}", uuid::Uuid::new_v4()));
+        let spool = spool(8, Some(dir.to_string_lossy().into_owned()), 30);
+
+        for byte in 1..=5u8 {
+            spool.push("session_a", "device_a", Bytes::from(vec![byte; 4])).await.unwrap();
+        }
+        assert!(spool.is_spooling("session_a").await);
+        assert_eq!(drain(&spool, "session_a").await, [1, 2, 3, 4, 5]);
+        assert!(!spool.is_spooling("session_a").await);
+
+        // 超出磁盘上限后丢弃整个会话，随后的音频也不再暂存
+        let result = spool.push("session_b", "device_a", Bytes::from(vec![9; 80])).await;
+        assert_eq!(result, Err(SpoolFailure::Overflow));
+        assert_eq!(spool.push("session_b", "device_a", Bytes::from_static(b"x")).await, Err(SpoolFailure::Overflow));
+        assert!(spool.expire().await.is_empty());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_spooled_audio_is_encrypted_on_disk() {
+        let dir = std::env::temp_dir().join(format!("echo-audio-spool-{}", uuid::Uuid::new_v4()));
+        let encryptor = FieldEncryptor::new(&echo_shared::EncryptionConfig {
+            enabled: true,
+            master_key_id: "v1".to_string(),
+            master_key: "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut spool = spool(4, Some(dir.to_string_lossy().into_owned()), 30).with_encryption(Arc::new(encryptor));
+        spool.config.max_disk_bytes_per_session = 4096;
+
+        spool.push("session_a", "device_a", Bytes::from_static(b"pcm0")).await.unwrap();
+        spool.push("session_a", "device_a", Bytes::from_static(b"pcm1")).await.unwrap();
+        let file = std::fs::read(dir.join("session_a.spool")).unwrap();
+        assert!(!file.windows(4).any(|window| window == b"pcm1"));
+
+        let mut frames = Vec::new();
+        while let Some(frame) = spool.next_frame("session_a").await {
+            frames.push(frame);
+        }
+        assert_eq!(frames, [Bytes::from_static(b"pcm0"), Bytes::from_static(b"pcm1")]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_spool_expires_after_grace_period() {
+        let without_disk = spool(4, None, 30);
+        assert_eq!(
+            without_disk.push("session_a", "device_a", Bytes::from(vec![1; 5])).await,
+            Err(SpoolFailure::Overflow)
+        );
+
+        let expiring = spool(1024, None, 0);
+        expiring.push("session_a", "device_a", Bytes::from_static(b"abc")).await.unwrap();
+        assert_eq!(expiring.expire().await, [("session_a".to_string(), "device_a".to_string())]);
+        assert!(!expiring.is_spooling("session_a").await);
+    }
+}
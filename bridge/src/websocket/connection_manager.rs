@@ -1,16 +1,79 @@
 use axum::extract::ws::{Message, WebSocket};
 use futures_util::stream::{SplitSink, SplitStream};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tokio::sync::{mpsc, RwLock};
+use tracing::{debug, error, info, warn};
 use axum::body::Bytes;
+use echo_shared::{DeadLetterFlushResult, DeviceConnectionInfo, EchoError, DeviceId, DeviceTransport, SessionId};
+use echo_shared::protocol::{Frame, FrameType, ProtocolVersion};
+use crate::cluster::ClusterRegistry;
+use crate::presence::PresenceTracker;
+use crate::telemetry;
+use super::dead_letter::DeadLetterQueue;
+
+pub type WsSender = Arc<RwLock<DeviceSink>>;
+
+/// 设备连接的发送端
+///
+/// 非 WebSocket 传输（gRPC 音频流）通过通道接收与 WebSocket 相同的消息，由传输层自行转换格式，
+/// 会话和 EchoKit 适配器的推送逻辑无需区分传输方式。
+pub enum DeviceSink {
+    WebSocket(SplitSink<WebSocket, Message>),
+    Channel(mpsc::Sender<Message>),
+}
+
+impl DeviceSink {
+    fn transport(&self) -> DeviceTransport {
+        match self {
+            DeviceSink::WebSocket(_) => DeviceTransport::Websocket,
+            DeviceSink::Channel(_) => DeviceTransport::Grpc,
+        }
+    }
 
-pub type WsSender = Arc<RwLock<SplitSink<WebSocket, Message>>>;
+    /// 音频字节数指标的 transport 标签
+    fn transport_label(&self) -> &'static str {
+        match self {
+            DeviceSink::WebSocket(_) => "websocket",
+            DeviceSink::Channel(_) => "grpc",
+        }
+    }
+
+    /// 发送一条消息，通道已满时立即失败而不是阻塞其他推送
+    async fn send(&mut self, message: Message) -> anyhow::Result<()> {
+        match self {
+            DeviceSink::WebSocket(sink) => {
+                use futures_util::SinkExt;
+                sink.send(message).await?;
+            }
+            DeviceSink::Channel(sender) => sender
+                .try_send(message)
+                .map_err(|e| anyhow::anyhow!("Device stream is not accepting messages: {}", e))?,
+        }
+        Ok(())
+    }
+}
+
+impl From<SplitSink<WebSocket, Message>> for DeviceSink {
+    fn from(sink: SplitSink<WebSocket, Message>) -> Self {
+        DeviceSink::WebSocket(sink)
+    }
+}
+
+/// 单个连接的流量统计
+#[derive(Debug, Clone)]
+struct ConnectionStats {
+    transport: DeviceTransport,
+    remote_addr: Option<SocketAddr>,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    bytes_received: u64,
+    bytes_sent: u64,
+}
 
 /// 设备连接管理器
 pub struct DeviceConnectionManager {
-    /// device_id -> WebSocket sender
+    /// device_id -> 设备连接的发送端
     connections: Arc<RwLock<HashMap<String, WsSender>>>,
 
     /// session_id -> device_id 映射
@@ -18,6 +81,21 @@ pub struct DeviceConnectionManager {
 
     /// device_id -> 最后心跳时间
     last_heartbeat: Arc<RwLock<HashMap<String, chrono::DateTime<chrono::Utc>>>>,
+
+    /// device_id -> 连接统计
+    connection_stats: Arc<RwLock<HashMap<String, ConnectionStats>>>,
+
+    /// device_id -> 连接协商的协议版本
+    protocol_versions: Arc<RwLock<HashMap<String, ProtocolVersion>>>,
+
+    /// 多副本部署时记录设备归属
+    cluster: Option<Arc<ClusterRegistry>>,
+
+    /// 在 Redis 中记录设备实时在线状态
+    presence: Option<Arc<PresenceTracker>>,
+
+    /// 发送失败的二进制消息，未设置时直接丢弃
+    dead_letters: Option<Arc<DeadLetterQueue>>,
 }
 
 impl DeviceConnectionManager {
@@ -26,21 +104,68 @@ impl DeviceConnectionManager {
             connections: Arc::new(RwLock::new(HashMap::new())),
             session_device_map: Arc::new(RwLock::new(HashMap::new())),
             last_heartbeat: Arc::new(RwLock::new(HashMap::new())),
+            connection_stats: Arc::new(RwLock::new(HashMap::new())),
+            protocol_versions: Arc::new(RwLock::new(HashMap::new())),
+            cluster: None,
+            presence: None,
+            dead_letters: None,
         }
     }
 
+    /// 连接和断开时在 Redis 中更新设备归属
+    pub fn with_cluster(mut self, cluster: Arc<ClusterRegistry>) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// 连接和断开时在 Redis 中更新设备在线状态
+    pub fn with_presence(mut self, presence: Arc<PresenceTracker>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// 二进制消息多次发送失败后保存到死信队列
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterQueue>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+
+    /// 死信队列，未启用时为 None
+    pub fn dead_letters(&self) -> Option<&Arc<DeadLetterQueue>> {
+        self.dead_letters.as_ref()
+    }
+
     /// 注册设备连接
     pub async fn register_device(
         &self,
         device_id: String,
-        sender: SplitSink<WebSocket, Message>,
+        sender: impl Into<DeviceSink>,
+        remote_addr: Option<SocketAddr>,
+        protocol: ProtocolVersion,
     ) -> anyhow::Result<()> {
+        let sender = sender.into();
+        let transport = sender.transport();
         let mut connections = self.connections.write().await;
         connections.insert(device_id.clone(), Arc::new(RwLock::new(sender)));
 
         // 更新心跳时间
+        let now = chrono::Utc::now();
         let mut heartbeats = self.last_heartbeat.write().await;
-        heartbeats.insert(device_id.clone(), chrono::Utc::now());
+        heartbeats.insert(device_id.clone(), now);
+
+        let mut stats = self.connection_stats.write().await;
+        stats.insert(
+            device_id.clone(),
+            ConnectionStats { transport, remote_addr, connected_at: now, bytes_received: 0, bytes_sent: 0 },
+        );
+        self.protocol_versions.write().await.insert(device_id.clone(), protocol);
+
+        if let Some(cluster) = &self.cluster {
+            cluster.device_connected(&device_id);
+        }
+        if let Some(presence) = &self.presence {
+            presence.device_connected(&device_id);
+        }
 
         info!("Device {} registered, total connections: {}", device_id, connections.len());
         Ok(())
@@ -49,11 +174,21 @@ impl DeviceConnectionManager {
     /// 移除设备连接
     pub async fn remove_device(&self, device_id: &str) -> anyhow::Result<()> {
         let mut connections = self.connections.write().await;
-        connections.remove(device_id);
+        if connections.remove(device_id).is_some() {
+            if let Some(cluster) = &self.cluster {
+                cluster.device_disconnected(device_id);
+            }
+            if let Some(presence) = &self.presence {
+                presence.device_disconnected(device_id);
+            }
+        }
 
         let mut heartbeats = self.last_heartbeat.write().await;
         heartbeats.remove(device_id);
 
+        self.connection_stats.write().await.remove(device_id);
+        self.protocol_versions.write().await.remove(device_id);
+
         // 清理该设备的所有会话映射
         let mut map = self.session_device_map.write().await;
         map.retain(|_, dev_id| dev_id != device_id);
@@ -65,11 +200,11 @@ impl DeviceConnectionManager {
     /// 绑定会话到设备
     pub async fn bind_session(
         &self,
-        session_id: String,
-        device_id: String,
+        session_id: SessionId,
+        device_id: DeviceId,
     ) -> anyhow::Result<()> {
         let mut map = self.session_device_map.write().await;
-        map.insert(session_id.clone(), device_id.clone());
+        map.insert(session_id.to_string(), device_id.to_string());
         debug!("Session {} bound to device {}", session_id, device_id);
         Ok(())
     }
@@ -111,29 +246,54 @@ impl DeviceConnectionManager {
             .get(device_id)
             .ok_or_else(|| anyhow::anyhow!("Device {} not connected", device_id))?;
 
-        use futures_util::SinkExt;
-        sender.write().await.send(Message::Binary(Bytes::from(audio_data))).await?;
+        let mut sender = sender.write().await;
+        telemetry::record_audio_bytes("out", sender.transport_label(), audio_data.len());
+        self.record_sent(device_id, audio_data.len()).await;
+        sender.send(Message::Binary(Bytes::from(audio_data))).await?;
         debug!("Pushed audio to device {}", device_id);
         Ok(())
     }
 
     /// 发送文本消息到设备
+    ///
+    /// v2 连接没有文本消息，JSON 通知转为 MessagePack 编码的 `Control` 帧，
+    /// 会话 ID 取自通知中的 `session_id` 字段。
     pub async fn send_text(
         &self,
         device_id: &str,
         text: &str,
     ) -> anyhow::Result<()> {
+        let message = match self.protocol_version(device_id).await {
+            ProtocolVersion::V1 => Message::Text(text.to_string().into()),
+            ProtocolVersion::V2 => Message::Binary(Bytes::from(encode_control_frame(text)?)),
+        };
+
         let connections = self.connections.read().await;
         let sender = connections
             .get(device_id)
             .ok_or_else(|| anyhow::anyhow!("Device {} not connected", device_id))?;
 
-        use futures_util::SinkExt;
-        sender.write().await.send(Message::Text(text.to_string().into())).await?;
+        self.record_sent(device_id, text.len()).await;
+        sender.write().await.send(message).await?;
         debug!("Sent text message to device {}", device_id);
         Ok(())
     }
 
+    /// 设备连接协商的协议版本，未连接时为 v1
+    pub async fn protocol_version(&self, device_id: &str) -> ProtocolVersion {
+        self.protocol_versions.read().await.get(device_id).copied().unwrap_or_default()
+    }
+
+    /// 按设备的协议版本封装发往设备的 MessagePack 事件：v1 原样发送，v2 封装为带会话 ID 的 `Event` 帧
+    ///
+    /// 回复音频暂存和死信队列保存的是封装后的帧，设备重连时切换了协议版本的话补发的帧仍按原版本编码。
+    pub async fn encode_event(&self, device_id: &str, session_id: &str, event: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match self.protocol_version(device_id).await {
+            ProtocolVersion::V1 => Ok(event),
+            ProtocolVersion::V2 => Ok(Frame::new(FrameType::Event, session_id, &event).encode()?),
+        }
+    }
+
     /// 响应 Pong
     pub async fn send_pong(
         &self,
@@ -145,7 +305,6 @@ impl DeviceConnectionManager {
             .get(device_id)
             .ok_or_else(|| anyhow::anyhow!("Device {} not connected", device_id))?;
 
-        use futures_util::SinkExt;
         sender.write().await.send(Message::Pong(Bytes::from(data))).await?;
 
         // 更新心跳时间
@@ -161,27 +320,105 @@ impl DeviceConnectionManager {
         heartbeats.insert(device_id.to_string(), chrono::Utc::now());
     }
 
+    /// 记录从设备收到的数据并更新心跳时间
+    pub async fn record_received(&self, device_id: &str, bytes: usize) {
+        self.update_heartbeat(device_id).await;
+        if let Some(stats) = self.connection_stats.write().await.get_mut(device_id) {
+            stats.bytes_received += bytes as u64;
+        }
+    }
+
+    // 记录发送给设备的字节数
+    async fn record_sent(&self, device_id: &str, bytes: usize) {
+        if let Some(stats) = self.connection_stats.write().await.get_mut(device_id) {
+            stats.bytes_sent += bytes as u64;
+        }
+    }
+
     /// 发送 MessagePack 编码的 ServerEvent
     /// 用于与 Web 客户端（index_zh.html）通信
     pub async fn send_server_event(
         &self,
         device_id: &str,
+        session_id: &str,
         event: super::protocol::ServerEvent,
     ) -> anyhow::Result<()> {
         use anyhow::Context;
 
         let binary_data = event.to_messagepack()
             .context("Failed to serialize ServerEvent to MessagePack")?;
+        let frame = self.encode_event(device_id, session_id, binary_data).await?;
 
-        self.send_binary(device_id, binary_data).await
+        self.send_binary(device_id, frame).await
     }
 
     /// 发送二进制数据到设备
+    ///
+    /// 启用死信队列时失败后重试，全部失败的消息保存到死信队列。
     pub async fn send_binary(
         &self,
         device_id: &str,
         data: Vec<u8>,
     ) -> anyhow::Result<()> {
+        let data = Bytes::from(data);
+        let Some(dead_letters) = &self.dead_letters else {
+            return self.try_send_binary(device_id, data).await;
+        };
+
+        let attempts = dead_letters.send_attempts();
+        let mut attempt = 1;
+        loop {
+            match self.try_send_binary(device_id, data.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < attempts => {
+                    debug!("Send to device {} failed (attempt {}/{}): {}", device_id, attempt, attempts, e);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "Dead-lettering {} bytes for device {} after {} attempts: {}",
+                        data.len(), device_id, attempts, e
+                    );
+                    dead_letters.push(device_id, data, e.to_string());
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// 按顺序重新投递设备的死信，遇到发送失败时停止，剩余消息保留在队列中
+    pub async fn redeliver_dead_letters(&self, device_id: &str) -> anyhow::Result<DeadLetterFlushResult> {
+        let Some(dead_letters) = &self.dead_letters else {
+            return Err(EchoError::ServiceUnavailable("Dead-letter queue is disabled".to_string()).into());
+        };
+        if !self.is_device_online(device_id).await {
+            return Err(EchoError::Conflict(format!("Device {} is not connected", device_id)).into());
+        }
+
+        let mut letters = dead_letters.take(device_id).into_iter();
+        let mut delivered = 0;
+        while let Some(data) = letters.next() {
+            if let Err(e) = self.try_send_binary(device_id, data.clone()).await {
+                let remaining: Vec<Bytes> = std::iter::once(data).chain(letters).collect();
+                let remaining_count = remaining.len();
+                warn!(
+                    "Redelivery to device {} stopped after {} messages, {} kept: {}",
+                    device_id, delivered, remaining_count, e
+                );
+                dead_letters.restore(device_id, remaining, e.to_string());
+                dead_letters.record_redelivered(delivered);
+                return Ok(DeadLetterFlushResult { device_id: device_id.to_string(), delivered, remaining: remaining_count });
+            }
+            delivered += 1;
+        }
+
+        dead_letters.record_redelivered(delivered);
+        info!("Redelivered {} dead-lettered messages to device {}", delivered, device_id);
+        Ok(DeadLetterFlushResult { device_id: device_id.to_string(), delivered, remaining: 0 })
+    }
+
+    /// 发送一次二进制数据，失败时不重试也不进入死信队列
+    pub async fn try_send_binary(&self, device_id: &str, data: Bytes) -> anyhow::Result<()> {
         let data_len = data.len();
 
         let connections = self.connections.read().await;
@@ -189,8 +426,10 @@ impl DeviceConnectionManager {
             .get(device_id)
             .ok_or_else(|| anyhow::anyhow!("Device {} not connected", device_id))?;
 
-        use futures_util::SinkExt;
-        sender.write().await.send(Message::Binary(Bytes::from(data))).await?;
+        let mut sender = sender.write().await;
+        telemetry::record_audio_bytes("out", sender.transport_label(), data_len);
+        self.record_sent(device_id, data_len).await;
+        sender.send(Message::Binary(data)).await?;
         debug!("Sent binary data ({} bytes) to device {}", data_len, device_id);
         Ok(())
     }
@@ -213,6 +452,46 @@ impl DeviceConnectionManager {
         connections.contains_key(device_id)
     }
 
+    /// 获取所有在线设备及其最后心跳时间，按设备ID排序
+    pub async fn get_online_devices(&self) -> Vec<(String, chrono::DateTime<chrono::Utc>)> {
+        let connections = self.connections.read().await;
+        let heartbeats = self.last_heartbeat.read().await;
+
+        let mut devices: Vec<(String, chrono::DateTime<chrono::Utc>)> = connections
+            .keys()
+            .map(|device_id| {
+                let last_heartbeat = heartbeats.get(device_id).copied().unwrap_or_else(chrono::Utc::now);
+                (device_id.clone(), last_heartbeat)
+            })
+            .collect();
+        devices.sort_by(|a, b| a.0.cmp(&b.0));
+        devices
+    }
+
+    /// 获取所有设备连接的详细信息
+    pub async fn get_connections(&self) -> Vec<DeviceConnectionInfo> {
+        let stats = self.connection_stats.read().await;
+        let heartbeats = self.last_heartbeat.read().await;
+        let sessions = self.session_device_map.read().await;
+
+        stats
+            .iter()
+            .map(|(device_id, stats)| DeviceConnectionInfo {
+                device_id: device_id.clone(),
+                transport: stats.transport,
+                remote_addr: stats.remote_addr.map(|addr| addr.to_string()),
+                connected_at: stats.connected_at,
+                last_heartbeat: heartbeats.get(device_id).copied().unwrap_or(stats.connected_at),
+                bytes_received: stats.bytes_received,
+                bytes_sent: stats.bytes_sent,
+                session_id: sessions
+                    .iter()
+                    .find(|(_, session_device)| *session_device == device_id)
+                    .map(|(session_id, _)| session_id.clone()),
+            })
+            .collect()
+    }
+
     /// 获取过期设备（用于心跳检测）
     pub async fn get_stale_devices(&self, timeout_seconds: i64) -> Vec<String> {
         let now = chrono::Utc::now();
@@ -231,3 +510,27 @@ impl DeviceConnectionManager {
         stale
     }
 }
+
+/// 将 JSON 通知转为 v2 `Control` 帧
+fn encode_control_frame(text: &str) -> anyhow::Result<Vec<u8>> {
+    let notice: serde_json::Value = serde_json::from_str(text)?;
+    let session_id = notice.get("session_id").and_then(|v| v.as_str()).unwrap_or_default();
+    let payload = rmp_serde::to_vec_named(&notice)?;
+    Ok(Frame::new(FrameType::Control, session_id, &payload).encode()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notice_becomes_control_frame() {
+        let frame = encode_control_frame(r#"{"event":"session_started","session_id":"session_1"}"#).unwrap();
+        let frame = Frame::decode(&frame).unwrap();
+        assert_eq!(frame.frame_type, FrameType::Control);
+        assert_eq!(frame.session_id, "session_1");
+
+        let notice: serde_json::Value = rmp_serde::from_slice(frame.payload).unwrap();
+        assert_eq!(notice["event"], "session_started");
+    }
+}
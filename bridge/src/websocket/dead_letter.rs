@@ -0,0 +1,286 @@
+//! 设备消息死信队列
+//!
+//! 发往设备的二进制消息重试 `bridge.dead_letter.send_attempts` 次仍失败时，按设备保留在
+//! 死信队列中，而不是直接丢弃。每个设备的队列有消息数、字节数和保留时间上限，超出时丢弃
+//! 最早的消息。内部 API 可查看队列、在设备重连后重新投递或直接丢弃。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use echo_shared::{DeadLetterConfig, DeadLetterEntry, DeadLetterQueueInfo, DeadLetterSummary};
+use tracing::warn;
+
+use crate::telemetry;
+
+// 一条发送失败的消息
+struct DeadLetter {
+    data: Bytes,
+    error: String,
+    dead_lettered_at: DateTime<Utc>,
+    queued_at: Instant,
+}
+
+// 单个设备的死信
+#[derive(Default)]
+struct DeviceLetters {
+    letters: VecDeque<DeadLetter>,
+    bytes: usize,
+}
+
+impl DeviceLetters {
+    fn pop_front(&mut self) -> Option<DeadLetter> {
+        let letter = self.letters.pop_front()?;
+        self.bytes -= letter.data.len();
+        Some(letter)
+    }
+
+    fn summary(&self, device_id: &str) -> DeadLetterSummary {
+        DeadLetterSummary {
+            device_id: device_id.to_string(),
+            messages: self.letters.len(),
+            bytes: self.bytes,
+            oldest_at: self.letters.front().map(|letter| letter.dead_lettered_at),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queues {
+    devices: HashMap<String, DeviceLetters>,
+    total: usize,
+}
+
+/// 按设备划分的死信队列
+pub struct DeadLetterQueue {
+    config: DeadLetterConfig,
+    ttl: Duration,
+    queues: Mutex<Queues>,
+}
+
+impl DeadLetterQueue {
+    pub fn new(config: DeadLetterConfig) -> Self {
+        Self {
+            ttl: Duration::from_secs(config.ttl_seconds),
+            config,
+            queues: Mutex::new(Queues::default()),
+        }
+    }
+
+    /// 每条消息的发送尝试次数
+    pub fn send_attempts(&self) -> u32 {
+        self.config.send_attempts.max(1)
+    }
+
+    /// 保存一条发送失败的消息，超出设备队列上限时丢弃最早的消息
+    pub fn push(&self, device_id: &str, data: Bytes, error: String) {
+        let mut queues = self.lock();
+        let expired = Self::purge_expired(&mut queues, self.ttl);
+
+        let Queues { devices, total } = &mut *queues;
+        let device = devices.entry(device_id.to_string()).or_default();
+        device.bytes += data.len();
+        device.letters.push_back(DeadLetter {
+            data,
+            error,
+            dead_lettered_at: Utc::now(),
+            queued_at: Instant::now(),
+        });
+        *total += 1;
+
+        let mut overflow = 0;
+        while device.letters.len() > self.config.max_messages_per_device
+            || device.bytes > self.config.max_bytes_per_device
+        {
+            if device.pop_front().is_none() {
+                break;
+            }
+            overflow += 1;
+        }
+        *total -= overflow;
+        if device.letters.is_empty() {
+            devices.remove(device_id);
+        }
+
+        let total = *total;
+        drop(queues);
+
+        if expired > 0 {
+            telemetry::record_dead_letters_dropped("expired", expired, total);
+        }
+        if overflow > 0 {
+            warn!("Dead-letter queue for device {} is full, dropped {} oldest messages", device_id, overflow);
+            telemetry::record_dead_letters_dropped("overflow", overflow, total);
+        }
+        telemetry::record_dead_lettered(total);
+    }
+
+    /// 所有有死信的设备，按设备 ID 排序
+    pub fn summaries(&self) -> Vec<DeadLetterSummary> {
+        let mut queues = self.lock();
+        self.purge_and_record(&mut queues);
+
+        let mut summaries: Vec<DeadLetterSummary> = queues
+            .devices
+            .iter()
+            .map(|(device_id, device)| device.summary(device_id))
+            .collect();
+        summaries.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        summaries
+    }
+
+    /// 设备的死信详情，没有死信时返回 None
+    pub fn inspect(&self, device_id: &str) -> Option<DeadLetterQueueInfo> {
+        let mut queues = self.lock();
+        self.purge_and_record(&mut queues);
+
+        let device = queues.devices.get(device_id)?;
+        Some(DeadLetterQueueInfo {
+            summary: device.summary(device_id),
+            entries: device
+                .letters
+                .iter()
+                .map(|letter| DeadLetterEntry {
+                    size: letter.data.len(),
+                    error: letter.error.clone(),
+                    dead_lettered_at: letter.dead_lettered_at,
+                })
+                .collect(),
+        })
+    }
+
+    /// 取出设备的全部死信（按进入队列的顺序）用于重新投递
+    pub fn take(&self, device_id: &str) -> Vec<Bytes> {
+        let mut queues = self.lock();
+        self.purge_and_record(&mut queues);
+
+        let Some(device) = queues.devices.remove(device_id) else {
+            return Vec::new();
+        };
+        queues.total -= device.letters.len();
+        device.letters.into_iter().map(|letter| letter.data).collect()
+    }
+
+    /// 将未投递成功的消息放回队列头部，保持原有顺序（保留时间重新计算）
+    pub fn restore(&self, device_id: &str, remaining: Vec<Bytes>, error: String) {
+        if remaining.is_empty() {
+            return;
+        }
+
+        let mut queues = self.lock();
+        let Queues { devices, total } = &mut *queues;
+        let device = devices.entry(device_id.to_string()).or_default();
+        *total += remaining.len();
+        let now = Utc::now();
+        for data in remaining.into_iter().rev() {
+            device.bytes += data.len();
+            device.letters.push_front(DeadLetter {
+                data,
+                error: error.clone(),
+                dead_lettered_at: now,
+                queued_at: Instant::now(),
+            });
+        }
+    }
+
+    /// 记录重新投递成功的消息数
+    pub fn record_redelivered(&self, count: usize) {
+        let total = self.lock().total;
+        telemetry::record_dead_letters_redelivered(count, total);
+    }
+
+    /// 丢弃设备的全部死信，返回丢弃的消息数
+    pub fn discard(&self, device_id: &str) -> usize {
+        let mut queues = self.lock();
+        let Some(device) = queues.devices.remove(device_id) else {
+            return 0;
+        };
+        let count = device.letters.len();
+        queues.total -= count;
+        telemetry::record_dead_letters_dropped("discarded", count, queues.total);
+        count
+    }
+
+    fn purge_and_record(&self, queues: &mut Queues) {
+        let expired = Self::purge_expired(queues, self.ttl);
+        if expired > 0 {
+            telemetry::record_dead_letters_dropped("expired", expired, queues.total);
+        }
+    }
+
+    // 丢弃所有设备中超过保留时间的消息，返回丢弃数
+    fn purge_expired(queues: &mut Queues, ttl: Duration) -> usize {
+        let mut expired = 0;
+        queues.devices.retain(|_, device| {
+            while device.letters.front().is_some_and(|letter| letter.queued_at.elapsed() >= ttl) {
+                device.pop_front();
+                expired += 1;
+            }
+            !device.letters.is_empty()
+        });
+        queues.total -= expired;
+        expired
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queues> {
+        self.queues.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(max_messages: usize, max_bytes: usize, ttl_seconds: u64) -> DeadLetterQueue {
+        DeadLetterQueue::new(DeadLetterConfig {
+            enabled: true,
+            send_attempts: 3,
+            max_messages_per_device: max_messages,
+            max_bytes_per_device: max_bytes,
+            ttl_seconds,
+        })
+    }
+
+    #[test]
+    fn test_dead_letter_limits_and_restore_order() {
+        let queue = queue(3, 10, 60);
+        for byte in 1..=4u8 {
+            queue.push("device_a", Bytes::from(vec![byte; 2]), "Device device_a not connected".to_string());
+        }
+
+        // 超过消息数上限时丢弃最早的
+        let info = queue.inspect("device_a").unwrap();
+        assert_eq!(info.summary.messages, 3);
+        assert_eq!(info.summary.bytes, 6);
+
+        // 超过字节数上限时同样丢弃最早的
+        queue.push("device_a", Bytes::from(vec![5; 6]), "closed".to_string());
+        assert_eq!(queue.inspect("device_a").unwrap().summary.bytes, 10);
+
+        let letters = queue.take("device_a");
+        assert_eq!(letters.iter().map(|data| data[0]).collect::<Vec<_>>(), [3, 4, 5]);
+        assert!(queue.inspect("device_a").is_none());
+
+        queue.restore("device_a", letters[1..].to_vec(), "closed".to_string());
+        let restored = queue.take("device_a");
+        assert_eq!(restored.iter().map(|data| data[0]).collect::<Vec<_>>(), [4, 5]);
+    }
+
+    #[test]
+    fn test_dead_letters_expire_and_discard() {
+        let expiring = queue(10, 1024, 0);
+        expiring.push("device_a", Bytes::from_static(b"abc"), "closed".to_string());
+        assert!(expiring.summaries().is_empty());
+
+        let queue = queue(10, 1024, 60);
+        queue.push("device_b", Bytes::from_static(b"abc"), "closed".to_string());
+        queue.push("device_a", Bytes::from_static(b"de"), "closed".to_string());
+        let devices: Vec<String> = queue.summaries().into_iter().map(|summary| summary.device_id).collect();
+        assert_eq!(devices, ["device_a", "device_b"]);
+
+        assert_eq!(queue.discard("device_b"), 1);
+        assert_eq!(queue.discard("device_b"), 0);
+    }
+}
@@ -4,7 +4,8 @@ use tokio::time;
 use tracing::{debug, info, warn};
 
 use super::connection_manager::DeviceConnectionManager;
-use super::session_manager::SessionManager;
+use super::session_manager::{SessionManager, SessionStatus};
+use crate::session_service::SessionService;
 
 /// 心跳检测配置
 #[derive(Debug, Clone)]
@@ -31,6 +32,8 @@ impl Default for HeartbeatConfig {
 pub struct HeartbeatMonitor {
     connection_manager: Arc<DeviceConnectionManager>,
     session_manager: Arc<SessionManager>,
+    // 设置后超时的会话同时在数据库中结束
+    session_service: Option<Arc<SessionService>>,
     config: HeartbeatConfig,
 }
 
@@ -43,10 +46,17 @@ impl HeartbeatMonitor {
         Self {
             connection_manager,
             session_manager,
+            session_service: None,
             config,
         }
     }
 
+    /// 设置会话存储，超时的会话通过统一的结束流程持久化
+    pub fn with_session_service(mut self, session_service: Arc<SessionService>) -> Self {
+        self.session_service = Some(session_service);
+        self
+    }
+
     /// 启动心跳监控
     pub async fn start(self: Arc<Self>) {
         info!(
@@ -107,10 +117,22 @@ impl HeartbeatMonitor {
             .await;
 
         for session_id in sessions {
-            info!("Marking session {} as timeout", session_id);
-            self.session_manager
-                .mark_timeout(&session_id)
-                .await?;
+            match &self.session_service {
+                Some(session_service) => {
+                    if self.session_manager
+                        .finalize(&session_id, SessionStatus::Timeout, session_service)
+                        .await?
+                    {
+                        info!("Session {} ended due to heartbeat timeout", session_id);
+                    }
+                }
+                None => {
+                    info!("Marking session {} as timeout", session_id);
+                    self.session_manager
+                        .mark_timeout(&session_id)
+                        .await?;
+                }
+            }
         }
 
         Ok(())
@@ -1,5 +1,7 @@
 // 模块导出
 pub mod connection_manager;
+pub mod audio_spool;
+pub mod dead_letter;
 pub mod session_manager;
 pub mod audio_handler;
 pub mod heartbeat;
@@ -1,9 +1,17 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use chrono::{DateTime, Utc};
+use echo_shared::{BridgeSessionState, DeviceId, MqttMessageBuilder, SessionId, SessionStage, WebSocketMessage};
+use echo_shared::mqtt::MqttMessage;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
+use crate::cluster::ClusterRegistry;
+use crate::presence::PresenceTracker;
+use crate::session_service::{SessionFinalization, SessionService};
+
+/// 16kHz 单声道 16-bit PCM 每秒字节数
+const PCM_BYTES_PER_SECOND: f64 = 32000.0;
 
 /// 会话状态
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -25,6 +33,12 @@ pub struct SessionInfo {
     pub status: SessionStatus,
     pub audio_frames_sent: u64,
     pub audio_frames_received: u64,
+    /// 当前所处阶段（唤醒、聆听、处理、回复、完成）
+    pub stage: SessionStage,
+    /// 本轮对话已接收但尚未提交的音频字节数
+    pub buffered_audio_bytes: u64,
+    /// 回复音频未能在宽限期内送达设备（设备断线后未及时重连）
+    pub delivery_failed: bool,
     /// 标记本轮对话是否已发送 StartChat 命令
     /// 每轮对话（从第一个音频包到Submit）需要发送一次 StartChat
     #[serde(skip)]
@@ -46,30 +60,93 @@ pub struct SessionInfo {
 /// 会话管理器
 pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
+    /// 会话实时事件输出（阶段变化、ASR 结果），由 MQTT 发布任务消费
+    event_sink: Option<mpsc::UnboundedSender<MqttMessage>>,
+    /// 多副本部署时记录会话归属，内部 API 请求据此转发到会话所在的副本
+    cluster: Option<Arc<ClusterRegistry>>,
+    /// 会话开始和结束时更新设备在线状态中的当前会话
+    presence: Option<Arc<PresenceTracker>>,
 }
 
 impl SessionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            event_sink: None,
+            cluster: None,
+            presence: None,
+        }
+    }
+
+    /// 设置会话实时事件输出
+    pub fn with_event_sink(mut self, event_sink: mpsc::UnboundedSender<MqttMessage>) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// 创建和结束会话时在 Redis 中更新会话归属
+    pub fn with_cluster(mut self, cluster: Arc<ClusterRegistry>) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// 创建和结束会话时在 Redis 中更新设备的当前会话
+    pub fn with_presence(mut self, presence: Arc<PresenceTracker>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// 发送会话实时事件
+    fn emit(&self, device_id: &str, event: WebSocketMessage) {
+        if let Some(sink) = &self.event_sink {
+            if sink.send(MqttMessageBuilder::session_event(device_id.to_string(), event)).is_err() {
+                debug!("Session event sink closed, dropping event for device {}", device_id);
+            }
         }
     }
 
+    /// 切换会话阶段，阶段发生变化时发送进度事件
+    fn set_stage(&self, session: &mut SessionInfo, stage: SessionStage) {
+        if session.stage == stage {
+            return;
+        }
+
+        let (progress, message) = match stage {
+            SessionStage::Wakeup => (0.0, "等待唤醒"),
+            SessionStage::Listening => (25.0, "正在聆听..."),
+            SessionStage::Processing => (50.0, "正在处理语音命令..."),
+            SessionStage::Responding => (75.0, "正在回复..."),
+            SessionStage::Completed => (100.0, "会话已结束"),
+        };
+
+        session.stage = stage.clone();
+        self.emit(&session.device_id, WebSocketMessage::SessionProgress {
+            session_id: session.session_id.clone(),
+            device_id: session.device_id.clone(),
+            stage,
+            progress,
+            message: message.to_string(),
+        });
+    }
+
     /// 创建会话
     pub async fn create_session(
         &self,
-        session_id: String,
-        device_id: String,
+        session_id: SessionId,
+        device_id: DeviceId,
     ) -> anyhow::Result<()> {
         let session_info = SessionInfo {
-            session_id: session_id.clone(),
-            device_id: device_id.clone(),
+            session_id: session_id.to_string(),
+            device_id: device_id.to_string(),
             echokit_session_id: None,
             created_at: Utc::now(),
             last_activity: Utc::now(),
             status: SessionStatus::Active,
             audio_frames_sent: 0,
             audio_frames_received: 0,
+            stage: SessionStage::Wakeup,
+            buffered_audio_bytes: 0,
+            delivery_failed: false,
             start_chat_sent_for_current_round: false, // 初始化为false
             conversation_transcripts: Vec::new(), // 🔧 初始化为空数组
             conversation_responses: Vec::new(), // 🔧 初始化为空数组
@@ -77,7 +154,13 @@ impl SessionManager {
         };
 
         let mut sessions = self.sessions.write().await;
-        sessions.insert(session_id.clone(), session_info);
+        sessions.insert(session_id.to_string(), session_info);
+        if let Some(cluster) = &self.cluster {
+            cluster.session_started(&session_id);
+        }
+        if let Some(presence) = &self.presence {
+            presence.session_started(&device_id, &session_id);
+        }
 
         info!("Session {} created for device {}", session_id, device_id);
         Ok(())
@@ -110,17 +193,98 @@ impl SessionManager {
         }
     }
 
+    /// 记录本轮对话接收到的音频（进入聆听阶段）
+    pub async fn add_buffered_audio(&self, session_id: &str, bytes: usize) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.buffered_audio_bytes += bytes as u64;
+            self.set_stage(session, SessionStage::Listening);
+            session.last_activity = Utc::now();
+        }
+    }
+
+    /// 标记本轮音频已提交（进入处理阶段，清空缓冲计数）
+    pub async fn mark_submitted(&self, session_id: &str) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.buffered_audio_bytes = 0;
+            self.set_stage(session, SessionStage::Processing);
+            session.last_activity = Utc::now();
+        }
+    }
+
     /// 结束会话
     pub async fn end_session(&self, session_id: &str) -> anyhow::Result<()> {
         let mut sessions = self.sessions.write().await;
         if let Some(session) = sessions.get_mut(session_id) {
+            if session.status == SessionStatus::Active {
+                if let Some(cluster) = &self.cluster {
+                    cluster.session_ended(session_id);
+                }
+                if let Some(presence) = &self.presence {
+                    presence.session_ended(&session.device_id, session_id);
+                }
+            }
             session.status = SessionStatus::Completed;
+            self.set_stage(session, SessionStage::Completed);
             info!("Session {} ended (sent: {}, received: {})",
                   session_id, session.audio_frames_sent, session.audio_frames_received);
         }
         Ok(())
     }
 
+    /// 将活跃会话切换为结束状态，并取出需要持久化的对话内容
+    ///
+    /// 状态切换和内容快照在同一把锁内完成；会话不存在或已结束时返回 None，
+    /// 保证断线、超时、设备主动结束和强制结束等路径中只有一个会结束会话。
+    pub async fn finish_session(&self, session_id: &str, status: SessionStatus) -> Option<FinishedSession> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).filter(|session| session.status == SessionStatus::Active)?;
+
+        if let Some(cluster) = &self.cluster {
+            cluster.session_ended(session_id);
+        }
+        if let Some(presence) = &self.presence {
+            presence.session_ended(&session.device_id, session_id);
+        }
+        session.status = status;
+        self.set_stage(session, SessionStage::Completed);
+        info!("Session {} finished as {:?} (sent: {}, received: {})",
+              session_id, session.status, session.audio_frames_sent, session.audio_frames_received);
+
+        let join = |parts: &[String]| (!parts.is_empty()).then(|| parts.join("\n"));
+        Some(FinishedSession {
+            transcript: join(&session.conversation_transcripts),
+            response: join(&session.conversation_responses),
+            turns: session.conversation_transcripts.len().max(session.conversation_responses.len()),
+        })
+    }
+
+    /// 结束会话并持久化结束状态、完整对话内容和轮数
+    ///
+    /// 内存状态先切换（设备连接循环随即停止转发音频），数据库在一个事务中写入；
+    /// 会话已被其他路径结束时返回 false。数据库写入失败时返回错误，遗留的会话由超时清理任务处理。
+    pub async fn finalize(&self, session_id: &str, status: SessionStatus, store: &SessionService) -> anyhow::Result<bool> {
+        let db_status = match status {
+            SessionStatus::Active => echo_shared::database::SessionStatus::Active,
+            SessionStatus::Completed => echo_shared::database::SessionStatus::Completed,
+            SessionStatus::Failed => echo_shared::database::SessionStatus::Failed,
+            SessionStatus::Timeout => echo_shared::database::SessionStatus::Timeout,
+        };
+        let Some(finished) = self.finish_session(session_id, status).await else {
+            return Ok(false);
+        };
+
+        let finalization = SessionFinalization {
+            transcript: finished.transcript,
+            response: finished.response,
+            turns: Some(finished.turns as i32),
+            ..SessionFinalization::new(db_status)
+        };
+        store.finalize_session(session_id, &finalization).await?;
+        Ok(true)
+    }
+
     /// 标记会话失败
     pub async fn mark_failed(&self, session_id: &str) -> anyhow::Result<()> {
         let mut sessions = self.sessions.write().await;
@@ -130,12 +294,70 @@ impl SessionManager {
         Ok(())
     }
 
+    /// 标记会话的回复音频投递失败
+    pub async fn mark_delivery_failed(&self, session_id: &str) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.delivery_failed = true;
+        }
+    }
+
+    /// 会话是否仍处于活跃状态（可能已被内部 API 强制结束）
+    pub async fn is_active(&self, session_id: &str) -> bool {
+        let sessions = self.sessions.read().await;
+        sessions
+            .get(session_id)
+            .map(|s| s.status == SessionStatus::Active)
+            .unwrap_or(false)
+    }
+
     /// 获取会话信息
     pub async fn get_session(&self, session_id: &str) -> Option<SessionInfo> {
         let sessions = self.sessions.read().await;
         sessions.get(session_id).cloned()
     }
 
+    /// 获取会话的实时状态快照（供内部 API 使用）
+    pub async fn get_session_state(&self, session_id: &str) -> Option<BridgeSessionState> {
+        let sessions = self.sessions.read().await;
+        sessions.get(session_id).map(Self::session_state)
+    }
+
+    /// 获取所有活跃会话的实时状态快照，按创建时间排序（供内部 API 使用）
+    pub async fn get_active_session_states(&self) -> Vec<BridgeSessionState> {
+        let sessions = self.sessions.read().await;
+        let mut states: Vec<BridgeSessionState> = sessions
+            .values()
+            .filter(|s| s.status == SessionStatus::Active)
+            .map(Self::session_state)
+            .collect();
+        states.sort_by_key(|s| s.created_at);
+        states
+    }
+
+    /// 将会话信息转换为对外的状态快照
+    fn session_state(session: &SessionInfo) -> BridgeSessionState {
+        BridgeSessionState {
+            session_id: session.session_id.clone(),
+            device_id: session.device_id.clone(),
+            echokit_session_id: session.echokit_session_id.clone(),
+            status: match session.status {
+                SessionStatus::Active => echo_shared::types::SessionStatus::Active,
+                SessionStatus::Completed => echo_shared::types::SessionStatus::Completed,
+                SessionStatus::Failed => echo_shared::types::SessionStatus::Failed,
+                SessionStatus::Timeout => echo_shared::types::SessionStatus::Timeout,
+            },
+            active: session.status == SessionStatus::Active,
+            stage: session.stage.clone(),
+            buffered_audio_seconds: session.buffered_audio_bytes as f64 / PCM_BYTES_PER_SECOND,
+            audio_frames_sent: session.audio_frames_sent,
+            audio_frames_received: session.audio_frames_received,
+            delivery_failed: session.delivery_failed,
+            created_at: session.created_at,
+            last_activity: session.last_activity,
+        }
+    }
+
     /// 获取设备的所有活跃会话
     pub async fn get_device_sessions(&self, device_id: &str) -> Vec<SessionInfo> {
         let sessions = self.sessions.read().await;
@@ -262,26 +484,22 @@ impl SessionManager {
 
             session.conversation_transcripts.push(transcript.clone());
             session.last_activity = Utc::now();
+            self.emit(&session.device_id, WebSocketMessage::EchoKitTranscription {
+                session_id: session.session_id.clone(),
+                device_id: session.device_id.clone(),
+                text: transcript.clone(),
+                confidence: 1.0,
+                is_final: true,
+                timestamp: Utc::now(),
+            });
             info!("📝 Appended transcript to session {} (total: {} turns)",
                   session_id, session.conversation_transcripts.len());
-            debug!("Transcript content: {}", transcript);
+            debug!("Transcript content: {}", echo_shared::redact_for_log(&transcript));
         } else {
             warn!("⚠️ Attempted to append transcript to non-existent session: {}", session_id);
         }
     }
 
-    /// 🔧 方案B：获取会话的所有转录文本（用于持久化到数据库）
-    /// 返回用换行符连接的完整对话文本
-    pub async fn get_full_transcript(&self, session_id: &str) -> Option<String> {
-        let sessions = self.sessions.read().await;
-        sessions.get(session_id).map(|session| {
-            if session.conversation_transcripts.is_empty() {
-                return None;
-            }
-            Some(session.conversation_transcripts.join("\n"))
-        }).flatten()
-    }
-
     /// 🔧 添加 AI 回复文本到会话（在内存中累积）
     /// 每次收到 StartAudio 事件时调用，将 AI 回复文本追加到当前轮次的临时缓存
     pub async fn append_response(&self, session_id: &str, response: String) {
@@ -289,27 +507,16 @@ impl SessionManager {
         if let Some(session) = sessions.get_mut(session_id) {
             // 添加到当前轮次的临时缓存，而不是直接添加到 conversation_responses
             session.current_round_responses.push(response.clone());
+            self.set_stage(session, SessionStage::Responding);
             session.last_activity = Utc::now();
             info!("🤖 Appended AI response fragment to session {} (current round: {} fragments)",
                   session_id, session.current_round_responses.len());
-            debug!("Response fragment content: {}", response);
+            debug!("Response fragment content: {}", echo_shared::redact_for_log(&response));
         } else {
             warn!("⚠️ Attempted to append response to non-existent session: {}", session_id);
         }
     }
 
-    /// 🔧 获取会话的所有 AI 回复文本（用于持久化到数据库）
-    /// 返回用换行符连接的完整 AI 回复文本
-    pub async fn get_full_response(&self, session_id: &str) -> Option<String> {
-        let sessions = self.sessions.read().await;
-        sessions.get(session_id).map(|session| {
-            if session.conversation_responses.is_empty() {
-                return None;
-            }
-            Some(session.conversation_responses.join("\n"))
-        }).flatten()
-    }
-
     /// 🔧 完成当前轮次的 AI 回复（在收到 EndResponse 时调用）
     /// 将当前轮次临时缓存的多条 AI 回复合并为一条，添加到 conversation_responses
     pub async fn finalize_current_round_response(&self, session_id: &str) {
@@ -321,13 +528,14 @@ impl SessionManager {
 
                 info!("✅ Finalizing current round response for session {} ({} fragments → 1 merged response)",
                       session_id, session.current_round_responses.len());
-                debug!("Merged response content: {}", merged_response);
+                debug!("Merged response content: {}", echo_shared::redact_for_log(&merged_response));
 
                 // 添加到 conversation_responses
                 session.conversation_responses.push(merged_response);
 
                 // 清空当前轮次的临时缓存，准备下一轮
                 session.current_round_responses.clear();
+                self.set_stage(session, SessionStage::Wakeup);
 
                 session.last_activity = Utc::now();
 
@@ -342,6 +550,17 @@ impl SessionManager {
     }
 }
 
+/// 结束会话时从内存中取出的对话内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinishedSession {
+    /// 多轮对话的转录文本，按换行连接
+    pub transcript: Option<String>,
+    /// 多轮对话的 AI 回复，按换行连接
+    pub response: Option<String>,
+    /// 对话轮数
+    pub turns: usize,
+}
+
 /// 会话统计
 #[derive(Debug, Serialize)]
 pub struct SessionStats {
@@ -352,3 +571,81 @@ pub struct SessionStats {
     pub timeout: usize,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use echo_shared::MqttPayload;
+
+    #[tokio::test]
+    async fn test_stage_changes_emit_session_events() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let manager = SessionManager::new().with_event_sink(tx);
+        manager.create_session("sess001".into(), "dev001".into()).await.unwrap();
+
+        // 连续的音频帧只在首次进入聆听阶段时发送一次事件
+        manager.add_buffered_audio("sess001", 640).await;
+        manager.add_buffered_audio("sess001", 640).await;
+        manager.append_transcript("sess001", "你好".to_string()).await;
+
+        let first = rx.try_recv().unwrap();
+        assert_eq!(first.topic, "device/dev001/session");
+        assert!(matches!(
+            first.payload,
+            MqttPayload::SessionEvent { event: WebSocketMessage::SessionProgress { stage: SessionStage::Listening, .. }, .. }
+        ));
+
+        let second = rx.try_recv().unwrap();
+        assert!(matches!(
+            second.payload,
+            MqttPayload::SessionEvent { event: WebSocketMessage::EchoKitTranscription { .. }, .. }
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ended_session_is_not_active() {
+        let manager = SessionManager::new();
+        manager.create_session("sess001".into(), "dev001".into()).await.unwrap();
+        assert!(manager.is_active("sess001").await);
+
+        manager.end_session("sess001").await.unwrap();
+        assert!(!manager.is_active("sess001").await);
+        assert!(!manager.is_active("unknown").await);
+    }
+
+    #[tokio::test]
+    async fn test_finish_session_only_once() {
+        let manager = SessionManager::new();
+        manager.create_session("sess001".into(), "dev001".into()).await.unwrap();
+        manager.append_transcript("sess001", "你好".to_string()).await;
+        manager.append_response("sess001", "你好，".to_string()).await;
+        manager.append_response("sess001", "有什么可以帮你？".to_string()).await;
+        manager.finalize_current_round_response("sess001").await;
+        manager.append_transcript("sess001", "今天天气".to_string()).await;
+
+        let finished = manager.finish_session("sess001", SessionStatus::Timeout).await.unwrap();
+        assert_eq!(finished.transcript.as_deref(), Some("你好\n今天天气"));
+        assert_eq!(finished.response.as_deref(), Some("你好，有什么可以帮你？"));
+        assert_eq!(finished.turns, 2);
+        assert_eq!(manager.get_session("sess001").await.unwrap().status, SessionStatus::Timeout);
+
+        // 其他路径再次结束同一会话时不重复持久化
+        assert!(manager.finish_session("sess001", SessionStatus::Completed).await.is_none());
+        assert!(manager.finish_session("unknown", SessionStatus::Completed).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_active_session_states_exclude_ended_sessions() {
+        let manager = SessionManager::new();
+        manager.create_session("sess001".into(), "dev001".into()).await.unwrap();
+        manager.create_session("sess002".into(), "dev002".into()).await.unwrap();
+        manager.end_session("sess001").await.unwrap();
+
+        let states = manager.get_active_session_states().await;
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0].session_id, "sess002");
+        assert_eq!(states[0].device_id, "dev002");
+        assert!(states[0].active);
+    }
+}
@@ -0,0 +1,29 @@
+-- ============================================================================
+-- Echo System 会话统计汇总表
+-- ============================================================================
+-- 描述: 按天、按设备预聚合的会话统计，用于会话分析接口
+-- 用途: 可选功能，API Gateway 设置 SESSION_ROLLUPS_ENABLED=true 时由后台任务定期刷新
+-- 说明: 脚本可重复执行，API Gateway 启用汇总功能时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS session_daily_rollups (
+    day DATE NOT NULL,
+    device_id VARCHAR(255) NOT NULL,
+    total_sessions INTEGER NOT NULL DEFAULT 0,
+    completed_sessions INTEGER NOT NULL DEFAULT 0,
+    failed_sessions INTEGER NOT NULL DEFAULT 0,
+    timeout_sessions INTEGER NOT NULL DEFAULT 0,
+    total_duration_seconds BIGINT NOT NULL DEFAULT 0,
+    duration_samples INTEGER NOT NULL DEFAULT 0,
+    asr_word_count BIGINT NOT NULL DEFAULT 0,
+    refreshed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (day, device_id)
+);
+
+-- 汇总表索引
+CREATE INDEX IF NOT EXISTS idx_session_daily_rollups_device_id ON session_daily_rollups(device_id);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.02', '会话统计汇总表 session_daily_rollups')
+ON CONFLICT (version) DO NOTHING;
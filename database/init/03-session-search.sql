@@ -0,0 +1,22 @@
+-- ============================================================================
+-- Echo System 会话全文搜索
+-- ============================================================================
+-- 描述: 为会话转录文本和 AI 回复建立全文搜索索引
+-- 用途: /api/v1/sessions/search 接口
+-- 说明: 使用 simple 分词配置（不做词干提取，兼容中英文混合文本）；
+--       脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', COALESCE(transcription, '')), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(response, '')), 'B')
+    ) STORED;
+
+-- 全文搜索索引
+CREATE INDEX IF NOT EXISTS idx_sessions_search_vector ON sessions USING GIN (search_vector);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.03', '会话全文搜索 search_vector')
+ON CONFLICT (version) DO NOTHING;
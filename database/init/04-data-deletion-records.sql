@@ -0,0 +1,28 @@
+-- ============================================================================
+-- Echo System 用户数据删除审计表
+-- ============================================================================
+-- 描述: 记录每次永久删除用户会话数据的操作（删除对象、操作人、删除范围）
+-- 用途: 满足数据删除请求（GDPR 等）的审计要求，记录本身不包含会话内容
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS data_deletion_records (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    subject_user_id VARCHAR(255) NOT NULL,
+    requested_by VARCHAR(255) NOT NULL,
+    session_count INTEGER NOT NULL DEFAULT 0,
+    session_ids JSONB NOT NULL DEFAULT '[]'::jsonb,
+    audio_file_count INTEGER NOT NULL DEFAULT 0,
+    audio_files_deleted INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    completed_at TIMESTAMP WITH TIME ZONE
+);
+
+-- 删除审计表索引
+CREATE INDEX IF NOT EXISTS idx_data_deletion_records_subject ON data_deletion_records(subject_user_id);
+CREATE INDEX IF NOT EXISTS idx_data_deletion_records_created_at ON data_deletion_records(created_at DESC);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.04', '用户数据删除审计表 data_deletion_records')
+ON CONFLICT (version) DO NOTHING;
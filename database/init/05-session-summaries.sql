@@ -0,0 +1,21 @@
+-- ============================================================================
+-- Echo System 会话摘要
+-- ============================================================================
+-- 描述: 为会话表增加摘要和主题标签字段
+-- 用途: 可选功能，API Gateway 设置 SESSION_SUMMARY_ENABLED=true 时
+--       由后台任务调用 LLM 为已完成的会话生成摘要
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS summary TEXT;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS summary_tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS summarized_at TIMESTAMP WITH TIME ZONE;
+
+-- 待生成摘要的会话索引
+CREATE INDEX IF NOT EXISTS idx_sessions_pending_summary ON sessions(end_time)
+    WHERE status = 'completed' AND summarized_at IS NULL;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.05', '会话摘要字段 summary, summary_tags, summarized_at')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,14 @@
+-- ============================================================================
+-- Echo System 游标分页索引
+-- ============================================================================
+-- 描述: 设备和会话列表的游标（keyset）分页按 (时间, id) 倒序扫描
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE INDEX IF NOT EXISTS idx_sessions_start_time_id ON sessions(start_time DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_devices_created_at_id ON devices(created_at DESC, id DESC);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.06', '设备和会话列表游标分页索引')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,31 @@
+-- ============================================================================
+-- Echo System 管理操作审计日志
+-- ============================================================================
+-- 描述: 记录设备删除、所有权转移、用户角色变更、会话强制终止和配置重载等管理操作
+-- 用途: 追溯操作人、来源 IP 以及操作前后的对象快照
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    action VARCHAR(50) NOT NULL,
+    actor_id VARCHAR(255) NOT NULL,
+    actor_username VARCHAR(255) NOT NULL,
+    actor_ip VARCHAR(64),
+    target_type VARCHAR(50) NOT NULL,
+    target_id VARCHAR(255) NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- 审计日志索引
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action);
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log(actor_id);
+CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log(target_type, target_id);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.07', '管理操作审计日志 audit_log')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,81 @@
+-- ============================================================================
+-- Echo System 会话表按月分区
+-- ============================================================================
+-- 描述: 将 sessions 转换为按 start_time 按月分区的表，保留期之外的数据按分区整体删除
+-- 说明: 原表不复制数据，整体作为 sessions_legacy 分区挂载（范围到下个月初为止），之后的会话
+--       写入 sessions_pYYYY_MM 月分区；没有对应月分区的会话写入 sessions_default。
+--       月分区由 API Gateway 的分区维护任务提前创建并按 database.partitions 删除过期分区。
+--       分区键必须包含在主键中，主键改为 (id, start_time)。
+--       脚本可重复执行，API Gateway 启动时也会自动执行（转换时会锁住 sessions 表并重建主键）
+-- ============================================================================
+
+DO $$
+DECLARE
+    boundary TIMESTAMPTZ := date_trunc('month', NOW() AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' + INTERVAL '1 month';
+    month_start TIMESTAMPTZ;
+    idx RECORD;
+    view RECORD;
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_partitioned_table WHERE partrelid = 'sessions'::regclass) THEN
+        RETURN;
+    END IF;
+
+    LOCK TABLE sessions IN ACCESS EXCLUSIVE MODE;
+
+    -- 分区键不能为空
+    UPDATE sessions SET start_time = LEAST(COALESCE(end_time, NOW()), NOW()) WHERE start_time IS NULL;
+    ALTER TABLE sessions ALTER COLUMN start_time SET NOT NULL;
+    ALTER TABLE sessions DROP CONSTRAINT sessions_pkey;
+    ALTER TABLE sessions RENAME TO sessions_legacy;
+    ALTER TABLE sessions_legacy ADD CONSTRAINT sessions_legacy_pkey PRIMARY KEY (id, start_time);
+
+    CREATE TABLE sessions (
+        LIKE sessions_legacy INCLUDING DEFAULTS INCLUDING GENERATED INCLUDING CONSTRAINTS
+    ) PARTITION BY RANGE (start_time);
+    ALTER TABLE sessions ADD CONSTRAINT sessions_pkey PRIMARY KEY (id, start_time);
+    ALTER TABLE sessions ADD CONSTRAINT sessions_device_id_fkey
+        FOREIGN KEY (device_id) REFERENCES devices(id) ON DELETE CASCADE;
+
+    -- 先加检查约束，挂载分区时不再扫描全表
+    EXECUTE format('ALTER TABLE sessions_legacy ADD CONSTRAINT sessions_legacy_range CHECK (start_time < %L)', boundary);
+    EXECUTE format('ALTER TABLE sessions ATTACH PARTITION sessions_legacy FOR VALUES FROM (MINVALUE) TO (%L)', boundary);
+    ALTER TABLE sessions_legacy DROP CONSTRAINT sessions_legacy_range;
+
+    -- 在分区表上按原名重建索引，原表中相同定义的索引直接挂载为分区索引
+    FOR idx IN
+        SELECT indexname, indexdef FROM pg_indexes
+        WHERE schemaname = current_schema() AND tablename = 'sessions_legacy' AND indexname LIKE 'idx_sessions_%'
+    LOOP
+        EXECUTE format('ALTER INDEX %I RENAME TO %I', idx.indexname, replace(idx.indexname, 'idx_sessions_', 'sessions_legacy_'));
+        EXECUTE replace(idx.indexdef, ' ON ' || current_schema() || '.sessions_legacy ', ' ON sessions ');
+    END LOOP;
+
+    -- 视图随原表改名，改回引用分区表
+    FOR view IN
+        SELECT DISTINCT c.oid::regclass AS name, pg_get_viewdef(c.oid) AS definition
+        FROM pg_depend d
+        JOIN pg_rewrite r ON r.oid = d.objid
+        JOIN pg_class c ON c.oid = r.ev_class
+        WHERE d.refobjid = 'sessions_legacy'::regclass AND c.relkind = 'v'
+    LOOP
+        EXECUTE format('CREATE OR REPLACE VIEW %s AS %s', view.name, replace(view.definition, 'sessions_legacy', 'sessions'));
+    END LOOP;
+
+    CREATE TABLE sessions_default PARTITION OF sessions DEFAULT;
+
+    -- 提前创建之后三个月的分区，之后由分区维护任务继续创建
+    FOR i IN 0..2 LOOP
+        month_start := boundary + make_interval(months => i);
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF sessions FOR VALUES FROM (%L) TO (%L)',
+            'sessions_p' || to_char(month_start AT TIME ZONE 'UTC', 'YYYY_MM'),
+            month_start,
+            month_start + INTERVAL '1 month'
+        );
+    END LOOP;
+END $$;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.08', '会话表按 start_time 按月分区')
+ON CONFLICT (version) DO NOTHING;
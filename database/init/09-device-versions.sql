@@ -0,0 +1,15 @@
+-- ============================================================================
+-- Echo System 设备版本号
+-- ============================================================================
+-- 描述: devices 增加 version 列，用于设备更新的乐观并发控制
+-- 用途: PUT /api/v1/devices/{id} 只在 If-Match（或请求体 version）与当前版本一致时更新，
+--       每次更新版本号加一，版本不一致时返回 409
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE devices ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.09', '设备乐观并发控制版本号 devices.version')
+ON CONFLICT (version) DO NOTHING;
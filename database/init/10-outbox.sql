@@ -0,0 +1,30 @@
+-- ============================================================================
+-- Echo System 事务性发件箱（outbox）
+-- ============================================================================
+-- 描述: 需要发布到 MQTT 的事件（设备注册、会话结束等）与对应的状态变更在同一个事务中写入 outbox，
+--       由 Bridge 的转发任务按 id 顺序发布后标记 delivered_at；MQTT 不可用时事件留在表中，恢复后补发
+-- 说明: 发布失败时 attempts 加一并记录 last_error，达到 bridge.outbox.max_attempts 后不再重试；
+--       已投递的事件超过 bridge.outbox.delivered_retention_hours 后由转发任务删除。
+--       脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS outbox (
+    id BIGSERIAL PRIMARY KEY,
+    topic VARCHAR(255) NOT NULL,
+    payload JSONB NOT NULL,
+    qos SMALLINT NOT NULL DEFAULT 1 CHECK (qos BETWEEN 0 AND 2),
+    retain BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+-- 转发任务只扫描未投递的事件
+CREATE INDEX IF NOT EXISTS idx_outbox_pending ON outbox(id) WHERE delivered_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_outbox_delivered_at ON outbox(delivered_at) WHERE delivered_at IS NOT NULL;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.10', '事务性发件箱 outbox，事件由 Bridge 转发到 MQTT')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,29 @@
+-- ============================================================================
+-- Echo System 数据库备份记录
+-- ============================================================================
+-- 描述: API Gateway 每日备份任务（database.backup）每次执行 pg_dump 的记录
+-- 用途: GET /admin/backups 列出备份；备份任务据此判断当天是否已备份，并删除超出保留份数的备份
+-- 说明: 恢复步骤见 docs/BACKUP_AND_RESTORE.md。脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS database_backups (
+    id BIGSERIAL PRIMARY KEY,
+    file_name VARCHAR(255) NOT NULL UNIQUE,
+    status VARCHAR(20) NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'completed', 'failed')),
+    size_bytes BIGINT,
+    sha256 VARCHAR(64),
+    -- 本地备份文件路径，文件已随保留策略删除时为空
+    local_path TEXT,
+    -- 对象存储中的 URL，未上传时为空
+    object_url TEXT,
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_database_backups_started_at ON database_backups(started_at DESC);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.11', '数据库备份记录 database_backups')
+ON CONFLICT (version) DO NOTHING;
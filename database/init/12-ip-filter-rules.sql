@@ -0,0 +1,23 @@
+-- ============================================================================
+-- Echo System IP 过滤规则
+-- ============================================================================
+-- 描述: 管理员通过 /admin/ip-filter/rules 添加的 IP 允许/拒绝规则
+-- 用途: API Gateway 和 Bridge 定期读取，与配置文件 [ip_filter] 中的规则合并后在鉴权之前检查来源地址
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS ip_filter_rules (
+    id BIGSERIAL PRIMARY KEY,
+    -- 单个地址或 CIDR，如 203.0.113.0/24
+    cidr VARCHAR(64) NOT NULL UNIQUE,
+    action VARCHAR(10) NOT NULL CHECK (action IN ('allow', 'deny')),
+    note TEXT,
+    -- 添加规则的管理员
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.11', 'IP 过滤规则 ip_filter_rules')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,54 @@
+-- ============================================================================
+-- Echo System Webhook 订阅
+-- ============================================================================
+-- 描述: 用户通过 /api/v1/webhooks 注册的回调地址和事件过滤（session.completed、device.offline、
+--       transcription.final），以及每次投递的记录
+-- 用途: API Gateway 的领导者副本把匹配的事件写入 webhook_deliveries，再按 next_attempt_at 投递，
+--       请求体用订阅的 secret 做 HMAC-SHA256 签名；失败后按指数退避重试，达到 webhooks.max_attempts 后标记为 failed
+-- 说明: 超过 webhooks.delivery_retention_days 的投递记录由投递任务删除。
+--       脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    -- 注册 Webhook 的用户，只接收该用户拥有的设备的事件
+    user_id VARCHAR(255) NOT NULL,
+    url TEXT NOT NULL,
+    -- 订阅的事件名
+    events TEXT[] NOT NULL,
+    -- HMAC 签名密钥，只在创建时返回给用户
+    secret VARCHAR(128) NOT NULL,
+    description TEXT,
+    -- 接收所有设备的事件（仅管理员可设置）
+    all_devices BOOLEAN NOT NULL DEFAULT false,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhooks_user_id ON webhooks(user_id);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    event VARCHAR(64) NOT NULL,
+    payload JSONB NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- 最后一次请求的 HTTP 状态码，请求未完成（连接失败、超时）时为空
+    response_status INTEGER,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ
+);
+
+-- 投递任务只扫描待投递的记录
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_pending ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook_id ON webhook_deliveries(webhook_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_created_at ON webhook_deliveries(created_at);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.12', 'Webhook 订阅 webhooks 和投递记录 webhook_deliveries')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,36 @@
+-- ============================================================================
+-- Echo System 通知接收地址
+-- ============================================================================
+-- 描述: 用户通过 /api/v1/notifications/destinations 添加的通知接收地址（邮件、Slack、Telegram）
+--       和订阅的通知类型（daily_digest、device_offline、session_failed）
+-- 用途: API Gateway 的领导者副本按订阅发送每日会话摘要、设备离线提醒和会话失败通知
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS notification_destinations (
+    id BIGSERIAL PRIMARY KEY,
+    -- 添加接收地址的用户，只接收该用户拥有的设备的通知
+    user_id VARCHAR(255) NOT NULL,
+    channel VARCHAR(20) NOT NULL CHECK (channel IN ('email', 'slack', 'telegram')),
+    -- 邮件地址、Slack incoming webhook 地址或 Telegram chat ID
+    target TEXT NOT NULL,
+    -- 订阅的通知类型
+    notifications TEXT[] NOT NULL,
+    -- 接收所有设备的通知（仅管理员可设置，用于整个组织的值班频道）
+    all_devices BOOLEAN NOT NULL DEFAULT false,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- 最近一次发送每日摘要的日期（UTC），多副本切换领导者时避免重复发送
+    last_digest_on DATE,
+    last_sent_at TIMESTAMPTZ,
+    -- 最近一次发送失败的错误，发送成功后清空
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_destinations_user_id ON notification_destinations(user_id);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.13', '通知接收地址 notification_destinations')
+ON CONFLICT (version) DO NOTHING;
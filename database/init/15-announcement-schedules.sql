@@ -0,0 +1,43 @@
+-- ============================================================================
+-- Echo System 定时语音播报
+-- ============================================================================
+-- 描述: 用户通过 /api/v1/announcements 创建的播报计划：播报文本、重复规则（cron 表达式或 RRULE）、
+--       时区和目标（设备 ID、设备位置）
+-- 用途: API Gateway 的领导者副本在 next_run_at 到期时向目标设备下发播报命令，由 Bridge 合成语音播放
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS announcement_schedules (
+    id BIGSERIAL PRIMARY KEY,
+    -- 创建计划的用户，触发时以该用户身份检查命令授权并签名
+    user_id VARCHAR(255) NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    text TEXT NOT NULL,
+    -- cron 表达式或 RRULE 规则
+    recurrence TEXT NOT NULL,
+    -- IANA 时区名，重复规则按该时区计算
+    timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    -- 规则开始生效的时间，RRULE 未指定的时间部分和 COUNT 都从该时间算起
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    device_ids TEXT[] NOT NULL DEFAULT '{}',
+    -- 按设备位置指定的目标（设备分组）
+    locations TEXT[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- 下一次触发时间，规则不再有触发时间时为 NULL
+    next_run_at TIMESTAMPTZ,
+    last_run_at TIMESTAMPTZ,
+    -- 最近一次触发时下发播报命令的设备数
+    last_device_count INTEGER,
+    -- 最近一次触发的错误（如没有可播报的设备），成功后清空
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_announcement_schedules_user_id ON announcement_schedules(user_id);
+CREATE INDEX IF NOT EXISTS idx_announcement_schedules_next_run_at ON announcement_schedules(next_run_at) WHERE enabled;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.14', '定时语音播报 announcement_schedules')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,15 @@
+-- ============================================================================
+-- Echo System 会话识别语言
+-- ============================================================================
+-- 描述: 为会话表增加识别语言字段
+-- 用途: 设备或 Web UI 在 StartChat 中指定本次会话的识别语言（如 en、zh-CN）时由 Bridge 写入，
+--       未指定的会话为 NULL，使用 EchoKit 的默认语言
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS language VARCHAR(35);
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.15', '会话识别语言字段 language')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,16 @@
+-- ============================================================================
+-- Echo System 会话摘要失败重试
+-- ============================================================================
+-- 描述: 为会话表增加摘要失败次数和下次重试时间
+-- 用途: 摘要任务对 LLM 持续拒绝的会话按次数退避重试，超过上限后不再摘要，
+--       避免单个会话一直排在待摘要队列最前面，阻塞之后的会话
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS summary_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS summary_retry_at TIMESTAMP WITH TIME ZONE;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.16', '会话摘要失败次数 summary_attempts 和重试时间 summary_retry_at')
+ON CONFLICT (version) DO NOTHING;
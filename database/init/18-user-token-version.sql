@@ -0,0 +1,15 @@
+-- ============================================================================
+-- Echo System 用户令牌版本
+-- ============================================================================
+-- 描述: 为用户表增加令牌版本号
+-- 用途: 签发的 JWT 携带签发时的版本号，重置密码时版本号加一，
+--       之前签发的令牌（包括泄露的令牌）随即失效
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.17', '用户令牌版本号 token_version，重置密码后旧令牌失效')
+ON CONFLICT (version) DO NOTHING;
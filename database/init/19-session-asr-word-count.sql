@@ -0,0 +1,15 @@
+-- ============================================================================
+-- Echo System 会话 ASR 词数
+-- ============================================================================
+-- 描述: 为会话表增加写入转写时统计的 ASR 词数
+-- 用途: 启用静态加密时转写以密文保存，数据库无法统计词数，由 Bridge 在加密前统计后写入；
+--       该字段为 NULL 的历史会话仍按明文转写统计
+-- 说明: 脚本可重复执行，API Gateway 启动时也会自动执行
+-- ============================================================================
+
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS asr_word_count INTEGER;
+
+-- 记录 schema 版本
+INSERT INTO schema_versions (version, description) VALUES
+('2025.18', '会话 ASR 词数 asr_word_count，启用静态加密时由 Bridge 写入')
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,59 @@
+-- ============================================================================
+-- Echo System SQLite 存储结构
+-- ============================================================================
+-- 描述: 单机部署使用的 SQLite 表结构，只包含设备、用户和会话（列名与 PostgreSQL 保持一致）
+-- 用途: DATABASE_URL 以 sqlite: 开头时由 echo-shared 的 SqliteStorage 在连接时自动执行
+-- 说明: 脚本可重复执行；时间以 RFC 3339 文本保存，会话标签以 JSON 数组文本保存
+-- ============================================================================
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL DEFAULT 'Viewer' CHECK (role IN ('Admin', 'Manager', 'Viewer')),
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS devices (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    device_type TEXT NOT NULL DEFAULT 'speaker',
+    status TEXT NOT NULL DEFAULT 'offline',
+    location TEXT,
+    firmware_version TEXT,
+    battery_level INTEGER CHECK (battery_level >= 0 AND battery_level <= 100),
+    volume_level INTEGER CHECK (volume_level >= 0 AND volume_level <= 100),
+    last_seen TEXT,
+    is_online BOOLEAN DEFAULT 0,
+    owner TEXT,
+    echokit_server_url TEXT NOT NULL DEFAULT '',
+    serial_number TEXT UNIQUE,
+    mac_address TEXT UNIQUE,
+    version INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    device_id TEXT NOT NULL REFERENCES devices(id) ON DELETE CASCADE,
+    user_id TEXT,
+    status TEXT NOT NULL DEFAULT 'active' CHECK (status IN ('active', 'completed', 'failed', 'timeout')),
+    start_time TEXT NOT NULL,
+    end_time TEXT,
+    duration INTEGER,
+    transcription TEXT,
+    response TEXT,
+    summary TEXT,
+    summary_tags TEXT NOT NULL DEFAULT '[]',
+    language TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_devices_status ON devices(status);
+CREATE INDEX IF NOT EXISTS idx_sessions_device_id ON sessions(device_id, start_time DESC);
+CREATE INDEX IF NOT EXISTS idx_sessions_start_time ON sessions(start_time DESC);
+
+-- 默认管理员用户（密码: admin123，使用 bcrypt 哈希）
+INSERT OR IGNORE INTO users (id, username, email, password_hash, role) VALUES
+(lower(hex(randomblob(16))), 'admin', 'admin@echo-system.com', '$2b$12$kkSiszw6xbg8ck/h0dPFY.Pm8PtHh.JOiPWACtMliYS3P5wKtLDcW', 'Admin');
@@ -9,11 +9,19 @@ const API_PREFIX = '/api/v1';
 const apiClient: AxiosInstance = axios.create({
   baseURL: `${API_BASE_URL}${API_PREFIX}`,
   timeout: 10000,
+  // 启用 Cookie 会话（cookie_auth）时由浏览器携带 HttpOnly 会话 Cookie
+  withCredentials: true,
   headers: {
     'Content-Type': 'application/json',
   },
 });
 
+// 读取脚本可见的 Cookie（CSRF 令牌）
+const readCookie = (name: string): string | null => {
+  const match = document.cookie.split('; ').find((item) => item.startsWith(`${name}=`));
+  return match ? decodeURIComponent(match.slice(name.length + 1)) : null;
+};
+
 // 请求拦截器
 apiClient.interceptors.request.use(
   (config) => {
@@ -29,6 +37,13 @@ apiClient.interceptors.request.use(
     if (token) {
       config.headers.Authorization = `Bearer ${token}`;
     }
+
+    // Cookie 会话中修改状态的请求需要带上 CSRF 令牌
+    const method = (config.method || 'get').toLowerCase();
+    const csrfToken = readCookie('echo_csrf');
+    if (csrfToken && !['get', 'head', 'options'].includes(method)) {
+      config.headers['X-CSRF-Token'] = csrfToken;
+    }
     return config;
   },
   (error) => {
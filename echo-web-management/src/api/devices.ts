@@ -19,6 +19,12 @@ export interface DeviceStats {
   };
 }
 
+export interface WsTicket {
+  ticket: string;
+  device_id: string;
+  expires_in: number;
+}
+
 // 设备 API 服务
 export const devicesApi = {
   // 获取设备列表
@@ -98,6 +104,17 @@ export const devicesApi = {
     }
   },
 
+  // 获取连接设备 WebSocket 的一次性票据，附在 Bridge 的 /ws/{id}?ticket= 上，避免把 JWT 放进 URL
+  async getWsTicket(deviceId: string): Promise<WsTicket> {
+    try {
+      const response = await apiClient.post<ApiResponse<WsTicket>>(`/devices/${deviceId}/ws-ticket`);
+      return response.data.data;
+    } catch (error) {
+      console.error(`Failed to get connection ticket for device ${deviceId}:`, error);
+      throw error;
+    }
+  },
+
   // 更新设备状态
   async updateDeviceStatus(deviceId: string, status: string): Promise<Device> {
     try {
@@ -24,7 +24,10 @@ class WebSocketService {
     this.callbacks = callbacks;
 
     try {
-      this.ws = new WebSocket(this.url);
+      // 浏览器无法为 WebSocket 设置 Authorization header，通过 query 传递 token
+      const token = localStorage.getItem('authToken');
+      const url = token ? `${this.url}?token=${encodeURIComponent(token)}` : this.url;
+      this.ws = new WebSocket(url);
 
       this.ws.onopen = () => {
         console.log('WebSocket connected');
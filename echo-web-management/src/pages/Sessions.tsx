@@ -106,6 +106,7 @@ export const Sessions: React.FC = () => {
     end_time: 150,
     duration: 100,
     conversation_rounds: 120,
+    summary: 260,
     actions: 150
   });
 
@@ -360,6 +361,28 @@ export const Sessions: React.FC = () => {
         );
       }
     },
+    {
+      title: '摘要',
+      key: 'summary',
+      width: columnWidths.summary,
+      onHeaderCell: () => ({
+        width: columnWidths.summary,
+        onResize: handleResize('summary')
+      }),
+      render: (_, record) => {
+        if (!record.summary) return <span style={{ color: '#999' }}>-</span>;
+        return (
+          <Tooltip title={record.summary}>
+            <div style={{ overflow: 'hidden', textOverflow: 'ellipsis', whiteSpace: 'nowrap' }}>
+              {record.summary}
+            </div>
+            <Space size={4} wrap>
+              {record.tags?.map(tag => <Tag key={tag}>{tag}</Tag>)}
+            </Space>
+          </Tooltip>
+        );
+      }
+    },
     {
       title: '操作',
       key: 'actions',
@@ -60,6 +60,8 @@ export interface Session {
   transcription?: string;
   response?: string;
   status: SessionStatus;
+  summary?: string | null;
+  tags?: string[];
 }
 
 export const SessionStatus = {
@@ -0,0 +1,81 @@
+//! etch-all：在一个进程中运行 Bridge 和 API Gateway
+//!
+//! 适用于小规模部署和本地开发。两个服务仍监听各自的端口（`bridge.websocket_port` 和
+//! `server.port`），但共用一个存储连接（`database.url` 可以是 PostgreSQL 或 SQLite）；Bridge 的
+//! 会话实时事件经进程内事件总线交给 API Gateway，不需要 MQTT 转发。两个服务的指标写入同一个记录器，任一 `/metrics` 都包含全部指标。
+//! 收到 Ctrl+C / SIGTERM 时等待 API Gateway 排空连接后退出，任一服务异常退出时整个进程退出。
+
+use anyhow::{Context, Result};
+use echo_shared::{AppConfig, InProcess};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use tracing::{error, info};
+
+fn main() -> Result<()> {
+    echo_shared::mark_process_start();
+
+    // 加载 .env 文件（如果存在）
+    // 注意：系统环境变量优先级高于 .env 文件
+    dotenvy::dotenv().ok();
+
+    // 加载配置（配置文件 + 环境变量覆盖），运行时参数来自配置，因此先在临时运行时中加载
+    let app_config: AppConfig = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to build config loader runtime")?
+        .block_on(echo_shared::load_config())?;
+
+    // 使用 Bridge 的运行时参数，音频转发是进程中最主要的负载
+    let runtime_settings = &app_config.bridge.runtime;
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if runtime_settings.worker_threads > 0 {
+        builder.worker_threads(runtime_settings.worker_threads);
+    }
+    builder
+        .max_blocking_threads(runtime_settings.max_blocking_threads)
+        .enable_all()
+        .build()
+        .context("Failed to build Tokio runtime")?
+        .block_on(run(app_config))
+}
+
+async fn run(app_config: AppConfig) -> Result<()> {
+    // 日志只初始化一次，日志级别随 Bridge 的配置热加载
+    let log_handle = echo_bridge::init_tracing(&app_config.log);
+
+    // 两个服务的直方图分桶合并后安装一个全局指标记录器
+    let metrics = echo_api_gateway::metrics_buckets(echo_bridge::metrics_buckets(PrometheusBuilder::new())?)?
+        .install_recorder()
+        .context("Failed to install metrics recorder")?;
+    echo_bridge::use_metrics_handle(metrics.clone());
+    echo_api_gateway::use_metrics_handle(metrics);
+
+    info!("Connecting to database: {}", app_config.database.url);
+    let storage = echo_shared::connect_storage(&app_config.database)
+        .await
+        .context("Failed to connect to database")?;
+    let in_process = InProcess::new(storage.clone());
+
+    info!(
+        "Starting Bridge (port {}) and API Gateway (port {}) in one process",
+        app_config.bridge.websocket_port, app_config.server.port
+    );
+    let gateway = echo_api_gateway::run_in_process(app_config.clone(), in_process.clone());
+    let bridge = echo_bridge::run_in_process(app_config, log_handle, in_process);
+
+    tokio::pin!(gateway);
+    let result = tokio::select! {
+        result = &mut gateway => result.context("API Gateway failed"),
+        result = bridge => match result {
+            // Ctrl+C 时 Bridge 先退出，继续等待 API Gateway 排空连接
+            Ok(()) => gateway.await.context("API Gateway failed"),
+            Err(e) => Err(e.context("Bridge failed")),
+        },
+    };
+    if let Err(e) = &result {
+        error!("{:#}", e);
+    }
+
+    storage.close().await;
+    info!("etch-all stopped");
+    result
+}
@@ -0,0 +1,602 @@
+//! Bridge WebSocket 客户端
+//!
+//! 后台任务持有 WebSocket 连接：转发命令和音频、解码服务端事件、定时发送 Ping，
+//! 连接断开后按 `ReconnectPolicy` 退避重连。
+//!
+//! Bridge 在连接断开时会结束设备的活跃会话，重连后（收到 `Event::Connected { reconnected: true }`）
+//! 需要重新 `start_chat` / `start_record`。断线期间的发送会直接返回 `Error::Disconnected`，
+//! 不会排队到重连后再发，避免把过期的音频送进新会话。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::protocol::{Command, Notice, ServerEvent};
+use crate::{Error, Result};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 获取连接票据的回调
+type TicketProvider =
+    Arc<dyn Fn() -> Pin<Box<dyn Future<Output = std::result::Result<String, String>> + Send>> + Send + Sync>;
+
+/// 发送队列长度（消息数）
+const OUTGOING_QUEUE: usize = 64;
+
+/// 事件队列长度；应用不读取事件时后台任务暂停接收，由 TCP 对 Bridge 形成背压
+const EVENT_QUEUE: usize = 256;
+
+/// 客户端事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// 已连接到 Bridge；`reconnected` 为 true 表示这是断线后的重连
+    Connected { reconnected: bool },
+
+    /// 连接已断开，`reason` 为断开原因
+    Disconnected { reason: String },
+
+    /// Bridge 转发的 EchoKit 事件（ASR、音频等）
+    Server(ServerEvent),
+
+    /// Bridge 的 JSON 通知（会话状态、错误等）
+    Notice(Notice),
+}
+
+/// 断线重连策略（指数退避）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// 是否自动重连
+    pub enabled: bool,
+    /// 第一次重连前的等待时间
+    pub initial_delay: Duration,
+    /// 最长等待时间
+    pub max_delay: Duration,
+    /// 连续失败多少次后放弃，`None` 表示一直重试
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 不自动重连，断开后 `next_event` 返回 `None`
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Self::default() }
+    }
+
+    /// 第 `attempt` 次（从 1 开始）重连前的等待时间
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1).min(16));
+        self.initial_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// `EtchClient` 构建器
+pub struct EtchClientBuilder {
+    bridge_url: String,
+    device_id: String,
+    record_mode: bool,
+    ticket: Option<String>,
+    ticket_provider: Option<TicketProvider>,
+    reconnect: ReconnectPolicy,
+    heartbeat_interval: Option<Duration>,
+    connect_timeout: Duration,
+}
+
+impl EtchClientBuilder {
+    /// 以录制模式连接（`?record=true`）
+    pub fn with_record_mode(mut self, record_mode: bool) -> Self {
+        self.record_mode = record_mode;
+        self
+    }
+
+    /// 连接票据（API Gateway 签发，只能使用一次）
+    ///
+    /// 启用了重连且 Bridge 要求票据时，应使用 `with_ticket_provider` 在每次连接前获取新票据。
+    pub fn with_ticket(mut self, ticket: impl Into<String>) -> Self {
+        self.ticket = Some(ticket.into());
+        self
+    }
+
+    /// 每次连接（包括重连）前调用，获取新的连接票据
+    pub fn with_ticket_provider<F, Fut>(mut self, provider: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<String, String>> + Send + 'static,
+    {
+        self.ticket_provider = Some(Arc::new(move || Box::pin(provider())));
+        self
+    }
+
+    /// 断线重连策略
+    pub fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    /// WebSocket Ping 间隔，`None` 表示不发送（默认 30 秒）
+    pub fn with_heartbeat_interval(mut self, interval: Option<Duration>) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// 单次连接（含 TLS 和 WebSocket 握手）的超时时间（默认 10 秒）
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// 建立第一次连接并启动后台任务
+    ///
+    /// 第一次连接失败直接返回错误（通常是地址或票据配置错误），不进入重连。
+    pub async fn connect(self) -> Result<EtchClient> {
+        let mut url = Url::parse(&self.bridge_url).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        if !matches!(url.scheme(), "ws" | "wss") {
+            return Err(Error::InvalidUrl(format!("unsupported scheme: {}", url.scheme())));
+        }
+        url.path_segments_mut()
+            .map_err(|_| Error::InvalidUrl(self.bridge_url.clone()))?
+            .pop_if_empty()
+            .extend(["ws", self.device_id.as_str()]);
+        if self.record_mode {
+            url.query_pairs_mut().append_pair("record", "true");
+        }
+
+        let connector = Connector {
+            url,
+            ticket: self.ticket,
+            ticket_provider: self.ticket_provider,
+            timeout: self.connect_timeout,
+        };
+        let socket = connector.connect().await?;
+        info!("Connected to bridge as device {}", self.device_id);
+
+        let connected = Arc::new(AtomicBool::new(true));
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(OUTGOING_QUEUE);
+        let (events_tx, events_rx) = mpsc::channel(EVENT_QUEUE);
+        // 第一条事件总是 Connected，方便应用在同一处处理首连和重连
+        let _ = events_tx.try_send(Event::Connected { reconnected: false });
+
+        let task = tokio::spawn(
+            Connection {
+                connector,
+                reconnect: self.reconnect,
+                heartbeat_interval: self.heartbeat_interval,
+                connected: connected.clone(),
+                outgoing: outgoing_rx,
+                events: events_tx,
+            }
+            .run(socket),
+        );
+
+        Ok(EtchClient {
+            device_id: self.device_id,
+            connected,
+            outgoing: outgoing_tx,
+            events: events_rx,
+            task,
+        })
+    }
+}
+
+/// Bridge WebSocket 客户端
+///
+/// ```no_run
+/// # async fn example() -> etch_client::Result<()> {
+/// use etch_client::{EtchClient, Event, ServerEvent};
+///
+/// let mut client = EtchClient::builder("ws://localhost:10031", "device-001").connect().await?;
+/// client.start_chat().await?;
+/// client.send_samples(&[0i16; 1600]).await?;
+/// client.submit().await?;
+///
+/// while let Some(event) = client.next_event().await {
+///     match event {
+///         Event::Server(ServerEvent::ASR { text }) => println!("ASR: {}", text),
+///         Event::Server(ServerEvent::AudioChunk { data }) => { /* 播放 PCM */ }
+///         Event::Server(ServerEvent::EndResponse) => break,
+///         _ => {}
+///     }
+/// }
+/// client.close().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EtchClient {
+    device_id: String,
+    connected: Arc<AtomicBool>,
+    outgoing: mpsc::Sender<Message>,
+    events: mpsc::Receiver<Event>,
+    task: JoinHandle<()>,
+}
+
+impl EtchClient {
+    /// 创建构建器，`bridge_url` 为 Bridge WebSocket 地址（如 `ws://localhost:10031`）
+    pub fn builder(bridge_url: impl Into<String>, device_id: impl Into<String>) -> EtchClientBuilder {
+        EtchClientBuilder {
+            bridge_url: bridge_url.into(),
+            device_id: device_id.into(),
+            record_mode: false,
+            ticket: None,
+            ticket_provider: None,
+            reconnect: ReconnectPolicy::default(),
+            heartbeat_interval: Some(Duration::from_secs(30)),
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// 设备 ID
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// 当前是否已连接
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    /// 开始对话模式会话
+    pub async fn start_chat(&self) -> Result<()> {
+        self.send_command(&Command::StartChat { language: None }).await
+    }
+
+    /// 开始对话模式会话并指定识别语言（如 `en-US`）；Bridge 不支持该语言时使用默认语言
+    pub async fn start_chat_with_language(&self, language: impl Into<String>) -> Result<()> {
+        self.send_command(&Command::StartChat { language: Some(language.into()) }).await
+    }
+
+    /// 开始录制模式会话
+    pub async fn start_record(&self) -> Result<()> {
+        self.send_command(&Command::StartRecord).await
+    }
+
+    /// 提交已发送的音频进行识别和回复
+    pub async fn submit(&self) -> Result<()> {
+        self.send_command(&Command::Submit).await
+    }
+
+    /// 发送文本输入
+    pub async fn send_text(&self, input: impl Into<String>) -> Result<()> {
+        self.send_command(&Command::Text { input: input.into() }).await
+    }
+
+    /// 发送命令
+    pub async fn send_command(&self, command: &Command) -> Result<()> {
+        let json = command.to_json().map_err(|e| Error::Protocol(e.to_string()))?;
+        self.send(Message::Text(json)).await
+    }
+
+    /// 发送 PCM 音频（16-bit 小端、16000Hz、单声道）
+    pub async fn send_audio(&self, pcm: &[u8]) -> Result<()> {
+        if !pcm.len().is_multiple_of(2) {
+            return Err(Error::Protocol("PCM audio must contain whole 16-bit samples".to_string()));
+        }
+        self.send(Message::Binary(pcm.to_vec())).await
+    }
+
+    /// 发送 PCM 采样（16000Hz、单声道）
+    pub async fn send_samples(&self, samples: &[i16]) -> Result<()> {
+        let pcm = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.send(Message::Binary(pcm)).await
+    }
+
+    /// 等待下一个事件；连接关闭且不再重连时返回 `None`
+    pub async fn next_event(&mut self) -> Option<Event> {
+        self.events.recv().await
+    }
+
+    /// 关闭连接并等待后台任务退出（直接丢弃客户端也会关闭连接，但不等待）
+    pub async fn close(self) {
+        let EtchClient { outgoing, task, .. } = self;
+        let _ = outgoing.send(Message::Close(None)).await;
+        drop(outgoing);
+        let _ = task.await;
+    }
+
+    async fn send(&self, message: Message) -> Result<()> {
+        if !self.is_connected() {
+            return Err(Error::Disconnected);
+        }
+        self.outgoing.send(message).await.map_err(|_| Error::Closed)
+    }
+}
+
+/// 建立连接所需的参数，重连时复用
+struct Connector {
+    url: Url,
+    ticket: Option<String>,
+    ticket_provider: Option<TicketProvider>,
+    timeout: Duration,
+}
+
+impl Connector {
+    async fn connect(&self) -> Result<Socket> {
+        let mut url = self.url.clone();
+        let ticket = match &self.ticket_provider {
+            Some(provider) => Some(provider().await.map_err(Error::Ticket)?),
+            None => self.ticket.clone(),
+        };
+        if let Some(ticket) = ticket {
+            url.query_pairs_mut().append_pair("ticket", &ticket);
+        }
+
+        let (socket, _) = tokio::time::timeout(self.timeout, tokio_tungstenite::connect_async(url.as_str()))
+            .await
+            .map_err(|_| Error::Connect("connection timed out".to_string()))?
+            .map_err(|e| Error::Connect(e.to_string()))?;
+        Ok(socket)
+    }
+}
+
+/// 后台连接任务
+struct Connection {
+    connector: Connector,
+    reconnect: ReconnectPolicy,
+    heartbeat_interval: Option<Duration>,
+    connected: Arc<AtomicBool>,
+    outgoing: mpsc::Receiver<Message>,
+    events: mpsc::Sender<Event>,
+}
+
+/// 一次连接结束的原因
+enum Ended {
+    /// 应用关闭了客户端
+    Closed,
+    /// 连接断开
+    Lost(String),
+}
+
+impl Connection {
+    async fn run(mut self, mut socket: Socket) {
+        loop {
+            let reason = match self.drive(&mut socket).await {
+                Ended::Closed => break,
+                Ended::Lost(reason) => reason,
+            };
+            self.connected.store(false, Ordering::Release);
+            warn!("Bridge connection lost: {}", reason);
+            if self.events.send(Event::Disconnected { reason }).await.is_err() || !self.reconnect.enabled {
+                break;
+            }
+
+            match self.reconnect().await {
+                Some(new_socket) => socket = new_socket,
+                None => break,
+            }
+            self.connected.store(true, Ordering::Release);
+            if self.events.send(Event::Connected { reconnected: true }).await.is_err() {
+                break;
+            }
+        }
+        self.connected.store(false, Ordering::Release);
+    }
+
+    /// 收发消息直到连接断开或客户端关闭
+    async fn drive(&mut self, socket: &mut Socket) -> Ended {
+        let mut heartbeat = self.heartbeat_interval.map(|interval| {
+            let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+
+        loop {
+            tokio::select! {
+                outgoing = self.outgoing.recv() => match outgoing {
+                    Some(Message::Close(frame)) => {
+                        let _ = socket.close(frame).await;
+                        return Ended::Closed;
+                    }
+                    Some(message) => {
+                        if let Err(e) = socket.send(message).await {
+                            return Ended::Lost(e.to_string());
+                        }
+                    }
+                    None => {
+                        let _ = socket.close(None).await;
+                        return Ended::Closed;
+                    }
+                },
+                incoming = socket.next() => match incoming {
+                    Some(Ok(message)) => {
+                        if let Some(reason) = self.handle_message(message).await {
+                            return reason;
+                        }
+                    }
+                    Some(Err(e)) => return Ended::Lost(e.to_string()),
+                    None => return Ended::Lost("connection closed".to_string()),
+                },
+                _ = async { heartbeat.as_mut().unwrap().tick().await }, if heartbeat.is_some() => {
+                    if let Err(e) = socket.send(Message::Ping(Vec::new())).await {
+                        return Ended::Lost(e.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 处理一条服务端消息，连接应结束时返回原因
+    async fn handle_message(&mut self, message: Message) -> Option<Ended> {
+        let event = match message {
+            Message::Binary(frame) => match ServerEvent::from_messagepack(&frame) {
+                Ok(event) => Event::Server(event),
+                Err(e) => {
+                    // 新版本 Bridge 可能新增事件类型，跳过而不是断开
+                    debug!("Skipping unrecognized bridge event ({} bytes): {}", frame.len(), e);
+                    return None;
+                }
+            },
+            Message::Text(text) => match Notice::from_json(&text) {
+                Ok(notice) => Event::Notice(notice),
+                Err(e) => {
+                    debug!("Skipping malformed bridge message: {}", e);
+                    return None;
+                }
+            },
+            Message::Close(frame) => {
+                let reason = frame
+                    .map(|f| format!("closed by bridge: {} {}", f.code, f.reason))
+                    .unwrap_or_else(|| "closed by bridge".to_string());
+                return Some(Ended::Lost(reason));
+            }
+            // Ping 由 tungstenite 自动应答
+            _ => return None,
+        };
+
+        if self.events.send(event).await.is_err() {
+            // 应用已丢弃客户端
+            return Some(Ended::Closed);
+        }
+        None
+    }
+
+    /// 按退避策略重连，放弃或客户端已关闭时返回 `None`
+    async fn reconnect(&mut self) -> Option<Socket> {
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            if self.reconnect.max_attempts.is_some_and(|max| attempt > max) {
+                warn!("Giving up reconnecting to bridge after {} attempts", attempt - 1);
+                return None;
+            }
+
+            let delay = self.reconnect.delay(attempt);
+            debug!("Reconnecting to bridge in {:?} (attempt {})", delay, attempt);
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+            // 等待期间丢弃断线前排队的消息，客户端关闭则停止重连
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    message = self.outgoing.recv() => match message {
+                        Some(Message::Close(_)) | None => return None,
+                        Some(_) => {}
+                    },
+                }
+            }
+
+            match self.connector.connect().await {
+                Ok(socket) => {
+                    info!("Reconnected to bridge after {} attempts", attempt);
+                    return Some(socket);
+                }
+                Err(e) => warn!("Failed to reconnect to bridge: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 模拟 Bridge：第一个连接收到 StartChat 和音频后回复 ASR 并断开，第二个连接回复 EndResponse
+    // 握手回调的错误类型由 tungstenite 决定
+    #[allow(clippy::result_large_err)]
+    async fn mock_bridge() -> (String, mpsc::Receiver<(String, Message)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (received_tx, received_rx) = mpsc::channel(16);
+
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut path = String::new();
+                let mut socket = tokio_tungstenite::accept_hdr_async(
+                    stream,
+                    |request: &tokio_tungstenite::tungstenite::handshake::server::Request, response| {
+                        path = request.uri().to_string();
+                        Ok(response)
+                    },
+                )
+                .await
+                .unwrap();
+
+                if connection == 0 {
+                    for _ in 0..2 {
+                        let message = socket.next().await.unwrap().unwrap();
+                        received_tx.send((path.clone(), message)).await.unwrap();
+                    }
+                    let asr = ServerEvent::ASR { text: "hello".to_string() }.to_messagepack().unwrap();
+                    socket.send(Message::Binary(asr)).await.unwrap();
+                    // 未知事件应被跳过
+                    socket.send(Message::Binary(b"\xa8Teleport".to_vec())).await.unwrap();
+                    socket.close(None).await.unwrap();
+                } else {
+                    let message = socket.next().await.unwrap().unwrap();
+                    received_tx.send((path.clone(), message)).await.unwrap();
+                    let end = ServerEvent::EndResponse.to_messagepack().unwrap();
+                    socket.send(Message::Binary(end)).await.unwrap();
+                    let _ = socket.next().await;
+                }
+            }
+        });
+
+        (format!("ws://{}", addr), received_rx)
+    }
+
+    #[tokio::test]
+    async fn test_client_streams_audio_and_reconnects() {
+        let (url, mut received) = mock_bridge().await;
+        let mut client = EtchClient::builder(url, "device-001")
+            .with_record_mode(true)
+            .with_ticket("t1")
+            .with_reconnect(ReconnectPolicy { initial_delay: Duration::from_millis(10), ..Default::default() })
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(client.next_event().await, Some(Event::Connected { reconnected: false }));
+
+        client.start_chat().await.unwrap();
+        client.send_samples(&[1, -1]).await.unwrap();
+
+        let (path, message) = received.recv().await.unwrap();
+        assert_eq!(path, "/ws/device-001?record=true&ticket=t1");
+        assert_eq!(message, Message::Text(r#"{"event":"StartChat"}"#.to_string()));
+        let (_, message) = received.recv().await.unwrap();
+        assert_eq!(message, Message::Binary(vec![0x01, 0x00, 0xff, 0xff]));
+
+        assert_eq!(
+            client.next_event().await,
+            Some(Event::Server(ServerEvent::ASR { text: "hello".to_string() }))
+        );
+        assert!(matches!(client.next_event().await, Some(Event::Disconnected { .. })));
+        assert_eq!(client.next_event().await, Some(Event::Connected { reconnected: true }));
+
+        // 重连后需要重新开始会话
+        client.start_chat_with_language("en-US").await.unwrap();
+        let (_, message) = received.recv().await.unwrap();
+        assert_eq!(message, Message::Text(r#"{"event":"StartChat","language":"en-US"}"#.to_string()));
+        assert_eq!(client.next_event().await, Some(Event::Server(ServerEvent::EndResponse)));
+
+        client.close().await;
+    }
+
+    #[test]
+    fn test_reconnect_delay_backs_off() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,40 @@
+//! Echo Bridge WebSocket 客户端 SDK
+//!
+//! 封装 Bridge 的设备 WebSocket 协议，供 Rust 编写的设备固件和桌面应用使用：
+//!
+//! - 连接 `ws://<bridge>/ws/<device_id>`，支持录制模式和连接票据
+//! - 发送 `StartChat` / `StartRecord` / `Submit` / `Text` 命令和 16kHz PCM 音频
+//! - 接收解码后的 EchoKit 事件（ASR、音频响应等）和 Bridge 通知
+//! - 定时 Ping 保活，断线后按指数退避自动重连
+//!
+//! 线上格式见 `protocol` 模块，与 Bridge 的一致性由 `echo-shared` 的 `testkit` 测试套件校验。
+
+pub mod client;
+pub mod protocol;
+
+pub use client::{EtchClient, EtchClientBuilder, Event, ReconnectPolicy};
+pub use protocol::{Command, Notice, ServerEvent, PROTOCOL_VERSION, SAMPLE_RATE};
+
+/// SDK 错误
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid bridge URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Failed to connect to bridge: {0}")]
+    Connect(String),
+
+    #[error("Failed to obtain connection ticket: {0}")]
+    Ticket(String),
+
+    #[error("Not connected to bridge")]
+    Disconnected,
+
+    #[error("Client is closed")]
+    Closed,
+
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
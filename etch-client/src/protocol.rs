@@ -0,0 +1,288 @@
+//! Bridge WebSocket 线上格式
+//!
+//! - 客户端命令：JSON 文本消息，`event` 字段为命令名（如 `{"event":"StartChat"}`）
+//! - 音频上行：二进制消息，16-bit PCM（小端）、16000Hz、单声道
+//! - 服务端事件：MessagePack 二进制消息，外部标签枚举。无字段的事件编码为字符串，
+//!   带字段的事件编码为 `{事件名: [字段...]}`，音频数据为 MessagePack bin
+//! - 通知：JSON 文本消息（会话开始/结束、错误、心跳应答等）
+//!
+//! 与 `echo-shared` 中的 `ClientCommand`、`EchoKitEvent` 保持线上兼容（由一致性测试保证），
+//! 但不依赖 `echo-shared`，避免把服务端依赖带进固件和桌面应用。
+
+use serde::{Deserialize, Serialize};
+
+/// 本 SDK 实现的协议版本，与 Bridge 的 `PROTOCOL_VERSION` 对应
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 音频采样率（Hz）
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// 发送给 Bridge 的命令
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Command {
+    /// 开始录制模式会话
+    StartRecord,
+
+    /// 开始对话模式会话，`language` 指定本次会话的识别语言（如 `en-US`），不指定时使用默认语言
+    StartChat {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+    },
+
+    /// 提交已发送的音频进行处理
+    Submit,
+
+    /// 发送文本输入
+    Text { input: String },
+}
+
+impl Command {
+    /// 编码为 JSON 文本消息
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Bridge 转发的 EchoKit 事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ServerEvent {
+    /// 开始发送问候音频
+    HelloStart,
+
+    /// 问候音频数据块（16-bit PCM, 16000Hz, 单声道）
+    HelloChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 问候音频结束
+    HelloEnd,
+
+    /// 开始发送背景音乐
+    BGStart,
+
+    /// 背景音乐数据块
+    BGChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 背景音乐结束
+    BGEnd,
+
+    /// 语音识别结果
+    ASR { text: String },
+
+    /// 动作指令
+    Action { action: String },
+
+    /// 开始音频响应，text 为本段 AI 回复文本
+    StartAudio { text: String },
+
+    /// 音频响应数据块（16-bit PCM, 16000Hz, 单声道）
+    AudioChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 音频响应结束
+    EndAudio,
+
+    /// 开始视频响应
+    StartVideo,
+
+    /// 视频响应结束
+    EndVideo,
+
+    /// 本轮响应结束
+    EndResponse,
+}
+
+impl ServerEvent {
+    /// 解码一帧 MessagePack 事件
+    pub fn from_messagepack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(data)
+    }
+
+    /// 编码为与 Bridge 相同的 MessagePack 格式
+    pub fn to_messagepack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    /// 事件携带的 PCM 音频（问候、背景音乐或响应音频）
+    pub fn audio(&self) -> Option<&[u8]> {
+        match self {
+            ServerEvent::HelloChunk { data } | ServerEvent::BGChunk { data } | ServerEvent::AudioChunk { data } => {
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Bridge 以 JSON 文本发送的通知
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+    /// 会话已开始
+    SessionStarted { session_id: String },
+
+    /// 会话已结束；`forced` 表示被管理员或服务端强制结束
+    SessionEnded {
+        session_id: String,
+        reason: Option<String>,
+        forced: bool,
+    },
+
+    /// 错误，如 `service_unavailable`、`session_limit_exceeded`
+    Error {
+        code: String,
+        message: String,
+        /// 不可用的服务（仅 `service_unavailable`）
+        service: Option<String>,
+        /// 建议的重试等待时间
+        retry_after_ms: Option<u64>,
+    },
+
+    /// 心跳应答
+    HeartbeatAck,
+
+    /// 其他文本消息（如签名的设备命令），原样保留
+    Other(serde_json::Value),
+}
+
+impl Notice {
+    /// 解析 Bridge 发送的文本消息
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        let value: serde_json::Value = serde_json::from_str(text)?;
+        let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(str::to_string);
+
+        let notice = match value.get("event").and_then(|v| v.as_str()) {
+            Some("session_started") => match field("session_id") {
+                Some(session_id) => Notice::SessionStarted { session_id },
+                None => Notice::Other(value),
+            },
+            Some("session_ended") => match field("session_id") {
+                Some(session_id) => Notice::SessionEnded {
+                    session_id,
+                    reason: field("reason"),
+                    forced: value.get("forced").and_then(|v| v.as_bool()).unwrap_or(false),
+                },
+                None => Notice::Other(value),
+            },
+            Some("error") => Notice::Error {
+                code: field("code").unwrap_or_default(),
+                message: field("message").unwrap_or_default(),
+                service: field("service"),
+                retry_after_ms: value.get("retry_after_ms").and_then(|v| v.as_u64()),
+            },
+            Some("heartbeat_ack") => Notice::HeartbeatAck,
+            _ => Notice::Other(value),
+        };
+        Ok(notice)
+    }
+}
+
+// 音频数据编码为 MessagePack bin；解码时同时接受 bin 和整数数组
+mod bytes {
+    use serde::de::{Deserializer, SeqAccess, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("audio bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    data.push(byte);
+                }
+                Ok(data)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use echo_shared::testkit::{check_conformance, ClientCodec};
+    use echo_shared::{ClientCommand, EchoKitEvent};
+
+    // 通过 JSON 在两边的类型之间转换，线上格式由各自的编解码决定
+    fn convert<T: Serialize, U: for<'de> Deserialize<'de>>(value: &T) -> Result<U, String> {
+        serde_json::to_value(value)
+            .and_then(serde_json::from_value)
+            .map_err(|e| e.to_string())
+    }
+
+    struct SdkCodec;
+
+    impl ClientCodec for SdkCodec {
+        fn decode_event(&self, frame: &[u8]) -> Result<EchoKitEvent, String> {
+            let event = ServerEvent::from_messagepack(frame).map_err(|e| e.to_string())?;
+            // 音频字段在 JSON 中是整数数组，两边都能解码
+            convert(&event)
+        }
+
+        fn encode_command(&self, command: &ClientCommand) -> Result<String, String> {
+            let command: Command = convert(command)?;
+            command.to_json().map_err(|e| e.to_string())
+        }
+    }
+
+    #[test]
+    fn test_sdk_codec_conforms_to_bridge_protocol() {
+        assert_eq!(PROTOCOL_VERSION, echo_shared::protocol::PROTOCOL_VERSION);
+        if let Err(failure) = check_conformance(&SdkCodec, 256) {
+            panic!("protocol conformance failed: {}", failure);
+        }
+    }
+
+    #[test]
+    fn test_parse_notices() {
+        let notice = Notice::from_json(
+            r#"{"event":"error","code":"service_unavailable","service":"echokit","message":"down","retry_after_ms":5000,"timestamp":1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            notice,
+            Notice::Error {
+                code: "service_unavailable".to_string(),
+                message: "down".to_string(),
+                service: Some("echokit".to_string()),
+                retry_after_ms: Some(5000),
+            }
+        );
+
+        let notice = Notice::from_json(r#"{"event":"session_ended","session_id":"s1","forced":true}"#).unwrap();
+        assert_eq!(
+            notice,
+            Notice::SessionEnded { session_id: "s1".to_string(), reason: None, forced: true }
+        );
+
+        let notice = Notice::from_json(r#"{"command":"reboot","signature":"abc"}"#).unwrap();
+        assert!(matches!(notice, Notice::Other(_)));
+    }
+}
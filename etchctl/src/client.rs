@@ -0,0 +1,191 @@
+// API Gateway 和 Bridge 内部 API 的 HTTP 客户端
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use echo_shared::ApiResponse;
+use futures::{stream, Stream, StreamExt};
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// 上传录音等大请求的超时
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 带 Bearer 令牌的 JSON API 客户端
+pub struct ApiClient {
+    base_url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self> {
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            http: reqwest::Client::builder().build()?,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<&serde_json::Value>) -> Result<Response> {
+        let mut request = self.request(method.clone(), path).timeout(REQUEST_TIMEOUT);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}{}", self.base_url, path))?;
+        check_status(method, path, response).await
+    }
+
+    /// 请求 Bridge 内部 API 等直接返回 JSON 的接口，204 时返回 null
+    pub async fn json<T: DeserializeOwned>(&self, method: Method, path: &str, body: Option<&serde_json::Value>) -> Result<T> {
+        let response = self.send(method, path, body).await?;
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok(serde_json::from_value(serde_json::Value::Null)?);
+        }
+        response.json().await.with_context(|| format!("Unexpected response from {}", path))
+    }
+
+    /// 以 multipart/form-data 上传文件，接口直接返回 JSON
+    pub async fn upload<T: DeserializeOwned>(&self, path: &str, form: reqwest::multipart::Form) -> Result<T> {
+        let response = self
+            .request(Method::POST, path)
+            .timeout(UPLOAD_TIMEOUT)
+            .multipart(form)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}{}", self.base_url, path))?;
+        let response = check_status(Method::POST, path, response).await?;
+        response.json().await.with_context(|| format!("Unexpected response from {}", path))
+    }
+
+    /// 请求 API Gateway 接口，返回 `ApiResponse` 中的 data
+    pub async fn data<T: DeserializeOwned>(&self, method: Method, path: &str, body: Option<&serde_json::Value>) -> Result<T> {
+        let response: ApiResponse<serde_json::Value> = self.json(method, path, body).await?;
+        if !response.success {
+            bail!("{}", response.message);
+        }
+        Ok(serde_json::from_value(response.data.unwrap_or_default())?)
+    }
+
+    /// 订阅 SSE 事件流，连接断开或服务端结束事件流时结束
+    pub async fn events(&self, path: &str) -> Result<impl Stream<Item = Result<SseEvent>>> {
+        let response = self
+            .request(Method::GET, path)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}{}", self.base_url, path))?;
+        let response = check_status(Method::GET, path, response).await?;
+
+        let state = (response.bytes_stream().boxed(), SseParser::default(), VecDeque::new());
+        Ok(stream::unfold(state, |(mut bytes, mut parser, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((Ok(event), (bytes, parser, pending)));
+                }
+                match bytes.next().await? {
+                    Ok(chunk) => pending.extend(parser.push(&chunk)),
+                    Err(e) => {
+                        let error = anyhow::Error::new(e).context("Event stream interrupted");
+                        return Some((Err(error), (bytes, parser, pending)));
+                    }
+                }
+            }
+        }))
+    }
+}
+
+/// 一条 SSE 事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+// 按行解析 text/event-stream，只处理 event 和 data 字段
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if line.is_empty() {
+                // 空行结束一条事件
+                let event = self.event.take();
+                if !self.data.is_empty() {
+                    let data = std::mem::take(&mut self.data).join("\n");
+                    events.push(SseEvent { event: event.unwrap_or_else(|| "message".to_string()), data });
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+// 非 2xx 响应转换为错误，优先使用 problem+json 中的 detail
+async fn check_status(method: Method, path: &str, response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let detail = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|value| {
+            value["detail"].as_str().or_else(|| value["message"].as_str()).map(str::to_string)
+        })
+        .unwrap_or(body);
+    bail!("{} {} failed with {}: {}", method, path, status, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(parser.push(b": keep-alive\n\nevent: snap").is_empty());
+        let events = parser.push("shot\ndata: {\"a\":1}\r\n\ndata: 你".as_bytes());
+        assert_eq!(events, vec![SseEvent { event: "snapshot".to_string(), data: "{\"a\":1}".to_string() }]);
+
+        // 多字节字符被拆分到两个数据块
+        let text = "好\n\n".as_bytes();
+        assert!(parser.push(&text[..1]).is_empty());
+        let events = parser.push(&text[1..]);
+        assert_eq!(events, vec![SseEvent { event: "message".to_string(), data: "你好".to_string() }]);
+    }
+}
@@ -0,0 +1,438 @@
+//! etchctl：Echo System 管理命令行工具
+//!
+//! 通过 API Gateway（管理员或设备所有者的 JWT）完成常用运维操作：查看和强制结束会话、查看在线设备、
+//! 向设备推送配置、轮换加密主密钥后重新包装会话密钥、实时跟踪会话事件。会话和设备查询也可以加
+//! `--bridge` 直接访问 Bridge 内部 API（需要 internal_token），用于 API Gateway 不可用时排查。
+//! 录音导入只经过 Bridge 内部 API，同样需要 internal_token。
+//!
+//! ```text
+//! export ETCH_TOKEN=$(etchctl login --username admin)
+//! etchctl sessions list
+//! etchctl devices push-config echo-001 --set volume=60 --set language=zh-CN
+//! etchctl -o json devices online --bridge
+//! etchctl imports create echo-001 kitchen-*.wav --wait
+//! ```
+
+mod client;
+mod output;
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use echo_shared::{
+    AudioImportJob, BridgeOnlineDevice, BridgeSessionState, DeviceConfiguration, BRIDGE_INTERNAL_TOKEN_ENV,
+};
+use futures::StreamExt;
+use reqwest::Method;
+use serde_json::json;
+
+use client::ApiClient;
+use output::{label, print_json, OutputFormat, Table};
+
+// 等待录音导入完成时的查询间隔
+const IMPORT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+const USAGE: &str = "\
+Usage: etchctl [OPTIONS] <COMMAND>
+
+Commands:
+  login --username <USER>              Log in and print an access token for ETCH_TOKEN
+                                       (password from --password, ETCH_PASSWORD or stdin)
+  sessions list                        List active sessions
+  sessions end <SESSION_ID>            Force-end an active session
+  sessions tail <SESSION_ID>           Follow the live events of a session until it ends
+  devices online                       List devices connected to the bridge
+  devices push-config <DEVICE_ID>      Push configuration from --file and/or --set KEY=VALUE
+  keys rotate                          Re-wrap session data keys with the current encryption master key
+  imports create <DEVICE_ID> <FILE>... Transcribe WAV/Ogg Opus recordings into sessions of a device
+                                       (bridge internal API; dated by file modification time)
+  imports status <JOB_ID>              Show the progress of an import on the same bridge instance
+
+Options:
+  --gateway <URL>       API Gateway base URL [env: ETCH_GATEWAY_URL] [default: http://localhost:10033]
+  --token <TOKEN>       API Gateway access token [env: ETCH_TOKEN]
+  --bridge              Query the bridge internal API directly (sessions list/end, devices online)
+  --bridge-url <URL>    Bridge internal API base URL [env: ETCH_BRIDGE_URL] [default: http://localhost:10031]
+  --bridge-token <T>    Bridge internal API token [env: BRIDGE_INTERNAL_TOKEN]
+  --reason <TEXT>       Reason recorded when ending a session via --bridge [default: terminated_by_etchctl]
+  --file <FILE>         JSON object with device configuration fields
+  --set <KEY=VALUE>     Device configuration field, value parsed as JSON when possible (repeatable)
+  --recorded-at <TIME>  Start time (RFC 3339) of every imported recording instead of its modification time
+  --wait                Wait for an import to finish and print the transcripts
+  -o, --output <FMT>    table or json [default: table]
+  -h, --help            Print this help";
+
+// 命令行参数
+#[derive(Debug, Default)]
+struct Options {
+    gateway_url: String,
+    token: Option<String>,
+    bridge: bool,
+    bridge_url: String,
+    bridge_token: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    reason: String,
+    file: Option<PathBuf>,
+    set: Vec<String>,
+    recorded_at: Option<DateTime<Utc>>,
+    wait: bool,
+    output: Option<OutputFormat>,
+    command: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let mut options = Options {
+            gateway_url: env("ETCH_GATEWAY_URL").unwrap_or_else(|| "http://localhost:10033".to_string()),
+            token: env("ETCH_TOKEN"),
+            bridge_url: env("ETCH_BRIDGE_URL").unwrap_or_else(|| "http://localhost:10031".to_string()),
+            bridge_token: env(BRIDGE_INTERNAL_TOKEN_ENV),
+            password: env("ETCH_PASSWORD"),
+            reason: "terminated_by_etchctl".to_string(),
+            ..Default::default()
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                "--bridge" => {
+                    options.bridge = true;
+                    continue;
+                }
+                "--wait" => {
+                    options.wait = true;
+                    continue;
+                }
+                flag if flag.starts_with('-') => {
+                    let value = args.next().with_context(|| format!("Missing value for {}\n\n{}", flag, USAGE))?;
+                    match flag {
+                        "--gateway" => options.gateway_url = value,
+                        "--token" => options.token = Some(value),
+                        "--bridge-url" => options.bridge_url = value,
+                        "--bridge-token" => options.bridge_token = Some(value),
+                        "--username" => options.username = Some(value),
+                        "--password" => options.password = Some(value),
+                        "--reason" => options.reason = value,
+                        "--file" => options.file = Some(PathBuf::from(value)),
+                        "--set" => options.set.push(value),
+                        "--recorded-at" => {
+                            let recorded_at = DateTime::parse_from_rfc3339(&value)
+                                .with_context(|| format!("Invalid --recorded-at {}, expected RFC 3339", value))?;
+                            options.recorded_at = Some(recorded_at.with_timezone(&Utc));
+                        }
+                        "-o" | "--output" => options.output = Some(value.parse()?),
+                        _ => bail!("Unknown option: {}\n\n{}", flag, USAGE),
+                    }
+                }
+                _ => options.command.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    fn format(&self) -> OutputFormat {
+        self.output.unwrap_or(OutputFormat::Table)
+    }
+
+    fn gateway(&self) -> Result<ApiClient> {
+        ApiClient::new(&self.gateway_url, self.token.clone())
+    }
+
+    // 需要登录的 API Gateway 接口
+    fn authenticated_gateway(&self) -> Result<ApiClient> {
+        if self.token.is_none() {
+            bail!("No access token, run `etchctl login` and set ETCH_TOKEN or pass --token");
+        }
+        self.gateway()
+    }
+
+    fn internal_bridge(&self) -> Result<ApiClient> {
+        let token = self
+            .bridge_token
+            .clone()
+            .with_context(|| format!("--bridge requires --bridge-token or {}", BRIDGE_INTERNAL_TOKEN_ENV))?;
+        ApiClient::new(&self.bridge_url, Some(token))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    let command: Vec<&str> = options.command.iter().map(String::as_str).collect();
+    match command.as_slice() {
+        ["login"] => login(&options).await,
+        ["sessions", "list"] => list_sessions(&options).await,
+        ["sessions", "end", session_id] => end_session(&options, session_id).await,
+        ["sessions", "tail", session_id] => tail_session(&options, session_id).await,
+        ["devices", "online"] => list_online_devices(&options).await,
+        ["devices", "push-config", device_id] => push_config(&options, device_id).await,
+        ["keys", "rotate"] => rotate_keys(&options).await,
+        ["imports", "create", device_id, files @ ..] if !files.is_empty() => create_import(&options, device_id, files).await,
+        ["imports", "status", job_id] => import_status(&options, job_id).await,
+        [] => bail!("Missing command\n\n{}", USAGE),
+        _ => bail!("Unknown command: {}\n\n{}", options.command.join(" "), USAGE),
+    }
+}
+
+async fn login(options: &Options) -> Result<()> {
+    let username = options.username.clone().context("login requires --username")?;
+    let password = match &options.password {
+        Some(password) => password.clone(),
+        None => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line).context("Failed to read password from stdin")?;
+            line.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let body = json!({ "username": username, "password": password });
+    let response: serde_json::Value = options.gateway()?.data(Method::POST, "/api/v1/auth/login", Some(&body)).await?;
+    match options.format() {
+        OutputFormat::Json => print_json(&response),
+        OutputFormat::Table => {
+            let token = response["token"].as_str().context("Login response has no token")?;
+            println!("{}", token);
+            Ok(())
+        }
+    }
+}
+
+async fn list_sessions(options: &Options) -> Result<()> {
+    let sessions: Vec<BridgeSessionState> = if options.bridge {
+        options.internal_bridge()?.json(Method::GET, "/internal/sessions/active", None).await?
+    } else {
+        options.authenticated_gateway()?.data(Method::GET, "/api/v1/sessions/live", None).await?
+    };
+    if options.format() == OutputFormat::Json {
+        return print_json(&sessions);
+    }
+
+    let mut table = Table::new(vec!["SESSION", "DEVICE", "STAGE", "STATUS", "BUFFERED", "FRAMES IN/OUT", "STARTED", "LAST ACTIVITY"]);
+    for session in &sessions {
+        table.row(vec![
+            session.session_id.clone(),
+            session.device_id.clone(),
+            label(&session.stage),
+            label(&session.status),
+            format!("{:.1}s", session.buffered_audio_seconds),
+            format!("{}/{}", session.audio_frames_received, session.audio_frames_sent),
+            session.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            session.last_activity.format("%H:%M:%S").to_string(),
+        ]);
+    }
+    println!("{}", table.render());
+    Ok(())
+}
+
+async fn end_session(options: &Options, session_id: &str) -> Result<()> {
+    let response = if options.bridge {
+        let path = format!("/internal/sessions/{}/end", session_id);
+        let body = json!({ "reason": options.reason });
+        options.internal_bridge()?.json::<serde_json::Value>(Method::POST, &path, Some(&body)).await?;
+        json!({ "message": "Session terminated", "session_id": session_id })
+    } else {
+        // 通过 API Gateway 结束的会话检查访问权限并记录审计日志
+        let path = format!("/api/v1/sessions/{}", session_id);
+        options.authenticated_gateway()?.data(Method::DELETE, &path, None).await?
+    };
+    match options.format() {
+        OutputFormat::Json => print_json(&response),
+        OutputFormat::Table => {
+            println!("Session {} ended", session_id);
+            Ok(())
+        }
+    }
+}
+
+async fn tail_session(options: &Options, session_id: &str) -> Result<()> {
+    let path = format!("/api/v1/sessions/{}/events", session_id);
+    let events = options.authenticated_gateway()?.events(&path).await?;
+    futures::pin_mut!(events);
+
+    // 会话结束后 API Gateway 关闭事件流
+    while let Some(event) = events.next().await {
+        let event = event?;
+        let data = serde_json::from_str(&event.data).unwrap_or(serde_json::Value::String(event.data));
+        match options.format() {
+            OutputFormat::Json => println!("{}", json!({ "event": event.event, "data": data })),
+            OutputFormat::Table => {
+                println!("{}  {:<12}  {}", chrono::Local::now().format("%H:%M:%S%.3f"), event.event, data)
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn list_online_devices(options: &Options) -> Result<()> {
+    let devices: Vec<BridgeOnlineDevice> = if options.bridge {
+        options.internal_bridge()?.json(Method::GET, "/internal/devices/online", None).await?
+    } else {
+        options.authenticated_gateway()?.data(Method::GET, "/api/v1/devices/live", None).await?
+    };
+    if options.format() == OutputFormat::Json {
+        return print_json(&devices);
+    }
+
+    let mut table = Table::new(vec!["DEVICE", "LAST HEARTBEAT", "ACTIVE SESSION"]);
+    for device in &devices {
+        table.row(vec![
+            device.device_id.clone(),
+            device.last_heartbeat.format("%Y-%m-%d %H:%M:%S").to_string(),
+            device.active_session_id.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    println!("{}", table.render());
+    Ok(())
+}
+
+async fn push_config(options: &Options, device_id: &str) -> Result<()> {
+    let config = device_configuration(options.file.as_ref(), &options.set)?;
+    let path = format!("/api/v1/devices/{}/config", device_id);
+    let response: serde_json::Value = options
+        .authenticated_gateway()?
+        .data(Method::POST, &path, Some(&serde_json::to_value(&config)?))
+        .await?;
+    match options.format() {
+        OutputFormat::Json => print_json(&response),
+        OutputFormat::Table => {
+            let signed = if response["signed"].as_bool() == Some(true) { " (signed)" } else { "" };
+            println!("Configuration sent to device {}{}", device_id, signed);
+            Ok(())
+        }
+    }
+}
+
+// 合并 --file 和 --set 中的配置字段，未知字段报错而不是静默忽略
+fn device_configuration(file: Option<&PathBuf>, set: &[String]) -> Result<DeviceConfiguration> {
+    let mut fields = match file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            match serde_json::from_str(&text).with_context(|| format!("{} is not valid JSON", path.display()))? {
+                serde_json::Value::Object(fields) => fields,
+                _ => bail!("{} must contain a JSON object", path.display()),
+            }
+        }
+        None => serde_json::Map::new(),
+    };
+    for assignment in set {
+        let (key, value) = assignment
+            .split_once('=')
+            .with_context(|| format!("Invalid --set {}, expected KEY=VALUE", assignment))?;
+        let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        fields.insert(key.trim().to_string(), value);
+    }
+    if fields.is_empty() {
+        bail!("push-config requires --file or at least one --set KEY=VALUE");
+    }
+
+    let known = serde_json::to_value(DeviceConfiguration {
+        volume: None,
+        location: None,
+        language: None,
+        timezone: None,
+        wake_word_enabled: None,
+        auto_reply_enabled: None,
+        custom_settings: None,
+    })?;
+    if let Some(unknown) = fields.keys().find(|key| known.get(key.as_str()).is_none()) {
+        bail!("Unknown device configuration field: {}", unknown);
+    }
+    serde_json::from_value(serde_json::Value::Object(fields)).context("Invalid device configuration")
+}
+
+async fn rotate_keys(options: &Options) -> Result<()> {
+    let result: serde_json::Value = options
+        .authenticated_gateway()?
+        .data(Method::POST, "/admin/encryption/rotate", None)
+        .await?;
+    match options.format() {
+        OutputFormat::Json => print_json(&result),
+        OutputFormat::Table => {
+            println!(
+                "Re-wrapped {} sessions ({} skipped, {} failed)",
+                result["rewrapped"], result["skipped"], result["failed"]
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn create_import(options: &Options, device_id: &str, files: &[&str]) -> Result<()> {
+    let mut form = reqwest::multipart::Form::new().text("device_id", device_id.to_string());
+    for file in files {
+        let path = PathBuf::from(file);
+        let data = std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let recorded_at = match options.recorded_at {
+            Some(recorded_at) => Some(recorded_at),
+            None => std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok().map(DateTime::<Utc>::from),
+        };
+        if let Some(recorded_at) = recorded_at {
+            form = form.text("recorded_at", recorded_at.to_rfc3339());
+        }
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| file.to_string());
+        form = form.part("file", reqwest::multipart::Part::bytes(data).file_name(name));
+    }
+
+    let bridge = options.internal_bridge()?;
+    let mut job: AudioImportJob = bridge.upload("/admin/imports", form).await?;
+    if options.wait {
+        let path = format!("/admin/imports/{}", job.id);
+        while job.finished_at.is_none() {
+            tokio::time::sleep(IMPORT_POLL_INTERVAL).await;
+            job = bridge.json(Method::GET, &path, None).await?;
+        }
+    }
+    print_import(options, &job)
+}
+
+async fn import_status(options: &Options, job_id: &str) -> Result<()> {
+    let path = format!("/admin/imports/{}", job_id);
+    let job: AudioImportJob = options.internal_bridge()?.json(Method::GET, &path, None).await?;
+    print_import(options, &job)
+}
+
+fn print_import(options: &Options, job: &AudioImportJob) -> Result<()> {
+    if options.format() == OutputFormat::Json {
+        return print_json(job);
+    }
+
+    let state = if job.finished_at.is_some() { "finished" } else { "running" };
+    println!("Import {} for device {} ({})", job.id, job.device_id, state);
+    let mut table = Table::new(vec!["FILE", "STATUS", "DURATION", "SESSION", "TRANSCRIPT"]);
+    for file in &job.files {
+        table.row(vec![
+            file.name.clone(),
+            label(&file.status),
+            file.duration_seconds.map(|seconds| format!("{:.1}s", seconds)).unwrap_or_else(|| "-".to_string()),
+            file.session_id.clone().unwrap_or_else(|| "-".to_string()),
+            file.transcript.clone().or_else(|| file.error.clone()).unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    println!("{}", table.render());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_configuration_from_set() {
+        let set = vec!["volume=60".to_string(), "language=zh-CN".to_string(), "wake_word_enabled=false".to_string()];
+        let config = device_configuration(None, &set).unwrap();
+        assert_eq!(config.volume, Some(60));
+        assert_eq!(config.language.as_deref(), Some("zh-CN"));
+        assert_eq!(config.wake_word_enabled, Some(false));
+
+        assert!(device_configuration(None, &["volum=60".to_string()]).is_err());
+        assert!(device_configuration(None, &["volume=loud".to_string()]).is_err());
+        assert!(device_configuration(None, &[]).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+// 输出格式：对齐的表格或 JSON
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            _ => bail!("Invalid output format: {} (expected table or json)", value),
+        }
+    }
+}
+
+/// 按列宽对齐的文本表格
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: Vec<&'static str>) -> Self {
+        Self { headers, rows: Vec::new() }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.headers.iter().map(|header| header.chars().count()).collect();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let line = |cells: Vec<&str>| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+
+        let mut lines = vec![line(self.headers.clone())];
+        lines.extend(self.rows.iter().map(|row| line(row.iter().map(String::as_str).collect())));
+        lines.join("\n")
+    }
+}
+
+pub fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// 枚举等值的 serde 字符串形式，如 SessionStage::Listening -> "Listening"
+pub fn label(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        Ok(other) => other.to_string(),
+        Err(_) => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_render() {
+        let mut table = Table::new(vec!["DEVICE", "SESSION"]);
+        table.row(vec!["echo-001".to_string(), "-".to_string()]);
+        table.row(vec!["a".to_string(), "s-1".to_string()]);
+        assert_eq!(table.render(), "DEVICE    SESSION\necho-001  -\na         s-1");
+    }
+}
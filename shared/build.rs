@@ -0,0 +1,23 @@
+// 构建脚本：启用 grpc feature 时根据 proto/ 生成 gRPC 代码
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    const PROTOS: &[&str] = &["../proto/echo_internal.proto", "../proto/echo_audio.proto"];
+    for proto in PROTOS {
+        println!("cargo:rerun-if-changed={}", proto);
+    }
+
+    // 未指定 PROTOC 时使用 vendored 的 protoc，避免依赖系统安装
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc is not available");
+        std::env::set_var("PROTOC", protoc);
+    }
+
+    tonic_build::configure()
+        .compile(PROTOS, &["../proto"])
+        .expect("failed to compile gRPC definitions");
+}
@@ -0,0 +1,15 @@
+//! 导出协议一致性样例，供固件和 Web UI 在各自的测试中使用
+//!
+//! 用法：cargo run -p echo-shared --features testkit --example export-protocol-fixtures -- <目录>
+
+use std::path::PathBuf;
+
+fn main() -> std::io::Result<()> {
+    let dir = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("protocol-fixtures"));
+    echo_shared::testkit::write_fixtures(&dir)?;
+    println!("Wrote protocol v{} fixtures to {}", echo_shared::PROTOCOL_VERSION, dir.display());
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! 告警规则判定
+//!
+//! 服务定期采集一次 `AlertSample`，`AlertEvaluator` 按 `alerts.rules` 判断每条规则：
+//! 条件持续满足指定时长后触发，条件解除时发送恢复通知；同一规则两次触发通知之间
+//! 至少间隔 `alerts.cooldown_seconds`，条件持续满足时每个冷却周期重复通知一次。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{AlertCondition, AlertConfig, AlertRule, AlertSeverity};
+
+/// 一次采集的运行状态
+#[derive(Debug, Clone, Default)]
+pub struct AlertSample {
+    /// EchoKit 连接总数
+    pub echokit_connections: usize,
+    /// 已建立的 EchoKit 连接数
+    pub echokit_connected: usize,
+    /// 累计创建成功的会话数
+    pub sessions_created: u64,
+    /// 累计创建失败的会话数
+    pub sessions_failed: u64,
+    /// 数据库连接池中正在使用的连接数
+    pub db_pool_active: u32,
+    /// 数据库连接池上限
+    pub db_pool_max: u32,
+}
+
+/// 告警状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// 告警通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: AlertSeverity,
+    pub state: AlertState,
+    pub service: String,
+    pub message: String,
+    /// 判定时的取值（断开的连接数、失败率或连接池使用率）
+    pub value: f64,
+    pub threshold: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct RuleState {
+    breached_since: Option<Instant>,
+    firing: bool,
+    last_notified: Option<Instant>,
+}
+
+/// 告警规则判定器
+pub struct AlertEvaluator {
+    service: String,
+    rules: Vec<AlertRule>,
+    states: Vec<RuleState>,
+    cooldown: Duration,
+    /// 会话计数的历史采样，用于计算窗口内的失败率
+    history: VecDeque<(Instant, u64, u64)>,
+    history_window: Duration,
+}
+
+impl AlertEvaluator {
+    pub fn new(service: &str, config: &AlertConfig) -> Self {
+        let history_window = config
+            .rules
+            .iter()
+            .filter_map(|rule| match rule.condition {
+                AlertCondition::SessionFailureRate { window_seconds, .. } => Some(window_seconds),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            service: service.to_string(),
+            rules: config.rules.clone(),
+            states: config.rules.iter().map(|_| RuleState::default()).collect(),
+            cooldown: Duration::from_secs(config.cooldown_seconds),
+            history: VecDeque::new(),
+            history_window: Duration::from_secs(history_window),
+        }
+    }
+
+    /// 判定所有规则，返回需要发送的通知
+    pub fn evaluate(&mut self, now: Instant, sample: &AlertSample) -> Vec<Alert> {
+        self.history.push_back((now, sample.sessions_created, sample.sessions_failed));
+        while let Some(&(at, _, _)) = self.history.front() {
+            if now.duration_since(at) > self.history_window && self.history.len() > 1 {
+                self.history.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let mut alerts = Vec::new();
+        for index in 0..self.rules.len() {
+            let check = self.check(&self.rules[index].condition, now, sample);
+            let rule = &self.rules[index];
+            let state = &mut self.states[index];
+
+            let Some(check) = check else {
+                state.breached_since = None;
+                if state.firing {
+                    state.firing = false;
+                    alerts.push(Alert {
+                        rule: rule.name.clone(),
+                        severity: rule.severity,
+                        state: AlertState::Resolved,
+                        service: self.service.clone(),
+                        message: format!("{} resolved", rule.name),
+                        value: 0.0,
+                        threshold: 0.0,
+                        timestamp: Utc::now(),
+                    });
+                }
+                continue;
+            };
+
+            let since = *state.breached_since.get_or_insert(now);
+            let held = now.duration_since(since) >= check.hold;
+            let cooled = state.last_notified.is_none_or(|at| now.duration_since(at) >= self.cooldown);
+            if held && cooled {
+                state.firing = true;
+                state.last_notified = Some(now);
+                alerts.push(Alert {
+                    rule: rule.name.clone(),
+                    severity: rule.severity,
+                    state: AlertState::Firing,
+                    service: self.service.clone(),
+                    message: check.message,
+                    value: check.value,
+                    threshold: check.threshold,
+                    timestamp: Utc::now(),
+                });
+            }
+        }
+        alerts
+    }
+
+    // 条件满足时返回判定结果
+    fn check(&self, condition: &AlertCondition, now: Instant, sample: &AlertSample) -> Option<Check> {
+        match *condition {
+            AlertCondition::EchokitDisconnected { for_seconds } => {
+                // 没有设备在线时没有 EchoKit 连接，不算断开
+                (sample.echokit_connections > 0 && sample.echokit_connected == 0).then(|| Check {
+                    hold: Duration::from_secs(for_seconds),
+                    value: sample.echokit_connections as f64,
+                    threshold: 0.0,
+                    message: format!(
+                        "All {} EchoKit connections down for at least {}s",
+                        sample.echokit_connections, for_seconds
+                    ),
+                })
+            }
+            AlertCondition::SessionFailureRate { threshold, window_seconds, min_sessions } => {
+                let window = Duration::from_secs(window_seconds);
+                let &(_, created, failed) = self
+                    .history
+                    .iter()
+                    .find(|(at, _, _)| now.duration_since(*at) <= window)?;
+                let created = sample.sessions_created.saturating_sub(created);
+                let failed = sample.sessions_failed.saturating_sub(failed);
+                let total = created + failed;
+                let rate = if total == 0 { 0.0 } else { failed as f64 / total as f64 };
+                (total >= min_sessions.max(1) && rate >= threshold).then(|| Check {
+                    hold: Duration::ZERO,
+                    value: rate,
+                    threshold,
+                    message: format!(
+                        "{} of {} sessions failed in the last {}s ({:.0}%)",
+                        failed,
+                        total,
+                        window_seconds,
+                        rate * 100.0
+                    ),
+                })
+            }
+            AlertCondition::DbPoolExhausted { utilization, for_seconds } => {
+                let used = if sample.db_pool_max == 0 {
+                    0.0
+                } else {
+                    sample.db_pool_active as f64 / sample.db_pool_max as f64
+                };
+                (used >= utilization).then(|| Check {
+                    hold: Duration::from_secs(for_seconds),
+                    value: used,
+                    threshold: utilization,
+                    message: format!(
+                        "Database pool {}/{} connections in use for at least {}s",
+                        sample.db_pool_active, sample.db_pool_max, for_seconds
+                    ),
+                })
+            }
+        }
+    }
+}
+
+struct Check {
+    // 条件需持续满足的时长
+    hold: Duration,
+    value: f64,
+    threshold: f64,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rules: Vec<AlertRule>) -> AlertConfig {
+        AlertConfig {
+            enabled: true,
+            check_interval_seconds: 10,
+            cooldown_seconds: 60,
+            webhook_urls: Vec::new(),
+            mqtt: false,
+            rules,
+        }
+    }
+
+    fn rule(condition: AlertCondition) -> AlertRule {
+        AlertRule { name: "rule".to_string(), severity: AlertSeverity::Critical, condition }
+    }
+
+    #[test]
+    fn test_fires_after_hold_and_respects_cooldown() {
+        let mut evaluator =
+            AlertEvaluator::new("echo-bridge", &config(vec![rule(AlertCondition::EchokitDisconnected { for_seconds: 30 })]));
+        let start = Instant::now();
+        let down = AlertSample { echokit_connections: 2, ..Default::default() };
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(evaluator.evaluate(at(0), &down).is_empty());
+        let alerts = evaluator.evaluate(at(30), &down);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].state, AlertState::Firing);
+
+        // 冷却期内不重复通知，冷却期过后仍未恢复则再次通知
+        assert!(evaluator.evaluate(at(60), &down).is_empty());
+        assert_eq!(evaluator.evaluate(at(90), &down).len(), 1);
+
+        let up = AlertSample { echokit_connections: 2, echokit_connected: 2, ..Default::default() };
+        let alerts = evaluator.evaluate(at(100), &up);
+        assert_eq!(alerts[0].state, AlertState::Resolved);
+        assert!(evaluator.evaluate(at(110), &up).is_empty());
+
+        // 恢复后立即再次断开仍受冷却期限制
+        assert!(evaluator.evaluate(at(105), &down).is_empty());
+        assert!(evaluator.evaluate(at(135), &down).is_empty());
+        assert_eq!(evaluator.evaluate(at(150), &down).len(), 1);
+    }
+
+    #[test]
+    fn test_session_failure_rate_uses_window() {
+        let mut evaluator = AlertEvaluator::new(
+            "echo-bridge",
+            &config(vec![rule(AlertCondition::SessionFailureRate { threshold: 0.5, window_seconds: 60, min_sessions: 4 })]),
+        );
+        let start = Instant::now();
+        let sample = |created, failed| AlertSample { sessions_created: created, sessions_failed: failed, ..Default::default() };
+
+        // 启动前累计的失败不计入
+        assert!(evaluator.evaluate(start, &sample(100, 50)).is_empty());
+        assert!(evaluator.evaluate(start + Duration::from_secs(10), &sample(101, 52)).is_empty());
+        let alerts = evaluator.evaluate(start + Duration::from_secs(20), &sample(102, 54));
+        assert_eq!(alerts.len(), 1);
+        assert!((alerts[0].value - 4.0 / 6.0).abs() < 1e-9);
+
+        // 窗口滑过失败高峰后恢复
+        let alerts = evaluator.evaluate(start + Duration::from_secs(100), &sample(140, 54));
+        assert_eq!(alerts[0].state, AlertState::Resolved);
+    }
+
+    #[test]
+    fn test_db_pool_utilization() {
+        let mut evaluator = AlertEvaluator::new(
+            "echo-bridge",
+            &config(vec![rule(AlertCondition::DbPoolExhausted { utilization: 0.9, for_seconds: 0 })]),
+        );
+        let sample = AlertSample { db_pool_active: 19, db_pool_max: 20, ..Default::default() };
+        let alerts = evaluator.evaluate(Instant::now(), &sample);
+        assert_eq!(alerts.len(), 1);
+        assert!((alerts[0].threshold - 0.9).abs() < 1e-9);
+    }
+}
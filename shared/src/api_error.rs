@@ -0,0 +1,97 @@
+//! HTTP 错误响应：统一将 EchoError 转换为 RFC 7807 (application/problem+json) 格式
+//!
+//! API Gateway 使用 axum 0.7、Bridge 使用 axum 0.8，分别启用 `axum07` / `axum08` feature 获得对应版本的
+//! `IntoResponse` 实现，两个服务返回的错误响应体和日志完全一致。
+
+use crate::types::EchoError;
+
+/// RFC 7807 响应的 Content-Type
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// HTTP handler 的错误类型
+#[derive(Debug)]
+pub struct ApiError(pub EchoError);
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self(EchoError::InvalidInput(message.into()))
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self(EchoError::Authentication(message.into()))
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self(EchoError::Authorization(message.into()))
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self(EchoError::NotFound(message.into()))
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self(EchoError::Conflict(message.into()))
+    }
+
+    pub fn service_unavailable(message: impl Into<String>) -> Self {
+        Self(EchoError::ServiceUnavailable(message.into()))
+    }
+
+    pub fn not_implemented(message: impl Into<String>) -> Self {
+        Self(EchoError::NotImplemented(message.into()))
+    }
+
+    // 响应体，服务端错误同时记录日志
+    #[cfg(any(feature = "axum07", feature = "axum08"))]
+    fn into_problem(self) -> crate::types::ProblemDetails {
+        let problem = self.0.to_problem();
+        if problem.status >= 500 {
+            tracing::error!("Request failed: {}", self.0);
+        }
+        problem
+    }
+}
+
+impl From<EchoError> for ApiError {
+    fn from(error: EchoError) -> Self {
+        Self(error)
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(error: sqlx::Error) -> Self {
+        Self(EchoError::Database(error.to_string()))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        // 保留底层的业务错误类型，其他错误按内部错误处理
+        match error.downcast::<EchoError>() {
+            Ok(error) => Self(error),
+            Err(error) => Self(EchoError::Internal(error)),
+        }
+    }
+}
+
+#[cfg(feature = "axum07")]
+impl axum07::response::IntoResponse for ApiError {
+    fn into_response(self) -> axum07::response::Response {
+        use axum07::http::{header, StatusCode};
+
+        let problem = self.into_problem();
+        let status = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, [(header::CONTENT_TYPE, PROBLEM_JSON)], axum07::Json(problem)).into_response()
+    }
+}
+
+#[cfg(feature = "axum08")]
+impl axum08::response::IntoResponse for ApiError {
+    fn into_response(self) -> axum08::response::Response {
+        use axum08::http::{header, StatusCode};
+
+        let problem = self.into_problem();
+        let status = StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, [(header::CONTENT_TYPE, PROBLEM_JSON)], axum08::Json(problem)).into_response()
+    }
+}
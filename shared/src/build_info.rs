@@ -0,0 +1,66 @@
+//! 构建信息与进程运行时长
+//!
+//! 构建信息由各服务的构建脚本（`built`）在编译期生成，运行时长从
+//! `mark_process_start()` 被调用时开始计算。
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+/// 记录进程启动时间，应在 main 开头调用
+pub fn mark_process_start() {
+    PROCESS_START.get_or_init(Instant::now);
+}
+
+/// 进程已运行的秒数
+pub fn process_uptime_seconds() -> u64 {
+    PROCESS_START.get_or_init(Instant::now).elapsed().as_secs()
+}
+
+/// 服务构建信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// crate 版本号
+    pub version: String,
+    /// 构建时的 git 提交（不在 git 仓库中构建时为空）
+    pub git_commit: Option<String>,
+    /// 工作区是否有未提交的修改
+    #[serde(default)]
+    pub git_dirty: bool,
+    /// 构建时间
+    pub build_timestamp: Option<DateTime<Utc>>,
+}
+
+impl BuildInfo {
+    /// `built_time` 为 `built` 生成的 RFC 2822 格式时间
+    pub fn new(version: &str, git_commit: Option<&str>, git_dirty: Option<bool>, built_time: &str) -> Self {
+        Self {
+            version: version.to_string(),
+            git_commit: git_commit.map(str::to_string),
+            git_dirty: git_dirty.unwrap_or(false),
+            build_timestamp: DateTime::parse_from_rfc2822(built_time)
+                .map(|time| time.with_timezone(&Utc))
+                .ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_parses_built_time() {
+        let info = BuildInfo::new("0.1.0", Some("d432d84"), None, "Fri, 16 Oct 2026 15:49:47 +0000");
+        assert_eq!(info.git_commit.as_deref(), Some("d432d84"));
+        assert!(!info.git_dirty);
+        assert_eq!(info.build_timestamp.unwrap().to_rfc3339(), "2026-10-16T15:49:47+00:00");
+
+        let info = BuildInfo::new("0.1.0", None, None, "not a date");
+        assert!(info.build_timestamp.is_none());
+    }
+}
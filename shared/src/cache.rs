@@ -3,6 +3,13 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use crate::{Device, DeviceStatus};
 
+pub mod degradation;
+pub mod key;
+pub mod local;
+pub use degradation::{CacheDegradation, CacheDegradationEvent, CacheOperation};
+pub use key::CacheKey;
+pub use local::{LocalCache, LocalCacheEvent};
+
 // Redis键名常量
 pub mod keys {
     pub const DEVICE_LIST_PREFIX: &str = "devices:list:";
@@ -10,7 +17,25 @@ pub mod keys {
     pub const DEVICE_CONFIG_PREFIX: &str = "device:config:";
     pub const USER_SESSION_PREFIX: &str = "user:session:";
     pub const USER_TOKEN_PREFIX: &str = "user:token:";
+    pub const USER_TOKEN_VERSION_PREFIX: &str = "user:token_version:";
     pub const MQTT_CONNECTION_PREFIX: &str = "mqtt:conn:";
+    pub const PASSWORD_RESET_PREFIX: &str = "password:reset:";
+    pub const RESPONSE_CACHE_PREFIX: &str = "http:response:";
+    pub const DEVICE_PRESENCE_PREFIX: &str = "device:presence:";
+    pub const DEVICE_RECORD_PREFIX: &str = "device:record:";
+    pub const USER_DAILY_SESSIONS_PREFIX: &str = "quota:sessions:user:";
+    pub const DEVICE_ACTIVE_SESSIONS_PREFIX: &str = "quota:sessions:device:";
+    pub const DEVICE_LOCK_PREFIX: &str = "lock:device:";
+    pub const WS_TICKET_PREFIX: &str = "ws:ticket:";
+    pub const ECHOKIT_HELLO_PREFIX: &str = "echokit:hello:";
+    /// 缓存失效通知的 pub/sub 频道
+    pub const CACHE_INVALIDATION_CHANNEL: &str = "cache:invalidate";
+}
+
+// API Gateway 只读接口响应缓存的作用域，写入方据此通知对应接口的缓存失效
+pub mod response_scopes {
+    pub const DEVICES: &str = "devices";
+    pub const SESSIONS: &str = "sessions";
 }
 
 // 缓存项过期时间（秒）
@@ -20,7 +45,11 @@ pub mod ttl {
     pub const DEVICE_CONFIG: u64 = 600;     // 设备配置缓存10分钟
     pub const USER_SESSION: u64 = 3600;     // 用户会话1小时
     pub const USER_TOKEN: u64 = 86400;      // 用户Token 24小时
+    pub const USER_TOKEN_VERSION: u64 = 60; // 用户令牌版本号60秒，限制重置密码后旧令牌的残留时间
     pub const MQTT_CONNECTION: u64 = 120;   // MQTT连接状态2分钟
+    pub const PASSWORD_RESET: u64 = 900;    // 密码重置令牌15分钟
+    pub const HTTP_RESPONSE: u64 = 15;      // 只读接口响应缓存15秒
+    pub const DEVICE_RECORD: u64 = 30;      // 按ID读取的设备30秒
 }
 
 // 缓存的数据结构
@@ -37,6 +66,97 @@ pub struct DeviceStatusCache {
     pub is_online: bool,
 }
 
+// 设备实时在线状态，由设备所连接的 Bridge 副本写入并定期刷新，过期即视为离线
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DevicePresence {
+    pub device_id: String,
+    pub last_heartbeat: DateTime<Utc>,
+    /// 正在进行的会话
+    pub session_id: Option<String>,
+    /// 写入该记录的 Bridge 副本
+    pub instance_id: String,
+}
+
+impl DevicePresence {
+    pub fn key(device_id: &str) -> String {
+        CacheKey::device_presence(device_id).into()
+    }
+}
+
+// 用实时在线状态覆盖数据库中的设备状态：有记录时在线，没有记录时离线
+// 维护、故障、待激活等状态不受影响
+pub fn apply_presence(device: &mut Device, presence: Option<&DevicePresence>) {
+    match presence {
+        Some(presence) => {
+            device.is_online = true;
+            if device.status == DeviceStatus::Offline {
+                device.status = DeviceStatus::Online;
+            }
+            device.last_seen = device.last_seen.max(presence.last_heartbeat);
+            device.current_session_id = presence.session_id.clone();
+        }
+        None => {
+            device.is_online = false;
+            if device.status == DeviceStatus::Online {
+                device.status = DeviceStatus::Offline;
+            }
+            device.current_session_id = None;
+        }
+    }
+}
+
+// 缓存失效通知
+//
+// 任何写入方（Bridge、运维脚本等）发布到 keys::CACHE_INVALIDATION_CHANNEL，所有订阅的服务实例各自执行：
+// 删除 keys 中的缓存键，并使 scopes 中的响应缓存作用域失效。重复执行没有副作用。
+// 直接修改数据库后也可以手动发布，例如：
+// PUBLISH cache:invalidate '{"scopes":["devices"]}'
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheInvalidation {
+    #[serde(default)]
+    pub keys: Vec<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl CacheInvalidation {
+    pub fn keys(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { keys: keys.into_iter().map(Into::into).collect(), scopes: Vec::new() }
+    }
+
+    pub fn scopes(scopes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self { keys: Vec::new(), scopes: scopes.into_iter().map(Into::into).collect() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.scopes.is_empty()
+    }
+
+    // 发布通知，返回收到通知的订阅者数量
+    pub async fn publish<C: redis::aio::ConnectionLike>(&self, conn: &mut C) -> redis::RedisResult<usize> {
+        let payload = serde_json::to_string(self)
+            .map_err(|e| redis::RedisError::from((redis::ErrorKind::TypeError, "Invalid cache invalidation", e.to_string())))?;
+        redis::cmd("PUBLISH")
+            .arg(keys::CACHE_INVALIDATION_CHANNEL)
+            .arg(payload)
+            .query_async(conn)
+            .await
+    }
+
+    // 解析收到的通知，格式不正确时返回 None
+    pub fn from_message(message: &redis::Msg) -> Option<Self> {
+        let payload: String = message.get_payload().ok()?;
+        serde_json::from_str(&payload).ok()
+    }
+}
+
+// 订阅缓存失效通知，返回的连接只用于接收通知
+pub async fn subscribe_invalidations(client: &redis::Client) -> redis::RedisResult<redis::aio::PubSub> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(keys::CACHE_INVALIDATION_CHANNEL).await?;
+    Ok(pubsub)
+}
+
 // 设备配置缓存
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceConfigCache {
@@ -116,32 +236,63 @@ impl RedisCache {
 
     // 生成设备列表缓存键
     pub fn device_list_key(owner_id: &str) -> String {
-        format!("{}{}", keys::DEVICE_LIST_PREFIX, owner_id)
+        CacheKey::device_list(owner_id, None).into()
     }
 
     // 生成设备状态缓存键
     pub fn device_status_key(device_id: &str) -> String {
-        format!("{}{}", keys::DEVICE_STATUS_PREFIX, device_id)
+        CacheKey::device_status(device_id).into()
     }
 
     // 生成设备配置缓存键
     pub fn device_config_key(device_id: &str) -> String {
-        format!("{}{}", keys::DEVICE_CONFIG_PREFIX, device_id)
+        CacheKey::device_config(device_id).into()
     }
 
     // 生成用户会话缓存键
     pub fn user_session_key(user_id: &str) -> String {
-        format!("{}{}", keys::USER_SESSION_PREFIX, user_id)
+        CacheKey::user_session(user_id).into()
     }
 
     // 生成用户Token缓存键
     pub fn user_token_key(token: &str) -> String {
-        format!("{}{}", keys::USER_TOKEN_PREFIX, token)
+        CacheKey::user_token(token).into()
     }
 
     // 生成MQTT连接缓存键
     pub fn mqtt_connection_key(client_id: &str) -> String {
-        format!("{}{}", keys::MQTT_CONNECTION_PREFIX, client_id)
+        CacheKey::mqtt_connection(client_id).into()
+    }
+
+    // 写入缓存，过期时间由键决定
+    pub async fn put<T: Serialize + Sync>(&self, key: &CacheKey, value: &T) -> Result<(), redis::RedisError> {
+        match key.ttl() {
+            Some(ttl_seconds) => self.set(key.as_str(), value, ttl_seconds).await,
+            None => {
+                let mut conn = self.get_connection().await?;
+                let json_str = serde_json::to_string(value)
+                    .map_err(|e| redis::RedisError::from((redis::ErrorKind::TypeError, "JSON serialization failed", e.to_string())))?;
+                redis::cmd("SET").arg(key).arg(json_str).query_async(&mut conn).await
+            }
+        }
+    }
+
+    // 旁路缓存读取：命中时直接返回，未命中时调用 load 并写入缓存（结果为 None 时不缓存）
+    pub async fn get_or_load<T, E, F, Fut>(&self, key: &CacheKey, load: F) -> Result<Option<T>, E>
+    where
+        T: Serialize + for<'de> Deserialize<'de> + Sync,
+        E: From<redis::RedisError>,
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Option<T>, E>>,
+    {
+        if let Some(value) = self.get::<T>(key.as_str()).await? {
+            return Ok(Some(value));
+        }
+        let value = load().await?;
+        if let Some(value) = &value {
+            self.put(key, value).await?;
+        }
+        Ok(value)
     }
 }
 
@@ -284,4 +435,60 @@ pub enum CacheError {
 
     #[error("Cache operation failed: {0}")]
     OperationFailed(String),
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DeviceType;
+
+    #[test]
+    fn test_cache_invalidation_format() {
+        let invalidation: CacheInvalidation = serde_json::from_str(r#"{"scopes":["devices"]}"#).unwrap();
+        assert_eq!(invalidation, CacheInvalidation::scopes([response_scopes::DEVICES]));
+        assert!(!invalidation.is_empty());
+        assert!(serde_json::from_str::<CacheInvalidation>("{}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_presence() {
+        let last_seen = Utc::now() - chrono::Duration::minutes(10);
+        let mut device = Device {
+            id: "device-1".to_string(),
+            name: "Kitchen".to_string(),
+            device_type: DeviceType::Speaker,
+            status: DeviceStatus::Offline,
+            location: String::new(),
+            firmware_version: "1.0.0".to_string(),
+            battery_level: 100,
+            volume: 50,
+            last_seen,
+            is_online: false,
+            owner: "user-1".to_string(),
+            echokit_server_url: None,
+            serial_number: None,
+            mac_address: None,
+            version: 1,
+            current_session_id: None,
+        };
+        let presence = DevicePresence {
+            device_id: "device-1".to_string(),
+            last_heartbeat: Utc::now(),
+            session_id: Some("session-1".to_string()),
+            instance_id: "bridge-1".to_string(),
+        };
+
+        apply_presence(&mut device, Some(&presence));
+        assert_eq!((device.status.clone(), device.is_online), (DeviceStatus::Online, true));
+        assert_eq!(device.last_seen, presence.last_heartbeat);
+        assert_eq!(device.current_session_id.as_deref(), Some("session-1"));
+
+        apply_presence(&mut device, None);
+        assert_eq!((device.status.clone(), device.is_online), (DeviceStatus::Offline, false));
+        assert_eq!(device.current_session_id, None);
+
+        // 维护中的设备保持原状态
+        device.status = DeviceStatus::Maintenance;
+        apply_presence(&mut device, Some(&presence));
+        assert_eq!((device.status.clone(), device.is_online), (DeviceStatus::Maintenance, true));
+    }
+}
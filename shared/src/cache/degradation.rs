@@ -0,0 +1,188 @@
+//! Redis 不可用时的缓存降级
+//!
+//! 连续 `failure_threshold` 次连接类错误（连接失败、断开、超时）后进入降级模式，不再访问 Redis：
+//! - 缓存读取视为未命中，调用方直接查询数据库
+//! - 缓存写入（包括删除和失效）直接跳过并记录指标，Redis 中的旧数据在 TTL 到期后失效
+//! - 必须依赖 Redis 的操作（如一次性令牌）直接失败，不再等待连接超时
+//!
+//! 降级期间由后台任务每隔 `probe_interval_seconds` 调用一次 `probe`，PING 成功后恢复使用缓存。
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+use redis::RedisError;
+
+use crate::types::CacheDegradationConfig;
+
+/// 降级期间跳过的缓存操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOperation {
+    Read,
+    Write,
+}
+
+impl CacheOperation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheOperation::Read => "read",
+            CacheOperation::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheDegradationEvent {
+    /// 进入降级模式
+    Degraded,
+    /// 探测成功，恢复使用缓存
+    Recovered,
+    /// 降级期间跳过了一次缓存操作
+    Skipped(CacheOperation),
+}
+
+type DegradationListener = Box<dyn Fn(CacheDegradationEvent) + Send + Sync>;
+
+pub struct CacheDegradation {
+    enabled: bool,
+    failure_threshold: u32,
+    probe_interval: Duration,
+    connect_timeout: Duration,
+    degraded: AtomicBool,
+    failures: AtomicU32,
+    listener: Option<DegradationListener>,
+}
+
+impl CacheDegradation {
+    pub fn new(config: &CacheDegradationConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            failure_threshold: config.failure_threshold.max(1),
+            probe_interval: Duration::from_secs(config.probe_interval_seconds.max(1)),
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            degraded: AtomicBool::new(false),
+            failures: AtomicU32::new(0),
+            listener: None,
+        }
+    }
+
+    /// 进入、退出降级模式和跳过缓存操作时回调（用于记录日志和指标）
+    pub fn with_listener(mut self, listener: impl Fn(CacheDegradationEvent) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
+
+    pub fn probe_interval(&self) -> Duration {
+        self.probe_interval
+    }
+
+    /// 建立 Redis 连接的超时
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// 降级期间返回 true 并记录跳过的操作，调用方应跳过本次缓存读写
+    pub fn skip(&self, operation: CacheOperation) -> bool {
+        if !self.is_degraded() {
+            return false;
+        }
+        self.emit(CacheDegradationEvent::Skipped(operation));
+        true
+    }
+
+    pub fn record_success(&self) {
+        self.failures.store(0, Ordering::Relaxed);
+    }
+
+    /// 记录 Redis 错误，只有连接类错误计入，命令或数据错误说明 Redis 可用
+    pub fn record_failure(&self, error: &RedisError) {
+        if !is_connection_error(error) {
+            return;
+        }
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.failure_threshold {
+            self.degrade();
+        }
+    }
+
+    /// 进入降级模式（如启动时 Redis 不可用），未启用降级时不生效
+    pub fn degrade(&self) {
+        if self.enabled && !self.degraded.swap(true, Ordering::AcqRel) {
+            self.emit(CacheDegradationEvent::Degraded);
+        }
+    }
+
+    /// 降级期间 PING Redis，成功时恢复使用缓存；返回当前是否可用
+    pub async fn probe(&self, client: &redis::Client) -> bool {
+        if !self.is_degraded() {
+            return true;
+        }
+        let ping = async {
+            let mut conn = client.get_multiplexed_async_connection().await?;
+            redis::cmd("PING").query_async::<_, ()>(&mut conn).await
+        };
+        match tokio::time::timeout(self.connect_timeout, ping).await {
+            Ok(Ok(())) => {
+                self.failures.store(0, Ordering::Relaxed);
+                if self.degraded.swap(false, Ordering::AcqRel) {
+                    self.emit(CacheDegradationEvent::Recovered);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn emit(&self, event: CacheDegradationEvent) {
+        if let Some(listener) = &self.listener {
+            listener(event);
+        }
+    }
+}
+
+/// 连接失败、断开或超时，说明 Redis 不可用
+pub fn is_connection_error(error: &RedisError) -> bool {
+    error.is_io_error() || error.is_connection_dropped() || error.is_connection_refusal() || error.is_timeout()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_degrades_after_consecutive_connection_errors() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let degradation = CacheDegradation::new(&CacheDegradationConfig { failure_threshold: 2, ..Default::default() })
+            .with_listener(move |event| recorded.lock().unwrap().push(event));
+
+        let refused = RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        let command = RedisError::from((redis::ErrorKind::ResponseError, "WRONGTYPE"));
+
+        // 命令错误和成功调用之间的失败不计入
+        degradation.record_failure(&refused);
+        degradation.record_success();
+        degradation.record_failure(&command);
+        degradation.record_failure(&refused);
+        assert!(!degradation.is_degraded());
+        assert!(!degradation.skip(CacheOperation::Read));
+
+        degradation.record_failure(&refused);
+        assert!(degradation.is_degraded());
+        assert!(degradation.skip(CacheOperation::Write));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![CacheDegradationEvent::Degraded, CacheDegradationEvent::Skipped(CacheOperation::Write)]
+        );
+
+        // 未启用降级时始终访问 Redis
+        let disabled = CacheDegradation::new(&CacheDegradationConfig { enabled: false, ..Default::default() });
+        disabled.degrade();
+        assert!(!disabled.is_degraded());
+    }
+}
@@ -0,0 +1,214 @@
+//! 类型化缓存键
+//!
+//! 缓存键由 `keys` 中的前缀和若干段组成，段之间用 `:` 分隔，可选参数写成 `name=value`，未设置的参数不写入。
+//! 段和参数值中的 `%`、`:` 转义为 `%25`、`%3A`，因此任意取值都不会拼出其他键；
+//! 参数按调用顺序写入，不依赖 Debug 输出，键在版本之间保持稳定。
+//! 每种键对应 `ttl` 中的一个过期时间，写入缓存时使用 `CacheKey::ttl`。
+
+use std::fmt;
+
+use super::{keys, ttl};
+use crate::database::DeviceFilter;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    key: String,
+    ttl: Option<u64>,
+}
+
+impl CacheKey {
+    fn new(prefix: &str, ttl: Option<u64>) -> Self {
+        Self { key: prefix.to_string(), ttl }
+    }
+
+    /// 按 ID 读取的设备
+    pub fn device(device_id: &str) -> Self {
+        Self::new(keys::DEVICE_RECORD_PREFIX, Some(ttl::DEVICE_RECORD)).segment(device_id)
+    }
+
+    /// 用户的设备列表，过滤条件作为参数写入键中
+    pub fn device_list(owner_id: &str, filter: Option<&DeviceFilter>) -> Self {
+        let key = Self::new(keys::DEVICE_LIST_PREFIX, Some(ttl::DEVICE_LIST)).segment(owner_id);
+        match filter {
+            Some(filter) => key
+                .param("owner", filter.owner_id.as_ref())
+                .param("type", filter.device_type.as_ref())
+                .param("status", filter.status.as_ref())
+                .param("online", filter.is_online)
+                .param("limit", filter.limit)
+                .param("offset", filter.offset),
+            None => key,
+        }
+    }
+
+    pub fn device_status(device_id: &str) -> Self {
+        Self::new(keys::DEVICE_STATUS_PREFIX, Some(ttl::DEVICE_STATUS)).segment(device_id)
+    }
+
+    pub fn device_config(device_id: &str) -> Self {
+        Self::new(keys::DEVICE_CONFIG_PREFIX, Some(ttl::DEVICE_CONFIG)).segment(device_id)
+    }
+
+    /// Bridge 写入的设备实时在线状态，过期时间由 `[bridge.presence]` 配置
+    pub fn device_presence(device_id: &str) -> Self {
+        Self::new(keys::DEVICE_PRESENCE_PREFIX, None).segment(device_id)
+    }
+
+    pub fn user_session(user_id: &str) -> Self {
+        Self::new(keys::USER_SESSION_PREFIX, Some(ttl::USER_SESSION)).segment(user_id)
+    }
+
+    pub fn user_token(token: &str) -> Self {
+        Self::new(keys::USER_TOKEN_PREFIX, Some(ttl::USER_TOKEN)).segment(token)
+    }
+
+    pub fn user_token_version(user_id: &str) -> Self {
+        Self::new(keys::USER_TOKEN_VERSION_PREFIX, Some(ttl::USER_TOKEN_VERSION)).segment(user_id)
+    }
+
+    pub fn mqtt_connection(client_id: &str) -> Self {
+        Self::new(keys::MQTT_CONNECTION_PREFIX, Some(ttl::MQTT_CONNECTION)).segment(client_id)
+    }
+
+    pub fn password_reset(token: &str) -> Self {
+        Self::new(keys::PASSWORD_RESET_PREFIX, Some(ttl::PASSWORD_RESET)).segment(token)
+    }
+
+    /// 用户当天（UTC，`YYYYMMDD`）创建的会话数，过期时间由 `SessionLimits` 设置
+    pub fn user_daily_sessions(user_id: &str, day: &str) -> Self {
+        Self::new(keys::USER_DAILY_SESSIONS_PREFIX, None).segment(user_id).segment(day)
+    }
+
+    /// 设备进行中的会话（有序集合，分数为租约到期时间），过期时间由 `SessionLimits` 设置
+    pub fn device_active_sessions(device_id: &str) -> Self {
+        Self::new(keys::DEVICE_ACTIVE_SESSIONS_PREFIX, None).segment(device_id)
+    }
+
+    /// 设备级操作的分布式锁，过期时间由 `DistributedLock` 设置
+    pub fn device_lock(device_id: &str, operation: &str) -> Self {
+        Self::new(keys::DEVICE_LOCK_PREFIX, None).segment(device_id).segment(operation)
+    }
+
+    /// WebSocket 一次性连接票据，过期时间由 `[redis.ws_tickets]` 配置
+    pub fn ws_ticket(ticket: &str) -> Self {
+        Self::new(keys::WS_TICKET_PREFIX, None).segment(ticket)
+    }
+
+    /// Bridge 保存的 EchoKit Server 欢迎语，`version` 为保存格式的版本，过期时间由 `[bridge.hello_persistence]` 配置
+    pub fn echokit_hello(server_url: &str, version: u32) -> Self {
+        Self::new(keys::ECHOKIT_HELLO_PREFIX, None).segment(format!("v{}", version)).segment(server_url)
+    }
+
+    /// 响应缓存作用域的版本号（不过期）
+    pub fn response_generation(scope: &str) -> Self {
+        Self::new(keys::RESPONSE_CACHE_PREFIX, None).segment(scope).segment("generation")
+    }
+
+    /// 响应缓存，`variant` 区分同一作用域下的不同请求
+    pub fn response(scope: &str, generation: u64, variant: &str) -> Self {
+        Self::new(keys::RESPONSE_CACHE_PREFIX, Some(ttl::HTTP_RESPONSE))
+            .segment(scope)
+            .segment(generation)
+            .segment(variant)
+    }
+
+    /// 追加一段
+    pub fn segment(mut self, value: impl fmt::Display) -> Self {
+        self.separate();
+        push_escaped(&mut self.key, &value.to_string());
+        self
+    }
+
+    /// 追加参数 `name=value`，值为 None 时不写入
+    pub fn param(mut self, name: &str, value: Option<impl fmt::Display>) -> Self {
+        if let Some(value) = value {
+            self.separate();
+            self.key.push_str(name);
+            self.key.push('=');
+            push_escaped(&mut self.key, &value.to_string());
+        }
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+
+    /// 写入缓存时使用的过期时间（秒），None 表示不过期或由写入方决定
+    pub fn ttl(&self) -> Option<u64> {
+        self.ttl
+    }
+
+    // 前缀以 `:` 结尾，第一段直接拼接在前缀后面
+    fn separate(&mut self) {
+        if !self.key.ends_with(':') {
+            self.key.push(':');
+        }
+    }
+}
+
+fn push_escaped(key: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '%' => key.push_str("%25"),
+            ':' => key.push_str("%3A"),
+            c => key.push(c),
+        }
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key)
+    }
+}
+
+impl AsRef<str> for CacheKey {
+    fn as_ref(&self) -> &str {
+        &self.key
+    }
+}
+
+impl From<CacheKey> for String {
+    fn from(key: CacheKey) -> Self {
+        key.key
+    }
+}
+
+impl redis::ToRedisArgs for CacheKey {
+    fn write_redis_args<W: ?Sized + redis::RedisWrite>(&self, out: &mut W) {
+        out.write_arg(self.key.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceStatus, DeviceType};
+
+    #[test]
+    fn test_cache_key_format() {
+        assert_eq!(CacheKey::device("dev-1").as_str(), "device:record:dev-1");
+        assert_eq!(CacheKey::device("dev-1").ttl(), Some(ttl::DEVICE_RECORD));
+        assert_eq!(CacheKey::device_presence("dev-1").as_str(), "device:presence:dev-1");
+        assert_eq!(CacheKey::response_generation("devices").as_str(), "http:response:devices:generation");
+        assert_eq!(CacheKey::response("devices", 3, "list:page=1").as_str(), "http:response:devices:3:list%3Apage=1");
+
+        // 取值中的分隔符被转义，不会与其他键冲突
+        assert_ne!(CacheKey::device_status("a:b").as_str(), CacheKey::device_status("a").segment("b").as_str());
+
+        let filter = DeviceFilter {
+            owner_id: None,
+            device_type: Some(DeviceType::Speaker),
+            status: Some(DeviceStatus::Online),
+            is_online: None,
+            limit: Some(20),
+            offset: None,
+        };
+        assert_eq!(
+            CacheKey::device_list("user-1", Some(&filter)).as_str(),
+            format!("devices:list:user-1:type={}:status={}:limit=20", DeviceType::Speaker, DeviceStatus::Online)
+        );
+        assert_eq!(CacheKey::device_list("user-1", None).as_str(), "devices:list:user-1");
+    }
+}
@@ -0,0 +1,293 @@
+//! 进程内缓存
+//!
+//! 放在 Redis 前面缓存热点键（如按 ID 读取的设备）：条目数有上限，超出时淘汰最久未访问的条目；
+//! 过期时间很短，其他实例的修改通过缓存失效通知（`CacheInvalidation`）删除。
+//! 同一个键同时未命中时只有一个调用方执行加载，其余调用方等待并直接使用加载结果（single-flight），
+//! 避免条目过期瞬间的并发请求同时访问 Redis 和数据库。
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::types::LocalCacheConfig;
+
+/// 缓存访问结果，用于记录指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalCacheEvent {
+    /// 命中
+    Hit,
+    /// 未命中，由本调用方加载
+    Miss,
+    /// 未命中，等待其他调用方加载后命中
+    Coalesced,
+    /// 条目数超出上限，淘汰最久未访问的条目
+    Eviction,
+}
+
+impl LocalCacheEvent {
+    /// 指标标签
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LocalCacheEvent::Hit => "hit",
+            LocalCacheEvent::Miss => "miss",
+            LocalCacheEvent::Coalesced => "coalesced",
+            LocalCacheEvent::Eviction => "eviction",
+        }
+    }
+}
+
+type EventListener = Box<dyn Fn(&str, LocalCacheEvent) + Send + Sync>;
+
+/// 带过期时间的 LRU 缓存
+pub struct LocalCache<V> {
+    name: String,
+    enabled: bool,
+    capacity: usize,
+    ttl: Duration,
+    inner: Mutex<Inner<V>>,
+    // 正在加载的键：key -> 加载锁，同一个键的调用方排队，第一个调用方加载
+    loading: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    listener: Option<EventListener>,
+}
+
+struct Inner<V> {
+    entries: HashMap<String, Entry<V>>,
+    // 访问顺序：访问序号 -> 键，序号最小的条目最久未访问
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    // 删除次数，加载期间发生过删除时不写入加载结果，避免写回已失效的值
+    removals: u64,
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+    tick: u64,
+}
+
+impl<V: Clone> LocalCache<V> {
+    pub fn new(name: impl Into<String>, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            name: name.into(),
+            enabled: capacity > 0 && !ttl.is_zero(),
+            capacity,
+            ttl,
+            inner: Mutex::new(Inner { entries: HashMap::new(), order: BTreeMap::new(), tick: 0, removals: 0 }),
+            loading: Mutex::new(HashMap::new()),
+            listener: None,
+        }
+    }
+
+    /// 按配置创建，`enabled = false` 时不缓存，每次都调用加载函数
+    pub fn from_config(name: impl Into<String>, config: &LocalCacheConfig) -> Self {
+        let mut cache = Self::new(name, config.capacity, Duration::from_secs(config.ttl_seconds));
+        cache.enabled &= config.enabled;
+        cache
+    }
+
+    /// 访问缓存时的回调（缓存名称，访问结果），用于记录指标
+    pub fn with_listener(mut self, listener: impl Fn(&str, LocalCacheEvent) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 读取未过期的条目
+    pub fn get(&self, key: &str) -> Option<V> {
+        if !self.enabled {
+            return None;
+        }
+        let mut inner = self.lock();
+        let tick = inner.next_tick();
+        let entry = inner.entries.get_mut(key)?;
+        if entry.expires_at <= Instant::now() {
+            inner.remove(key);
+            return None;
+        }
+        let previous = std::mem::replace(&mut entry.tick, tick);
+        let value = entry.value.clone();
+        inner.order.remove(&previous);
+        inner.order.insert(tick, key.to_string());
+        Some(value)
+    }
+
+    /// 写入条目，超出上限时淘汰最久未访问的条目
+    pub fn insert(&self, key: &str, value: V) {
+        if !self.enabled {
+            return;
+        }
+        let evicted = {
+            let mut inner = self.lock();
+            inner.insert(key, value, Instant::now() + self.ttl);
+            let mut evicted = 0;
+            while inner.entries.len() > self.capacity {
+                let Some((_, oldest)) = inner.order.pop_first() else { break };
+                inner.entries.remove(&oldest);
+                evicted += 1;
+            }
+            evicted
+        };
+        for _ in 0..evicted {
+            self.emit(LocalCacheEvent::Eviction);
+        }
+    }
+
+    /// 删除条目（缓存失效）
+    pub fn remove(&self, key: &str) {
+        let mut inner = self.lock();
+        inner.remove(key);
+        inner.removals += 1;
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.order.clear();
+        inner.removals += 1;
+    }
+
+    /// 读取条目，未命中时调用 `load` 加载并写入缓存（加载结果为 None 或出错时不缓存）
+    ///
+    /// 同一个键的并发调用只有一个执行 `load`，其余等待后读取缓存；加载失败时下一个等待者重新加载
+    pub async fn get_or_load<E, F, Fut>(&self, key: &str, load: F) -> Result<Option<V>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<V>, E>>,
+    {
+        if !self.enabled {
+            return load().await;
+        }
+        if let Some(value) = self.get(key) {
+            self.emit(LocalCacheEvent::Hit);
+            return Ok(Some(value));
+        }
+
+        let lock = self
+            .loading
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        let result = {
+            let _guard = lock.lock().await;
+            // 等待期间其他调用方可能已经加载完成
+            if let Some(value) = self.get(key) {
+                self.emit(LocalCacheEvent::Coalesced);
+                Ok(Some(value))
+            } else {
+                self.emit(LocalCacheEvent::Miss);
+                let removals = self.lock().removals;
+                let result = load().await;
+                if let Ok(Some(value)) = &result {
+                    if self.lock().removals == removals {
+                        self.insert(key, value.clone());
+                    }
+                }
+                result
+            }
+        };
+
+        // 没有其他调用方在等待时移除加载锁（映射表和本调用方各持有一个引用）
+        let mut loading = self.loading.lock().unwrap_or_else(|e| e.into_inner());
+        if Arc::strong_count(&lock) == 2 {
+            loading.remove(key);
+        }
+        result
+    }
+
+    fn emit(&self, event: LocalCacheEvent) {
+        if let Some(listener) = &self.listener {
+            listener(&self.name, event);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<V>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<V> Inner<V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn insert(&mut self, key: &str, value: V, expires_at: Instant) {
+        let tick = self.next_tick();
+        if let Some(previous) = self.entries.insert(key.to_string(), Entry { value, expires_at, tick }) {
+            self.order.remove(&previous.tick);
+        }
+        self.order.insert(tick, key.to_string());
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let evictions = Arc::new(AtomicUsize::new(0));
+        let counter = evictions.clone();
+        let cache = LocalCache::new("devices", 2, Duration::from_secs(60)).with_listener(move |_, event| {
+            if event == LocalCacheEvent::Eviction {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get("a"), Some(1));
+        cache.insert("c", 3);
+
+        assert_eq!((cache.get("a"), cache.get("b"), cache.get("c")), (Some(1), None, Some(3)));
+        assert_eq!(evictions.load(Ordering::SeqCst), 1);
+
+        cache.remove("a");
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_coalesces_concurrent_loads() {
+        let cache = LocalCache::new("devices", 10, Duration::from_secs(60));
+        let loads = AtomicUsize::new(0);
+        let load = || async {
+            loads.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Ok::<_, ()>(Some("device".to_string()))
+        };
+
+        let (first, second) = tokio::join!(cache.get_or_load("dev-1", load), cache.get_or_load("dev-1", load));
+        assert_eq!(first, Ok(Some("device".to_string())));
+        assert_eq!(second, Ok(Some("device".to_string())));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(cache.loading.lock().unwrap().is_empty());
+
+        // 加载结果为空时不缓存
+        let missing = cache.get_or_load("dev-2", || async { Ok::<Option<String>, ()>(None) }).await;
+        assert_eq!(missing, Ok(None));
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,309 @@
+//! 熔断器
+//!
+//! 包裹对外部依赖（EchoKit、PostgreSQL、Redis）的调用：连续失败达到阈值后熔断，
+//! 熔断期间调用直接返回 `CircuitOpen`，不再等待超时；熔断时间结束后放行一次探测调用，
+//! 成功则恢复，失败则重新熔断。
+
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::CircuitBreakerConfig;
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行
+    Closed,
+    /// 熔断中，调用直接失败
+    Open,
+    /// 熔断时间已过，正在放行探测调用
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// 指标中使用的数值：0 正常，1 熔断，2 探测中
+    pub fn as_gauge(self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::Open => 1.0,
+            CircuitState::HalfOpen => 2.0,
+        }
+    }
+}
+
+/// 熔断期间拒绝调用时返回的错误
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{name} is unavailable (circuit open), retry in {}ms", retry_after.as_millis())]
+pub struct CircuitOpen {
+    /// 熔断器名称（依赖名）
+    pub name: String,
+    /// 距离下一次探测的时间
+    pub retry_after: Duration,
+}
+
+type StateListener = Box<dyn Fn(&str, CircuitState) + Send + Sync>;
+
+/// 熔断器
+pub struct CircuitBreaker {
+    name: String,
+    enabled: bool,
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<Inner>,
+    listener: Option<StateListener>,
+}
+
+struct Inner {
+    state: CircuitState,
+    failures: u32,
+    opened_at: Instant,
+    probe_started: Instant,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, failure_threshold: u32, open_duration: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            name: name.into(),
+            enabled: true,
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                failures: 0,
+                opened_at: now,
+                probe_started: now,
+            }),
+            listener: None,
+        }
+    }
+
+    /// 按配置创建，`enabled = false` 时始终放行
+    pub fn from_config(name: impl Into<String>, config: &CircuitBreakerConfig) -> Self {
+        let mut breaker = Self::new(name, config.failure_threshold, Duration::from_millis(config.open_duration_ms));
+        breaker.enabled = config.enabled;
+        breaker
+    }
+
+    /// 始终放行的熔断器，用于未配置熔断的调用方
+    pub fn disabled(name: impl Into<String>) -> Self {
+        let mut breaker = Self::new(name, 1, Duration::ZERO);
+        breaker.enabled = false;
+        breaker
+    }
+
+    /// 状态变化时的回调（熔断器名称，新状态），用于记录日志和指标
+    pub fn with_listener(mut self, listener: impl Fn(&str, CircuitState) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.lock().state
+    }
+
+    /// 检查是否允许调用；熔断时间已过时转为探测状态并放行本次调用
+    ///
+    /// 放行后调用方必须通过 `record_success` / `record_failure` 上报结果。
+    pub fn check(&self) -> Result<(), CircuitOpen> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let mut inner = self.lock();
+        let now = Instant::now();
+        match inner.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open => {
+                let elapsed = now.duration_since(inner.opened_at);
+                if elapsed < self.open_duration {
+                    return Err(self.open_error(self.open_duration - elapsed));
+                }
+                inner.state = CircuitState::HalfOpen;
+                inner.probe_started = now;
+                drop(inner);
+                self.notify(CircuitState::HalfOpen);
+                Ok(())
+            }
+            CircuitState::HalfOpen => {
+                // 同一时间只放行一次探测；探测调用被取消而未上报结果时，超过熔断时间后重新探测
+                let elapsed = now.duration_since(inner.probe_started);
+                if elapsed < self.open_duration {
+                    return Err(self.open_error(self.open_duration - elapsed));
+                }
+                inner.probe_started = now;
+                Ok(())
+            }
+        }
+    }
+
+    /// 上报一次成功调用
+    pub fn record_success(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut inner = self.lock();
+        inner.failures = 0;
+        if inner.state != CircuitState::Closed {
+            inner.state = CircuitState::Closed;
+            drop(inner);
+            self.notify(CircuitState::Closed);
+        }
+    }
+
+    /// 上报一次失败调用
+    pub fn record_failure(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut inner = self.lock();
+        let trip = match inner.state {
+            CircuitState::Closed => {
+                inner.failures += 1;
+                inner.failures >= self.failure_threshold
+            }
+            CircuitState::HalfOpen => true,
+            // 熔断前发出的调用随后失败，不延长熔断时间
+            CircuitState::Open => false,
+        };
+        if trip {
+            inner.state = CircuitState::Open;
+            inner.failures = 0;
+            inner.opened_at = Instant::now();
+            drop(inner);
+            self.notify(CircuitState::Open);
+        }
+    }
+
+    /// 执行一次调用，任何错误都计为失败
+    pub async fn call<T, E, F>(&self, call: F) -> Result<T, E>
+    where
+        E: From<CircuitOpen>,
+        F: Future<Output = Result<T, E>>,
+    {
+        self.call_with(call, |_| true).await
+    }
+
+    /// 执行一次调用，只有 `is_failure` 返回 true 的错误计为失败
+    ///
+    /// 用于区分依赖不可用（连接失败、超时）和请求本身的错误（如违反约束）。
+    pub async fn call_with<T, E, F>(&self, call: F, is_failure: impl Fn(&E) -> bool) -> Result<T, E>
+    where
+        E: From<CircuitOpen>,
+        F: Future<Output = Result<T, E>>,
+    {
+        self.check()?;
+        let result = call.await;
+        match &result {
+            Err(e) if is_failure(e) => self.record_failure(),
+            _ => self.record_success(),
+        }
+        result
+    }
+
+    fn open_error(&self, retry_after: Duration) -> CircuitOpen {
+        CircuitOpen { name: self.name.clone(), retry_after }
+    }
+
+    fn notify(&self, state: CircuitState) {
+        if let Some(listener) = &self.listener {
+            listener(&self.name, state);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Open,
+        Failed,
+        Rejected,
+    }
+
+    impl From<CircuitOpen> for TestError {
+        fn from(_: CircuitOpen) -> Self {
+            TestError::Open
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trips_after_threshold_and_recovers_after_probe() {
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transitions.clone();
+        let breaker = CircuitBreaker::new("echokit", 2, Duration::from_millis(20))
+            .with_listener(move |_, state| recorded.lock().unwrap().push(state));
+
+        for _ in 0..2 {
+            let result: Result<(), _> = breaker.call(async { Err(TestError::Failed) }).await;
+            assert_eq!(result, Err(TestError::Failed));
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // 熔断期间不执行调用
+        let result: Result<(), TestError> = breaker.call(async { unreachable!() }).await;
+        assert_eq!(result, Err(TestError::Open));
+        assert!(breaker.check().unwrap_err().retry_after <= Duration::from_millis(20));
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.check().is_ok());
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        // 探测期间其他调用仍被拒绝
+        assert!(breaker.check().is_err());
+        breaker.record_success();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            [CircuitState::Open, CircuitState::HalfOpen, CircuitState::Closed]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens_and_ignored_errors_do_not_trip() {
+        let breaker = CircuitBreaker::new("database", 1, Duration::from_millis(10));
+
+        let result: Result<(), _> = breaker
+            .call_with(async { Err(TestError::Rejected) }, |e| *e == TestError::Failed)
+            .await;
+        assert_eq!(result, Err(TestError::Rejected));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(breaker.check().is_ok());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.check().is_err());
+    }
+
+    #[test]
+    fn test_disabled_breaker_always_allows() {
+        let config = CircuitBreakerConfig { enabled: false, failure_threshold: 1, open_duration_ms: 1000 };
+        let breaker = CircuitBreaker::from_config("redis", &config);
+        for _ in 0..3 {
+            breaker.record_failure();
+        }
+        assert!(breaker.check().is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,289 @@
+//! 设备命令授权
+//!
+//! 重启、音量调节、固件升级和播报等命令按 `[command_authorization]` 要求的最低设备权限检查：设备所有者
+//! （`devices.owner`）视为 owner，其他用户的权限来自 `user_devices.permission_level`。管理员角色不受限制，
+//! viewer 角色不能下发任何命令。
+//!
+//! API Gateway 在命令接口中检查，下发的命令带上 `issued_by`（签名命令在签名范围内）；Bridge 执行命令前
+//! 按同一规则再次检查，MQTT 上被伪造或绕过 API Gateway 的命令也会被拒绝。没有 `issued_by` 的命令视为
+//! 系统命令（如定时任务），不做检查。被拒绝的命令记录到 `audit_log`（`device_command_denied`）。
+//! SQLite 存储没有 `user_devices` 和 `audit_log`，只有设备所有者视为有权限，拒绝记录只写日志。
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::database::DevicePermission;
+use crate::mqtt::DeviceCommand;
+use crate::storage::Storage;
+use crate::types::{CommandAuthorizationConfig, UserRole};
+use crate::DeviceId;
+
+/// 审计日志中的操作类型
+pub const AUDIT_ACTION_COMMAND_DENIED: &str = "device_command_denied";
+
+/// 授权检查的命令类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceCommandKind {
+    Restart,
+    Volume,
+    Ota,
+    Announcement,
+    Other,
+}
+
+impl DeviceCommandKind {
+    pub fn of(command: &DeviceCommand) -> Self {
+        match command {
+            DeviceCommand::Reboot => Self::Restart,
+            DeviceCommand::SetVolume { .. } => Self::Volume,
+            DeviceCommand::UpdateFirmware { .. } => Self::Ota,
+            DeviceCommand::PlaySound { .. } => Self::Announcement,
+            DeviceCommand::Custom { command_type, .. } if command_type == "announce" => Self::Announcement,
+            _ => Self::Other,
+        }
+    }
+
+    /// 签名命令中的 JSON 命令；不是 DeviceCommand 的命令（如配置）按 Other 处理
+    pub fn of_value(command: &serde_json::Value) -> Self {
+        serde_json::from_value::<DeviceCommand>(command.clone())
+            .map(|command| Self::of(&command))
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Restart => "restart",
+            Self::Volume => "volume",
+            Self::Ota => "ota",
+            Self::Announcement => "announcement",
+            Self::Other => "other",
+        }
+    }
+
+    /// 该类命令要求的最低设备权限
+    pub fn required(self, config: &CommandAuthorizationConfig) -> DevicePermission {
+        match self {
+            Self::Restart => config.restart,
+            Self::Volume => config.volume,
+            Self::Ota => config.ota,
+            Self::Announcement => config.announcement,
+            Self::Other => config.other,
+        }
+    }
+}
+
+/// 下发命令的用户
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandIssuer {
+    pub user_id: String,
+    pub username: String,
+    pub role: UserRole,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CommandDenied {
+    #[error("viewer accounts cannot send device commands")]
+    ReadOnlyRole,
+    #[error("no access to this device")]
+    NoAccess,
+    #[error("{kind} commands require {required} permission on this device, user has {actual}")]
+    InsufficientPermission {
+        kind: &'static str,
+        required: &'static str,
+        actual: &'static str,
+    },
+    #[error("failed to look up device permission")]
+    LookupFailed,
+}
+
+impl CommandDenied {
+    /// 指标标签
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::ReadOnlyRole => "read_only_role",
+            Self::NoAccess => "no_access",
+            Self::InsufficientPermission { .. } => "insufficient_permission",
+            Self::LookupFailed => "lookup_failed",
+        }
+    }
+}
+
+/// 按角色和设备权限判断是否允许下发该类命令，access 为 None 表示用户与设备没有关联
+pub fn authorize(
+    config: &CommandAuthorizationConfig,
+    issuer: &CommandIssuer,
+    access: Option<DevicePermission>,
+    kind: DeviceCommandKind,
+) -> Result<(), CommandDenied> {
+    if !config.enabled || issuer.role == UserRole::Admin {
+        return Ok(());
+    }
+    if issuer.role == UserRole::Viewer {
+        return Err(CommandDenied::ReadOnlyRole);
+    }
+    let access = access.ok_or(CommandDenied::NoAccess)?;
+    let required = kind.required(config);
+    if access.rank() < required.rank() {
+        return Err(CommandDenied::InsufficientPermission {
+            kind: kind.as_str(),
+            required: required.as_str(),
+            actual: access.as_str(),
+        });
+    }
+    Ok(())
+}
+
+/// 用户对设备的权限：设备所有者为 Owner，否则读取 user_devices
+pub async fn device_access(
+    pool: &PgPool,
+    device_id: &str,
+    user_id: &str,
+) -> Result<Option<DevicePermission>, sqlx::Error> {
+    let owner: Option<Option<String>> = sqlx::query_scalar("SELECT owner FROM devices WHERE id = $1")
+        .bind(device_id)
+        .fetch_optional(pool)
+        .await?;
+    if owner.flatten().as_deref() == Some(user_id) {
+        return Ok(Some(DevicePermission::Owner));
+    }
+
+    let level: Option<String> = sqlx::query_scalar(
+        "SELECT permission_level FROM user_devices WHERE device_id = $1 AND user_id::text = $2",
+    )
+    .bind(device_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(level.and_then(|level| serde_json::from_value(serde_json::Value::String(level)).ok()))
+}
+
+/// 带数据库的授权检查，API Gateway 和 Bridge 共用
+#[derive(Clone)]
+pub struct CommandAuthorizer {
+    config: CommandAuthorizationConfig,
+    storage: Arc<dyn Storage>,
+}
+
+impl CommandAuthorizer {
+    pub fn new(config: CommandAuthorizationConfig, storage: Arc<dyn Storage>) -> Self {
+        Self { config, storage }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// issuer 为 None 的系统命令直接放行；查询权限失败时拒绝
+    pub async fn check(
+        &self,
+        device_id: &str,
+        kind: DeviceCommandKind,
+        issuer: Option<&CommandIssuer>,
+    ) -> Result<(), CommandDenied> {
+        let Some(issuer) = issuer else {
+            return Ok(());
+        };
+        if !self.config.enabled || issuer.role != UserRole::User {
+            return authorize(&self.config, issuer, None, kind);
+        }
+        let access = match self.storage.postgres_pool() {
+            Some(pool) => device_access(pool, device_id, &issuer.user_id)
+                .await
+                .map_err(|_| CommandDenied::LookupFailed)?,
+            None => self
+                .storage
+                .get_device(&DeviceId::new(device_id))
+                .await
+                .map_err(|_| CommandDenied::LookupFailed)?
+                .filter(|device| device.owner == issuer.user_id)
+                .map(|_| DevicePermission::Owner),
+        };
+        authorize(&self.config, issuer, access, kind)
+    }
+
+    /// 记录被拒绝的命令，source 为检查位置（如 "gateway"、"bridge_mqtt"）
+    pub async fn record_denial(
+        &self,
+        device_id: &str,
+        kind: DeviceCommandKind,
+        issuer: &CommandIssuer,
+        denied: &CommandDenied,
+        source: &str,
+        actor_ip: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        match self.storage.postgres_pool() {
+            Some(pool) => record_denial(pool, device_id, kind, issuer, denied, source, actor_ip).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// 写入一条 device_command_denied 审计日志
+pub async fn record_denial(
+    pool: &PgPool,
+    device_id: &str,
+    kind: DeviceCommandKind,
+    issuer: &CommandIssuer,
+    denied: &CommandDenied,
+    source: &str,
+    actor_ip: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let details = serde_json::json!({
+        "command": kind.as_str(),
+        "reason": denied.to_string(),
+        "source": source,
+    });
+    sqlx::query(
+        "INSERT INTO audit_log (action, actor_id, actor_username, actor_ip, target_type, target_id, after) \
+         VALUES ($1, $2, $3, $4, 'device', $5, $6)",
+    )
+    .bind(AUDIT_ACTION_COMMAND_DENIED)
+    .bind(&issuer.user_id)
+    .bind(&issuer.username)
+    .bind(actor_ip)
+    .bind(device_id)
+    .bind(details)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issuer(role: UserRole) -> CommandIssuer {
+        CommandIssuer { user_id: "u1".to_string(), username: "alice".to_string(), role }
+    }
+
+    #[test]
+    fn test_authorize() {
+        let config = CommandAuthorizationConfig::default();
+        let user = issuer(UserRole::User);
+
+        assert!(authorize(&config, &issuer(UserRole::Admin), None, DeviceCommandKind::Ota).is_ok());
+        assert_eq!(
+            authorize(&config, &issuer(UserRole::Viewer), Some(DevicePermission::Owner), DeviceCommandKind::Volume),
+            Err(CommandDenied::ReadOnlyRole)
+        );
+        assert_eq!(authorize(&config, &user, None, DeviceCommandKind::Volume), Err(CommandDenied::NoAccess));
+
+        assert!(authorize(&config, &user, Some(DevicePermission::User), DeviceCommandKind::Volume).is_ok());
+        assert!(authorize(&config, &user, Some(DevicePermission::User), DeviceCommandKind::Restart).is_err());
+        assert!(authorize(&config, &user, Some(DevicePermission::Admin), DeviceCommandKind::Restart).is_ok());
+        assert!(authorize(&config, &user, Some(DevicePermission::Admin), DeviceCommandKind::Ota).is_err());
+        assert!(authorize(&config, &user, Some(DevicePermission::Owner), DeviceCommandKind::Ota).is_ok());
+        assert!(authorize(&config, &user, Some(DevicePermission::Viewer), DeviceCommandKind::Announcement).is_err());
+
+        let disabled = CommandAuthorizationConfig { enabled: false, ..config };
+        assert!(authorize(&disabled, &issuer(UserRole::Viewer), None, DeviceCommandKind::Ota).is_ok());
+
+        assert_eq!(
+            DeviceCommandKind::of_value(&serde_json::json!({"type": "SetVolume", "level": 30})),
+            DeviceCommandKind::Volume
+        );
+        assert_eq!(DeviceCommandKind::of_value(&serde_json::json!({"volume": 30})), DeviceCommandKind::Other);
+    }
+}
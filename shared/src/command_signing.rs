@@ -0,0 +1,273 @@
+//! 设备命令签名
+//!
+//! API Gateway 用 Ed25519 私钥对下发给设备的控制命令和配置签名，Bridge 在转发给设备之前验证签名、
+//! 目标设备、签发时间和 nonce，拒绝未签名、被篡改、过期或重放的命令，MQTT broker 被攻破时也无法伪造
+//! 重启或配置变更。设备注册验证成功时响应中带上公钥（`command_public_key`），直接订阅 MQTT 的设备
+//! 可以自行验证收到的命令。
+//!
+//! 签名覆盖 `SignedCommand.payload` 的原始 JSON 文本，验证方不需要重新序列化。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+use crate::command_authz::CommandIssuer;
+use crate::types::CommandSigningConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CommandSignatureError {
+    #[error("{0} must be 32 bytes encoded as Base64")]
+    InvalidKey(&'static str),
+    #[error("command_signing requires private_key or public_key")]
+    MissingKey,
+    #[error("malformed signed command")]
+    Malformed,
+    #[error("invalid command signature")]
+    BadSignature,
+    #[error("command was issued for device {0}")]
+    WrongDevice(String),
+    #[error("command was issued at {0}, outside the accepted window")]
+    Expired(DateTime<Utc>),
+    #[error("command nonce {0} was already used")]
+    Replayed(String),
+}
+
+impl CommandSignatureError {
+    /// 指标标签
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::InvalidKey(_) | Self::MissingKey => "key",
+            Self::Malformed => "malformed",
+            Self::BadSignature => "signature",
+            Self::WrongDevice(_) => "device",
+            Self::Expired(_) => "expired",
+            Self::Replayed(_) => "replayed",
+        }
+    }
+}
+
+/// 被签名的命令内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandEnvelope {
+    pub device_id: String,
+    pub issued_at: DateTime<Utc>,
+    /// 每条命令唯一，用于拒绝重放
+    pub nonce: String,
+    /// DeviceCommand、DeviceConfiguration 或经 WebSocket 下发的任意 JSON 命令
+    pub command: serde_json::Value,
+    /// 下发命令的用户（在签名范围内，Bridge 据此做命令授权检查）；系统命令为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_by: Option<CommandIssuer>,
+}
+
+/// 签名后的命令，经 MQTT 或 WebSocket 原样下发给设备
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedCommand {
+    /// `CommandEnvelope` 的 JSON 文本
+    pub payload: String,
+    /// 对 payload 字节的 Ed25519 签名（Base64）
+    pub signature: String,
+}
+
+/// 命令签名方（API Gateway）
+pub struct CommandSigner {
+    key: SigningKey,
+}
+
+impl CommandSigner {
+    /// 未启用或未配置私钥时返回 None
+    pub fn from_config(config: &CommandSigningConfig) -> Result<Option<Self>, CommandSignatureError> {
+        if !config.enabled || config.private_key.is_empty() {
+            return Ok(None);
+        }
+        Self::new(&config.private_key).map(Some)
+    }
+
+    /// 私钥为 Base64 编码的 32 字节种子
+    pub fn new(private_key: &str) -> Result<Self, CommandSignatureError> {
+        let seed = decode_key(private_key, "command_signing.private_key")?;
+        Ok(Self { key: SigningKey::from_bytes(&seed) })
+    }
+
+    /// 下发给设备的公钥（Base64）
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// 为指定设备签名一条命令
+    pub fn sign<T: Serialize>(&self, device_id: &str, command: &T) -> Result<SignedCommand, serde_json::Error> {
+        self.sign_as(device_id, command, None)
+    }
+
+    /// 签名用户下发的命令，issued_by 一并签名
+    pub fn sign_as<T: Serialize>(
+        &self,
+        device_id: &str,
+        command: &T,
+        issued_by: Option<&CommandIssuer>,
+    ) -> Result<SignedCommand, serde_json::Error> {
+        let envelope = CommandEnvelope {
+            device_id: device_id.to_string(),
+            issued_at: Utc::now(),
+            nonce: crate::generate_uuid(),
+            command: serde_json::to_value(command)?,
+            issued_by: issued_by.cloned(),
+        };
+        let payload = serde_json::to_string(&envelope)?;
+        let signature = self.key.sign(payload.as_bytes());
+        Ok(SignedCommand {
+            payload,
+            signature: STANDARD.encode(signature.to_bytes()),
+        })
+    }
+}
+
+/// 命令验证方（Bridge）
+pub struct CommandVerifier {
+    key: VerifyingKey,
+    max_age: Duration,
+    // nonce -> 签发时间，超出有效期的条目在验证时清理
+    seen: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl CommandVerifier {
+    /// 未启用签名时返回 None；未配置 public_key 时从 private_key 推导
+    pub fn from_config(config: &CommandSigningConfig) -> Result<Option<Self>, CommandSignatureError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let key = if !config.public_key.is_empty() {
+            let bytes = decode_key(&config.public_key, "command_signing.public_key")?;
+            VerifyingKey::from_bytes(&bytes).map_err(|_| CommandSignatureError::InvalidKey("command_signing.public_key"))?
+        } else if !config.private_key.is_empty() {
+            CommandSigner::new(&config.private_key)?.key.verifying_key()
+        } else {
+            return Err(CommandSignatureError::MissingKey);
+        };
+        Ok(Some(Self::new(key, Duration::seconds(config.max_age_seconds as i64))))
+    }
+
+    pub fn new(key: VerifyingKey, max_age: Duration) -> Self {
+        Self {
+            key,
+            max_age,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 验证签名、目标设备、签发时间和 nonce，通过后返回命令内容
+    ///
+    /// 签发时间与本机时间相差超过 `max_age_seconds`（双向，容忍时钟偏差）的命令视为过期
+    pub fn verify(&self, signed: &SignedCommand, device_id: &str) -> Result<CommandEnvelope, CommandSignatureError> {
+        let signature = STANDARD
+            .decode(&signed.signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or(CommandSignatureError::Malformed)?;
+        self.key
+            .verify(signed.payload.as_bytes(), &signature)
+            .map_err(|_| CommandSignatureError::BadSignature)?;
+
+        let envelope: CommandEnvelope =
+            serde_json::from_str(&signed.payload).map_err(|_| CommandSignatureError::Malformed)?;
+        if envelope.device_id != device_id {
+            return Err(CommandSignatureError::WrongDevice(envelope.device_id));
+        }
+
+        let now = Utc::now();
+        if (now - envelope.issued_at).abs() > self.max_age {
+            return Err(CommandSignatureError::Expired(envelope.issued_at));
+        }
+
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, issued_at| now - *issued_at <= self.max_age);
+        if seen.insert(envelope.nonce.clone(), envelope.issued_at).is_some() {
+            return Err(CommandSignatureError::Replayed(envelope.nonce));
+        }
+        Ok(envelope)
+    }
+
+    /// 验证 JSON 文本形式的签名命令（WebSocket 下发路径）
+    pub fn verify_json(&self, text: &str, device_id: &str) -> Result<CommandEnvelope, CommandSignatureError> {
+        let signed: SignedCommand = serde_json::from_str(text).map_err(|_| CommandSignatureError::Malformed)?;
+        self.verify(&signed, device_id)
+    }
+}
+
+fn decode_key(value: &str, field: &'static str) -> Result<[u8; 32], CommandSignatureError> {
+    STANDARD
+        .decode(value.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or(CommandSignatureError::InvalidKey(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::DeviceCommand;
+
+    fn config() -> CommandSigningConfig {
+        CommandSigningConfig {
+            enabled: true,
+            private_key: STANDARD.encode([7u8; 32]),
+            ..CommandSigningConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = CommandSigner::from_config(&config()).unwrap().unwrap();
+        // 只配置公钥的 Bridge
+        let verifier = CommandVerifier::from_config(&CommandSigningConfig {
+            enabled: true,
+            public_key: signer.public_key(),
+            ..CommandSigningConfig::default()
+        })
+        .unwrap()
+        .unwrap();
+
+        let signed = signer.sign("device-1", &DeviceCommand::Reboot).unwrap();
+        let envelope = verifier.verify(&signed, "device-1").unwrap();
+        assert_eq!(envelope.command, serde_json::json!({ "type": "Reboot" }));
+
+        // 同一条命令不能重放
+        assert!(matches!(verifier.verify(&signed, "device-1"), Err(CommandSignatureError::Replayed(_))));
+
+        let signed = signer.sign("device-1", &DeviceCommand::Reboot).unwrap();
+        assert!(matches!(verifier.verify(&signed, "device-2"), Err(CommandSignatureError::WrongDevice(_))));
+
+        let json = serde_json::to_string(&signer.sign("device-1", &DeviceCommand::EndSession).unwrap()).unwrap();
+        assert!(verifier.verify_json(&json, "device-1").is_ok());
+        assert_eq!(verifier.verify_json("{\"type\":\"Reboot\"}", "device-1"), Err(CommandSignatureError::Malformed));
+    }
+
+    #[test]
+    fn test_rejects_tampered_and_expired_commands() {
+        let signer = CommandSigner::from_config(&config()).unwrap().unwrap();
+        let verifier = CommandVerifier::from_config(&config()).unwrap().unwrap();
+
+        let mut signed = signer.sign("device-1", &DeviceCommand::SetVolume { level: 10 }).unwrap();
+        signed.payload = signed.payload.replace("10", "100");
+        assert_eq!(verifier.verify(&signed, "device-1"), Err(CommandSignatureError::BadSignature));
+
+        // 其他密钥签名的命令
+        let other = CommandSigner::new(&STANDARD.encode([8u8; 32])).unwrap();
+        let signed = other.sign("device-1", &DeviceCommand::Reboot).unwrap();
+        assert_eq!(verifier.verify(&signed, "device-1"), Err(CommandSignatureError::BadSignature));
+
+        let strict = CommandVerifier::new(signer.key.verifying_key(), Duration::seconds(-1));
+        let signed = signer.sign("device-1", &DeviceCommand::Reboot).unwrap();
+        assert!(matches!(strict.verify(&signed, "device-1"), Err(CommandSignatureError::Expired(_))));
+
+        assert_eq!(
+            CommandVerifier::from_config(&CommandSigningConfig { enabled: true, ..CommandSigningConfig::default() }).err(),
+            Some(CommandSignatureError::MissingKey)
+        );
+        assert!(CommandSigner::new("short").is_err());
+    }
+}
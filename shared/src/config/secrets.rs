@@ -0,0 +1,522 @@
+// 密钥管理：配置中形如 `secret:<name>` 的值在加载时通过密钥提供者解析
+//
+// 支持的提供者：
+// - env：读取环境变量 `{env_prefix}{NAME}`（名称转大写，`-` 和 `.` 替换为 `_`）
+// - file：读取 `{file_dir}/{name}` 文件（Docker / Kubernetes secrets）
+// - vault：读取 HashiCorp Vault KV v2 中 `{vault_mount}/data/{vault_path}` 的字段
+// - aws：读取 AWS Secrets Manager 中 `aws_secret_id` 的 JSON 字段
+use std::collections::HashMap;
+use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+/// 密钥引用前缀
+pub const SECRET_REF_PREFIX: &str = "secret:";
+
+/// 请求 Vault / AWS 的超时时间
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 密钥提供者类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsProviderKind {
+    Env,
+    File,
+    Vault,
+    Aws,
+}
+
+// 密钥提供者配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    pub provider: SecretsProviderKind,
+    /// env：环境变量前缀
+    pub env_prefix: String,
+    /// file：密钥文件目录
+    pub file_dir: String,
+    /// vault：服务地址，如 https://vault.example.com:8200
+    pub vault_address: Option<String>,
+    /// vault：访问令牌，未设置时读取 VAULT_TOKEN
+    pub vault_token: Option<String>,
+    /// vault：KV v2 引擎挂载点
+    pub vault_mount: String,
+    /// vault：密钥路径
+    pub vault_path: String,
+    /// aws：区域，未设置时读取 AWS_REGION
+    pub aws_region: Option<String>,
+    /// aws：密钥 ID 或 ARN
+    pub aws_secret_id: Option<String>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            provider: SecretsProviderKind::Env,
+            env_prefix: "ECHO_SECRET_".to_string(),
+            file_dir: "/run/secrets".to_string(),
+            vault_address: None,
+            vault_token: None,
+            vault_mount: "secret".to_string(),
+            vault_path: "echo".to_string(),
+            aws_region: None,
+            aws_secret_id: None,
+        }
+    }
+}
+
+/// 密钥提供者
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    /// 提供者名称（用于错误信息）
+    fn name(&self) -> &'static str;
+
+    /// 读取指定名称的密钥
+    async fn get_secret(&self, name: &str) -> Result<String>;
+}
+
+/// 根据配置创建密钥提供者
+pub fn provider_from_config(config: &SecretsConfig) -> Result<Box<dyn SecretsProvider>> {
+    Ok(match config.provider {
+        SecretsProviderKind::Env => Box::new(EnvSecretsProvider::new(&config.env_prefix)),
+        SecretsProviderKind::File => Box::new(FileSecretsProvider::new(&config.file_dir)),
+        SecretsProviderKind::Vault => {
+            let address = config
+                .vault_address
+                .clone()
+                .ok_or_else(|| anyhow!("secrets.vault_address is required for the vault provider"))?;
+            let token = config
+                .vault_token
+                .clone()
+                .or_else(|| std::env::var("VAULT_TOKEN").ok())
+                .ok_or_else(|| anyhow!("secrets.vault_token or VAULT_TOKEN is required for the vault provider"))?;
+            Box::new(VaultSecretsProvider::new(address, token, &config.vault_mount, &config.vault_path)?)
+        }
+        SecretsProviderKind::Aws => {
+            let region = config
+                .aws_region
+                .clone()
+                .or_else(|| std::env::var("AWS_REGION").ok())
+                .ok_or_else(|| anyhow!("secrets.aws_region or AWS_REGION is required for the aws provider"))?;
+            let secret_id = config
+                .aws_secret_id
+                .clone()
+                .ok_or_else(|| anyhow!("secrets.aws_secret_id is required for the aws provider"))?;
+            Box::new(AwsSecretsProvider::new(region, secret_id, AwsCredentials::from_env()?)?)
+        }
+    })
+}
+
+/// 解析 JSON 配置树中的所有密钥引用
+pub async fn resolve_secret_refs(value: &mut serde_json::Value, provider: &dyn SecretsProvider) -> Result<()> {
+    let mut pending = vec![(String::new(), value)];
+
+    while let Some((path, value)) = pending.pop() {
+        match value {
+            serde_json::Value::String(s) => {
+                if let Some(name) = s.strip_prefix(SECRET_REF_PREFIX) {
+                    let secret = provider
+                        .get_secret(name)
+                        .await
+                        .with_context(|| format!("Failed to resolve {} from the {} secrets provider", path, provider.name()))?;
+                    *s = secret;
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                    pending.push((child_path, child));
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for (i, child) in items.iter_mut().enumerate() {
+                    pending.push((format!("{}[{}]", path, i), child));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// 从环境变量读取密钥
+pub struct EnvSecretsProvider {
+    prefix: String,
+}
+
+impl EnvSecretsProvider {
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.to_string() }
+    }
+
+    /// 密钥名称对应的环境变量名，如 jwt-secret -> ECHO_SECRET_JWT_SECRET
+    pub fn variable_name(&self, name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| if c == '-' || c == '.' { '_' } else { c.to_ascii_uppercase() })
+            .collect();
+        format!("{}{}", self.prefix, name)
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for EnvSecretsProvider {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    async fn get_secret(&self, name: &str) -> Result<String> {
+        let variable = self.variable_name(name);
+        std::env::var(&variable).with_context(|| format!("Environment variable {} is not set", variable))
+    }
+}
+
+/// 从文件读取密钥，每个密钥一个文件
+pub struct FileSecretsProvider {
+    dir: PathBuf,
+}
+
+impl FileSecretsProvider {
+    pub fn new(dir: &str) -> Self {
+        Self { dir: PathBuf::from(dir) }
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for FileSecretsProvider {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn get_secret(&self, name: &str) -> Result<String> {
+        if name.is_empty() || name.contains('/') || name.contains("..") {
+            return Err(anyhow!("Invalid secret name: {}", name));
+        }
+
+        let path = self.dir.join(name);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read secret file {}", path.display()))?;
+        Ok(content.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// 从 HashiCorp Vault KV v2 读取密钥，同一次加载中只请求一次
+pub struct VaultSecretsProvider {
+    url: String,
+    token: String,
+    http: reqwest::Client,
+    secrets: OnceCell<HashMap<String, String>>,
+}
+
+impl VaultSecretsProvider {
+    pub fn new(address: String, token: String, mount: &str, path: &str) -> Result<Self> {
+        Ok(Self {
+            url: format!(
+                "{}/v1/{}/data/{}",
+                address.trim_end_matches('/'),
+                mount.trim_matches('/'),
+                path.trim_matches('/')
+            ),
+            token,
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            secrets: OnceCell::new(),
+        })
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, String>> {
+        #[derive(Deserialize)]
+        struct KvResponse {
+            data: KvData,
+        }
+        #[derive(Deserialize)]
+        struct KvData {
+            data: HashMap<String, serde_json::Value>,
+        }
+
+        let response: KvResponse = self
+            .http
+            .get(&self.url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(string_fields(response.data.data))
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for VaultSecretsProvider {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn get_secret(&self, name: &str) -> Result<String> {
+        let secrets = self.secrets.get_or_try_init(|| self.fetch()).await?;
+        secrets
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Secret {} not found in {}", name, self.url))
+    }
+}
+
+/// AWS 访问凭证
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// 从 AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY / AWS_SESSION_TOKEN 读取
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            access_key_id: std::env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID is not set")?,
+            secret_access_key: std::env::var("AWS_SECRET_ACCESS_KEY").context("AWS_SECRET_ACCESS_KEY is not set")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok().filter(|t| !t.is_empty()),
+        })
+    }
+}
+
+/// 从 AWS Secrets Manager 读取密钥（SecretString 为 JSON 对象），同一次加载中只请求一次
+pub struct AwsSecretsProvider {
+    region: String,
+    secret_id: String,
+    credentials: AwsCredentials,
+    http: reqwest::Client,
+    secrets: OnceCell<HashMap<String, String>>,
+}
+
+impl AwsSecretsProvider {
+    pub fn new(region: String, secret_id: String, credentials: AwsCredentials) -> Result<Self> {
+        Ok(Self {
+            region,
+            secret_id,
+            credentials,
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            secrets: OnceCell::new(),
+        })
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, String>> {
+        #[derive(Deserialize)]
+        struct GetSecretValueResponse {
+            #[serde(rename = "SecretString")]
+            secret_string: Option<String>,
+        }
+
+        let host = format!("secretsmanager.{}.amazonaws.com", self.region);
+        let body = serde_json::json!({ "SecretId": self.secret_id }).to_string();
+        let target = "secretsmanager.GetSecretValue";
+        let now = Utc::now();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+            ("x-amz-target", target.to_string()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = sigv4_authorization(
+            &self.credentials,
+            &self.region,
+            "secretsmanager",
+            "POST",
+            "/",
+            &headers,
+            body.as_bytes(),
+        );
+
+        let mut request = self.http.post(format!("https://{}/", host)).body(body);
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+        let response: GetSecretValueResponse = request
+            .header("authorization", authorization)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let secret_string = response
+            .secret_string
+            .ok_or_else(|| anyhow!("Secret {} has no SecretString", self.secret_id))?;
+        let fields: HashMap<String, serde_json::Value> = serde_json::from_str(&secret_string)
+            .with_context(|| format!("Secret {} is not a JSON object", self.secret_id))?;
+
+        Ok(string_fields(fields))
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for AwsSecretsProvider {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    async fn get_secret(&self, name: &str) -> Result<String> {
+        let secrets = self.secrets.get_or_try_init(|| self.fetch()).await?;
+        secrets
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Secret {} not found in {}", name, self.secret_id))
+    }
+}
+
+/// 将 JSON 字段转换为字符串（非字符串值使用 JSON 文本）
+fn string_fields(fields: HashMap<String, serde_json::Value>) -> HashMap<String, String> {
+    fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            serde_json::Value::String(s) => (key, s),
+            other => (key, other.to_string()),
+        })
+        .collect()
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// AWS Signature Version 4 签名密钥
+fn sigv4_signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// 生成 AWS Signature Version 4 的 Authorization 头（无查询参数）
+///
+/// `path` 为已按 URI 编码的请求路径；`headers` 需使用小写名称并包含 host 和 x-amz-date（签名时间取自该头）
+pub(crate) fn sigv4_authorization(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> String {
+    let amz_date = headers
+        .iter()
+        .find(|(name, _)| *name == "x-amz-date")
+        .map(|(_, value)| value.clone())
+        .unwrap_or_default();
+    let date = amz_date.get(..8).unwrap_or_default().to_string();
+
+    let mut headers = headers.to_vec();
+    headers.sort_by_key(|(name, _)| *name);
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(body))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let signing_key = sigv4_signing_key(&credentials.secret_access_key, &date, region, service);
+    let signature = hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticProvider(HashMap<String, String>);
+
+    #[async_trait]
+    impl SecretsProvider for StaticProvider {
+        fn name(&self) -> &'static str {
+            "static"
+        }
+
+        async fn get_secret(&self, name: &str) -> Result<String> {
+            self.0.get(name).cloned().ok_or_else(|| anyhow!("missing {}", name))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_secret_refs() {
+        let provider = StaticProvider(HashMap::from([("jwt".to_string(), "s3cr3t".to_string())]));
+        let mut value = serde_json::json!({
+            "jwt": { "secret": "secret:jwt", "expiration_hours": 24 },
+            "mqtt": { "password": null, "username": "echo" }
+        });
+
+        resolve_secret_refs(&mut value, &provider).await.unwrap();
+        assert_eq!(value["jwt"]["secret"], "s3cr3t");
+        assert_eq!(value["mqtt"]["username"], "echo");
+
+        let mut missing = serde_json::json!({ "redis": { "url": "secret:redis-url" } });
+        let error = resolve_secret_refs(&mut missing, &provider).await.unwrap_err();
+        assert!(error.to_string().contains("redis.url"));
+    }
+
+    #[tokio::test]
+    async fn test_file_provider_trims_newline_and_rejects_traversal() {
+        let dir = std::env::temp_dir().join(format!("echo-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("db-password"), "hunter2\n").unwrap();
+
+        let provider = FileSecretsProvider::new(dir.to_str().unwrap());
+        assert_eq!(provider.get_secret("db-password").await.unwrap(), "hunter2");
+        assert!(provider.get_secret("../etc/passwd").await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_env_provider_variable_name() {
+        let provider = EnvSecretsProvider::new("ECHO_SECRET_");
+        assert_eq!(provider.variable_name("jwt-secret"), "ECHO_SECRET_JWT_SECRET");
+        assert_eq!(provider.variable_name("mqtt.password"), "ECHO_SECRET_MQTT_PASSWORD");
+    }
+
+    #[test]
+    fn test_sigv4_signing_key() {
+        // AWS 文档中的签名密钥示例
+        let key = sigv4_signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+}
@@ -0,0 +1,134 @@
+// 日志初始化和配置热加载的公共部分，API Gateway 和 Bridge 共用
+use std::future::Future;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer, Registry};
+use crate::config_watcher::ConfigWatcher;
+use crate::types::{LogConfig, LogFormat, RuntimeConfig};
+
+/// 日志过滤器的热更新句柄
+pub type LogReloadHandle = reload::Handle<EnvFilter, Registry>;
+
+/// 不受日志级别过滤的附加日志层（如 Bridge 的 tokio-console）
+pub type ExtraLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// 初始化日志（文本或 JSON 格式），返回用于调整日志级别的句柄
+///
+/// 日志过滤器只作用于输出层，`extra` 接收全部事件。配置的日志级别无效时回退到 info，
+/// 并在日志初始化后输出警告。
+pub fn init_tracing(config: &LogConfig, extra: Option<ExtraLayer>) -> LogReloadHandle {
+    let directives = config.filter_directives();
+    let (filter, invalid) = match EnvFilter::try_new(&directives) {
+        Ok(filter) => (filter, None),
+        Err(e) => (EnvFilter::new("info"), Some(e)),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+    crate::set_log_sample_every(config.sample_every);
+    crate::set_scrub_secrets(config.scrub_secrets);
+
+    // 日志写出前替换密码、令牌和音频数据
+    let writer = crate::ScrubbingWriter::stdout;
+    let fmt_layer: ExtraLayer = if config.format == LogFormat::Json {
+        Box::new(tracing_subscriber::fmt::layer().json().flatten_event(true).with_writer(writer))
+    } else {
+        Box::new(tracing_subscriber::fmt::layer().with_writer(writer))
+    };
+
+    let mut layers = vec![fmt_layer.with_filter(filter).boxed()];
+    layers.extend(extra);
+    tracing_subscriber::registry().with(layers).init();
+
+    if let Some(e) = invalid {
+        warn!("Invalid log level {:?} ({}), falling back to info", directives, e);
+    }
+
+    // panic 信息经日志输出并脱敏，不打印 panic 时的变量状态
+    std::panic::set_hook(Box::new(|info| error!("{}", crate::scrubbed_panic_message(info))));
+
+    handle
+}
+
+/// 重新加载配置并记录变化的配置项
+pub async fn reload(watcher: &ConfigWatcher) -> anyhow::Result<Vec<&'static str>> {
+    match watcher.reload().await {
+        Ok(changed) if changed.is_empty() => {
+            info!("Configuration reloaded, no runtime settings changed");
+            Ok(changed)
+        }
+        Ok(changed) => {
+            info!("Configuration reloaded, changed: {}", changed.join(", "));
+            Ok(changed)
+        }
+        Err(e) => {
+            error!("Configuration reload failed, keeping the current settings: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// 监听日志级别和采样率变化并更新日志过滤器
+pub fn spawn_log_level_watcher(handle: LogReloadHandle, mut config: watch::Receiver<RuntimeConfig>) {
+    tokio::spawn(async move {
+        while config.changed().await.is_ok() {
+            let (level, sample_every) = {
+                let config = config.borrow_and_update();
+                (config.log_level.clone(), config.log_sample_every)
+            };
+            if sample_every != crate::log_sample_every() {
+                crate::set_log_sample_every(sample_every);
+                info!("Audio frame log sampling set to 1/{}", sample_every.max(1));
+            }
+            match EnvFilter::try_new(&level) {
+                Ok(filter) => match handle.reload(filter) {
+                    Ok(()) => info!("Log level set to {}", level),
+                    Err(e) => warn!("Failed to update log level: {}", e),
+                },
+                Err(e) => warn!("Ignoring invalid log level {:?}: {}", level, e),
+            }
+        }
+    });
+}
+
+/// 收到 SIGHUP 时调用 `on_hangup` 重新加载配置，`shutdown` 完成时退出
+#[cfg(unix)]
+pub fn spawn_sighup_listener<F, Fut, S>(mut on_hangup: F, shutdown: S)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+    S: Future<Output = ()> + Send + 'static,
+{
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                error!("Failed to listen for SIGHUP: {}", e);
+                return;
+            }
+        };
+
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                received = hangup.recv() => {
+                    if received.is_none() {
+                        break;
+                    }
+                    info!("Received SIGHUP, reloading configuration");
+                    on_hangup().await;
+                }
+                _ = &mut shutdown => break,
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_listener<F, Fut, S>(_on_hangup: F, _shutdown: S)
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+    S: Future<Output = ()> + Send + 'static,
+{
+}
@@ -0,0 +1,83 @@
+// 配置热加载：重新读取配置文件和环境变量，通过 watch 通道把可热加载的配置推送给各组件
+use std::sync::Arc;
+use anyhow::Result;
+use tokio::sync::watch;
+use crate::config::load_config;
+use crate::types::{AppConfig, RuntimeConfig};
+
+/// 运行时配置的发布端
+///
+/// 组件通过 `subscribe()` 获取 `watch::Receiver`，每次使用时读取最新值，
+/// 或等待 `changed()` 在配置变化时做出响应
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    sender: Arc<watch::Sender<RuntimeConfig>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            sender: Arc::new(watch::channel(RuntimeConfig::from(config)).0),
+        }
+    }
+
+    /// 订阅运行时配置
+    pub fn subscribe(&self) -> watch::Receiver<RuntimeConfig> {
+        self.sender.subscribe()
+    }
+
+    /// 当前生效的运行时配置
+    pub fn current(&self) -> RuntimeConfig {
+        self.sender.borrow().clone()
+    }
+
+    /// 重新加载配置，返回发生变化的配置项；新配置校验失败时保持原配置不变
+    pub async fn reload(&self) -> Result<Vec<&'static str>> {
+        let config = load_config().await?;
+        Ok(self.apply(&config))
+    }
+
+    /// 应用新配置中可热加载的部分，只有配置发生变化时才通知订阅者
+    pub fn apply(&self, config: &AppConfig) -> Vec<&'static str> {
+        let next = RuntimeConfig::from(config);
+        let mut changed = Vec::new();
+
+        self.sender.send_if_modified(|current| {
+            changed = current.changed_settings(&next);
+            if changed.is_empty() {
+                return false;
+            }
+            *current = next;
+            true
+        });
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_notifies_only_on_change() {
+        let mut config = AppConfig::default();
+        let watcher = ConfigWatcher::new(&config);
+        let mut receiver = watcher.subscribe();
+
+        assert!(watcher.apply(&config).is_empty());
+        assert!(!receiver.has_changed().unwrap());
+
+        config.log.level = "debug".to_string();
+        config.bridge.session_timeout_seconds = 60;
+        // 不可热加载的配置不会触发通知
+        config.server.port = 9090;
+        let changed = watcher.apply(&config);
+
+        assert_eq!(changed, vec!["log.level", "bridge.session_timeout_seconds"]);
+        assert!(receiver.has_changed().unwrap());
+        let current = receiver.borrow_and_update();
+        assert_eq!(current.log_level, "debug");
+        assert_eq!(current.session_timeout_seconds, 60);
+    }
+}
@@ -72,7 +72,9 @@ pub struct UserDeviceRecord {
     pub granted_by: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// 用户对设备的权限（user_devices.permission_level），设备所有者视为 Owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DevicePermission {
     Owner,
     Admin,
@@ -80,6 +82,27 @@ pub enum DevicePermission {
     Viewer,
 }
 
+impl DevicePermission {
+    /// 权限高低，Owner 最高
+    pub fn rank(self) -> u8 {
+        match self {
+            DevicePermission::Owner => 3,
+            DevicePermission::Admin => 2,
+            DevicePermission::User => 1,
+            DevicePermission::Viewer => 0,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DevicePermission::Owner => "owner",
+            DevicePermission::Admin => "admin",
+            DevicePermission::User => "user",
+            DevicePermission::Viewer => "viewer",
+        }
+    }
+}
+
 // 创建新用户的请求
 #[derive(Debug, Deserialize)]
 pub struct CreateUserRequest {
@@ -321,4 +344,37 @@ pub enum DatabaseError {
 
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    #[error("Database unavailable: {0}")]
+    Unavailable(#[from] crate::circuit_breaker::CircuitOpen),
+}
+
+impl DatabaseError {
+    /// 是否为数据库不可用导致的错误（连接失败、连接池超时等），计入熔断
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DatabaseError::Connection(
+                sqlx::Error::Io(_)
+                    | sqlx::Error::Tls(_)
+                    | sqlx::Error::Protocol(_)
+                    | sqlx::Error::PoolTimedOut
+                    | sqlx::Error::PoolClosed
+                    | sqlx::Error::WorkerCrashed
+            )
+        )
+    }
+
+    /// 是否值得重试：数据库短暂不可用，或事务因序列化冲突、死锁被回滚
+    pub fn is_retryable(&self) -> bool {
+        if self.is_transient() {
+            return true;
+        }
+        match self {
+            DatabaseError::Connection(sqlx::Error::Database(e)) => {
+                matches!(e.code().as_deref(), Some("40001") | Some("40P01"))
+            }
+            _ => false,
+        }
+    }
 }
\ No newline at end of file
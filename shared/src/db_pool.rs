@@ -0,0 +1,155 @@
+//! 数据库连接池的创建、统计与自动扩容
+//!
+//! 两个服务按 `database` 配置创建连接池。`PoolMonitor` 定期从连接池取一次连接，测量调用方
+//! 此刻获取连接需要等待的时间；启用 `database.pool.resize` 时，等待时间超过阈值会提前建立
+//! 连接。sqlx 连接池的上限在创建后不可修改，因此扩容只在 `min_connections` 和
+//! `max_connections` 之间增加已建立的连接，负载下降后多出的连接在空闲超时后由连接池关闭。
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use crate::types::{DatabaseConfig, DatabasePoolConfig};
+
+type SampleListener = Box<dyn Fn(&PoolStats) + Send + Sync>;
+
+/// 按配置设置连接数、获取连接超时和空闲超时
+pub fn pool_options(config: &DatabaseConfig) -> PgPoolOptions {
+    let pool = &config.pool;
+    PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(pool.acquire_timeout_seconds))
+        .idle_timeout((pool.idle_timeout_seconds > 0).then(|| Duration::from_secs(pool.idle_timeout_seconds)))
+}
+
+/// 连接池状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolStats {
+    /// 已建立的连接数
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max: u32,
+    /// 获取一个连接等待的时间（未采样时为 0）
+    pub acquire_wait: Duration,
+    /// 获取连接失败的原因（如超时）
+    pub acquire_error: Option<String>,
+    /// 本次采样后自动扩容新建的连接数
+    pub grown: u32,
+}
+
+impl PoolStats {
+    /// 连接池当前的连接数，不测量等待时间
+    pub fn snapshot(pool: &PgPool) -> Self {
+        let size = pool.size();
+        let idle = (pool.num_idle() as u32).min(size);
+        Self {
+            size,
+            idle,
+            in_use: size - idle,
+            max: pool.options().get_max_connections(),
+            acquire_wait: Duration::ZERO,
+            acquire_error: None,
+            grown: 0,
+        }
+    }
+}
+
+impl fmt::Display for PoolStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} connections in use, {} idle", self.in_use, self.max, self.idle)
+    }
+}
+
+/// 连接池等待时间采样与自动扩容
+pub struct PoolMonitor {
+    pool: PgPool,
+    config: DatabasePoolConfig,
+    listener: Option<SampleListener>,
+}
+
+impl PoolMonitor {
+    pub fn new(pool: PgPool, config: &DatabasePoolConfig) -> Self {
+        Self {
+            pool,
+            config: config.clone(),
+            listener: None,
+        }
+    }
+
+    /// 每次采样后的回调，用于记录日志和指标
+    pub fn with_listener(mut self, listener: impl Fn(&PoolStats) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// 采样间隔
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.sample_interval_seconds)
+    }
+
+    /// 取一次连接并立即归还，测量等待时间；超过阈值且启用自动扩容时提前建立连接
+    pub async fn sample(&self) -> PoolStats {
+        let started = Instant::now();
+        let acquired = self.pool.acquire().await;
+        let acquire_wait = started.elapsed();
+        let acquire_error = acquired.err().map(|e| e.to_string());
+
+        let resize = &self.config.resize;
+        let grown = if resize.enabled && acquire_wait >= Duration::from_millis(resize.wait_threshold_ms) {
+            self.grow(resize.step).await
+        } else {
+            0
+        };
+
+        let stats = PoolStats {
+            acquire_wait,
+            acquire_error,
+            grown,
+            ..PoolStats::snapshot(&self.pool)
+        };
+        if let Some(listener) = &self.listener {
+            listener(&stats);
+        }
+        stats
+    }
+
+    // 同时持有若干连接，空闲连接用完后连接池会新建连接，归还后作为空闲连接供后续请求使用
+    async fn grow(&self, step: u32) -> u32 {
+        let before = self.pool.size();
+        let count = step.min(self.pool.options().get_max_connections().saturating_sub(before));
+        let mut held = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            match self.pool.acquire().await {
+                Ok(conn) => held.push(conn),
+                Err(_) => break,
+            }
+        }
+        drop(held);
+        self.pool.size().saturating_sub(before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_options_follow_config() {
+        let mut config = crate::AppConfig::default().database;
+        config.pool.acquire_timeout_seconds = 3;
+        config.pool.idle_timeout_seconds = 0;
+
+        let pool = pool_options(&config).connect_lazy(&config.url).unwrap();
+        assert_eq!(pool.options().get_max_connections(), config.max_connections);
+        assert_eq!(pool.options().get_acquire_timeout(), Duration::from_secs(3));
+        assert_eq!(pool.options().get_idle_timeout(), None);
+
+        let stats = PoolStats::snapshot(&pool);
+        assert_eq!((stats.size, stats.in_use), (0, 0));
+        assert_eq!(stats.to_string(), format!("0/{} connections in use, 0 idle", config.max_connections));
+    }
+}
@@ -0,0 +1,197 @@
+//! Redis 分布式锁
+//!
+//! 用于多个实例之间不能并发执行的设备级操作（如固件升级的某一步、会话恢复）。
+//! 加锁使用 `SET key token NX PX ttl`，每次加锁生成随机令牌；续期和释放在 Lua 脚本中先比较令牌，
+//! 锁过期后被其他实例获得时，原持有者不会误删或续期别人的锁。
+//!
+//! 持有者异常退出时锁在 TTL 到期后自动释放。执行时间可能超过 TTL 的操作使用 `spawn_auto_extend`
+//! 在后台每隔 TTL 的三分之一续期一次，续期失败（锁已丢失）时通过 `LockExtension::is_lost` 告知持有者。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use redis::aio::ConnectionLike;
+use redis::{RedisResult, Script};
+use tokio::task::JoinHandle;
+
+use crate::cache::CacheKey;
+use crate::utils::generate_uuid;
+
+// KEYS[1]：锁；ARGV[1]：令牌；ARGV[2]：新的过期时间（毫秒）
+const EXTEND_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+// KEYS[1]：锁；ARGV[1]：令牌
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+// 等待加锁时的重试间隔
+const ACQUIRE_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 一把分布式锁，每个实例创建自己的 `DistributedLock`，令牌在创建时生成
+pub struct DistributedLock {
+    key: CacheKey,
+    token: String,
+    ttl: Duration,
+    extend: Script,
+    release: Script,
+}
+
+impl DistributedLock {
+    pub fn new(key: CacheKey, ttl: Duration) -> Self {
+        Self {
+            key,
+            token: generate_uuid(),
+            ttl: ttl.max(Duration::from_millis(1)),
+            extend: Script::new(EXTEND_SCRIPT),
+            release: Script::new(RELEASE_SCRIPT),
+        }
+    }
+
+    /// 设备上的某种操作（如 `restart`），同一设备的不同操作互不影响
+    pub fn device(device_id: &str, operation: &str, ttl: Duration) -> Self {
+        Self::new(CacheKey::device_lock(device_id, operation), ttl)
+    }
+
+    pub fn key(&self) -> &CacheKey {
+        &self.key
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// 尝试加锁一次，锁已被持有时返回 false
+    pub async fn try_acquire<C: ConnectionLike>(&self, conn: &mut C) -> RedisResult<bool> {
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&self.key)
+            .arg(&self.token)
+            .arg("NX")
+            .arg("PX")
+            .arg(self.ttl_millis())
+            .query_async(conn)
+            .await?;
+        Ok(acquired.is_some())
+    }
+
+    /// 在 `wait` 内反复尝试加锁，超时仍未获得时返回 false
+    pub async fn acquire<C: ConnectionLike>(&self, conn: &mut C, wait: Duration) -> RedisResult<bool> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            if self.try_acquire(conn).await? {
+                return Ok(true);
+            }
+            if tokio::time::Instant::now() + ACQUIRE_RETRY_INTERVAL > deadline {
+                return Ok(false);
+            }
+            tokio::time::sleep(ACQUIRE_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// 续期到一个完整的 TTL，锁已不属于本实例时返回 false
+    pub async fn extend<C: ConnectionLike>(&self, conn: &mut C) -> RedisResult<bool> {
+        let extended: i64 = self
+            .extend
+            .key(&self.key)
+            .arg(&self.token)
+            .arg(self.ttl_millis())
+            .invoke_async(conn)
+            .await?;
+        Ok(extended == 1)
+    }
+
+    /// 释放锁，锁已过期或已被其他实例获得时返回 false
+    pub async fn release<C: ConnectionLike>(&self, conn: &mut C) -> RedisResult<bool> {
+        let released: i64 = self.release.key(&self.key).arg(&self.token).invoke_async(conn).await?;
+        Ok(released == 1)
+    }
+
+    /// 在后台定期续期，返回的 `LockExtension` 被丢弃或调用 `stop` 时停止续期（不释放锁）
+    ///
+    /// 续期返回 false 说明锁已丢失，此后不再续期；Redis 错误不视为丢失，下一轮继续续期。
+    pub fn spawn_auto_extend<C>(self: &Arc<Self>, mut conn: C) -> LockExtension
+    where
+        C: ConnectionLike + Send + 'static,
+    {
+        let lock = self.clone();
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = lost.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(lock.extend_interval());
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // 第一次 tick 立即返回，刚加锁时不需要续期
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Ok(false) = lock.extend(&mut conn).await {
+                    lost_flag.store(true, Ordering::Release);
+                    return;
+                }
+            }
+        });
+        LockExtension { handle, lost }
+    }
+
+    fn ttl_millis(&self) -> u64 {
+        self.ttl.as_millis() as u64
+    }
+
+    // 每隔 TTL 的三分之一续期一次，一两次续期失败不会导致锁过期
+    fn extend_interval(&self) -> Duration {
+        (self.ttl / 3).max(Duration::from_millis(1))
+    }
+}
+
+/// 后台续期任务
+pub struct LockExtension {
+    handle: JoinHandle<()>,
+    lost: Arc<AtomicBool>,
+}
+
+impl LockExtension {
+    /// 续期时发现锁已不属于本实例，持有者应尽快中止操作
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    pub fn stop(self) {}
+}
+
+impl Drop for LockExtension {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_lock_key_and_extend_interval() {
+        let lock = DistributedLock::device("dev-1", "restart", Duration::from_secs(30));
+        assert_eq!(lock.key().as_str(), "lock:device:dev-1:restart");
+        assert_eq!(lock.ttl_millis(), 30_000);
+        assert_eq!(lock.extend_interval(), Duration::from_secs(10));
+
+        // 每个实例的令牌不同，释放时不会删除其他实例的锁
+        let other = DistributedLock::device("dev-1", "restart", Duration::from_secs(30));
+        assert_eq!(lock.key(), other.key());
+        assert_ne!(lock.token, other.token);
+
+        assert_ne!(
+            DistributedLock::device("dev-1", "restart", Duration::ZERO).key(),
+            DistributedLock::device("dev-1", "recover", Duration::ZERO).key()
+        );
+        assert_eq!(DistributedLock::device("dev-1", "restart", Duration::ZERO).ttl(), Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,235 @@
+//! EchoKit Server 事件模型
+//!
+//! EchoKit Server 通过 WebSocket 二进制帧发送 MessagePack 编码的事件（外部标签枚举）：
+//! 无字段的事件编码为字符串（如 "HelloStart"），带字段的事件编码为
+//! `{事件名: [字段...]}`，音频数据使用 MessagePack bin 类型。
+
+use serde::{Deserialize, Serialize};
+
+/// EchoKit Server 事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EchoKitEvent {
+    /// 开始发送问候音频
+    HelloStart,
+
+    /// 问候音频数据块（16-bit PCM, 16000Hz, 单声道）
+    HelloChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 问候音频结束
+    HelloEnd,
+
+    /// 开始发送背景音乐
+    BGStart,
+
+    /// 背景音乐数据块
+    BGChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 背景音乐结束
+    BGEnd,
+
+    /// 语音识别结果
+    ASR { text: String },
+
+    /// 动作指令
+    Action { action: String },
+
+    /// 开始音频响应，text 为本段 AI 回复文本
+    StartAudio { text: String },
+
+    /// 音频响应数据块（16-bit PCM, 16000Hz, 单声道）
+    AudioChunk {
+        #[serde(with = "bytes")]
+        data: Vec<u8>,
+    },
+
+    /// 音频响应结束
+    EndAudio,
+
+    /// 开始视频响应
+    StartVideo,
+
+    /// 视频响应结束
+    EndVideo,
+
+    /// 本轮响应结束
+    EndResponse,
+}
+
+impl EchoKitEvent {
+    /// 从 EchoKit Server 发送的 MessagePack 帧解码事件
+    pub fn from_messagepack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(data)
+    }
+
+    /// 编码为与 EchoKit Server 相同的 MessagePack 格式
+    pub fn to_messagepack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    /// 直接将音频数据编码为 AudioChunk 帧，无需先复制到 `EchoKitEvent::AudioChunk`
+    pub fn encode_audio_chunk(data: &[u8]) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        // 与 EchoKitEvent::AudioChunk 的线上格式相同，只是借用数据
+        #[derive(Serialize)]
+        enum AudioChunkRef<'a> {
+            AudioChunk {
+                #[serde(with = "bytes")]
+                data: &'a [u8],
+            },
+        }
+
+        // 预留标签和 bin 长度头的空间
+        let mut frame = Vec::with_capacity(data.len() + 32);
+        rmp_serde::encode::write(&mut frame, &AudioChunkRef::AudioChunk { data })?;
+        Ok(frame)
+    }
+
+    /// 事件名称（与线上格式中的标签一致）
+    pub fn name(&self) -> &'static str {
+        match self {
+            EchoKitEvent::HelloStart => "HelloStart",
+            EchoKitEvent::HelloChunk { .. } => "HelloChunk",
+            EchoKitEvent::HelloEnd => "HelloEnd",
+            EchoKitEvent::BGStart => "BGStart",
+            EchoKitEvent::BGChunk { .. } => "BGChunk",
+            EchoKitEvent::BGEnd => "BGEnd",
+            EchoKitEvent::ASR { .. } => "ASR",
+            EchoKitEvent::Action { .. } => "Action",
+            EchoKitEvent::StartAudio { .. } => "StartAudio",
+            EchoKitEvent::AudioChunk { .. } => "AudioChunk",
+            EchoKitEvent::EndAudio => "EndAudio",
+            EchoKitEvent::StartVideo => "StartVideo",
+            EchoKitEvent::EndVideo => "EndVideo",
+            EchoKitEvent::EndResponse => "EndResponse",
+        }
+    }
+
+    /// 是否属于问候语序列（需要缓存并回放给后续会话）
+    pub fn is_hello(&self) -> bool {
+        matches!(
+            self,
+            EchoKitEvent::HelloStart | EchoKitEvent::HelloChunk { .. } | EchoKitEvent::HelloEnd
+        )
+    }
+}
+
+// 音频数据编码为 MessagePack bin；解码时同时接受 bin 和整数数组
+mod bytes {
+    use serde::de::{Deserializer, SeqAccess, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("audio bytes")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                Ok(v.to_vec())
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    data.push(byte);
+                }
+                Ok(data)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // EchoKit Server 帧样本（tests/fixtures/echokit）
+    const HELLO_START: &[u8] = include_bytes!("../tests/fixtures/echokit/hello_start.msgpack");
+    const HELLO_CHUNK: &[u8] = include_bytes!("../tests/fixtures/echokit/hello_chunk.msgpack");
+    const HELLO_END: &[u8] = include_bytes!("../tests/fixtures/echokit/hello_end.msgpack");
+    const ASR: &[u8] = include_bytes!("../tests/fixtures/echokit/asr.msgpack");
+    const START_AUDIO: &[u8] = include_bytes!("../tests/fixtures/echokit/start_audio.msgpack");
+    const AUDIO_CHUNK: &[u8] = include_bytes!("../tests/fixtures/echokit/audio_chunk.msgpack");
+    const END_AUDIO: &[u8] = include_bytes!("../tests/fixtures/echokit/end_audio.msgpack");
+    const END_RESPONSE: &[u8] = include_bytes!("../tests/fixtures/echokit/end_response.msgpack");
+
+    // 样本中的音频：8 个 16-bit 小端采样
+    const PCM: [u8; 16] = [0, 0, 0, 2, 0, 4, 0, 2, 0, 0, 0, 254, 0, 252, 0, 254];
+
+    #[test]
+    fn test_decode_fixtures() {
+        assert_eq!(EchoKitEvent::from_messagepack(HELLO_START).unwrap(), EchoKitEvent::HelloStart);
+        assert_eq!(
+            EchoKitEvent::from_messagepack(HELLO_CHUNK).unwrap(),
+            EchoKitEvent::HelloChunk { data: PCM.to_vec() }
+        );
+        assert_eq!(EchoKitEvent::from_messagepack(HELLO_END).unwrap(), EchoKitEvent::HelloEnd);
+        assert_eq!(
+            EchoKitEvent::from_messagepack(ASR).unwrap(),
+            EchoKitEvent::ASR { text: "今天天气怎么样".to_string() }
+        );
+        assert_eq!(
+            EchoKitEvent::from_messagepack(START_AUDIO).unwrap(),
+            EchoKitEvent::StartAudio { text: "今天是晴天".to_string() }
+        );
+        assert_eq!(
+            EchoKitEvent::from_messagepack(AUDIO_CHUNK).unwrap(),
+            EchoKitEvent::AudioChunk { data: PCM.to_vec() }
+        );
+        assert_eq!(EchoKitEvent::from_messagepack(END_AUDIO).unwrap(), EchoKitEvent::EndAudio);
+        assert_eq!(EchoKitEvent::from_messagepack(END_RESPONSE).unwrap(), EchoKitEvent::EndResponse);
+    }
+
+    #[test]
+    fn test_fixtures_round_trip_byte_for_byte() {
+        for frame in [HELLO_START, HELLO_CHUNK, HELLO_END, ASR, START_AUDIO, AUDIO_CHUNK, END_AUDIO, END_RESPONSE] {
+            let event = EchoKitEvent::from_messagepack(frame).unwrap();
+            assert_eq!(event.to_messagepack().unwrap(), frame, "{} did not round-trip", event.name());
+        }
+    }
+
+    #[test]
+    fn test_encode_audio_chunk_matches_event_encoding() {
+        assert_eq!(EchoKitEvent::encode_audio_chunk(&PCM).unwrap(), AUDIO_CHUNK);
+    }
+
+    #[test]
+    fn test_audio_accepts_integer_arrays() {
+        // protocol::ServerEvent 将音频编码为整数数组
+        let legacy = rmp_serde::to_vec(&crate::protocol::ServerEvent::AudioChunk { data: PCM.to_vec() }).unwrap();
+        assert_eq!(
+            EchoKitEvent::from_messagepack(&legacy).unwrap(),
+            EchoKitEvent::AudioChunk { data: PCM.to_vec() }
+        );
+    }
+
+    #[test]
+    fn test_hello_events_and_unknown_frames() {
+        assert!(EchoKitEvent::from_messagepack(HELLO_CHUNK).unwrap().is_hello());
+        assert!(!EchoKitEvent::from_messagepack(ASR).unwrap().is_hello());
+
+        // 未定义的事件无法解码，由调用方决定是否原样转发
+        let unknown = rmp_serde::to_vec(&"ListeningStart").unwrap();
+        assert!(EchoKitEvent::from_messagepack(&unknown).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! 转写文本和录音文件的静态加密（信封加密）
+//!
+//! 每个会话生成独立的 AES-256-GCM 数据密钥加密转写、AI 回复和录音，会话进行中的多次写入共用同一个数据密钥
+//! （`session_key`），会话结束时释放。数据密钥由主密钥包装后与密文一起保存，
+//! 数据库中不需要额外的密钥表。文本密文的格式为 `enc:v1:{主密钥 ID}:{包装后的数据密钥}:{随机数+密文}`
+//! （Base64），二进制密文以 `ECHOENC1` 开头。不带前缀的值视为加密启用前写入的明文，读取时原样返回。
+//!
+//! 轮换主密钥时把旧密钥移到 `encryption.previous_master_keys`，`rewrap_*` 用当前主密钥重新包装数据密钥，
+//! 密文本身不需要重新加密。
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::types::EncryptionConfig;
+
+/// 文本密文前缀
+pub const TEXT_PREFIX: &str = "enc:v1:";
+/// 二进制密文的文件头
+pub const BINARY_MAGIC: &[u8; 8] = b"ECHOENC1";
+
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("master key {0} must be 32 bytes encoded as Base64")]
+    InvalidMasterKey(String),
+    #[error("master key id {0:?} must be non-empty and must not contain ':'")]
+    InvalidKeyId(String),
+    #[error("data was encrypted with unknown master key {0}")]
+    UnknownKey(String),
+    #[error("malformed ciphertext")]
+    Malformed,
+    #[error("decryption failed")]
+    DecryptFailed,
+}
+
+/// 一个会话的数据密钥，同一会话的转写、回复和录音共用
+pub struct DataKey {
+    cipher: Aes256Gcm,
+    // {主密钥 ID}:{包装后的数据密钥}
+    header: String,
+}
+
+impl DataKey {
+    /// 加密文本，返回带前缀的密文
+    pub fn encrypt_text(&self, plaintext: &str) -> String {
+        let sealed = seal(&self.cipher, plaintext.as_bytes(), b"");
+        format!("{}{}:{}", TEXT_PREFIX, self.header, STANDARD.encode(sealed))
+    }
+
+    /// 加密二进制数据（录音文件）
+    pub fn encrypt_bytes(&self, plaintext: &[u8]) -> Vec<u8> {
+        let sealed = seal(&self.cipher, plaintext, b"");
+        let mut data = Vec::with_capacity(BINARY_MAGIC.len() + 2 + self.header.len() + sealed.len());
+        data.extend_from_slice(BINARY_MAGIC);
+        data.extend_from_slice(&(self.header.len() as u16).to_be_bytes());
+        data.extend_from_slice(self.header.as_bytes());
+        data.extend_from_slice(&sealed);
+        data
+    }
+}
+
+pub struct FieldEncryptor {
+    current_key_id: String,
+    master_keys: HashMap<String, Aes256Gcm>,
+    // 进行中会话的数据密钥，会话结束时释放
+    session_keys: Mutex<HashMap<String, Arc<DataKey>>>,
+}
+
+impl FieldEncryptor {
+    /// 未启用加密时返回 None
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>, EncryptionError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        Self::new(config).map(Some)
+    }
+
+    /// 解析当前和轮换前的主密钥
+    pub fn new(config: &EncryptionConfig) -> Result<Self, EncryptionError> {
+        let mut master_keys = HashMap::new();
+        // 当前主密钥最后插入，previous_master_keys 中的同名条目不会覆盖它
+        let keys = config.previous_master_keys.iter().chain([(&config.master_key_id, &config.master_key)]);
+        for (key_id, encoded) in keys {
+            if key_id.is_empty() || key_id.contains(':') {
+                return Err(EncryptionError::InvalidKeyId(key_id.clone()));
+            }
+            let key = STANDARD
+                .decode(encoded.trim())
+                .ok()
+                .filter(|key| key.len() == 32)
+                .ok_or_else(|| EncryptionError::InvalidMasterKey(key_id.clone()))?;
+            master_keys.insert(key_id.clone(), Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)));
+        }
+
+        Ok(Self {
+            current_key_id: config.master_key_id.clone(),
+            master_keys,
+            session_keys: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current_key_id
+    }
+
+    /// 生成新的数据密钥，并用当前主密钥包装
+    pub fn data_key(&self) -> DataKey {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let wrapped = seal(&self.master_keys[&self.current_key_id], &key, self.current_key_id.as_bytes());
+        DataKey {
+            cipher: Aes256Gcm::new(&key),
+            header: format!("{}:{}", self.current_key_id, STANDARD.encode(wrapped)),
+        }
+    }
+
+    /// 会话的数据密钥，首次使用时生成，之后该会话的转写、回复和录音都用它加密
+    pub fn session_key(&self, session_id: &str) -> Arc<DataKey> {
+        self.session_keys
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(session_id.to_string())
+            .or_insert_with(|| Arc::new(self.data_key()))
+            .clone()
+    }
+
+    /// 会话结束后释放其数据密钥，已写入的密文仍可用主密钥解开
+    pub fn release_session_key(&self, session_id: &str) {
+        self.session_keys.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id);
+    }
+
+    /// 值是否为文本密文
+    pub fn is_encrypted(value: &str) -> bool {
+        value.starts_with(TEXT_PREFIX)
+    }
+
+    /// 解密文本，不带前缀的明文原样返回
+    pub fn decrypt_text<'a>(&self, value: &'a str) -> Result<Cow<'a, str>, EncryptionError> {
+        let Some(rest) = value.strip_prefix(TEXT_PREFIX) else {
+            return Ok(Cow::Borrowed(value));
+        };
+        let (key_id, wrapped, sealed) = split_text(rest)?;
+        let cipher = self.unwrap_data_key(key_id, wrapped)?;
+        let sealed = STANDARD.decode(sealed).map_err(|_| EncryptionError::Malformed)?;
+        let plaintext = open(&cipher, &sealed, b"")?;
+        String::from_utf8(plaintext).map(Cow::Owned).map_err(|_| EncryptionError::Malformed)
+    }
+
+    /// 解密二进制数据，不以 `ECHOENC1` 开头的数据原样返回
+    pub fn decrypt_bytes<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>, EncryptionError> {
+        let Some((header, sealed)) = split_binary(data)? else {
+            return Ok(Cow::Borrowed(data));
+        };
+        let (key_id, wrapped) = header.split_once(':').ok_or(EncryptionError::Malformed)?;
+        let cipher = self.unwrap_data_key(key_id, wrapped)?;
+        open(&cipher, sealed, b"").map(Cow::Owned)
+    }
+
+    /// 用当前主密钥重新包装文本密文的数据密钥，已是当前主密钥或不是密文时返回 None
+    pub fn rewrap_text(&self, value: &str) -> Result<Option<String>, EncryptionError> {
+        let Some(rest) = value.strip_prefix(TEXT_PREFIX) else {
+            return Ok(None);
+        };
+        let (key_id, wrapped, sealed) = split_text(rest)?;
+        if key_id == self.current_key_id {
+            return Ok(None);
+        }
+        let header = self.rewrap_header(key_id, wrapped)?;
+        Ok(Some(format!("{}{}:{}", TEXT_PREFIX, header, sealed)))
+    }
+
+    /// 用当前主密钥重新包装二进制密文的数据密钥，已是当前主密钥或不是密文时返回 None
+    pub fn rewrap_bytes(&self, data: &[u8]) -> Result<Option<Vec<u8>>, EncryptionError> {
+        let Some((header, sealed)) = split_binary(data)? else {
+            return Ok(None);
+        };
+        let (key_id, wrapped) = header.split_once(':').ok_or(EncryptionError::Malformed)?;
+        if key_id == self.current_key_id {
+            return Ok(None);
+        }
+        let header = self.rewrap_header(key_id, wrapped)?;
+        let mut rewrapped = Vec::with_capacity(BINARY_MAGIC.len() + 2 + header.len() + sealed.len());
+        rewrapped.extend_from_slice(BINARY_MAGIC);
+        rewrapped.extend_from_slice(&(header.len() as u16).to_be_bytes());
+        rewrapped.extend_from_slice(header.as_bytes());
+        rewrapped.extend_from_slice(sealed);
+        Ok(Some(rewrapped))
+    }
+
+    fn master_key(&self, key_id: &str) -> Result<&Aes256Gcm, EncryptionError> {
+        self.master_keys.get(key_id).ok_or_else(|| EncryptionError::UnknownKey(key_id.to_string()))
+    }
+
+    fn unwrap_data_key_bytes(&self, key_id: &str, wrapped: &str) -> Result<Vec<u8>, EncryptionError> {
+        let wrapped = STANDARD.decode(wrapped).map_err(|_| EncryptionError::Malformed)?;
+        let key = open(self.master_key(key_id)?, &wrapped, key_id.as_bytes())?;
+        if key.len() != 32 {
+            return Err(EncryptionError::Malformed);
+        }
+        Ok(key)
+    }
+
+    fn unwrap_data_key(&self, key_id: &str, wrapped: &str) -> Result<Aes256Gcm, EncryptionError> {
+        let key = self.unwrap_data_key_bytes(key_id, wrapped)?;
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+
+    fn rewrap_header(&self, key_id: &str, wrapped: &str) -> Result<String, EncryptionError> {
+        let key = self.unwrap_data_key_bytes(key_id, wrapped)?;
+        let rewrapped = seal(&self.master_keys[&self.current_key_id], &key, self.current_key_id.as_bytes());
+        Ok(format!("{}:{}", self.current_key_id, STANDARD.encode(rewrapped)))
+    }
+}
+
+// 随机数在前，密文和认证标签在后
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .expect("AES-GCM encryption only fails for inputs larger than 64 GiB");
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    sealed
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(EncryptionError::Malformed);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| EncryptionError::DecryptFailed)
+}
+
+// {主密钥 ID}:{包装后的数据密钥}:{随机数+密文}
+fn split_text(rest: &str) -> Result<(&str, &str, &str), EncryptionError> {
+    let mut parts = rest.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(key_id), Some(wrapped), Some(sealed)) => Ok((key_id, wrapped, sealed)),
+        _ => Err(EncryptionError::Malformed),
+    }
+}
+
+// 返回 (密钥头, 随机数+密文)，不是二进制密文时返回 None
+fn split_binary(data: &[u8]) -> Result<Option<(&str, &[u8])>, EncryptionError> {
+    let Some(rest) = data.strip_prefix(BINARY_MAGIC.as_slice()) else {
+        return Ok(None);
+    };
+    if rest.len() < 2 {
+        return Err(EncryptionError::Malformed);
+    }
+    let (len, rest) = rest.split_at(2);
+    let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+    if rest.len() < len {
+        return Err(EncryptionError::Malformed);
+    }
+    let (header, sealed) = rest.split_at(len);
+    let header = std::str::from_utf8(header).map_err(|_| EncryptionError::Malformed)?;
+    Ok(Some((header, sealed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(key_id: &str, key: u8) -> EncryptionConfig {
+        EncryptionConfig {
+            enabled: true,
+            master_key_id: key_id.to_string(),
+            master_key: STANDARD.encode([key; 32]),
+            ..EncryptionConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let encryptor = FieldEncryptor::new(&config("v1", 1)).unwrap();
+        let key = encryptor.data_key();
+
+        let sealed = key.encrypt_text("今天天气怎么样");
+        assert!(FieldEncryptor::is_encrypted(&sealed));
+        assert_eq!(encryptor.decrypt_text(&sealed).unwrap(), "今天天气怎么样");
+        // 加密启用前写入的明文原样返回
+        assert!(matches!(encryptor.decrypt_text("旧数据").unwrap(), Cow::Borrowed("旧数据")));
+
+        let audio = key.encrypt_bytes(&[1, 2, 3, 4]);
+        assert_eq!(encryptor.decrypt_bytes(&audio).unwrap().as_ref(), &[1, 2, 3, 4]);
+
+        let mut tampered = sealed.clone();
+        tampered.pop();
+        tampered.push(if sealed.ends_with('A') { 'B' } else { 'A' });
+        assert!(encryptor.decrypt_text(&tampered).is_err());
+
+        let other = FieldEncryptor::new(&config("v1", 2)).unwrap();
+        assert_eq!(other.decrypt_text(&sealed), Err(EncryptionError::DecryptFailed));
+    }
+
+    #[test]
+    fn test_session_key_is_reused_until_released() {
+        let encryptor = FieldEncryptor::new(&config("v1", 1)).unwrap();
+        let header = |text: &str| text.rsplit_once(':').unwrap().0.to_string();
+
+        let first = encryptor.session_key("session_a").encrypt_text("你好");
+        let second = encryptor.session_key("session_a").encrypt_text("再见");
+        assert_eq!(header(&first), header(&second));
+        assert_ne!(header(&first), header(&encryptor.session_key("session_b").encrypt_text("你好")));
+
+        encryptor.release_session_key("session_a");
+        let after_release = encryptor.session_key("session_a").encrypt_text("你好");
+        assert_ne!(header(&first), header(&after_release));
+        assert_eq!(encryptor.decrypt_text(&second).unwrap(), "再见");
+    }
+
+    #[test]
+    fn test_rewrap_after_rotation() {
+        let old = FieldEncryptor::new(&config("v1", 1)).unwrap();
+        let key = old.data_key();
+        let text = key.encrypt_text("hello");
+        let audio = key.encrypt_bytes(b"pcm");
+
+        let mut rotated = config("v2", 2);
+        rotated.previous_master_keys.insert("v1".to_string(), STANDARD.encode([1u8; 32]));
+        let encryptor = FieldEncryptor::new(&rotated).unwrap();
+        assert_eq!(encryptor.decrypt_text(&text).unwrap(), "hello");
+
+        let text = encryptor.rewrap_text(&text).unwrap().unwrap();
+        let audio = encryptor.rewrap_bytes(&audio).unwrap().unwrap();
+        assert!(text.starts_with("enc:v1:v2:"));
+        assert_eq!(encryptor.rewrap_text(&text).unwrap(), None);
+
+        // 删除旧主密钥后仍可解密
+        let current = FieldEncryptor::new(&config("v2", 2)).unwrap();
+        assert_eq!(current.decrypt_text(&text).unwrap(), "hello");
+        assert_eq!(current.decrypt_bytes(&audio).unwrap().as_ref(), b"pcm");
+        assert_eq!(old.decrypt_text(&text), Err(EncryptionError::UnknownKey("v2".to_string())));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let mut short = config("v1", 1);
+        short.master_key = STANDARD.encode([1u8; 16]);
+        assert!(matches!(FieldEncryptor::new(&short), Err(EncryptionError::InvalidMasterKey(_))));
+        assert!(matches!(FieldEncryptor::new(&config("a:b", 1)), Err(EncryptionError::InvalidKeyId(_))));
+        assert!(FieldEncryptor::from_config(&EncryptionConfig::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,40 @@
+// 内部 gRPC 接口：由 proto/echo_internal.proto 生成的消息类型、服务端和客户端
+// 拦截器签名由 tonic 规定，Status 体积较大无法避免
+#![allow(clippy::result_large_err)]
+use tonic::{metadata::MetadataValue, Request, Status};
+
+tonic::include_proto!("echo.internal.v1");
+
+/// 设备音频流接口：由 proto/echo_audio.proto 生成，作为 WebSocket 之外的设备接入方式
+pub mod audio {
+    tonic::include_proto!("echo.audio.v1");
+}
+
+/// 服务端拦截器：校验 authorization 元数据中的内部 API 令牌
+pub fn require_internal_token(token: String) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone {
+    move |request: Request<()>| {
+        let presented = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match presented {
+            Some(presented) if crate::utils::constant_time_eq(presented.as_bytes(), token.as_bytes()) => Ok(request),
+            Some(_) => Err(Status::unauthenticated("Invalid internal API token")),
+            None => Err(Status::unauthenticated("Missing internal API token")),
+        }
+    }
+}
+
+/// 客户端拦截器：为每个请求附加内部 API 令牌
+pub fn with_internal_token(token: &str) -> Result<impl Fn(Request<()>) -> Result<Request<()>, Status> + Clone, Status> {
+    let value: MetadataValue<_> = format!("Bearer {}", token)
+        .parse()
+        .map_err(|_| Status::invalid_argument("Internal API token is not a valid header value"))?;
+
+    Ok(move |mut request: Request<()>| {
+        request.metadata_mut().insert("authorization", value.clone());
+        Ok(request)
+    })
+}
@@ -0,0 +1,109 @@
+//! 存活与就绪探针
+//!
+//! `/health/live` 只表示进程在运行；`/health/ready` 逐项检查依赖组件，
+//! 配置为关键依赖（`health.*_critical`）的组件全部可用时才算就绪。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 就绪检查支持的依赖组件名称
+pub const HEALTH_COMPONENTS: &[&str] = &["database", "redis", "mqtt", "echokit", "udp", "gateway", "tasks"];
+
+/// 组件状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentStatus {
+    Up,
+    Down,
+}
+
+/// 单个依赖组件的检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub status: ComponentStatus,
+    /// 是否为关键依赖（不可用时服务不就绪）
+    pub critical: bool,
+    /// 附加说明，如错误信息或监听地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+    pub fn up(name: &str) -> Self {
+        Self { name: name.to_string(), status: ComponentStatus::Up, critical: false, detail: None }
+    }
+
+    pub fn down(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: ComponentStatus::Down,
+            critical: false,
+            detail: Some(detail.into()),
+        }
+    }
+
+    /// 根据检查结果创建，错误信息作为说明
+    pub fn from_result<E: std::fmt::Display>(name: &str, result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => Self::up(name),
+            Err(e) => Self::down(name, e.to_string()),
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// 就绪检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub service: String,
+    pub components: Vec<ComponentHealth>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl ReadinessReport {
+    /// 标记关键依赖并汇总就绪状态
+    pub fn new(service: &str, mut components: Vec<ComponentHealth>, critical: &[String]) -> Self {
+        for component in &mut components {
+            component.critical = critical.iter().any(|name| name == &component.name);
+        }
+        let ready = components
+            .iter()
+            .all(|component| !component.critical || component.status == ComponentStatus::Up);
+
+        Self { ready, service: service.to_string(), components, timestamp: Utc::now() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_critical_components_affect_readiness() {
+        let critical = vec!["database".to_string(), "udp".to_string()];
+        let components = || {
+            vec![
+                ComponentHealth::up("database"),
+                ComponentHealth::down("echokit", "no active connections"),
+                ComponentHealth::up("udp").with_detail("0.0.0.0:8083"),
+            ]
+        };
+
+        let report = ReadinessReport::new("echo-bridge", components(), &critical);
+        assert!(report.ready);
+        assert!(report.components[0].critical);
+        assert!(!report.components[1].critical);
+
+        let mut down = components();
+        down[0] = ComponentHealth::from_result("database", Err("connection refused"));
+        let report = ReadinessReport::new("echo-bridge", down, &critical);
+        assert!(!report.ready);
+        assert_eq!(report.components[0].detail.as_deref(), Some("connection refused"));
+    }
+}
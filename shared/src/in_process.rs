@@ -0,0 +1,58 @@
+//! 单进程模式（etch-all）
+//!
+//! Bridge 和 API Gateway 运行在同一进程时共享一个存储连接，Bridge 的会话实时事件
+//! （阶段变化、ASR 结果）经进程内事件总线直接交给 API Gateway，不再经过 MQTT。
+//! 日志和指标记录器由 etch-all 统一初始化。
+
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+
+use crate::mqtt::MqttPayload;
+use crate::storage::Storage;
+
+/// 事件总线的缓冲条数，订阅方落后超过该数量时丢弃最早的事件
+pub const EVENT_BUS_CAPACITY: usize = 1000;
+
+/// 进程内实时事件总线
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MqttPayload>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// 发布事件，没有订阅方时直接丢弃
+    pub fn publish(&self, payload: MqttPayload) {
+        let _ = self.sender.send(payload);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MqttPayload> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(EVENT_BUS_CAPACITY)
+    }
+}
+
+/// 单进程模式下 Bridge 和 API Gateway 共享的资源
+#[derive(Clone)]
+pub struct InProcess {
+    /// 共享的存储（PostgreSQL 或 SQLite）
+    pub storage: Arc<dyn Storage>,
+    /// Bridge -> API Gateway 的实时事件
+    pub events: EventBus,
+}
+
+impl InProcess {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage, events: EventBus::default() }
+    }
+}
@@ -0,0 +1,131 @@
+//! 外部输入的大小检查
+//!
+//! Bridge 和 API Gateway 接收的 WebSocket 帧、UDP 数据包、HTTP 请求体和 MessagePack 数据都按
+//! `[limits]`（`IngressLimitsConfig`）限制大小，超出限制的输入在分配内存或解码之前被拒绝，
+//! 并计入各服务的 `*_ingress_rejected_total` 指标（ingress: websocket / udp / http / messagepack）。
+
+/// MessagePack 数据检查失败的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MessagePackRejected {
+    #[error("MessagePack nesting depth exceeds {0}")]
+    TooDeep(usize),
+    #[error("Malformed MessagePack data")]
+    Malformed,
+}
+
+/// 检查 MessagePack 数据中第一个值的数组和映射嵌套深度
+///
+/// 只扫描类型标记和长度，不分配内存也不递归，深度很大的恶意数据不会耗尽解码器的栈。
+/// 元素数量声明得比实际数据多时在数据结束处返回 `Malformed`。
+pub fn check_messagepack_depth(data: &[u8], max_depth: usize) -> Result<(), MessagePackRejected> {
+    // 每个未结束的数组/映射中剩余的元素数，最外层是一个待读取的值
+    let mut remaining: Vec<u64> = vec![1];
+    let mut pos = 0usize;
+
+    while let Some(top) = remaining.last_mut() {
+        if *top == 0 {
+            remaining.pop();
+            continue;
+        }
+        *top -= 1;
+
+        let marker = *data.get(pos).ok_or(MessagePackRejected::Malformed)?;
+        pos += 1;
+        let (skip, children) = match marker {
+            0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => (0, None),
+            0x80..=0x8f => (0, Some(u64::from(marker & 0x0f) * 2)),
+            0x90..=0x9f => (0, Some(u64::from(marker & 0x0f))),
+            0xa0..=0xbf => (u64::from(marker & 0x1f), None),
+            0xc4 | 0xd9 => (read_len(data, &mut pos, 1)?, None),
+            0xc5 | 0xda => (read_len(data, &mut pos, 2)?, None),
+            0xc6 | 0xdb => (read_len(data, &mut pos, 4)?, None),
+            // ext 8/16/32：长度之后还有 1 字节类型
+            0xc7 => (read_len(data, &mut pos, 1)? + 1, None),
+            0xc8 => (read_len(data, &mut pos, 2)? + 1, None),
+            0xc9 => (read_len(data, &mut pos, 4)? + 1, None),
+            0xca => (4, None),
+            0xcb => (8, None),
+            0xcc | 0xd0 => (1, None),
+            0xcd | 0xd1 => (2, None),
+            0xce | 0xd2 => (4, None),
+            0xcf | 0xd3 => (8, None),
+            // fixext 1/2/4/8/16：1 字节类型加数据
+            0xd4 => (2, None),
+            0xd5 => (3, None),
+            0xd6 => (5, None),
+            0xd7 => (9, None),
+            0xd8 => (17, None),
+            0xdc => (0, Some(read_len(data, &mut pos, 2)?)),
+            0xdd => (0, Some(read_len(data, &mut pos, 4)?)),
+            0xde => (0, Some(read_len(data, &mut pos, 2)? * 2)),
+            0xdf => (0, Some(read_len(data, &mut pos, 4)? * 2)),
+            // 0xc1 未定义
+            _ => return Err(MessagePackRejected::Malformed),
+        };
+
+        pos = usize::try_from(skip)
+            .ok()
+            .and_then(|skip| pos.checked_add(skip))
+            .filter(|&end| end <= data.len())
+            .ok_or(MessagePackRejected::Malformed)?;
+
+        if let Some(children) = children {
+            if remaining.len() > max_depth {
+                return Err(MessagePackRejected::TooDeep(max_depth));
+            }
+            remaining.push(children);
+        }
+    }
+    Ok(())
+}
+
+// 读取大端序的长度字段
+fn read_len(data: &[u8], pos: &mut usize, width: usize) -> Result<u64, MessagePackRejected> {
+    let bytes = data.get(*pos..*pos + width).ok_or(MessagePackRejected::Malformed)?;
+    *pos += width;
+    Ok(bytes.iter().fold(0u64, |len, &byte| (len << 8) | u64::from(byte)))
+}
+
+/// WebSocket 错误是否因消息或帧超过大小限制
+///
+/// axum 与 tokio-tungstenite 依赖的 tungstenite 版本可能不同，无法按类型判断，按错误信息匹配。
+pub fn is_websocket_capacity_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if error.to_string().contains("Space limit exceeded") {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 嵌套 depth 层的单元素数组，最内层是 nil
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut data = vec![0x91; depth];
+        data.push(0xc0);
+        data
+    }
+
+    #[test]
+    fn test_messagepack_depth() {
+        assert_eq!(check_messagepack_depth(&nested(16), 16), Ok(()));
+        assert_eq!(check_messagepack_depth(&nested(17), 16), Err(MessagePackRejected::TooDeep(16)));
+        assert_eq!(check_messagepack_depth(&nested(100_000), 16), Err(MessagePackRejected::TooDeep(16)));
+
+        let event = rmp_serde::to_vec(&crate::EchoKitEvent::ASR { text: "你好".to_string() }).unwrap();
+        assert_eq!(check_messagepack_depth(&event, 2), Ok(()));
+        let chunk = crate::EchoKitEvent::encode_audio_chunk(&[0u8; 300]).unwrap();
+        assert_eq!(check_messagepack_depth(&chunk, 2), Ok(()));
+
+        // 声明的元素数或长度超出实际数据
+        assert_eq!(check_messagepack_depth(&[0xdd, 0xff, 0xff, 0xff, 0xff], 16), Err(MessagePackRejected::Malformed));
+        assert_eq!(check_messagepack_depth(&[0xc6, 0xff, 0xff, 0xff, 0xff, 0x00], 16), Err(MessagePackRejected::Malformed));
+        assert_eq!(check_messagepack_depth(&[0xc1], 16), Err(MessagePackRejected::Malformed));
+        assert_eq!(check_messagepack_depth(&[], 16), Err(MessagePackRejected::Malformed));
+    }
+}
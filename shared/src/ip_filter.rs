@@ -0,0 +1,320 @@
+//! IP 允许/拒绝列表和按国家过滤
+//!
+//! API Gateway 的接口、Bridge 的内部和管理接口以及 UDP 音频入口在鉴权和解析之前按 `[ip_filter]` 检查来源地址：
+//! 命中拒绝列表的地址直接拒绝，允许列表非空时只接受其中的地址。配置文件中的规则之外，管理员可以通过
+//! API Gateway 的 `/admin/ip-filter/rules` 添加或删除规则（`ip_filter_rules` 表，见
+//! database/init/12-ip-filter-rules.sql），各服务每 `refresh_seconds` 秒重新读取。
+//!
+//! 按国家过滤依赖反向代理或 CDN 写入的国家代码请求头（`country_header`），只适用于 HTTP 请求。
+
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::types::IpFilterConfig;
+
+/// 规则动作
+pub const ACTION_ALLOW: &str = "allow";
+pub const ACTION_DENY: &str = "deny";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IpFilterError {
+    #[error("invalid IP address or CIDR block {0:?}")]
+    InvalidCidr(String),
+    #[error("invalid rule action {0:?}, expected \"allow\" or \"deny\"")]
+    InvalidAction(String),
+}
+
+/// 请求被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpBlocked {
+    /// 命中拒绝列表
+    Denied,
+    /// 不在允许列表中（或无法确定来源地址）
+    NotAllowed,
+    /// 来源国家被拒绝
+    Country(String),
+}
+
+impl IpBlocked {
+    /// 指标标签
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Denied => "denied",
+            Self::NotAllowed => "not_allowed",
+            Self::Country(_) => "country",
+        }
+    }
+}
+
+/// IP 地址段，单个地址视为 /32 或 /128
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 映射的 IPv6 地址按 IPv4 比较
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_matches(&net.octets(), &ip.octets(), self.prefix),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_matches(&net.octets(), &ip.octets(), self.prefix),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = IpFilterError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || IpFilterError::InvalidCidr(value.to_string());
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok().filter(|&prefix| prefix <= max).ok_or_else(invalid)?,
+            None => max,
+        };
+        Ok(Self { addr, prefix })
+    }
+}
+
+fn prefix_matches(net: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let full = (prefix / 8) as usize;
+    if net[..full] != ip[..full] {
+        return false;
+    }
+    let rest = prefix % 8;
+    rest == 0 || {
+        let mask = 0xffu8 << (8 - rest);
+        net[full] & mask == ip[full] & mask
+    }
+}
+
+/// 通过管理接口添加的规则
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct IpFilterRule {
+    pub id: i64,
+    pub cidr: String,
+    /// allow / deny
+    pub action: String,
+    pub note: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 读取管理接口添加的全部规则
+pub async fn list_rules(pool: &PgPool) -> Result<Vec<IpFilterRule>, sqlx::Error> {
+    sqlx::query_as::<_, IpFilterRule>(
+        "SELECT id, cidr, action, note, created_by, created_at FROM ip_filter_rules ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// 编译后的过滤规则
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    enabled: bool,
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    trust_proxy_headers: bool,
+    country_header: Option<String>,
+    allowed_countries: Vec<String>,
+    blocked_countries: Vec<String>,
+}
+
+impl IpFilter {
+    /// 合并配置文件中的规则和管理接口添加的规则，返回无效的规则
+    ///
+    /// 配置文件中的无效规则在配置校验时已拒绝；数据库中的无效规则被跳过，不影响其他规则生效
+    pub fn new(config: &IpFilterConfig, rules: &[IpFilterRule]) -> (Self, Vec<IpFilterError>) {
+        let mut errors = Vec::new();
+        let mut parse = |cidrs: &mut dyn Iterator<Item = &str>| -> Vec<IpNet> {
+            cidrs
+                .filter_map(|cidr| cidr.parse().map_err(|e| errors.push(e)).ok())
+                .collect()
+        };
+        let allow = parse(&mut config.allow.iter().map(String::as_str).chain(
+            rules.iter().filter(|rule| rule.action == ACTION_ALLOW).map(|rule| rule.cidr.as_str()),
+        ));
+        let deny = parse(&mut config.deny.iter().map(String::as_str).chain(
+            rules.iter().filter(|rule| rule.action == ACTION_DENY).map(|rule| rule.cidr.as_str()),
+        ));
+
+        let filter = Self {
+            enabled: config.enabled,
+            allow,
+            deny,
+            trust_proxy_headers: config.trust_proxy_headers,
+            country_header: Some(config.country_header.clone()).filter(|header| !header.is_empty()),
+            allowed_countries: config.allowed_countries.iter().map(|c| c.to_ascii_uppercase()).collect(),
+            blocked_countries: config.blocked_countries.iter().map(|c| c.to_ascii_uppercase()).collect(),
+        };
+        (filter, errors)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 是否使用 X-Forwarded-For / X-Real-IP 中的客户端地址（服务部署在可信的反向代理之后）
+    pub fn trust_proxy_headers(&self) -> bool {
+        self.trust_proxy_headers
+    }
+
+    /// 携带国家代码的请求头，未配置时不按国家过滤
+    pub fn country_header(&self) -> Option<&str> {
+        self.country_header.as_deref()
+    }
+
+    /// 检查来源地址，无法确定地址时只有允许列表为空才放行
+    pub fn check_ip(&self, ip: Option<IpAddr>) -> Result<(), IpBlocked> {
+        if !self.enabled {
+            return Ok(());
+        }
+        match ip {
+            Some(ip) if self.deny.iter().any(|net| net.contains(ip)) => Err(IpBlocked::Denied),
+            Some(ip) if self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip)) => Ok(()),
+            None if self.allow.is_empty() => Ok(()),
+            _ => Err(IpBlocked::NotAllowed),
+        }
+    }
+
+    /// 检查来源国家（ISO 3166 两位代码），未知国家只有 allowed_countries 为空才放行
+    pub fn check_country(&self, country: Option<&str>) -> Result<(), IpBlocked> {
+        if !self.enabled || self.country_header.is_none() {
+            return Ok(());
+        }
+        let country = country.map(|c| c.trim().to_ascii_uppercase());
+        match country {
+            Some(country) if self.blocked_countries.contains(&country) => Err(IpBlocked::Country(country)),
+            Some(country) if !self.allowed_countries.is_empty() && !self.allowed_countries.contains(&country) => {
+                Err(IpBlocked::Country(country))
+            }
+            None if !self.allowed_countries.is_empty() => Err(IpBlocked::Country("unknown".to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 各请求共享的当前规则，刷新时整体替换
+#[derive(Clone, Default)]
+pub struct IpFilterHandle {
+    current: Arc<RwLock<Arc<IpFilter>>>,
+}
+
+impl IpFilterHandle {
+    pub fn new(filter: IpFilter) -> Self {
+        Self { current: Arc::new(RwLock::new(Arc::new(filter))) }
+    }
+
+    pub fn current(&self) -> Arc<IpFilter> {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, filter: IpFilter) {
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(filter);
+    }
+
+    /// 按配置和数据库中的规则重建，返回被跳过的无效规则；SQLite 存储没有规则表，只使用配置中的规则
+    pub async fn refresh(&self, config: &IpFilterConfig, pool: Option<&PgPool>) -> Result<Vec<IpFilterError>, sqlx::Error> {
+        let rules = match pool {
+            Some(pool) if config.enabled => list_rules(pool).await?,
+            _ => Vec::new(),
+        };
+        let (filter, errors) = IpFilter::new(config, &rules);
+        self.replace(filter);
+        Ok(errors)
+    }
+}
+
+/// 校验配置文件中的规则
+pub fn validate_config(config: &IpFilterConfig) -> Vec<IpFilterError> {
+    IpFilter::new(config, &[]).1
+}
+
+/// 校验管理接口提交的规则
+pub fn validate_rule(cidr: &str, action: &str) -> Result<(), IpFilterError> {
+    cidr.parse::<IpNet>()?;
+    if action != ACTION_ALLOW && action != ACTION_DENY {
+        return Err(IpFilterError::InvalidAction(action.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(cidr: &str, action: &str) -> IpFilterRule {
+        IpFilterRule {
+            id: 1,
+            cidr: cidr.to_string(),
+            action: action.to_string(),
+            note: None,
+            created_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_cidr_matching() {
+        let net: IpNet = "10.1.0.0/16".parse().unwrap();
+        assert!(net.contains("10.1.200.3".parse().unwrap()));
+        assert!(!net.contains("10.2.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.1.0.9".parse().unwrap()));
+
+        let net: IpNet = "192.168.1.128/25".parse().unwrap();
+        assert!(net.contains("192.168.1.200".parse().unwrap()));
+        assert!(!net.contains("192.168.1.100".parse().unwrap()));
+
+        let net: IpNet = "2001:db8::/32".parse().unwrap();
+        assert!(net.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!net.contains("10.0.0.1".parse().unwrap()));
+
+        assert!("0.0.0.0/0".parse::<IpNet>().unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("not-an-ip".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_allow_deny_and_countries() {
+        let config = IpFilterConfig {
+            enabled: true,
+            allow: vec!["10.0.0.0/8".to_string()],
+            country_header: "CF-IPCountry".to_string(),
+            blocked_countries: vec!["kp".to_string()],
+            ..IpFilterConfig::default()
+        };
+        let (filter, errors) = IpFilter::new(&config, &[rule("10.9.0.0/16", ACTION_DENY), rule("bogus", ACTION_ALLOW)]);
+        assert_eq!(errors, vec![IpFilterError::InvalidCidr("bogus".to_string())]);
+
+        assert_eq!(filter.check_ip(Some("10.1.2.3".parse().unwrap())), Ok(()));
+        // 拒绝列表优先于允许列表
+        assert_eq!(filter.check_ip(Some("10.9.2.3".parse().unwrap())), Err(IpBlocked::Denied));
+        assert_eq!(filter.check_ip(Some("8.8.8.8".parse().unwrap())), Err(IpBlocked::NotAllowed));
+        assert_eq!(filter.check_ip(None), Err(IpBlocked::NotAllowed));
+
+        assert_eq!(filter.check_country(Some("KP")), Err(IpBlocked::Country("KP".to_string())));
+        assert_eq!(filter.check_country(Some("cn")), Ok(()));
+        assert_eq!(filter.check_country(None), Ok(()));
+
+        let (disabled, _) = IpFilter::new(&IpFilterConfig { enabled: false, ..config }, &[]);
+        assert_eq!(disabled.check_ip(Some("8.8.8.8".parse().unwrap())), Ok(()));
+        assert!(validate_rule("10.0.0.0/8", "block").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Redis Streams 任务队列
+//!
+//! 不需要在请求中完成的后处理（会话摘要、Webhook 投递等）写入 Redis Stream（`[redis.jobs] stream`，按长度近似截断），
+//! API Gateway 各副本的任务 worker 以同一个消费者组读取，每个任务只投递给组内一个消费者。
+//! 处理成功后确认并删除（XACK + XDEL）。处理失败或消费者退出时任务留在消费者组的待确认列表中，
+//! 闲置超过 `retry_delay_seconds` 后由任意消费者领取重试（XPENDING + XCLAIM）；
+//! 投递次数达到 `max_attempts` 或任务无法处理时写入死信流（`dead_letter_stream`），附带最后的错误，留待人工处理。
+//! 任务至少投递一次，处理函数需要能安全地重复执行。
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use redis::aio::ConnectionLike;
+use redis::streams::{StreamClaimReply, StreamId, StreamPendingCountReply, StreamReadReply};
+use redis::{ErrorKind, RedisError, RedisResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::types::JobQueueConfig;
+use crate::utils::now_utc;
+
+/// 任务类型
+pub mod job_kinds {
+    /// 生成会话摘要，payload：`{"session_id": "..."}`
+    pub const SESSION_SUMMARY: &str = "session_summary";
+    /// 投递 Webhook，payload：`{"url": "...", "body": {...}}`
+    pub const WEBHOOK: &str = "webhook";
+}
+
+// 任务内容所在的字段
+const JOB_FIELD: &str = "job";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub kind: String,
+    pub payload: serde_json::Value,
+}
+
+impl Job {
+    pub fn new(kind: &str, payload: &impl Serialize) -> serde_json::Result<Self> {
+        Ok(Self { kind: kind.to_string(), payload: serde_json::to_value(payload)? })
+    }
+
+    /// 按任务类型解析 payload
+    pub fn payload<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_value(self.payload.clone())
+    }
+}
+
+/// 读取到的任务
+#[derive(Debug, Clone)]
+pub struct JobDelivery {
+    /// Stream 中的条目 ID
+    pub id: String,
+    /// 第几次投递（从 1 开始）
+    pub attempt: u32,
+    raw: String,
+}
+
+impl JobDelivery {
+    fn from_entry(entry: &StreamId, attempt: u32) -> Self {
+        Self { id: entry.id.clone(), attempt, raw: entry.get(JOB_FIELD).unwrap_or_default() }
+    }
+
+    /// 解析任务内容，格式不正确的任务无法重试，应写入死信流
+    pub fn job(&self) -> serde_json::Result<Job> {
+        serde_json::from_str(&self.raw)
+    }
+}
+
+pub struct JobQueue {
+    config: JobQueueConfig,
+}
+
+impl JobQueue {
+    pub fn new(config: JobQueueConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &JobQueueConfig {
+        &self.config
+    }
+
+    /// 写入任务，返回条目 ID
+    pub async fn enqueue<C: ConnectionLike>(&self, conn: &mut C, job: &Job) -> RedisResult<String> {
+        let payload = serde_json::to_string(job)
+            .map_err(|e| RedisError::from((ErrorKind::TypeError, "Invalid job", e.to_string())))?;
+        redis::cmd("XADD")
+            .arg(&self.config.stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.config.max_len)
+            .arg("*")
+            .arg(JOB_FIELD)
+            .arg(payload)
+            .query_async(conn)
+            .await
+    }
+
+    /// 创建消费者组（已存在时不变），队列中已有的任务也会被投递
+    pub async fn ensure_group<C: ConnectionLike>(&self, conn: &mut C) -> RedisResult<()> {
+        let result = redis::cmd("XGROUP")
+            .arg("CREATE")
+            .arg(&self.config.stream)
+            .arg(&self.config.group)
+            .arg("0")
+            .arg("MKSTREAM")
+            .query_async::<_, ()>(conn)
+            .await;
+        match result {
+            Err(e) if e.code() == Some("BUSYGROUP") => Ok(()),
+            result => result,
+        }
+    }
+
+    /// 读取尚未投递的任务，没有任务时最多等待 `block`
+    pub async fn read_new<C: ConnectionLike>(
+        &self,
+        conn: &mut C,
+        consumer: &str,
+        block: Duration,
+    ) -> RedisResult<Vec<JobDelivery>> {
+        let reply: Option<StreamReadReply> = redis::cmd("XREADGROUP")
+            .arg("GROUP")
+            .arg(&self.config.group)
+            .arg(consumer)
+            .arg("COUNT")
+            .arg(self.config.batch_size)
+            .arg("BLOCK")
+            .arg(block.as_millis() as u64)
+            .arg("STREAMS")
+            .arg(&self.config.stream)
+            .arg(">")
+            .query_async(conn)
+            .await?;
+
+        Ok(reply
+            .into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .map(|entry| JobDelivery::from_entry(&entry, 1))
+            .collect())
+    }
+
+    /// 领取闲置超过重试间隔的未确认任务（处理失败或消费者已退出）
+    ///
+    /// 投递次数已达上限的任务直接写入死信流，不再返回
+    pub async fn claim_stale<C: ConnectionLike>(&self, conn: &mut C, consumer: &str) -> RedisResult<Vec<JobDelivery>> {
+        let min_idle_ms = self.config.retry_delay_seconds * 1000;
+        let pending: StreamPendingCountReply = redis::cmd("XPENDING")
+            .arg(&self.config.stream)
+            .arg(&self.config.group)
+            .arg("IDLE")
+            .arg(min_idle_ms)
+            .arg("-")
+            .arg("+")
+            .arg(self.config.batch_size)
+            .query_async(conn)
+            .await?;
+        if pending.ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let delivered: HashMap<String, usize> =
+            pending.ids.iter().map(|pending| (pending.id.clone(), pending.times_delivered)).collect();
+        // 领取时重新检查闲置时间，其他消费者刚领取的任务不会被重复领取
+        let claimed: StreamClaimReply = redis::cmd("XCLAIM")
+            .arg(&self.config.stream)
+            .arg(&self.config.group)
+            .arg(consumer)
+            .arg(min_idle_ms)
+            .arg(delivered.keys().collect::<Vec<_>>())
+            .query_async(conn)
+            .await?;
+
+        let mut deliveries = Vec::new();
+        for entry in &claimed.ids {
+            let attempt = delivered.get(&entry.id).map_or(1, |times| *times as u32 + 1);
+            let delivery = JobDelivery::from_entry(entry, attempt);
+            if attempt > self.config.max_attempts {
+                let error = format!("Job was not acknowledged after {} attempts", attempt - 1);
+                self.dead_letter(conn, &delivery, &error).await?;
+            } else {
+                deliveries.push(delivery);
+            }
+        }
+        Ok(deliveries)
+    }
+
+    /// 处理成功：确认并删除任务
+    pub async fn complete<C: ConnectionLike>(&self, conn: &mut C, id: &str) -> RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .cmd("XACK").arg(&self.config.stream).arg(&self.config.group).arg(id).ignore()
+            .cmd("XDEL").arg(&self.config.stream).arg(id).ignore()
+            .query_async(conn)
+            .await
+    }
+
+    /// 写入死信流，并从队列中删除
+    pub async fn dead_letter<C: ConnectionLike>(&self, conn: &mut C, delivery: &JobDelivery, error: &str) -> RedisResult<()> {
+        redis::pipe()
+            .atomic()
+            .cmd("XADD")
+            .arg(&self.config.dead_letter_stream)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.config.max_len)
+            .arg("*")
+            .arg(JOB_FIELD)
+            .arg(&delivery.raw)
+            .arg("job_id")
+            .arg(&delivery.id)
+            .arg("attempts")
+            .arg(delivery.attempt)
+            .arg("error")
+            .arg(error)
+            .arg("failed_at")
+            .arg(now_utc().to_rfc3339())
+            .ignore()
+            .cmd("XACK").arg(&self.config.stream).arg(&self.config.group).arg(&delivery.id).ignore()
+            .cmd("XDEL").arg(&self.config.stream).arg(&delivery.id).ignore()
+            .query_async(conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_delivery_decoding() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Summary {
+            session_id: String,
+        }
+
+        let job = Job::new(job_kinds::SESSION_SUMMARY, &Summary { session_id: "s-1".to_string() }).unwrap();
+        let entry = StreamId {
+            id: "1700000000000-0".to_string(),
+            map: HashMap::from([(
+                JOB_FIELD.to_string(),
+                redis::Value::Data(serde_json::to_vec(&job).unwrap()),
+            )]),
+        };
+
+        let delivery = JobDelivery::from_entry(&entry, 2);
+        assert_eq!(delivery.id, "1700000000000-0");
+        assert_eq!(delivery.attempt, 2);
+        let decoded = delivery.job().unwrap();
+        assert_eq!(decoded, job);
+        assert_eq!(decoded.payload::<Summary>().unwrap(), Summary { session_id: "s-1".to_string() });
+
+        // 缺少任务内容的条目无法解析
+        let empty = JobDelivery::from_entry(&StreamId { id: "1-0".to_string(), map: HashMap::new() }, 1);
+        assert!(empty.job().is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! 单例后台任务的领导者选举
+//!
+//! 会话超时清理、统计汇总等周期任务在多副本部署下只应由一个副本执行。每个任务按名称
+//! 使用一个 PostgreSQL 会话级 advisory lock：获得锁的副本为领导者，并在专用连接上一直
+//! 持有；连接断开或进程退出时锁由数据库自动释放，其他副本在下一轮检查时接管。
+//! SQLite 存储只用于单机部署，使用 `single_instance`，不做选举。
+
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, PgPool, Postgres};
+
+type LeadershipListener = Box<dyn Fn(&str, bool) + Send + Sync>;
+
+/// 基于 PostgreSQL advisory lock 的领导者选举
+pub struct LeaderElection {
+    name: String,
+    key: i64,
+    // None 表示单实例部署，总是领导者
+    pool: Option<PgPool>,
+    // 持有锁的专用连接，不为空时本副本是领导者
+    connection: tokio::sync::Mutex<Option<PoolConnection<Postgres>>>,
+    leader: Mutex<bool>,
+    listener: Option<LeadershipListener>,
+}
+
+impl LeaderElection {
+    pub fn new(name: impl Into<String>, pool: PgPool) -> Self {
+        let name = name.into();
+        Self {
+            key: lock_key(&name),
+            name,
+            pool: Some(pool),
+            connection: tokio::sync::Mutex::new(None),
+            leader: Mutex::new(false),
+            listener: None,
+        }
+    }
+
+    /// 单实例部署（SQLite 存储）使用，不做选举，总是领导者
+    pub fn single_instance(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            key: lock_key(&name),
+            name,
+            pool: None,
+            connection: tokio::sync::Mutex::new(None),
+            leader: Mutex::new(false),
+            listener: None,
+        }
+    }
+
+    /// 领导权变化时的回调（任务名称，是否为领导者），用于记录日志和指标
+    pub fn with_listener(mut self, listener: impl Fn(&str, bool) + Send + Sync + 'static) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 本副本是否为领导者
+    ///
+    /// 已持有锁时确认专用连接仍然可用，否则尝试获取锁。周期任务在每轮执行前调用，
+    /// 返回 false 时跳过本轮。数据库不可用时视为不是领导者。
+    pub async fn is_leader(&self) -> bool {
+        if self.pool.is_none() {
+            self.set_leader(true);
+            return true;
+        }
+        let mut connection = self.connection.lock().await;
+
+        if let Some(conn) = connection.as_mut() {
+            if sqlx::query("SELECT 1").execute(&mut **conn).await.is_ok() {
+                return true;
+            }
+            // 连接已断开，锁已由数据库释放；关闭连接而不是放回连接池
+            if let Some(conn) = connection.take() {
+                drop(conn.detach());
+            }
+        }
+
+        *connection = self.try_acquire().await;
+        let leader = connection.is_some();
+        self.set_leader(leader);
+        leader
+    }
+
+    /// 放弃领导权（关闭时调用）：关闭持有锁的连接，其他副本在下一轮检查时即可接管
+    ///
+    /// 持有锁的连接不会归还连接池，关闭连接池前必须先调用。
+    pub async fn release(&self) {
+        if let Some(conn) = self.connection.lock().await.take() {
+            let _ = conn.detach().close().await;
+            self.set_leader(false);
+        } else if self.pool.is_none() {
+            self.set_leader(false);
+        }
+    }
+
+    // 获取锁成功时返回持有锁的连接
+    async fn try_acquire(&self) -> Option<PoolConnection<Postgres>> {
+        let mut conn = self.pool.as_ref()?.acquire().await.ok()?;
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.key)
+            .fetch_one(&mut *conn)
+            .await
+            .ok()?;
+        acquired.then_some(conn)
+    }
+
+    fn set_leader(&self, leader: bool) {
+        let changed = {
+            let mut current = self.leader.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            std::mem::replace(&mut *current, leader) != leader
+        };
+        if changed {
+            if let Some(listener) = &self.listener {
+                listener(&self.name, leader);
+            }
+        }
+    }
+}
+
+/// 任务名称对应的 advisory lock 键，所有副本和服务计算结果一致
+pub fn lock_key(name: &str) -> i64 {
+    let digest = Sha256::digest(format!("echo:leader:{}", name).as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    i64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_key_is_stable_per_job() {
+        assert_eq!(lock_key("session_rollups"), lock_key("session_rollups"));
+        assert_ne!(lock_key("session_rollups"), lock_key("session_summaries"));
+    }
+
+    #[tokio::test]
+    async fn test_single_instance_is_always_leader() {
+        let election = LeaderElection::single_instance("session_timeout_sweep");
+        assert!(election.is_leader().await);
+        election.release().await;
+        assert!(election.is_leader().await);
+    }
+}
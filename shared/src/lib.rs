@@ -1,14 +1,82 @@
 pub mod types;
+pub mod api_error;
 pub mod config;
+pub mod config_watcher;
+pub mod config_reload;
 pub mod utils;
 pub mod mqtt;
 pub mod database;
 pub mod cache;
+pub mod notify;
+pub mod protocol;
+pub mod echokit;
+pub mod log_sampling;
+pub mod health;
+pub mod build_info;
+pub mod alert;
+pub mod circuit_breaker;
+pub mod retry;
+pub mod leader;
+pub mod db_pool;
+pub mod partitions;
+pub mod storage;
+pub mod outbox;
+pub mod object_storage;
+pub mod job_queue;
+pub mod session_limits;
+pub mod distributed_lock;
+pub mod ws_ticket;
+pub mod ingress;
+pub mod redaction;
+pub mod encryption;
+pub mod command_signing;
+pub mod ip_filter;
+pub mod log_scrubbing;
+pub mod command_authz;
+pub mod security_headers;
+pub mod in_process;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
 // 重新导出所有内容，但避免模糊重导出冲突
 pub use types::*;
+pub use api_error::{ApiError, PROBLEM_JSON};
 pub use config::*;
+pub use config_watcher::*;
+pub use config_reload::{init_tracing, spawn_log_level_watcher, spawn_sighup_listener, ExtraLayer, LogReloadHandle};
 pub use utils::*;
 pub use mqtt::*;
 pub use database::*;
-pub use cache::*;
\ No newline at end of file
+pub use cache::*;
+pub use notify::*;
+pub use protocol::*;
+pub use echokit::*;
+pub use log_sampling::*;
+pub use health::*;
+pub use build_info::*;
+pub use alert::*;
+pub use circuit_breaker::*;
+pub use retry::*;
+pub use leader::*;
+pub use db_pool::*;
+pub use partitions::*;
+pub use storage::*;
+// outbox 的函数名较通用（enqueue 等），按 echo_shared::outbox::enqueue 调用
+pub use outbox::OutboxEntry;
+pub use object_storage::*;
+pub use job_queue::{job_kinds, Job, JobDelivery, JobQueue};
+pub use session_limits::{SessionLimitExceeded, SessionLimits};
+pub use distributed_lock::{DistributedLock, LockExtension};
+pub use ws_ticket::{WsTicket, WsTicketRejected, WsTickets};
+pub use ingress::{check_messagepack_depth, is_websocket_capacity_error, MessagePackRejected};
+pub use redaction::{install_log_redactor, redact_for_log, Redacted, Redactor};
+pub use encryption::{EncryptionError, FieldEncryptor};
+pub use command_signing::{CommandEnvelope, CommandSignatureError, CommandSigner, CommandVerifier, SignedCommand};
+pub use ip_filter::{IpBlocked, IpFilter, IpFilterError, IpFilterHandle, IpFilterRule};
+pub use log_scrubbing::{scrub_secrets, scrubbed_panic_message, set_scrub_secrets, ScrubbingWriter};
+pub use command_authz::{CommandAuthorizer, CommandDenied, CommandIssuer, DeviceCommandKind};
+pub use security_headers::SecurityHeaders;
+pub use in_process::{EventBus, InProcess};
+// grpc 模块中的生成类型与现有类型同名（如 SessionEvent），不做重导出
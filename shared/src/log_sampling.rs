@@ -0,0 +1,55 @@
+//! 高频日志采样
+//!
+//! 音频路径上每个数据帧都会打日志，采样后每个调用点每 N 次只记录一次。
+//! 采样率来自 `log.sample_every`，可热加载。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static SAMPLE_EVERY: AtomicU64 = AtomicU64::new(1);
+
+/// 设置采样率：每 `every` 次记录一次，0 或 1 表示不采样
+pub fn set_log_sample_every(every: u64) {
+    SAMPLE_EVERY.store(every, Ordering::Relaxed);
+}
+
+/// 当前采样率
+pub fn log_sample_every() -> u64 {
+    SAMPLE_EVERY.load(Ordering::Relaxed)
+}
+
+/// 按当前采样率判断本次是否记录，`counter` 为调用点自己的计数器
+pub fn should_log(counter: &AtomicU64) -> bool {
+    let every = log_sample_every();
+    every <= 1 || counter.fetch_add(1, Ordering::Relaxed).is_multiple_of(every)
+}
+
+/// 每个调用点独立计数的采样判断，用于包裹逐帧日志：
+/// `if echo_shared::log_sampled!() { info!(...) }`
+#[macro_export]
+macro_rules! log_sampled {
+    () => {{
+        static COUNTER: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0);
+        $crate::log_sampling::should_log(&COUNTER)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_log_samples_per_counter() {
+        set_log_sample_every(3);
+        let counter = AtomicU64::new(0);
+        let logged: Vec<bool> = (0..6).map(|_| should_log(&counter)).collect();
+        assert_eq!(logged, [true, false, false, true, false, false]);
+
+        // 每个调用点的计数互不影响
+        assert!(log_sampled!());
+        assert!(log_sampled!());
+
+        set_log_sample_every(0);
+        assert!((0..3).all(|_| should_log(&counter)));
+        set_log_sample_every(1);
+    }
+}
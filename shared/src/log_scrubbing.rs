@@ -0,0 +1,219 @@
+//! 日志和 panic 信息中的密钥脱敏
+//!
+//! 日志中会出现完整的消息 JSON、连接地址等内容，其中可能带有令牌、密码或整段音频数据。各服务的日志
+//! 输出层使用 `ScrubbingWriter`，每条日志写出前替换：
+//!
+//! - URL 中的密码（如 `postgres://echo:password@db/echo`）
+//! - Bearer / Basic 凭据和 JWT
+//! - `password`、`secret`、`token`、`api_key`、`private_key` 等字段的值（JSON 和 `key=value` 形式）
+//! - 序列化为数字数组的音频数据和超长的 Base64 文本，只保留长度
+//!
+//! `log.scrub_secrets` 默认开启。panic 钩子（`scrubbed_panic_message`）同样脱敏，且不输出 panic 时的变量状态。
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex, RegexSet};
+
+/// 替换密钥的文本
+pub const SECRET_PLACEHOLDER: &str = "[SECRET]";
+
+const URL_PASSWORD_PATTERN: &str = r"(?i)\b([a-z][a-z0-9+.-]*://[^/\s:@]+):[^@\s/]+@";
+const AUTH_SCHEME_PATTERN: &str = r"(?i)\b(bearer|basic)\s+[A-Za-z0-9._~+/=-]{8,}";
+const JWT_PATTERN: &str = r"\beyJ[A-Za-z0-9_-]{4,}\.[A-Za-z0-9_-]{4,}\.[A-Za-z0-9_-]+";
+// 字段名可带前缀（如 internal_token、client_secret），max_tokens 之类的字段名不匹配；JSON 格式的日志中引号被转义为 \"
+const SECRET_FIELD_PATTERN: &str = r#"(?i)((?:\\?"|\b)(?:[a-z0-9]+[_-])*(?:password|passwd|secret|token|api[_-]?key|private[_-]?key)(?:\\?"|\b)\s*[:=]\s*(?:\\?")?)([^"\\\s,;&}]+)"#;
+// 音频数据序列化为 JSON 数字数组（如 EchoKit AudioData 的 audio_data）
+const BYTE_ARRAY_PATTERN: &str = r"\[(?:\s*\d{1,3}\s*,){32,}\s*\d{1,3}\s*\]";
+const BASE64_PATTERN: &str = r"[A-Za-z0-9+/]{256,}={0,2}";
+
+static SCRUB_ENABLED: AtomicBool = AtomicBool::new(true);
+
+struct Scrubber {
+    // 先判断是否有任何规则匹配，大部分日志不需要替换
+    any: RegexSet,
+    url_password: Regex,
+    auth_scheme: Regex,
+    jwt: Regex,
+    secret_field: Regex,
+    byte_array: Regex,
+    base64: Regex,
+}
+
+static SCRUBBER: LazyLock<Scrubber> = LazyLock::new(|| {
+    let patterns = [
+        URL_PASSWORD_PATTERN,
+        AUTH_SCHEME_PATTERN,
+        JWT_PATTERN,
+        SECRET_FIELD_PATTERN,
+        BYTE_ARRAY_PATTERN,
+        BASE64_PATTERN,
+    ];
+    let regex = |pattern| Regex::new(pattern).expect("valid scrubbing pattern");
+    Scrubber {
+        any: RegexSet::new(patterns).expect("valid scrubbing patterns"),
+        url_password: regex(URL_PASSWORD_PATTERN),
+        auth_scheme: regex(AUTH_SCHEME_PATTERN),
+        jwt: regex(JWT_PATTERN),
+        secret_field: regex(SECRET_FIELD_PATTERN),
+        byte_array: regex(BYTE_ARRAY_PATTERN),
+        base64: regex(BASE64_PATTERN),
+    }
+});
+
+/// 设置是否脱敏日志（log.scrub_secrets），启动时调用
+pub fn set_scrub_secrets(enabled: bool) {
+    SCRUB_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 替换文本中的密钥和音频数据，不受 `log.scrub_secrets` 影响
+pub fn scrub_secrets(text: &str) -> Cow<'_, str> {
+    let scrubber = &*SCRUBBER;
+    if !scrubber.any.is_match(text) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut text = Cow::Borrowed(text);
+    let mut apply = |regex: &Regex, replace: &dyn Fn(&Captures) -> String| {
+        let replaced = regex.replace_all(&text, |caps: &Captures| replace(caps));
+        if let Cow::Owned(replaced) = replaced {
+            text = Cow::Owned(replaced);
+        }
+    };
+    apply(&scrubber.url_password, &|caps| format!("{}:{}@", &caps[1], SECRET_PLACEHOLDER));
+    apply(&scrubber.auth_scheme, &|caps| format!("{} {}", &caps[1], SECRET_PLACEHOLDER));
+    apply(&scrubber.jwt, &|_| SECRET_PLACEHOLDER.to_string());
+    apply(&scrubber.secret_field, &|caps| {
+        // 已替换过的值（如 Bearer [SECRET]）不再处理
+        if caps[2].starts_with('[') { caps[0].to_string() } else { format!("{}{}", &caps[1], SECRET_PLACEHOLDER) }
+    });
+    apply(&scrubber.byte_array, &|caps| format!("[{} bytes]", caps[0].matches(',').count() + 1));
+    apply(&scrubber.base64, &|caps| format!("[base64, {} chars]", caps[0].len()));
+    text
+}
+
+/// 日志输出层的 writer：缓存一条日志，写出前脱敏
+///
+/// tracing 的 fmt 层为每条日志创建一个 writer 并一次写入整条日志，`ScrubbingWriter::stdout` 可直接
+/// 作为 `with_writer` 的参数。
+pub struct ScrubbingWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl ScrubbingWriter<io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> ScrubbingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, buffer: Vec::new() }
+    }
+}
+
+impl<W: Write> Write for ScrubbingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return self.inner.flush();
+        }
+        let buffer = std::mem::take(&mut self.buffer);
+        if SCRUB_ENABLED.load(Ordering::Relaxed) {
+            let text = String::from_utf8_lossy(&buffer);
+            self.inner.write_all(scrub_secrets(&text).as_bytes())?;
+        } else {
+            self.inner.write_all(&buffer)?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for ScrubbingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// panic 钩子输出的信息：线程、位置和脱敏后的 panic 消息
+///
+/// 不输出 panic 载荷以外的内容；`unwrap`/`expect` 的错误值可能包含连接地址或消息内容，同样脱敏
+pub fn scrubbed_panic_message(info: &std::panic::PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    let location = info
+        .location()
+        .map(|location| format!("{}:{}", location.file(), location.line()))
+        .unwrap_or_else(|| "unknown location".to_string());
+    let thread = std::thread::current();
+    let mut text = format!(
+        "thread '{}' panicked at {}: {}",
+        thread.name().unwrap_or("<unnamed>"),
+        location,
+        scrub_secrets(message)
+    );
+    // 与默认钩子一致，设置 RUST_BACKTRACE 时附带调用栈
+    let backtrace = std::backtrace::Backtrace::capture();
+    if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+        text.push_str(&format!("\n{}", backtrace));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_secrets() {
+        assert_eq!(
+            scrub_secrets("Connecting to database: postgres://echo:s3cret@db:5432/echo"),
+            "Connecting to database: postgres://echo:[SECRET]@db:5432/echo"
+        );
+        assert_eq!(
+            scrub_secrets("authorization: Bearer abcdefgh.ijkl-123"),
+            "authorization: Bearer [SECRET]"
+        );
+        assert_eq!(
+            scrub_secrets(r#"{"username":"admin","password":"hunter2","max_tokens":100}"#),
+            r#"{"username":"admin","password":"[SECRET]","max_tokens":100}"#
+        );
+        assert_eq!(scrub_secrets("internal_token=abc123 ok"), "internal_token=[SECRET] ok");
+        // JSON 格式日志中转义过的引号
+        assert_eq!(
+            scrub_secrets(r#"{"message":"login {\"password\":\"hunter2\"}"}"#),
+            r#"{"message":"login {\"password\":\"[SECRET]\"}"}"#
+        );
+        assert_eq!(scrub_secrets("token eyJhbGciOi.eyJzdWIiOi.sig_-1"), "token [SECRET]");
+
+        let audio: Vec<String> = (0..64).map(|i| i.to_string()).collect();
+        let message = format!(r#"{{"type":"AudioData","audio_data":[{}]}}"#, audio.join(","));
+        assert_eq!(scrub_secrets(&message), r#"{"type":"AudioData","audio_data":[64 bytes]}"#);
+        assert_eq!(scrub_secrets(&"QUJD".repeat(100)), "[base64, 400 chars]");
+
+        // 没有敏感内容时不复制文本
+        assert!(matches!(scrub_secrets("Session started for device-1"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_scrubbing_writer() {
+        let mut output = Vec::new();
+        {
+            let mut writer = ScrubbingWriter::new(&mut output);
+            writer.write_all(b"login password=").unwrap();
+            writer.write_all(b"hunter2\n").unwrap();
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "login password=[SECRET]\n");
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use crate::{DeviceStatus};
+use crate::{Alert, CommandIssuer, DeviceStatus, SignedCommand, WebSocketMessage};
 
 mod qos_serde {
     use serde::{Deserialize, Deserializer, Serializer};
@@ -43,10 +43,13 @@ pub enum MqttTopic {
     DeviceStatus(String),      // device/{device_id}/status
     DeviceConfig(String),      // device/{device_id}/config
     DeviceControl(String),     // device/{device_id}/control
+    DeviceSession(String),     // device/{device_id}/session
+    DeviceEvent(String),       // device/{device_id}/event
 
     // 系统相关主题
     SystemHeartbeat(String),   // system/{service}/heartbeat
     SystemStatus(String),      // system/{service}/status
+    SystemAlert(String),       // system/{service}/alert
 
     // 用户相关主题
     UserNotification(String),  // user/{user_id}/notification
@@ -63,8 +66,11 @@ impl MqttTopic {
             MqttTopic::DeviceStatus(device_id) => format!("device/{}/status", device_id),
             MqttTopic::DeviceConfig(device_id) => format!("device/{}/config", device_id),
             MqttTopic::DeviceControl(device_id) => format!("device/{}/control", device_id),
+            MqttTopic::DeviceSession(device_id) => format!("device/{}/session", device_id),
+            MqttTopic::DeviceEvent(device_id) => format!("device/{}/event", device_id),
             MqttTopic::SystemHeartbeat(service) => format!("system/{}/heartbeat", service),
             MqttTopic::SystemStatus(service) => format!("system/{}/status", service),
+            MqttTopic::SystemAlert(service) => format!("system/{}/alert", service),
             MqttTopic::UserNotification(user_id) => format!("user/{}/notification", user_id),
             MqttTopic::Broadcast(message_type) => format!("broadcast/{}", message_type),
         }
@@ -79,8 +85,11 @@ impl MqttTopic {
             ["device", device_id, "status"] => Some(MqttTopic::DeviceStatus(device_id.to_string())),
             ["device", device_id, "config"] => Some(MqttTopic::DeviceConfig(device_id.to_string())),
             ["device", device_id, "control"] => Some(MqttTopic::DeviceControl(device_id.to_string())),
+            ["device", device_id, "session"] => Some(MqttTopic::DeviceSession(device_id.to_string())),
+            ["device", device_id, "event"] => Some(MqttTopic::DeviceEvent(device_id.to_string())),
             ["system", service, "heartbeat"] => Some(MqttTopic::SystemHeartbeat(service.to_string())),
             ["system", service, "status"] => Some(MqttTopic::SystemStatus(service.to_string())),
+            ["system", service, "alert"] => Some(MqttTopic::SystemAlert(service.to_string())),
             ["user", user_id, "notification"] => Some(MqttTopic::UserNotification(user_id.to_string())),
             ["broadcast", message_type] => Some(MqttTopic::Broadcast(message_type.to_string())),
             _ => None,
@@ -93,7 +102,9 @@ impl MqttTopic {
             MqttTopic::DeviceWake(device_id) |
             MqttTopic::DeviceStatus(device_id) |
             MqttTopic::DeviceConfig(device_id) |
-            MqttTopic::DeviceControl(device_id) => Some(device_id.clone()),
+            MqttTopic::DeviceControl(device_id) |
+            MqttTopic::DeviceSession(device_id) |
+            MqttTopic::DeviceEvent(device_id) => Some(device_id.clone()),
             _ => None,
         }
     }
@@ -163,6 +174,41 @@ pub enum MqttPayload {
         device_id: String,
         command: DeviceCommand,
         timestamp: DateTime<Utc>,
+        // 下发命令的用户，Bridge 按 command_authorization 检查；系统命令为空
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        issued_by: Option<CommandIssuer>,
+    },
+
+    // 签名的设备控制命令或配置（启用 command_signing 时代替 DeviceControl / DeviceConfig）
+    SignedCommand {
+        device_id: String,
+        command: SignedCommand,
+        timestamp: DateTime<Utc>,
+    },
+
+    // 会话实时事件（会话进度、ASR 识别结果等）
+    SessionEvent {
+        device_id: String,
+        event: WebSocketMessage,
+        timestamp: DateTime<Utc>,
+    },
+
+    // 设备注册完成（经 outbox 发布）
+    DeviceRegistered {
+        device_id: String,
+        name: String,
+        owner: Option<String>,
+        timestamp: DateTime<Utc>,
+    },
+
+    // 会话结束，status 为 completed / failed / timeout（经 outbox 发布）
+    SessionEnded {
+        session_id: String,
+        device_id: String,
+        user_id: Option<String>,
+        status: String,
+        duration_seconds: Option<i64>,
+        timestamp: DateTime<Utc>,
     },
 
     // 系统心跳消息
@@ -183,6 +229,11 @@ pub enum MqttPayload {
         timestamp: DateTime<Utc>,
     },
 
+    // 告警触发与恢复通知
+    SystemAlert {
+        alert: Alert,
+    },
+
     // 用户通知消息
     UserNotification {
         user_id: String,
@@ -240,6 +291,26 @@ pub enum DeviceCommand {
     Custom { command_type: String, parameters: serde_json::Value },
 }
 
+impl DeviceCommand {
+    /// 语音播报命令：由连接设备的 Bridge 合成语音后播放
+    pub fn announce(text: impl Into<String>) -> Self {
+        DeviceCommand::Custom {
+            command_type: "announce".to_string(),
+            parameters: serde_json::json!({ "text": text.into() }),
+        }
+    }
+
+    /// 语音播报命令的文本，不是播报命令时为 None
+    pub fn announcement_text(&self) -> Option<&str> {
+        match self {
+            DeviceCommand::Custom { command_type, parameters } if command_type == "announce" => {
+                parameters.get("text").and_then(|text| text.as_str())
+            }
+            _ => None,
+        }
+    }
+}
+
 // 服务状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServiceStatus {
@@ -270,36 +341,6 @@ pub enum NotificationLevel {
     Success,
 }
 
-// MQTT 客户端配置
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MqttConfig {
-    pub broker_host: String,
-    pub broker_port: u16,
-    pub client_id: String,
-    pub username: Option<String>,
-    pub password: Option<String>,
-    pub keep_alive: u64,
-    pub clean_session: bool,
-    pub max_reconnect_attempts: u32,
-    pub reconnect_interval_ms: u64,
-}
-
-impl Default for MqttConfig {
-    fn default() -> Self {
-        Self {
-            broker_host: "localhost".to_string(),
-            broker_port: 1883,
-            client_id: format!("echo-{}", uuid::Uuid::new_v4()),
-            username: None,
-            password: None,
-            keep_alive: 60,
-            clean_session: true,
-            max_reconnect_attempts: 10,
-            reconnect_interval_ms: 5000,
-        }
-    }
-}
-
 // MQTT 错误类型
 #[derive(Debug, thiserror::Error)]
 pub enum MqttError {
@@ -375,6 +416,14 @@ impl TopicFilter {
     pub fn device_control(device_id: &str) -> Self {
         Self::new(format!("device/{}/control", device_id), QoS::AtLeastOnce)
     }
+
+    pub fn all_device_session() -> Self {
+        Self::new("device/+/session".to_string(), QoS::AtMostOnce)
+    }
+
+    pub fn all_device_events() -> Self {
+        Self::new("device/+/event".to_string(), QoS::AtLeastOnce)
+    }
 }
 
 // 消息构建器
@@ -428,10 +477,31 @@ impl MqttMessageBuilder {
 
     // 构建设备控制消息
     pub fn device_control(device_id: String, command: DeviceCommand) -> MqttMessage {
+        Self::device_control_by(device_id, command, None)
+    }
+
+    // 构建用户下发的设备控制消息
+    pub fn device_control_by(device_id: String, command: DeviceCommand, issued_by: Option<CommandIssuer>) -> MqttMessage {
         let payload = MqttPayload::DeviceControl {
             device_id: device_id.clone(),
             command,
             timestamp: Utc::now(),
+            issued_by,
+        };
+
+        MqttMessage::new(
+            MqttTopic::DeviceControl(device_id).to_string(),
+            payload,
+            QoS::AtLeastOnce,
+        )
+    }
+
+    // 构建签名的设备命令消息，与未签名的控制命令使用同一主题
+    pub fn signed_command(device_id: String, command: SignedCommand) -> MqttMessage {
+        let payload = MqttPayload::SignedCommand {
+            device_id: device_id.clone(),
+            command,
+            timestamp: Utc::now(),
         };
 
         MqttMessage::new(
@@ -441,6 +511,61 @@ impl MqttMessageBuilder {
         )
     }
 
+    // 构建会话实时事件消息
+    pub fn session_event(device_id: String, event: WebSocketMessage) -> MqttMessage {
+        let payload = MqttPayload::SessionEvent {
+            device_id: device_id.clone(),
+            event,
+            timestamp: Utc::now(),
+        };
+
+        MqttMessage::new(
+            MqttTopic::DeviceSession(device_id).to_string(),
+            payload,
+            QoS::AtMostOnce,
+        )
+    }
+
+    // 构建设备注册事件
+    pub fn device_registered(device_id: String, name: String, owner: Option<String>) -> MqttMessage {
+        let payload = MqttPayload::DeviceRegistered {
+            device_id: device_id.clone(),
+            name,
+            owner,
+            timestamp: Utc::now(),
+        };
+
+        MqttMessage::new(
+            MqttTopic::DeviceEvent(device_id).to_string(),
+            payload,
+            QoS::AtLeastOnce,
+        )
+    }
+
+    // 构建会话结束事件
+    pub fn session_ended(
+        session_id: String,
+        device_id: String,
+        user_id: Option<String>,
+        status: String,
+        duration_seconds: Option<i64>,
+    ) -> MqttMessage {
+        let payload = MqttPayload::SessionEnded {
+            session_id,
+            device_id: device_id.clone(),
+            user_id,
+            status,
+            duration_seconds,
+            timestamp: Utc::now(),
+        };
+
+        MqttMessage::new(
+            MqttTopic::DeviceEvent(device_id).to_string(),
+            payload,
+            QoS::AtLeastOnce,
+        )
+    }
+
     // 构建系统心跳消息
     pub fn system_heartbeat(
         service: String,
@@ -462,6 +587,15 @@ impl MqttMessageBuilder {
             QoS::AtMostOnce,
         )
     }
+
+    // 构建告警消息
+    pub fn system_alert(service: String, alert: Alert) -> MqttMessage {
+        MqttMessage::new(
+            MqttTopic::SystemAlert(service).to_string(),
+            MqttPayload::SystemAlert { alert },
+            QoS::AtLeastOnce,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -492,4 +626,27 @@ mod tests {
         assert_eq!(msg.qos, QoS::AtLeastOnce);
         assert!(msg.retain);
     }
+
+    #[test]
+    fn test_session_event_round_trip() {
+        let event = WebSocketMessage::SessionProgress {
+            session_id: "sess001".to_string(),
+            device_id: "dev001".to_string(),
+            stage: crate::SessionStage::Listening,
+            progress: 20.0,
+            message: "listening".to_string(),
+        };
+        let msg = MqttMessageBuilder::session_event("dev001".to_string(), event);
+        assert_eq!(MqttTopic::from_string(&msg.topic), Some(MqttTopic::DeviceSession("dev001".to_string())));
+
+        let bytes = serde_json::to_vec(&msg.payload).unwrap();
+        let payload: MqttPayload = serde_json::from_slice(&bytes).unwrap();
+        match payload {
+            MqttPayload::SessionEvent { device_id, event, .. } => {
+                assert_eq!(device_id, "dev001");
+                assert!(matches!(event, WebSocketMessage::SessionProgress { .. }));
+            }
+            other => panic!("unexpected payload: {:?}", other),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,233 @@
+// 消息投递抽象（邮件/短信/Slack/Telegram 等），具体投递通道由各部署自行实现
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+// 投递通道
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DeliveryChannel {
+    Email,
+    Sms,
+    /// 收件人为 Slack incoming webhook 地址
+    Slack,
+    /// 收件人为 Telegram chat ID
+    Telegram,
+}
+
+// 待投递的消息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutboundMessage {
+    pub channel: DeliveryChannel,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl OutboundMessage {
+    pub fn email(recipient: impl Into<String>, subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            channel: DeliveryChannel::Email,
+            recipient: recipient.into(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+
+    pub fn sms(recipient: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            channel: DeliveryChannel::Sms,
+            recipient: recipient.into(),
+            subject: String::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn new(
+        channel: DeliveryChannel,
+        recipient: impl Into<String>,
+        subject: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Self {
+        Self {
+            channel,
+            recipient: recipient.into(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+}
+
+// 消息发送接口，部署方实现该 trait 以接入自己的邮件/短信服务
+#[async_trait::async_trait]
+pub trait MessageSender: Send + Sync {
+    // 发送者名称（用于日志）
+    fn name(&self) -> &str;
+
+    // 是否支持指定的投递通道
+    fn supports(&self, channel: DeliveryChannel) -> bool;
+
+    // 发送消息
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError>;
+}
+
+// 内存发送者：只记录消息不实际投递，用于开发环境和测试
+#[derive(Default)]
+pub struct InMemorySender {
+    outbox: Mutex<Vec<OutboundMessage>>,
+}
+
+impl InMemorySender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 获取已发送的消息
+    pub fn sent_messages(&self) -> Vec<OutboundMessage> {
+        self.outbox.lock().map(|outbox| outbox.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender for InMemorySender {
+    fn name(&self) -> &str {
+        "in-memory"
+    }
+
+    fn supports(&self, _channel: DeliveryChannel) -> bool {
+        true
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        let mut outbox = self
+            .outbox
+            .lock()
+            .map_err(|e| NotifyError::DeliveryFailed(e.to_string()))?;
+        outbox.push(message.clone());
+        Ok(())
+    }
+}
+
+// 按投递通道分发的发送者：交给第一个支持该通道的发送者，都不支持时交给兜底发送者
+pub struct RoutingSender {
+    senders: Vec<Arc<dyn MessageSender>>,
+    fallback: Option<Arc<dyn MessageSender>>,
+}
+
+impl RoutingSender {
+    pub fn new() -> Self {
+        Self { senders: Vec::new(), fallback: None }
+    }
+
+    pub fn with_sender(mut self, sender: Arc<dyn MessageSender>) -> Self {
+        self.senders.push(sender);
+        self
+    }
+
+    pub fn with_fallback(mut self, sender: Arc<dyn MessageSender>) -> Self {
+        self.fallback = Some(sender);
+        self
+    }
+
+    fn route(&self, channel: DeliveryChannel) -> Option<&Arc<dyn MessageSender>> {
+        self.senders
+            .iter()
+            .find(|sender| sender.supports(channel))
+            .or_else(|| self.fallback.as_ref().filter(|sender| sender.supports(channel)))
+    }
+}
+
+impl Default for RoutingSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl MessageSender for RoutingSender {
+    fn name(&self) -> &str {
+        "routing"
+    }
+
+    fn supports(&self, channel: DeliveryChannel) -> bool {
+        self.route(channel).is_some()
+    }
+
+    async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+        match self.route(message.channel) {
+            Some(sender) => sender.send(message).await,
+            None => Err(NotifyError::UnsupportedChannel(message.channel)),
+        }
+    }
+}
+
+// 消息发送错误
+#[derive(Debug, thiserror::Error)]
+pub enum NotifyError {
+    #[error("Unsupported delivery channel: {0:?}")]
+    UnsupportedChannel(DeliveryChannel),
+
+    #[error("Invalid recipient: {0}")]
+    InvalidRecipient(String),
+
+    #[error("Delivery failed: {0}")]
+    DeliveryFailed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_sender_records_messages() {
+        let sender = InMemorySender::new();
+        let message = OutboundMessage::email("user@example.com", "Reset", "token=abc");
+
+        assert!(sender.supports(DeliveryChannel::Email));
+        sender.send(&message).await.unwrap();
+
+        let sent = sender.sent_messages();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0], message);
+    }
+
+    #[tokio::test]
+    async fn test_routing_sender_uses_first_supporting_sender() {
+        struct EmailOnly(InMemorySender);
+
+        #[async_trait::async_trait]
+        impl MessageSender for EmailOnly {
+            fn name(&self) -> &str {
+                "email-only"
+            }
+
+            fn supports(&self, channel: DeliveryChannel) -> bool {
+                channel == DeliveryChannel::Email
+            }
+
+            async fn send(&self, message: &OutboundMessage) -> Result<(), NotifyError> {
+                self.0.send(message).await
+            }
+        }
+
+        let email = Arc::new(EmailOnly(InMemorySender::new()));
+        let fallback = Arc::new(InMemorySender::new());
+        let router = RoutingSender::new().with_sender(email.clone()).with_fallback(fallback.clone());
+
+        router.send(&OutboundMessage::email("user@example.com", "Hi", "body")).await.unwrap();
+        router.send(&OutboundMessage::new(DeliveryChannel::Slack, "https://hooks.example.com", "Hi", "body")).await.unwrap();
+        assert_eq!(email.0.sent_messages().len(), 1);
+        assert_eq!(fallback.sent_messages()[0].channel, DeliveryChannel::Slack);
+
+        let router = RoutingSender::new().with_sender(email);
+        assert!(matches!(
+            router.send(&OutboundMessage::sms("+8613800000000", "code")).await,
+            Err(NotifyError::UnsupportedChannel(DeliveryChannel::Sms))
+        ));
+    }
+
+    #[test]
+    fn test_sms_message_has_no_subject() {
+        let message = OutboundMessage::sms("+8613800000000", "code 1234");
+        assert_eq!(message.channel, DeliveryChannel::Sms);
+        assert!(message.subject.is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! S3 兼容对象存储客户端
+//!
+//! 只实现备份需要的上传和删除对象，使用路径风格 URL（`{endpoint}/{bucket}/{key}`），AWS S3 和 MinIO
+//! 都支持。请求使用 Signature Version 4 签名，签名实现与 AWS Secrets Manager 提供者共用。
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::config::secrets::{hex, sigv4_authorization, AwsCredentials};
+use crate::ObjectStorageConfig;
+
+/// 上传和删除对象的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub struct ObjectStorageClient {
+    endpoint: reqwest::Url,
+    bucket: String,
+    region: String,
+    prefix: String,
+    credentials: AwsCredentials,
+    http: reqwest::Client,
+}
+
+impl ObjectStorageClient {
+    pub fn new(config: &ObjectStorageConfig) -> Result<Self> {
+        let endpoint = reqwest::Url::parse(&config.endpoint)
+            .with_context(|| format!("Invalid object storage endpoint: {}", config.endpoint))?;
+        if endpoint.host_str().is_none() {
+            return Err(anyhow!("Object storage endpoint has no host: {}", config.endpoint));
+        }
+
+        let credentials = match (&config.access_key_id, &config.secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => AwsCredentials {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+                session_token: None,
+            },
+            _ => AwsCredentials::from_env()?,
+        };
+
+        Ok(Self {
+            endpoint,
+            bucket: config.bucket.clone(),
+            region: config.region.clone(),
+            prefix: config.prefix.clone(),
+            credentials,
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+        })
+    }
+
+    /// 对象的完整 URL（对象名为 prefix + name）
+    pub fn object_url(&self, name: &str) -> String {
+        format!("{}{}", self.endpoint.as_str().trim_end_matches('/'), self.object_path(name))
+    }
+
+    /// 上传对象
+    pub async fn put_object(&self, name: &str, body: Vec<u8>) -> Result<()> {
+        let payload_hash = hex(&Sha256::digest(&body));
+        let response = self
+            .signed_request(reqwest::Method::PUT, name, &body, payload_hash)
+            .body(body)
+            .send()
+            .await?;
+        check_status(response, "upload", name).await
+    }
+
+    /// 删除对象，对象不存在时同样视为成功
+    pub async fn delete_object(&self, name: &str) -> Result<()> {
+        let payload_hash = hex(&Sha256::digest(b""));
+        let response = self
+            .signed_request(reqwest::Method::DELETE, name, b"", payload_hash)
+            .send()
+            .await?;
+        check_status(response, "delete", name).await
+    }
+
+    fn object_path(&self, name: &str) -> String {
+        format!("/{}/{}", uri_encode_path(&self.bucket), uri_encode_path(&format!("{}{}", self.prefix, name)))
+    }
+
+    fn signed_request(
+        &self,
+        method: reqwest::Method,
+        name: &str,
+        body: &[u8],
+        payload_hash: String,
+    ) -> reqwest::RequestBuilder {
+        let path = self.object_path(name);
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", self.endpoint.host_str().unwrap_or_default(), port),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash),
+            ("x-amz-date", Utc::now().format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization =
+            sigv4_authorization(&self.credentials, &self.region, "s3", method.as_str(), &path, &headers, body);
+
+        let url = format!("{}{}", self.endpoint.as_str().trim_end_matches('/'), path);
+        let mut request = self.http.request(method, url);
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+        request.header("authorization", authorization)
+    }
+}
+
+async fn check_status(response: reqwest::Response, action: &str, name: &str) -> Result<()> {
+    let status = response.status();
+    if status.is_success() || (action == "delete" && status == reqwest::StatusCode::NOT_FOUND) {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(anyhow!("Object storage {} of {} failed with {}: {}", action, name, status, body))
+}
+
+/// 按 S3 规则对路径编码：保留 `A-Z a-z 0-9 - _ . ~` 和 `/`，其余字节编码为 %XX
+fn uri_encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_url() {
+        let client = ObjectStorageClient::new(&ObjectStorageConfig {
+            endpoint: "http://minio:9000/".to_string(),
+            bucket: "echo-backups".to_string(),
+            region: "us-east-1".to_string(),
+            prefix: "postgres nightly/".to_string(),
+            access_key_id: Some("key".to_string()),
+            secret_access_key: Some("secret".to_string()),
+        })
+        .unwrap();
+
+        assert_eq!(
+            client.object_url("echo-20261016T030000Z.dump"),
+            "http://minio:9000/echo-backups/postgres%20nightly/echo-20261016T030000Z.dump"
+        );
+    }
+}
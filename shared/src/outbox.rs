@@ -0,0 +1,166 @@
+//! 事务性发件箱（outbox）
+//!
+//! 设备注册、会话结束等需要发布到 MQTT 的事件不在提交后直接发布，而是与状态变更在同一个事务中
+//! 写入 `outbox` 表（见 database/init/10-outbox.sql）。Bridge 的转发任务按 id 顺序取出未投递的事件，
+//! 发布成功后标记 `delivered_at`，失败时记录尝试次数和错误并在下一轮重试。事务回滚时事件一并回滚，
+//! MQTT 在提交时不可用也不会丢失事件。多个副本同时转发时使用 `FOR UPDATE SKIP LOCKED` 领取事件，
+//! 同一事件不会被重复领取。
+
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::{FromRow, PgExecutor, Postgres, Transaction};
+
+use crate::mqtt::{MqttMessage, MqttPayload, QoS};
+
+/// 待投递的事件
+#[derive(Debug, Clone, FromRow)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub topic: String,
+    pub payload: serde_json::Value,
+    pub qos: i16,
+    pub retain: bool,
+    pub created_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+impl OutboxEntry {
+    /// 还原为 MQTT 消息，时间戳为写入 outbox 的时间
+    pub fn to_message(&self) -> Result<MqttMessage, serde_json::Error> {
+        let payload: MqttPayload = serde_json::from_value(self.payload.clone())?;
+        let qos = match self.qos {
+            0 => QoS::AtMostOnce,
+            2 => QoS::ExactlyOnce,
+            _ => QoS::AtLeastOnce,
+        };
+        Ok(MqttMessage {
+            topic: self.topic.clone(),
+            payload,
+            qos,
+            retain: self.retain,
+            timestamp: self.created_at,
+        })
+    }
+}
+
+/// 在调用方的事务中写入一条待发布的事件，返回事件 id
+pub async fn enqueue<'e, E>(executor: E, message: &MqttMessage) -> Result<i64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_scalar(
+        "INSERT INTO outbox (topic, payload, qos, retain, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+    )
+    .bind(&message.topic)
+    .bind(Json(&message.payload))
+    .bind(message.qos as i16)
+    .bind(message.retain)
+    .bind(message.timestamp)
+    .fetch_one(executor)
+    .await
+}
+
+/// 领取最多 `limit` 条未投递且尝试次数小于 `max_attempts` 的事件
+///
+/// 事件在事务提交或回滚前保持锁定，其他副本领取时跳过
+pub async fn claim_pending(
+    tx: &mut Transaction<'_, Postgres>,
+    limit: i64,
+    max_attempts: i32,
+) -> Result<Vec<OutboxEntry>, sqlx::Error> {
+    sqlx::query_as::<_, OutboxEntry>(
+        r#"
+        SELECT id, topic, payload, qos, retain, created_at, attempts
+        FROM outbox
+        WHERE delivered_at IS NULL AND attempts < $2
+        ORDER BY id
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(limit)
+    .bind(max_attempts)
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// 标记事件已投递
+pub async fn mark_delivered(tx: &mut Transaction<'_, Postgres>, ids: &[i64]) -> Result<(), sqlx::Error> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    sqlx::query("UPDATE outbox SET delivered_at = NOW(), attempts = attempts + 1, last_error = NULL WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// 记录一次发布失败
+pub async fn mark_failed(tx: &mut Transaction<'_, Postgres>, id: i64, error: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE outbox SET attempts = attempts + 1, last_error = $2 WHERE id = $1")
+        .bind(id)
+        .bind(error)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// 删除投递时间早于 `before` 的事件，返回删除的行数
+pub async fn purge_delivered<'e, E>(executor: E, before: DateTime<Utc>) -> Result<u64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let result = sqlx::query("DELETE FROM outbox WHERE delivered_at IS NOT NULL AND delivered_at < $1")
+        .bind(before)
+        .execute(executor)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+/// 未投递的事件数（包括已达到最大尝试次数的），用于指标
+pub async fn pending_count<'e, E>(executor: E) -> Result<i64, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_scalar("SELECT COUNT(*) FROM outbox WHERE delivered_at IS NULL")
+        .fetch_one(executor)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::MqttMessageBuilder;
+
+    #[test]
+    fn test_entry_round_trip() {
+        let message = MqttMessageBuilder::session_ended(
+            "session-1".to_string(),
+            "device-1".to_string(),
+            None,
+            "completed".to_string(),
+            Some(42),
+        );
+        let entry = OutboxEntry {
+            id: 1,
+            topic: message.topic.clone(),
+            payload: serde_json::to_value(&message.payload).unwrap(),
+            qos: message.qos as i16,
+            retain: message.retain,
+            created_at: message.timestamp,
+            attempts: 0,
+        };
+
+        let restored = entry.to_message().unwrap();
+        assert_eq!(restored.topic, "device/device-1/event");
+        assert_eq!(restored.qos, QoS::AtLeastOnce);
+        match restored.payload {
+            MqttPayload::SessionEnded { session_id, duration_seconds, .. } => {
+                assert_eq!(session_id, "session-1");
+                assert_eq!(duration_seconds, Some(42));
+            }
+            other => panic!("unexpected payload: {:?}", other),
+        }
+    }
+}
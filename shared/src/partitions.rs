@@ -0,0 +1,117 @@
+//! 按月分区表的分区规划
+//!
+//! 分区表按时间列按月分区，月分区名为 `<表名>_pYYYY_MM`，范围为该月第一天 0 点（UTC）到下月第一天。
+//! `plan_partitions` 根据数据库中已有的分区计算需要提前创建的月分区，以及数据全部早于保留期、
+//! 可以整体删除的分区，由 API Gateway 的分区维护任务执行。默认分区不会被删除。
+
+use chrono::{DateTime, Datelike, Months, TimeZone, Utc};
+
+/// 数据库中已有的分区，下界为 MINVALUE 或上界为 MAXVALUE 时对应的值为 None
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionRange {
+    pub name: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub is_default: bool,
+}
+
+impl PartitionRange {
+    fn overlaps(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        !self.is_default
+            && self.from.is_none_or(|start| start < to)
+            && self.to.is_none_or(|end| end > from)
+    }
+}
+
+/// 需要创建的月分区
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyPartition {
+    pub name: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// 一次分区维护需要执行的操作
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartitionPlan {
+    pub create: Vec<MonthlyPartition>,
+    /// 需要删除的分区名
+    pub drop: Vec<String>,
+}
+
+/// 所在月第一天 0 点（UTC）
+pub fn month_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(time.year(), time.month(), 1, 0, 0, 0).unwrap()
+}
+
+/// 计算当月及之后 `months_ahead` 个月中缺少的月分区，以及上界不晚于保留期起点的分区；
+/// `retention_months` 为 0 时不删除分区
+pub fn plan_partitions(
+    table: &str,
+    existing: &[PartitionRange],
+    now: DateTime<Utc>,
+    months_ahead: u32,
+    retention_months: u32,
+) -> PartitionPlan {
+    let current = month_start(now);
+
+    let create = (0..=months_ahead)
+        .map(|offset| current + Months::new(offset))
+        .filter(|from| {
+            let to = *from + Months::new(1);
+            !existing.iter().any(|partition| partition.overlaps(*from, to))
+        })
+        .map(|from| MonthlyPartition {
+            name: format!("{}_p{}", table, from.format("%Y_%m")),
+            from,
+            to: from + Months::new(1),
+        })
+        .collect();
+
+    let drop = if retention_months == 0 {
+        Vec::new()
+    } else {
+        let cutoff = current - Months::new(retention_months);
+        existing
+            .iter()
+            .filter(|partition| !partition.is_default && partition.to.is_some_and(|end| end <= cutoff))
+            .map(|partition| partition.name.clone())
+            .collect()
+    };
+
+    PartitionPlan { create, drop }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn range(name: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> PartitionRange {
+        PartitionRange { name: name.to_string(), from, to, is_default: false }
+    }
+
+    #[test]
+    fn test_plan_creates_missing_months_and_drops_expired() {
+        let existing = vec![
+            range("sessions_legacy", None, Some(at(2026, 1, 1))),
+            range("sessions_p2026_01", Some(at(2026, 1, 1)), Some(at(2026, 2, 1))),
+            range("sessions_p2026_11", Some(at(2026, 11, 1)), Some(at(2026, 12, 1))),
+            PartitionRange { name: "sessions_default".to_string(), from: None, to: None, is_default: true },
+        ];
+
+        let plan = plan_partitions("sessions", &existing, at(2026, 10, 16), 2, 0);
+        let names: Vec<_> = plan.create.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["sessions_p2026_10", "sessions_p2026_12"]);
+        assert_eq!(plan.create[1].from, at(2026, 12, 1));
+        assert_eq!(plan.create[1].to, at(2027, 1, 1));
+        assert!(plan.drop.is_empty());
+
+        // 保留 9 个月：保留期从 2026-01 开始，只有上界不晚于 2026-01-01 的历史分区被删除
+        let plan = plan_partitions("sessions", &existing, at(2026, 10, 16), 2, 9);
+        assert_eq!(plan.drop, ["sessions_legacy"]);
+    }
+}
@@ -0,0 +1,504 @@
+//! Bridge WebSocket 协议定义
+//!
+//! 兼容 EchoKit Server 的自定义协议（MessagePack + JSON），
+//! 供 Bridge、Web 客户端（如 index_zh.html）和设备固件共用。
+//!
+//! - 客户端命令（`ClientCommand`）：JSON 文本消息，`event` 字段为命令名
+//! - 服务端事件（`ServerEvent`）：MessagePack 二进制消息，外部标签枚举
+//!
+//! 消息格式发生不兼容变化时需要递增 `PROTOCOL_VERSION`。
+//!
+//! v2（分帧协议）：连接时通过 WebSocket 子协议 `echo-bridge.v2` 或查询参数 `protocol=2` 协商，
+//! 所有消息都是二进制帧，帧头为 1 字节类型 + 1 字节会话 ID 长度 + 会话 ID，之后是负载：
+//!
+//! - `Audio`：设备上行的 16-bit PCM
+//! - `Event`：Bridge 下发的 MessagePack 事件（与 v1 的二进制消息相同）
+//! - `Control`：MessagePack map，上行为 `ClientCommand`，下行为会话开始/结束、错误等通知
+//!
+//! 未协商 v2 的连接按 v1 处理，旧客户端无需改动。
+
+use serde::{Deserialize, Serialize};
+
+/// 协议版本号
+///
+/// 新增事件类型不改变版本号；删除/重命名事件或字段、修改编码方式时递增，
+/// 客户端据此判断能否与当前 Bridge 通信。
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// 客户端命令（来自 Web 客户端）
+///
+/// 支持 JSON 格式的文本消息
+/// 示例：{"event": "StartChat"}、{"event": "StartChat", "language": "en"}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "event")]
+pub enum ClientCommand {
+    /// 开始录制模式会话
+    StartRecord,
+
+    /// 开始对话模式会话
+    StartChat {
+        /// 本次会话的识别语言（如 "en"、"zh-CN"），未指定时使用默认语言
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+    },
+
+    /// 提交音频数据进行处理
+    Submit,
+
+    /// 发送文本输入
+    Text { input: String },
+}
+
+/// 服务端事件（发送到 Web 客户端）
+///
+/// 使用 MessagePack 二进制格式编码
+/// 对应 EchoKit Server 的 ServerEvent 定义
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ServerEvent {
+    // === 问候消息 ===
+    /// 开始发送问候音频
+    HelloStart,
+
+    /// 问候音频数据块
+    HelloChunk { data: Vec<u8> },
+
+    /// 问候音频结束
+    HelloEnd,
+
+    // === 背景音乐 ===
+    /// 开始发送背景音乐
+    BGStart,
+
+    /// 背景音乐数据块
+    BGChunk { data: Vec<u8> },
+
+    /// 背景音乐结束
+    BGEnd,
+
+    // === 语音识别结果 ===
+    /// ASR（自动语音识别）结果
+    ASR { text: String },
+
+    // === 动作指令 ===
+    /// 动作指令（用于控制设备行为）
+    Action { action: String },
+
+    // === 音频响应 ===
+    /// 开始音频响应
+    StartAudio { text: String },
+
+    /// 音频数据块（16-bit PCM, 16000Hz, 单声道）
+    AudioChunk { data: Vec<u8> },
+
+    /// 音频响应结束
+    EndAudio,
+
+    // === 视频响应（预留）===
+    /// 开始视频响应
+    StartVideo,
+
+    /// 视频响应结束
+    EndVideo,
+
+    // === 响应结束标记 ===
+    /// 完整响应结束
+    EndResponse,
+}
+
+impl ClientCommand {
+    /// 从 JSON 字符串解析客户端命令
+    pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    /// 判断是否为会话开始命令
+    pub fn is_session_start(&self) -> bool {
+        matches!(self, ClientCommand::StartChat { .. } | ClientCommand::StartRecord)
+    }
+
+    /// StartChat 指定的识别语言
+    pub fn language(&self) -> Option<&str> {
+        match self {
+            ClientCommand::StartChat { language } => language.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// 判断是否为录制模式
+    pub fn is_record_mode(&self) -> bool {
+        matches!(self, ClientCommand::StartRecord)
+    }
+
+    /// 命令名，与 JSON 中的 `event` 字段相同
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientCommand::StartRecord => "StartRecord",
+            ClientCommand::StartChat { .. } => "StartChat",
+            ClientCommand::Submit => "Submit",
+            ClientCommand::Text { .. } => "Text",
+        }
+    }
+}
+
+impl ServerEvent {
+    /// 将事件编码为 MessagePack 二进制格式
+    pub fn to_messagepack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    /// 从 MessagePack 二进制格式解码事件
+    pub fn from_messagepack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(data)
+    }
+
+    /// 判断是否为音频相关事件
+    pub fn is_audio_event(&self) -> bool {
+        matches!(
+            self,
+            ServerEvent::StartAudio { .. }
+                | ServerEvent::AudioChunk { .. }
+                | ServerEvent::EndAudio
+        )
+    }
+
+    /// 判断是否为控制事件
+    pub fn is_control_event(&self) -> bool {
+        matches!(
+            self,
+            ServerEvent::HelloStart
+                | ServerEvent::HelloEnd
+                | ServerEvent::BGStart
+                | ServerEvent::BGEnd
+                | ServerEvent::EndResponse
+        )
+    }
+}
+
+impl ClientCommand {
+    /// 将命令编码为 JSON 字符串
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// 将命令编码为 MessagePack（使用字段名编码，保留 `event` 标签）
+    pub fn to_messagepack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec_named(self)
+    }
+
+    /// 从 MessagePack 二进制格式解码命令
+    pub fn from_messagepack(data: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(data)
+    }
+}
+
+/// v2 分帧协议版本号
+pub const FRAMED_PROTOCOL_VERSION: u32 = 2;
+
+/// 协商 v2 的 WebSocket 子协议名
+pub const V2_SUBPROTOCOL: &str = "echo-bridge.v2";
+
+/// 连接使用的协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    /// JSON 文本命令 + 原始 PCM / MessagePack 二进制消息
+    #[default]
+    V1,
+    /// 带类型和会话 ID 的二进制帧
+    V2,
+}
+
+impl ProtocolVersion {
+    /// 根据握手时的子协议和 `protocol` 查询参数判断版本，未协商时为 v1
+    pub fn negotiate(subprotocol: Option<&str>, query: Option<&str>) -> Self {
+        if subprotocol == Some(V2_SUBPROTOCOL) || query == Some("2") {
+            ProtocolVersion::V2
+        } else {
+            ProtocolVersion::V1
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProtocolVersion::V1 => "v1",
+            ProtocolVersion::V2 => "v2",
+        }
+    }
+}
+
+/// v2 帧类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameType {
+    /// 16-bit PCM 音频
+    Audio = 0x01,
+    /// MessagePack 编码的 EchoKit 事件
+    Event = 0x02,
+    /// MessagePack 编码的命令或通知
+    Control = 0x03,
+}
+
+impl TryFrom<u8> for FrameType {
+    type Error = FrameError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(FrameType::Audio),
+            0x02 => Ok(FrameType::Event),
+            0x03 => Ok(FrameType::Control),
+            other => Err(FrameError::UnknownType(other)),
+        }
+    }
+}
+
+/// v2 帧编解码错误
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    #[error("frame is truncated")]
+    Truncated,
+    #[error("unknown frame type 0x{0:02x}")]
+    UnknownType(u8),
+    #[error("session id is not valid UTF-8")]
+    InvalidSessionId,
+    #[error("session id is longer than 255 bytes")]
+    SessionIdTooLong,
+}
+
+/// v2 帧（借用底层缓冲区，解码音频时不复制）
+///
+/// ```text
+/// +------+--------+----------------+---------+
+/// | type | id_len | session_id     | payload |
+/// | 1 B  | 1 B    | id_len B UTF-8 | ...     |
+/// +------+--------+----------------+---------+
+/// ```
+///
+/// 会话 ID 为空表示不属于任何会话（如开始会话的命令、连接级错误）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub frame_type: FrameType,
+    pub session_id: &'a str,
+    pub payload: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    pub fn new(frame_type: FrameType, session_id: &'a str, payload: &'a [u8]) -> Self {
+        Self { frame_type, session_id, payload }
+    }
+
+    /// 解码一条二进制消息
+    pub fn decode(data: &'a [u8]) -> Result<Self, FrameError> {
+        let [frame_type, id_len, rest @ ..] = data else {
+            return Err(FrameError::Truncated);
+        };
+        let frame_type = FrameType::try_from(*frame_type)?;
+        let id_len = *id_len as usize;
+        if rest.len() < id_len {
+            return Err(FrameError::Truncated);
+        }
+        let (session_id, payload) = rest.split_at(id_len);
+        let session_id = std::str::from_utf8(session_id).map_err(|_| FrameError::InvalidSessionId)?;
+        Ok(Self { frame_type, session_id, payload })
+    }
+
+    /// 编码为二进制消息
+    pub fn encode(&self) -> Result<Vec<u8>, FrameError> {
+        let id_len = u8::try_from(self.session_id.len()).map_err(|_| FrameError::SessionIdTooLong)?;
+        let mut frame = Vec::with_capacity(2 + self.session_id.len() + self.payload.len());
+        frame.push(self.frame_type as u8);
+        frame.push(id_len);
+        frame.extend_from_slice(self.session_id.as_bytes());
+        frame.extend_from_slice(self.payload);
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_command_json_parsing() {
+        // 测试 StartChat
+        let json = r#"{"event":"StartChat"}"#;
+        let cmd = ClientCommand::from_json(json).unwrap();
+        assert_eq!(cmd, ClientCommand::StartChat { language: None });
+        assert!(cmd.is_session_start());
+        assert!(!cmd.is_record_mode());
+
+        // 测试指定识别语言的 StartChat
+        let json = r#"{"event":"StartChat","language":"en-US"}"#;
+        let cmd = ClientCommand::from_json(json).unwrap();
+        assert_eq!(cmd.language(), Some("en-US"));
+
+        // 测试 StartRecord
+        let json = r#"{"event":"StartRecord"}"#;
+        let cmd = ClientCommand::from_json(json).unwrap();
+        assert_eq!(cmd, ClientCommand::StartRecord);
+        assert!(cmd.is_session_start());
+        assert!(cmd.is_record_mode());
+
+        // 测试 Submit
+        let json = r#"{"event":"Submit"}"#;
+        let cmd = ClientCommand::from_json(json).unwrap();
+        assert_eq!(cmd, ClientCommand::Submit);
+
+        // 测试 Text
+        let json = r#"{"event":"Text","input":"Hello"}"#;
+        let cmd = ClientCommand::from_json(json).unwrap();
+        assert_eq!(cmd, ClientCommand::Text { input: "Hello".to_string() });
+    }
+
+    #[test]
+    fn test_server_event_messagepack_encoding() {
+        // 测试 ASR 事件
+        let event = ServerEvent::ASR {
+            text: "你好世界".to_string(),
+        };
+        let encoded = event.to_messagepack().unwrap();
+        let decoded = ServerEvent::from_messagepack(&encoded).unwrap();
+        assert_eq!(event, decoded);
+
+        // 测试 StartAudio 事件
+        let event = ServerEvent::StartAudio {
+            text: "正在回答".to_string(),
+        };
+        let encoded = event.to_messagepack().unwrap();
+        let decoded = ServerEvent::from_messagepack(&encoded).unwrap();
+        assert_eq!(event, decoded);
+        assert!(decoded.is_audio_event());
+
+        // 测试 AudioChunk 事件
+        let audio_data = vec![1, 2, 3, 4, 5];
+        let event = ServerEvent::AudioChunk {
+            data: audio_data.clone(),
+        };
+        let encoded = event.to_messagepack().unwrap();
+        let decoded = ServerEvent::from_messagepack(&encoded).unwrap();
+        assert_eq!(event, decoded);
+        assert!(decoded.is_audio_event());
+
+        // 测试 EndAudio 事件
+        let event = ServerEvent::EndAudio;
+        let encoded = event.to_messagepack().unwrap();
+        let decoded = ServerEvent::from_messagepack(&encoded).unwrap();
+        assert_eq!(event, decoded);
+        assert!(decoded.is_audio_event());
+    }
+
+    #[test]
+    fn test_server_event_control_events() {
+        let event = ServerEvent::HelloStart;
+        assert!(event.is_control_event());
+        assert!(!event.is_audio_event());
+
+        let event = ServerEvent::EndResponse;
+        assert!(event.is_control_event());
+        assert!(!event.is_audio_event());
+    }
+
+    #[test]
+    fn test_messagepack_compatibility() {
+        // 测试与 EchoKit Server 协议的兼容性
+        // 确保编码格式一致
+        let event = ServerEvent::ASR {
+            text: "测试".to_string(),
+        };
+
+        let encoded = event.to_messagepack().unwrap();
+
+        // MessagePack 编码应该是紧凑的二进制格式
+        assert!(!encoded.is_empty());
+
+        // 验证可以正确解码
+        let decoded = ServerEvent::from_messagepack(&encoded).unwrap();
+        assert_eq!(event, decoded);
+    }
+
+    fn all_client_commands() -> Vec<ClientCommand> {
+        vec![
+            ClientCommand::StartRecord,
+            ClientCommand::StartChat { language: None },
+            ClientCommand::StartChat { language: Some("en".to_string()) },
+            ClientCommand::Submit,
+            ClientCommand::Text { input: "你好".to_string() },
+        ]
+    }
+
+    fn all_server_events() -> Vec<ServerEvent> {
+        vec![
+            ServerEvent::HelloStart,
+            ServerEvent::HelloChunk { data: vec![0, 1, 2] },
+            ServerEvent::HelloEnd,
+            ServerEvent::BGStart,
+            ServerEvent::BGChunk { data: vec![3, 4] },
+            ServerEvent::BGEnd,
+            ServerEvent::ASR { text: "今天天气怎么样".to_string() },
+            ServerEvent::Action { action: "wake".to_string() },
+            ServerEvent::StartAudio { text: "今天晴".to_string() },
+            ServerEvent::AudioChunk { data: vec![0xff; 32] },
+            ServerEvent::EndAudio,
+            ServerEvent::StartVideo,
+            ServerEvent::EndVideo,
+            ServerEvent::EndResponse,
+        ]
+    }
+
+    #[test]
+    fn test_client_command_round_trip() {
+        for cmd in all_client_commands() {
+            let json = cmd.to_json().unwrap();
+            assert_eq!(ClientCommand::from_json(&json).unwrap(), cmd);
+
+            let encoded = cmd.to_messagepack().unwrap();
+            assert_eq!(ClientCommand::from_messagepack(&encoded).unwrap(), cmd);
+        }
+    }
+
+    #[test]
+    fn test_server_event_round_trip() {
+        for event in all_server_events() {
+            let encoded = event.to_messagepack().unwrap();
+            assert_eq!(ServerEvent::from_messagepack(&encoded).unwrap(), event);
+
+            let json = serde_json::to_string(&event).unwrap();
+            assert_eq!(serde_json::from_str::<ServerEvent>(&json).unwrap(), event);
+        }
+    }
+
+    #[test]
+    fn test_v2_frame_round_trip() {
+        let payload = ClientCommand::StartChat { language: None }.to_messagepack().unwrap();
+        let encoded = Frame::new(FrameType::Control, "session_1", &payload).encode().unwrap();
+        assert_eq!(&encoded[..11], b"\x03\x09session_1");
+
+        let frame = Frame::decode(&encoded).unwrap();
+        assert_eq!(frame.frame_type, FrameType::Control);
+        assert_eq!(frame.session_id, "session_1");
+        assert_eq!(ClientCommand::from_messagepack(frame.payload).unwrap(), ClientCommand::StartChat { language: None });
+
+        // 空会话 ID、空负载
+        assert_eq!(Frame::decode(b"\x01\x00").unwrap(), Frame::new(FrameType::Audio, "", b""));
+
+        assert_eq!(Frame::decode(b"\x01"), Err(FrameError::Truncated));
+        assert_eq!(Frame::decode(b"\x02\x05abc"), Err(FrameError::Truncated));
+        assert_eq!(Frame::decode(b"\x7f\x00"), Err(FrameError::UnknownType(0x7f)));
+        let long_id = "x".repeat(256);
+        assert_eq!(Frame::new(FrameType::Event, &long_id, b"").encode(), Err(FrameError::SessionIdTooLong));
+
+        assert_eq!(ProtocolVersion::negotiate(Some(V2_SUBPROTOCOL), None), ProtocolVersion::V2);
+        assert_eq!(ProtocolVersion::negotiate(None, Some("2")), ProtocolVersion::V2);
+        assert_eq!(ProtocolVersion::negotiate(None, None), ProtocolVersion::V1);
+    }
+
+    #[test]
+    fn test_wire_format_is_stable() {
+        // 线上格式变化意味着需要递增 PROTOCOL_VERSION
+        assert_eq!(PROTOCOL_VERSION, 1);
+        assert_eq!(ClientCommand::Text { input: "hi".to_string() }.to_json().unwrap(), r#"{"event":"Text","input":"hi"}"#);
+
+        // 单元变体编码为字符串，带字段的变体编码为 {变体名: [字段...]}
+        assert_eq!(ServerEvent::EndAudio.to_messagepack().unwrap(), rmp_serde::to_vec(&"EndAudio").unwrap());
+        let encoded = ServerEvent::ASR { text: "hi".to_string() }.to_messagepack().unwrap();
+        assert_eq!(encoded, vec![0x81, 0xa3, b'A', b'S', b'R', 0x91, 0xa2, b'h', b'i']);
+    }
+}
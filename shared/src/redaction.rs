@@ -0,0 +1,183 @@
+//! 转写文本脱敏
+//!
+//! 会话结束写入数据库前，按会话所属用户的设置（`redaction.enabled`，可被 `redaction.users` 按用户覆盖）
+//! 把转写和 AI 回复中的手机号、邮箱、银行卡号以及自定义正则匹配的内容替换为 `redaction.replacement`，
+//! 会话 metadata 的 `redacted` 字段记录本次写入是否替换过内容。
+//!
+//! 日志不区分用户，`redaction.logs` 启用时启动阶段通过 `install_log_redactor` 安装全局规则，
+//! 输出转写或回复文本的日志使用 `redact_for_log` 包裹文本。
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use regex::{Captures, NoExpand, Regex};
+
+use crate::types::RedactionConfig;
+
+const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+// 13-19 位数字，允许空格或短横线分组，匹配后再做 Luhn 校验
+const CREDIT_CARD_PATTERN: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+// 可选的国际区号，至少 6 位数字，允许空格或短横线分组
+const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[ -]?)?\b\d{3,4}[ -]?\d{3,4}(?:[ -]?\d{3,4})?\b";
+
+static LOG_REDACTOR: OnceLock<Arc<Redactor>> = OnceLock::new();
+
+enum Rule {
+    Pattern(Regex),
+    // 只替换通过 Luhn 校验的数字串，减少订单号等的误判
+    CreditCard(Regex),
+}
+
+/// 脱敏后的文本
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redacted<'a> {
+    pub text: Cow<'a, str>,
+    /// 是否替换过内容
+    pub redacted: bool,
+}
+
+pub struct Redactor {
+    rules: Vec<Rule>,
+    replacement: String,
+    enabled: bool,
+    users: HashMap<String, bool>,
+}
+
+impl Redactor {
+    /// 按配置编译规则，自定义正则无效时返回错误
+    pub fn from_config(config: &RedactionConfig) -> Result<Self, regex::Error> {
+        let mut rules = Vec::new();
+        // 邮箱先于号码替换，避免邮箱中的数字被当作号码
+        if config.emails {
+            rules.push(Rule::Pattern(Regex::new(EMAIL_PATTERN)?));
+        }
+        if config.credit_cards {
+            rules.push(Rule::CreditCard(Regex::new(CREDIT_CARD_PATTERN)?));
+        }
+        if config.phone_numbers {
+            rules.push(Rule::Pattern(Regex::new(PHONE_PATTERN)?));
+        }
+        for pattern in &config.patterns {
+            rules.push(Rule::Pattern(Regex::new(pattern)?));
+        }
+
+        Ok(Self {
+            rules,
+            replacement: config.replacement.clone(),
+            enabled: config.enabled,
+            users: config.users.clone(),
+        })
+    }
+
+    /// 设备所属用户的会话是否需要脱敏，没有所属用户时按 `enabled`
+    pub fn applies_to(&self, user_id: Option<&str>) -> bool {
+        user_id
+            .and_then(|user_id| self.users.get(user_id))
+            .copied()
+            .unwrap_or(self.enabled)
+    }
+
+    /// 替换文本中的敏感内容
+    pub fn redact<'a>(&self, text: &'a str) -> Redacted<'a> {
+        let mut text = Cow::Borrowed(text);
+        let mut redacted = false;
+        for rule in &self.rules {
+            let replaced = match rule {
+                Rule::Pattern(regex) => regex.replace_all(&text, NoExpand(&self.replacement)),
+                Rule::CreditCard(regex) => {
+                    if !regex.find_iter(&text).any(|m| luhn_valid(m.as_str())) {
+                        continue;
+                    }
+                    regex.replace_all(&text, |caps: &Captures| {
+                        if luhn_valid(&caps[0]) {
+                            self.replacement.clone()
+                        } else {
+                            caps[0].to_string()
+                        }
+                    })
+                }
+            };
+            if let Cow::Owned(replaced) = replaced {
+                text = Cow::Owned(replaced);
+                redacted = true;
+            }
+        }
+        Redacted { text, redacted }
+    }
+}
+
+// Luhn 校验，忽略分组用的空格和短横线
+fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// 安装日志脱敏规则，只在启动时调用一次，重复调用被忽略
+pub fn install_log_redactor(redactor: Arc<Redactor>) {
+    let _ = LOG_REDACTOR.set(redactor);
+}
+
+/// 日志中输出转写或回复文本时使用，未安装日志脱敏规则时原样返回
+pub fn redact_for_log(text: &str) -> Cow<'_, str> {
+    match LOG_REDACTOR.get() {
+        Some(redactor) => redactor.redact(text).text,
+        None => Cow::Borrowed(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_builtin_and_custom_patterns() {
+        let config = RedactionConfig {
+            enabled: true,
+            patterns: vec![r"订单号\s*\w+".to_string()],
+            ..RedactionConfig::default()
+        };
+        let redactor = Redactor::from_config(&config).unwrap();
+
+        let result = redactor.redact("请联系 zhang.san@example.com 或拨打 +86 139 1234 5678");
+        assert!(result.redacted);
+        assert_eq!(result.text, "请联系 [REDACTED] 或拨打 [REDACTED]");
+
+        assert_eq!(redactor.redact("卡号 4111 1111 1111 1111").text, "卡号 [REDACTED]");
+        assert_eq!(redactor.redact("订单号 A123 已发货").text, "[REDACTED] 已发货");
+
+        // 没有敏感内容时不复制文本
+        let result = redactor.redact("今天天气怎么样");
+        assert!(!result.redacted);
+        assert!(matches!(result.text, Cow::Borrowed(_)));
+
+        // 替换文本中的 $ 不作为分组引用
+        let dollar = Redactor::from_config(&RedactionConfig { replacement: "$1".to_string(), ..config }).unwrap();
+        assert_eq!(dollar.redact("a@b.cn").text, "$1");
+    }
+
+    #[test]
+    fn test_luhn_and_user_overrides() {
+        assert!(luhn_valid("4111-1111-1111-1111"));
+        assert!(!luhn_valid("4111111111111112"));
+
+        let config = RedactionConfig {
+            users: HashMap::from([("user-a".to_string(), true)]),
+            ..RedactionConfig::default()
+        };
+        let redactor = Redactor::from_config(&config).unwrap();
+        assert!(redactor.applies_to(Some("user-a")));
+        assert!(!redactor.applies_to(Some("user-b")));
+        assert!(!redactor.applies_to(None));
+    }
+}
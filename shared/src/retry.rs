@@ -0,0 +1,143 @@
+//! 带抖动的指数退避重试
+//!
+//! 用于数据库短暂不可用（连接断开、连接池超时等）时重试写操作。每次重试前的等待时间翻倍，
+//! 并在 [一半, 全部] 区间内随机抖动，避免多个调用方同时重试。
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::types::DatabaseRetryConfig;
+
+/// 重试策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 总尝试次数（包括第一次）
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间
+    pub initial_backoff: Duration,
+    /// 最长等待时间
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff,
+            max_backoff: max_backoff.max(initial_backoff),
+        }
+    }
+
+    pub fn from_config(config: &DatabaseRetryConfig) -> Self {
+        Self::new(
+            config.max_attempts,
+            Duration::from_millis(config.initial_backoff_ms),
+            Duration::from_millis(config.max_backoff_ms),
+        )
+    }
+
+    /// 不重试
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO, Duration::ZERO)
+    }
+
+    /// 第 `attempt` 次尝试失败后的等待时间（attempt 从 1 开始）
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let base = self.initial_backoff.saturating_mul(1 << exponent).min(self.max_backoff);
+        let half = base / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// 执行 `operation`，失败且 `should_retry(错误, 已尝试次数)` 返回 true 时等待后重试
+///
+/// `should_retry` 同时可用于记录重试日志；达到最大尝试次数后返回最后一次的错误。
+pub async fn retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    mut operation: F,
+    mut should_retry: impl FnMut(&E, u32) -> bool,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && should_retry(&e, attempt) => {
+                tokio::time::sleep(policy.backoff(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// [0, 1) 之间的随机数，每次调用使用新的随机种子
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_doubles_with_jitter_and_caps() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_millis(300));
+        for (attempt, base) in [(1, 100), (2, 200), (3, 300), (10, 300)] {
+            let backoff = policy.backoff(attempt);
+            assert!(backoff >= Duration::from_millis(base / 2) && backoff <= Duration::from_millis(base));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_on_success_or_non_retryable_error() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1), Duration::from_millis(2));
+
+        let calls = AtomicU32::new(0);
+        let result: Result<u32, &str> = retry(
+            &policy,
+            || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err("transient"),
+                    n => Ok(n),
+                }
+            },
+            |e, _| *e == "transient",
+        )
+        .await;
+        assert_eq!(result, Ok(1));
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<(), &str> = retry(
+            &policy,
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err("constraint")
+            },
+            |e, _| *e == "transient",
+        )
+        .await;
+        assert_eq!((result, calls.load(Ordering::SeqCst)), (Err("constraint"), 1));
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<(), &str> = retry(
+            &policy,
+            || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err("transient")
+            },
+            |_, _| true,
+        )
+        .await;
+        assert_eq!((result, calls.load(Ordering::SeqCst)), (Err("transient"), 3));
+    }
+}
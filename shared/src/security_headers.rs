@@ -0,0 +1,93 @@
+//! HTTP 安全响应头
+//!
+//! API Gateway 和 Bridge 的中间件按 `[security_headers]` 为每个响应补充以下头（处理函数已设置的不覆盖）：
+//!
+//! - `Strict-Transport-Security`：只在 HTTPS 请求中发送（本服务启用 TLS，或反向代理标记 `X-Forwarded-Proto: https`）
+//! - `X-Content-Type-Options: nosniff`
+//! - `Content-Security-Policy: frame-ancestors ...` 和对应的 `X-Frame-Options`，限制 Bridge 自带 Web UI 等页面被嵌入
+//! - `Referrer-Policy`
+
+use crate::types::SecurityHeadersConfig;
+
+/// 按配置生成的响应头，启动时创建一次
+#[derive(Debug, Clone, Default)]
+pub struct SecurityHeaders {
+    // 本服务是否直接提供 HTTPS
+    tls: bool,
+    hsts: Option<String>,
+    // 所有请求都发送的头
+    common: Vec<(&'static str, String)>,
+}
+
+impl SecurityHeaders {
+    pub fn new(config: &SecurityHeadersConfig) -> Self {
+        if !config.enabled {
+            return Self::default();
+        }
+
+        let hsts = config.hsts.then(|| {
+            let mut value = format!("max-age={}", config.hsts_max_age_seconds);
+            if config.hsts_include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            value
+        });
+
+        let mut common = Vec::new();
+        if config.content_type_options {
+            common.push(("x-content-type-options", "nosniff".to_string()));
+        }
+        if !config.frame_ancestors.is_empty() {
+            common.push(("content-security-policy", format!("frame-ancestors {}", config.frame_ancestors.join(" "))));
+            // 不支持 CSP 的旧浏览器只能表达 DENY 和 SAMEORIGIN
+            match config.frame_ancestors.as_slice() {
+                [only] if only == "'none'" => common.push(("x-frame-options", "DENY".to_string())),
+                [only] if only == "'self'" => common.push(("x-frame-options", "SAMEORIGIN".to_string())),
+                _ => {}
+            }
+        }
+        if !config.referrer_policy.is_empty() {
+            common.push(("referrer-policy", config.referrer_policy.clone()));
+        }
+
+        Self { tls: false, hsts, common }
+    }
+
+    /// 本服务启用 TLS 时所有请求都视为 HTTPS
+    pub fn with_tls(mut self, enabled: bool) -> Self {
+        self.tls = enabled;
+        self
+    }
+
+    /// 响应需要补充的头，forwarded_https 表示反向代理是否标记请求经 HTTPS 到达
+    pub fn headers(&self, forwarded_https: bool) -> impl Iterator<Item = (&'static str, &str)> {
+        let https = self.tls || forwarded_https;
+        let hsts = self.hsts.as_deref().filter(|_| https).map(|value| ("strict-transport-security", value));
+        self.common.iter().map(|(name, value)| (*name, value.as_str())).chain(hsts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_headers() {
+        let headers = SecurityHeaders::new(&SecurityHeadersConfig::default());
+        let plain: Vec<_> = headers.headers(false).collect();
+        assert_eq!(
+            plain,
+            vec![
+                ("x-content-type-options", "nosniff"),
+                ("content-security-policy", "frame-ancestors 'self'"),
+                ("x-frame-options", "SAMEORIGIN"),
+                ("referrer-policy", "no-referrer"),
+            ]
+        );
+        assert!(headers.headers(true).any(|header| header == ("strict-transport-security", "max-age=31536000")));
+        assert!(headers.with_tls(true).headers(false).any(|(name, _)| name == "strict-transport-security"));
+
+        let disabled = SecurityHeadersConfig { enabled: false, ..SecurityHeadersConfig::default() };
+        assert_eq!(SecurityHeaders::new(&disabled).headers(true).count(), 0);
+    }
+}
@@ -0,0 +1,194 @@
+//! 会话配额计数
+//!
+//! Bridge 和 API Gateway 创建会话前在 Redis 中检查并占用配额，检查和计数在同一个 Lua 脚本中完成，
+//! 多个副本同时创建会话时也不会超出限额：
+//! - 每个用户每天（UTC）创建的会话数：计数器 `quota:sessions:user:{user_id}:{YYYYMMDD}`，两天后过期
+//! - 每个设备同时进行的会话数：有序集合 `quota:sessions:device:{device_id}`，成员为会话 ID，分数为租约到期时间。
+//!   会话结束时移除；实例异常退出未能移除的会话在租约到期后不再计入。租约按 Redis 服务器时间计算，
+//!   不受各实例时钟偏差影响
+//!
+//! 同一会话重复占用（如创建会话的重试）只续期租约，不重复计数。
+
+use redis::aio::ConnectionLike;
+use redis::{RedisResult, Script};
+
+use crate::cache::CacheKey;
+use crate::types::SessionLimitConfig;
+use crate::utils::now_utc;
+
+// 每日计数器的过期时间，覆盖跨天前后的时钟偏差
+const DAILY_COUNTER_TTL_SECONDS: u64 = 2 * 86400;
+
+// KEYS[1]：设备进行中的会话；KEYS[2]：用户当天的会话数（没有用户时不传）
+// ARGV：会话 ID、设备并发上限、用户每日上限（0 表示不限制）、租约（毫秒）、每日计数器过期时间（秒）
+// 返回 0 表示占用成功，1 表示设备并发数已满，2 表示用户当天的会话数已满
+const ACQUIRE_SCRIPT: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local lease = tonumber(ARGV[4])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now)
+if redis.call('ZSCORE', KEYS[1], ARGV[1]) then
+    redis.call('ZADD', KEYS[1], now + lease, ARGV[1])
+    redis.call('PEXPIRE', KEYS[1], lease)
+    return 0
+end
+local device_limit = tonumber(ARGV[2])
+if device_limit > 0 and redis.call('ZCARD', KEYS[1]) >= device_limit then
+    return 1
+end
+if #KEYS > 1 then
+    local user_limit = tonumber(ARGV[3])
+    if user_limit > 0 and tonumber(redis.call('GET', KEYS[2]) or '0') >= user_limit then
+        return 2
+    end
+    if redis.call('INCR', KEYS[2]) == 1 then
+        redis.call('EXPIRE', KEYS[2], ARGV[5])
+    end
+end
+redis.call('ZADD', KEYS[1], now + lease, ARGV[1])
+redis.call('PEXPIRE', KEYS[1], lease)
+return 0
+"#;
+
+// 会话创建失败时撤销占用：只有会话确实在设备的集合中时才退还用户当天的计数
+// KEYS[1]：设备进行中的会话；KEYS[2]：用户当天的会话数（可选）；ARGV[1]：会话 ID
+const CANCEL_SCRIPT: &str = r#"
+if redis.call('ZREM', KEYS[1], ARGV[1]) == 1 and #KEYS > 1 then
+    if tonumber(redis.call('GET', KEYS[2]) or '0') > 0 then
+        redis.call('DECR', KEYS[2])
+    end
+end
+return 0
+"#;
+
+/// 超出会话配额
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SessionLimitExceeded {
+    #[error("Device {device_id} already has {limit} active sessions")]
+    Device { device_id: String, limit: u32 },
+    #[error("User {user_id} has reached the daily limit of {limit} sessions")]
+    User { user_id: String, limit: u32 },
+}
+
+pub struct SessionLimits {
+    config: SessionLimitConfig,
+    acquire: Script,
+    cancel: Script,
+}
+
+impl SessionLimits {
+    pub fn new(config: SessionLimitConfig) -> Self {
+        Self { config, acquire: Script::new(ACQUIRE_SCRIPT), cancel: Script::new(CANCEL_SCRIPT) }
+    }
+
+    /// 配置启用时创建
+    pub fn from_config(config: &SessionLimitConfig) -> Option<Self> {
+        config.enabled.then(|| Self::new(config.clone()))
+    }
+
+    pub fn config(&self) -> &SessionLimitConfig {
+        &self.config
+    }
+
+    /// 为新会话占用配额，超出配额时返回 `Ok(Err(..))`
+    ///
+    /// 没有用户的会话（如设备直接发起的会话）只检查设备并发数
+    pub async fn acquire<C: ConnectionLike>(
+        &self,
+        conn: &mut C,
+        session_id: &str,
+        device_id: &str,
+        user_id: Option<&str>,
+    ) -> RedisResult<Result<(), SessionLimitExceeded>> {
+        let mut invocation = self.acquire.prepare_invoke();
+        invocation.key(CacheKey::device_active_sessions(device_id));
+        if let Some(user_id) = user_id {
+            invocation.key(daily_key(user_id));
+        }
+        let code: i64 = invocation
+            .arg(session_id)
+            .arg(self.config.concurrent_sessions_per_device)
+            .arg(self.config.sessions_per_user_per_day)
+            .arg(self.config.session_lease_seconds * 1000)
+            .arg(DAILY_COUNTER_TTL_SECONDS)
+            .invoke_async(conn)
+            .await?;
+        Ok(self.outcome(code, device_id, user_id))
+    }
+
+    /// 会话结束，释放设备的并发配额（当天的会话数不退还）
+    pub async fn release<C: ConnectionLike>(&self, conn: &mut C, session_id: &str, device_id: &str) -> RedisResult<()> {
+        redis::cmd("ZREM")
+            .arg(CacheKey::device_active_sessions(device_id))
+            .arg(session_id)
+            .query_async(conn)
+            .await
+    }
+
+    /// 占用配额后会话创建失败：释放设备的并发配额，并退还用户当天的计数
+    pub async fn cancel<C: ConnectionLike>(
+        &self,
+        conn: &mut C,
+        session_id: &str,
+        device_id: &str,
+        user_id: Option<&str>,
+    ) -> RedisResult<()> {
+        let mut invocation = self.cancel.prepare_invoke();
+        invocation.key(CacheKey::device_active_sessions(device_id));
+        if let Some(user_id) = user_id {
+            invocation.key(daily_key(user_id));
+        }
+        invocation.arg(session_id).invoke_async(conn).await
+    }
+
+    fn outcome(&self, code: i64, device_id: &str, user_id: Option<&str>) -> Result<(), SessionLimitExceeded> {
+        match (code, user_id) {
+            (1, _) => Err(SessionLimitExceeded::Device {
+                device_id: device_id.to_string(),
+                limit: self.config.concurrent_sessions_per_device,
+            }),
+            (2, Some(user_id)) => Err(SessionLimitExceeded::User {
+                user_id: user_id.to_string(),
+                limit: self.config.sessions_per_user_per_day,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// 用户当天（UTC）的会话计数器
+fn daily_key(user_id: &str) -> CacheKey {
+    CacheKey::user_daily_sessions(user_id, &now_utc().format("%Y%m%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_limit_outcome() {
+        let limits = SessionLimits::new(SessionLimitConfig {
+            enabled: true,
+            sessions_per_user_per_day: 20,
+            concurrent_sessions_per_device: 1,
+            session_lease_seconds: 600,
+        });
+
+        assert_eq!(limits.outcome(0, "dev-1", Some("user-1")), Ok(()));
+        assert_eq!(
+            limits.outcome(1, "dev-1", Some("user-1")),
+            Err(SessionLimitExceeded::Device { device_id: "dev-1".to_string(), limit: 1 })
+        );
+        assert_eq!(
+            limits.outcome(2, "dev-1", Some("user-1")).unwrap_err().to_string(),
+            "User user-1 has reached the daily limit of 20 sessions"
+        );
+
+        assert_eq!(
+            CacheKey::user_daily_sessions("user-1", "20260101").as_str(),
+            "quota:sessions:user:user-1:20260101"
+        );
+        assert_eq!(CacheKey::device_active_sessions("dev-1").as_str(), "quota:sessions:device:dev-1");
+        assert!(SessionLimits::from_config(&SessionLimitConfig::default()).is_none());
+    }
+}
@@ -0,0 +1,901 @@
+//! 设备、用户和会话的存储层
+//!
+//! `Storage` 抽象了这三类数据的基本读写，按 `database.url` 的协议选择实现：`postgres://` /
+//! `postgresql://` 使用 PostgreSQL（表结构见 database/init），`sqlite:` 使用 SQLite（需要启用
+//! `sqlite` feature，表结构见 database/sqlite/schema.sql，连接时自动创建），便于不想部署
+//! PostgreSQL 的单机安装。两种实现的 SQL 尽量相同，列名与 PostgreSQL 表结构保持一致。
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::database::DatabaseError;
+use crate::types::{DatabaseConfig, Device, DeviceStatus, DeviceType, Session, SessionStatus, User, UserRole};
+use crate::{DeviceId, SessionId, UserId};
+
+const DEVICE_COLUMNS: &str = "id, name, device_type, status, location, firmware_version, battery_level, volume_level, \
+     last_seen, is_online, owner, echokit_server_url, serial_number, mac_address, version";
+
+const SESSION_COLUMNS: &str = "id, device_id, user_id, status, start_time, end_time, duration, transcription, response, summary, language";
+
+const UPSERT_DEVICE: &str = r#"
+    INSERT INTO devices (id, name, device_type, status, location, firmware_version, battery_level, volume_level,
+                         last_seen, is_online, owner, echokit_server_url, serial_number, mac_address)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+    ON CONFLICT (id) DO UPDATE SET
+        name = EXCLUDED.name,
+        device_type = EXCLUDED.device_type,
+        status = EXCLUDED.status,
+        location = EXCLUDED.location,
+        firmware_version = EXCLUDED.firmware_version,
+        battery_level = EXCLUDED.battery_level,
+        volume_level = EXCLUDED.volume_level,
+        last_seen = EXCLUDED.last_seen,
+        is_online = EXCLUDED.is_online,
+        owner = EXCLUDED.owner,
+        echokit_server_url = EXCLUDED.echokit_server_url,
+        serial_number = EXCLUDED.serial_number,
+        mac_address = EXCLUDED.mac_address,
+        version = devices.version + 1
+"#;
+
+const UPDATE_DEVICE_STATUS: &str = "UPDATE devices SET status = $2, is_online = $3, last_seen = $4 WHERE id = $1";
+
+const DELETE_DEVICE: &str = "DELETE FROM devices WHERE id = $1";
+
+const INSERT_SESSION: &str = r#"
+    INSERT INTO sessions (id, device_id, user_id, status, start_time, end_time, duration, transcription, response, language)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+"#;
+
+const UPDATE_SESSION: &str = r#"
+    UPDATE sessions
+    SET status = $2, end_time = $3, duration = $4, transcription = $5, response = $6
+    WHERE id = $1
+"#;
+
+const TIMEOUT_SESSIONS: &str = "UPDATE sessions SET status = 'timeout', end_time = $2 WHERE status = 'active' AND start_time < $1";
+
+/// 存储后端类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Postgres,
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// 按数据库 URL 的协议选择后端
+    pub fn from_url(url: &str) -> Result<Self, DatabaseError> {
+        match url.split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase()).as_deref() {
+            Some("postgres") | Some("postgresql") => Ok(StorageBackend::Postgres),
+            Some("sqlite") => Ok(StorageBackend::Sqlite),
+            _ => Err(DatabaseError::InvalidInput(format!(
+                "unsupported database url scheme (expected postgres:// or sqlite:): {}",
+                url.split_once(':').map_or(url, |(scheme, _)| scheme)
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for StorageBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageBackend::Postgres => write!(f, "postgres"),
+            StorageBackend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// 会话查询过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    pub device_id: Option<String>,
+    pub status: Option<SessionStatus>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+/// 设备、用户和会话的读写
+#[async_trait]
+pub trait Storage: Send + Sync {
+    fn backend(&self) -> StorageBackend;
+
+    /// PostgreSQL 后端的连接池，其他后端为 None
+    ///
+    /// 搜索、统计、分区、Webhook 等只有 PostgreSQL 实现的功能通过它访问数据库
+    fn postgres_pool(&self) -> Option<&PgPool> {
+        None
+    }
+
+    /// 健康检查，失败时返回错误原因
+    async fn health_check(&self) -> Result<(), DatabaseError>;
+
+    /// 关闭连接池，等待进行中的查询结束
+    async fn close(&self);
+
+    // 设备
+    async fn list_devices(&self) -> Result<Vec<Device>, DatabaseError>;
+    async fn get_device(&self, id: &DeviceId) -> Result<Option<Device>, DatabaseError>;
+    /// 设备不存在时创建，存在时覆盖
+    async fn save_device(&self, device: &Device) -> Result<(), DatabaseError>;
+    /// 更新设备状态和最后在线时间，设备不存在时返回 false
+    async fn update_device_status(&self, id: &DeviceId, status: DeviceStatus) -> Result<bool, DatabaseError>;
+    /// 删除设备及其会话，设备不存在时返回 false
+    async fn delete_device(&self, id: &DeviceId) -> Result<bool, DatabaseError>;
+
+    // 用户（只返回启用的用户）
+    async fn get_user(&self, id: &UserId) -> Result<Option<User>, DatabaseError>;
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, DatabaseError>;
+    /// 创建用户，用户名或邮箱已存在时返回 DuplicateRecord
+    async fn create_user(&self, user: &User) -> Result<(), DatabaseError>;
+
+    // 会话
+    /// 创建会话，会话 ID 已存在时返回 DuplicateRecord
+    async fn create_session(&self, session: &Session) -> Result<(), DatabaseError>;
+    async fn get_session(&self, id: &SessionId) -> Result<Option<Session>, DatabaseError>;
+    /// 更新会话状态、结束时间、时长、转录和回复，会话不存在时返回 false
+    async fn update_session(&self, session: &Session) -> Result<bool, DatabaseError>;
+    /// 设备最近的会话（最新的在前）
+    async fn list_device_sessions(&self, device_id: &DeviceId, limit: i64) -> Result<Vec<Session>, DatabaseError>;
+    /// 按条件分页查询会话（最新的在前），返回 (当前页会话, 满足条件的总数)
+    async fn list_sessions(&self, filter: &SessionFilter, limit: i64, offset: i64) -> Result<(Vec<Session>, u64), DatabaseError>;
+    /// 开始时间早于 `started_before` 的活跃会话标记为超时，返回更新的会话数
+    async fn timeout_sessions(&self, started_before: DateTime<Utc>) -> Result<u64, DatabaseError>;
+}
+
+/// 按 `database.url` 的协议连接对应的存储后端
+pub async fn connect_storage(config: &DatabaseConfig) -> Result<Arc<dyn Storage>, DatabaseError> {
+    match StorageBackend::from_url(&config.url)? {
+        StorageBackend::Postgres => {
+            let pool = crate::pool_options(config).connect(&config.url).await?;
+            Ok(Arc::new(PostgresStorage::new(pool)))
+        }
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Ok(Arc::new(SqliteStorage::connect(config).await?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Err(DatabaseError::InvalidInput(
+            "sqlite database urls require building with the sqlite feature".to_string(),
+        )),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct DeviceRow {
+    id: String,
+    name: String,
+    device_type: String,
+    status: String,
+    location: Option<String>,
+    firmware_version: Option<String>,
+    battery_level: Option<i32>,
+    volume_level: Option<i32>,
+    last_seen: Option<DateTime<Utc>>,
+    is_online: Option<bool>,
+    owner: Option<String>,
+    echokit_server_url: String,
+    serial_number: Option<String>,
+    mac_address: Option<String>,
+    version: i32,
+}
+
+impl From<DeviceRow> for Device {
+    fn from(row: DeviceRow) -> Self {
+        Device {
+            id: row.id,
+            name: row.name,
+            // 与 API Gateway 一致，未识别的设备类型按音箱处理
+            device_type: match row.device_type.as_str() {
+                "unknown" => DeviceType::Unknown,
+                _ => DeviceType::Speaker,
+            },
+            status: DeviceStatus::from(row.status.as_str()),
+            location: row.location.unwrap_or_default(),
+            firmware_version: row.firmware_version.unwrap_or_default(),
+            battery_level: row.battery_level.unwrap_or(0),
+            volume: row.volume_level.unwrap_or(50),
+            last_seen: row.last_seen.unwrap_or_else(Utc::now),
+            is_online: row.is_online.unwrap_or(false),
+            owner: row.owner.unwrap_or_default(),
+            echokit_server_url: Some(row.echokit_server_url).filter(|url| !url.is_empty()),
+            serial_number: row.serial_number,
+            mac_address: row.mac_address,
+            version: row.version,
+            current_session_id: None,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+    id: String,
+    username: String,
+    email: String,
+    password_hash: String,
+    role: String,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        User {
+            id: row.id,
+            username: row.username,
+            email: row.email,
+            password_hash: row.password_hash,
+            role: match row.role.as_str() {
+                "Admin" => UserRole::Admin,
+                "Manager" => UserRole::User,
+                _ => UserRole::Viewer,
+            },
+        }
+    }
+}
+
+// 数据库中的角色取值为 Admin / Manager / Viewer
+fn role_str(role: &UserRole) -> &'static str {
+    match role {
+        UserRole::Admin => "Admin",
+        UserRole::User => "Manager",
+        UserRole::Viewer => "Viewer",
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SessionRow {
+    id: String,
+    device_id: String,
+    user_id: Option<String>,
+    status: String,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    duration: Option<i32>,
+    transcription: Option<String>,
+    response: Option<String>,
+    summary: Option<String>,
+    language: Option<String>,
+    /// JSON 数组文本
+    summary_tags: Option<String>,
+}
+
+impl From<SessionRow> for Session {
+    fn from(row: SessionRow) -> Self {
+        Session {
+            id: row.id,
+            device_id: row.device_id,
+            user_id: row.user_id,
+            start_time: row.start_time,
+            end_time: row.end_time,
+            duration: row.duration,
+            transcription: row.transcription,
+            response: row.response,
+            status: match row.status.as_str() {
+                "active" => SessionStatus::Active,
+                "completed" => SessionStatus::Completed,
+                "timeout" => SessionStatus::Timeout,
+                _ => SessionStatus::Failed,
+            },
+            summary: row.summary,
+            tags: row
+                .summary_tags
+                .and_then(|tags| serde_json::from_str(&tags).ok())
+                .unwrap_or_default(),
+            language: row.language,
+        }
+    }
+}
+
+fn session_status_str(status: &SessionStatus) -> &'static str {
+    match status {
+        SessionStatus::Active => "active",
+        SessionStatus::Completed => "completed",
+        SessionStatus::Failed => "failed",
+        SessionStatus::Timeout => "timeout",
+    }
+}
+
+// 唯一约束冲突转换为 DuplicateRecord
+fn duplicate_or(error: sqlx::Error, record: String) -> DatabaseError {
+    match &error {
+        sqlx::Error::Database(e) if e.is_unique_violation() => DatabaseError::DuplicateRecord(record),
+        _ => DatabaseError::Connection(error),
+    }
+}
+
+/// PostgreSQL 存储
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Postgres
+    }
+
+    fn postgres_pool(&self) -> Option<&PgPool> {
+        Some(&self.pool)
+    }
+
+    async fn health_check(&self) -> Result<(), DatabaseError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    async fn list_devices(&self) -> Result<Vec<Device>, DatabaseError> {
+        let rows: Vec<DeviceRow> = sqlx::query_as(&format!("SELECT {} FROM devices ORDER BY created_at DESC, id DESC", DEVICE_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(Device::from).collect())
+    }
+
+    async fn get_device(&self, id: &DeviceId) -> Result<Option<Device>, DatabaseError> {
+        let row: Option<DeviceRow> = sqlx::query_as(&format!("SELECT {} FROM devices WHERE id = $1", DEVICE_COLUMNS))
+            .bind(id.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(Device::from))
+    }
+
+    async fn save_device(&self, device: &Device) -> Result<(), DatabaseError> {
+        bind_device(sqlx::query(UPSERT_DEVICE), device)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| duplicate_or(e, format!("device serial number or mac address of {}", device.id)))?;
+        Ok(())
+    }
+
+    async fn update_device_status(&self, id: &DeviceId, status: DeviceStatus) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(UPDATE_DEVICE_STATUS)
+            .bind(id.as_str())
+            .bind(status.as_str())
+            .bind(status == DeviceStatus::Online)
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_device(&self, id: &DeviceId) -> Result<bool, DatabaseError> {
+        let result = sqlx::query(DELETE_DEVICE).bind(id.as_str()).execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_user(&self, id: &UserId) -> Result<Option<User>, DatabaseError> {
+        let row: Option<UserRow> = sqlx::query_as(
+            "SELECT id::text AS id, username, email, password_hash, role FROM users WHERE id::text = $1 AND is_active = true",
+        )
+        .bind(id.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(User::from))
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, DatabaseError> {
+        let row: Option<UserRow> = sqlx::query_as(
+            "SELECT id::text AS id, username, email, password_hash, role FROM users WHERE username = $1 AND is_active = true",
+        )
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(User::from))
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), DatabaseError> {
+        sqlx::query("INSERT INTO users (id, username, email, password_hash, role) VALUES ($1::uuid, $2, $3, $4, $5)")
+            .bind(&user.id)
+            .bind(&user.username)
+            .bind(&user.email)
+            .bind(&user.password_hash)
+            .bind(role_str(&user.role))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| duplicate_or(e, format!("user {}", user.username)))?;
+        Ok(())
+    }
+
+    async fn create_session(&self, session: &Session) -> Result<(), DatabaseError> {
+        bind_session(sqlx::query(INSERT_SESSION), session)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| duplicate_or(e, format!("session {}", session.id)))?;
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &SessionId) -> Result<Option<Session>, DatabaseError> {
+        let row: Option<SessionRow> = sqlx::query_as(&format!(
+            "SELECT {}, array_to_json(summary_tags)::text AS summary_tags FROM sessions WHERE id = $1",
+            SESSION_COLUMNS
+        ))
+        .bind(id.as_str())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Session::from))
+    }
+
+    async fn update_session(&self, session: &Session) -> Result<bool, DatabaseError> {
+        let result = bind_session_update(sqlx::query(UPDATE_SESSION), session)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_device_sessions(&self, device_id: &DeviceId, limit: i64) -> Result<Vec<Session>, DatabaseError> {
+        let rows: Vec<SessionRow> = sqlx::query_as(&format!(
+            "SELECT {}, array_to_json(summary_tags)::text AS summary_tags FROM sessions
+             WHERE device_id = $1 ORDER BY start_time DESC LIMIT $2",
+            SESSION_COLUMNS
+        ))
+        .bind(device_id.as_str())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Session::from).collect())
+    }
+
+    async fn list_sessions(&self, filter: &SessionFilter, limit: i64, offset: i64) -> Result<(Vec<Session>, u64), DatabaseError> {
+        let (conditions, params) = session_filter_sql(filter);
+        let (total,): (i64,) = bind_session_filter(sqlx::query_as(&format!("SELECT COUNT(*) FROM sessions{}", conditions)), filter)
+            .fetch_one(&self.pool)
+            .await?;
+        let rows: Vec<SessionRow> = bind_session_filter(
+            sqlx::query_as(&format!(
+                "SELECT {}, array_to_json(summary_tags)::text AS summary_tags FROM sessions{} ORDER BY start_time DESC LIMIT ${} OFFSET ${}",
+                SESSION_COLUMNS,
+                conditions,
+                params + 1,
+                params + 2
+            )),
+            filter,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((rows.into_iter().map(Session::from).collect(), total as u64))
+    }
+
+    async fn timeout_sessions(&self, started_before: DateTime<Utc>) -> Result<u64, DatabaseError> {
+        let result = sqlx::query(TIMEOUT_SESSIONS).bind(started_before).bind(Utc::now()).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+}
+
+// 两种后端共用的参数绑定（查询参数顺序见对应 SQL）
+fn bind_device<'q, DB: sqlx::Database>(
+    query: sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>,
+    device: &'q Device,
+) -> sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>
+where
+    &'q str: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<&'q str>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    String: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    i32: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    bool: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    DateTime<Utc>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    query
+        .bind(device.id.as_str())
+        .bind(device.name.as_str())
+        .bind(device.device_type.to_string())
+        .bind(device.status.as_str())
+        .bind(Some(device.location.as_str()).filter(|location| !location.is_empty()))
+        .bind(Some(device.firmware_version.as_str()).filter(|version| !version.is_empty()))
+        .bind(device.battery_level)
+        .bind(device.volume)
+        .bind(device.last_seen)
+        .bind(device.is_online)
+        .bind(Some(device.owner.as_str()).filter(|owner| !owner.is_empty()))
+        .bind(device.echokit_server_url.as_deref().unwrap_or_default())
+        .bind(device.serial_number.as_deref())
+        .bind(device.mac_address.as_deref())
+}
+
+fn bind_session<'q, DB: sqlx::Database>(
+    query: sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>,
+    session: &'q Session,
+) -> sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>
+where
+    &'q str: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<&'q str>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<i32>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    DateTime<Utc>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<DateTime<Utc>>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    query
+        .bind(session.id.as_str())
+        .bind(session.device_id.as_str())
+        .bind(session.user_id.as_deref())
+        .bind(session_status_str(&session.status))
+        .bind(session.start_time)
+        .bind(session.end_time)
+        .bind(session.duration)
+        .bind(session.transcription.as_deref())
+        .bind(session.response.as_deref())
+        .bind(session.language.as_deref())
+}
+
+fn bind_session_update<'q, DB: sqlx::Database>(
+    query: sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>,
+    session: &'q Session,
+) -> sqlx::query::Query<'q, DB, <DB as sqlx::database::HasArguments<'q>>::Arguments>
+where
+    &'q str: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<&'q str>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<i32>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    Option<DateTime<Utc>>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    query
+        .bind(session.id.as_str())
+        .bind(session_status_str(&session.status))
+        .bind(session.end_time)
+        .bind(session.duration)
+        .bind(session.transcription.as_deref())
+        .bind(session.response.as_deref())
+}
+
+// 会话过滤条件的 WHERE 子句，返回 (子句, 参数个数)；参数按 bind_session_filter 的顺序编号
+fn session_filter_sql(filter: &SessionFilter) -> (String, usize) {
+    let conditions: Vec<String> = [
+        filter.device_id.is_some().then_some("device_id = "),
+        filter.status.is_some().then_some("status = "),
+        filter.start_date.is_some().then_some("start_time >= "),
+        filter.end_date.is_some().then_some("start_time <= "),
+    ]
+    .into_iter()
+    .flatten()
+    .enumerate()
+    .map(|(i, condition)| format!("{}${}", condition, i + 1))
+    .collect();
+
+    if conditions.is_empty() {
+        (String::new(), 0)
+    } else {
+        (format!(" WHERE {}", conditions.join(" AND ")), conditions.len())
+    }
+}
+
+fn bind_session_filter<'q, DB: sqlx::Database, O>(
+    mut query: sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments>,
+    filter: &'q SessionFilter,
+) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments>
+where
+    &'q str: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    DateTime<Utc>: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    if let Some(device_id) = &filter.device_id {
+        query = query.bind(device_id.as_str());
+    }
+    if let Some(status) = &filter.status {
+        query = query.bind(session_status_str(status));
+    }
+    if let Some(start_date) = filter.start_date {
+        query = query.bind(start_date);
+    }
+    if let Some(end_date) = filter.end_date {
+        query = query.bind(end_date);
+    }
+    query
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+    use sqlx::Executor;
+
+    use super::*;
+
+    /// SQLite 存储，连接时创建数据库文件和表结构
+    #[derive(Clone)]
+    pub struct SqliteStorage {
+        pool: SqlitePool,
+    }
+
+    impl SqliteStorage {
+        pub async fn connect(config: &DatabaseConfig) -> Result<Self, DatabaseError> {
+            let options = SqliteConnectOptions::from_str(&config.url)?
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal)
+                .foreign_keys(true);
+            // 内存数据库每个连接各自独立，只能使用一个连接
+            let max_connections = if config.url.contains(":memory:") { 1 } else { config.max_connections };
+            let pool = SqlitePoolOptions::new()
+                .max_connections(max_connections)
+                .acquire_timeout(Duration::from_secs(config.pool.acquire_timeout_seconds))
+                .connect_with(options)
+                .await?;
+
+            pool.execute(include_str!("../../database/sqlite/schema.sql")).await?;
+            Ok(Self { pool })
+        }
+
+        pub fn pool(&self) -> &SqlitePool {
+            &self.pool
+        }
+    }
+
+    #[async_trait]
+    impl Storage for SqliteStorage {
+        fn backend(&self) -> StorageBackend {
+            StorageBackend::Sqlite
+        }
+
+        async fn health_check(&self) -> Result<(), DatabaseError> {
+            sqlx::query("SELECT 1").execute(&self.pool).await?;
+            Ok(())
+        }
+
+        async fn close(&self) {
+            self.pool.close().await;
+        }
+
+        async fn list_devices(&self) -> Result<Vec<Device>, DatabaseError> {
+            let rows: Vec<DeviceRow> = sqlx::query_as(&format!("SELECT {} FROM devices ORDER BY created_at DESC, id DESC", DEVICE_COLUMNS))
+                .fetch_all(&self.pool)
+                .await?;
+            Ok(rows.into_iter().map(Device::from).collect())
+        }
+
+        async fn get_device(&self, id: &DeviceId) -> Result<Option<Device>, DatabaseError> {
+            let row: Option<DeviceRow> = sqlx::query_as(&format!("SELECT {} FROM devices WHERE id = $1", DEVICE_COLUMNS))
+                .bind(id.as_str())
+                .fetch_optional(&self.pool)
+                .await?;
+            Ok(row.map(Device::from))
+        }
+
+        async fn save_device(&self, device: &Device) -> Result<(), DatabaseError> {
+            bind_device(sqlx::query(UPSERT_DEVICE), device)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| duplicate_or(e, format!("device serial number or mac address of {}", device.id)))?;
+            Ok(())
+        }
+
+        async fn update_device_status(&self, id: &DeviceId, status: DeviceStatus) -> Result<bool, DatabaseError> {
+            let result = sqlx::query(UPDATE_DEVICE_STATUS)
+                .bind(id.as_str())
+                .bind(status.as_str())
+                .bind(status == DeviceStatus::Online)
+                .bind(Utc::now())
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn delete_device(&self, id: &DeviceId) -> Result<bool, DatabaseError> {
+            let result = sqlx::query(DELETE_DEVICE).bind(id.as_str()).execute(&self.pool).await?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn get_user(&self, id: &UserId) -> Result<Option<User>, DatabaseError> {
+            let row: Option<UserRow> = sqlx::query_as(
+                "SELECT id, username, email, password_hash, role FROM users WHERE id = $1 AND is_active = 1",
+            )
+            .bind(id.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+            Ok(row.map(User::from))
+        }
+
+        async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, DatabaseError> {
+            let row: Option<UserRow> = sqlx::query_as(
+                "SELECT id, username, email, password_hash, role FROM users WHERE username = $1 AND is_active = 1",
+            )
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?;
+            Ok(row.map(User::from))
+        }
+
+        async fn create_user(&self, user: &User) -> Result<(), DatabaseError> {
+            sqlx::query("INSERT INTO users (id, username, email, password_hash, role) VALUES ($1, $2, $3, $4, $5)")
+                .bind(&user.id)
+                .bind(&user.username)
+                .bind(&user.email)
+                .bind(&user.password_hash)
+                .bind(role_str(&user.role))
+                .execute(&self.pool)
+                .await
+                .map_err(|e| duplicate_or(e, format!("user {}", user.username)))?;
+            Ok(())
+        }
+
+        async fn create_session(&self, session: &Session) -> Result<(), DatabaseError> {
+            bind_session(sqlx::query(INSERT_SESSION), session)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| duplicate_or(e, format!("session {}", session.id)))?;
+            Ok(())
+        }
+
+        async fn get_session(&self, id: &SessionId) -> Result<Option<Session>, DatabaseError> {
+            let row: Option<SessionRow> = sqlx::query_as(&format!(
+                "SELECT {}, summary_tags FROM sessions WHERE id = $1",
+                SESSION_COLUMNS
+            ))
+            .bind(id.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+            Ok(row.map(Session::from))
+        }
+
+        async fn update_session(&self, session: &Session) -> Result<bool, DatabaseError> {
+            let result = bind_session_update(sqlx::query(UPDATE_SESSION), session)
+                .execute(&self.pool)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        }
+
+        async fn list_device_sessions(&self, device_id: &DeviceId, limit: i64) -> Result<Vec<Session>, DatabaseError> {
+            let rows: Vec<SessionRow> = sqlx::query_as(&format!(
+                "SELECT {}, summary_tags FROM sessions WHERE device_id = $1 ORDER BY start_time DESC LIMIT $2",
+                SESSION_COLUMNS
+            ))
+            .bind(device_id.as_str())
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows.into_iter().map(Session::from).collect())
+        }
+
+        async fn list_sessions(&self, filter: &SessionFilter, limit: i64, offset: i64) -> Result<(Vec<Session>, u64), DatabaseError> {
+            let (conditions, params) = session_filter_sql(filter);
+            let (total,): (i64,) = bind_session_filter(sqlx::query_as(&format!("SELECT COUNT(*) FROM sessions{}", conditions)), filter)
+                .fetch_one(&self.pool)
+                .await?;
+            let rows: Vec<SessionRow> = bind_session_filter(
+                sqlx::query_as(&format!(
+                    "SELECT {}, summary_tags FROM sessions{} ORDER BY start_time DESC LIMIT ${} OFFSET ${}",
+                    SESSION_COLUMNS,
+                    conditions,
+                    params + 1,
+                    params + 2
+                )),
+                filter,
+            )
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
+            Ok((rows.into_iter().map(Session::from).collect(), total as u64))
+        }
+
+        async fn timeout_sessions(&self, started_before: DateTime<Utc>) -> Result<u64, DatabaseError> {
+            let result = sqlx::query(TIMEOUT_SESSIONS).bind(started_before).bind(Utc::now()).execute(&self.pool).await?;
+            Ok(result.rows_affected())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_from_url_scheme() {
+        assert_eq!(StorageBackend::from_url("postgres://u:p@db:5432/echo").unwrap(), StorageBackend::Postgres);
+        assert_eq!(StorageBackend::from_url("postgresql://db/echo").unwrap(), StorageBackend::Postgres);
+        assert_eq!(StorageBackend::from_url("sqlite://echo.db").unwrap(), StorageBackend::Sqlite);
+        assert_eq!(StorageBackend::from_url("sqlite::memory:").unwrap(), StorageBackend::Sqlite);
+        assert!(matches!(StorageBackend::from_url("mysql://db/echo"), Err(DatabaseError::InvalidInput(_))));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_sqlite_round_trip() {
+        let mut config = crate::AppConfig::default().database;
+        config.url = "sqlite::memory:".to_string();
+        let storage = connect_storage(&config).await.unwrap();
+        assert_eq!(storage.backend(), StorageBackend::Sqlite);
+        // 建表脚本写入默认管理员
+        let admin = storage.get_user_by_username("admin").await.unwrap().unwrap();
+        assert_eq!(admin.role, UserRole::Admin);
+
+        let device = Device {
+            id: "dev-1".to_string(),
+            name: "Kitchen".to_string(),
+            device_type: DeviceType::Speaker,
+            status: DeviceStatus::Offline,
+            location: "kitchen".to_string(),
+            firmware_version: "1.0.0".to_string(),
+            battery_level: 80,
+            volume: 40,
+            last_seen: Utc::now(),
+            is_online: false,
+            owner: String::new(),
+            echokit_server_url: None,
+            serial_number: Some("SN-1".to_string()),
+            mac_address: None,
+            version: 1,
+            current_session_id: None,
+        };
+        storage.save_device(&device).await.unwrap();
+        assert!(storage.update_device_status(&DeviceId::new("dev-1"), DeviceStatus::Online).await.unwrap());
+        let saved = storage.get_device(&DeviceId::new("dev-1")).await.unwrap().unwrap();
+        assert_eq!((saved.status, saved.is_online, saved.volume), (DeviceStatus::Online, true, 40));
+
+        let user = User {
+            id: "u-1".to_string(),
+            username: "alice".to_string(),
+            email: "alice@echo.system".to_string(),
+            password_hash: "hash".to_string(),
+            role: UserRole::User,
+        };
+        storage.create_user(&user).await.unwrap();
+        assert!(matches!(storage.create_user(&user).await, Err(DatabaseError::DuplicateRecord(_))));
+        let saved = storage.get_user_by_username("alice").await.unwrap().unwrap();
+        assert_eq!((saved.id.as_str(), saved.role), ("u-1", UserRole::User));
+
+        let mut session = Session {
+            id: "s-1".to_string(),
+            device_id: "dev-1".to_string(),
+            user_id: Some("u-1".to_string()),
+            start_time: Utc::now(),
+            end_time: None,
+            duration: None,
+            transcription: None,
+            response: None,
+            status: SessionStatus::Active,
+            summary: None,
+            tags: Vec::new(),
+            language: Some("en-US".to_string()),
+        };
+        storage.create_session(&session).await.unwrap();
+        assert!(matches!(storage.create_session(&session).await, Err(DatabaseError::DuplicateRecord(_))));
+
+        session.status = SessionStatus::Completed;
+        session.transcription = Some("hello".to_string());
+        assert!(storage.update_session(&session).await.unwrap());
+        let sessions = storage.list_device_sessions(&DeviceId::new("dev-1"), 10).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].status, SessionStatus::Completed);
+        assert_eq!(sessions[0].transcription.as_deref(), Some("hello"));
+        assert_eq!(sessions[0].language.as_deref(), Some("en-US"));
+
+        let stale = Session {
+            id: "s-2".to_string(),
+            status: SessionStatus::Active,
+            start_time: Utc::now() - chrono::Duration::hours(2),
+            ..session.clone()
+        };
+        storage.create_session(&stale).await.unwrap();
+        assert_eq!(storage.timeout_sessions(Utc::now() - chrono::Duration::hours(1)).await.unwrap(), 1);
+        let filter = SessionFilter {
+            device_id: Some("dev-1".to_string()),
+            status: Some(SessionStatus::Timeout),
+            ..Default::default()
+        };
+        let (sessions, total) = storage.list_sessions(&filter, 10, 0).await.unwrap();
+        assert_eq!((sessions.len(), total), (1, 1));
+        assert_eq!(sessions[0].id, "s-2");
+        let (sessions, total) = storage.list_sessions(&SessionFilter::default(), 1, 1).await.unwrap();
+        assert_eq!((sessions.len(), total), (1, 2));
+
+        // 删除设备时一并删除会话
+        assert!(storage.delete_device(&DeviceId::new("dev-1")).await.unwrap());
+        assert!(storage.get_session(&SessionId::new("s-1")).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,488 @@
+//! 协议一致性测试套件（`testkit` 特性）
+//!
+//! 供设备固件、Web UI 和其他 SDK 校验自己的协议实现：
+//!
+//! - 黄金样例：Bridge 下发的每种事件（`EchoKitEvent`）和客户端命令（`ClientCommand`）的
+//!   线上字节，以及带未知字段、未知事件的样例。非 Rust 实现可用 `write_fixtures`
+//!   （或 `cargo run -p echo-shared --features testkit --example export-protocol-fixtures -- <目录>`）
+//!   导出为文件，在自己的测试中逐个解码比对
+//! - 属性测试：随机生成事件和命令，检查往返编解码一致、忽略未知字段、拒绝未知事件
+//!
+//! Rust 实现（或通过 FFI 绑定的实现）实现 `ClientCodec` 后调用 `check_conformance` 即可。
+//!
+//! 未知字段只在按字段名编码（MessagePack map、JSON）时可以忽略；Bridge 默认按位置编码
+//! （`{事件名: [字段...]}`），给已有事件追加字段属于不兼容变化，需要递增 `PROTOCOL_VERSION`。
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use rmpv::Value;
+
+use crate::echokit::EchoKitEvent;
+use crate::protocol::{ClientCommand, PROTOCOL_VERSION};
+
+/// 一条事件样例
+#[derive(Debug, Clone)]
+pub struct EventFixture {
+    pub name: &'static str,
+    /// Bridge 下发的 MessagePack 帧
+    pub messagepack: &'static [u8],
+    /// 帧应解码成的事件
+    pub event: EchoKitEvent,
+}
+
+/// 一条客户端命令样例
+#[derive(Debug, Clone)]
+pub struct CommandFixture {
+    pub name: &'static str,
+    pub json: &'static str,
+    pub messagepack: &'static [u8],
+    pub command: ClientCommand,
+}
+
+/// 一条无法识别的事件帧，实现应返回错误并跳过，而不是崩溃或断开连接
+#[derive(Debug, Clone)]
+pub struct UnknownEventFixture {
+    pub name: &'static str,
+    pub messagepack: &'static [u8],
+}
+
+/// Bridge 下发的每种事件的线上字节
+pub fn event_fixtures() -> Vec<EventFixture> {
+    let fixture = |name, messagepack, event| EventFixture { name, messagepack, event };
+    vec![
+        fixture("hello_start", b"\xaaHelloStart", EchoKitEvent::HelloStart),
+        fixture(
+            "hello_chunk",
+            b"\x81\xaaHelloChunk\x91\xc4\x04\x00\x01\xfe\xff",
+            EchoKitEvent::HelloChunk { data: vec![0x00, 0x01, 0xfe, 0xff] },
+        ),
+        fixture("hello_end", b"\xa8HelloEnd", EchoKitEvent::HelloEnd),
+        fixture("bg_start", b"\xa7BGStart", EchoKitEvent::BGStart),
+        fixture(
+            "bg_chunk",
+            b"\x81\xa7BGChunk\x91\xc4\x02\x10\x20",
+            EchoKitEvent::BGChunk { data: vec![0x10, 0x20] },
+        ),
+        fixture("bg_end", b"\xa5BGEnd", EchoKitEvent::BGEnd),
+        fixture(
+            "asr",
+            b"\x81\xa3ASR\x91\xa6\xe4\xbd\xa0\xe5\xa5\xbd",
+            EchoKitEvent::ASR { text: "你好".to_string() },
+        ),
+        fixture("action", b"\x81\xa6Action\x91\xa4wake", EchoKitEvent::Action { action: "wake".to_string() }),
+        fixture(
+            "start_audio",
+            b"\x81\xaaStartAudio\x91\xa9\xe4\xbb\x8a\xe5\xa4\xa9\xe6\x99\xb4",
+            EchoKitEvent::StartAudio { text: "今天晴".to_string() },
+        ),
+        fixture(
+            "audio_chunk",
+            b"\x81\xaaAudioChunk\x91\xc4\x04\x01\x00\xff\x7f",
+            EchoKitEvent::AudioChunk { data: vec![0x01, 0x00, 0xff, 0x7f] },
+        ),
+        fixture("end_audio", b"\xa8EndAudio", EchoKitEvent::EndAudio),
+        fixture("start_video", b"\xaaStartVideo", EchoKitEvent::StartVideo),
+        fixture("end_video", b"\xa8EndVideo", EchoKitEvent::EndVideo),
+        fixture("end_response", b"\xabEndResponse", EchoKitEvent::EndResponse),
+    ]
+}
+
+/// 与黄金样例编码方式不同、但实现必须接受的事件帧
+pub fn tolerant_event_fixtures() -> Vec<EventFixture> {
+    vec![
+        EventFixture {
+            name: "asr_named_with_unknown_field",
+            // {"ASR": {"text": "你好", "lang": "zh"}}
+            messagepack: b"\x81\xa3ASR\x82\xa4text\xa6\xe4\xbd\xa0\xe5\xa5\xbd\xa4lang\xa2zh",
+            event: EchoKitEvent::ASR { text: "你好".to_string() },
+        },
+        EventFixture {
+            name: "audio_chunk_named_with_unknown_field",
+            // {"AudioChunk": {"data": bin[1, 2], "seq": 7}}
+            messagepack: b"\x81\xaaAudioChunk\x82\xa4data\xc4\x02\x01\x02\xa3seq\x07",
+            event: EchoKitEvent::AudioChunk { data: vec![0x01, 0x02] },
+        },
+        EventFixture {
+            name: "audio_chunk_integer_array",
+            // 早期实现把音频编码为整数数组而不是 bin
+            messagepack: b"\x81\xaaAudioChunk\x91\x92\x01\x02",
+            event: EchoKitEvent::AudioChunk { data: vec![0x01, 0x02] },
+        },
+    ]
+}
+
+/// 实现必须拒绝（并跳过）的未知事件帧
+pub fn unknown_event_fixtures() -> Vec<UnknownEventFixture> {
+    vec![
+        UnknownEventFixture { name: "unknown_unit_event", messagepack: b"\xa9NewSignal" },
+        // {"Volume": [50]}
+        UnknownEventFixture { name: "unknown_event_with_fields", messagepack: b"\x81\xa6Volume\x91\x32" },
+    ]
+}
+
+/// 每种客户端命令的 JSON 和 MessagePack 编码
+pub fn command_fixtures() -> Vec<CommandFixture> {
+    vec![
+        CommandFixture {
+            name: "start_record",
+            json: r#"{"event":"StartRecord"}"#,
+            messagepack: b"\x81\xa5event\xabStartRecord",
+            command: ClientCommand::StartRecord,
+        },
+        CommandFixture {
+            name: "start_chat",
+            json: r#"{"event":"StartChat"}"#,
+            messagepack: b"\x81\xa5event\xa9StartChat",
+            command: ClientCommand::StartChat { language: None },
+        },
+        CommandFixture {
+            name: "submit",
+            json: r#"{"event":"Submit"}"#,
+            messagepack: b"\x81\xa5event\xa6Submit",
+            command: ClientCommand::Submit,
+        },
+        CommandFixture {
+            name: "text",
+            json: r#"{"event":"Text","input":"你好"}"#,
+            messagepack: b"\x82\xa5event\xa4Text\xa5input\xa6\xe4\xbd\xa0\xe5\xa5\xbd",
+            command: ClientCommand::Text { input: "你好".to_string() },
+        },
+        CommandFixture {
+            name: "start_chat_language",
+            json: r#"{"event":"StartChat","language":"en"}"#,
+            messagepack: b"\x82\xa5event\xa9StartChat\xa8language\xa2en",
+            command: ClientCommand::StartChat { language: Some("en".to_string()) },
+        },
+    ]
+}
+
+/// 把所有样例写入目录，附带描述期望结果的 manifest.json
+///
+/// 事件写入 `events/<name>.msgpack`，命令写入 `commands/<name>.json` 和 `commands/<name>.msgpack`。
+/// manifest 中期望的事件和命令按 serde JSON 形式给出（音频数据为整数数组）。
+pub fn write_fixtures(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir.join("events"))?;
+    fs::create_dir_all(dir.join("commands"))?;
+
+    let mut events = Vec::new();
+    for (kind, fixtures) in [("golden", event_fixtures()), ("tolerant", tolerant_event_fixtures())] {
+        for fixture in fixtures {
+            let file = format!("events/{}.msgpack", fixture.name);
+            fs::write(dir.join(&file), fixture.messagepack)?;
+            events.push(serde_json::json!({
+                "name": fixture.name,
+                "kind": kind,
+                "file": file,
+                "expected": fixture.event,
+            }));
+        }
+    }
+
+    let mut unknown_events = Vec::new();
+    for fixture in unknown_event_fixtures() {
+        let file = format!("events/{}.msgpack", fixture.name);
+        fs::write(dir.join(&file), fixture.messagepack)?;
+        unknown_events.push(serde_json::json!({ "name": fixture.name, "file": file }));
+    }
+
+    let mut commands = Vec::new();
+    for fixture in command_fixtures() {
+        let json_file = format!("commands/{}.json", fixture.name);
+        let messagepack_file = format!("commands/{}.msgpack", fixture.name);
+        fs::write(dir.join(&json_file), fixture.json)?;
+        fs::write(dir.join(&messagepack_file), fixture.messagepack)?;
+        commands.push(serde_json::json!({
+            "name": fixture.name,
+            "json_file": json_file,
+            "messagepack_file": messagepack_file,
+            "expected": fixture.command,
+        }));
+    }
+
+    let manifest = serde_json::json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "events": events,
+        "unknown_events": unknown_events,
+        "commands": commands,
+    });
+    fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)
+}
+
+/// 被测的客户端协议实现
+pub trait ClientCodec {
+    /// 解码 Bridge 下发的一帧 MessagePack 事件
+    fn decode_event(&self, frame: &[u8]) -> Result<EchoKitEvent, String>;
+
+    /// 把命令编码为发送给 Bridge 的 JSON 文本消息
+    fn encode_command(&self, command: &ClientCommand) -> Result<String, String>;
+}
+
+/// 本仓库自身的编解码，作为参照实现
+pub struct ReferenceCodec;
+
+impl ClientCodec for ReferenceCodec {
+    fn decode_event(&self, frame: &[u8]) -> Result<EchoKitEvent, String> {
+        EchoKitEvent::from_messagepack(frame).map_err(|e| e.to_string())
+    }
+
+    fn encode_command(&self, command: &ClientCommand) -> Result<String, String> {
+        command.to_json().map_err(|e| e.to_string())
+    }
+}
+
+/// 一致性检查失败
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{case}: {reason}")]
+pub struct ConformanceFailure {
+    /// 失败的样例名或属性名
+    pub case: String,
+    pub reason: String,
+}
+
+impl ConformanceFailure {
+    fn new(case: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self { case: case.into(), reason: reason.into() }
+    }
+}
+
+/// 运行全部样例和属性测试，每个属性随机生成 `cases` 组输入
+pub fn check_conformance(codec: &impl ClientCodec, cases: u32) -> Result<(), ConformanceFailure> {
+    check_fixtures(codec)?;
+    check_properties(codec, cases)
+}
+
+/// 逐个检查黄金样例
+pub fn check_fixtures(codec: &impl ClientCodec) -> Result<(), ConformanceFailure> {
+    for fixture in event_fixtures().into_iter().chain(tolerant_event_fixtures()) {
+        let decoded = codec
+            .decode_event(fixture.messagepack)
+            .map_err(|e| ConformanceFailure::new(fixture.name, format!("failed to decode: {}", e)))?;
+        if decoded != fixture.event {
+            return Err(ConformanceFailure::new(
+                fixture.name,
+                format!("decoded {:?}, expected {:?}", decoded, fixture.event),
+            ));
+        }
+    }
+
+    for fixture in unknown_event_fixtures() {
+        if let Ok(decoded) = codec.decode_event(fixture.messagepack) {
+            return Err(ConformanceFailure::new(fixture.name, format!("unknown event decoded as {:?}", decoded)));
+        }
+    }
+
+    for fixture in command_fixtures() {
+        let json = codec
+            .encode_command(&fixture.command)
+            .map_err(|e| ConformanceFailure::new(fixture.name, format!("failed to encode: {}", e)))?;
+        check_command_json(&fixture.command, &json).map_err(|reason| ConformanceFailure::new(fixture.name, reason))?;
+    }
+    Ok(())
+}
+
+/// 属性测试：往返编解码、忽略未知字段、拒绝未知事件
+pub fn check_properties(codec: &impl ClientCodec, cases: u32) -> Result<(), ConformanceFailure> {
+    let run = |property: &str, test: &dyn Fn(&mut TestRunner) -> Result<(), String>| {
+        let mut runner = TestRunner::new(Config { cases, failure_persistence: None, ..Config::default() });
+        test(&mut runner).map_err(|reason| ConformanceFailure::new(property, reason))
+    };
+
+    run("event_round_trip", &|runner| {
+        runner
+            .run(&arb_echokit_event(), |event| {
+                let frame = event.to_messagepack().map_err(|e| TestCaseError::fail(e.to_string()))?;
+                let decoded = codec.decode_event(&frame).map_err(TestCaseError::fail)?;
+                prop_assert_eq!(decoded, event);
+                Ok(())
+            })
+            .map_err(|e| e.to_string())
+    })?;
+
+    run("command_round_trip", &|runner| {
+        runner
+            .run(&arb_client_command(), |command| {
+                let json = codec.encode_command(&command).map_err(TestCaseError::fail)?;
+                check_command_json(&command, &json).map_err(TestCaseError::fail)
+            })
+            .map_err(|e| e.to_string())
+    })?;
+
+    run("unknown_field_tolerance", &|runner| {
+        runner
+            .run(&(arb_echokit_event(), arb_unknown_fields()), |(event, extra)| {
+                let Some(frame) = encode_named_with_fields(&event, extra).map_err(TestCaseError::fail)? else {
+                    return Ok(());
+                };
+                let decoded = codec.decode_event(&frame).map_err(TestCaseError::fail)?;
+                prop_assert_eq!(decoded, event);
+                Ok(())
+            })
+            .map_err(|e| e.to_string())
+    })?;
+
+    run("unknown_event_rejected", &|runner| {
+        runner
+            .run(&arb_unknown_event_frame(), |frame| {
+                if let Ok(decoded) = codec.decode_event(&frame) {
+                    return Err(TestCaseError::fail(format!("unknown event decoded as {:?}", decoded)));
+                }
+                Ok(())
+            })
+            .map_err(|e| e.to_string())
+    })
+}
+
+// 实现编码的 JSON 不要求与参照实现逐字节相同，只要 Bridge 能解析回同一命令
+fn check_command_json(command: &ClientCommand, json: &str) -> Result<(), String> {
+    match ClientCommand::from_json(json) {
+        Ok(parsed) if parsed == *command => Ok(()),
+        Ok(parsed) => Err(format!("{} parsed as {:?}, expected {:?}", json, parsed, command)),
+        Err(e) => Err(format!("{} is not a valid command: {}", json, e)),
+    }
+}
+
+/// 任意 `EchoKitEvent`
+pub fn arb_echokit_event() -> impl Strategy<Value = EchoKitEvent> {
+    let audio = || proptest::collection::vec(any::<u8>(), 0..640);
+    prop_oneof![
+        proptest::sample::select(vec![
+            EchoKitEvent::HelloStart,
+            EchoKitEvent::HelloEnd,
+            EchoKitEvent::BGStart,
+            EchoKitEvent::BGEnd,
+            EchoKitEvent::EndAudio,
+            EchoKitEvent::StartVideo,
+            EchoKitEvent::EndVideo,
+            EchoKitEvent::EndResponse,
+        ]),
+        audio().prop_map(|data| EchoKitEvent::HelloChunk { data }),
+        audio().prop_map(|data| EchoKitEvent::BGChunk { data }),
+        audio().prop_map(|data| EchoKitEvent::AudioChunk { data }),
+        any::<String>().prop_map(|text| EchoKitEvent::ASR { text }),
+        any::<String>().prop_map(|action| EchoKitEvent::Action { action }),
+        any::<String>().prop_map(|text| EchoKitEvent::StartAudio { text }),
+    ]
+}
+
+/// 任意 `ClientCommand`
+pub fn arb_client_command() -> impl Strategy<Value = ClientCommand> {
+    prop_oneof![
+        Just(ClientCommand::StartRecord),
+        proptest::option::of("[a-z]{2}(-[A-Z]{2})?").prop_map(|language| ClientCommand::StartChat { language }),
+        Just(ClientCommand::Submit),
+        any::<String>().prop_map(|input| ClientCommand::Text { input }),
+    ]
+}
+
+// 不与现有字段重名的未知字段
+fn arb_unknown_fields() -> impl Strategy<Value = Vec<(String, Value)>> {
+    let value = prop_oneof![
+        Just(Value::Nil),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".{0,16}".prop_map(Value::from),
+        proptest::collection::vec(any::<u8>(), 0..16).prop_map(Value::Binary),
+        proptest::collection::vec(any::<u16>().prop_map(Value::from), 0..4).prop_map(Value::Array),
+    ];
+    proptest::collection::vec(("x_[a-z_]{1,12}", value), 1..4)
+}
+
+// 不属于 `EchoKitEvent` 的事件名，编码为字符串或 {事件名: [字段...]}
+fn arb_unknown_event_frame() -> impl Strategy<Value = Vec<u8>> {
+    let name = "[A-Z][A-Za-z]{1,15}".prop_filter("known event", |name| !is_known_event(name));
+    (name, proptest::option::of(proptest::collection::vec(any::<u32>(), 0..3))).prop_map(|(name, fields)| {
+        let value = match fields {
+            None => Value::from(name),
+            Some(fields) => Value::Map(vec![(
+                Value::from(name),
+                Value::Array(fields.into_iter().map(Value::from).collect()),
+            )]),
+        };
+        let mut frame = Vec::new();
+        rmpv::encode::write_value(&mut frame, &value).expect("writing to a Vec cannot fail");
+        frame
+    })
+}
+
+fn is_known_event(name: &str) -> bool {
+    event_fixtures().iter().any(|fixture| fixture.event.name() == name)
+}
+
+// 按字段名编码事件并追加未知字段；无字段的事件编码为字符串，无法携带字段，返回 None
+fn encode_named_with_fields(event: &EchoKitEvent, extra: Vec<(String, Value)>) -> Result<Option<Vec<u8>>, String> {
+    let named = rmp_serde::to_vec_named(event).map_err(|e| e.to_string())?;
+    let value = rmpv::decode::read_value(&mut named.as_slice()).map_err(|e| e.to_string())?;
+    let Value::Map(mut variant) = value else {
+        return Ok(None);
+    };
+    let Some((_, Value::Map(fields))) = variant.first_mut() else {
+        return Err(format!("unexpected named encoding for {}", event.name()));
+    };
+    fields.extend(extra.into_iter().map(|(key, value)| (Value::from(key), value)));
+
+    let mut frame = Vec::new();
+    rmpv::encode::write_value(&mut frame, &Value::Map(variant)).map_err(|e| e.to_string())?;
+    Ok(Some(frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_match_reference_encoding() {
+        // 样例字节与当前编码不一致说明线上格式变了，需要同时递增 PROTOCOL_VERSION
+        for fixture in event_fixtures() {
+            assert_eq!(fixture.event.to_messagepack().unwrap(), fixture.messagepack, "{}", fixture.name);
+        }
+        for fixture in command_fixtures() {
+            assert_eq!(fixture.command.to_json().unwrap(), fixture.json, "{}", fixture.name);
+            assert_eq!(fixture.command.to_messagepack().unwrap(), fixture.messagepack, "{}", fixture.name);
+        }
+    }
+
+    #[test]
+    fn test_reference_codec_conforms() {
+        check_conformance(&ReferenceCodec, 64).unwrap();
+    }
+
+    #[test]
+    fn test_nonconforming_codec_is_reported() {
+        // 把未知事件当作 EndResponse 处理的实现
+        struct Lenient;
+        impl ClientCodec for Lenient {
+            fn decode_event(&self, frame: &[u8]) -> Result<EchoKitEvent, String> {
+                Ok(ReferenceCodec.decode_event(frame).unwrap_or(EchoKitEvent::EndResponse))
+            }
+            fn encode_command(&self, command: &ClientCommand) -> Result<String, String> {
+                ReferenceCodec.encode_command(command)
+            }
+        }
+
+        let failure = check_fixtures(&Lenient).unwrap_err();
+        assert_eq!(failure.case, "unknown_unit_event");
+    }
+
+    #[test]
+    fn test_write_fixtures() {
+        let dir = std::env::temp_dir().join(format!("echo-testkit-{}", std::process::id()));
+        write_fixtures(&dir).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        assert_eq!(manifest["protocol_version"], PROTOCOL_VERSION);
+        assert_eq!(manifest["events"].as_array().unwrap().len(), 17);
+        assert_eq!(fs::read(dir.join("events/end_audio.msgpack")).unwrap(), b"\xa8EndAudio");
+        let command = |name: &str| {
+            manifest["commands"].as_array().unwrap().iter().find(|command| command["name"] == name).unwrap().clone()
+        };
+        assert_eq!(command("text")["expected"]["input"], "你好");
+        assert_eq!(command("start_chat_language")["expected"]["language"], "en");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
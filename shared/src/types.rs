@@ -1,5 +1,107 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use validator::{Validate, ValidationError, ValidationErrors};
+use crate::database::DevicePermission;
+use crate::utils::{MAC_ADDRESS_RE, SERIAL_NUMBER_RE};
+
+// 字符串 ID 的强类型包装：序列化、数据库编码与原始字符串一致，
+// 但不同类型的 ID 不能互相传参，避免 device_id/session_id 等参数顺序写反
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
+        #[serde(transparent)]
+        #[sqlx(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> String {
+                self.0
+            }
+        }
+
+        // 允许在仍接受 &str 的接口中直接传入 &ID
+        impl std::ops::Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        // 允许以 &str 查询以 ID 为键的 HashMap
+        impl std::borrow::Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+string_id! {
+    /// 设备 ID
+    DeviceId
+}
+
+string_id! {
+    /// 会话 ID（Bridge 生成，与数据库 sessions.id 一致）
+    SessionId
+}
+
+string_id! {
+    /// 用户 ID
+    UserId
+}
 
 // 设备相关类型
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -17,6 +119,18 @@ pub struct Device {
     pub owner: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub echokit_server_url: Option<String>,
+    /// 设备序列号（注册时填写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    /// 设备 MAC 地址（注册时填写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac_address: Option<String>,
+    /// 版本号，每次更新加一，用于更新时的冲突检测（ETag）
+    #[serde(default)]
+    pub version: i32,
+    /// 设备正在进行的会话，来自 Bridge 写入 Redis 的实时在线状态，不保存在数据库中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_session_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -26,16 +140,61 @@ pub enum DeviceType {
     Unknown,
 }
 
+// 设备状态
+//
+// API 中序列化为变体名（如 "Online"），数据库和 MQTT 中使用小写形式（如 "online"），
+// 反序列化两种形式都接受
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DeviceStatus {
+    #[serde(alias = "online")]
     Online,
+    #[serde(alias = "offline")]
     Offline,
+    #[serde(alias = "maintenance")]
     Maintenance,
+    #[serde(alias = "error")]
     Error,
+    /// 已注册、等待设备完成配对
+    #[serde(alias = "pending")]
     Pending,
+    #[serde(alias = "registration_expired")]
     RegistrationExpired,
 }
 
+impl DeviceStatus {
+    /// 数据库和 MQTT 中使用的小写形式
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceStatus::Online => "online",
+            DeviceStatus::Offline => "offline",
+            DeviceStatus::Maintenance => "maintenance",
+            DeviceStatus::Error => "error",
+            DeviceStatus::Pending => "pending",
+            DeviceStatus::RegistrationExpired => "registration_expired",
+        }
+    }
+}
+
+// 从数据库/MQTT 的状态字符串转换，未识别的状态（如 restarting）视为离线
+impl From<&str> for DeviceStatus {
+    fn from(status: &str) -> Self {
+        match status.to_ascii_lowercase().as_str() {
+            "online" => DeviceStatus::Online,
+            "maintenance" => DeviceStatus::Maintenance,
+            "error" => DeviceStatus::Error,
+            "pending" => DeviceStatus::Pending,
+            "registration_expired" | "registrationexpired" => DeviceStatus::RegistrationExpired,
+            _ => DeviceStatus::Offline,
+        }
+    }
+}
+
+impl From<DeviceStatus> for &'static str {
+    fn from(status: DeviceStatus) -> Self {
+        status.as_str()
+    }
+}
+
 impl std::fmt::Display for DeviceType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -47,14 +206,7 @@ impl std::fmt::Display for DeviceType {
 
 impl std::fmt::Display for DeviceStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            DeviceStatus::Online => write!(f, "online"),
-            DeviceStatus::Offline => write!(f, "offline"),
-            DeviceStatus::Maintenance => write!(f, "maintenance"),
-            DeviceStatus::Error => write!(f, "error"),
-            DeviceStatus::Pending => write!(f, "pending"),
-            DeviceStatus::RegistrationExpired => write!(f, "registration_expired"),
-        }
+        f.write_str(self.as_str())
     }
 }
 
@@ -78,16 +230,30 @@ pub struct DeviceRegistration {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_device_identifiers"))]
 pub struct DeviceRegistrationRequest {
     pub device_id: Option<String>, // Changed to optional since it's generated by the server
+    #[validate(length(min = 1, max = 50, message = "设备名称长度为1-50个字符"))]
     pub name: String,
     pub device_type: DeviceType,
+    #[validate(regex(path = *SERIAL_NUMBER_RE, message = "序列号只能包含字母、数字、下划线和横线，长度为3-50个字符"))]
     pub serial_number: Option<String>,
+    #[validate(regex(path = *MAC_ADDRESS_RE, message = "MAC地址格式不正确"))]
     pub mac_address: Option<String>,
+    #[validate(url(message = "EchoKit Server URL 格式不正确"))]
     pub echokit_server_url: Option<String>,
 }
 
+// 序列号和MAC地址至少提供一个，用于生成设备ID
+fn validate_device_identifiers(request: &DeviceRegistrationRequest) -> Result<(), ValidationError> {
+    if request.serial_number.is_none() && request.mac_address.is_none() {
+        return Err(ValidationError::new("missing_identifier")
+            .with_message("序列号和MAC地址至少提供一个".into()));
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceRegistrationResponse {
     pub device_id: String,
@@ -118,6 +284,9 @@ pub struct DeviceVerificationResponse {
     pub success: bool,
     pub message: String,
     pub device_config: Option<DeviceConfig>,
+    /// 验证设备命令签名的 Ed25519 公钥（Base64），未启用命令签名时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_public_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -201,6 +370,15 @@ pub struct Session {
     pub transcription: Option<String>,
     pub response: Option<String>,
     pub status: SessionStatus,
+    /// 会话摘要（由可选的摘要任务生成）
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// 会话主题标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 会话的识别语言，未指定时为 None（使用默认语言）
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -351,7 +529,7 @@ pub enum WebSocketMessage {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SessionStage {
     Wakeup,
     Listening,
@@ -360,6 +538,163 @@ pub enum SessionStage {
     Completed,
 }
 
+// Bridge 实时会话状态（由 Bridge 内部 API 提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeSessionState {
+    pub session_id: String,
+    pub device_id: String,
+    pub echokit_session_id: Option<String>,
+    pub status: SessionStatus,
+    pub active: bool,
+    pub stage: SessionStage,
+    pub buffered_audio_seconds: f64,
+    pub audio_frames_sent: u64,
+    pub audio_frames_received: u64,
+    /// 回复音频未能在宽限期内送达设备
+    #[serde(default)]
+    pub delivery_failed: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_activity: DateTime<Utc>,
+}
+
+// Bridge 上当前在线的设备（由 Bridge 内部 API 提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeOnlineDevice {
+    pub device_id: String,
+    pub last_heartbeat: DateTime<Utc>,
+    /// 设备当前正在进行的会话，为空表示设备空闲
+    pub active_session_id: Option<String>,
+}
+
+// Bridge 上某设备死信队列的概况（由 Bridge 内部 API 提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterSummary {
+    pub device_id: String,
+    pub messages: usize,
+    pub bytes: usize,
+    pub oldest_at: Option<DateTime<Utc>>,
+}
+
+// 一条发送失败的设备消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterEntry {
+    pub size: usize,
+    /// 最后一次发送失败的原因
+    pub error: String,
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+// Bridge 上某设备的死信队列详情（由 Bridge 内部 API 提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterQueueInfo {
+    #[serde(flatten)]
+    pub summary: DeadLetterSummary,
+    pub entries: Vec<DeadLetterEntry>,
+}
+
+// 重新投递死信的结果，投递失败时剩余的消息保留在队列中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterFlushResult {
+    pub device_id: String,
+    pub delivered: usize,
+    pub remaining: usize,
+}
+
+/// 录音导入中单个文件的处理状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioImportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+// 导入任务中的一个录音文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioImportFile {
+    /// 上传时的文件名
+    pub name: String,
+    pub status: AudioImportStatus,
+    /// 解码后的录音时长，解码失败时为空
+    pub duration_seconds: Option<f64>,
+    /// 写入的会话 ID，识别成功后才有
+    pub session_id: Option<String>,
+    pub transcript: Option<String>,
+    pub error: Option<String>,
+}
+
+// 录音批量导入任务（由 Bridge 管理接口提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioImportJob {
+    pub id: String,
+    pub device_id: String,
+    pub created_at: DateTime<Utc>,
+    /// 所有文件处理完成的时间，仍在处理时为空
+    pub finished_at: Option<DateTime<Utc>>,
+    pub files: Vec<AudioImportFile>,
+}
+
+/// 设备接入 Bridge 的传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceTransport {
+    Websocket,
+    Udp,
+    Grpc,
+}
+
+/// Bridge 上的一个设备连接（由 Bridge `/stats/connections` 提供）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceConnectionInfo {
+    pub device_id: String,
+    pub transport: DeviceTransport,
+    /// 设备的远端地址，未知时为空
+    pub remote_addr: Option<String>,
+    pub connected_at: DateTime<Utc>,
+    pub last_heartbeat: DateTime<Utc>,
+    /// 从设备收到的字节数
+    pub bytes_received: u64,
+    /// 发送给设备的字节数
+    pub bytes_sent: u64,
+    /// 设备当前正在进行的会话，为空表示设备空闲
+    pub session_id: Option<String>,
+}
+
+/// Bridge 设备连接统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceConnectionStats {
+    pub total: usize,
+    pub websocket: usize,
+    pub udp: usize,
+    pub grpc: usize,
+    pub connections: Vec<DeviceConnectionInfo>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl DeviceConnectionStats {
+    /// 按设备 ID 排序并汇总各传输方式的连接数
+    pub fn new(mut connections: Vec<DeviceConnectionInfo>) -> Self {
+        connections.sort_by(|a, b| a.device_id.cmp(&b.device_id).then(a.connected_at.cmp(&b.connected_at)));
+        let count = |transport| connections.iter().filter(|connection| connection.transport == transport).count();
+
+        Self {
+            total: connections.len(),
+            websocket: count(DeviceTransport::Websocket),
+            udp: count(DeviceTransport::Udp),
+            grpc: count(DeviceTransport::Grpc),
+            connections,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Bridge 内部 API 鉴权令牌的环境变量名，Gateway 与 Bridge 需配置相同的值
+pub const BRIDGE_INTERNAL_TOKEN_ENV: &str = "BRIDGE_INTERNAL_TOKEN";
+
+/// 未配置鉴权令牌时使用的开发环境默认值，生产环境必须覆盖
+pub const DEFAULT_BRIDGE_INTERNAL_TOKEN: &str = "echo-bridge-internal-dev-token";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RegistrationStage {
     Created,
@@ -438,10 +773,164 @@ pub enum EchoError {
     #[error("Invalid input: {0}")]
     InvalidInput(String),
 
+    #[error("Validation failed for {} field(s)", .0.len())]
+    Validation(Vec<FieldError>),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
 
+impl EchoError {
+    /// 对应的 HTTP 状态码
+    pub fn status_code(&self) -> u16 {
+        match self {
+            EchoError::InvalidInput(_) => 400,
+            EchoError::Validation(_) => 422,
+            EchoError::Jwt(_) | EchoError::Authentication(_) => 401,
+            EchoError::Authorization(_) => 403,
+            EchoError::DeviceNotFound(_) | EchoError::SessionNotFound(_) | EchoError::NotFound(_) => 404,
+            EchoError::Conflict(_) => 409,
+            EchoError::PayloadTooLarge(_) => 413,
+            EchoError::RateLimited(_) => 429,
+            EchoError::NotImplemented(_) => 501,
+            EchoError::ServiceUnavailable(_) => 503,
+            EchoError::Database(_)
+            | EchoError::Redis(_)
+            | EchoError::Serialization(_)
+            | EchoError::Bcrypt(_)
+            | EchoError::Internal(_) => 500,
+        }
+    }
+
+    /// 机器可读的错误代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            EchoError::Database(_) => "database_error",
+            EchoError::Redis(_) => "cache_error",
+            EchoError::Serialization(_) => "serialization_error",
+            EchoError::Jwt(_) => "invalid_token",
+            EchoError::Bcrypt(_) => "password_hash_error",
+            EchoError::Authentication(_) => "unauthenticated",
+            EchoError::Authorization(_) => "forbidden",
+            EchoError::DeviceNotFound(_) => "device_not_found",
+            EchoError::SessionNotFound(_) => "session_not_found",
+            EchoError::InvalidInput(_) => "invalid_input",
+            EchoError::Validation(_) => "validation_failed",
+            EchoError::NotFound(_) => "not_found",
+            EchoError::Conflict(_) => "conflict",
+            EchoError::PayloadTooLarge(_) => "payload_too_large",
+            EchoError::RateLimited(_) => "rate_limited",
+            EchoError::ServiceUnavailable(_) => "service_unavailable",
+            EchoError::NotImplemented(_) => "not_implemented",
+            EchoError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// 转换为 RFC 7807 错误响应体，服务端内部错误不向客户端暴露细节
+    pub fn to_problem(&self) -> ProblemDetails {
+        let status = self.status_code();
+        let detail = if status == 500 {
+            "Internal server error".to_string()
+        } else {
+            self.to_string()
+        };
+
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: http_status_title(status).to_string(),
+            status,
+            detail,
+            code: self.code().to_string(),
+            instance: None,
+            errors: match self {
+                EchoError::Validation(errors) => errors.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
+impl From<ValidationErrors> for EchoError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut field_errors: Vec<FieldError> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                errors.iter().map(move |error| FieldError {
+                    field: field.to_string(),
+                    code: error.code.to_string(),
+                    message: error
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| error.code.to_string()),
+                })
+            })
+            .collect();
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+        EchoError::Validation(field_errors)
+    }
+}
+
+/// 请求体字段校验错误（结构体级别的校验使用 "__all__" 作为字段名）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// RFC 7807 错误响应体（application/problem+json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// 扩展字段：机器可读的错误代码
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// 扩展字段：字段级校验错误
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+fn http_status_title(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
 // 分页相关类型
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginationParams {
@@ -465,6 +954,9 @@ pub struct PaginatedResponse<T> {
     pub page: u32,
     pub page_size: u32,
     pub total_pages: u32,
+    /// 游标分页模式下的下一页游标，没有更多数据时为 None（偏移分页模式下不返回）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 impl<T> PaginatedResponse<T> {
@@ -476,6 +968,19 @@ impl<T> PaginatedResponse<T> {
             page: params.page,
             page_size: params.page_size,
             total_pages,
+            next_cursor: None,
+        }
+    }
+
+    /// 游标分页结果（page 固定为 0，客户端使用 next_cursor 翻页）
+    pub fn with_cursor(items: Vec<T>, total: u64, page_size: u32, next_cursor: Option<String>) -> Self {
+        Self {
+            items,
+            total,
+            page: 0,
+            page_size,
+            total_pages: crate::calculate_total_pages(total, page_size),
+            next_cursor,
         }
     }
 }
@@ -488,6 +993,36 @@ pub struct AppConfig {
     pub redis: RedisConfig,
     pub mqtt: MqttConfig,
     pub jwt: JwtConfig,
+    pub secrets: crate::config::secrets::SecretsConfig,
+    pub log: LogConfig,
+    pub rate_limit: RateLimitConfig,
+    pub bridge: BridgeConfig,
+    pub health: HealthConfig,
+    pub alerts: AlertConfig,
+    #[serde(default)]
+    pub limits: IngressLimitsConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    #[serde(default)]
+    pub command_signing: CommandSigningConfig,
+    #[serde(default)]
+    pub cookie_auth: CookieAuthConfig,
+    #[serde(default)]
+    pub ip_filter: IpFilterConfig,
+    #[serde(default)]
+    pub command_authorization: CommandAuthorizationConfig,
+    #[serde(default)]
+    pub security_headers: SecurityHeadersConfig,
+    #[serde(default)]
+    pub home_assistant: HomeAssistantConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub announcements: AnnouncementConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -495,6 +1030,10 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub workers: usize,
+    /// 优雅关闭时等待连接排空的最长时间（秒）
+    pub shutdown_timeout_secs: u64,
+    #[serde(default)]
+    pub tls: TlsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -502,121 +1041,1657 @@ pub struct DatabaseConfig {
     pub url: String,
     pub max_connections: u32,
     pub min_connections: u32,
+    #[serde(default)]
+    pub retry: DatabaseRetryConfig,
+    #[serde(default)]
+    pub pool: DatabasePoolConfig,
+    #[serde(default)]
+    pub partitions: DatabasePartitionConfig,
+    #[serde(default)]
+    pub backup: DatabaseBackupConfig,
 }
 
+// 数据库短暂不可用（连接断开、连接池超时、死锁等）时写操作的重试，等待时间每次翻倍并随机抖动
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RedisConfig {
-    pub url: String,
-    pub max_connections: u32,
+#[serde(default)]
+pub struct DatabaseRetryConfig {
+    /// 总尝试次数（包括第一次），1 表示不重试
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间（毫秒）
+    pub initial_backoff_ms: u64,
+    /// 最长等待时间（毫秒）
+    pub max_backoff_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MqttConfig {
-    pub broker: String,
-    pub port: u16,
-    pub username: Option<String>,
-    pub password: Option<String>,
+impl Default for DatabaseRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2000,
+        }
+    }
 }
 
+// 连接池超时与等待时间采样
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct JwtConfig {
-    pub secret: String,
-    pub expiration_hours: u64,
+#[serde(default)]
+pub struct DatabasePoolConfig {
+    /// 获取连接的最长等待时间（秒），超时后查询失败
+    pub acquire_timeout_seconds: u64,
+    /// 空闲连接超过该时间（秒）后关闭，但保留 min_connections 个连接；0 表示不关闭
+    pub idle_timeout_seconds: u64,
+    /// 采样获取连接等待时间的间隔（秒）
+    pub sample_interval_seconds: u64,
+    pub resize: DatabasePoolResizeConfig,
 }
 
-// EchoKit 集成相关类型
+impl Default for DatabasePoolConfig {
+    fn default() -> Self {
+        Self {
+            acquire_timeout_seconds: 30,
+            idle_timeout_seconds: 600,
+            sample_interval_seconds: 15,
+            resize: DatabasePoolResizeConfig::default(),
+        }
+    }
+}
+
+// 等待时间超过阈值时提前建立连接（不超过 max_connections），多出的连接空闲超时后关闭
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EchoKitConfig {
-    pub vad_enabled: bool,
-    pub vad_threshold: f32,
-    pub vad_silence_duration: f32,
-    pub asr_model: String,
-    pub asr_language: String,
-    pub llm_model: String,
-    pub llm_provider: String,
-    pub tts_voice: String,
-    pub tts_provider: String,
-    pub stream_response: bool,
-    pub max_audio_length: f32,
-    pub session_timeout: f32,
+#[serde(default)]
+pub struct DatabasePoolResizeConfig {
+    pub enabled: bool,
+    /// 获取连接的等待时间阈值（毫秒）
+    pub wait_threshold_ms: u64,
+    /// 每次最多新建的连接数
+    pub step: u32,
 }
 
-impl Default for EchoKitConfig {
+impl Default for DatabasePoolResizeConfig {
     fn default() -> Self {
         Self {
-            vad_enabled: true,
-            vad_threshold: 0.5,
-            vad_silence_duration: 1.0,
-            asr_model: "whisper".to_string(),
-            asr_language: "zh".to_string(),
-            llm_model: "gpt-3.5-turbo".to_string(),
-            llm_provider: "openai".to_string(),
-            tts_voice: "zh-CN-female-1".to_string(),
-            tts_provider: "azure".to_string(),
-            stream_response: true,
-            max_audio_length: 30.0,
-            session_timeout: 60.0,
+            enabled: false,
+            wait_threshold_ms: 100,
+            step: 2,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
-pub enum AudioFormat {
-    PCM16,
-    WAV,
-    Opus,
-    MP3,
+// 按月分区表的维护：API Gateway 提前创建之后几个月的分区，并整体删除保留期之外的分区
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabasePartitionConfig {
+    pub enabled: bool,
+    /// 除当月外提前创建的月分区数
+    pub months_ahead: u32,
+    /// 会话数据保留的月数，早于该月数的分区整体删除；0 表示永久保留
+    pub session_retention_months: u32,
+    /// 检查分区的间隔（秒）
+    pub check_interval_seconds: u64,
+}
+
+impl Default for DatabasePartitionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            months_ahead: 3,
+            session_retention_months: 0,
+            check_interval_seconds: 3600,
+        }
+    }
 }
 
+// 每日数据库备份：API Gateway 的领导者副本在 hour_utc 点之后用 pg_dump 导出一次，
+// 备份写入 local_dir，配置 object_storage 时同时上传，只保留最近 retention_count 份
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EchoKitSession {
-    pub id: String,
-    pub device_id: String,
-    pub user_id: String,
-    pub config: EchoKitConfig,
-    pub status: EchoKitSessionStatus,
-    pub start_time: DateTime<Utc>,
-    pub end_time: Option<DateTime<Utc>>,
-    pub current_stage: SessionStage,
-    pub progress: f32,
-    pub transcription: Option<String>,
-    pub response: Option<String>,
-    pub audio_buffer: Vec<u8>,
+#[serde(default)]
+pub struct DatabaseBackupConfig {
+    pub enabled: bool,
+    /// 每天开始备份的时间（UTC 小时，0-23）
+    pub hour_utc: u32,
+    /// 备份文件目录
+    pub local_dir: String,
+    /// 保留的备份份数，更早的备份连同本地文件和对象存储中的对象一起删除
+    pub retention_count: u32,
+    /// pg_dump 可执行文件路径，版本需不低于数据库版本
+    pub pg_dump_path: String,
+    /// 单次备份的超时时间（秒）
+    pub timeout_seconds: u64,
+    /// S3 兼容对象存储，不设置时只保存在本地
+    pub object_storage: Option<ObjectStorageConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum EchoKitSessionStatus {
-    Initializing,
-    Active,
-    Processing,
-    Responding,
-    Completed,
-    Failed,
-    Timeout,
+impl Default for DatabaseBackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hour_utc: 3,
+            local_dir: "/var/lib/echo/backups".to_string(),
+            retention_count: 7,
+            pg_dump_path: "pg_dump".to_string(),
+            timeout_seconds: 3600,
+            object_storage: None,
+        }
+    }
 }
 
+// S3 兼容对象存储（AWS S3、MinIO 等），使用路径风格 URL：{endpoint}/{bucket}/{prefix}{文件名}
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EchoKitDevice {
-    pub device_id: String,
-    pub websocket_url: String,
-    pub is_connected: bool,
-    pub last_heartbeat: DateTime<Utc>,
-    pub current_session_id: Option<String>,
-    pub audio_format: AudioFormat,
-    pub sample_rate: u32,
-    pub channels: u8,
+pub struct ObjectStorageConfig {
+    /// 如 https://s3.us-east-1.amazonaws.com 或 http://minio:9000
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    /// 对象名前缀
+    #[serde(default)]
+    pub prefix: String,
+    /// 不设置时读取 AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY / AWS_SESSION_TOKEN
+    #[serde(default)]
+    pub access_key_id: Option<String>,
+    #[serde(default)]
+    pub secret_access_key: Option<String>,
 }
 
-// EchoKit 服务状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EchoKitServiceStatus {
-    pub is_connected: bool,
-    pub websocket_url: String,
-    pub last_heartbeat: DateTime<Utc>,
-    pub active_sessions: u32,
+pub struct RedisConfig {
+    pub url: String,
+    pub max_connections: u32,
+    #[serde(default)]
+    pub local_cache: LocalCacheConfig,
+    #[serde(default)]
+    pub jobs: JobQueueConfig,
+    #[serde(default)]
+    pub session_limits: SessionLimitConfig,
+    #[serde(default)]
+    pub degradation: CacheDegradationConfig,
+    #[serde(default)]
+    pub ws_tickets: WsTicketConfig,
+}
+
+// Redis 前面的进程内缓存：热点键（如按 ID 读取的设备）在本进程缓存很短的时间，
+// 其他实例的修改通过缓存失效通知删除，过期时间是未收到通知时读到旧数据的上限
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalCacheConfig {
+    pub enabled: bool,
+    /// 每个缓存的最大条目数，超出时淘汰最久未访问的条目
+    pub capacity: usize,
+    /// 条目的过期时间（秒）
+    pub ttl_seconds: u64,
+}
+
+impl Default for LocalCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: 10000,
+            ttl_seconds: 5,
+        }
+    }
+}
+
+// Redis Streams 任务队列：会话摘要、Webhook 投递等后处理写入队列，由 API Gateway 各副本的任务 worker 以消费者组方式处理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobQueueConfig {
+    /// 是否在本服务运行任务 worker，写入任务不受影响
+    pub enabled: bool,
+    pub stream: String,
+    /// 多次失败或无法处理的任务写入的死信流
+    pub dead_letter_stream: String,
+    /// 消费者组名称
+    pub group: String,
+    /// 队列和死信流最多保留的条目数（近似截断）
+    pub max_len: usize,
+    /// worker 每次读取的任务数
+    pub batch_size: usize,
+    /// 处理失败或未确认的任务闲置多久后重试（秒），应大于 job_timeout_seconds
+    pub retry_delay_seconds: u64,
+    /// 每个任务的最大投递次数，达到后写入死信流
+    pub max_attempts: u32,
+    /// 单个任务的处理超时（秒）
+    pub job_timeout_seconds: u64,
+}
+
+impl Default for JobQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stream: "jobs:queue".to_string(),
+            dead_letter_stream: "jobs:dead-letter".to_string(),
+            group: "echo-workers".to_string(),
+            max_len: 100000,
+            batch_size: 10,
+            retry_delay_seconds: 60,
+            max_attempts: 5,
+            job_timeout_seconds: 30,
+        }
+    }
+}
+
+// Redis 不可用时的缓存降级：读取直接查询数据库，写入跳过，后台探测恢复后重新使用缓存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheDegradationConfig {
+    pub enabled: bool,
+    /// 连续多少次连接错误后进入降级模式
+    pub failure_threshold: u32,
+    /// 降级期间探测 Redis 的间隔（秒）
+    pub probe_interval_seconds: u64,
+    /// 建立 Redis 连接和探测的超时（毫秒）
+    pub connect_timeout_ms: u64,
+}
+
+impl Default for CacheDegradationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_threshold: 3,
+            probe_interval_seconds: 5,
+            connect_timeout_ms: 1000,
+        }
+    }
+}
+
+// WebSocket 一次性连接票据：API Gateway 签发，Bridge 的 `/ws/{id}` 校验并消耗
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WsTicketConfig {
+    pub enabled: bool,
+    /// 票据的有效期（秒）
+    pub ttl_seconds: u64,
+    /// Bridge 是否拒绝没有票据的连接；设备固件不携带票据，只有所有设备都经由 Web UI 连接时才开启
+    pub required: bool,
+}
+
+impl Default for WsTicketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_seconds: 30,
+            required: false,
+        }
+    }
+}
+
+// 会话配额：创建会话前在 Redis 中检查并计数，Bridge 和 API Gateway 各副本共用同一组计数器
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionLimitConfig {
+    pub enabled: bool,
+    /// 每个用户每天（UTC）最多创建的会话数，0 表示不限制
+    pub sessions_per_user_per_day: u32,
+    /// 每个设备最多同时进行的会话数，0 表示不限制
+    pub concurrent_sessions_per_device: u32,
+    /// 进行中会话的租约（秒）：实例异常退出未能释放的会话在租约到期后不再计入，应大于最长会话时长
+    pub session_lease_seconds: u64,
+}
+
+impl Default for SessionLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sessions_per_user_per_day: 0,
+            concurrent_sessions_per_device: 0,
+            session_lease_seconds: 7200,
+        }
+    }
+}
+
+// MQTT 连接配置，Gateway 和 Bridge 的所有 MQTT 客户端共用
+//
+// 兼容旧的客户端配置字段名（broker_host、broker_port、keep_alive），未填写的字段使用默认值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    #[serde(alias = "broker_host")]
+    pub broker: String,
+    #[serde(alias = "broker_port")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// 固定的客户端 ID，为空时每个连接自动生成（见 `client_id_or`）
+    pub client_id: Option<String>,
+    #[serde(alias = "keep_alive")]
+    pub keep_alive_secs: u64,
+    pub clean_session: bool,
+    pub max_reconnect_attempts: u32,
+    pub reconnect_interval_ms: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: None,
+            keep_alive_secs: 60,
+            clean_session: true,
+            max_reconnect_attempts: 10,
+            reconnect_interval_ms: 5000,
+        }
+    }
+}
+
+impl MqttConfig {
+    /// 配置的客户端 ID，未配置时生成 `{prefix}-{uuid}`
+    ///
+    /// 同一进程内的多个连接需要不同的客户端 ID，否则 broker 会断开先建立的连接
+    pub fn client_id_or(&self, prefix: &str) -> String {
+        self.client_id
+            .clone()
+            .unwrap_or_else(|| format!("{}-{}", prefix, uuid::Uuid::new_v4()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtConfig {
+    /// 签名密钥，可使用 `secret:<name>` 引用密钥提供者中的值
+    pub secret: String,
+    pub expiration_hours: u64,
+    /// 重新读取签名密钥的间隔（秒），密钥变化时自动轮换，0 表示不检查
+    pub key_refresh_secs: u64,
+    /// 自动轮换签名密钥的周期（小时），每个周期从 secret 派生新的签名密钥，各副本得到相同的密钥；0 表示不自动轮换
+    #[serde(default)]
+    pub rotation_hours: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// tracing 过滤指令，如 "info" 或 "info,echo_bridge=debug"
+    pub level: String,
+    /// 输出格式（LOG_FORMAT=json 输出结构化日志），修改后需重启服务
+    pub format: LogFormat,
+    /// 按 target 覆盖日志级别，如 "echo_bridge::udp_server" = "warn"
+    #[serde(default)]
+    pub targets: std::collections::BTreeMap<String, String>,
+    /// 逐帧音频日志的采样率：每 N 帧记录一次，0 或 1 表示全部记录
+    pub sample_every: u64,
+    /// 输出前替换日志中的密码、令牌和音频数据，修改后需重启服务
+    pub scrub_secrets: bool,
+}
+
+impl LogConfig {
+    /// 合并 level 和按 target 覆盖的级别，得到完整的 tracing 过滤指令
+    pub fn filter_directives(&self) -> String {
+        std::iter::once(self.level.trim().to_string())
+            .chain(self.targets.iter().map(|(target, level)| format!("{}={}", target, level.trim())))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// 便于阅读的文本格式
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+// API 限流配置（按客户端 IP 计数，固定一分钟窗口）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub requests_per_minute: u32,
+}
+
+// EchoKit Hello 消息缓存策略（缓存欢迎语，新会话开始时回放）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HelloCacheConfig {
+    pub enabled: bool,
+    /// 最多缓存的 Hello 消息条数
+    pub max_messages: usize,
+}
+
+// 把 EchoKit 欢迎语（Hello 消息序列）按 EchoKit Server 保存到 Redis，新启动的 Bridge 副本
+// 无需等待 EchoKit 重新发送即可回放欢迎语；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HelloPersistenceConfig {
+    pub enabled: bool,
+    /// 保存的欢迎语的过期时间（秒），EchoKit 更换欢迎语后其他副本最多在该时间内回放旧的欢迎语
+    pub ttl_seconds: u64,
+}
+
+impl Default for HelloPersistenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_seconds: 86400,
+        }
+    }
+}
+
+// 录音批量导入：上传的 WAV / Ogg Opus 录音经 EchoKit 识别后作为已完成的会话写入历史，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioImportConfig {
+    pub enabled: bool,
+    /// 一次导入请求的最大字节数（所有文件合计）
+    pub max_request_bytes: usize,
+    /// 单个录音的最大时长（秒），超出的文件标记为失败
+    pub max_recording_seconds: u64,
+    /// 提交录音后等待 EchoKit 返回识别结果的时间（秒）
+    pub asr_timeout_seconds: u64,
+    /// 同时识别的录音数，每个录音使用独立的 EchoKit 连接
+    pub max_concurrent_files: usize,
+    /// 已结束的导入任务保留多久（秒）供查询
+    pub job_retention_seconds: u64,
+}
+
+impl Default for AudioImportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_request_bytes: 100 * 1024 * 1024,
+            max_recording_seconds: 300,
+            asr_timeout_seconds: 60,
+            max_concurrent_files: 2,
+            job_retention_seconds: 86400,
+        }
+    }
+}
+
+// HTTP 流式接口：不支持 WebSocket 的瘦客户端以 chunked 请求体上传 PCM，并以 chunked 响应接收 TTS 音频，
+// 流的状态只保存在处理该会话的 Bridge 实例中，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpStreamConfig {
+    pub enabled: bool,
+    /// 同时进行中的流数量上限，每个流使用独立的 EchoKit 连接
+    pub max_streams: usize,
+    /// 一次上传的最大音频时长（秒）
+    pub max_upload_seconds: u64,
+    /// 上传过程中两个数据块之间的最长间隔（秒）
+    pub chunk_timeout_seconds: u64,
+    /// 上传结束后等待 EchoKit 回复结束的最长时间（秒）
+    pub response_timeout_seconds: u64,
+    /// 回复结束后没有新的上传时，经过该时间（秒）结束会话
+    pub idle_timeout_seconds: u64,
+}
+
+impl Default for HttpStreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_streams: 32,
+            max_upload_seconds: 60,
+            chunk_timeout_seconds: 10,
+            response_timeout_seconds: 60,
+            idle_timeout_seconds: 120,
+        }
+    }
+}
+
+// gRPC 音频流接口：设备通过双向流接入，与 WebSocket 共用会话和 EchoKit 适配流程，
+// 需以 `grpc` feature 构建 Bridge，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrpcAudioConfig {
+    pub enabled: bool,
+    /// 监听端口，与内部 gRPC 接口（GRPC_PORT）分开，便于只对设备网络开放
+    pub port: u16,
+    /// 同时连接的设备流数量上限，超出时新连接以 RESOURCE_EXHAUSTED 拒绝
+    pub max_streams: usize,
+    /// 下行消息缓冲数量，设备读取过慢导致缓冲写满时发送失败（启用死信队列时进入死信队列）
+    pub send_buffer: usize,
+}
+
+impl Default for GrpcAudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 10041,
+            max_streams: 256,
+            send_buffer: 64,
+        }
+    }
+}
+
+// MQTT 音频接入：只能使用 MQTT 的受限设备分块发布 PCM，由 Bridge 重组后接入会话流程，回复音频分块发布回设备；
+// 多副本部署时只有领导者副本订阅，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttAudioConfig {
+    pub enabled: bool,
+    /// 主题前缀，设备在 `{topic_prefix}/{device_id}/audio/in` 发布音频，在 `audio/out` 和 `audio/event` 接收回复和事件
+    pub topic_prefix: String,
+    /// 同时进行中的设备会话数量上限，每个会话使用独立的 EchoKit 连接
+    pub max_devices: usize,
+    /// 单个音频块的最大字节数（不含 5 字节头），回复音频也按该大小分块
+    pub max_chunk_bytes: usize,
+    /// 一句话的最大音频时长（秒）
+    pub max_utterance_seconds: u64,
+    /// 一句话中两个音频块之间的最长间隔（秒），超出时丢弃这句话
+    pub chunk_timeout_seconds: u64,
+    /// 提交后等待 EchoKit 回复结束的最长时间（秒）
+    pub response_timeout_seconds: u64,
+    /// 回复结束后没有新的语音时，经过该时间（秒）结束会话
+    pub idle_timeout_seconds: u64,
+}
+
+impl Default for MqttAudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            topic_prefix: "echo/device".to_string(),
+            max_devices: 64,
+            max_chunk_bytes: 32_000,
+            max_utterance_seconds: 60,
+            chunk_timeout_seconds: 5,
+            response_timeout_seconds: 60,
+            idle_timeout_seconds: 120,
+        }
+    }
+}
+
+// 语音播报的语音合成：Bridge 收到播报命令后请求 EchoKit Server 所用的 TTS 服务（OpenAI 兼容的
+// /v1/audio/speech 接口，返回 WAV），转为 16kHz PCM 后播放给连接在本副本的设备，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnouncementTtsConfig {
+    pub enabled: bool,
+    /// TTS 接口地址，如 http://localhost:8000/v1/audio/speech
+    pub tts_url: String,
+    /// 接口密钥（Bearer），为空时不发送，通常配置为 `secret:<name>`
+    pub api_key: String,
+    pub model: String,
+    pub voice: String,
+    /// 合成请求超时（秒）
+    pub request_timeout_seconds: u64,
+    /// 合成音频的最大时长（秒），超出部分不播放
+    pub max_audio_seconds: u64,
+    /// 每个音频块的字节数，3200 字节为 100ms
+    pub chunk_bytes: usize,
+}
+
+impl Default for AnnouncementTtsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tts_url: String::new(),
+            api_key: String::new(),
+            model: "tts-1".to_string(),
+            voice: "alloy".to_string(),
+            request_timeout_seconds: 30,
+            max_audio_seconds: 120,
+            chunk_bytes: 3200,
+        }
+    }
+}
+
+// ASR 文本后处理：识别结果在保存到会话记录和转发给设备之前依次经过处理链（自定义词汇替换、脏话屏蔽、
+// 标点恢复）；可按设备所属账号（组织）配置各自的处理链，未配置的账号使用默认处理链，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsrPostProcessingConfig {
+    pub enabled: bool,
+    /// 默认处理链
+    pub default: AsrPipelineConfig,
+    /// 按设备所属用户 ID 覆盖的处理链
+    pub owners: std::collections::HashMap<String, AsrPipelineConfig>,
+    /// 设备所属用户的缓存时长（秒），仅在配置了 owners 时查询
+    pub owner_cache_seconds: u64,
+}
+
+impl Default for AsrPostProcessingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default: AsrPipelineConfig::default(),
+            owners: std::collections::HashMap::new(),
+            owner_cache_seconds: 300,
+        }
+    }
+}
+
+/// ASR 文本处理链
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsrPipelineConfig {
+    /// 按顺序执行的处理器
+    pub processors: Vec<AsrProcessorKind>,
+    /// 自定义词汇：识别结果中的写法 -> 替换后的写法，英文不区分大小写
+    pub vocabulary: std::collections::BTreeMap<String, String>,
+    /// 需要屏蔽的词，英文不区分大小写且只匹配完整单词
+    pub profanity_words: Vec<String>,
+    /// 屏蔽时替换每个字符的字符
+    pub profanity_mask: String,
+}
+
+impl Default for AsrPipelineConfig {
+    fn default() -> Self {
+        Self {
+            processors: Vec::new(),
+            vocabulary: std::collections::BTreeMap::new(),
+            profanity_words: Vec::new(),
+            profanity_mask: "*".to_string(),
+        }
+    }
+}
+
+/// 内置的 ASR 文本处理器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AsrProcessorKind {
+    /// 自定义词汇替换
+    Vocabulary,
+    /// 脏话屏蔽
+    Profanity,
+    /// 标点恢复：补全句末标点，英文句首大写
+    Punctuation,
+}
+
+// SIP 电话接入：Bridge 作为 SIP 终端接听来电，将 RTP 语音接入会话流程并把 EchoKit 的回复播放给来电者，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SipConfig {
+    pub enabled: bool,
+    /// SIP 信令监听地址（UDP）
+    pub bind_address: String,
+    /// 写入 SDP 和 Contact 头的对外地址（IP），为空时使用监听地址
+    pub public_address: String,
+    /// RTP 端口范围（含两端），每路通话占用一个端口
+    pub rtp_port_min: u16,
+    pub rtp_port_max: u16,
+    /// 同时进行的通话数上限，超出时回复 486 Busy Here
+    pub max_calls: usize,
+    /// 单次通话的最长时长（秒），超出后由 Bridge 挂断
+    pub max_call_seconds: u64,
+    /// 超过该时间（秒）未收到 RTP 时视为对端已断开
+    pub rtp_timeout_seconds: u64,
+    /// 被叫号码（Request-URI 的用户部分）到设备 ID 的映射
+    pub extensions: std::collections::HashMap<String, String>,
+    /// 未匹配到分机时使用的设备，为空时拒绝来电（404）
+    pub default_device_id: String,
+    /// 语音检测阈值（16-bit PCM 帧的均方根），高于该值视为在说话
+    pub vad_threshold: u16,
+    /// 说话后静音超过该时间（毫秒）即提交本轮语音
+    pub end_of_speech_ms: u64,
+    /// 单轮语音的最长时长（秒），超出后直接提交
+    pub max_utterance_seconds: u64,
+}
+
+impl Default for SipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "0.0.0.0:5060".to_string(),
+            public_address: String::new(),
+            rtp_port_min: 20000,
+            rtp_port_max: 20099,
+            max_calls: 8,
+            max_call_seconds: 600,
+            rtp_timeout_seconds: 30,
+            extensions: std::collections::HashMap::new(),
+            default_device_id: String::new(),
+            vad_threshold: 500,
+            end_of_speech_ms: 800,
+            max_utterance_seconds: 30,
+        }
+    }
+}
+
+// Bridge 的 Tokio 运行时参数，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokioRuntimeConfig {
+    /// 工作线程数，0 表示使用 CPU 核数
+    pub worker_threads: usize,
+    /// 阻塞线程池（spawn_blocking、文件 IO）的线程数上限
+    pub max_blocking_threads: usize,
+}
+
+// UDP 音频服务器的接收与分发参数，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdpServerConfig {
+    /// 接收任务数，大于 1 时通过 SO_REUSEPORT 绑定多个 socket 由内核分流
+    pub reader_tasks: usize,
+    /// 单次系统调用最多接收的数据包数（Linux 使用 recvmmsg）
+    pub batch_size: usize,
+    /// 处理任务数，同一设备的数据包固定由同一任务按序处理
+    pub dispatch_workers: usize,
+    /// 每个处理任务的队列容量（数据包），队列满时丢弃最旧的数据包
+    pub dispatch_queue_capacity: usize,
+}
+
+// Bridge 调用 API Gateway HTTP 接口的客户端参数（连接池、超时与重试），修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayClientConfig {
+    /// API Gateway HTTP 地址，如 http://api-gateway:8080
+    pub base_url: String,
+    /// 每个主机保留的空闲 keep-alive 连接数上限
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接的保留时间（秒）
+    pub pool_idle_timeout_seconds: u64,
+    /// 使用 HTTP/2（h2c）在单个连接上复用并发请求，需 API Gateway 支持
+    pub http2: bool,
+    pub connect_timeout_ms: u64,
+    /// 设备查询超时（毫秒）
+    pub device_lookup_timeout_ms: u64,
+    /// 用户令牌校验超时（毫秒）
+    pub auth_timeout_ms: u64,
+    /// 连接错误、超时或 502/503/504 时的最大重试次数（仅幂等请求）
+    pub max_retries: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次翻倍
+    pub retry_backoff_ms: u64,
+}
+
+// 多副本部署：设备归属和会话路由保存在 Redis（使用顶层 redis.url），修改后需重启服务
+//
+// 设备的 WebSocket 和 UDP 数据包可以到达不同副本，UDP 数据包和内部 API 请求会转发到设备或会话所在的副本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    /// 副本 ID，留空时使用 HOSTNAME 环境变量，仍为空时随机生成
+    pub instance_id: String,
+    /// 其他副本访问本副本内部 API 的地址，留空时使用 http://{HOSTNAME}:{websocket_port}
+    pub advertise_url: String,
+    /// 其他副本转发 UDP 数据包的目标地址（host:port），留空时使用 {HOSTNAME}:{UDP 端口}
+    pub advertise_udp_address: String,
+    /// Redis 键前缀，多套环境共用一个 Redis 时区分
+    pub key_prefix: String,
+    /// 副本、设备和会话记录的过期时间（秒），副本停止刷新后由其他副本接管其设备
+    pub presence_ttl_seconds: u64,
+    /// 刷新记录的间隔（秒），应明显小于 presence_ttl_seconds
+    pub heartbeat_interval_seconds: u64,
+    /// UDP 路由结果的本地缓存时间（毫秒），避免每个数据包都查询 Redis
+    pub route_cache_ms: u64,
+}
+
+// Bridge 服务配置（API Gateway 通过 internal_url / internal_token 访问 Bridge 内部 API）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    pub internal_url: String,
+    pub internal_token: String,
+    /// 允许使用开发环境默认的 internal_token，仅限本地开发；未开启时 API Gateway 和 Bridge 拒绝以默认令牌启动
+    #[serde(default)]
+    pub allow_dev_internal_token: bool,
+    /// HTTP/WebSocket 端口（健康检查、设备 WebSocket、内部 API）
+    pub websocket_port: u16,
+    pub udp_bind_address: String,
+    pub echokit_websocket_url: String,
+    pub api_gateway_websocket_url: String,
+    pub max_sessions: u32,
+    pub session_timeout_seconds: i64,
+    pub heartbeat_interval_seconds: u64,
+    /// 数据库查询耗时超过该值（毫秒）时记录警告并计数，0 表示不检测
+    pub slow_query_threshold_ms: u64,
+    /// 锁等待或持有时间超过该值（毫秒）时记录警告并计数，0 表示不检测
+    pub slow_lock_threshold_ms: u64,
+    /// 音频通道容量（帧），队列满时丢弃最旧的帧
+    pub audio_channel_capacity: usize,
+    /// 控制通道（ASR、AI 回复、原始消息）容量，队列满时发送端等待
+    pub control_channel_capacity: usize,
+    pub hello_cache: HelloCacheConfig,
+    pub runtime: TokioRuntimeConfig,
+    pub udp: UdpServerConfig,
+    pub gateway: GatewayClientConfig,
+    pub cluster: ClusterConfig,
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub dead_letter: DeadLetterConfig,
+    pub audio_spool: AudioSpoolConfig,
+    #[serde(default)]
+    pub outbox: OutboxRelayConfig,
+    #[serde(default)]
+    pub presence: DevicePresenceConfig,
+    #[serde(default)]
+    pub hello_persistence: HelloPersistenceConfig,
+    #[serde(default)]
+    pub audio_import: AudioImportConfig,
+    #[serde(default)]
+    pub sip: SipConfig,
+    #[serde(default)]
+    pub http_stream: HttpStreamConfig,
+    #[serde(default)]
+    pub grpc_audio: GrpcAudioConfig,
+    #[serde(default)]
+    pub mqtt_audio: MqttAudioConfig,
+    #[serde(default)]
+    pub announcements: AnnouncementTtsConfig,
+    #[serde(default)]
+    pub asr_postprocessing: AsrPostProcessingConfig,
+    pub startup: StartupConfig,
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+impl BridgeConfig {
+    /// internal_token 是否仍为开发环境默认值
+    pub fn uses_dev_internal_token(&self) -> bool {
+        self.internal_token == DEFAULT_BRIDGE_INTERNAL_TOKEN
+    }
+
+    /// 启动前检查 internal_token：仍为开发环境默认值且未开启 allow_dev_internal_token 时返回错误
+    pub fn check_internal_token(&self) -> anyhow::Result<()> {
+        if self.uses_dev_internal_token() && !self.allow_dev_internal_token {
+            anyhow::bail!(
+                "bridge.internal_token is the development default; set {} or enable bridge.allow_dev_internal_token for local development",
+                BRIDGE_INTERNAL_TOKEN_ENV
+            );
+        }
+        Ok(())
+    }
+}
+
+// EchoKit、PostgreSQL、Redis 调用的熔断参数（三者各自独立熔断），修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    /// 连续失败达到该次数后熔断，熔断期间调用直接失败
+    pub failure_threshold: u32,
+    /// 熔断持续时间（毫秒），之后放行一次探测调用，成功则恢复
+    pub open_duration_ms: u64,
+}
+
+// 发往设备失败的消息保留在按设备划分的死信队列中，可通过内部 API 查看、重新投递或丢弃
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetterConfig {
+    pub enabled: bool,
+    /// 每条消息的发送尝试次数，全部失败后进入死信队列
+    pub send_attempts: u32,
+    /// 每个设备最多保留的消息数，超出时丢弃最早的
+    pub max_messages_per_device: usize,
+    /// 每个设备最多保留的字节数，超出时丢弃最早的
+    pub max_bytes_per_device: usize,
+    /// 消息保留时间（秒），过期后丢弃
+    pub ttl_seconds: u64,
+}
+
+// 启动时各依赖的检查策略，修改后需重启服务
+//
+// 必需的依赖不可用时 Bridge 退出（由容器编排重启），可选的依赖在启动后按需连接
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupConfig {
+    pub database: StartupDependencyConfig,
+    /// 仅在启用多副本部署（bridge.cluster）时检查
+    pub redis: StartupDependencyConfig,
+    pub mqtt: StartupDependencyConfig,
+    pub echokit: StartupDependencyConfig,
+    pub gateway: StartupDependencyConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupDependencyConfig {
+    pub policy: StartupPolicy,
+    /// `retry` 策略下等待依赖可用的最长时间（秒）
+    #[serde(default)]
+    pub retry_seconds: u64,
+}
+
+impl StartupDependencyConfig {
+    pub fn new(policy: StartupPolicy, retry_seconds: u64) -> Self {
+        Self { policy, retry_seconds }
+    }
+}
+
+/// 启动时依赖不可用的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPolicy {
+    /// 检查一次，不可用时退出
+    Required,
+    /// 在 retry_seconds 内重试，仍不可用时退出
+    Retry,
+    /// 检查一次，不可用时只记录警告，启动后按需连接
+    Optional,
+}
+
+// 设备在接收回复音频时断线，未送达的音频按会话暂存，设备在宽限期内重连后补发，
+// 否则会话标记为投递失败
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioSpoolConfig {
+    pub enabled: bool,
+    /// 等待设备重连的时间（秒），超时后丢弃暂存的音频
+    pub grace_period_seconds: u64,
+    /// 每个会话在内存中暂存的最大字节数
+    pub max_memory_bytes_per_session: usize,
+    /// 内存暂存满后溢出到该目录，不设置时不使用磁盘，超出内存上限即视为投递失败
+    #[serde(default)]
+    pub spool_dir: Option<String>,
+    /// 每个会话在磁盘上暂存的最大字节数
+    pub max_disk_bytes_per_session: usize,
+}
+
+// outbox 事件转发：把与状态变更同一事务写入 outbox 表的事件发布到 MQTT，多副本时各自领取不同的事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutboxRelayConfig {
+    pub enabled: bool,
+    /// 检查未投递事件的间隔（毫秒）
+    pub poll_interval_ms: u64,
+    /// 每轮最多发布的事件数
+    pub batch_size: u32,
+    /// 每个事件的最大发布尝试次数，达到后留在表中不再重试
+    pub max_attempts: u32,
+    /// 已投递事件的保留时间（小时），0 表示不删除
+    pub delivered_retention_hours: u64,
+}
+
+impl Default for OutboxRelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_ms: 1000,
+            batch_size: 100,
+            max_attempts: 10,
+            delivered_retention_hours: 72,
+        }
+    }
+}
+
+// 设备实时在线状态：Bridge 把在线设备的最近心跳和当前会话写入 Redis（带过期时间），
+// API Gateway 的设备接口读取后合并到响应中，Redis 不可用时使用数据库中的状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevicePresenceConfig {
+    pub enabled: bool,
+    /// 在线状态的过期时间（秒），Bridge 停止刷新后设备在该时间后视为离线
+    pub ttl_seconds: u64,
+    /// Bridge 刷新在线状态的间隔（秒），应明显小于 ttl_seconds
+    pub refresh_interval_seconds: u64,
+}
+
+impl Default for DevicePresenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_seconds: 90,
+            refresh_interval_seconds: 30,
+        }
+    }
+}
+
+// 就绪检查（/health/ready）中视为关键的依赖，可选 database / redis / mqtt / echokit / udp / gateway / tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    pub bridge_critical: Vec<String>,
+    pub gateway_critical: Vec<String>,
+}
+
+// 外部输入的大小限制（Bridge 与 API Gateway 共用），超出限制的输入被拒绝并计入 ingress_rejected 指标；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngressLimitsConfig {
+    /// WebSocket 单个消息（含分片重组后）的最大字节数
+    pub max_websocket_message_bytes: usize,
+    /// WebSocket 单个帧的最大字节数
+    pub max_websocket_frame_bytes: usize,
+    /// UDP 数据包的最大字节数，超出的数据包整包丢弃
+    pub max_udp_datagram_bytes: usize,
+    /// HTTP 请求体的最大字节数
+    pub max_http_body_bytes: usize,
+    /// MessagePack 数据的最大嵌套深度（数组和映射）
+    pub max_messagepack_depth: usize,
+}
+
+impl Default for IngressLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_websocket_message_bytes: 1024 * 1024,
+            max_websocket_frame_bytes: 256 * 1024,
+            max_udp_datagram_bytes: 4096,
+            max_http_body_bytes: 2 * 1024 * 1024,
+            max_messagepack_depth: 16,
+        }
+    }
+}
+
+// 转写文本脱敏：会话结束写入数据库前替换转写和 AI 回复中的敏感内容，会话 metadata.redacted 记录是否替换过；
+// 修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// 默认是否对会话脱敏
+    pub enabled: bool,
+    /// 按设备所属用户覆盖 enabled（用户 ID -> 是否脱敏）
+    pub users: std::collections::HashMap<String, bool>,
+    /// 日志中的转写和回复文本是否脱敏（日志不区分用户）
+    pub logs: bool,
+    pub phone_numbers: bool,
+    pub emails: bool,
+    /// 通过 Luhn 校验的 13-19 位银行卡号
+    pub credit_cards: bool,
+    /// 额外的正则表达式
+    pub patterns: Vec<String>,
+    /// 替换敏感内容的文本
+    pub replacement: String,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            users: std::collections::HashMap::new(),
+            logs: false,
+            phone_numbers: true,
+            emails: true,
+            credit_cards: true,
+            patterns: Vec::new(),
+            replacement: "[REDACTED]".to_string(),
+        }
+    }
+}
+
+// 静态加密：会话转写和 AI 回复使用每个会话独立的数据密钥加密后写入数据库，数据密钥由主密钥包装后随密文保存；
+// 主密钥通常配置为 `secret:<name>` 由密钥提供者解析；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    /// 当前主密钥的 ID，写入密文头，轮换时改为新的 ID
+    pub master_key_id: String,
+    /// 当前主密钥（Base64 编码的 32 字节）
+    pub master_key: String,
+    /// 轮换前的主密钥（ID -> Base64 编码的 32 字节），用于解密和重新包装旧数据
+    pub previous_master_keys: std::collections::HashMap<String, String>,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            master_key_id: "v1".to_string(),
+            master_key: String::new(),
+            previous_master_keys: std::collections::HashMap::new(),
+        }
+    }
+}
+
+// 浏览器客户端的 Cookie 会话（API Gateway）：登录时请求 cookie 模式的客户端获得 HttpOnly 会话 Cookie，
+// 修改状态的请求需在请求头中带上 CSRF 令牌；Bearer 令牌模式不受影响。修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CookieAuthConfig {
+    pub enabled: bool,
+    /// 保存 JWT 的 HttpOnly Cookie 名
+    pub session_cookie: String,
+    /// 保存 CSRF 令牌的 Cookie 名（脚本可读，用于填写请求头）
+    pub csrf_cookie: String,
+    /// 携带 CSRF 令牌的请求头
+    pub csrf_header: String,
+    /// 只通过 HTTPS 发送 Cookie，本地 HTTP 调试时可关闭
+    pub secure: bool,
+    pub same_site: CookieSameSite,
+}
+
+impl Default for CookieAuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            session_cookie: "echo_session".to_string(),
+            csrf_cookie: "echo_csrf".to_string(),
+            csrf_header: "X-CSRF-Token".to_string(),
+            secure: true,
+            same_site: CookieSameSite::Strict,
+        }
+    }
+}
+
+/// Cookie 的 SameSite 属性
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CookieSameSite {
+    #[default]
+    Strict,
+    Lax,
+    /// 跨站请求也发送 Cookie，必须同时启用 secure
+    None,
+}
+
+// IP 允许/拒绝列表（API Gateway 接口、Bridge 内部接口和 UDP 音频入口），在鉴权之前检查；
+// 管理员可通过 /admin/ip-filter/rules 追加规则。支持热加载
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpFilterConfig {
+    pub enabled: bool,
+    /// 允许的地址或 CIDR，非空时只接受其中的地址
+    pub allow: Vec<String>,
+    /// 拒绝的地址或 CIDR，优先于 allow
+    pub deny: Vec<String>,
+    /// 使用 X-Forwarded-For / X-Real-IP 中的客户端地址，仅在部署于可信反向代理之后时开启
+    pub trust_proxy_headers: bool,
+    /// 反向代理或 CDN 写入的国家代码请求头（如 CF-IPCountry），为空时不按国家过滤
+    pub country_header: String,
+    /// 允许的国家代码（ISO 3166），非空时只接受这些国家
+    pub allowed_countries: Vec<String>,
+    /// 拒绝的国家代码
+    pub blocked_countries: Vec<String>,
+    /// 重新读取管理接口规则的间隔（秒）
+    pub refresh_seconds: u64,
+}
+
+impl Default for IpFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            trust_proxy_headers: false,
+            country_header: String::new(),
+            allowed_countries: Vec::new(),
+            blocked_countries: Vec::new(),
+            refresh_seconds: 30,
+        }
+    }
+}
+
+// 设备命令签名：API Gateway 用 Ed25519 私钥签名下发的控制命令和配置，Bridge 用公钥验证后才转发给设备；
+// 私钥只需配置在 API Gateway，通常配置为 `secret:<name>`；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandSigningConfig {
+    pub enabled: bool,
+    /// 签名私钥（Base64 编码的 32 字节种子）
+    pub private_key: String,
+    /// 验证公钥（Base64 编码的 32 字节），为空时从 private_key 推导
+    pub public_key: String,
+    /// 命令签发时间与本机时间允许的最大差值（秒），超出视为过期
+    pub max_age_seconds: u64,
+}
+
+impl Default for CommandSigningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            private_key: String::new(),
+            public_key: String::new(),
+            max_age_seconds: 300,
+        }
+    }
+}
+
+// 设备命令授权：各类命令要求的最低设备权限（设备所有者为 owner，其他用户见 user_devices.permission_level）。
+// 管理员角色不受限制，viewer 角色不能下发命令；API Gateway 和 Bridge 都会检查，拒绝记录到审计日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandAuthorizationConfig {
+    pub enabled: bool,
+    /// 重启
+    pub restart: DevicePermission,
+    /// 调节音量
+    pub volume: DevicePermission,
+    /// 固件升级
+    pub ota: DevicePermission,
+    /// 播放提示音、播报
+    pub announcement: DevicePermission,
+    /// 其他命令（会话控制、位置、自定义命令等）
+    pub other: DevicePermission,
+}
+
+impl Default for CommandAuthorizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            restart: DevicePermission::Admin,
+            volume: DevicePermission::User,
+            ota: DevicePermission::Owner,
+            announcement: DevicePermission::User,
+            other: DevicePermission::Owner,
+        }
+    }
+}
+
+// HTTP 服务的 TLS（rustls）：启用后直接以 HTTPS / WSS 提供服务，不再需要反向代理终止 TLS。
+// 证书和私钥为 PEM 格式；修改后需重启服务
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// 证书链（PEM）
+    pub cert_path: String,
+    /// 私钥（PEM，PKCS#8、PKCS#1 或 SEC1）
+    pub key_path: String,
+}
+
+// API Gateway 和 Bridge 响应中的安全头，修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    pub enabled: bool,
+    /// 通过 HTTPS 访问（本服务启用 TLS 或反向代理标记 X-Forwarded-Proto: https）时发送 Strict-Transport-Security
+    pub hsts: bool,
+    pub hsts_max_age_seconds: u64,
+    pub hsts_include_subdomains: bool,
+    /// X-Content-Type-Options: nosniff
+    pub content_type_options: bool,
+    /// 允许嵌入页面（如 Bridge 自带的 Web UI）的来源，写入 CSP frame-ancestors；为空时不发送
+    pub frame_ancestors: Vec<String>,
+    /// Referrer-Policy，为空时不发送
+    pub referrer_policy: String,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            hsts: true,
+            hsts_max_age_seconds: 31_536_000,
+            hsts_include_subdomains: false,
+            content_type_options: true,
+            frame_ancestors: vec!["'self'".to_string()],
+            referrer_policy: "no-referrer".to_string(),
+        }
+    }
+}
+
+// Home Assistant 集成：API Gateway 通过 MQTT discovery 把设备发布为 Home Assistant 实体
+// （在线状态、音量、播报），Home Assistant 的命令经命令签名和授权检查后下发给设备。
+// 多副本部署时只有领导者副本连接 broker；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HomeAssistantConfig {
+    pub enabled: bool,
+    /// Home Assistant 的 discovery 前缀
+    pub discovery_prefix: String,
+    /// 实体状态和命令的主题前缀，集成在线状态发布到 `<base_topic>/status`
+    pub base_topic: String,
+    /// 从数据库重新发布 discovery 配置和设备状态的间隔（秒）
+    pub refresh_interval_seconds: u64,
+    /// 是否接受 Home Assistant 的音量和播报命令
+    pub commands_enabled: bool,
+    /// 以该用户身份检查授权并签名 Home Assistant 的命令；为空时按系统命令下发，
+    /// 此时应通过 broker ACL 限制谁能发布到命令主题
+    pub command_user_id: String,
+}
+
+impl Default for HomeAssistantConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            discovery_prefix: "homeassistant".to_string(),
+            base_topic: "etch/ha".to_string(),
+            refresh_interval_seconds: 300,
+            commands_enabled: true,
+            command_user_id: String::new(),
+        }
+    }
+}
+
+// Webhook 订阅：用户通过 /api/v1/webhooks 注册回调地址，API Gateway 把匹配的会话和设备事件以
+// HMAC-SHA256 签名的 JSON POST 到回调地址，失败后按指数退避重试。
+// 多副本部署时只有领导者副本投递；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    /// 每个用户最多注册的 Webhook 数
+    pub max_webhooks_per_user: u32,
+    /// 每次投递最多尝试的次数，之后标记为失败
+    pub max_attempts: u32,
+    /// 第一次重试的等待时间（秒），之后每次翻倍
+    pub retry_base_seconds: u64,
+    /// 重试等待时间的上限（秒）
+    pub retry_max_seconds: u64,
+    /// 单次请求超时（秒）
+    pub request_timeout_seconds: u64,
+    /// 检查待投递记录的间隔（秒）
+    pub poll_interval_seconds: u64,
+    /// 每次最多投递的记录数
+    pub batch_size: u32,
+    /// 投递记录的保留天数
+    pub delivery_retention_days: u32,
+    /// 是否允许 http:// 回调地址（默认只允许 https://）
+    pub allow_http: bool,
+    /// 允许作为回调目标的内部地址段（CIDR）。回环、私有、链路本地和未指定地址默认拒绝
+    pub allowed_internal_targets: Vec<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_webhooks_per_user: 10,
+            max_attempts: 6,
+            retry_base_seconds: 10,
+            retry_max_seconds: 3600,
+            request_timeout_seconds: 10,
+            poll_interval_seconds: 5,
+            batch_size: 50,
+            delivery_retention_days: 7,
+            allow_http: false,
+            allowed_internal_targets: Vec::new(),
+        }
+    }
+}
+
+// 通知：用户通过 /api/v1/notifications/destinations 添加接收地址（邮件、Slack、Telegram），
+// API Gateway 按订阅发送每日会话摘要、设备离线提醒和会话失败通知。
+// 多副本部署时只有领导者副本发送；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    /// 每个用户最多添加的接收地址数
+    pub max_destinations_per_user: u32,
+    /// 每日会话摘要的发送时间（UTC 小时，0-23）
+    pub digest_hour_utc: u32,
+    /// 同一设备两次离线提醒之间的最短间隔（秒）
+    pub offline_alert_cooldown_seconds: u64,
+    /// 检查失败会话和每日摘要的间隔（秒）
+    pub check_interval_seconds: u64,
+    /// 发送请求超时（秒）
+    pub request_timeout_seconds: u64,
+    pub smtp: SmtpConfig,
+    pub telegram: TelegramConfig,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_destinations_per_user: 10,
+            digest_hour_utc: 8,
+            offline_alert_cooldown_seconds: 600,
+            check_interval_seconds: 60,
+            request_timeout_seconds: 10,
+            smtp: SmtpConfig::default(),
+            telegram: TelegramConfig::default(),
+        }
+    }
+}
+
+/// SMTP 连接的加密方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SmtpTls {
+    /// 明文连接后升级为 TLS（通常为 587 端口）
+    #[default]
+    Starttls,
+    /// 直接建立 TLS 连接（通常为 465 端口）
+    Tls,
+    /// 不加密，只用于本地测试
+    None,
+}
+
+// 邮件通知使用的 SMTP 服务器；host 为空时邮件只写入日志。密码通常配置为 `secret:<name>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: String,
+    pub password: String,
+    /// 发件人，如 "Etch <noreply@example.com>"
+    pub from: String,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            tls: SmtpTls::Starttls,
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+        }
+    }
+}
+
+// Telegram 通知使用的机器人；bot_token 为空时不支持 Telegram 接收地址。令牌通常配置为 `secret:<name>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    /// Bot API 地址，使用自建 Bot API 服务器时修改
+    pub api_url: String,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            bot_token: String::new(),
+            api_url: "https://api.telegram.org".to_string(),
+        }
+    }
+}
+
+// 定时语音播报：用户通过 /api/v1/announcements 按 cron 表达式或 RRULE 规则安排播报，
+// 到期时 API Gateway 向目标设备下发播报命令，由连接设备的 Bridge 合成语音并播放。
+// 多副本部署时只有领导者副本触发；修改后需重启服务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnouncementConfig {
+    pub enabled: bool,
+    /// 每个用户最多创建的播报计划数
+    pub max_schedules_per_user: u32,
+    /// 播报文本的最大字符数
+    pub max_text_chars: usize,
+    /// 单个计划最多指定的设备和位置数
+    pub max_targets: usize,
+    /// 检查到期计划的间隔（秒）
+    pub check_interval_seconds: u64,
+    /// 未指定时区的计划使用的时区（IANA 名称，如 Asia/Shanghai）
+    pub default_timezone: String,
+}
+
+impl Default for AnnouncementConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_schedules_per_user: 20,
+            max_text_chars: 500,
+            max_targets: 50,
+            check_interval_seconds: 15,
+            default_timezone: "UTC".to_string(),
+        }
+    }
+}
+
+// 告警（Bridge 定期按规则检查，触发和恢复时通过 Webhook 和 MQTT system/bridge/alert 通知）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    /// 规则检查间隔（秒）
+    pub check_interval_seconds: u64,
+    /// 同一规则两次触发通知之间的最短间隔（秒），防止告警风暴
+    pub cooldown_seconds: u64,
+    /// 接收告警的 Webhook 地址（POST JSON）
+    #[serde(default)]
+    pub webhook_urls: Vec<String>,
+    /// 是否发布到 MQTT
+    pub mqtt: bool,
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+}
+
+// 告警规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    #[serde(default)]
+    pub severity: AlertSeverity,
+    pub condition: AlertCondition,
+}
+
+/// 告警级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// 告警条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// 已建立的 EchoKit 连接全部断开超过 for_seconds 秒
+    EchokitDisconnected { for_seconds: u64 },
+    /// window_seconds 内会话创建失败率达到 threshold（会话数不少于 min_sessions 时才计算）
+    SessionFailureRate { threshold: f64, window_seconds: u64, min_sessions: u64 },
+    /// 数据库连接池使用率达到 utilization 超过 for_seconds 秒
+    DbPoolExhausted { utilization: f64, for_seconds: u64 },
+}
+
+// 可在运行时热加载的配置（SIGHUP 或 POST /admin/reload），其余配置修改后需重启服务
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    /// 完整的日志过滤指令（log.level 与 log.targets 合并）
+    pub log_level: String,
+    pub log_sample_every: u64,
+    pub session_timeout_seconds: i64,
+    pub rate_limit: RateLimitConfig,
+    pub hello_cache: HelloCacheConfig,
+    pub ip_filter: IpFilterConfig,
+}
+
+impl From<&AppConfig> for RuntimeConfig {
+    fn from(config: &AppConfig) -> Self {
+        Self {
+            log_level: config.log.filter_directives(),
+            log_sample_every: config.log.sample_every,
+            session_timeout_seconds: config.bridge.session_timeout_seconds,
+            rate_limit: config.rate_limit.clone(),
+            hello_cache: config.bridge.hello_cache.clone(),
+            ip_filter: config.ip_filter.clone(),
+        }
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::from(&AppConfig::default())
+    }
+}
+
+impl RuntimeConfig {
+    /// 与新配置相比发生变化的配置项
+    pub fn changed_settings(&self, other: &RuntimeConfig) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.log_level != other.log_level {
+            changed.push("log.level");
+        }
+        if self.log_sample_every != other.log_sample_every {
+            changed.push("log.sample_every");
+        }
+        if self.session_timeout_seconds != other.session_timeout_seconds {
+            changed.push("bridge.session_timeout_seconds");
+        }
+        if self.rate_limit != other.rate_limit {
+            changed.push("rate_limit");
+        }
+        if self.hello_cache != other.hello_cache {
+            changed.push("bridge.hello_cache");
+        }
+        if self.ip_filter != other.ip_filter {
+            changed.push("ip_filter");
+        }
+        changed
+    }
+}
+
+/// 配置热加载结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigReloadResult {
+    /// 发生变化的配置项，为空表示配置未变化
+    pub changed: Vec<String>,
+    pub config: RuntimeConfig,
+}
+
+// EchoKit 集成相关类型
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EchoKitConfig {
+    pub vad_enabled: bool,
+    pub vad_threshold: f32,
+    pub vad_silence_duration: f32,
+    pub asr_model: String,
+    pub asr_language: String,
+    pub llm_model: String,
+    pub llm_provider: String,
+    pub tts_voice: String,
+    pub tts_provider: String,
+    pub stream_response: bool,
+    pub max_audio_length: f32,
+    pub session_timeout: f32,
+}
+
+impl Default for EchoKitConfig {
+    fn default() -> Self {
+        Self {
+            vad_enabled: true,
+            vad_threshold: 0.5,
+            vad_silence_duration: 1.0,
+            asr_model: "whisper".to_string(),
+            asr_language: "zh".to_string(),
+            llm_model: "gpt-3.5-turbo".to_string(),
+            llm_provider: "openai".to_string(),
+            tts_voice: "zh-CN-female-1".to_string(),
+            tts_provider: "azure".to_string(),
+            stream_response: true,
+            max_audio_length: 30.0,
+            session_timeout: 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AudioFormat {
+    PCM16,
+    WAV,
+    Opus,
+    MP3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EchoKitSession {
+    pub id: String,
+    pub device_id: String,
+    pub user_id: String,
+    pub config: EchoKitConfig,
+    pub status: EchoKitSessionStatus,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub current_stage: SessionStage,
+    pub progress: f32,
+    pub transcription: Option<String>,
+    pub response: Option<String>,
+    pub audio_buffer: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum EchoKitSessionStatus {
+    Initializing,
+    Active,
+    Processing,
+    Responding,
+    Completed,
+    Failed,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EchoKitDevice {
+    pub device_id: String,
+    pub websocket_url: String,
+    pub is_connected: bool,
+    pub last_heartbeat: DateTime<Utc>,
+    pub current_session_id: Option<String>,
+    pub audio_format: AudioFormat,
+    pub sample_rate: u32,
+    pub channels: u8,
+}
+
+// EchoKit 服务状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EchoKitServiceStatus {
+    pub is_connected: bool,
+    pub websocket_url: String,
+    pub last_heartbeat: DateTime<Utc>,
+    pub active_sessions: u32,
     pub max_sessions: u32,
     pub supported_formats: Vec<AudioFormat>,
     pub service_version: String,
+    /// EchoKit Server 支持的识别语言，未上报时为空
+    #[serde(default)]
+    pub supported_languages: Vec<String>,
 }
 
 // EchoKit 统计信息
@@ -824,4 +2899,79 @@ pub struct OpenAIResponseConfig {
     pub modalities: Option<Vec<String>>,
     pub instructions: Option<String>,
     pub voice: Option<String>,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_ids_are_transparent() {
+        let device_id = DeviceId::from("device-1");
+        assert_eq!(serde_json::to_string(&device_id).unwrap(), r#""device-1""#);
+        assert_eq!(serde_json::from_str::<DeviceId>(r#""device-1""#).unwrap(), device_id);
+        assert_eq!(device_id.to_string(), "device-1");
+        assert_eq!(device_id, "device-1");
+
+        let mut sessions = std::collections::HashMap::new();
+        sessions.insert(SessionId::new("session-1"), UserId::new("user-1"));
+        assert_eq!(sessions.get("session-1").map(UserId::as_str), Some("user-1"));
+    }
+
+    #[test]
+    fn test_device_status_accepts_both_spellings() {
+        assert_eq!(serde_json::from_str::<DeviceStatus>(r#""Pending""#).unwrap(), DeviceStatus::Pending);
+        assert_eq!(serde_json::from_str::<DeviceStatus>(r#""pending""#).unwrap(), DeviceStatus::Pending);
+        assert_eq!(
+            serde_json::from_str::<DeviceStatus>(r#""registration_expired""#).unwrap(),
+            DeviceStatus::RegistrationExpired
+        );
+        assert_eq!(serde_json::to_string(&DeviceStatus::Error).unwrap(), r#""Error""#);
+
+        assert_eq!(DeviceStatus::from("error"), DeviceStatus::Error);
+        assert_eq!(DeviceStatus::from("restarting"), DeviceStatus::Offline);
+        assert_eq!(DeviceStatus::from(DeviceStatus::Pending.as_str()), DeviceStatus::Pending);
+    }
+
+    #[test]
+    fn test_mqtt_config_accepts_legacy_client_fields() {
+        let config: MqttConfig = serde_json::from_value(serde_json::json!({
+            "broker_host": "mqtt",
+            "broker_port": 10039,
+            "client_id": "bridge-1",
+            "keep_alive": 30,
+        }))
+        .unwrap();
+
+        assert_eq!(config.broker, "mqtt");
+        assert_eq!(config.port, 10039);
+        assert_eq!(config.keep_alive_secs, 30);
+        assert_eq!(config.client_id_or("bridge"), "bridge-1");
+        assert_eq!(config.max_reconnect_attempts, MqttConfig::default().max_reconnect_attempts);
+
+        assert!(MqttConfig::default().client_id_or("bridge").starts_with("bridge-"));
+    }
+
+    #[test]
+    fn test_device_connection_stats_counts_transports() {
+        let connection = |device_id: &str, transport| DeviceConnectionInfo {
+            device_id: device_id.to_string(),
+            transport,
+            remote_addr: None,
+            connected_at: Utc::now(),
+            last_heartbeat: Utc::now(),
+            bytes_received: 0,
+            bytes_sent: 0,
+            session_id: None,
+        };
+
+        let stats = DeviceConnectionStats::new(vec![
+            connection("device-2", DeviceTransport::Udp),
+            connection("device-1", DeviceTransport::Websocket),
+            connection("device-3", DeviceTransport::Websocket),
+            connection("device-4", DeviceTransport::Grpc),
+        ]);
+        assert_eq!((stats.total, stats.websocket, stats.udp, stats.grpc), (4, 2, 1, 1));
+        assert_eq!(stats.connections[0].device_id, "device-1");
+        assert_eq!(serde_json::to_value(&stats.connections[1]).unwrap()["transport"], "udp");
+    }
+}
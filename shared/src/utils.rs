@@ -3,6 +3,9 @@ use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey}
 use crate::types::{Claims, UserRole, EchoError};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use uuid::Uuid;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use regex::Regex;
+use std::sync::LazyLock;
 
 // JWT 工具函数
 pub fn generate_jwt(user_id: &str, username: &str, role: UserRole, secret: &str, expiration_hours: u64) -> Result<String, EchoError> {
@@ -71,12 +74,43 @@ pub fn validate_device_name(name: &str) -> bool {
     !name.trim().is_empty() && name.len() <= 50
 }
 
+/// 设备序列号格式（与管理界面的校验规则一致）
+pub static SERIAL_NUMBER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9_-]{3,50}$").unwrap());
+
+/// MAC 地址格式：12 位十六进制，可使用冒号或横线分隔
+pub static MAC_ADDRESS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:[0-9A-Fa-f]{12}|[0-9A-Fa-f]{2}(?::[0-9A-Fa-f]{2}){5}|[0-9A-Fa-f]{2}(?:-[0-9A-Fa-f]{2}){5})$").unwrap()
+});
+
 pub fn validate_username(username: &str) -> bool {
     use regex::Regex;
     let username_regex = Regex::new(r"^[a-zA-Z0-9_-]{3,20}$").unwrap();
     username_regex.is_match(username)
 }
 
+/// 识别语言标签格式（BCP 47 的常用子集）：语言、可选的文字和地区，如 "en"、"zh-Hans"、"pt-BR"
+static LANGUAGE_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z]{2,3}(?:[-_][A-Za-z]{4})?(?:[-_](?:[A-Za-z]{2}|[0-9]{3}))?$").unwrap());
+
+/// 规范化识别语言标签（"zh_cn" -> "zh-CN"），格式无效时返回 None
+pub fn normalize_language_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    if !LANGUAGE_TAG_RE.is_match(tag) {
+        return None;
+    }
+    let subtags: Vec<String> = tag
+        .split(['-', '_'])
+        .enumerate()
+        .map(|(index, subtag)| match (index, subtag.len()) {
+            (0, _) => subtag.to_ascii_lowercase(),
+            (_, 4) => subtag[..1].to_ascii_uppercase() + &subtag[1..].to_ascii_lowercase(),
+            _ => subtag.to_ascii_uppercase(),
+        })
+        .collect();
+    Some(subtags.join("-"))
+}
+
 // 分页计算工具函数
 pub fn calculate_offset(page: u32, page_size: u32) -> u32 {
     (page - 1) * page_size
@@ -86,6 +120,40 @@ pub fn calculate_total_pages(total: u64, page_size: u32) -> u32 {
     ((total as f64) / (page_size as f64)).ceil() as u32
 }
 
+/// 游标分页位置：上一页最后一条记录的排序时间和 ID
+///
+/// 列表按 (时间, ID) 倒序排列，下一页从严格小于该位置的记录开始
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: String,
+}
+
+impl PageCursor {
+    pub fn new(timestamp: DateTime<Utc>, id: impl Into<String>) -> Self {
+        Self { timestamp, id: id.into() }
+    }
+
+    /// 编码为不透明的 URL 安全字符串
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}|{}",
+            self.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+            self.id
+        );
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// 解码游标，格式无效时返回 None
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let raw = String::from_utf8(raw).ok()?;
+        let (timestamp, id) = raw.split_once('|')?;
+        let timestamp = DateTime::parse_from_rfc3339(timestamp).ok()?.with_timezone(&Utc);
+        Some(Self::new(timestamp, id))
+    }
+}
+
 // 字符串工具函数
 pub fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
@@ -95,6 +163,29 @@ pub fn truncate_string(s: &str, max_len: usize) -> String {
     }
 }
 
+/// ASR 词数：中文按字计数，其他语言按空白和标点分词计数
+///
+/// 与 API Gateway 统计查询中的 SQL 表达式规则一致；启用静态加密时转写以密文保存，
+/// 由 Bridge 在加密前统计并写入 sessions.asr_word_count。
+pub fn asr_word_count(text: &str) -> i32 {
+    let is_cjk = |c: char| ('\u{4e00}'..='\u{9fff}').contains(&c);
+    let is_separator = |c: char| {
+        is_cjk(c)
+            || ('\u{3000}'..='\u{303f}').contains(&c)
+            || ('\u{ff00}'..='\u{ffef}').contains(&c)
+            || c.is_ascii_punctuation()
+            || c.is_whitespace()
+    };
+    let characters = text.chars().filter(|c| is_cjk(*c)).count();
+    let words = text.split(is_separator).filter(|word| !word.is_empty()).count();
+    (characters + words) as i32
+}
+
+// 比较令牌等敏感值，不会在第一个不同的字节处提前返回
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 // 错误处理工具函数
 pub fn map_anyhow_error(err: anyhow::Error) -> EchoError {
     EchoError::Internal(err)
@@ -105,6 +196,16 @@ mod tests {
     use super::*;
     use crate::types::UserRole;
 
+    #[test]
+    fn test_normalize_language_tag() {
+        assert_eq!(normalize_language_tag("EN").as_deref(), Some("en"));
+        assert_eq!(normalize_language_tag(" zh_cn ").as_deref(), Some("zh-CN"));
+        assert_eq!(normalize_language_tag("zh-hans-cn").as_deref(), Some("zh-Hans-CN"));
+        assert_eq!(normalize_language_tag("es-419").as_deref(), Some("es-419"));
+        assert_eq!(normalize_language_tag("english"), None);
+        assert_eq!(normalize_language_tag("en-US; drop table"), None);
+    }
+
     #[test]
     fn test_jwt_generation_and_verification() {
         let secret = "test-secret";
@@ -138,6 +239,21 @@ mod tests {
         assert_eq!(uuid1.len(), 36); // 标准UUID长度
     }
 
+    #[test]
+    fn test_asr_word_count() {
+        assert_eq!(asr_word_count(""), 0);
+        assert_eq!(asr_word_count("Turn on the kitchen light, please."), 6);
+        assert_eq!(asr_word_count("打开厨房的灯。"), 6);
+        assert_eq!(asr_word_count("播放 jazz 音乐，音量 50%"), 8);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret-token", b"secret-token"));
+        assert!(!constant_time_eq(b"secret-token", b"secret-tokeN"));
+        assert!(!constant_time_eq(b"secret", b"secret-token"));
+    }
+
     #[test]
     fn test_email_validation() {
         assert!(validate_email("test@example.com"));
@@ -174,4 +290,56 @@ mod tests {
         assert_eq!(truncate_string("short", 10), "short");
         assert_eq!(truncate_string(long_string, 20), "This is a very lo...");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_page_cursor_round_trip() {
+        let timestamp = DateTime::parse_from_rfc3339("2025-01-15T08:30:00.123456Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cursor = PageCursor::new(timestamp, "session|with-separator");
+
+        let encoded = cursor.encode();
+        assert!(!encoded.contains('|'));
+        assert_eq!(PageCursor::decode(&encoded), Some(cursor));
+
+        assert_eq!(PageCursor::decode("not a cursor"), None);
+        assert_eq!(PageCursor::decode(""), None);
+    }
+
+    #[test]
+    fn test_device_registration_validation() {
+        use crate::types::{DeviceRegistrationRequest, DeviceType, EchoError};
+        use validator::Validate;
+
+        assert!(MAC_ADDRESS_RE.is_match("a1b2c3d4e5f6"));
+        assert!(MAC_ADDRESS_RE.is_match("A1:B2:C3:D4:E5:F6"));
+        assert!(!MAC_ADDRESS_RE.is_match("a1:b2-c3d4e5f6"));
+        assert!(SERIAL_NUMBER_RE.is_match("ES20240115001"));
+        assert!(!SERIAL_NUMBER_RE.is_match("ES 001"));
+
+        let mut request = DeviceRegistrationRequest {
+            device_id: None,
+            name: "客厅音箱".to_string(),
+            device_type: DeviceType::Speaker,
+            serial_number: Some("ES20240115001".to_string()),
+            mac_address: Some("a1b2c3d4e5f6".to_string()),
+            echokit_server_url: Some("ws://localhost:9988/ws".to_string()),
+        };
+        assert!(request.validate().is_ok());
+
+        request.name = String::new();
+        request.mac_address = Some("not-a-mac".to_string());
+        let error = EchoError::from(request.validate().unwrap_err());
+        assert_eq!(error.status_code(), 422);
+
+        let problem = error.to_problem();
+        let fields: Vec<&str> = problem.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["mac_address", "name"]);
+
+        request.name = "客厅音箱".to_string();
+        request.serial_number = None;
+        request.mac_address = None;
+        let error = EchoError::from(request.validate().unwrap_err());
+        assert!(matches!(error, EchoError::Validation(ref errors) if errors[0].field == "__all__"));
+    }
+}
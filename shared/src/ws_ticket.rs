@@ -0,0 +1,125 @@
+//! WebSocket 一次性连接票据
+//!
+//! 浏览器无法为 WebSocket 设置请求头，把长期有效的 JWT 放进 URL 会留在代理日志和浏览器历史中。
+//! Web UI 先向 API Gateway 申请票据，Gateway 校验用户对设备的权限后写入 Redis 键 `ws:ticket:{ticket}`
+//! （JSON，过期时间很短），Web UI 再连接 Bridge 的 `/ws/{device_id}?ticket=...`。
+//! Bridge 用 GETDEL 取出并删除票据，同一票据只能使用一次，且只能用于签发时的设备。
+
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionLike;
+use redis::RedisResult;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheKey;
+use crate::types::WsTicketConfig;
+use crate::utils::{generate_uuid, now_utc};
+
+/// 票据对应的连接
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WsTicket {
+    pub device_id: String,
+    /// 申请票据的用户
+    pub user_id: String,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// 票据校验失败的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WsTicketRejected {
+    #[error("Connection ticket is required")]
+    Missing,
+    #[error("Connection ticket is invalid, expired or already used")]
+    Invalid,
+    #[error("Connection ticket was issued for another device")]
+    WrongDevice,
+}
+
+pub struct WsTickets {
+    config: WsTicketConfig,
+}
+
+impl WsTickets {
+    pub fn new(config: WsTicketConfig) -> Self {
+        Self { config }
+    }
+
+    /// 配置启用时创建
+    pub fn from_config(config: &WsTicketConfig) -> Option<Self> {
+        config.enabled.then(|| Self::new(config.clone()))
+    }
+
+    pub fn config(&self) -> &WsTicketConfig {
+        &self.config
+    }
+
+    /// 签发票据，返回票据字符串
+    pub async fn issue<C: ConnectionLike>(&self, conn: &mut C, device_id: &str, user_id: &str) -> RedisResult<String> {
+        let ticket = new_ticket();
+        let value = WsTicket { device_id: device_id.to_string(), user_id: user_id.to_string(), issued_at: now_utc() };
+        let json = serde_json::to_string(&value)
+            .map_err(|e| redis::RedisError::from((redis::ErrorKind::TypeError, "Invalid connection ticket", e.to_string())))?;
+        redis::cmd("SET")
+            .arg(CacheKey::ws_ticket(&ticket))
+            .arg(json)
+            .arg("EX")
+            .arg(self.config.ttl_seconds)
+            .query_async::<_, ()>(conn)
+            .await?;
+        Ok(ticket)
+    }
+
+    /// 校验并消耗设备连接携带的票据，票据无论是否通过校验都已被删除
+    ///
+    /// 未携带票据的连接由调用方按 `config().required` 决定是否放行（设备固件直接连接时不携带票据）
+    pub async fn redeem<C: ConnectionLike>(
+        &self,
+        conn: &mut C,
+        device_id: &str,
+        ticket: &str,
+    ) -> RedisResult<Result<WsTicket, WsTicketRejected>> {
+        let value: Option<String> = redis::cmd("GETDEL").arg(CacheKey::ws_ticket(ticket)).query_async(conn).await?;
+        Ok(check(value.as_deref(), device_id))
+    }
+}
+
+// 票据内容是否有效且属于该设备
+fn check(value: Option<&str>, device_id: &str) -> Result<WsTicket, WsTicketRejected> {
+    let ticket: WsTicket = value
+        .and_then(|json| serde_json::from_str(json).ok())
+        .ok_or(WsTicketRejected::Invalid)?;
+    if ticket.device_id != device_id {
+        return Err(WsTicketRejected::WrongDevice);
+    }
+    Ok(ticket)
+}
+
+// 两个 UUID v4 拼接，约 244 位随机数
+fn new_ticket() -> String {
+    format!("{}{}", generate_uuid(), generate_uuid()).replace('-', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_check() {
+        let json = serde_json::to_string(&WsTicket {
+            device_id: "dev-1".to_string(),
+            user_id: "user-1".to_string(),
+            issued_at: now_utc(),
+        })
+        .unwrap();
+
+        assert_eq!(check(Some(&json), "dev-1").unwrap().user_id, "user-1");
+        assert_eq!(check(Some(&json), "dev-2"), Err(WsTicketRejected::WrongDevice));
+        assert_eq!(check(None, "dev-1"), Err(WsTicketRejected::Invalid));
+        assert_eq!(check(Some("not json"), "dev-1"), Err(WsTicketRejected::Invalid));
+
+        let ticket = new_ticket();
+        assert_eq!(ticket.len(), 64);
+        assert_ne!(ticket, new_ticket());
+        assert_eq!(CacheKey::ws_ticket("abc").as_str(), "ws:ticket:abc");
+        assert!(WsTickets::from_config(&WsTicketConfig::default()).is_none());
+    }
+}